@@ -0,0 +1,143 @@
+//! wasm-bindgen wrapper around the mavkit plan core for browser-side
+//! planning: validation, lint reports, flight estimates and `.param` parsing
+//! without a round trip to the Tauri backend.
+//!
+//! Plans and results cross the boundary as JSON strings in the same shape
+//! the Tauri commands use, so the frontend types apply unchanged.
+//!
+//! Build with `wasm-pack build crates/mavkit-wasm --target web`.
+
+use mavkit::params::parse_param_file;
+use mavkit::{
+    normalize_for_compare, plans_equivalent, simulate, validate_ardupilot_acceptance,
+    validate_plan, validate_plan_report, ArduPilotProfile, CompareTolerance, MissionPlan,
+    ValidationOptions, VehicleProfile, WindProfile,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(js_name = validatePlan)]
+pub fn validate_plan_js(plan: &str) -> Result<String, JsError> {
+    wrap(validate(plan))
+}
+
+#[wasm_bindgen(js_name = validatePlanReport)]
+pub fn validate_plan_report_js(plan: &str, options: &str) -> Result<String, JsError> {
+    wrap(report(plan, options))
+}
+
+#[wasm_bindgen(js_name = validateArdupilot)]
+pub fn validate_ardupilot_js(plan: &str, profile: &str) -> Result<String, JsError> {
+    wrap(ardupilot(plan, profile))
+}
+
+/// Flight timeline for `plan`; `wind` may be empty.
+#[wasm_bindgen(js_name = simulatePlan)]
+pub fn simulate_plan_js(plan: &str, profile: &str, wind: &str) -> Result<String, JsError> {
+    wrap(estimate(plan, profile, wind))
+}
+
+#[wasm_bindgen(js_name = normalizePlan)]
+pub fn normalize_plan_js(plan: &str) -> Result<String, JsError> {
+    wrap(normalize(plan))
+}
+
+#[wasm_bindgen(js_name = plansEquivalent)]
+pub fn plans_equivalent_js(lhs: &str, rhs: &str) -> Result<bool, JsError> {
+    equivalent(lhs, rhs).map_err(|e| JsError::new(&e))
+}
+
+#[wasm_bindgen(js_name = parseParamFile)]
+pub fn parse_param_file_js(contents: &str) -> Result<String, JsError> {
+    wrap(parse_param_file(contents).and_then(|params| to_json(&params)))
+}
+
+fn validate(plan: &str) -> Result<String, String> {
+    to_json(&validate_plan(&from_json::<MissionPlan>(plan)?))
+}
+
+fn report(plan: &str, options: &str) -> Result<String, String> {
+    let options: ValidationOptions = from_json_or_default(options)?;
+    to_json(&validate_plan_report(&from_json(plan)?, &options))
+}
+
+fn ardupilot(plan: &str, profile: &str) -> Result<String, String> {
+    let profile: ArduPilotProfile = from_json_or_default(profile)?;
+    to_json(&validate_ardupilot_acceptance(&from_json(plan)?, &profile))
+}
+
+fn estimate(plan: &str, profile: &str, wind: &str) -> Result<String, String> {
+    let profile: VehicleProfile = from_json_or_default(profile)?;
+    let wind: Option<WindProfile> = if wind.trim().is_empty() {
+        None
+    } else {
+        Some(from_json(wind)?)
+    };
+    to_json(&simulate(&from_json(plan)?, &profile, wind.as_ref()))
+}
+
+fn normalize(plan: &str) -> Result<String, String> {
+    to_json(&normalize_for_compare(&from_json(plan)?))
+}
+
+fn equivalent(lhs: &str, rhs: &str) -> Result<bool, String> {
+    Ok(plans_equivalent(
+        &from_json(lhs)?,
+        &from_json(rhs)?,
+        CompareTolerance::default(),
+    ))
+}
+
+fn from_json<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
+
+fn from_json_or_default<T: DeserializeOwned + Default>(text: &str) -> Result<T, String> {
+    if text.trim().is_empty() {
+        Ok(T::default())
+    } else {
+        from_json(text)
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+fn wrap(result: Result<String, String>) -> Result<String, JsError> {
+    result.map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = r#"{
+        "mission_type": "mission",
+        "home": null,
+        "items": [{
+            "seq": 0, "command": 16, "frame": "global_relative_alt_int",
+            "current": false, "autocontinue": true,
+            "param1": 0, "param2": 0, "param3": 0, "param4": 0,
+            "x": 470000000, "y": 80000000, "z": 50
+        }]
+    }"#;
+
+    #[test]
+    fn validates_and_estimates_json_plans() {
+        let issues: Vec<mavkit::MissionIssue> = from_json(&validate(PLAN).unwrap()).unwrap();
+        assert!(issues.is_empty(), "{issues:?}");
+        let report: mavkit::ValidationReport = from_json(&report(PLAN, "").unwrap()).unwrap();
+        assert_eq!(report.errors, 0);
+        let timeline: mavkit::SimTimeline = from_json(&estimate(PLAN, "", "").unwrap()).unwrap();
+        assert!(!timeline.samples.is_empty());
+        assert!(equivalent(PLAN, &normalize(PLAN).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn reports_malformed_input() {
+        assert!(validate("{").is_err());
+        assert!(estimate(PLAN, "{\"cruise_speed_mps\": \"fast\"}", "").is_err());
+    }
+}
@@ -0,0 +1,609 @@
+//! Airspace and no-fly zone datasets.
+//!
+//! Loads OpenAIP airspace exports and GeoJSON feature collections, then
+//! answers two questions: which zones does a mission route touch, and which
+//! zones is the vehicle in or near right now. Checks are horizontal only;
+//! vertical limits are reported so the operator can judge them, since AGL
+//! limits need terrain data the planner does not have.
+
+use crate::mission::geo::{
+    point_in_polygon, point_segment_distance, polygon_edges, segment_intersection, LocalFrame,
+    EARTH_RADIUS_M,
+};
+use crate::mission::{IssueSeverity, MissionIssue, MissionPlan};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AltitudeUnit {
+    Meters,
+    Feet,
+    FlightLevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AltitudeReference {
+    Ground,
+    Msl,
+    /// Standard pressure (1013.25 hPa), used with flight levels.
+    Standard,
+}
+
+/// Upper or lower vertical limit of an airspace.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AltitudeLimit {
+    pub value: f64,
+    pub unit: AltitudeUnit,
+    pub reference: AltitudeReference,
+}
+
+impl AltitudeLimit {
+    /// Aeronautical notation, e.g. "SFC", "2500 ft MSL", "FL95".
+    pub fn label(&self) -> String {
+        match (self.unit, self.reference) {
+            (AltitudeUnit::FlightLevel, _) => format!("FL{:.0}", self.value),
+            (_, AltitudeReference::Ground) if self.value == 0.0 => "SFC".to_string(),
+            (unit, reference) => {
+                let unit = if unit == AltitudeUnit::Feet {
+                    "ft"
+                } else {
+                    "m"
+                };
+                let reference = match reference {
+                    AltitudeReference::Ground => "AGL",
+                    AltitudeReference::Msl => "MSL",
+                    AltitudeReference::Standard => "STD",
+                };
+                format!("{:.0} {unit} {reference}", self.value)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Airspace {
+    pub name: String,
+    /// ICAO class ("A".."G"), when the dataset gives one.
+    pub class: Option<String>,
+    /// Zone type, e.g. "restricted", "ctr", "danger".
+    pub kind: Option<String>,
+    pub lower: Option<AltitudeLimit>,
+    pub upper: Option<AltitudeLimit>,
+    /// Outer rings as `(lat, lon)` degrees. Holes are ignored.
+    pub polygons: Vec<Vec<(f64, f64)>>,
+}
+
+impl Airspace {
+    /// "Restricted EDR 123 (class D, SFC–FL95)"-style description for warnings.
+    pub fn describe(&self) -> String {
+        let mut details = Vec::new();
+        if let Some(class) = &self.class {
+            details.push(format!("class {class}"));
+        }
+        if self.lower.is_some() || self.upper.is_some() {
+            let label = |limit: &Option<AltitudeLimit>| {
+                limit.map(|l| l.label()).unwrap_or_else(|| "?".to_string())
+            };
+            details.push(format!("{}–{}", label(&self.lower), label(&self.upper)));
+        }
+        let kind = self
+            .kind
+            .as_deref()
+            .map(|kind| format!("{kind} "))
+            .unwrap_or_default();
+        if details.is_empty() {
+            format!("{kind}{}", self.name)
+        } else {
+            format!("{kind}{} ({})", self.name, details.join(", "))
+        }
+    }
+}
+
+/// Where a position stands relative to one airspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AirspaceProximity {
+    /// Index into `AirspaceSet::airspaces`.
+    pub index: usize,
+    pub name: String,
+    pub inside: bool,
+    /// Distance to the nearest boundary, metres.
+    pub distance_m: f64,
+}
+
+/// An airspace file that is not OpenAIP or GeoJSON, or has a malformed feature.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid airspace file: {0}")]
+pub struct AirspaceError(pub String);
+
+/// Degree bounding box `(min_lat, min_lon, max_lat, max_lon)`.
+type Bounds = (f64, f64, f64, f64);
+
+/// A loaded airspace dataset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AirspaceSet {
+    pub airspaces: Vec<Airspace>,
+    /// Features that were not polygons (points, lines) and were ignored.
+    pub skipped: usize,
+    #[serde(skip)]
+    bounds: Vec<Bounds>,
+}
+
+impl AirspaceSet {
+    pub fn new(airspaces: Vec<Airspace>) -> Self {
+        let bounds = airspaces.iter().map(bounds_of).collect();
+        Self {
+            airspaces,
+            skipped: 0,
+            bounds,
+        }
+    }
+
+    /// Parse an OpenAIP airspace export (`{"items": [...]}` or a bare array)
+    /// or a GeoJSON `FeatureCollection`.
+    pub fn parse(contents: &str) -> Result<Self, AirspaceError> {
+        let invalid = AirspaceError;
+        let root: Value = serde_json::from_str(contents).map_err(|err| invalid(err.to_string()))?;
+        // GeoJSON features keep their attributes under "properties"; OpenAIP
+        // items carry them alongside the geometry.
+        let entries: Vec<(&Value, Option<&Value>)> =
+            if let Some(features) = root.get("features").and_then(Value::as_array) {
+                features
+                    .iter()
+                    .map(|feature| {
+                        let properties = feature.get("properties").unwrap_or(&Value::Null);
+                        (properties, feature.get("geometry"))
+                    })
+                    .collect()
+            } else if let Some(items) = root
+                .get("items")
+                .and_then(Value::as_array)
+                .or_else(|| root.as_array())
+            {
+                items
+                    .iter()
+                    .map(|item| (item, item.get("geometry")))
+                    .collect()
+            } else {
+                return Err(invalid(
+                    "expected a GeoJSON FeatureCollection or an OpenAIP airspace export".into(),
+                ));
+            };
+
+        let mut airspaces = Vec::new();
+        let mut skipped = 0;
+        for (index, (properties, geometry)) in entries.into_iter().enumerate() {
+            let polygons = geometry
+                .map(polygons_from_geometry)
+                .transpose()
+                .map_err(|err| invalid(format!("feature {index}: {err}")))?
+                .unwrap_or_default();
+            if polygons.is_empty() {
+                skipped += 1;
+                continue;
+            }
+            airspaces.push(Airspace {
+                name: properties
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Airspace {}", index + 1)),
+                class: properties
+                    .get("icaoClass")
+                    .or_else(|| properties.get("class"))
+                    .and_then(icao_class),
+                kind: properties.get("type").and_then(airspace_kind),
+                lower: properties.get("lowerLimit").and_then(altitude_limit),
+                upper: properties.get("upperLimit").and_then(altitude_limit),
+                polygons,
+            });
+        }
+        let mut set = Self::new(airspaces);
+        set.skipped = skipped;
+        Ok(set)
+    }
+
+    pub fn len(&self) -> usize {
+        self.airspaces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.airspaces.is_empty()
+    }
+
+    /// Airspaces containing the position or within `radius_m` of it, nearest
+    /// first. Zones the position is inside come before all others.
+    pub fn nearby(&self, lat: f64, lon: f64, radius_m: f64) -> Vec<AirspaceProximity> {
+        let frame = LocalFrame::new(lat, lon);
+        let margin = margin_deg(lat, radius_m);
+        let mut nearby: Vec<AirspaceProximity> = self
+            .candidates((lat, lon, lat, lon), margin)
+            .filter_map(|index| {
+                let airspace = &self.airspaces[index];
+                let rings = local_rings(&frame, airspace);
+                let inside = rings.iter().any(|ring| point_in_polygon((0.0, 0.0), ring));
+                let distance_m = rings
+                    .iter()
+                    .flat_map(|ring| polygon_edges(ring))
+                    .map(|(a, b)| point_segment_distance((0.0, 0.0), a, b))
+                    .fold(f64::INFINITY, f64::min);
+                (inside || distance_m <= radius_m).then(|| AirspaceProximity {
+                    index,
+                    name: airspace.name.clone(),
+                    inside,
+                    distance_m,
+                })
+            })
+            .collect();
+        nearby.sort_by(|a, b| {
+            b.inside
+                .cmp(&a.inside)
+                .then(a.distance_m.total_cmp(&b.distance_m))
+        });
+        nearby
+    }
+
+    /// One warning per airspace the route touches, at the first item inside
+    /// it or the first leg crossing into it.
+    pub fn plan_warnings(&self, plan: &MissionPlan) -> Vec<MissionIssue> {
+        let positions: Vec<(u16, (f64, f64))> = plan
+            .items
+            .iter()
+            .filter(|item| item.is_nav_position())
+            .map(|item| (item.seq, item.latlon_deg()))
+            .collect();
+        let Some(&(_, origin)) = positions.first() else {
+            return Vec::new();
+        };
+        let route_bounds = positions.iter().fold(
+            (origin.0, origin.1, origin.0, origin.1),
+            |(min_lat, min_lon, max_lat, max_lon), (_, (lat, lon))| {
+                (
+                    min_lat.min(*lat),
+                    min_lon.min(*lon),
+                    max_lat.max(*lat),
+                    max_lon.max(*lon),
+                )
+            },
+        );
+        let frame = LocalFrame::new(origin.0, origin.1);
+        let local: Vec<(u16, (f64, f64))> = positions
+            .iter()
+            .map(|(seq, (lat, lon))| (*seq, frame.to_local(*lat, *lon)))
+            .collect();
+
+        let mut issues = Vec::new();
+        for index in self.candidates(route_bounds, (0.0, 0.0)) {
+            let airspace = &self.airspaces[index];
+            let rings = local_rings(&frame, airspace);
+            let inside = |p| rings.iter().any(|ring| point_in_polygon(p, ring));
+            let first_inside = local.iter().position(|(_, p)| inside(*p));
+            let first_crossing = local.windows(2).position(|pair| {
+                rings.iter().any(|ring| {
+                    polygon_edges(ring)
+                        .any(|(c, d)| segment_intersection(pair[0].1, pair[1].1, c, d).is_some())
+                })
+            });
+            let (seq, contact) = match (first_inside, first_crossing) {
+                (Some(item), Some(leg)) if leg + 1 < item => (
+                    local[leg + 1].0,
+                    format!("Leg from item {} passes through", local[leg].0),
+                ),
+                (Some(item), _) => (local[item].0, "Item is inside".to_string()),
+                (None, Some(leg)) => (
+                    local[leg + 1].0,
+                    format!("Leg from item {} passes through", local[leg].0),
+                ),
+                (None, None) => continue,
+            };
+            issues.push(MissionIssue {
+                code: "airspace.route_enters".to_string(),
+                message: format!("{contact} {}", airspace.describe()),
+                seq: Some(seq),
+                severity: IssueSeverity::Warning,
+            });
+        }
+        issues.sort_by_key(|issue| issue.seq);
+        issues
+    }
+
+    /// Airspaces whose bounding box overlaps `bounds` grown by `margin_deg`.
+    fn candidates(
+        &self,
+        bounds: Bounds,
+        margin_deg: (f64, f64),
+    ) -> impl Iterator<Item = usize> + '_ {
+        let (min_lat, min_lon, max_lat, max_lon) = (
+            bounds.0 - margin_deg.0,
+            bounds.1 - margin_deg.1,
+            bounds.2 + margin_deg.0,
+            bounds.3 + margin_deg.1,
+        );
+        self.bounds
+            .iter()
+            .enumerate()
+            .filter(move |(_, b)| {
+                b.0 <= max_lat && b.2 >= min_lat && b.1 <= max_lon && b.3 >= min_lon
+            })
+            .map(|(index, _)| index)
+    }
+}
+
+fn bounds_of(airspace: &Airspace) -> Bounds {
+    airspace.polygons.iter().flatten().fold(
+        (
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(min_lat, min_lon, max_lat, max_lon), (lat, lon)| {
+            (
+                min_lat.min(*lat),
+                min_lon.min(*lon),
+                max_lat.max(*lat),
+                max_lon.max(*lon),
+            )
+        },
+    )
+}
+
+/// `(lat, lon)` degrees spanning `radius_m` at `lat`.
+fn margin_deg(lat: f64, radius_m: f64) -> (f64, f64) {
+    let dlat = (radius_m / EARTH_RADIUS_M).to_degrees();
+    (dlat, dlat / lat.to_radians().cos().max(0.01))
+}
+
+fn local_rings(frame: &LocalFrame, airspace: &Airspace) -> Vec<Vec<(f64, f64)>> {
+    airspace
+        .polygons
+        .iter()
+        .map(|ring| {
+            ring.iter()
+                .map(|(lat, lon)| frame.to_local(*lat, *lon))
+                .collect()
+        })
+        .collect()
+}
+
+/// Outer rings of a GeoJSON Polygon or MultiPolygon. Other geometry types
+/// yield no rings.
+fn polygons_from_geometry(geometry: &Value) -> Result<Vec<Vec<(f64, f64)>>, String> {
+    let coordinates = geometry.get("coordinates");
+    let polygons: Vec<&Value> = match geometry.get("type").and_then(Value::as_str) {
+        Some("Polygon") => coordinates.into_iter().collect(),
+        Some("MultiPolygon") => coordinates
+            .and_then(Value::as_array)
+            .map(|polygons| polygons.iter().collect())
+            .unwrap_or_default(),
+        _ => return Ok(Vec::new()),
+    };
+    polygons
+        .into_iter()
+        .map(|polygon| {
+            let outer = polygon
+                .as_array()
+                .and_then(|rings| rings.first())
+                .and_then(Value::as_array)
+                .ok_or("polygon has no outer ring")?;
+            let mut ring = outer
+                .iter()
+                .map(|position| {
+                    let lon = position.get(0).and_then(Value::as_f64);
+                    let lat = position.get(1).and_then(Value::as_f64);
+                    match (lat, lon) {
+                        (Some(lat), Some(lon)) => Ok((lat, lon)),
+                        _ => Err("position is not [lon, lat]".to_string()),
+                    }
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            // GeoJSON rings repeat the first position at the end
+            if ring.len() > 1 && ring.first() == ring.last() {
+                ring.pop();
+            }
+            if ring.len() < 3 {
+                return Err("polygon ring has fewer than 3 positions".to_string());
+            }
+            Ok(ring)
+        })
+        .collect()
+}
+
+/// OpenAIP encodes the class as 0 = A .. 6 = G; 8 is unclassified.
+fn icao_class(value: &Value) -> Option<String> {
+    if let Some(class) = value.as_str() {
+        return Some(class.to_uppercase());
+    }
+    match value.as_u64()? {
+        code @ 0..=6 => Some(char::from(b'A' + code as u8).to_string()),
+        _ => None,
+    }
+}
+
+fn airspace_kind(value: &Value) -> Option<String> {
+    if let Some(kind) = value.as_str() {
+        return Some(kind.to_lowercase());
+    }
+    let kind = match value.as_u64()? {
+        0 => return None,
+        1 => "restricted",
+        2 => "danger",
+        3 => "prohibited",
+        4 => "ctr",
+        5 => "tmz",
+        6 => "rmz",
+        7 => "tma",
+        8 => "tra",
+        9 => "tsa",
+        10 => "fir",
+        13 => "atz",
+        21 => "gliding",
+        28 => "sport",
+        code => return Some(format!("type {code}")),
+    };
+    Some(kind.to_string())
+}
+
+/// `{"value", "unit", "referenceDatum"}`, with OpenAIP's numeric codes
+/// (unit 0 = m, 1 = ft, 6 = FL; datum 0 = GND, 1 = MSL, 2 = STD) or their
+/// names.
+fn altitude_limit(value: &Value) -> Option<AltitudeLimit> {
+    let unit = match value.get("unit")? {
+        unit if unit.as_u64() == Some(0) => AltitudeUnit::Meters,
+        unit if unit.as_u64() == Some(1) => AltitudeUnit::Feet,
+        unit if unit.as_u64() == Some(6) => AltitudeUnit::FlightLevel,
+        unit => match unit.as_str()?.to_lowercase().as_str() {
+            "m" | "meters" => AltitudeUnit::Meters,
+            "ft" | "feet" => AltitudeUnit::Feet,
+            "fl" => AltitudeUnit::FlightLevel,
+            _ => return None,
+        },
+    };
+    let reference = match value.get("referenceDatum") {
+        None => match unit {
+            AltitudeUnit::FlightLevel => AltitudeReference::Standard,
+            _ => AltitudeReference::Msl,
+        },
+        Some(datum) if datum.as_u64() == Some(0) => AltitudeReference::Ground,
+        Some(datum) if datum.as_u64() == Some(1) => AltitudeReference::Msl,
+        Some(datum) if datum.as_u64() == Some(2) => AltitudeReference::Standard,
+        Some(datum) => match datum.as_str()?.to_uppercase().as_str() {
+            "GND" | "AGL" | "SFC" => AltitudeReference::Ground,
+            "MSL" | "AMSL" => AltitudeReference::Msl,
+            "STD" => AltitudeReference::Standard,
+            _ => return None,
+        },
+    };
+    Some(AltitudeLimit {
+        value: value.get("value")?.as_f64()?,
+        unit,
+        reference,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{global_item, MAV_CMD_NAV_WAYPOINT};
+    use crate::mission::{MissionItem, MissionType};
+
+    const ORIGIN: (f64, f64) = (47.0, 8.0);
+
+    fn at(north_m: f64, east_m: f64) -> (f64, f64) {
+        LocalFrame::new(ORIGIN.0, ORIGIN.1).to_global(east_m, north_m)
+    }
+
+    /// A square zone spanning `[south, north] x [west, east]` metres.
+    fn square(name: &str, south: f64, west: f64, north: f64, east: f64) -> Airspace {
+        Airspace {
+            name: name.to_string(),
+            class: Some("D".to_string()),
+            kind: Some("ctr".to_string()),
+            lower: Some(AltitudeLimit {
+                value: 0.0,
+                unit: AltitudeUnit::Feet,
+                reference: AltitudeReference::Ground,
+            }),
+            upper: Some(AltitudeLimit {
+                value: 95.0,
+                unit: AltitudeUnit::FlightLevel,
+                reference: AltitudeReference::Standard,
+            }),
+            polygons: vec![vec![
+                at(south, west),
+                at(south, east),
+                at(north, east),
+                at(north, west),
+            ]],
+        }
+    }
+
+    fn waypoint(north_m: f64, east_m: f64) -> MissionItem {
+        let (lat, lon) = at(north_m, east_m);
+        global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, 50.0)
+    }
+
+    #[test]
+    fn nearby_reports_inside_first_then_by_distance() {
+        let set = AirspaceSet::new(vec![
+            square("far", 2000.0, 2000.0, 3000.0, 3000.0),
+            square("near", 300.0, -100.0, 600.0, 100.0),
+            square("here", -100.0, -100.0, 100.0, 100.0),
+        ]);
+        let nearby = set.nearby(ORIGIN.0, ORIGIN.1, 500.0);
+        assert_eq!(nearby.len(), 2);
+        assert_eq!((nearby[0].name.as_str(), nearby[0].inside), ("here", true));
+        assert!((nearby[0].distance_m - 100.0).abs() < 1.0);
+        assert_eq!((nearby[1].name.as_str(), nearby[1].inside), ("near", false));
+        assert!((nearby[1].distance_m - 300.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn warns_once_per_airspace_at_first_contact() {
+        let set = AirspaceSet::new(vec![
+            square("crossed", 400.0, -100.0, 600.0, 100.0),
+            square("landed in", 1400.0, -100.0, 1600.0, 100.0),
+            square("clear", 0.0, 500.0, 100.0, 600.0),
+        ]);
+        let plan = MissionPlan::for_test(
+            MissionType::Mission,
+            None,
+            vec![
+                waypoint(0.0, 0.0),
+                waypoint(1000.0, 0.0),
+                waypoint(1500.0, 0.0),
+                waypoint(1550.0, 0.0),
+            ],
+        );
+        let issues = set.plan_warnings(&plan);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].seq, Some(1));
+        assert!(issues[0]
+            .message
+            .starts_with("Leg from item 0 passes through ctr crossed"));
+        assert_eq!(issues[1].seq, Some(2));
+        assert_eq!(
+            issues[1].message,
+            "Item is inside ctr landed in (class D, SFC–FL95)"
+        );
+        assert!(issues
+            .iter()
+            .all(|issue| issue.code == "airspace.route_enters"
+                && issue.severity == IssueSeverity::Warning));
+    }
+
+    #[test]
+    fn parses_openaip_and_geojson() {
+        let openaip = r#"{"items": [{
+            "name": "EDR 123",
+            "type": 1,
+            "icaoClass": 8,
+            "geometry": {"type": "Polygon", "coordinates": [[[8.0, 47.0], [8.1, 47.0], [8.1, 47.1], [8.0, 47.0]]]},
+            "lowerLimit": {"value": 0, "unit": 1, "referenceDatum": 0},
+            "upperLimit": {"value": 2500, "unit": 1, "referenceDatum": 1}
+        }]}"#;
+        let set = AirspaceSet::parse(openaip).unwrap();
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.airspaces[0].kind.as_deref(), Some("restricted"));
+        assert_eq!(set.airspaces[0].class, None);
+        assert_eq!(set.airspaces[0].polygons[0].len(), 3);
+        assert_eq!(set.airspaces[0].upper.unwrap().label(), "2500 ft MSL");
+
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"properties": {"name": "Park"}, "geometry": {"type": "MultiPolygon", "coordinates": [
+                [[[8.0, 47.0], [8.1, 47.0], [8.1, 47.1]]],
+                [[[9.0, 47.0], [9.1, 47.0], [9.1, 47.1]]]
+            ]}},
+            {"properties": {"name": "Tower"}, "geometry": {"type": "Point", "coordinates": [8.0, 47.0]}}
+        ]}"#;
+        let set = AirspaceSet::parse(geojson).unwrap();
+        assert_eq!((set.len(), set.skipped), (1, 1));
+        assert_eq!(set.airspaces[0].polygons.len(), 2);
+
+        assert!(matches!(
+            AirspaceSet::parse(r#"{"type": "Feature"}"#),
+            Err(AirspaceError(_))
+        ));
+    }
+}
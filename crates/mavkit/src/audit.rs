@@ -0,0 +1,96 @@
+//! Per-session audit trail of commands sent to the vehicle.
+//!
+//! Every command that acts on the vehicle (arming, mode changes, guided
+//! targets, mission and parameter writes, raw COMMAND_LONG/INT) is recorded
+//! with its wall-clock time, parameters and outcome, including commands that
+//! were refused before reaching the link. The trail lives as long as the
+//! `Vehicle` and holds at most `AUDIT_LOG_CAPACITY` entries.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Oldest entries are dropped beyond this many.
+pub const AUDIT_LOG_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// When the command was issued, milliseconds since the Unix epoch.
+    pub time_unix_ms: u64,
+    /// Short command name, e.g. `arm` or `set_mode`.
+    pub command: String,
+    /// Human-readable parameters.
+    pub params: String,
+    /// Time until the vehicle answered or the command failed.
+    pub duration_ms: u64,
+    /// `None` when the command succeeded.
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    pub(crate) fn new(
+        issued_at: SystemTime,
+        command: &str,
+        params: String,
+        duration: Duration,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            time_unix_ms: issued_at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            command: command.to_string(),
+            params,
+            duration_ms: duration.as_millis() as u64,
+            error,
+        }
+    }
+}
+
+/// Append `entry`, dropping the oldest entries beyond `AUDIT_LOG_CAPACITY`.
+pub(crate) fn record(log: &mut VecDeque<AuditEntry>, entry: AuditEntry) {
+    log.push_back(entry);
+    while log.len() > AUDIT_LOG_CAPACITY {
+        log.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(n: usize) -> AuditEntry {
+        AuditEntry::new(
+            UNIX_EPOCH + Duration::from_secs(n as u64),
+            "arm",
+            format!("force=false #{n}"),
+            Duration::from_millis(120),
+            None,
+        )
+    }
+
+    #[test]
+    fn entry_records_wall_clock_and_duration() {
+        let e = AuditEntry::new(
+            UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            "set_mode",
+            "custom_mode=4".to_string(),
+            Duration::from_millis(250),
+            Some("operation timed out".to_string()),
+        );
+        assert_eq!(e.time_unix_ms, 1_700_000_000_123);
+        assert_eq!(e.duration_ms, 250);
+        assert_eq!(e.error.as_deref(), Some("operation timed out"));
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped_beyond_capacity() {
+        let mut log = VecDeque::new();
+        for n in 0..AUDIT_LOG_CAPACITY + 3 {
+            record(&mut log, entry(n));
+        }
+        assert_eq!(log.len(), AUDIT_LOG_CAPACITY);
+        assert_eq!(log.front(), Some(&entry(3)));
+    }
+}
@@ -0,0 +1,295 @@
+//! Cell imbalance and voltage sag from BATTERY_STATUS cell voltages.
+//!
+//! A failing pack usually shows itself before it browns out: its cells
+//! drift apart, and one cell drops much further than the others under load.
+//! `Vehicle::battery_health` follows the per-cell voltages and raises a
+//! `BatteryAlert` when the spread, the sag of the weakest cell or its loaded
+//! voltage crosses the `CellLimits` of the pack's chemistry.
+//!
+//! Sag is the weakest cell's internal resistance times the present current.
+//! The resistance is estimated from how the cell's voltage moves when the
+//! current steps, so the slow fall of a discharging pack doesn't count as
+//! sag. Autopilots that only know the pack voltage report it as the first
+//! cell; with fewer than two cells nothing is measured.
+
+use crate::state::Telemetry;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Samples further apart than this don't give a resistance.
+const MAX_SAMPLE_GAP: Duration = Duration::from_secs(2);
+/// Smallest current step a resistance is estimated from.
+const MIN_CURRENT_STEP_A: f64 = 2.0;
+/// Estimates above this are measurement noise rather than a cell.
+const MAX_CELL_RESISTANCE_OHM: f64 = 0.5;
+/// Weight of each new estimate in the running resistance.
+const RESISTANCE_SMOOTHING: f64 = 0.3;
+/// Imbalance and sag alerts clear once below this fraction of their limit.
+const ALERT_CLEAR_FRACTION: f64 = 0.8;
+/// The low cell alert clears once the cell is this far above its limit.
+const LOW_CELL_CLEAR_V: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatteryChemistry {
+    #[default]
+    LiPo,
+    /// High-voltage LiPo, charged to 4.35 V.
+    LiHv,
+    /// Cylindrical Li-ion cells (18650, 21700), which sag more and may run
+    /// lower than LiPo.
+    LiIon,
+    /// LiFePO4, with its flat discharge curve.
+    LiFe,
+}
+
+/// Thresholds for `BatteryAlert`s, per cell.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CellLimits {
+    /// Highest minus lowest cell voltage.
+    pub max_imbalance_v: f64,
+    /// Voltage the weakest cell loses to its internal resistance.
+    pub max_sag_v: f64,
+    /// Lowest voltage of any cell, loaded or not.
+    pub min_cell_v: f64,
+}
+
+impl CellLimits {
+    pub fn for_chemistry(chemistry: BatteryChemistry) -> Self {
+        match chemistry {
+            BatteryChemistry::LiPo | BatteryChemistry::LiHv => CellLimits {
+                max_imbalance_v: 0.1,
+                max_sag_v: 0.5,
+                min_cell_v: 3.3,
+            },
+            BatteryChemistry::LiIon => CellLimits {
+                max_imbalance_v: 0.1,
+                max_sag_v: 0.6,
+                min_cell_v: 2.8,
+            },
+            BatteryChemistry::LiFe => CellLimits {
+                max_imbalance_v: 0.1,
+                max_sag_v: 0.3,
+                min_cell_v: 2.8,
+            },
+        }
+    }
+}
+
+impl Default for CellLimits {
+    fn default() -> Self {
+        Self::for_chemistry(BatteryChemistry::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatteryAlert {
+    Imbalance,
+    Sag,
+    LowCell,
+}
+
+/// Cell metrics of the pack, see the module docs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatteryHealth {
+    /// Highest minus lowest cell voltage.
+    pub imbalance_v: Option<f64>,
+    /// Zero-based index of the lowest cell.
+    pub weakest_cell: Option<usize>,
+    pub weakest_cell_v: Option<f64>,
+    /// Estimated internal resistance of the weakest cell.
+    pub cell_resistance_mohm: Option<f64>,
+    /// Voltage the weakest cell loses at the present current.
+    pub sag_v: Option<f64>,
+    /// Limits currently exceeded.
+    pub alerts: Vec<BatteryAlert>,
+}
+
+/// Running estimate behind `BatteryHealth`.
+#[derive(Debug, Clone)]
+pub(crate) struct CellMonitor {
+    limits: CellLimits,
+    /// Cell voltages, current and arrival of the previous sample.
+    previous: Option<(Vec<f64>, f64, Instant)>,
+    resistance_ohm: Option<f64>,
+    health: BatteryHealth,
+}
+
+impl CellMonitor {
+    pub(crate) fn new(limits: CellLimits) -> Self {
+        Self {
+            limits,
+            previous: None,
+            resistance_ohm: None,
+            health: BatteryHealth::default(),
+        }
+    }
+
+    /// Take in a sample of the cell voltages and pack current.
+    pub(crate) fn update(
+        &mut self,
+        cells: &[f64],
+        current_a: Option<f64>,
+        now: Instant,
+    ) -> &BatteryHealth {
+        if cells.len() < 2 {
+            self.previous = None;
+            self.health = BatteryHealth::default();
+            return &self.health;
+        }
+        let (weakest, weakest_v) = cells
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or_default();
+        let highest_v = cells.iter().copied().fold(f64::MIN, f64::max);
+        let imbalance_v = highest_v - weakest_v;
+
+        if let Some(current_a) = current_a {
+            self.estimate_resistance(cells, weakest, current_a, now);
+            self.previous = Some((cells.to_vec(), current_a, now));
+        }
+        let sag_v = self
+            .resistance_ohm
+            .zip(current_a)
+            .map(|(ohm, current_a)| ohm * current_a.max(0.0));
+
+        let was = |alert| self.health.alerts.contains(&alert);
+        let limits = self.limits;
+        let exceeds = |value: f64, limit: f64, raised: bool| {
+            let limit = if raised {
+                limit * ALERT_CLEAR_FRACTION
+            } else {
+                limit
+            };
+            value > limit
+        };
+        let mut alerts = Vec::new();
+        if exceeds(
+            imbalance_v,
+            limits.max_imbalance_v,
+            was(BatteryAlert::Imbalance),
+        ) {
+            alerts.push(BatteryAlert::Imbalance);
+        }
+        if sag_v.is_some_and(|sag| exceeds(sag, limits.max_sag_v, was(BatteryAlert::Sag))) {
+            alerts.push(BatteryAlert::Sag);
+        }
+        let low_cell_v = if was(BatteryAlert::LowCell) {
+            limits.min_cell_v + LOW_CELL_CLEAR_V
+        } else {
+            limits.min_cell_v
+        };
+        if weakest_v < low_cell_v {
+            alerts.push(BatteryAlert::LowCell);
+        }
+
+        self.health = BatteryHealth {
+            imbalance_v: Some(imbalance_v),
+            weakest_cell: Some(weakest),
+            weakest_cell_v: Some(weakest_v),
+            cell_resistance_mohm: self.resistance_ohm.map(|ohm| ohm * 1000.0),
+            sag_v,
+            alerts,
+        };
+        &self.health
+    }
+
+    fn estimate_resistance(&mut self, cells: &[f64], weakest: usize, current_a: f64, now: Instant) {
+        let Some((previous_cells, previous_a, at)) = &self.previous else {
+            return;
+        };
+        let step_a = current_a - previous_a;
+        let usable = now.saturating_duration_since(*at) <= MAX_SAMPLE_GAP
+            && previous_cells.len() == cells.len()
+            && step_a.abs() >= MIN_CURRENT_STEP_A;
+        if !usable {
+            return;
+        }
+        let ohm = (previous_cells[weakest] - cells[weakest]) / step_a;
+        let plausible = ohm > 0.0 && ohm <= MAX_CELL_RESISTANCE_OHM;
+        if !plausible {
+            return;
+        }
+        self.resistance_ohm = Some(match self.resistance_ohm {
+            Some(running) => running + RESISTANCE_SMOOTHING * (ohm - running),
+            None => ohm,
+        });
+    }
+}
+
+/// Feed `out` from the cell voltages in `telemetry` until the vehicle
+/// disconnects.
+pub(crate) async fn run_battery_health(
+    mut telemetry: watch::Receiver<Telemetry>,
+    out: watch::Sender<BatteryHealth>,
+    limits: CellLimits,
+) {
+    let mut monitor = CellMonitor::new(limits);
+    let mut last_sample = None;
+    while telemetry.changed().await.is_ok() {
+        let sample = {
+            let telemetry = telemetry.borrow_and_update();
+            (
+                telemetry.battery_voltage_cells.clone().unwrap_or_default(),
+                telemetry.battery_current_a,
+            )
+        };
+        // Other telemetry changes more often than the battery does
+        if last_sample.as_ref() == Some(&sample) {
+            continue;
+        }
+        let health = monitor.update(&sample.0, sample.1, Instant::now()).clone();
+        last_sample = Some(sample);
+        out.send_if_modified(|shown| {
+            let changed = *shown != health;
+            *shown = health;
+            changed
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imbalance_and_low_cell_need_per_cell_voltages() {
+        let mut monitor = CellMonitor::new(CellLimits::default());
+        let now = Instant::now();
+        let pack_only = monitor.update(&[16.4], Some(1.0), now);
+        assert_eq!(*pack_only, BatteryHealth::default());
+
+        let health = monitor.update(&[4.05, 4.1, 3.25, 4.08], None, now);
+        assert_eq!(health.weakest_cell, Some(2));
+        assert!((health.imbalance_v.unwrap() - 0.85).abs() < 1e-9);
+        assert_eq!(
+            health.alerts,
+            vec![BatteryAlert::Imbalance, BatteryAlert::LowCell]
+        );
+
+        // Just above the limit is not enough to clear
+        let health = monitor.update(&[3.33, 3.34, 3.32, 3.34], None, now);
+        assert_eq!(health.alerts, vec![BatteryAlert::LowCell]);
+    }
+
+    #[test]
+    fn sag_comes_from_current_steps_not_discharge() {
+        let mut monitor = CellMonitor::new(CellLimits::default());
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        monitor.update(&[3.9, 3.9, 3.9], Some(2.0), at(0));
+        // Slow discharge at constant current gives no estimate
+        let health = monitor.update(&[3.88, 3.88, 3.88], Some(2.0), at(500));
+        assert_eq!(health.sag_v, None);
+
+        // 40 mOhm on the weak cell, 10 on the others
+        let health = monitor.update(&[3.68, 3.68, 3.08], Some(22.0), at(1000));
+        assert!((health.cell_resistance_mohm.unwrap() - 40.0).abs() < 1e-6);
+        assert!((health.sag_v.unwrap() - 0.88).abs() < 1e-6);
+        assert!(health.alerts.contains(&BatteryAlert::Sag));
+    }
+}
@@ -0,0 +1,133 @@
+//! Offline mavkit tools for scripts and CI.
+//!
+//! ```text
+//! mavkit-cli lint <plan.json> [--fence <fence.json>] [--vtol] [--ignore <code>]...
+//!                             [--ardupilot plane|copter|rover] [--fence-alt-max <m>]
+//!                             [--warnings-as-errors] [--format text|json]
+//! ```
+//!
+//! `lint` exits 0 when the plan passes, 1 when it has errors (or warnings
+//! with `--warnings-as-errors`) and 2 on usage or file errors.
+
+use mavkit::{
+    validate_plan_report, ArduPilotProfile, IssueSeverity, MissionPlan, ValidationOptions,
+    VehicleType, VtolProfile,
+};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: mavkit-cli lint <plan.json> [--fence <fence.json>] [--vtol] \
+[--ignore <code>]... [--ardupilot plane|copter|rover] [--fence-alt-max <m>] \
+[--warnings-as-errors] [--format text|json]";
+
+enum Format {
+    Text,
+    Json,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("lint") => lint(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn lint(args: &[String]) -> Result<u8, String> {
+    let mut plan_path = None;
+    let mut options = ValidationOptions::default();
+    let mut format = Format::Text;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--fence" => options.fence = Some(read_plan(&value()?)?),
+            "--vtol" => options.vtol = Some(VtolProfile::default()),
+            "--ignore" => options.ignore.push(value()?),
+            "--ardupilot" => {
+                let vehicle_type = match value()?.as_str() {
+                    "plane" => VehicleType::FixedWing,
+                    "copter" => VehicleType::Quadrotor,
+                    "rover" => VehicleType::GroundRover,
+                    other => return Err(format!("unknown vehicle {other:?}\n{USAGE}")),
+                };
+                options
+                    .ardupilot
+                    .get_or_insert_with(ArduPilotProfile::default)
+                    .vehicle_type = vehicle_type;
+            }
+            "--fence-alt-max" => {
+                let value = value()?;
+                let alt_m = value
+                    .parse()
+                    .map_err(|_| format!("invalid altitude {value:?}\n{USAGE}"))?;
+                options
+                    .ardupilot
+                    .get_or_insert_with(ArduPilotProfile::default)
+                    .fence_alt_max_m = Some(alt_m);
+            }
+            "--warnings-as-errors" => options.warnings_as_errors = true,
+            "--format" => {
+                format = match value()?.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    other => return Err(format!("unknown format {other:?}\n{USAGE}")),
+                }
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option {flag}\n{USAGE}")),
+            path if plan_path.is_none() => plan_path = Some(path.to_string()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let plan_path = plan_path.ok_or_else(|| USAGE.to_string())?;
+    let plan = read_plan(&plan_path)?;
+
+    let report = validate_plan_report(&plan, &options);
+    match format {
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&report).map_err(|err| err.to_string())?;
+            println!("{json}");
+        }
+        Format::Text => {
+            for issue in &report.issues {
+                let severity = match issue.severity {
+                    IssueSeverity::Error => "error",
+                    IssueSeverity::Warning => "warning",
+                };
+                let location = issue
+                    .seq
+                    .map(|seq| format!("item {seq}"))
+                    .unwrap_or_else(|| "plan".to_string());
+                println!(
+                    "{plan_path}: {location}: {severity}[{}]: {}",
+                    issue.code, issue.message
+                );
+            }
+            println!(
+                "{plan_path}: {} error(s), {} warning(s)",
+                report.errors, report.warnings
+            );
+        }
+    }
+    Ok(report.exit_code() as u8)
+}
+
+fn read_plan(path: &str) -> Result<MissionPlan, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    serde_json::from_str(&text).map_err(|err| format!("{path}: invalid plan: {err}"))
+}
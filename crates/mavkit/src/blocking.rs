@@ -0,0 +1,378 @@
+//! Blocking facade over the async [`Vehicle`](crate::Vehicle).
+//!
+//! For callers without a tokio runtime. Each `blocking::Vehicle` owns a
+//! single-threaded runtime on a background thread that runs the event loop;
+//! methods block the calling thread until the async call completes, and
+//! state is read as snapshots or delivered to callbacks.
+//!
+//! Each subscription runs its callback on its own thread, fed through a
+//! bounded event queue, so a slow callback never holds up the event loop:
+//! it misses intermediate telemetry instead (see `Subscription::stats`).
+//! Methods may be called from callbacks but not from async code, which runs
+//! on the runtime thread, where blocking on the runtime panics.
+
+use crate::audit::AuditEntry;
+use crate::config::VehicleConfig;
+use crate::error::VehicleError;
+use crate::events::{
+    event_channel, EventClass, EventMonitor, EventReceiver, EventSender, EventStats, VehicleEvent,
+    DEFAULT_EVENT_CAPACITY,
+};
+use crate::mission::{HomePosition, MissionPlan, MissionType, PlanSyncStatus, TransferProgress};
+use crate::params::{Param, ParamCache, ParamStore};
+use crate::state::{LinkState, MissionState, Telemetry, VehicleState};
+use std::future::Future;
+use std::sync::Arc;
+use std::thread;
+use tokio::runtime::{Builder, Handle};
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+
+/// The runtime thread. Dropping it stops the runtime, cancelling any tasks
+/// still running on it.
+struct RuntimeThread {
+    handle: Handle,
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl RuntimeThread {
+    fn start() -> Result<Self, VehicleError> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let handle = runtime.handle().clone();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("mavkit-runtime".into())
+            .spawn(move || {
+                runtime.block_on(async {
+                    let _ = stopped.await;
+                });
+            })?;
+        Ok(Self {
+            handle,
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for RuntimeThread {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A callback registered with one of the `Vehicle::on_*` methods. The
+/// callback stops when this is dropped.
+pub struct Subscription {
+    /// Feeds the queue from a watch channel, if the source is one.
+    task: Option<JoinHandle<()>>,
+    close: Box<dyn Fn() + Send + Sync>,
+    monitor: EventMonitor,
+}
+
+impl Subscription {
+    /// Start a thread running `callback` for each event on `events`.
+    fn start<E: Send + 'static>(
+        task: Option<JoinHandle<()>>,
+        tx: EventSender<E>,
+        mut events: EventReceiver<E>,
+        mut callback: impl FnMut(E) + Send + 'static,
+    ) -> Self {
+        let monitor = events.monitor();
+        thread::Builder::new()
+            .name("mavkit-callback".into())
+            .spawn(move || {
+                while let Some(event) = events.recv_blocking() {
+                    callback(event);
+                }
+            })
+            .expect("failed to spawn callback thread");
+        Self {
+            task,
+            close: Box::new(move || tx.close()),
+            monitor,
+        }
+    }
+
+    /// Delivered and dropped event counts of this callback's queue.
+    pub fn stats(&self) -> EventStats {
+        self.monitor.stats()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+        (self.close)();
+    }
+}
+
+/// Synchronous MAVLink vehicle handle.
+pub struct Vehicle {
+    // Declared before `runtime` so the connection is released before the
+    // runtime stops.
+    inner: crate::Vehicle,
+    runtime: RuntimeThread,
+}
+
+impl Vehicle {
+    /// Connect using a mavlink address string (e.g. `udpin:0.0.0.0:14550`),
+    /// blocking until the first HEARTBEAT.
+    pub fn connect(address: &str) -> Result<Self, VehicleError> {
+        Self::connect_with_config(address, VehicleConfig::default())
+    }
+
+    pub fn connect_with_config(address: &str, config: VehicleConfig) -> Result<Self, VehicleError> {
+        let runtime = RuntimeThread::start()?;
+        let inner = runtime
+            .handle
+            .block_on(crate::Vehicle::connect_with_config(address, config))?;
+        Ok(Self { inner, runtime })
+    }
+
+    /// The async vehicle, for calls this facade does not wrap. Run them with
+    /// [`Vehicle::block_on`].
+    pub fn async_vehicle(&self) -> &crate::Vehicle {
+        &self.inner
+    }
+
+    /// Run a future on this vehicle's runtime and wait for it.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.handle.block_on(future)
+    }
+
+    // --- Snapshots ---
+
+    pub fn state(&self) -> VehicleState {
+        self.inner.state().borrow().clone()
+    }
+
+    pub fn telemetry(&self) -> Telemetry {
+        self.inner.telemetry().borrow().clone()
+    }
+
+    pub fn home_position(&self) -> Option<HomePosition> {
+        self.inner.home_position().borrow().clone()
+    }
+
+    pub fn mission_state(&self) -> MissionState {
+        self.inner.mission_state().borrow().clone()
+    }
+
+    pub fn link_state(&self) -> LinkState {
+        self.inner.link_state().borrow().clone()
+    }
+
+    pub fn param_store(&self) -> Arc<ParamStore> {
+        self.inner.param_store().borrow().clone()
+    }
+
+    // --- Callbacks ---
+
+    /// Call `callback` with each telemetry update, on its own thread.
+    pub fn on_telemetry(&self, callback: impl FnMut(Telemetry) + Send + 'static) -> Subscription {
+        self.subscribe(self.inner.telemetry(), callback)
+    }
+
+    pub fn on_state(&self, callback: impl FnMut(VehicleState) + Send + 'static) -> Subscription {
+        self.subscribe(self.inner.state(), callback)
+    }
+
+    pub fn on_link_state(&self, callback: impl FnMut(LinkState) + Send + 'static) -> Subscription {
+        self.subscribe(self.inner.link_state(), callback)
+    }
+
+    pub fn on_mission_progress(
+        &self,
+        callback: impl FnMut(Option<TransferProgress>) + Send + 'static,
+    ) -> Subscription {
+        self.subscribe(self.inner.mission_progress(), callback)
+    }
+
+    /// Call `callback` with the result of every later command. Results are
+    /// never dropped, however slow the callback.
+    pub fn on_command_result(
+        &self,
+        mut callback: impl FnMut(AuditEntry) + Send + 'static,
+    ) -> Subscription {
+        let (tx, events) = event_channel(DEFAULT_EVENT_CAPACITY);
+        self.inner.subscribe_command_results(tx.clone());
+        Subscription::start(None, tx, events, move |event| {
+            if let VehicleEvent::CommandResult(entry) = event {
+                callback(entry);
+            }
+        })
+    }
+
+    fn subscribe<T: Clone + Send + Sync + 'static>(
+        &self,
+        rx: watch::Receiver<T>,
+        callback: impl FnMut(T) + Send + 'static,
+    ) -> Subscription {
+        let (tx, events) = event_channel(DEFAULT_EVENT_CAPACITY);
+        let task = self.runtime.handle.spawn(forward(rx, tx.clone()));
+        Subscription::start(Some(task), tx, events, callback)
+    }
+
+    // --- Commands ---
+
+    pub fn arm(&self, force: bool) -> Result<(), VehicleError> {
+        self.block_on(self.inner.arm(force))
+    }
+
+    pub fn disarm(&self, force: bool) -> Result<(), VehicleError> {
+        self.block_on(self.inner.disarm(force))
+    }
+
+    pub fn set_mode(&self, custom_mode: u32) -> Result<(), VehicleError> {
+        self.block_on(self.inner.set_mode(custom_mode))
+    }
+
+    pub fn set_mode_unchecked(&self, custom_mode: u32) -> Result<(), VehicleError> {
+        self.block_on(self.inner.set_mode_unchecked(custom_mode))
+    }
+
+    pub fn set_mode_by_name(&self, name: &str) -> Result<(), VehicleError> {
+        self.block_on(self.inner.set_mode_by_name(name))
+    }
+
+    pub fn takeoff(&self, altitude_m: f32) -> Result<(), VehicleError> {
+        self.block_on(self.inner.takeoff(altitude_m))
+    }
+
+    pub fn land_here(&self) -> Result<(), VehicleError> {
+        self.block_on(self.inner.land_here())
+    }
+
+    pub fn abort_landing(&self, climb_to_m: f32) -> Result<(), VehicleError> {
+        self.block_on(self.inner.abort_landing(climb_to_m))
+    }
+
+    pub fn goto(&self, lat_deg: f64, lon_deg: f64, alt_m: f32) -> Result<(), VehicleError> {
+        self.block_on(self.inner.goto(lat_deg, lon_deg, alt_m))
+    }
+
+    // --- Mission ---
+
+    pub fn upload_mission(&self, plan: MissionPlan) -> Result<(), VehicleError> {
+        self.block_on(self.inner.mission().upload(plan))
+    }
+
+    pub fn download_mission(&self, mission_type: MissionType) -> Result<MissionPlan, VehicleError> {
+        self.block_on(self.inner.mission().download(mission_type))
+    }
+
+    pub fn clear_mission(&self, mission_type: MissionType) -> Result<(), VehicleError> {
+        self.block_on(self.inner.mission().clear(mission_type))
+    }
+
+    /// See `MissionHandle::sync_status`.
+    pub fn mission_sync_status(&self, plan: &MissionPlan) -> Result<PlanSyncStatus, VehicleError> {
+        self.block_on(self.inner.mission().sync_status(plan))
+    }
+
+    pub fn set_current_mission_item(&self, seq: u16) -> Result<(), VehicleError> {
+        self.block_on(self.inner.mission().set_current(seq))
+    }
+
+    /// See `MissionHandle::resume_from`.
+    pub fn resume_mission_from(&self, seq: u16) -> Result<(), VehicleError> {
+        self.block_on(self.inner.mission().resume_from(seq))
+    }
+
+    // --- Parameters ---
+
+    pub fn download_params(&self) -> Result<ParamStore, VehicleError> {
+        self.block_on(self.inner.params().download_all())
+    }
+
+    /// See `ParamsHandle::download_cached`.
+    pub fn download_params_cached(&self, cache: &ParamCache) -> Result<ParamStore, VehicleError> {
+        self.block_on(self.inner.params().download_cached(cache))
+    }
+
+    pub fn write_param(&self, name: &str, value: f32) -> Result<Param, VehicleError> {
+        self.block_on(self.inner.params().write(name.to_string(), value))
+    }
+
+    /// Close the connection and stop the runtime thread.
+    pub fn disconnect(self) -> Result<(), VehicleError> {
+        let Self { inner, runtime } = self;
+        let result = runtime.handle.block_on(inner.disconnect());
+        drop(runtime);
+        result
+    }
+}
+
+/// Queue every value published on `rx`, starting with the current one, on
+/// `tx` until either side closes.
+async fn forward<T: Clone>(mut rx: watch::Receiver<T>, tx: EventSender<T>) {
+    loop {
+        let value = rx.borrow_and_update().clone();
+        if !tx.send(EventClass::Telemetry, value) || rx.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn runtime_thread_runs_futures_and_callbacks() {
+        let runtime = RuntimeThread::start().unwrap();
+        assert_eq!(runtime.handle.block_on(async { 40 + 2 }), 42);
+
+        let (tx, rx) = watch::channel(1);
+        let (seen_tx, seen_rx) = mpsc::channel();
+        let (queue, events) = event_channel(DEFAULT_EVENT_CAPACITY);
+        let task = runtime.handle.spawn(forward(rx, queue.clone()));
+        let subscription = Subscription::start(Some(task), queue, events, move |value| {
+            let _ = seen_tx.send(value);
+        });
+        let wait = || seen_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(wait(), 1);
+        tx.send(2).unwrap();
+        assert_eq!(wait(), 2);
+
+        drop(subscription);
+        thread::sleep(Duration::from_millis(50));
+        let _ = tx.send(3);
+        assert!(seen_rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+    #[test]
+    fn slow_callbacks_drop_telemetry_instead_of_stalling_the_runtime() {
+        let runtime = RuntimeThread::start().unwrap();
+        let (tx, rx) = watch::channel(0);
+        let (queue, events) = event_channel(2);
+        let task = runtime.handle.spawn(forward(rx, queue.clone()));
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let subscription = Subscription::start(Some(task), queue, events, move |_| {
+            let _ = release_rx.recv();
+        });
+        for n in 1..=50 {
+            tx.send(n).unwrap();
+            runtime
+                .handle
+                .block_on(tokio::time::sleep(Duration::from_millis(1)));
+        }
+        assert!(
+            subscription.stats().dropped > 0,
+            "{:?}",
+            subscription.stats()
+        );
+        assert!(subscription.stats().queued <= 2);
+        drop(release_tx);
+    }
+}
@@ -0,0 +1,221 @@
+//! Vehicle clock tracking.
+//!
+//! TIMESYNC round trips give the offset between our monotonic clock and the
+//! vehicle's boot clock, with half the round trip taken off as link latency.
+//! SYSTEM_TIME gives the vehicle's UTC once it has GPS time, and stands in for
+//! TIMESYNC on firmware that does not answer it. Telemetry updates are stamped
+//! with both, so recorded data lines up with the onboard log.
+
+use std::time::{Duration, Instant};
+
+/// Round trips slower than this say more about the link than the clock.
+const MAX_TIMESYNC_RTT: Duration = Duration::from_millis(500);
+/// Each TIMESYNC sample moves the offset this fraction of the way (1/n).
+const TIMESYNC_SMOOTHING: i64 = 8;
+/// A vehicle clock this far behind the estimate has rebooted.
+const REBOOT_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Nanoseconds from `epoch` to `now` on our monotonic clock, the time base
+/// of TIMESYNC `ts1`.
+pub(crate) fn local_ns(epoch: Instant, now: Instant) -> i64 {
+    now.saturating_duration_since(epoch).as_nanos() as i64
+}
+
+/// When something happened by the vehicle's clock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct VehicleTime {
+    pub boot_ms: Option<u32>,
+    pub utc_us: Option<u64>,
+}
+
+pub(crate) struct VehicleClock {
+    epoch: Instant,
+    /// Vehicle boot time minus `local_ns`.
+    boot_offset_ns: Option<i64>,
+    /// `boot_offset_ns` came from TIMESYNC rather than one-way SYSTEM_TIME.
+    synced: bool,
+    /// UTC minus vehicle boot time, µs.
+    utc_offset_us: Option<i64>,
+}
+
+impl VehicleClock {
+    pub(crate) fn new(epoch: Instant) -> Self {
+        Self {
+            epoch,
+            boot_offset_ns: None,
+            synced: false,
+            utc_offset_us: None,
+        }
+    }
+
+    /// Apply a TIMESYNC reply: `tc1` is the vehicle's boot time when it
+    /// answered and `ts1` our `local_ns` when we asked, both in ns.
+    /// Requests (`tc1` of 0) and slow or foreign replies are ignored.
+    pub(crate) fn observe_timesync(&mut self, tc1: i64, ts1: i64, now: Instant) {
+        let now_ns = local_ns(self.epoch, now);
+        let rtt_ns = now_ns - ts1;
+        if tc1 == 0 || ts1 < 0 || !(0..=MAX_TIMESYNC_RTT.as_nanos() as i64).contains(&rtt_ns) {
+            return;
+        }
+        let sample = tc1 - (ts1 + now_ns) / 2;
+        self.boot_offset_ns = Some(match self.boot_offset_ns {
+            Some(offset)
+                if self.synced && (sample - offset).abs() < REBOOT_THRESHOLD.as_nanos() as i64 =>
+            {
+                offset + (sample - offset) / TIMESYNC_SMOOTHING
+            }
+            _ => sample,
+        });
+        self.synced = true;
+    }
+
+    /// Apply SYSTEM_TIME. `time_unix_usec` is 0 until the vehicle has GPS
+    /// time; the last known UTC offset is kept meanwhile.
+    pub(crate) fn observe_system_time(
+        &mut self,
+        time_unix_usec: u64,
+        time_boot_ms: u32,
+        now: Instant,
+    ) {
+        let boot_ns = i64::from(time_boot_ms) * 1_000_000;
+        if self
+            .boot_ns(now)
+            .is_some_and(|estimate| boot_ns < estimate - REBOOT_THRESHOLD.as_nanos() as i64)
+        {
+            *self = Self::new(self.epoch);
+        }
+        if !self.synced {
+            // One-way, so late by the link latency
+            self.boot_offset_ns = Some(boot_ns - local_ns(self.epoch, now));
+        }
+        if time_unix_usec > 0 {
+            self.utc_offset_us = Some(time_unix_usec as i64 - i64::from(time_boot_ms) * 1000);
+        }
+    }
+
+    fn boot_ns(&self, now: Instant) -> Option<i64> {
+        Some(local_ns(self.epoch, now) + self.boot_offset_ns?)
+    }
+
+    /// Vehicle time of a telemetry update received at `now`. A message that
+    /// carries its own `time_boot_ms` is stamped with that.
+    pub(crate) fn stamp(&self, time_boot_ms: Option<u32>, now: Instant) -> VehicleTime {
+        let boot_ms = time_boot_ms.or_else(|| u32::try_from(self.boot_ns(now)? / 1_000_000).ok());
+        let utc_us = boot_ms
+            .zip(self.utc_offset_us)
+            .and_then(|(ms, offset)| u64::try_from(i64::from(ms) * 1000 + offset).ok());
+        VehicleTime { boot_ms, utc_us }
+    }
+}
+
+/// Spots a vehicle reboot by its boot clock running backwards between
+/// messages of the autopilot.
+#[derive(Debug, Default)]
+pub(crate) struct BootWatch {
+    last_boot_ms: Option<u32>,
+}
+
+impl BootWatch {
+    /// Feed a message's `time_boot_ms`; true if the vehicle rebooted since
+    /// the previous one. Messages a little out of order are not a reboot.
+    pub(crate) fn observe(&mut self, boot_ms: u32) -> bool {
+        let threshold_ms = REBOOT_THRESHOLD.as_millis() as u64;
+        let rebooted = self
+            .last_boot_ms
+            .is_some_and(|last| u64::from(boot_ms) + threshold_ms < u64::from(last));
+        self.last_boot_ms = Some(boot_ms);
+        rebooted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+    /// 2024-01-01T00:00:00Z
+    const UTC_US: u64 = 1_704_067_200_000_000;
+
+    #[test]
+    fn timesync_removes_link_latency() {
+        let epoch = Instant::now();
+        let mut clock = VehicleClock::new(epoch);
+        assert_eq!(clock.stamp(None, epoch), VehicleTime::default());
+
+        // Vehicle booted 60 s before our epoch; 40 ms each way
+        let sent = epoch + MS * 100;
+        let answered_ns = (60_000 + 140) * 1_000_000;
+        clock.observe_timesync(answered_ns, local_ns(epoch, sent), sent + MS * 80);
+        let now = epoch + MS * 1000;
+        assert_eq!(clock.stamp(None, now).boot_ms, Some(61_000));
+
+        // Requests and replies slower than the limit are ignored
+        clock.observe_timesync(0, local_ns(epoch, now), now);
+        clock.observe_timesync(answered_ns + 5_000_000_000, 0, now);
+        assert_eq!(clock.stamp(None, now).boot_ms, Some(61_000));
+
+        // One-way SYSTEM_TIME no longer moves a synced clock
+        clock.observe_system_time(0, 60_950, now);
+        assert_eq!(clock.stamp(None, now).boot_ms, Some(61_000));
+    }
+
+    #[test]
+    fn system_time_gives_utc() {
+        let epoch = Instant::now();
+        let mut clock = VehicleClock::new(epoch);
+        clock.observe_system_time(0, 10_000, epoch);
+        let later = epoch + MS * 500;
+        assert_eq!(
+            clock.stamp(None, later),
+            VehicleTime {
+                boot_ms: Some(10_500),
+                utc_us: None,
+            }
+        );
+
+        clock.observe_system_time(UTC_US, 10_500, later);
+        // A message's own boot time wins over the estimate
+        assert_eq!(
+            clock.stamp(Some(10_400), later),
+            VehicleTime {
+                boot_ms: Some(10_400),
+                utc_us: Some(UTC_US - 100_000),
+            }
+        );
+        // GPS time lost: UTC still follows the boot clock
+        clock.observe_system_time(0, 11_500, later + MS * 1000);
+        assert_eq!(
+            clock.stamp(None, later + MS * 1000).utc_us,
+            Some(UTC_US + 1_000_000)
+        );
+    }
+
+    #[test]
+    fn vehicle_reboot_resets_the_clock() {
+        let epoch = Instant::now();
+        let mut clock = VehicleClock::new(epoch);
+        let sent = epoch + MS * 10;
+        clock.observe_timesync(300_000 * 1_000_000, local_ns(epoch, sent), sent);
+        clock.observe_system_time(UTC_US, 300_000, sent);
+
+        let rebooted = epoch + MS * 20_000;
+        clock.observe_system_time(0, 2_000, rebooted);
+        assert_eq!(
+            clock.stamp(None, rebooted),
+            VehicleTime {
+                boot_ms: Some(2_000),
+                utc_us: None,
+            }
+        );
+    }
+
+    #[test]
+    fn boot_clock_running_backwards_is_a_reboot() {
+        let mut watch = BootWatch::default();
+        assert!(!watch.observe(300_000));
+        assert!(!watch.observe(299_950));
+        assert!(!watch.observe(300_100));
+        assert!(watch.observe(1_500));
+        assert!(!watch.observe(1_600));
+    }
+}
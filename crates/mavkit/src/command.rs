@@ -1,8 +1,11 @@
+use crate::config::ConfigPatch;
 use crate::error::VehicleError;
 use crate::mission::{MissionPlan, MissionType};
 use crate::params::{Param, ParamStore};
-use mavlink::common::MavCmd;
+use crate::rate_benchmark::{RateBenchmarkOptions, RateReport};
+use mavlink::ardupilotmega::{MavCmd, MavFrame};
 use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
 
 pub(crate) enum Command {
     Arm {
@@ -22,18 +25,35 @@ pub(crate) enum Command {
         params: [f32; 7],
         reply: oneshot::Sender<Result<(), VehicleError>>,
     },
+    CommandInt {
+        command: MavCmd,
+        frame: MavFrame,
+        params: [f32; 4],
+        x: i32,
+        y: i32,
+        z: f32,
+        reply: oneshot::Sender<Result<(), VehicleError>>,
+    },
     GuidedGoto {
         lat_e7: i32,
         lon_e7: i32,
         alt_m: f32,
         reply: oneshot::Sender<Result<(), VehicleError>>,
     },
+    SendRaw {
+        message: mavlink::ardupilotmega::MavMessage,
+        response: Option<String>,
+        reply: oneshot::Sender<Result<Option<String>, VehicleError>>,
+    },
     MissionUpload {
         plan: MissionPlan,
+        /// Ends this transfer only; the event loop's token ends everything.
+        cancel: CancellationToken,
         reply: oneshot::Sender<Result<(), VehicleError>>,
     },
     MissionDownload {
         mission_type: MissionType,
+        cancel: CancellationToken,
         reply: oneshot::Sender<Result<MissionPlan, VehicleError>>,
     },
     MissionClear {
@@ -44,8 +64,8 @@ pub(crate) enum Command {
         seq: u16,
         reply: oneshot::Sender<Result<(), VehicleError>>,
     },
-    MissionCancelTransfer,
     ParamDownloadAll {
+        cancel: CancellationToken,
         reply: oneshot::Sender<Result<ParamStore, VehicleError>>,
     },
     ParamWrite {
@@ -53,5 +73,249 @@ pub(crate) enum Command {
         value: f32,
         reply: oneshot::Sender<Result<Param, VehicleError>>,
     },
+    /// Publish a store loaded from the parameter cache.
+    ParamSeed {
+        store: ParamStore,
+        reply: oneshot::Sender<Result<(), VehicleError>>,
+    },
+    /// Read the vehicle's parameter count and `_HASH_CHECK`.
+    ParamHashCheck {
+        reply: oneshot::Sender<Result<(u16, u32), VehicleError>>,
+    },
+    /// Listen to the autopilot's messages for a window and measure their
+    /// rates; runs beside later commands.
+    RateBenchmark {
+        options: RateBenchmarkOptions,
+        cancel: CancellationToken,
+        reply: oneshot::Sender<Result<RateReport, VehicleError>>,
+    },
+    SetGcsIdentity {
+        system_id: u8,
+        component_id: u8,
+        reply: oneshot::Sender<Result<(), VehicleError>>,
+    },
+    RequestControl {
+        force: bool,
+        reply: oneshot::Sender<Result<(), VehicleError>>,
+    },
+    ReleaseControl {
+        reply: oneshot::Sender<Result<(), VehicleError>>,
+    },
+    SetControlOverride {
+        enabled: bool,
+        reply: oneshot::Sender<Result<(), VehicleError>>,
+    },
+    UpdateConfig {
+        patch: ConfigPatch,
+        reply: oneshot::Sender<Result<(), VehicleError>>,
+    },
     Shutdown,
 }
+
+impl Command {
+    /// Commands that act on the vehicle and are refused while a peer GCS owns
+    /// it. Disarming and forced arming always go out, as do COMMAND_LONGs
+    /// that bring the vehicle down or home; RTL/LAND mode changes are let
+    /// through by the event loop, which knows the mode table.
+    pub(crate) fn commands_vehicle(&self) -> bool {
+        match self {
+            Command::Disarm { .. } | Command::Arm { force: true, .. } => false,
+            Command::CommandLong {
+                command, params, ..
+            } => !is_safety_command(*command, params),
+            Command::CommandInt {
+                command, params, ..
+            } => {
+                let [p1, p2, p3, p4] = *params;
+                !is_safety_command(*command, &[p1, p2, p3, p4, 0.0, 0.0, 0.0])
+            }
+            Command::Arm { .. }
+            | Command::SetMode { .. }
+            | Command::GuidedGoto { .. }
+            | Command::SendRaw { .. }
+            | Command::MissionUpload { .. }
+            | Command::MissionClear { .. }
+            | Command::MissionSetCurrent { .. }
+            | Command::ParamWrite { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Commands that may be needed to recover the vehicle: disarm, mode
+    /// changes and the safety commands. They take the priority lane, which
+    /// runs them while a transfer holds the main command loop.
+    pub(crate) fn is_urgent(&self) -> bool {
+        match self {
+            Command::Disarm { .. } | Command::SetMode { .. } => true,
+            Command::CommandLong {
+                command, params, ..
+            } => is_safety_command(*command, params),
+            Command::CommandInt {
+                command, params, ..
+            } => {
+                let [p1, p2, p3, p4] = *params;
+                is_safety_command(*command, &[p1, p2, p3, p4, 0.0, 0.0, 0.0])
+            }
+            _ => false,
+        }
+    }
+
+    /// Name and parameters for the audit trail, for commands that act on the
+    /// vehicle; `None` for reads and local bookkeeping.
+    pub(crate) fn audit(&self) -> Option<(&'static str, String)> {
+        let entry = match self {
+            Command::Arm { force, .. } => ("arm", format!("force={force}")),
+            Command::Disarm { force, .. } => ("disarm", format!("force={force}")),
+            Command::SetMode { custom_mode, .. } => {
+                ("set_mode", format!("custom_mode={custom_mode}"))
+            }
+            Command::CommandLong {
+                command, params, ..
+            } => ("command_long", format!("{command:?} {params:?}")),
+            Command::CommandInt {
+                command,
+                frame,
+                params,
+                x,
+                y,
+                z,
+                ..
+            } => (
+                "command_int",
+                format!("{command:?} {frame:?} {params:?} x={x} y={y} z={z}"),
+            ),
+            Command::GuidedGoto {
+                lat_e7,
+                lon_e7,
+                alt_m,
+                ..
+            } => (
+                "goto",
+                format!("lat_e7={lat_e7} lon_e7={lon_e7} alt_m={alt_m}"),
+            ),
+            Command::SendRaw { message, .. } => ("send_raw", format!("{message:?}")),
+            Command::MissionUpload { plan, .. } => (
+                "mission_upload",
+                format!("{:?} items={}", plan.mission_type, plan.items.len()),
+            ),
+            Command::MissionClear { mission_type, .. } => {
+                ("mission_clear", format!("{mission_type:?}"))
+            }
+            Command::MissionSetCurrent { seq, .. } => ("mission_set_current", format!("seq={seq}")),
+            Command::ParamWrite { name, value, .. } => ("param_write", format!("{name}={value}")),
+            _ => return None,
+        };
+        Some(entry)
+    }
+
+    /// Answer the command with `err` without running it.
+    pub(crate) fn reject(self, err: VehicleError) {
+        match self {
+            Command::Arm { reply, .. }
+            | Command::Disarm { reply, .. }
+            | Command::SetMode { reply, .. }
+            | Command::CommandLong { reply, .. }
+            | Command::CommandInt { reply, .. }
+            | Command::GuidedGoto { reply, .. }
+            | Command::MissionUpload { reply, .. }
+            | Command::MissionClear { reply, .. }
+            | Command::MissionSetCurrent { reply, .. }
+            | Command::SetGcsIdentity { reply, .. }
+            | Command::RequestControl { reply, .. }
+            | Command::ReleaseControl { reply }
+            | Command::SetControlOverride { reply, .. }
+            | Command::ParamSeed { reply, .. }
+            | Command::UpdateConfig { reply, .. } => {
+                let _ = reply.send(Err(err));
+            }
+            Command::SendRaw { reply, .. } => {
+                let _ = reply.send(Err(err));
+            }
+            Command::MissionDownload { reply, .. } => {
+                let _ = reply.send(Err(err));
+            }
+            Command::ParamDownloadAll { reply, .. } => {
+                let _ = reply.send(Err(err));
+            }
+            Command::ParamWrite { reply, .. } => {
+                let _ = reply.send(Err(err));
+            }
+            Command::ParamHashCheck { reply } => {
+                let _ = reply.send(Err(err));
+            }
+            Command::RateBenchmark { reply, .. } => {
+                let _ = reply.send(Err(err));
+            }
+            Command::Shutdown => {}
+        }
+    }
+}
+
+/// Disarm, forced arm/disarm, return to launch, land and flight termination.
+fn is_safety_command(command: MavCmd, params: &[f32; 7]) -> bool {
+    match command {
+        MavCmd::MAV_CMD_COMPONENT_ARM_DISARM => params[0] == 0.0 || params[1] != 0.0,
+        MavCmd::MAV_CMD_NAV_RETURN_TO_LAUNCH
+        | MavCmd::MAV_CMD_NAV_LAND
+        | MavCmd::MAV_CMD_NAV_VTOL_LAND
+        | MavCmd::MAV_CMD_DO_FLIGHTTERMINATION => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_long(command: MavCmd, params: [f32; 7]) -> Command {
+        let (reply, _) = oneshot::channel();
+        Command::CommandLong {
+            command,
+            params,
+            reply,
+        }
+    }
+
+    #[test]
+    fn safety_commands_go_out_while_a_peer_has_control() {
+        let (reply, _) = oneshot::channel();
+        assert!(!Command::Disarm {
+            force: false,
+            reply
+        }
+        .commands_vehicle());
+        let (reply, _) = oneshot::channel();
+        assert!(!Command::Arm { force: true, reply }.commands_vehicle());
+        let (reply, _) = oneshot::channel();
+        assert!(Command::Arm {
+            force: false,
+            reply
+        }
+        .commands_vehicle());
+
+        let disarm = [0.0; 7];
+        let arm = [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        assert!(!command_long(MavCmd::MAV_CMD_COMPONENT_ARM_DISARM, disarm).commands_vehicle());
+        assert!(command_long(MavCmd::MAV_CMD_COMPONENT_ARM_DISARM, arm).commands_vehicle());
+        assert!(!command_long(MavCmd::MAV_CMD_NAV_RETURN_TO_LAUNCH, [0.0; 7]).commands_vehicle());
+        assert!(command_long(MavCmd::MAV_CMD_DO_REPOSITION, [0.0; 7]).commands_vehicle());
+    }
+
+    #[test]
+    fn recovery_commands_take_the_priority_lane() {
+        let (reply, _) = oneshot::channel();
+        assert!(Command::SetMode {
+            custom_mode: 6,
+            reply
+        }
+        .is_urgent());
+        assert!(command_long(MavCmd::MAV_CMD_NAV_LAND, [0.0; 7]).is_urgent());
+        assert!(!command_long(MavCmd::MAV_CMD_DO_REPOSITION, [0.0; 7]).is_urgent());
+        let (reply, _) = oneshot::channel();
+        assert!(!Command::MissionClear {
+            mission_type: MissionType::Mission,
+            reply
+        }
+        .is_urgent());
+    }
+}
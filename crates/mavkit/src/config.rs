@@ -1,6 +1,16 @@
+use crate::battery_health::CellLimits;
+use crate::esc::DEFAULT_ESC_MAX_TEMPERATURE_C;
+use crate::guided::{NudgeLimits, TerrainCheck};
 use crate::mission::RetryPolicy;
+use crate::modes::{ModeCheck, ModeOverrides};
+use crate::remote_id::RemoteIdOperator;
+use crate::send_queue::LinkPacing;
+use crate::smoothing::TelemetrySmoothing;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::time::Duration;
 
+#[derive(Clone)]
 pub struct VehicleConfig {
     pub gcs_system_id: u8,
     pub gcs_component_id: u8,
@@ -8,6 +18,52 @@ pub struct VehicleConfig {
     pub auto_request_home: bool,
     pub command_buffer_size: usize,
     pub connect_timeout: Duration,
+    /// ESC temperature above which `EscTelemetry::over_temperature` is set.
+    pub esc_max_temperature_c: f32,
+    /// Per-cell limits behind the alerts of `Vehicle::battery_health`; LiPo
+    /// by default, see `CellLimits::for_chemistry`.
+    pub battery_cell_limits: CellLimits,
+    /// Outgoing bandwidth budget; see `LinkPacing::serial` for radio links.
+    pub link_pacing: LinkPacing,
+    /// Drop a message whose sender ids, sequence number and message id
+    /// repeat one received within this window, for streams that reach us
+    /// over two routes (a router and a direct link, fused radios). Off
+    /// (`None`) by default; keep it well under the time a component takes
+    /// to send 256 messages, after which sequence numbers repeat.
+    pub link_dedup_window: Option<Duration>,
+    /// How often the event loop checks for due housekeeping jobs.
+    pub tick_interval: Duration,
+    /// Period of our own GCS HEARTBEAT. Off (`None`) by default, so mavkit
+    /// only listens unless asked to announce itself.
+    pub gcs_heartbeat_interval: Option<Duration>,
+    /// Period of TIMESYNC requests used to track the vehicle clock. Off
+    /// (`None`) by default, relying on SYSTEM_TIME alone.
+    pub timesync_interval: Option<Duration>,
+    /// Largest step `Vehicle::nudge` and `Vehicle::change_altitude` take.
+    pub nudge_limits: NudgeLimits,
+    /// Whether guided gotos whose path grazes the terrain are flown with a
+    /// warning or refused. Only applies once `Vehicle::set_terrain` is set.
+    pub terrain_check: TerrainCheck,
+    /// STATUSTEXT sent by `Vehicle::disconnect`, followed by a HEARTBEAT
+    /// announcing the GCS powering off, so the vehicle log and other ground
+    /// stations see a deliberate goodbye rather than a lost link. Off
+    /// (`None`) by default.
+    pub shutdown_notice: Option<String>,
+    /// Operator ID and self-ID for the vehicle's Remote ID broadcast, sent
+    /// once a second while set. Empty by default.
+    pub remote_id: RemoteIdOperator,
+    /// Directory keeping armed time and distance flown per airframe, see
+    /// `FlightLog`. Off (`None`) by default, counting the session only.
+    pub flight_log_dir: Option<PathBuf>,
+    /// Renamed or added modes of custom firmware, used for mode names,
+    /// `Vehicle::available_modes` and `Vehicle::set_mode_by_name`.
+    pub mode_overrides: ModeOverrides,
+    /// Whether `Vehicle::set_mode` refuses, warns about or ignores mode
+    /// changes with a `ModeHazard`.
+    pub mode_check: ModeCheck,
+    /// Filtering and prediction behind `Vehicle::telemetry_smoothed`. Off
+    /// (`None`) by default, passing the raw values through.
+    pub telemetry_smoothing: Option<TelemetrySmoothing>,
 }
 
 impl Default for VehicleConfig {
@@ -19,6 +75,98 @@ impl Default for VehicleConfig {
             auto_request_home: true,
             command_buffer_size: 32,
             connect_timeout: Duration::from_secs(30),
+            esc_max_temperature_c: DEFAULT_ESC_MAX_TEMPERATURE_C,
+            battery_cell_limits: CellLimits::default(),
+            link_pacing: LinkPacing::UNLIMITED,
+            link_dedup_window: None,
+            tick_interval: Duration::from_millis(100),
+            gcs_heartbeat_interval: None,
+            timesync_interval: None,
+            nudge_limits: NudgeLimits::default(),
+            terrain_check: TerrainCheck::default(),
+            shutdown_notice: None,
+            remote_id: RemoteIdOperator::default(),
+            flight_log_dir: None,
+            mode_overrides: ModeOverrides::default(),
+            mode_check: ModeCheck::default(),
+            telemetry_smoothing: None,
         }
     }
 }
+
+/// Settings that can change on a live connection, see
+/// `Vehicle::update_config`. Fields left `None` keep their current value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigPatch {
+    pub retry_policy: Option<RetryPolicy>,
+    pub auto_request_home: Option<bool>,
+    /// Outgoing bandwidth budget, e.g. `LinkPacing::serial` when moving from
+    /// a LAN to a telemetry radio.
+    pub link_pacing: Option<LinkPacing>,
+    pub esc_max_temperature_c: Option<f32>,
+    pub nudge_limits: Option<NudgeLimits>,
+    pub terrain_check: Option<TerrainCheck>,
+    /// Replaces the Remote ID operator data; empty fields stop being sent.
+    pub remote_id: Option<RemoteIdOperator>,
+    /// Replaces the custom mode table; shown from the next HEARTBEAT.
+    pub mode_overrides: Option<ModeOverrides>,
+    pub mode_check: Option<ModeCheck>,
+}
+
+impl ConfigPatch {
+    pub fn apply(&self, config: &mut VehicleConfig) {
+        if let Some(retry_policy) = self.retry_policy {
+            config.retry_policy = retry_policy;
+        }
+        if let Some(auto_request_home) = self.auto_request_home {
+            config.auto_request_home = auto_request_home;
+        }
+        if let Some(link_pacing) = self.link_pacing {
+            config.link_pacing = link_pacing;
+        }
+        if let Some(esc_max_temperature_c) = self.esc_max_temperature_c {
+            config.esc_max_temperature_c = esc_max_temperature_c;
+        }
+        if let Some(nudge_limits) = self.nudge_limits {
+            config.nudge_limits = nudge_limits;
+        }
+        if let Some(terrain_check) = self.terrain_check {
+            config.terrain_check = terrain_check;
+        }
+        if let Some(remote_id) = &self.remote_id {
+            config.remote_id = remote_id.clone();
+        }
+        if let Some(mode_overrides) = &self.mode_overrides {
+            config.mode_overrides = mode_overrides.clone();
+        }
+        if let Some(mode_check) = self.mode_check {
+            config.mode_check = mode_check;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_only_changes_the_fields_it_sets() {
+        let mut config = VehicleConfig::default();
+        let retry_policy = RetryPolicy {
+            request_timeout_ms: 4000,
+            item_timeout_ms: 1000,
+            max_retries: 8,
+        };
+        ConfigPatch {
+            retry_policy: Some(retry_policy),
+            link_pacing: Some(LinkPacing::serial(57_600)),
+            ..ConfigPatch::default()
+        }
+        .apply(&mut config);
+        assert_eq!(config.retry_policy, retry_policy);
+        assert_eq!(config.link_pacing, LinkPacing::serial(57_600));
+        assert!(config.auto_request_home);
+        assert_eq!(config.gcs_system_id, 255);
+    }
+}
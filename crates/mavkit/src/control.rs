@@ -0,0 +1,209 @@
+//! Control ownership between ground stations sharing a vehicle.
+//!
+//! The common dialect has no operator-control handshake, so ownership is a
+//! convention: we own the vehicle after `request_control`, and a peer GCS
+//! (see `gcs`) owns it once we see it send control traffic to the vehicle.
+//! Peer ownership lapses when the peer stops heartbeating or has not
+//! commanded for `PEER_CONTROL_IDLE`. While a peer owns the vehicle our own
+//! commands are refused unless the override is enabled; disarming, forced
+//! arming and switching to RTL or LAND always go out.
+
+use crate::gcs::GcsPeer;
+use mavlink::ardupilotmega::MavCmd;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Peer ownership lapses after this long without a command from the peer.
+pub const PEER_CONTROL_IDLE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ControlOwner {
+    #[default]
+    Unclaimed,
+    Us,
+    Peer {
+        system_id: u8,
+        component_id: u8,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ControlState {
+    pub owner: ControlOwner,
+    /// Send commands even while a peer owns the vehicle.
+    pub override_enabled: bool,
+    #[serde(skip)]
+    pub(crate) peer_last_command: Option<Instant>,
+}
+
+impl ControlState {
+    /// Whether our commands may go out right now.
+    pub fn may_command(&self) -> bool {
+        self.override_enabled || !matches!(self.owner, ControlOwner::Peer { .. })
+    }
+}
+
+/// Commands that steer the vehicle. A peer sending one of these in
+/// COMMAND_LONG or COMMAND_INT takes control; requests such as
+/// REQUEST_MESSAGE or SET_MESSAGE_INTERVAL do not.
+pub(crate) fn is_control_command(command: MavCmd) -> bool {
+    matches!(
+        command,
+        MavCmd::MAV_CMD_COMPONENT_ARM_DISARM
+            | MavCmd::MAV_CMD_DO_SET_MODE
+            | MavCmd::MAV_CMD_NAV_TAKEOFF
+            | MavCmd::MAV_CMD_NAV_VTOL_TAKEOFF
+            | MavCmd::MAV_CMD_NAV_LAND
+            | MavCmd::MAV_CMD_NAV_VTOL_LAND
+            | MavCmd::MAV_CMD_NAV_RETURN_TO_LAUNCH
+            | MavCmd::MAV_CMD_NAV_WAYPOINT
+            | MavCmd::MAV_CMD_NAV_LOITER_UNLIM
+            | MavCmd::MAV_CMD_MISSION_START
+            | MavCmd::MAV_CMD_DO_SET_MISSION_CURRENT
+            | MavCmd::MAV_CMD_DO_PAUSE_CONTINUE
+            | MavCmd::MAV_CMD_DO_REPOSITION
+            | MavCmd::MAV_CMD_DO_ORBIT
+            | MavCmd::MAV_CMD_DO_CHANGE_SPEED
+            | MavCmd::MAV_CMD_DO_LAND_START
+            | MavCmd::MAV_CMD_DO_FLIGHTTERMINATION
+            | MavCmd::MAV_CMD_CONDITION_YAW
+    )
+}
+
+/// A peer GCS sent control traffic. Returns true when ownership changed.
+pub(crate) fn observe_peer_command(
+    state: &mut ControlState,
+    system_id: u8,
+    component_id: u8,
+    now: Instant,
+) -> bool {
+    let owner = ControlOwner::Peer {
+        system_id,
+        component_id,
+    };
+    state.peer_last_command = Some(now);
+    let changed = state.owner != owner;
+    state.owner = owner;
+    changed
+}
+
+/// Release peer ownership once the peer has gone silent or idle.
+pub(crate) fn expire_peer_control(
+    state: &mut ControlState,
+    peers: &[GcsPeer],
+    now: Instant,
+) -> bool {
+    let ControlOwner::Peer {
+        system_id,
+        component_id,
+    } = state.owner
+    else {
+        return false;
+    };
+    let alive = peers
+        .iter()
+        .any(|p| p.system_id == system_id && p.component_id == component_id);
+    let idle = state
+        .peer_last_command
+        .is_none_or(|t| now.saturating_duration_since(t) >= PEER_CONTROL_IDLE);
+    if alive && !idle {
+        return false;
+    }
+    state.owner = ControlOwner::Unclaimed;
+    state.peer_last_command = None;
+    true
+}
+
+/// Claim ownership. Fails with the current owner if a peer holds it and
+/// `force` is not set.
+pub(crate) fn claim_control(state: &mut ControlState, force: bool) -> Result<(), ControlOwner> {
+    if matches!(state.owner, ControlOwner::Peer { .. }) && !force {
+        return Err(state.owner);
+    }
+    state.owner = ControlOwner::Us;
+    state.peer_last_command = None;
+    Ok(())
+}
+
+pub(crate) fn release_control(state: &mut ControlState) {
+    if state.owner == ControlOwner::Us {
+        state.owner = ControlOwner::Unclaimed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(system_id: u8) -> GcsPeer {
+        GcsPeer {
+            system_id,
+            component_id: 190,
+            conflicts: false,
+            last_seen: None,
+        }
+    }
+
+    #[test]
+    fn peer_command_blocks_us_until_override() {
+        let now = Instant::now();
+        let mut state = ControlState::default();
+        assert!(state.may_command());
+
+        assert!(observe_peer_command(&mut state, 254, 190, now));
+        assert!(!state.may_command());
+        assert_eq!(
+            claim_control(&mut state, false),
+            Err(ControlOwner::Peer {
+                system_id: 254,
+                component_id: 190
+            })
+        );
+
+        state.override_enabled = true;
+        assert!(state.may_command());
+    }
+
+    #[test]
+    fn only_steering_commands_count_as_control() {
+        assert!(is_control_command(MavCmd::MAV_CMD_COMPONENT_ARM_DISARM));
+        assert!(is_control_command(MavCmd::MAV_CMD_DO_REPOSITION));
+        assert!(!is_control_command(MavCmd::MAV_CMD_REQUEST_MESSAGE));
+        assert!(!is_control_command(MavCmd::MAV_CMD_SET_MESSAGE_INTERVAL));
+    }
+
+    #[test]
+    fn forced_claim_takes_over() {
+        let now = Instant::now();
+        let mut state = ControlState::default();
+        observe_peer_command(&mut state, 254, 190, now);
+        assert!(claim_control(&mut state, true).is_ok());
+        assert_eq!(state.owner, ControlOwner::Us);
+
+        release_control(&mut state);
+        assert_eq!(state.owner, ControlOwner::Unclaimed);
+    }
+
+    #[test]
+    fn peer_control_lapses_when_idle_or_gone() {
+        let now = Instant::now();
+        let mut state = ControlState::default();
+        observe_peer_command(&mut state, 254, 190, now);
+
+        assert!(!expire_peer_control(
+            &mut state,
+            &[peer(254)],
+            now + Duration::from_secs(5)
+        ));
+        assert!(expire_peer_control(
+            &mut state,
+            &[peer(254)],
+            now + PEER_CONTROL_IDLE
+        ));
+        assert_eq!(state.owner, ControlOwner::Unclaimed);
+
+        observe_peer_command(&mut state, 254, 190, now);
+        assert!(expire_peer_control(&mut state, &[peer(253)], now));
+    }
+}
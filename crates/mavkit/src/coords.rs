@@ -0,0 +1,633 @@
+//! Coordinate display formats: decimal degrees, degrees and decimal
+//! minutes, degrees-minutes-seconds, UTM and MGRS.
+//!
+//! Positions are WGS84 `(lat, lon)` in degrees everywhere else in mavkit;
+//! these are only for showing them to the operator and reading back what
+//! they type. UTM and MGRS cover 80°S to 84°N; the polar UPS grids are not
+//! supported.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Text that is not a coordinate, or a position outside the UTM/MGRS grids.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid coordinate: {0}")]
+pub struct CoordinateError(pub String);
+
+// WGS84
+const SEMI_MAJOR_AXIS_M: f64 = 6_378_137.0;
+const FLATTENING: f64 = 1.0 / 298.257_223_563;
+
+const UTM_SCALE: f64 = 0.9996;
+const FALSE_EASTING_M: f64 = 500_000.0;
+const FALSE_NORTHING_SOUTH_M: f64 = 10_000_000.0;
+const UTM_MIN_LAT: f64 = -80.0;
+const UTM_MAX_LAT: f64 = 84.0;
+/// Latitude bands of 8° from 80°S; X is stretched to 12°.
+const BANDS: &[u8; 20] = b"CDEFGHJKLMNPQRSTUVWX";
+
+const MGRS_SQUARE_M: f64 = 100_000.0;
+/// MGRS row letters repeat every 2000 km of northing.
+const MGRS_ROW_CYCLE_M: f64 = 2_000_000.0;
+const MGRS_COLUMNS: [&[u8; 8]; 3] = [b"ABCDEFGH", b"JKLMNPQR", b"STUVWXYZ"];
+const MGRS_ROWS: &[u8; 20] = b"ABCDEFGHJKLMNPQRSTUV";
+/// Digits per axis in formatted MGRS: 1 m.
+const MGRS_DIGITS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoordinateFormat {
+    /// `60.170100, 24.937600`
+    #[default]
+    DecimalDegrees,
+    /// `60°10.2060'N 24°56.2560'E`
+    DegreesMinutes,
+    /// `60°10'12.36"N 24°56'15.36"E`
+    DegreesMinutesSeconds,
+    /// `35V 385568 6672142`
+    Utm,
+    /// `35V LG 85567 72142`
+    Mgrs,
+}
+
+/// A UTM grid position.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Utm {
+    pub zone: u8,
+    /// Latitude band letter, `C` to `X`; `N` and above are north of the
+    /// equator.
+    pub band: char,
+    pub easting_m: f64,
+    /// From the equator, or from 10 000 km south of it in the southern
+    /// hemisphere.
+    pub northing_m: f64,
+}
+
+impl Utm {
+    pub fn from_lat_lon(lat: f64, lon: f64) -> Result<Self, CoordinateError> {
+        if !(UTM_MIN_LAT..UTM_MAX_LAT).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(invalid(format!(
+                "{lat:.6}, {lon:.6} is outside the UTM grid (80°S to 84°N)"
+            )));
+        }
+        let zone = utm_zone(lat, lon);
+        let (easting_m, northing_m) = project(lat, lon, zone);
+        Ok(Self {
+            zone,
+            band: band_letter(lat),
+            easting_m,
+            northing_m: if lat < 0.0 {
+                northing_m + FALSE_NORTHING_SOUTH_M
+            } else {
+                northing_m
+            },
+        })
+    }
+
+    pub fn to_lat_lon(&self) -> (f64, f64) {
+        let northing = if self.is_north() {
+            self.northing_m
+        } else {
+            self.northing_m - FALSE_NORTHING_SOUTH_M
+        };
+        unproject(self.easting_m, northing, self.zone)
+    }
+
+    pub fn is_north(&self) -> bool {
+        self.band >= 'N'
+    }
+
+    /// `35V 385568 6672142`, or with a separate band letter
+    /// `35 V 385568 6672142`; `m`, `mE` and `mN` suffixes are accepted.
+    pub fn parse(text: &str) -> Result<Self, CoordinateError> {
+        let tokens: Vec<&str> = text
+            .split([' ', ',', '\t'])
+            .filter(|t| !t.is_empty())
+            .collect();
+        let (zone_band, rest): (String, &[&str]) = match tokens.as_slice() {
+            [zone_band, rest @ ..] if rest.len() == 2 => (zone_band.to_string(), rest),
+            [zone, band, rest @ ..] if rest.len() == 2 => (format!("{zone}{band}"), rest),
+            _ => return Err(invalid(format!("not a UTM position: {text}"))),
+        };
+        let (zone, band) = parse_zone_band(&zone_band)
+            .ok_or_else(|| invalid(format!("not a UTM zone and band: {zone_band}")))?;
+        let metres = |token: &str| -> Option<f64> {
+            let token = token.to_ascii_lowercase();
+            let number = ["me", "mn", "m"]
+                .iter()
+                .find_map(|suffix| token.strip_suffix(suffix))
+                .unwrap_or(&token);
+            number.parse().ok()
+        };
+        match (metres(rest[0]), metres(rest[1])) {
+            (Some(easting_m), Some(northing_m)) => Ok(Self {
+                zone,
+                band,
+                easting_m,
+                northing_m,
+            }),
+            _ => Err(invalid(format!("not a UTM position: {text}"))),
+        }
+    }
+}
+
+impl fmt::Display for Utm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{} {:.0} {:.0}",
+            self.zone, self.band, self.easting_m, self.northing_m
+        )
+    }
+}
+
+/// Show `(lat, lon)` in `format`. Fails only for UTM and MGRS outside
+/// their coverage.
+pub fn format_coordinate(
+    lat: f64,
+    lon: f64,
+    format: CoordinateFormat,
+) -> Result<String, CoordinateError> {
+    Ok(match format {
+        CoordinateFormat::DecimalDegrees => format!("{lat:.6}, {lon:.6}"),
+        CoordinateFormat::DegreesMinutes | CoordinateFormat::DegreesMinutesSeconds => format!(
+            "{} {}",
+            sexagesimal(lat, 'N', 'S', format),
+            sexagesimal(lon, 'E', 'W', format)
+        ),
+        CoordinateFormat::Utm => Utm::from_lat_lon(lat, lon)?.to_string(),
+        CoordinateFormat::Mgrs => to_mgrs(lat, lon, MGRS_DIGITS)?,
+    })
+}
+
+/// Read a position typed in any of the `CoordinateFormat`s. Degrees may be
+/// signed or carry N/S/E/W on either side; symbols are optional.
+pub fn parse_coordinate(text: &str) -> Result<(f64, f64), CoordinateError> {
+    let text = text.trim();
+    if let Ok(position) = from_mgrs(text) {
+        return Ok(position);
+    }
+    if let Ok(utm) = Utm::parse(text) {
+        return Ok(utm.to_lat_lon());
+    }
+    parse_degrees(text)
+}
+
+/// MGRS reference with `digits` (1 to 5) per axis, i.e. a precision of
+/// 10 km down to 1 m. As the standard requires, the position is truncated
+/// to the south-west corner of its square rather than rounded.
+pub fn to_mgrs(lat: f64, lon: f64, digits: usize) -> Result<String, CoordinateError> {
+    if !(1..=MGRS_DIGITS).contains(&digits) {
+        return Err(invalid(format!("MGRS precision of {digits} digits")));
+    }
+    let utm = Utm::from_lat_lon(lat, lon)?;
+    let column = (utm.easting_m / MGRS_SQUARE_M).floor() as usize;
+    let columns = MGRS_COLUMNS[(utm.zone as usize - 1) % 3];
+    let Some(&column_letter) = column.checked_sub(1).and_then(|i| columns.get(i)) else {
+        return Err(invalid(format!(
+            "easting {:.0} has no MGRS square",
+            utm.easting_m
+        )));
+    };
+    let row = (utm.northing_m / MGRS_SQUARE_M).floor() as usize;
+    let row_letter = MGRS_ROWS[(row + mgrs_row_offset(utm.zone)) % MGRS_ROWS.len()];
+    let scale = 10f64.powi((MGRS_DIGITS - digits) as i32);
+    let easting = ((utm.easting_m % MGRS_SQUARE_M) / scale).floor() as u32;
+    let northing = ((utm.northing_m % MGRS_SQUARE_M) / scale).floor() as u32;
+    Ok(format!(
+        "{}{} {}{} {easting:0digits$} {northing:0digits$}",
+        utm.zone, utm.band, column_letter as char, row_letter as char
+    ))
+}
+
+/// The south-west corner of the square an MGRS reference names, with or
+/// without spaces.
+pub fn from_mgrs(text: &str) -> Result<(f64, f64), CoordinateError> {
+    let compact: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    let not_mgrs = || invalid(format!("not an MGRS reference: {text}"));
+    let letters = compact
+        .find(|c: char| c.is_ascii_alphabetic())
+        .ok_or_else(not_mgrs)?;
+    let (zone_band, rest) = compact.split_at((letters + 1).min(compact.len()));
+    let (zone, band) = parse_zone_band(zone_band).ok_or_else(not_mgrs)?;
+    let rest = rest.as_bytes();
+    if rest.len() < 2 || !rest.len().is_multiple_of(2) || rest.len() > 2 + 2 * MGRS_DIGITS {
+        return Err(not_mgrs());
+    }
+    let (square, digits) = rest.split_at(2);
+    if !digits.iter().all(u8::is_ascii_digit) {
+        return Err(not_mgrs());
+    }
+    let column = MGRS_COLUMNS[(zone as usize - 1) % 3]
+        .iter()
+        .position(|&c| c == square[0])
+        .ok_or_else(not_mgrs)?;
+    let row = MGRS_ROWS
+        .iter()
+        .position(|&c| c == square[1])
+        .ok_or_else(not_mgrs)?;
+
+    let half = digits.len() / 2;
+    let scale = 10f64.powi((MGRS_DIGITS - half) as i32);
+    let number = |bytes: &[u8]| -> f64 {
+        let text = std::str::from_utf8(bytes).unwrap_or("0");
+        text.parse::<f64>().unwrap_or(0.0) * scale
+    };
+    let easting_m = (column + 1) as f64 * MGRS_SQUARE_M + number(&digits[..half]);
+    let rows = MGRS_ROWS.len();
+    let row_in_cycle = (row + rows - mgrs_row_offset(zone)) % rows;
+    let northing_in_cycle = row_in_cycle as f64 * MGRS_SQUARE_M + number(&digits[half..]);
+
+    // The row letters repeat every 2000 km; a band is under 1400 km tall, so
+    // the cycle nearest the middle of the band is the right one.
+    let band_index = BANDS.iter().position(|&b| b as char == band).unwrap_or(0);
+    let band_middle_lat = if band == 'X' {
+        78.0
+    } else {
+        UTM_MIN_LAT + 8.0 * band_index as f64 + 4.0
+    };
+    let expected = Utm::from_lat_lon(band_middle_lat, central_meridian(zone))?;
+    let cycles = ((expected.northing_m - northing_in_cycle) / MGRS_ROW_CYCLE_M).round();
+    let utm = Utm {
+        zone,
+        band,
+        easting_m,
+        northing_m: northing_in_cycle + cycles * MGRS_ROW_CYCLE_M,
+    };
+    Ok(utm.to_lat_lon())
+}
+
+fn invalid(message: String) -> CoordinateError {
+    CoordinateError(message)
+}
+
+fn utm_zone(lat: f64, lon: f64) -> u8 {
+    // South-western Norway
+    if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+        return 32;
+    }
+    // Svalbard
+    if (72.0..84.0).contains(&lat) && (0.0..42.0).contains(&lon) {
+        return match lon {
+            lon if lon < 9.0 => 31,
+            lon if lon < 21.0 => 33,
+            lon if lon < 33.0 => 35,
+            _ => 37,
+        };
+    }
+    (((lon + 180.0) / 6.0).floor() as i32 + 1).clamp(1, 60) as u8
+}
+
+fn band_letter(lat: f64) -> char {
+    let index = ((lat - UTM_MIN_LAT) / 8.0).floor() as usize;
+    BANDS[index.min(BANDS.len() - 1)] as char
+}
+
+fn central_meridian(zone: u8) -> f64 {
+    zone as f64 * 6.0 - 183.0
+}
+
+/// Even zones start their row letters at `F`.
+fn mgrs_row_offset(zone: u8) -> usize {
+    if zone.is_multiple_of(2) {
+        5
+    } else {
+        0
+    }
+}
+
+/// `35V`; `None` unless the zone is 1 to 60 and the band a valid letter.
+fn parse_zone_band(text: &str) -> Option<(u8, char)> {
+    let band = text.chars().last()?;
+    if !band.is_ascii() {
+        return None;
+    }
+    let zone: u8 = text[..text.len() - 1].parse().ok()?;
+    let band = band.to_ascii_uppercase();
+    ((1..=60).contains(&zone) && BANDS.contains(&(band as u8))).then_some((zone, band))
+}
+
+/// Coefficients of the Krüger series for WGS84, accurate to well under a
+/// millimetre within a zone.
+struct Kruger {
+    /// Meridian radius: 2π times this is the length of a meridian circle.
+    radius: f64,
+    alpha: [f64; 3],
+    beta: [f64; 3],
+    delta: [f64; 3],
+}
+
+fn kruger() -> Kruger {
+    let n = FLATTENING / (2.0 - FLATTENING);
+    let (n2, n3) = (n * n, n * n * n);
+    Kruger {
+        radius: SEMI_MAJOR_AXIS_M / (1.0 + n) * (1.0 + n2 / 4.0 + n2 * n2 / 64.0),
+        alpha: [
+            n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0,
+            13.0 * n2 / 48.0 - 3.0 * n3 / 5.0,
+            61.0 * n3 / 240.0,
+        ],
+        beta: [
+            n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0,
+            n2 / 48.0 + n3 / 15.0,
+            17.0 * n3 / 480.0,
+        ],
+        delta: [
+            2.0 * n - 2.0 * n2 / 3.0 - 2.0 * n3,
+            7.0 * n2 / 3.0 - 8.0 * n3 / 5.0,
+            56.0 * n3 / 15.0,
+        ],
+    }
+}
+
+/// Easting and northing (without the southern false northing) in `zone`.
+fn project(lat: f64, lon: f64, zone: u8) -> (f64, f64) {
+    let k = kruger();
+    let n = FLATTENING / (2.0 - FLATTENING);
+    let e = 2.0 * n.sqrt() / (1.0 + n);
+    let phi = lat.to_radians();
+    let dlon = (lon - central_meridian(zone)).to_radians();
+    let t = (phi.sin().atanh() - e * (e * phi.sin()).atanh()).sinh();
+    let xi = (t / dlon.cos()).atan();
+    let eta = (dlon.sin() / (1.0 + t * t).sqrt()).atanh();
+    let (mut x, mut y) = (eta, xi);
+    for (j, alpha) in k.alpha.iter().enumerate() {
+        let m = 2.0 * (j + 1) as f64;
+        x += alpha * (m * xi).cos() * (m * eta).sinh();
+        y += alpha * (m * xi).sin() * (m * eta).cosh();
+    }
+    (
+        FALSE_EASTING_M + UTM_SCALE * k.radius * x,
+        UTM_SCALE * k.radius * y,
+    )
+}
+
+fn unproject(easting: f64, northing: f64, zone: u8) -> (f64, f64) {
+    let k = kruger();
+    let xi = northing / (UTM_SCALE * k.radius);
+    let eta = (easting - FALSE_EASTING_M) / (UTM_SCALE * k.radius);
+    let (mut xi_p, mut eta_p) = (xi, eta);
+    for (j, beta) in k.beta.iter().enumerate() {
+        let m = 2.0 * (j + 1) as f64;
+        xi_p -= beta * (m * xi).sin() * (m * eta).cosh();
+        eta_p -= beta * (m * xi).cos() * (m * eta).sinh();
+    }
+    let chi = (xi_p.sin() / eta_p.cosh()).asin();
+    let mut phi = chi;
+    for (j, delta) in k.delta.iter().enumerate() {
+        phi += delta * (2.0 * (j + 1) as f64 * chi).sin();
+    }
+    let dlon = (eta_p.sinh() / xi_p.cos()).atan();
+    (phi.to_degrees(), central_meridian(zone) + dlon.to_degrees())
+}
+
+/// One axis as `60°10.2060'N` or `60°10'12.36"N`. Rounds in whole output
+/// units first, so 59.9999 seconds never shows as `60.00"`.
+fn sexagesimal(value: f64, positive: char, negative: char, format: CoordinateFormat) -> String {
+    let hemisphere = if value < 0.0 { negative } else { positive };
+    if format == CoordinateFormat::DegreesMinutes {
+        // 1/10000 minute
+        let units = (value.abs() * 600_000.0).round() as u64;
+        let minutes = (units % 600_000) as f64 / 10_000.0;
+        format!("{}°{minutes:07.4}'{hemisphere}", units / 600_000)
+    } else {
+        // 1/100 second
+        let units = (value.abs() * 360_000.0).round() as u64;
+        let minutes = units % 360_000 / 6_000;
+        let seconds = (units % 6_000) as f64 / 100.0;
+        format!(
+            "{}°{minutes:02}'{seconds:05.2}\"{hemisphere}",
+            units / 360_000
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Hemisphere(char),
+}
+
+/// Decimal degrees, degrees-minutes or degrees-minutes-seconds, one
+/// latitude and one longitude.
+fn parse_degrees(text: &str) -> Result<(f64, f64), CoordinateError> {
+    let not_degrees = || invalid(format!("not a coordinate: {text}"));
+    let mut tokens = Vec::new();
+    let mut number = String::new();
+    let flush = |number: &mut String, tokens: &mut Vec<Token>| -> Result<(), CoordinateError> {
+        if !number.is_empty() {
+            tokens.push(Token::Number(number.parse().map_err(|_| not_degrees())?));
+            number.clear();
+        }
+        Ok(())
+    };
+    for c in text.chars() {
+        match c.to_ascii_uppercase() {
+            '0'..='9' | '.' => number.push(c),
+            '-' | '+' if number.is_empty() => number.push(c),
+            c @ ('N' | 'S' | 'E' | 'W') => {
+                flush(&mut number, &mut tokens)?;
+                tokens.push(Token::Hemisphere(c));
+            }
+            ' ' | '\t' | ',' | ';' | '°' | '\'' | '"' | '′' | '″' => {
+                flush(&mut number, &mut tokens)?;
+            }
+            _ => return Err(not_degrees()),
+        }
+    }
+    flush(&mut number, &mut tokens)?;
+
+    let hemispheres = tokens
+        .iter()
+        .filter(|t| matches!(t, Token::Hemisphere(_)))
+        .count();
+    let (first, second) = match hemispheres {
+        0 if tokens.len().is_multiple_of(2) => tokens.split_at(tokens.len() / 2),
+        // Letters either all follow or all precede their numbers
+        2 if matches!(tokens.last(), Some(Token::Hemisphere(_))) => {
+            let split = tokens
+                .iter()
+                .position(|t| matches!(t, Token::Hemisphere(_)))
+                .ok_or_else(not_degrees)?;
+            tokens.split_at(split + 1)
+        }
+        2 if matches!(tokens.first(), Some(Token::Hemisphere(_))) => {
+            let split = tokens
+                .iter()
+                .skip(1)
+                .position(|t| matches!(t, Token::Hemisphere(_)))
+                .ok_or_else(not_degrees)?;
+            tokens.split_at(split + 1)
+        }
+        _ => return Err(not_degrees()),
+    };
+    let (a, a_hemisphere) = axis(first).ok_or_else(not_degrees)?;
+    let (b, b_hemisphere) = axis(second).ok_or_else(not_degrees)?;
+    let is_lon = |h: Option<char>| matches!(h, Some('E' | 'W'));
+    let (lat, lon) = match (a_hemisphere, b_hemisphere) {
+        (a_h, b_h) if is_lon(a_h) && !is_lon(b_h) => (b, a),
+        (a_h, b_h) if (!is_lon(a_h) && is_lon(b_h)) || (a_h, b_h) == (None, None) => (a, b),
+        _ => return Err(not_degrees()),
+    };
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(invalid(format!("{lat}, {lon} is out of range")));
+    }
+    Ok((lat, lon))
+}
+
+/// Degrees from up to three numbers and an optional hemisphere letter.
+fn axis(tokens: &[Token]) -> Option<(f64, Option<char>)> {
+    let mut numbers = Vec::new();
+    let mut hemisphere = None;
+    for token in tokens {
+        match token {
+            Token::Number(n) => numbers.push(*n),
+            Token::Hemisphere(h) => hemisphere = Some(*h),
+        }
+    }
+    let (degrees, minutes, seconds) = match numbers.as_slice() {
+        [d] => (*d, 0.0, 0.0),
+        [d, m] => (*d, *m, 0.0),
+        [d, m, s] => (*d, *m, *s),
+        _ => return None,
+    };
+    if !(0.0..60.0).contains(&minutes) || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    let negative = degrees.is_sign_negative() || matches!(hemisphere, Some('S' | 'W'));
+    let value = degrees.abs() + minutes / 60.0 + seconds / 3600.0;
+    Some((if negative { -value } else { value }, hemisphere))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Both positions within `tolerance_m` of each other.
+    fn assert_close(actual: (f64, f64), expected: (f64, f64), tolerance_m: f64) {
+        let north_m = (actual.0 - expected.0) * 111_320.0;
+        let east_m = (actual.1 - expected.1) * 111_320.0 * expected.0.to_radians().cos();
+        assert!(
+            north_m.hypot(east_m) < tolerance_m,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn utm_matches_reference_points() {
+        let origin = Utm::from_lat_lon(0.0, 0.0).unwrap();
+        assert_eq!((origin.zone, origin.band), (31, 'N'));
+        assert!((origin.easting_m - 166_021.443).abs() < 0.01, "{origin:?}");
+        assert!(origin.northing_m.abs() < 0.01);
+
+        // CN Tower, Toronto
+        let tower = Utm::from_lat_lon(43.642567, -79.387139).unwrap();
+        assert_eq!((tower.zone, tower.band), (17, 'T'));
+        assert!((tower.easting_m - 630_084.0).abs() < 1.5, "{tower:?}");
+        assert!((tower.northing_m - 4_833_438.0).abs() < 1.5, "{tower:?}");
+
+        let south = Utm::from_lat_lon(-33.8568, 151.2153).unwrap();
+        assert_eq!((south.zone, south.band), (56, 'H'));
+        assert!(!south.is_north());
+        assert_close(south.to_lat_lon(), (-33.8568, 151.2153), 0.001);
+    }
+
+    #[test]
+    fn utm_zone_exceptions_and_coverage() {
+        assert_eq!(Utm::from_lat_lon(60.4, 5.3).unwrap().zone, 32);
+        assert_eq!(Utm::from_lat_lon(78.2, 15.6).unwrap().zone, 33);
+        assert_eq!(Utm::from_lat_lon(78.2, 15.6).unwrap().band, 'X');
+        assert!(matches!(
+            Utm::from_lat_lon(85.0, 0.0),
+            Err(CoordinateError(_))
+        ));
+    }
+
+    #[test]
+    fn utm_and_mgrs_round_trip() {
+        for &(lat, lon) in &[
+            (60.1701, 24.9376),
+            (-33.8568, 151.2153),
+            (0.0001, -0.0001),
+            (-79.9, 120.0),
+            (83.9, -30.0),
+            (37.2431, -115.7930),
+            (56.5, 3.5),
+        ] {
+            let utm = Utm::from_lat_lon(lat, lon).unwrap();
+            assert_close(utm.to_lat_lon(), (lat, lon), 0.001);
+            assert_close(
+                Utm::parse(&utm.to_string()).unwrap().to_lat_lon(),
+                (lat, lon),
+                1.0,
+            );
+
+            let mgrs = to_mgrs(lat, lon, 5).unwrap();
+            // Truncated to the metre, so within about 1.5 m
+            assert_close(from_mgrs(&mgrs).unwrap(), (lat, lon), 1.5);
+        }
+    }
+
+    #[test]
+    fn mgrs_reference_and_precision() {
+        assert_eq!(to_mgrs(0.0, 0.0, 5).unwrap(), "31N AA 66021 00000");
+        assert_eq!(to_mgrs(0.0, 0.0, 2).unwrap(), "31N AA 66 00");
+        assert_close(from_mgrs("31naa6602100000").unwrap(), (0.0, 0.0), 1.0);
+        assert!(to_mgrs(0.0, 0.0, 6).is_err());
+        assert!(from_mgrs("31N AA 660210000").is_err());
+        assert!(from_mgrs("31N IA 66021 00000").is_err());
+    }
+
+    #[test]
+    fn sexagesimal_formats_round_without_overflow() {
+        assert_eq!(
+            format_coordinate(60.170100, 24.937600, CoordinateFormat::DegreesMinutes).unwrap(),
+            "60°10.2060'N 24°56.2560'E"
+        );
+        assert_eq!(
+            format_coordinate(-33.8568, -70.0, CoordinateFormat::DegreesMinutesSeconds).unwrap(),
+            "33°51'24.48\"S 70°00'00.00\"W"
+        );
+        // 59.9999999 seconds rounds up into the next minute
+        let almost = 10.0 + 59.999_999_9 / 3600.0;
+        assert_eq!(
+            format_coordinate(almost, 0.0, CoordinateFormat::DegreesMinutesSeconds).unwrap(),
+            "10°01'00.00\"N 0°00'00.00\"E"
+        );
+    }
+
+    #[test]
+    fn parses_every_format() {
+        let expected = (60.1701, 24.9376);
+        for format in [
+            CoordinateFormat::DecimalDegrees,
+            CoordinateFormat::DegreesMinutes,
+            CoordinateFormat::DegreesMinutesSeconds,
+            CoordinateFormat::Utm,
+            CoordinateFormat::Mgrs,
+        ] {
+            let text = format_coordinate(expected.0, expected.1, format).unwrap();
+            assert_close(parse_coordinate(&text).unwrap(), expected, 1.5);
+        }
+        for text in [
+            "60.1701 24.9376",
+            "N 60 10.206 E 24 56.256",
+            "24°56'15.36\"E, 60°10'12.36\"N",
+            "35 V 385568mE 6672142mN",
+        ] {
+            assert_close(parse_coordinate(text).unwrap(), expected, 1.5);
+        }
+        assert_close(
+            parse_coordinate("-33.8568, -70").unwrap(),
+            (-33.8568, -70.0),
+            0.001,
+        );
+        for text in ["", "60.1", "60 70 N", "91 0", "60°75'N 24E", "hello"] {
+            assert!(parse_coordinate(text).is_err(), "{text}");
+        }
+    }
+}
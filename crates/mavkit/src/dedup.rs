@@ -0,0 +1,119 @@
+//! Dropping copies of a message that arrives over two routes.
+//!
+//! When a router forwards a vehicle's stream that also reaches us directly,
+//! or two radios are fused onto one link, every packet shows up twice.
+//! A copy has the sender's system id, component id, sequence number and
+//! message id of a packet seen moments before; `Deduplicator` drops those
+//! so telemetry doesn't jitter and sequence-based loss counts stay right.
+//!
+//! Sequence numbers wrap every 256 packets per component, so the window has
+//! to be shorter than it takes a component to send that many.
+
+use mavlink::ardupilotmega::MavMessage;
+use mavlink::{MavHeader, Message};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Sender system id, component id, sequence and message id.
+type PacketKey = (u8, u8, u8, u32);
+
+#[derive(Debug, Clone)]
+pub(crate) struct Deduplicator {
+    window: Duration,
+    /// When each packet in the window was first seen.
+    seen: HashMap<PacketKey, Instant>,
+    /// The same packets, oldest first, for expiry.
+    order: VecDeque<(PacketKey, Instant)>,
+}
+
+impl Deduplicator {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// False if the same packet was already seen within the window.
+    pub(crate) fn accepts(
+        &mut self,
+        header: &MavHeader,
+        message: &MavMessage,
+        now: Instant,
+    ) -> bool {
+        self.expire(now);
+        let key = (
+            header.system_id,
+            header.component_id,
+            header.sequence,
+            message.message_id(),
+        );
+        if self.seen.contains_key(&key) {
+            return false;
+        }
+        self.seen.insert(key, now);
+        self.order.push_back((key, now));
+        true
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some(&(key, seen_at)) = self.order.front() {
+            if now.saturating_duration_since(seen_at) <= self.window {
+                break;
+            }
+            self.order.pop_front();
+            self.seen.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mavlink::ardupilotmega as common;
+
+    fn packet(sequence: u8) -> (MavHeader, MavMessage) {
+        let header = MavHeader {
+            system_id: 1,
+            component_id: 1,
+            sequence,
+        };
+        (
+            header,
+            MavMessage::HEARTBEAT(common::HEARTBEAT_DATA::default()),
+        )
+    }
+
+    #[test]
+    fn drops_copies_within_the_window() {
+        let mut dedup = Deduplicator::new(Duration::from_millis(200));
+        let start = Instant::now();
+        let (header, message) = packet(7);
+        assert!(dedup.accepts(&header, &message, start));
+        assert!(!dedup.accepts(&header, &message, start + Duration::from_millis(50)));
+
+        let (next, _) = packet(8);
+        assert!(dedup.accepts(&next, &message, start + Duration::from_millis(60)));
+        let other_component = MavHeader {
+            component_id: 154,
+            ..header
+        };
+        assert!(dedup.accepts(
+            &other_component,
+            &message,
+            start + Duration::from_millis(60)
+        ));
+    }
+
+    #[test]
+    fn sequence_may_repeat_after_the_window() {
+        let mut dedup = Deduplicator::new(Duration::from_millis(200));
+        let start = Instant::now();
+        let (header, message) = packet(7);
+        assert!(dedup.accepts(&header, &message, start));
+        // Wrapped around 256 packets later
+        assert!(dedup.accepts(&header, &message, start + Duration::from_millis(500)));
+        assert_eq!(dedup.order.len(), 1);
+    }
+}
@@ -1,9 +1,25 @@
+#[cfg(feature = "mission")]
+use crate::airspace::AirspaceError;
+use crate::coords::CoordinateError;
+#[cfg(feature = "ardupilot")]
+use crate::modes::ModeHazard;
+#[cfg(feature = "mission")]
+use crate::weather::WeatherError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum VehicleError {
     #[error("connection failed: {0}")]
     ConnectionFailed(String),
+    /// Another program (or a connection that has not finished closing) holds
+    /// the local address.
+    #[error("address already in use: {0}")]
+    AddressInUse(String),
     #[error("vehicle disconnected")]
     Disconnected,
+    /// Messages were dropped before this waiter read them, so the reply it
+    /// is waiting for may have been among them.
+    #[error("link fell behind and dropped {skipped} messages")]
+    LinkLagged { skipped: u64 },
     #[error("operation timed out")]
     Timeout,
     #[error("operation cancelled")]
@@ -14,10 +30,90 @@ pub enum VehicleError {
     IdentityUnknown,
     #[error("mode '{0}' not available for this vehicle")]
     ModeNotAvailable(String),
+    /// The mode change looks like a mistake in the vehicle's current state;
+    /// `Vehicle::set_mode_unchecked` switches anyway.
+    #[cfg(feature = "ardupilot")]
+    #[error("mode change to {mode} refused: {hazard}")]
+    ModeRefused { mode: String, hazard: ModeHazard },
+    /// Disarming in flight stops the motors; `disarm(true)` does it anyway.
+    #[error("disarm refused: vehicle is airborne")]
+    DisarmRefused,
+    #[error("vehicle is commanded by another GCS (sysid {system_id})")]
+    NotInControl { system_id: u8 },
+    /// Return-to-me needs an operator position from `update_gcs_position`
+    /// that is newer than `stale_after_ms`.
+    #[error("no recent operator position")]
+    NoGcsPosition,
+    #[error("goto proposal expired or unknown")]
+    GotoProposalExpired,
+    #[error("nudge refused: {0}")]
+    NudgeRefused(String),
+    #[error("goto refused: {0}")]
+    TerrainConflict(String),
+    #[error("command '{0}' not supported by this vehicle")]
+    CommandNotSupported(String),
+    #[error("invalid MAVLink message: {0}")]
+    InvalidMessage(String),
     #[error("mission transfer failed: [{code}] {message}")]
     MissionTransfer { code: String, message: String },
+    #[error("vehicle is flying the active mission; set allow_inflight_update to replace it")]
+    MissionInFlight,
     #[error("mission validation failed: {0}")]
     MissionValidation(String),
+    #[error("sealed plan: {0}")]
+    SealedPlan(String),
+    #[error("unknown airframe preset '{0}'")]
+    UnknownPreset(String),
+    #[error("parameter {0} is unknown; download the parameters first")]
+    ParamUnknown(String),
+    #[error("parameter {name} reads back {actual} after writing {requested}")]
+    ParamMismatch {
+        name: String,
+        requested: f32,
+        actual: f32,
+    },
+    #[error("parameter transaction refused: {0}")]
+    ParamTransaction(String),
+    /// A write of a parameter transaction failed; the ones written before
+    /// it were set back, except those in `not_restored`.
+    #[error("parameter transaction stopped at {name}: {reason}")]
+    ParamTransactionRolledBack {
+        name: String,
+        reason: String,
+        not_restored: Vec<String>,
+    },
+    #[error("RC calibration failed: {0}")]
+    RcCalibration(String),
+    #[error("invalid failsafe configuration: {0}")]
+    InvalidFailsafe(String),
+    #[error("invalid Remote ID operator data: {0}")]
+    InvalidRemoteId(String),
+    #[error("sensor setup: {0}")]
+    SensorSetup(String),
+    #[error("invalid firmware file: {0}")]
+    InvalidFirmware(String),
+    #[cfg(feature = "mission")]
+    #[error(transparent)]
+    Airspace(#[from] AirspaceError),
+    #[error(transparent)]
+    Coordinate(#[from] CoordinateError),
+    #[cfg(feature = "mission")]
+    #[error(transparent)]
+    Weather(#[from] WeatherError),
+    #[error("firmware flash failed: {0}")]
+    Flash(String),
+    #[error("SiK radio: {0}")]
+    Radio(String),
+    #[error("pose output: {0}")]
+    PoseOutput(String),
+    #[error("invalid telemetry smoothing: {0}")]
+    InvalidSmoothing(String),
+    /// DO_FENCE_ENABLE was accepted but no FENCE_STATUS followed, so the
+    /// fence may not be checked.
+    #[error("fence enable accepted but no FENCE_STATUS received")]
+    FenceNotEnabled,
+    #[error("SITL harness: {0}")]
+    SitlHarness(String),
     #[error("MAVLink I/O: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+/// Default ESC over-temperature alert threshold.
+pub const DEFAULT_ESC_MAX_TEMPERATURE_C: f32 = 90.0;
+
+/// Per-motor ESC telemetry from ESC_STATUS / ESC_INFO, or ArduPilot's
+/// ESC_TELEMETRY_x_TO_y.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EscTelemetry {
+    /// Zero-based motor index.
+    pub index: u8,
+    pub rpm: Option<i32>,
+    pub voltage_v: Option<f32>,
+    pub current_a: Option<f32>,
+    pub temperature_c: Option<f32>,
+    /// Temperature is above the configured alert threshold.
+    pub over_temperature: bool,
+}
+
+fn entry(all: &mut Vec<EscTelemetry>, index: u8) -> &mut EscTelemetry {
+    let pos = match all.iter().position(|e| e.index == index) {
+        Some(pos) => pos,
+        None => {
+            all.push(EscTelemetry {
+                index,
+                ..EscTelemetry::default()
+            });
+            all.sort_by_key(|e| e.index);
+            all.iter().position(|e| e.index == index).unwrap()
+        }
+    };
+    &mut all[pos]
+}
+
+/// Merge an ESC_STATUS block of four ESCs starting at `first_index`.
+/// ESCs reporting all zeros are treated as absent.
+pub(crate) fn merge_esc_status(
+    all: &mut Vec<EscTelemetry>,
+    first_index: u8,
+    rpm: &[i32; 4],
+    voltage: &[f32; 4],
+    current: &[f32; 4],
+) {
+    for i in 0..4 {
+        if rpm[i] == 0 && voltage[i] == 0.0 && current[i] == 0.0 {
+            continue;
+        }
+        let esc = entry(all, first_index.saturating_add(i as u8));
+        esc.rpm = Some(rpm[i]);
+        esc.voltage_v = Some(voltage[i]);
+        esc.current_a = Some(current[i]);
+    }
+}
+
+/// Merge the temperatures (cdegC) of an ESC_INFO block of four ESCs starting
+/// at `first_index`. Only the first `count - first_index` entries are used.
+pub(crate) fn merge_esc_temperatures(
+    all: &mut Vec<EscTelemetry>,
+    first_index: u8,
+    count: u8,
+    temperature_cdeg: &[i16; 4],
+    max_temperature_c: f32,
+) {
+    let present = count.saturating_sub(first_index).min(4) as usize;
+    for (i, &cdeg) in temperature_cdeg.iter().enumerate().take(present) {
+        let esc = entry(all, first_index.saturating_add(i as u8));
+        let celsius = cdeg as f32 / 100.0;
+        esc.temperature_c = Some(celsius);
+        esc.over_temperature = celsius > max_temperature_c;
+    }
+}
+
+/// Merge an ArduPilot ESC_TELEMETRY_x_TO_y block of four ESCs starting at
+/// `first_index`: temperature in degC, voltage in cV, current in cA.
+/// ESCs reporting all zeros are treated as absent.
+pub(crate) fn merge_esc_telemetry(
+    all: &mut Vec<EscTelemetry>,
+    first_index: u8,
+    temperature_c: &[u8; 4],
+    voltage_cv: &[u16; 4],
+    current_ca: &[u16; 4],
+    rpm: &[u16; 4],
+    max_temperature_c: f32,
+) {
+    for i in 0..4 {
+        if temperature_c[i] == 0 && voltage_cv[i] == 0 && current_ca[i] == 0 && rpm[i] == 0 {
+            continue;
+        }
+        let esc = entry(all, first_index.saturating_add(i as u8));
+        let celsius = temperature_c[i] as f32;
+        esc.rpm = Some(rpm[i] as i32);
+        esc.voltage_v = Some(voltage_cv[i] as f32 / 100.0);
+        esc.current_a = Some(current_ca[i] as f32 / 100.0);
+        esc.temperature_c = Some(celsius);
+        esc.over_temperature = celsius > max_temperature_c;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_blocks_merge_by_index() {
+        let mut all = Vec::new();
+        merge_esc_status(
+            &mut all,
+            4,
+            &[1000, 1100, 0, 0],
+            &[16.0, 16.1, 0.0, 0.0],
+            &[3.0, 3.5, 0.0, 0.0],
+        );
+        merge_esc_status(&mut all, 0, &[900, 910, 920, 930], &[16.0; 4], &[2.0; 4]);
+        let indices: Vec<u8> = all.iter().map(|e| e.index).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(all[5].rpm, Some(1100));
+        assert_eq!(all[5].current_a, Some(3.5));
+    }
+
+    #[test]
+    fn temperature_alert_uses_threshold() {
+        let mut all = Vec::new();
+        merge_esc_temperatures(&mut all, 0, 2, &[4500, 9550, 0, 0], 90.0);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].temperature_c, Some(45.0));
+        assert!(!all[0].over_temperature);
+        assert!(all[1].over_temperature);
+
+        merge_esc_temperatures(&mut all, 0, 2, &[4500, 8000, 0, 0], 90.0);
+        assert!(!all[1].over_temperature);
+    }
+
+    #[test]
+    fn ardupilot_telemetry_blocks_decode_units() {
+        let mut all = Vec::new();
+        merge_esc_telemetry(
+            &mut all,
+            0,
+            &[45, 95, 0, 0],
+            &[1610, 1605, 0, 0],
+            &[350, 420, 0, 0],
+            &[9000, 9100, 0, 0],
+            90.0,
+        );
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].rpm, Some(9000));
+        assert_eq!(all[0].voltage_v, Some(16.1));
+        assert_eq!(all[0].current_a, Some(3.5));
+        assert_eq!(all[0].temperature_c, Some(45.0));
+        assert!(!all[0].over_temperature);
+        assert!(all[1].over_temperature);
+    }
+
+    #[test]
+    fn ardupilot_telemetry_blocks_merge_by_index() {
+        let mut all = Vec::new();
+        for first_index in [8, 4, 0] {
+            merge_esc_telemetry(
+                &mut all,
+                first_index,
+                &[40; 4],
+                &[1600; 4],
+                &[200; 4],
+                &[5000; 4],
+                90.0,
+            );
+        }
+        let indices: Vec<u8> = all.iter().map(|e| e.index).collect();
+        assert_eq!(indices, (0..12).collect::<Vec<u8>>());
+        assert_eq!(all[11].current_a, Some(2.0));
+    }
+}
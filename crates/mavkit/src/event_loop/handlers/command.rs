@@ -0,0 +1,480 @@
+//! COMMAND_LONG and COMMAND_INT, answered by COMMAND_ACK: arming, mode
+//! changes and the generic commands of the public API, plus guided goto.
+
+use super::{get_target, send_message, HandlerContext, ProtocolHandler};
+use crate::command::Command;
+use crate::config::VehicleConfig;
+use crate::error::VehicleError;
+use crate::event_loop::{is_gcs_heartbeat, VehicleTarget};
+use crate::link::Link;
+use mavlink::ardupilotmega::{self as common, MavCmd};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+const MAGIC_FORCE_ARM_VALUE: f32 = 2989.0;
+const MAGIC_FORCE_DISARM_VALUE: f32 = 21196.0;
+
+pub(super) struct CommandProtocol;
+
+impl ProtocolHandler for CommandProtocol {
+    async fn handle(&self, cmd: Command, ctx: &mut HandlerContext<'_>) -> Option<Command> {
+        let HandlerContext {
+            connection,
+            vehicle_target,
+            config,
+            cancel,
+            ..
+        } = ctx;
+        match cmd {
+            Command::Arm { force, reply } => {
+                let result =
+                    handle_arm_disarm(true, force, connection, vehicle_target, config, cancel)
+                        .await;
+                let _ = reply.send(result);
+            }
+            Command::Disarm { force, reply } => {
+                let result =
+                    handle_arm_disarm(false, force, connection, vehicle_target, config, cancel)
+                        .await;
+                let _ = reply.send(result);
+            }
+            Command::SetMode { custom_mode, reply } => {
+                let result =
+                    handle_set_mode(custom_mode, connection, vehicle_target, config, cancel).await;
+                let _ = reply.send(result);
+            }
+            Command::CommandLong {
+                command,
+                params,
+                reply,
+            } => {
+                let result = handle_command_long(
+                    command,
+                    params,
+                    connection,
+                    vehicle_target,
+                    config,
+                    cancel,
+                )
+                .await;
+                let _ = reply.send(result);
+            }
+            Command::CommandInt {
+                command,
+                frame,
+                params,
+                x,
+                y,
+                z,
+                reply,
+            } => {
+                let result = handle_command_int(
+                    command,
+                    frame,
+                    params,
+                    x,
+                    y,
+                    z,
+                    connection,
+                    vehicle_target,
+                    config,
+                    cancel,
+                )
+                .await;
+                let _ = reply.send(result);
+            }
+            Command::GuidedGoto {
+                lat_e7,
+                lon_e7,
+                alt_m,
+                reply,
+            } => {
+                let result =
+                    handle_guided_goto(lat_e7, lon_e7, alt_m, connection, vehicle_target, config)
+                        .await;
+                let _ = reply.send(result);
+            }
+            cmd => return Some(cmd),
+        }
+        None
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Arm / Disarm
+// ---------------------------------------------------------------------------
+
+async fn handle_arm_disarm(
+    arm: bool,
+    force: bool,
+    connection: &mut Link,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    let target = get_target(vehicle_target)?;
+    let param1 = if arm { 1.0 } else { 0.0 };
+    let param2 = if force {
+        if arm {
+            MAGIC_FORCE_ARM_VALUE
+        } else {
+            MAGIC_FORCE_DISARM_VALUE
+        }
+    } else {
+        0.0
+    };
+
+    send_command_long_ack(
+        MavCmd::MAV_CMD_COMPONENT_ARM_DISARM,
+        [param1, param2, 0.0, 0.0, 0.0, 0.0, 0.0],
+        target,
+        connection,
+        config,
+        cancel,
+    )
+    .await
+}
+
+async fn send_command_long_ack(
+    command: MavCmd,
+    params: [f32; 7],
+    target: VehicleTarget,
+    connection: &mut Link,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    let message = |confirmation| {
+        common::MavMessage::COMMAND_LONG(common::COMMAND_LONG_DATA {
+            target_system: target.system_id,
+            target_component: target.component_id,
+            command,
+            confirmation,
+            param1: params[0],
+            param2: params[1],
+            param3: params[2],
+            param4: params[3],
+            param5: params[4],
+            param6: params[5],
+            param7: params[6],
+        })
+    };
+    send_command_ack(command, message, connection, config, cancel).await
+}
+
+/// Send the COMMAND_LONG or COMMAND_INT carrying `command` that `message`
+/// builds until the vehicle acknowledges it or the retries run out.
+/// `message` gets the attempt number, for COMMAND_LONG's `confirmation`, so
+/// the vehicle can tell a retry from a new command.
+///
+/// The first final ACK settles the command, whichever copy it answers; the
+/// ACKs of the other copies come after and are ignored, as are ACKs to
+/// other ground stations. IN_PROGRESS restarts the wait for the final one.
+/// Only the ACK matters here; the state updater applies every message,
+/// this one included, from its own handle, so telemetry keeps flowing while
+/// a command retries.
+async fn send_command_ack(
+    command: MavCmd,
+    message: impl Fn(u8) -> common::MavMessage,
+    connection: &mut Link,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    let retry_policy = &config.retry_policy;
+    for attempt in 0..=retry_policy.max_retries {
+        send_message(connection, config, message(attempt)).await?;
+
+        let timeout = Duration::from_millis(retry_policy.request_timeout_ms);
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => return Err(VehicleError::Cancelled),
+                _ = &mut deadline => break, // retry
+                result = connection.recv() => {
+                    let received = result?;
+                    let msg = &received.1;
+                    let common::MavMessage::COMMAND_ACK(ack) = msg else {
+                        continue;
+                    };
+                    // MAVLink 1 ACKs carry no target
+                    let to_us =
+                        ack.target_system == 0 || ack.target_system == config.gcs_system_id;
+                    if ack.command != command || !to_us {
+                        continue;
+                    }
+                    match ack.result {
+                        common::MavResult::MAV_RESULT_ACCEPTED => return Ok(()),
+                        common::MavResult::MAV_RESULT_IN_PROGRESS => {
+                            deadline.as_mut().reset(tokio::time::Instant::now() + timeout);
+                        }
+                        result => {
+                            return Err(VehicleError::CommandRejected {
+                                command: format!("{command:?}"),
+                                result: format!("{result:?}"),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Err(VehicleError::Timeout)
+}
+
+// ---------------------------------------------------------------------------
+// Set mode
+// ---------------------------------------------------------------------------
+
+async fn handle_set_mode(
+    custom_mode: u32,
+    connection: &mut Link,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    let target = get_target(vehicle_target)?;
+
+    // Try COMMAND_LONG(DO_SET_MODE) first
+    let do_set_mode_result = send_command_long_ack(
+        MavCmd::MAV_CMD_DO_SET_MODE,
+        [1.0, custom_mode as f32, 0.0, 0.0, 0.0, 0.0, 0.0],
+        target,
+        connection,
+        config,
+        cancel,
+    )
+    .await;
+
+    if do_set_mode_result.is_ok() {
+        return Ok(());
+    }
+
+    // Fallback: wait for confirming heartbeat
+    let timeout = Duration::from_secs(2);
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Err(VehicleError::Cancelled),
+            _ = &mut deadline => {
+                return Err(VehicleError::CommandRejected {
+                    command: format!("DO_SET_MODE({custom_mode})"),
+                    result: "no confirming HEARTBEAT".to_string(),
+                });
+            }
+            result = connection.recv() => {
+                let received = result?;
+                let msg = &received.1;
+                if let common::MavMessage::HEARTBEAT(hb) = msg {
+                    if !is_gcs_heartbeat(msg) && hb.custom_mode == custom_mode {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Generic COMMAND_LONG (public API)
+// ---------------------------------------------------------------------------
+
+async fn handle_command_long(
+    command: MavCmd,
+    params: [f32; 7],
+    connection: &mut Link,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    let target = get_target(vehicle_target)?;
+    send_command_long_ack(command, params, target, connection, config, cancel).await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_command_int(
+    command: MavCmd,
+    frame: common::MavFrame,
+    params: [f32; 4],
+    x: i32,
+    y: i32,
+    z: f32,
+    connection: &mut Link,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    let target = get_target(vehicle_target)?;
+    let message = common::MavMessage::COMMAND_INT(common::COMMAND_INT_DATA {
+        target_system: target.system_id,
+        target_component: target.component_id,
+        frame,
+        command,
+        current: 0,
+        autocontinue: 0,
+        param1: params[0],
+        param2: params[1],
+        param3: params[2],
+        param4: params[3],
+        x,
+        y,
+        z,
+    });
+    // COMMAND_INT has no confirmation field; every retry is the same message
+    send_command_ack(command, |_| message.clone(), connection, config, cancel).await
+}
+
+// ---------------------------------------------------------------------------
+// Guided goto
+// ---------------------------------------------------------------------------
+
+async fn handle_guided_goto(
+    lat_e7: i32,
+    lon_e7: i32,
+    alt_m: f32,
+    connection: &Link,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+) -> Result<(), VehicleError> {
+    let target = get_target(vehicle_target)?;
+    let type_mask = common::PositionTargetTypemask::from_bits_truncate(0x07F8);
+
+    send_message(
+        connection,
+        config,
+        common::MavMessage::SET_POSITION_TARGET_GLOBAL_INT(
+            common::SET_POSITION_TARGET_GLOBAL_INT_DATA {
+                time_boot_ms: 0,
+                target_system: target.system_id,
+                target_component: target.component_id,
+                coordinate_frame: common::MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+                type_mask,
+                lat_int: lat_e7,
+                lon_int: lon_e7,
+                alt: alt_m,
+                vx: 0.0,
+                vy: 0.0,
+                vz: 0.0,
+                afx: 0.0,
+                afy: 0.0,
+                afz: 0.0,
+                yaw: 0.0,
+                yaw_rate: 0.0,
+            },
+        ),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mavlink::MavHeader;
+    use std::sync::Arc;
+
+    const GCS: u8 = 255;
+    const OTHER_GCS: u8 = 200;
+
+    fn target() -> VehicleTarget {
+        VehicleTarget {
+            system_id: 1,
+            component_id: 1,
+            autopilot: common::MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA,
+            vehicle_type: common::MavType::MAV_TYPE_QUADROTOR,
+        }
+    }
+
+    /// Send DO_SET_MODE to a vehicle that answers the copy with each
+    /// confirmation by `answer`'s ACKs, as (target system, result). Returns
+    /// the outcome and the confirmation of every copy sent.
+    async fn set_mode_against(
+        answer: fn(u8) -> Vec<(u8, common::MavResult)>,
+    ) -> (Result<(), VehicleError>, Vec<u8>) {
+        let (mut link, mut sent, inbound) = Link::loopback();
+        let vehicle = tokio::spawn(async move {
+            let mut confirmations = Vec::new();
+            while let Some((_, _, message)) = sent.recv().await {
+                let common::MavMessage::COMMAND_LONG(data) = message else {
+                    continue;
+                };
+                confirmations.push(data.confirmation);
+                for (target_system, result) in answer(data.confirmation) {
+                    let ack = common::MavMessage::COMMAND_ACK(common::COMMAND_ACK_DATA {
+                        command: data.command,
+                        result,
+                        target_system,
+                        ..Default::default()
+                    });
+                    let _ = inbound.send(Ok(Arc::new((MavHeader::default(), ack))));
+                }
+            }
+            confirmations
+        });
+        let mut config = VehicleConfig {
+            gcs_system_id: GCS,
+            ..VehicleConfig::default()
+        };
+        config.retry_policy.request_timeout_ms = 20;
+        config.retry_policy.max_retries = 2;
+        let outcome = send_command_long_ack(
+            MavCmd::MAV_CMD_DO_SET_MODE,
+            [1.0, 4.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            target(),
+            &mut link,
+            &config,
+            &CancellationToken::new(),
+        )
+        .await;
+        drop(link);
+        (outcome, vehicle.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn acks_end_the_command_and_silence_retries_it() {
+        let (outcome, sent) =
+            set_mode_against(|_| vec![(GCS, common::MavResult::MAV_RESULT_ACCEPTED)]).await;
+        assert!(outcome.is_ok());
+        assert_eq!(sent, [0]);
+
+        let (outcome, sent) =
+            set_mode_against(|_| vec![(GCS, common::MavResult::MAV_RESULT_DENIED)]).await;
+        assert!(matches!(outcome, Err(VehicleError::CommandRejected { .. })));
+        assert_eq!(sent, [0]);
+
+        // Each retry counts up the confirmation
+        let (outcome, sent) = set_mode_against(|_| Vec::new()).await;
+        assert!(matches!(outcome, Err(VehicleError::Timeout)));
+        assert_eq!(sent, [0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn only_the_final_ack_to_us_counts() {
+        // Another GCS's rejection and an interim IN_PROGRESS don't settle it
+        let (outcome, sent) = set_mode_against(|_| {
+            vec![
+                (OTHER_GCS, common::MavResult::MAV_RESULT_DENIED),
+                (GCS, common::MavResult::MAV_RESULT_IN_PROGRESS),
+            ]
+        })
+        .await;
+        assert!(matches!(outcome, Err(VehicleError::Timeout)));
+        assert_eq!(sent, [0, 1, 2]);
+
+        // A MAVLink 1 vehicle names no target, and may ACK a copy twice
+        let (outcome, sent) = set_mode_against(|confirmation| match confirmation {
+            0 => Vec::new(),
+            _ => vec![
+                (0, common::MavResult::MAV_RESULT_ACCEPTED),
+                (0, common::MavResult::MAV_RESULT_ACCEPTED),
+            ],
+        })
+        .await;
+        assert!(outcome.is_ok());
+        assert_eq!(sent, [0, 1]);
+    }
+}
@@ -0,0 +1,63 @@
+//! Our own side of GCS coordination: the identity we send under and the
+//! control of the vehicle shared with other ground stations. These commands
+//! only change local state; nothing goes out on the link.
+
+use super::{HandlerContext, ProtocolHandler};
+use crate::command::Command;
+use crate::control::{claim_control, release_control, ControlOwner};
+use crate::error::VehicleError;
+use crate::gcs::refresh_gcs_conflicts;
+
+pub(super) struct ControlProtocol;
+
+impl ProtocolHandler for ControlProtocol {
+    async fn handle(&self, cmd: Command, ctx: &mut HandlerContext<'_>) -> Option<Command> {
+        let HandlerContext {
+            writers, config, ..
+        } = ctx;
+        match cmd {
+            Command::SetGcsIdentity {
+                system_id,
+                component_id,
+                reply,
+            } => {
+                config.gcs_system_id = system_id;
+                config.gcs_component_id = component_id;
+                writers
+                    .gcs_peers
+                    .send_if_modified(|peers| refresh_gcs_conflicts(peers, system_id));
+                let _ = reply.send(Ok(()));
+            }
+            Command::RequestControl { force, reply } => {
+                let mut result = Ok(());
+                writers.control.send_if_modified(|control| {
+                    let before = control.clone();
+                    if let Err(ControlOwner::Peer { system_id, .. }) = claim_control(control, force)
+                    {
+                        result = Err(VehicleError::NotInControl { system_id });
+                    }
+                    *control != before
+                });
+                let _ = reply.send(result);
+            }
+            Command::ReleaseControl { reply } => {
+                writers.control.send_if_modified(|control| {
+                    let before = control.owner;
+                    release_control(control);
+                    control.owner != before
+                });
+                let _ = reply.send(Ok(()));
+            }
+            Command::SetControlOverride { enabled, reply } => {
+                writers.control.send_if_modified(|control| {
+                    let changed = control.override_enabled != enabled;
+                    control.override_enabled = enabled;
+                    changed
+                });
+                let _ = reply.send(Ok(()));
+            }
+            cmd => return Some(cmd),
+        }
+        None
+    }
+}
@@ -0,0 +1,878 @@
+//! Mission protocol: plan upload, download and clear, setting the current
+//! item, and following the plans others change on the vehicle.
+
+use super::{get_target, send_message, HandlerContext, OperationToken, ProtocolHandler};
+use crate::command::Command;
+use crate::config::VehicleConfig;
+use crate::error::VehicleError;
+use crate::event_loop::VehicleTarget;
+use crate::link::Link;
+use crate::mission::{
+    self, deg_to_e7, IssueSeverity, MissionFrame, MissionItem, MissionPlan, MissionTransferMachine,
+    MissionType, PlanSyncMarker, TransferPhase,
+};
+use crate::state::{MissionRunState, MissionState, StateWriters};
+use mavlink::ardupilotmega::{self as common, MavCmd};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+pub(super) struct MissionProtocol;
+
+impl ProtocolHandler for MissionProtocol {
+    async fn handle(&self, cmd: Command, ctx: &mut HandlerContext<'_>) -> Option<Command> {
+        let HandlerContext {
+            connection,
+            writers,
+            vehicle_target,
+            config,
+            cancel,
+        } = ctx;
+        match cmd {
+            Command::MissionUpload {
+                plan,
+                cancel: abort,
+                reply,
+            } => {
+                let op = OperationToken::new(cancel, &abort);
+                let result = handle_mission_upload(
+                    plan,
+                    connection,
+                    writers,
+                    vehicle_target,
+                    config,
+                    &op.token,
+                )
+                .await;
+                let _ = reply.send(result);
+            }
+            Command::MissionDownload {
+                mission_type,
+                cancel: abort,
+                reply,
+            } => {
+                let op = OperationToken::new(cancel, &abort);
+                let result = handle_mission_download(
+                    mission_type,
+                    connection,
+                    writers,
+                    vehicle_target,
+                    config,
+                    &op.token,
+                )
+                .await;
+                let _ = reply.send(result);
+            }
+            Command::MissionClear {
+                mission_type,
+                reply,
+            } => {
+                let result = handle_mission_clear(
+                    mission_type,
+                    connection,
+                    writers,
+                    vehicle_target,
+                    config,
+                    cancel,
+                )
+                .await;
+                let _ = reply.send(result);
+            }
+            Command::MissionSetCurrent { seq, reply } => {
+                let result =
+                    handle_mission_set_current(seq, connection, vehicle_target, config, cancel)
+                        .await;
+                let _ = reply.send(result);
+            }
+            cmd => return Some(cmd),
+        }
+        None
+    }
+
+    fn observe(
+        &self,
+        message: &common::MavMessage,
+        from_vehicle: bool,
+        writers: &StateWriters,
+        config: &VehicleConfig,
+    ) -> bool {
+        match message {
+            common::MavMessage::MISSION_CURRENT(data) => {
+                let previous = writers.mission_state.borrow().clone();
+                let _ = writers.mission_state.send(MissionState {
+                    current_seq: data.seq,
+                    total_items: data.total,
+                    mission_id: data.mission_id,
+                    fence_id: data.fence_id,
+                    rally_points_id: data.rally_points_id,
+                    mission_state: MissionRunState::from_mav(data.mission_state),
+                });
+                // Only ids that just changed count; a report queued from before
+                // our own transfer finished still carries the old id
+                let changed = |id: u32, previous: u32| if id != previous { id } else { 0 };
+                let vehicle_ids = [
+                    (
+                        MissionType::Mission,
+                        changed(data.mission_id, previous.mission_id),
+                    ),
+                    (
+                        MissionType::Fence,
+                        changed(data.fence_id, previous.fence_id),
+                    ),
+                    (
+                        MissionType::Rally,
+                        changed(data.rally_points_id, previous.rally_points_id),
+                    ),
+                ];
+                let markers = *writers.plan_markers.borrow();
+                writers
+                    .current_vehicle_plan
+                    .send_if_modified(|plans| plans.invalidate_changed(&markers, vehicle_ids));
+                for (mission_type, opaque_id) in vehicle_ids {
+                    observe_plan_id(writers, mission_type, opaque_id);
+                }
+            }
+            // Another GCS finished changing a plan on the vehicle
+            common::MavMessage::MISSION_ACK(data)
+                if from_vehicle
+                    && data.target_system != config.gcs_system_id
+                    && data.mavtype == common::MavMissionResult::MAV_MISSION_ACCEPTED =>
+            {
+                if let Some(mission_type) = from_mav_mission_type(data.mission_type) {
+                    writers
+                        .current_vehicle_plan
+                        .send_if_modified(|plans| plans.invalidate(mission_type));
+                    observe_plan_id(writers, mission_type, data.opaque_id);
+                }
+            }
+            // Another GCS is downloading a plan, which shows its current id
+            common::MavMessage::MISSION_COUNT(data)
+                if from_vehicle && data.target_system != config.gcs_system_id =>
+            {
+                if let Some(mission_type) = from_mav_mission_type(data.mission_type) {
+                    observe_plan_id(writers, mission_type, data.opaque_id);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Mission operations
+// ---------------------------------------------------------------------------
+
+fn to_mav_mission_type(mission_type: MissionType) -> common::MavMissionType {
+    match mission_type {
+        MissionType::Mission => common::MavMissionType::MAV_MISSION_TYPE_MISSION,
+        MissionType::Fence => common::MavMissionType::MAV_MISSION_TYPE_FENCE,
+        MissionType::Rally => common::MavMissionType::MAV_MISSION_TYPE_RALLY,
+    }
+}
+
+fn from_mav_mission_type(mission_type: common::MavMissionType) -> Option<MissionType> {
+    match mission_type {
+        common::MavMissionType::MAV_MISSION_TYPE_MISSION => Some(MissionType::Mission),
+        common::MavMissionType::MAV_MISSION_TYPE_FENCE => Some(MissionType::Fence),
+        common::MavMissionType::MAV_MISSION_TYPE_RALLY => Some(MissionType::Rally),
+        _ => None,
+    }
+}
+
+fn to_mav_frame(frame: MissionFrame) -> common::MavFrame {
+    match frame {
+        MissionFrame::Mission => common::MavFrame::MAV_FRAME_MISSION,
+        MissionFrame::GlobalInt => common::MavFrame::MAV_FRAME_GLOBAL,
+        MissionFrame::GlobalRelativeAltInt => common::MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+        MissionFrame::GlobalTerrainAltInt => common::MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT,
+        MissionFrame::LocalNed => common::MavFrame::MAV_FRAME_LOCAL_NED,
+        MissionFrame::Other => common::MavFrame::MAV_FRAME_MISSION,
+    }
+}
+
+#[allow(deprecated)]
+fn from_mav_frame(frame: common::MavFrame) -> MissionFrame {
+    match frame {
+        common::MavFrame::MAV_FRAME_MISSION => MissionFrame::Mission,
+        common::MavFrame::MAV_FRAME_GLOBAL | common::MavFrame::MAV_FRAME_GLOBAL_INT => {
+            MissionFrame::GlobalInt
+        }
+        common::MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT
+        | common::MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT_INT => MissionFrame::GlobalRelativeAltInt,
+        common::MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT
+        | common::MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT_INT => MissionFrame::GlobalTerrainAltInt,
+        common::MavFrame::MAV_FRAME_LOCAL_NED => MissionFrame::LocalNed,
+        _ => MissionFrame::Other,
+    }
+}
+
+fn from_mission_item_int(data: &common::MISSION_ITEM_INT_DATA) -> MissionItem {
+    MissionItem {
+        seq: data.seq,
+        command: data.command as u16,
+        frame: from_mav_frame(data.frame),
+        current: data.current > 0,
+        autocontinue: data.autocontinue > 0,
+        param1: data.param1,
+        param2: data.param2,
+        param3: data.param3,
+        param4: data.param4,
+        x: data.x,
+        y: data.y,
+        z: data.z,
+        label: None,
+        notes: None,
+        downloaded_as_float: false,
+    }
+}
+
+#[allow(deprecated)]
+fn from_mission_item_float(data: &common::MISSION_ITEM_DATA) -> MissionItem {
+    let is_global = matches!(
+        data.frame,
+        common::MavFrame::MAV_FRAME_GLOBAL
+            | common::MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT
+            | common::MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT
+            | common::MavFrame::MAV_FRAME_GLOBAL_INT
+            | common::MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT_INT
+            | common::MavFrame::MAV_FRAME_GLOBAL_TERRAIN_ALT_INT
+    );
+
+    MissionItem {
+        seq: data.seq,
+        command: data.command as u16,
+        frame: from_mav_frame(data.frame),
+        current: data.current > 0,
+        autocontinue: data.autocontinue > 0,
+        param1: data.param1,
+        param2: data.param2,
+        param3: data.param3,
+        param4: data.param4,
+        x: if is_global {
+            deg_to_e7(data.x as f64)
+        } else {
+            data.x as i32
+        },
+        y: if is_global {
+            deg_to_e7(data.y as f64)
+        } else {
+            data.y as i32
+        },
+        z: data.z,
+        label: None,
+        notes: None,
+        downloaded_as_float: is_global,
+    }
+}
+
+fn mission_type_matches(received: common::MavMissionType, expected: MissionType) -> bool {
+    let expected_mav = to_mav_mission_type(expected);
+    if expected == MissionType::Mission {
+        received == expected_mav || received == common::MavMissionType::MAV_MISSION_TYPE_MISSION
+    } else {
+        received == expected_mav
+    }
+}
+
+fn send_requested_item_msg(
+    wire_items: &[MissionItem],
+    target: VehicleTarget,
+    mission_type: MissionType,
+    seq: u16,
+) -> Result<common::MavMessage, VehicleError> {
+    let item = wire_items
+        .get(seq as usize)
+        .ok_or_else(|| VehicleError::MissionTransfer {
+            code: "item_out_of_range".to_string(),
+            message: format!("requested item {seq} out of range"),
+        })?;
+
+    let command = num_traits::FromPrimitive::from_u16(item.command).ok_or_else(|| {
+        VehicleError::MissionTransfer {
+            code: "unsupported_command".to_string(),
+            message: format!("unsupported MAV_CMD value {}", item.command),
+        }
+    })?;
+    let frame = to_mav_frame(item.frame);
+
+    Ok(common::MavMessage::MISSION_ITEM_INT(
+        common::MISSION_ITEM_INT_DATA {
+            param1: item.param1,
+            param2: item.param2,
+            param3: item.param3,
+            param4: item.param4,
+            x: item.x,
+            y: item.y,
+            z: item.z,
+            seq: item.seq,
+            command,
+            target_system: target.system_id,
+            target_component: target.component_id,
+            frame,
+            current: 0,
+            autocontinue: u8::from(item.autocontinue),
+            mission_type: to_mav_mission_type(mission_type),
+        },
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Mission Upload
+// ---------------------------------------------------------------------------
+
+#[allow(deprecated)]
+async fn handle_mission_upload(
+    plan: MissionPlan,
+    connection: &mut Link,
+    writers: &StateWriters,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    // Validate
+    let issues = mission::validate_plan(&plan);
+    if let Some(issue) = issues.iter().find(|i| i.severity == IssueSeverity::Error) {
+        return Err(VehicleError::MissionValidation(format!(
+            "{}: {}",
+            issue.code, issue.message
+        )));
+    }
+
+    let wire_items = mission::items_for_wire_upload(&plan);
+    let target = get_target(vehicle_target)?;
+    let mav_mission_type = to_mav_mission_type(plan.mission_type);
+
+    let mut machine = MissionTransferMachine::new_upload(
+        plan.mission_type,
+        wire_items.len() as u16,
+        config.retry_policy,
+    );
+    let _ = writers.mission_progress.send(Some(machine.progress()));
+
+    let count_msg = common::MavMessage::MISSION_COUNT(common::MISSION_COUNT_DATA {
+        count: wire_items.len() as u16,
+        target_system: target.system_id,
+        target_component: target.component_id,
+        mission_type: mav_mission_type,
+        opaque_id: 0,
+    });
+
+    send_message(connection, config, count_msg.clone()).await?;
+
+    // If empty plan, just wait for ACK
+    if wire_items.is_empty() {
+        let opaque_id = wait_for_mission_ack(
+            &mut machine,
+            plan.mission_type,
+            connection,
+            writers,
+            config,
+            cancel,
+            || count_msg.clone(),
+        )
+        .await?;
+        record_vehicle_plan(writers, plan, opaque_id);
+        return Ok(());
+    }
+
+    let mut acknowledged = HashSet::<u16>::new();
+
+    // Wait for MISSION_REQUEST_INT / MISSION_REQUEST messages
+    while machine.progress().phase != TransferPhase::AwaitAck {
+        let timeout = Duration::from_millis(machine.timeout_ms());
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
+        let msg = loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    machine.cancel();
+                    let _ = writers.mission_progress.send(Some(machine.progress()));
+                    return Err(VehicleError::Cancelled);
+                }
+                _ = &mut deadline => {
+                    if let Some(err) = machine.on_timeout() {
+                        let _ = writers.mission_progress.send(Some(machine.progress()));
+                        return Err(VehicleError::MissionTransfer {
+                            code: err.code,
+                            message: err.message,
+                        });
+                    }
+                    let _ = writers.mission_progress.send(Some(machine.progress()));
+                    send_message(connection, config, count_msg.clone()).await?;
+                    break None;
+                }
+                result = connection.recv() => {
+                    let received = result?;
+                    let msg = &received.1;
+
+                    match msg {
+                        common::MavMessage::MISSION_REQUEST_INT(data) if data.mission_type == mav_mission_type => {
+                            break Some(("int", data.seq));
+                        }
+                        common::MavMessage::MISSION_REQUEST(data) if data.mission_type == mav_mission_type => {
+                            break Some(("req", data.seq));
+                        }
+                        common::MavMessage::MISSION_ACK(data) if data.mission_type == mav_mission_type => {
+                            if data.mavtype == common::MavMissionResult::MAV_MISSION_ACCEPTED {
+                                machine.on_ack_success();
+                                let _ = writers.mission_progress.send(Some(machine.progress()));
+                                record_vehicle_plan(writers, plan, data.opaque_id);
+                                return Ok(());
+                            }
+                            return Err(VehicleError::MissionTransfer {
+                                code: "transfer.ack_error".to_string(),
+                                message: format!("MISSION_ACK error: {:?}", data.mavtype),
+                            });
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+            }
+        };
+
+        if let Some((_kind, seq)) = msg {
+            let item_msg = send_requested_item_msg(&wire_items, target, plan.mission_type, seq)?;
+            send_message(connection, config, item_msg).await?;
+            if acknowledged.insert(seq) {
+                machine.on_item_transferred();
+                let _ = writers.mission_progress.send(Some(machine.progress()));
+            }
+        }
+    }
+
+    // Await final ACK
+    let opaque_id = wait_for_mission_ack(
+        &mut machine,
+        plan.mission_type,
+        connection,
+        writers,
+        config,
+        cancel,
+        || count_msg.clone(),
+    )
+    .await?;
+    record_vehicle_plan(writers, plan, opaque_id);
+    Ok(())
+}
+
+async fn wait_for_mission_ack<F>(
+    machine: &mut MissionTransferMachine,
+    mission_type: MissionType,
+    connection: &mut Link,
+    writers: &StateWriters,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+    retry_msg: F,
+) -> Result<u32, VehicleError>
+where
+    F: Fn() -> common::MavMessage,
+{
+    let mav_mission_type = to_mav_mission_type(mission_type);
+    loop {
+        let timeout = Duration::from_millis(machine.timeout_ms());
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                machine.cancel();
+                let _ = writers.mission_progress.send(Some(machine.progress()));
+                return Err(VehicleError::Cancelled);
+            }
+            _ = &mut deadline => {
+                if let Some(err) = machine.on_timeout() {
+                    let _ = writers.mission_progress.send(Some(machine.progress()));
+                    return Err(VehicleError::MissionTransfer {
+                        code: err.code,
+                        message: err.message,
+                    });
+                }
+                let _ = writers.mission_progress.send(Some(machine.progress()));
+                send_message(connection, config, retry_msg()).await?;
+            }
+            result = connection.recv() => {
+                let received = result?;
+                let msg = &received.1;
+
+                if let common::MavMessage::MISSION_ACK(data) = msg {
+                    if data.mission_type != mav_mission_type {
+                        continue;
+                    }
+                    if data.mavtype == common::MavMissionResult::MAV_MISSION_ACCEPTED {
+                        machine.on_ack_success();
+                        let _ = writers.mission_progress.send(Some(machine.progress()));
+                        return Ok(data.opaque_id);
+                    }
+                    return Err(VehicleError::MissionTransfer {
+                        code: "transfer.ack_error".to_string(),
+                        message: format!("MISSION_ACK error: {:?}", data.mavtype),
+                    });
+                }
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Mission Download
+// ---------------------------------------------------------------------------
+
+#[allow(deprecated)]
+async fn handle_mission_download(
+    mission_type: MissionType,
+    connection: &mut Link,
+    writers: &StateWriters,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<MissionPlan, VehicleError> {
+    let target = get_target(vehicle_target)?;
+    let mav_mission_type = to_mav_mission_type(mission_type);
+    let mut machine = MissionTransferMachine::new_download(mission_type, config.retry_policy);
+    let _ = writers.mission_progress.send(Some(machine.progress()));
+
+    let request_list_msg =
+        common::MavMessage::MISSION_REQUEST_LIST(common::MISSION_REQUEST_LIST_DATA {
+            target_system: target.system_id,
+            target_component: target.component_id,
+            mission_type: mav_mission_type,
+        });
+    send_message(connection, config, request_list_msg.clone()).await?;
+
+    // Wait for MISSION_COUNT
+    let (count, opaque_id) = loop {
+        let timeout = Duration::from_millis(machine.timeout_ms());
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                machine.cancel();
+                let _ = writers.mission_progress.send(Some(machine.progress()));
+                return Err(VehicleError::Cancelled);
+            }
+            _ = &mut deadline => {
+                if let Some(err) = machine.on_timeout() {
+                    let _ = writers.mission_progress.send(Some(machine.progress()));
+                    return Err(VehicleError::MissionTransfer {
+                        code: err.code,
+                        message: err.message,
+                    });
+                }
+                let _ = writers.mission_progress.send(Some(machine.progress()));
+                send_message(connection, config, request_list_msg.clone()).await?;
+            }
+            result = connection.recv() => {
+                let received = result?;
+                let msg = &received.1;
+
+                if let common::MavMessage::MISSION_COUNT(data) = msg {
+                    if mission_type_matches(data.mission_type, mission_type) {
+                        break (data.count, data.opaque_id);
+                    }
+                }
+            }
+        }
+    };
+
+    machine.set_download_total(count);
+    let _ = writers.mission_progress.send(Some(machine.progress()));
+
+    // Request each item
+    let mut items = Vec::with_capacity(count as usize);
+    for seq in 0..count {
+        let mut use_int_request = true;
+
+        let request_int_msg =
+            common::MavMessage::MISSION_REQUEST_INT(common::MISSION_REQUEST_INT_DATA {
+                seq,
+                target_system: target.system_id,
+                target_component: target.component_id,
+                mission_type: mav_mission_type,
+            });
+        let request_float_msg = common::MavMessage::MISSION_REQUEST(common::MISSION_REQUEST_DATA {
+            seq,
+            target_system: target.system_id,
+            target_component: target.component_id,
+            mission_type: mav_mission_type,
+        });
+
+        let make_request_msg = |use_int: bool| -> common::MavMessage {
+            if use_int {
+                request_int_msg.clone()
+            } else {
+                request_float_msg.clone()
+            }
+        };
+
+        send_message(connection, config, make_request_msg(use_int_request)).await?;
+
+        let item = loop {
+            let timeout = Duration::from_millis(machine.timeout_ms());
+            let deadline = tokio::time::sleep(timeout);
+            tokio::pin!(deadline);
+
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    machine.cancel();
+                    let _ = writers.mission_progress.send(Some(machine.progress()));
+                    return Err(VehicleError::Cancelled);
+                }
+                _ = &mut deadline => {
+                    if let Some(err) = machine.on_timeout() {
+                        let _ = writers.mission_progress.send(Some(machine.progress()));
+                        return Err(VehicleError::MissionTransfer {
+                            code: err.code,
+                            message: err.message,
+                        });
+                    }
+                    let _ = writers.mission_progress.send(Some(machine.progress()));
+                    if use_int_request {
+                        use_int_request = false;
+                    }
+                    send_message(connection, config, make_request_msg(use_int_request)).await?;
+                }
+                result = connection.recv() => {
+                    let received = result?;
+                    let msg = &received.1;
+
+                    match msg {
+                        common::MavMessage::MISSION_ITEM_INT(data)
+                            if data.seq == seq && mission_type_matches(data.mission_type, mission_type) =>
+                        {
+                            break from_mission_item_int(data);
+                        }
+                        common::MavMessage::MISSION_ITEM(data)
+                            if data.seq == seq && mission_type_matches(data.mission_type, mission_type) =>
+                        {
+                            break from_mission_item_float(data);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        };
+
+        items.push(item);
+        machine.on_item_transferred();
+        let _ = writers.mission_progress.send(Some(machine.progress()));
+    }
+
+    // Send ACK
+    let _ = send_message(
+        connection,
+        config,
+        common::MavMessage::MISSION_ACK(common::MISSION_ACK_DATA {
+            target_system: target.system_id,
+            target_component: target.component_id,
+            mavtype: common::MavMissionResult::MAV_MISSION_ACCEPTED,
+            mission_type: mav_mission_type,
+            opaque_id: 0,
+        }),
+    )
+    .await;
+
+    machine.on_ack_success();
+    let _ = writers.mission_progress.send(Some(machine.progress()));
+
+    let plan = mission::plan_from_wire_download(mission_type, items);
+    record_vehicle_plan(writers, plan.clone(), opaque_id);
+    Ok(plan)
+}
+
+/// Remember the plan the vehicle now holds, as `current_vehicle_plan` and
+/// for `MissionHandle::sync_status`.
+fn record_vehicle_plan(writers: &StateWriters, plan: MissionPlan, opaque_id: u32) {
+    let marker = PlanSyncMarker {
+        opaque_id,
+        checksum: mission::plan_checksum(&plan),
+    };
+    writers
+        .plan_markers
+        .send_modify(|markers| markers.set(plan.mission_type, marker));
+    writers
+        .plan_epochs
+        .send_modify(|epochs| epochs.record(plan.mission_type, opaque_id));
+    writers
+        .current_vehicle_plan
+        .send_modify(|plans| plans.set(plan));
+}
+
+/// Take in an opaque id the vehicle reported for `mission_type`, announcing
+/// it when it differs from the one last known.
+fn observe_plan_id(writers: &StateWriters, mission_type: MissionType, opaque_id: u32) {
+    let mut change = None;
+    writers
+        .plan_epochs
+        .send_modify(|epochs| change = epochs.observe(mission_type, opaque_id));
+    if let Some(change) = change {
+        debug!(
+            "{mission_type:?} changed on the vehicle: opaque id {} -> {}",
+            change.previous_id, change.opaque_id
+        );
+        let _ = writers.plan_changes.send(change);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Mission Clear
+// ---------------------------------------------------------------------------
+
+async fn handle_mission_clear(
+    mission_type: MissionType,
+    connection: &mut Link,
+    writers: &StateWriters,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    let target = get_target(vehicle_target)?;
+    let mav_mission_type = to_mav_mission_type(mission_type);
+
+    let mut machine = MissionTransferMachine::new_upload(mission_type, 0, config.retry_policy);
+    let _ = writers.mission_progress.send(Some(machine.progress()));
+
+    let clear_msg = common::MavMessage::MISSION_CLEAR_ALL(common::MISSION_CLEAR_ALL_DATA {
+        target_system: target.system_id,
+        target_component: target.component_id,
+        mission_type: mav_mission_type,
+    });
+
+    send_message(connection, config, clear_msg.clone()).await?;
+
+    let opaque_id = wait_for_mission_ack(
+        &mut machine,
+        mission_type,
+        connection,
+        writers,
+        config,
+        cancel,
+        || clear_msg.clone(),
+    )
+    .await?;
+    let empty = MissionPlan {
+        mission_type,
+        home: None,
+        items: Vec::new(),
+        metadata: Default::default(),
+    };
+    record_vehicle_plan(writers, empty, opaque_id);
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Mission Set Current
+// ---------------------------------------------------------------------------
+
+async fn handle_mission_set_current(
+    seq: u16,
+    connection: &mut Link,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(), VehicleError> {
+    let target = get_target(vehicle_target)?;
+    let retry_policy = &config.retry_policy;
+
+    for attempt in 0..=retry_policy.max_retries {
+        send_message(
+            connection,
+            config,
+            common::MavMessage::COMMAND_LONG(common::COMMAND_LONG_DATA {
+                target_system: target.system_id,
+                target_component: target.component_id,
+                command: MavCmd::MAV_CMD_DO_SET_MISSION_CURRENT,
+                confirmation: attempt,
+                param1: seq as f32,
+                param2: 0.0,
+                param3: 0.0,
+                param4: 0.0,
+                param5: 0.0,
+                param6: 0.0,
+                param7: 0.0,
+            }),
+        )
+        .await?;
+
+        let timeout = Duration::from_millis(retry_policy.request_timeout_ms);
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => return Err(VehicleError::Cancelled),
+                _ = &mut deadline => break, // retry outer loop
+                result = connection.recv() => {
+                    let received = result?;
+                    let msg = &received.1;
+
+                    match msg {
+                        common::MavMessage::COMMAND_ACK(data) => {
+                            if data.command == MavCmd::MAV_CMD_DO_SET_MISSION_CURRENT
+                                && data.result == common::MavResult::MAV_RESULT_ACCEPTED
+                            {
+                                return Ok(());
+                            }
+                        }
+                        common::MavMessage::MISSION_CURRENT(data) => {
+                            if data.seq == seq {
+                                return Ok(());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    Err(VehicleError::MissionTransfer {
+        code: "mission.set_current_timeout".to_string(),
+        message: "Did not receive confirmation for set-current command".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::create_channels;
+
+    #[test]
+    fn plans_changed_by_another_gcs_are_announced() {
+        let (writers, _channels) = create_channels();
+        let config = VehicleConfig::default();
+        let mut changes = writers.plan_changes.subscribe();
+        writers
+            .plan_epochs
+            .send_modify(|epochs| epochs.record(MissionType::Fence, 5));
+        let fence_ack = |target_system, opaque_id| {
+            common::MavMessage::MISSION_ACK(common::MISSION_ACK_DATA {
+                target_system,
+                mavtype: common::MavMissionResult::MAV_MISSION_ACCEPTED,
+                mission_type: common::MavMissionType::MAV_MISSION_TYPE_FENCE,
+                opaque_id,
+                ..Default::default()
+            })
+        };
+
+        // The ack of a transfer of ours is the transfer's business
+        let ours = fence_ack(config.gcs_system_id, 9);
+        assert!(!MissionProtocol.observe(&ours, true, &writers, &config));
+        assert!(changes.try_recv().is_err());
+
+        let theirs = fence_ack(config.gcs_system_id.wrapping_add(1), 7);
+        assert!(MissionProtocol.observe(&theirs, true, &writers, &config));
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.mission_type, MissionType::Fence);
+        assert_eq!((change.previous_id, change.opaque_id), (5, 7));
+    }
+}
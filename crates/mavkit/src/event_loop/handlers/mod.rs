@@ -0,0 +1,199 @@
+//! Command handling, one submodule per protocol.
+//!
+//! Each protocol is a `ProtocolHandler` that owns its commands: how their
+//! requests are built, which replies match and when to retry. `route` offers
+//! a command to each handler in turn, after the control check they share.
+//! Handlers may also take in the messages the state updater receives, for
+//! traffic of their protocol that isn't a reply to one of our requests,
+//! such as another GCS changing the mission.
+//!
+//! A handler only reaches the event loop through its `HandlerContext`, so
+//! it can be run against a loopback `Link` in tests.
+
+mod command;
+mod control;
+mod mission;
+mod params;
+mod raw;
+
+use crate::command::Command;
+use crate::config::VehicleConfig;
+use crate::control::ControlOwner;
+use crate::error::VehicleError;
+use crate::event_loop::VehicleTarget;
+use crate::link::Link;
+use crate::state::{AutopilotType, StateWriters, VehicleType};
+use command::CommandProtocol;
+use control::ControlProtocol;
+use mavlink::ardupilotmega as common;
+use mavlink::MavHeader;
+use mission::MissionProtocol;
+use params::ParamProtocol;
+use raw::RawProtocol;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// What a handler works with while it runs a command.
+pub(super) struct HandlerContext<'a> {
+    pub(super) connection: &'a mut Link,
+    pub(super) writers: &'a StateWriters,
+    pub(super) vehicle_target: &'a mut Option<VehicleTarget>,
+    pub(super) config: &'a mut VehicleConfig,
+    /// Fires when the event loop shuts down.
+    pub(super) cancel: &'a CancellationToken,
+}
+
+/// The commands and messages of one protocol.
+pub(super) trait ProtocolHandler {
+    /// Run `cmd` and reply to it if it belongs to this protocol; otherwise
+    /// hand it back for the next handler.
+    async fn handle(&self, cmd: Command, ctx: &mut HandlerContext<'_>) -> Option<Command>;
+
+    /// Take in a message the state updater received. True when it was
+    /// this protocol's to handle, so the updater skips it.
+    fn observe(
+        &self,
+        _message: &common::MavMessage,
+        _from_vehicle: bool,
+        _writers: &StateWriters,
+        _config: &VehicleConfig,
+    ) -> bool {
+        false
+    }
+}
+
+/// Offer `cmd` to each protocol in turn; `None` once one has taken it.
+async fn dispatch(cmd: Command, ctx: &mut HandlerContext<'_>) -> Option<Command> {
+    let cmd = CommandProtocol.handle(cmd, ctx).await?;
+    let cmd = MissionProtocol.handle(cmd, ctx).await?;
+    let cmd = ParamProtocol.handle(cmd, ctx).await?;
+    let cmd = RawProtocol.handle(cmd, ctx).await?;
+    ControlProtocol.handle(cmd, ctx).await
+}
+
+/// Run `cmd`, unless it would steer the vehicle while another GCS has
+/// control.
+pub(super) async fn route(cmd: Command, ctx: &mut HandlerContext<'_>) {
+    if cmd.commands_vehicle() && !is_recovery_mode_change(&cmd, ctx.vehicle_target) {
+        let control = ctx.writers.control.borrow().clone();
+        if let (false, ControlOwner::Peer { system_id, .. }) =
+            (control.may_command(), control.owner)
+        {
+            cmd.reject(VehicleError::NotInControl { system_id });
+            return;
+        }
+    }
+    // Shutdown and UpdateConfig are handled in the main loop
+    let _ = dispatch(cmd, ctx).await;
+}
+
+/// Let each protocol look at a received message; true when one took it.
+pub(super) fn observe(
+    message: &common::MavMessage,
+    from_vehicle: bool,
+    writers: &StateWriters,
+    config: &VehicleConfig,
+) -> bool {
+    CommandProtocol.observe(message, from_vehicle, writers, config)
+        || MissionProtocol.observe(message, from_vehicle, writers, config)
+        || ParamProtocol.observe(message, from_vehicle, writers, config)
+        || RawProtocol.observe(message, from_vehicle, writers, config)
+        || ControlProtocol.observe(message, from_vehicle, writers, config)
+}
+
+fn is_recovery_mode_change(cmd: &Command, vehicle_target: &Option<VehicleTarget>) -> bool {
+    let (Command::SetMode { custom_mode, .. }, Some(target)) = (cmd, vehicle_target) else {
+        return false;
+    };
+    crate::modes::is_recovery_mode(
+        AutopilotType::from_mav(target.autopilot),
+        VehicleType::from_mav(target.vehicle_type),
+        *custom_mode,
+    )
+}
+
+/// Cancellation for one long-running command: fires when the event loop
+/// shuts down or when the caller cancels its own token.
+pub(super) struct OperationToken {
+    pub(super) token: CancellationToken,
+    link: tokio::task::JoinHandle<()>,
+}
+
+impl OperationToken {
+    pub(super) fn new(shutdown: &CancellationToken, abort: &CancellationToken) -> Self {
+        let token = shutdown.child_token();
+        if abort.is_cancelled() {
+            token.cancel();
+        }
+        let link = tokio::spawn({
+            let (token, abort) = (token.clone(), abort.clone());
+            async move {
+                abort.cancelled().await;
+                token.cancel();
+            }
+        });
+        Self { token, link }
+    }
+}
+
+impl Drop for OperationToken {
+    fn drop(&mut self) {
+        self.link.abort();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Helpers: send message, wait for response
+// ---------------------------------------------------------------------------
+
+pub(super) async fn send_message(
+    connection: &Link,
+    config: &VehicleConfig,
+    message: common::MavMessage,
+) -> Result<(), VehicleError> {
+    connection
+        .send(
+            &MavHeader {
+                system_id: config.gcs_system_id,
+                component_id: config.gcs_component_id,
+                sequence: 0,
+            },
+            &message,
+        )
+        .await
+}
+
+/// Wait for a message matching `predicate`. Other messages are skipped here;
+/// the state updater sees them on its own handle.
+pub(super) async fn wait_for_response<F, T>(
+    connection: &mut Link,
+    cancel: &CancellationToken,
+    timeout: Duration,
+    mut predicate: F,
+) -> Result<T, VehicleError>
+where
+    F: FnMut(&MavHeader, &common::MavMessage) -> Option<T>,
+{
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Err(VehicleError::Cancelled),
+            _ = &mut deadline => return Err(VehicleError::Timeout),
+            result = connection.recv() => {
+                let received = result?;
+                let (header, msg) = &*received;
+                if let Some(val) = predicate(header, msg) {
+                    return Ok(val);
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn get_target(
+    vehicle_target: &Option<VehicleTarget>,
+) -> Result<VehicleTarget, VehicleError> {
+    vehicle_target.ok_or(VehicleError::IdentityUnknown)
+}
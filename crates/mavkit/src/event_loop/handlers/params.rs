@@ -0,0 +1,480 @@
+//! Parameter protocol: downloading every parameter, writing one and
+//! reading the hash that tells whether any changed.
+
+use super::{
+    get_target, send_message, wait_for_response, HandlerContext, OperationToken, ProtocolHandler,
+};
+use crate::command::Command;
+use crate::config::VehicleConfig;
+use crate::error::VehicleError;
+use crate::event_loop::VehicleTarget;
+use crate::link::Link;
+use crate::params::progress::ParamDownloadTracker;
+use crate::params::{Param, ParamStore, ParamTransferPhase, ParamType, ParamUpdate};
+use crate::state::StateWriters;
+use mavlink::ardupilotmega::{self as common, MavParamType};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Read by name, this returns a hash over all parameter values instead of
+/// a parameter (ArduPilot and PX4).
+const PARAM_HASH_CHECK: &str = "_HASH_CHECK";
+const PARAM_DOWNLOAD_MAX_RETRIES: u32 = 3;
+const PARAM_GAP_FILL_BATCH: usize = 10;
+
+pub(super) struct ParamProtocol;
+
+impl ProtocolHandler for ParamProtocol {
+    async fn handle(&self, cmd: Command, ctx: &mut HandlerContext<'_>) -> Option<Command> {
+        let HandlerContext {
+            connection,
+            writers,
+            vehicle_target,
+            config,
+            cancel,
+        } = ctx;
+        match cmd {
+            Command::ParamDownloadAll {
+                cancel: abort,
+                reply,
+            } => {
+                let op = OperationToken::new(cancel, &abort);
+                let result = handle_param_download_all(
+                    connection,
+                    writers,
+                    vehicle_target,
+                    config,
+                    &op.token,
+                )
+                .await;
+                let _ = reply.send(result);
+            }
+            Command::ParamWrite { name, value, reply } => {
+                let result = handle_param_write(
+                    &name,
+                    value,
+                    connection,
+                    writers,
+                    vehicle_target,
+                    config,
+                    cancel,
+                )
+                .await;
+                let _ = reply.send(result);
+            }
+            Command::ParamSeed { store, reply } => {
+                let _ = writers.param_store.send(Arc::new(store));
+                let _ = reply.send(Ok(()));
+            }
+            Command::ParamHashCheck { reply } => {
+                let result =
+                    handle_param_hash_check(connection, vehicle_target, config, cancel).await;
+                let _ = reply.send(result);
+            }
+            cmd => return Some(cmd),
+        }
+        None
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Parameter type helpers
+// ---------------------------------------------------------------------------
+
+fn from_mav_param_type(mav: MavParamType) -> ParamType {
+    match mav {
+        MavParamType::MAV_PARAM_TYPE_UINT8 => ParamType::Uint8,
+        MavParamType::MAV_PARAM_TYPE_INT8 => ParamType::Int8,
+        MavParamType::MAV_PARAM_TYPE_UINT16 => ParamType::Uint16,
+        MavParamType::MAV_PARAM_TYPE_INT16 => ParamType::Int16,
+        MavParamType::MAV_PARAM_TYPE_UINT32 => ParamType::Uint32,
+        MavParamType::MAV_PARAM_TYPE_INT32 => ParamType::Int32,
+        _ => ParamType::Real32,
+    }
+}
+
+/// PX4 carries integer parameters in the bits of the float (bytewise
+/// encoding) where ArduPilot converts them. The store holds real values, so
+/// PARAM_VALUE is decoded on receipt and PARAM_SET encoded on sending.
+fn is_bytewise(target: &VehicleTarget, param_type: ParamType) -> bool {
+    target.autopilot == common::MavAutopilot::MAV_AUTOPILOT_PX4 && param_type != ParamType::Real32
+}
+
+fn decode_param_value(target: &VehicleTarget, param_type: ParamType, wire: f32) -> f32 {
+    match param_type {
+        _ if !is_bytewise(target, param_type) => wire,
+        ParamType::Uint32 => wire.to_bits() as f32,
+        _ => wire.to_bits() as i32 as f32,
+    }
+}
+
+fn encode_param_value(target: &VehicleTarget, param_type: ParamType, value: f32) -> f32 {
+    match param_type {
+        _ if !is_bytewise(target, param_type) => value,
+        ParamType::Uint32 => f32::from_bits(value as u32),
+        _ => f32::from_bits(value as i32 as u32),
+    }
+}
+
+fn to_mav_param_type(pt: ParamType) -> MavParamType {
+    match pt {
+        ParamType::Uint8 => MavParamType::MAV_PARAM_TYPE_UINT8,
+        ParamType::Int8 => MavParamType::MAV_PARAM_TYPE_INT8,
+        ParamType::Uint16 => MavParamType::MAV_PARAM_TYPE_UINT16,
+        ParamType::Int16 => MavParamType::MAV_PARAM_TYPE_INT16,
+        ParamType::Uint32 => MavParamType::MAV_PARAM_TYPE_UINT32,
+        ParamType::Int32 => MavParamType::MAV_PARAM_TYPE_INT32,
+        ParamType::Real32 => MavParamType::MAV_PARAM_TYPE_REAL32,
+    }
+}
+
+fn param_id_to_string(param_id: &mavlink::types::CharArray<16>) -> String {
+    param_id.to_str().unwrap_or("").to_string()
+}
+
+fn string_to_param_id(name: &str) -> mavlink::types::CharArray<16> {
+    name.into()
+}
+
+// ---------------------------------------------------------------------------
+// Parameter Download All
+// ---------------------------------------------------------------------------
+
+async fn handle_param_download_all(
+    connection: &mut Link,
+    writers: &StateWriters,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<ParamStore, VehicleError> {
+    let target = get_target(vehicle_target)?;
+    if cancel.is_cancelled() {
+        return Err(VehicleError::Cancelled);
+    }
+
+    let mut tracker = ParamDownloadTracker::new(PARAM_DOWNLOAD_MAX_RETRIES, Instant::now());
+
+    // Reset progress
+    let _ = writers
+        .param_progress
+        .send(tracker.progress(ParamTransferPhase::Downloading, Instant::now()));
+
+    // Send PARAM_REQUEST_LIST
+    send_message(
+        connection,
+        config,
+        common::MavMessage::PARAM_REQUEST_LIST(common::PARAM_REQUEST_LIST_DATA {
+            target_system: target.system_id,
+            target_component: target.component_id,
+        }),
+    )
+    .await?;
+
+    let mut params: HashMap<String, Param> = HashMap::new();
+    let mut last_progress_update = 0u16;
+
+    loop {
+        let timeout = Duration::from_secs(2);
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
+        let mut got_new = false;
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    let _ = writers
+                        .param_progress
+                        .send(tracker.progress(ParamTransferPhase::Failed, Instant::now()));
+                    return Err(VehicleError::Cancelled);
+                }
+                _ = &mut deadline => break,
+                result = connection.recv() => {
+                    let received = match result {
+                        // Dropped values show up as gaps and are re-requested
+                        Err(VehicleError::LinkLagged { .. }) => continue,
+                        result => result?,
+                    };
+                    let msg = &received.1;
+
+                    if let common::MavMessage::PARAM_VALUE(data) = msg {
+                        let name = param_id_to_string(&data.param_id);
+                        if name.is_empty() {
+                            continue;
+                        }
+
+                        tracker.set_expected(data.param_count);
+
+                        if tracker.record(data.param_index) {
+                            got_new = true;
+                            let param_type = from_mav_param_type(data.param_type);
+                            let value = decode_param_value(&target, param_type, data.param_value);
+                            let _ = writers.param_updates.send(ParamUpdate {
+                                name: name.clone(),
+                                value,
+                                index: data.param_index,
+                            });
+                            params.insert(name.clone(), Param {
+                                name,
+                                value,
+                                param_type,
+                                index: data.param_index,
+                            });
+                        }
+
+                        // Update progress every 50 params, and on every gap-fill reply
+                        let received = tracker.received_count();
+                        if received - last_progress_update >= 50
+                            || tracker.is_complete()
+                            || tracker.missing_count() < 50
+                        {
+                            last_progress_update = received;
+                            let _ = writers
+                                .param_progress
+                                .send(tracker.progress(ParamTransferPhase::Downloading, Instant::now()));
+                        }
+
+                        // Reset deadline on new data
+                        deadline.as_mut().reset(tokio::time::Instant::now() + Duration::from_secs(2));
+                    }
+                }
+            }
+        }
+
+        // Timeout reached — check if we're done
+        if tracker.is_complete() {
+            break; // Done
+        }
+
+        if tracker.on_timeout(got_new) {
+            // Accept partial if we have more than 50% of expected
+            let received = tracker.received_count();
+            if let Some(expected) = tracker.expected().filter(|&e| received > e / 2) {
+                warn!(
+                    "param download: accepting partial {}/{} after {} retries",
+                    received, expected, PARAM_DOWNLOAD_MAX_RETRIES
+                );
+                break;
+            }
+            let _ = writers
+                .param_progress
+                .send(tracker.progress(ParamTransferPhase::Failed, Instant::now()));
+            return Err(VehicleError::Timeout);
+        }
+
+        // Request missing indices in batches so we don't flood the link
+        let batch = tracker.next_gap_batch(PARAM_GAP_FILL_BATCH);
+        for &idx in &batch {
+            send_message(
+                connection,
+                config,
+                common::MavMessage::PARAM_REQUEST_READ(common::PARAM_REQUEST_READ_DATA {
+                    param_index: idx as i16,
+                    target_system: target.system_id,
+                    target_component: target.component_id,
+                    param_id: string_to_param_id(""),
+                }),
+            )
+            .await?;
+        }
+        if !batch.is_empty() {
+            debug!(
+                "param download: requested {} missing params (retry {})",
+                batch.len(),
+                tracker.retries()
+            );
+            let _ = writers
+                .param_progress
+                .send(tracker.progress(ParamTransferPhase::Downloading, Instant::now()));
+        }
+    }
+
+    let store = ParamStore {
+        params,
+        expected_count: tracker.expected().unwrap_or(0),
+    };
+
+    let _ = writers.param_store.send(Arc::new(store.clone()));
+    let _ = writers
+        .param_progress
+        .send(tracker.progress(ParamTransferPhase::Completed, Instant::now()));
+
+    Ok(store)
+}
+
+/// The parameter count and `_HASH_CHECK` of the vehicle, which change
+/// whenever any parameter does. The hash travels as the bits of the float.
+async fn handle_param_hash_check(
+    connection: &mut Link,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<(u16, u32), VehicleError> {
+    let target = get_target(vehicle_target)?;
+    let timeout = Duration::from_millis(config.retry_policy.request_timeout_ms);
+
+    for _attempt in 0..=config.retry_policy.max_retries {
+        send_message(
+            connection,
+            config,
+            common::MavMessage::PARAM_REQUEST_READ(common::PARAM_REQUEST_READ_DATA {
+                param_index: -1,
+                target_system: target.system_id,
+                target_component: target.component_id,
+                param_id: string_to_param_id(PARAM_HASH_CHECK),
+            }),
+        )
+        .await?;
+
+        let result = wait_for_response(connection, cancel, timeout, |_, msg| match msg {
+            common::MavMessage::PARAM_VALUE(data)
+                if param_id_to_string(&data.param_id) == PARAM_HASH_CHECK =>
+            {
+                Some((data.param_count, data.param_value.to_bits()))
+            }
+            _ => None,
+        })
+        .await;
+        match result {
+            Err(VehicleError::Timeout) => continue,
+            result => return result,
+        }
+    }
+
+    Err(VehicleError::Timeout)
+}
+
+// ---------------------------------------------------------------------------
+// Parameter Write
+// ---------------------------------------------------------------------------
+
+async fn handle_param_write(
+    name: &str,
+    value: f32,
+    connection: &mut Link,
+    writers: &StateWriters,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<Param, VehicleError> {
+    let target = get_target(vehicle_target)?;
+
+    // Look up current param_type from store, or default to Real32
+    let param_type = {
+        let store = writers.param_store.borrow();
+        store
+            .params
+            .get(name)
+            .map(|p| p.param_type)
+            .unwrap_or(ParamType::Real32)
+    };
+
+    let wire_value = encode_param_value(&target, param_type, value);
+
+    let retry_policy = &config.retry_policy;
+
+    for _attempt in 0..=retry_policy.max_retries {
+        send_message(
+            connection,
+            config,
+            common::MavMessage::PARAM_SET(common::PARAM_SET_DATA {
+                param_value: wire_value,
+                target_system: target.system_id,
+                target_component: target.component_id,
+                param_id: string_to_param_id(name),
+                param_type: to_mav_param_type(param_type),
+            }),
+        )
+        .await?;
+
+        let timeout = Duration::from_millis(retry_policy.request_timeout_ms);
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => return Err(VehicleError::Cancelled),
+                _ = &mut deadline => break, // retry
+                result = connection.recv() => {
+                    let received = result?;
+                    let msg = &received.1;
+
+                    if let common::MavMessage::PARAM_VALUE(data) = msg {
+                        let received_name = param_id_to_string(&data.param_id);
+                        if received_name == name {
+                            let param_type = from_mav_param_type(data.param_type);
+                            let confirmed = Param {
+                                name: received_name.clone(),
+                                value: decode_param_value(&target, param_type, data.param_value),
+                                param_type,
+                                index: data.param_index,
+                            };
+
+                            let _ = writers.param_updates.send(ParamUpdate {
+                                name: received_name.clone(),
+                                value: confirmed.value,
+                                index: confirmed.index,
+                            });
+                            // Store subscribers only hear about writes that
+                            // changed the stored value
+                            writers.param_store.send_if_modified(|store| {
+                                if store.params.get(&received_name) == Some(&confirmed) {
+                                    return false;
+                                }
+                                let mut updated = ParamStore::clone(store);
+                                updated.params.insert(received_name, confirmed.clone());
+                                *store = Arc::new(updated);
+                                true
+                            });
+
+                            return Ok(confirmed);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Err(VehicleError::Timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(autopilot: common::MavAutopilot) -> VehicleTarget {
+        VehicleTarget {
+            system_id: 1,
+            component_id: 1,
+            autopilot,
+            vehicle_type: common::MavType::MAV_TYPE_QUADROTOR,
+        }
+    }
+
+    #[test]
+    fn px4_integers_round_trip_bytewise() {
+        let px4 = target(common::MavAutopilot::MAV_AUTOPILOT_PX4);
+        let wire = encode_param_value(&px4, ParamType::Int32, 4001.0);
+        assert_eq!(wire.to_bits(), 4001);
+        assert_eq!(decode_param_value(&px4, ParamType::Int32, wire), 4001.0);
+        assert_eq!(decode_param_value(&px4, ParamType::Real32, 0.25), 0.25);
+    }
+
+    #[test]
+    fn ardupilot_values_pass_through() {
+        let ardupilot = target(common::MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA);
+        assert_eq!(
+            encode_param_value(&ardupilot, ParamType::Int32, 4001.0),
+            4001.0
+        );
+        assert_eq!(
+            decode_param_value(&ardupilot, ParamType::Int32, 4001.0),
+            4001.0
+        );
+    }
+}
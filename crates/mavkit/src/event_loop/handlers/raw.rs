@@ -0,0 +1,126 @@
+//! Raw messages sent for the caller, and the message rate benchmark.
+
+use super::{
+    get_target, send_message, wait_for_response, HandlerContext, OperationToken, ProtocolHandler,
+};
+use crate::command::Command;
+use crate::config::VehicleConfig;
+use crate::error::VehicleError;
+use crate::event_loop::VehicleTarget;
+use crate::link::Link;
+use crate::rate_benchmark::{RateBenchmarkOptions, RateMeter, RateReport};
+use mavlink::ardupilotmega as common;
+use mavlink::Message;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+pub(super) struct RawProtocol;
+
+impl ProtocolHandler for RawProtocol {
+    async fn handle(&self, cmd: Command, ctx: &mut HandlerContext<'_>) -> Option<Command> {
+        let HandlerContext {
+            connection,
+            vehicle_target,
+            config,
+            cancel,
+            ..
+        } = ctx;
+        match cmd {
+            Command::SendRaw {
+                message,
+                response,
+                reply,
+            } => {
+                let result = handle_send_raw(
+                    message,
+                    response,
+                    connection,
+                    vehicle_target,
+                    config,
+                    cancel,
+                )
+                .await;
+                let _ = reply.send(result);
+            }
+            Command::RateBenchmark {
+                options,
+                cancel: abort,
+                reply,
+            } => {
+                // Only listens, so it needn't hold up other commands for the window
+                let op = OperationToken::new(cancel, &abort);
+                let link = connection.clone();
+                let target = get_target(vehicle_target);
+                tokio::spawn(async move {
+                    let result = match target {
+                        Ok(target) => run_rate_benchmark(link, target, &options, &op.token).await,
+                        Err(err) => Err(err),
+                    };
+                    let _ = reply.send(result);
+                });
+            }
+            cmd => return Some(cmd),
+        }
+        None
+    }
+}
+
+async fn handle_send_raw(
+    message: common::MavMessage,
+    response: Option<String>,
+    connection: &mut Link,
+    vehicle_target: &mut Option<VehicleTarget>,
+    config: &VehicleConfig,
+    cancel: &CancellationToken,
+) -> Result<Option<String>, VehicleError> {
+    send_message(connection, config, message).await?;
+    let Some(response) = response else {
+        return Ok(None);
+    };
+    let timeout = Duration::from_millis(config.retry_policy.request_timeout_ms);
+    let received = wait_for_response(connection, cancel, timeout, |_, msg| {
+        msg.message_name()
+            .eq_ignore_ascii_case(&response)
+            .then(|| msg.clone())
+    })
+    .await?;
+    crate::raw::raw_message_to_json(&received).map(Some)
+}
+
+/// Note when each of the autopilot's messages arrives until the window ends.
+async fn run_rate_benchmark(
+    mut connection: Link,
+    target: VehicleTarget,
+    options: &RateBenchmarkOptions,
+    cancel: &CancellationToken,
+) -> Result<RateReport, VehicleError> {
+    let mut meter = RateMeter::new(Instant::now());
+    let deadline = tokio::time::sleep(Duration::from_millis(options.window_ms));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Err(VehicleError::Cancelled),
+            _ = &mut deadline => break,
+            result = connection.recv() => {
+                let received = match result {
+                    Ok(received) => received,
+                    // Messages we fell behind on show up as gaps
+                    Err(VehicleError::LinkLagged { skipped }) => {
+                        warn!("rate benchmark fell behind, skipped {skipped} messages");
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                };
+                let (header, msg) = &*received;
+                let from_autopilot = header.system_id == target.system_id
+                    && header.component_id == target.component_id;
+                if from_autopilot {
+                    meter.record(msg.message_name(), Instant::now());
+                }
+            }
+        }
+    }
+    Ok(meter.report(Instant::now(), options))
+}
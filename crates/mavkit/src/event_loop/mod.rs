@@ -0,0 +1,1115 @@
+mod handlers;
+
+use crate::clock::{local_ns, BootWatch, VehicleClock};
+use crate::command::Command;
+use crate::config::VehicleConfig;
+use crate::control::{expire_peer_control, is_control_command, observe_peer_command};
+use crate::error::VehicleError;
+use crate::esc::{merge_esc_status, merge_esc_telemetry, merge_esc_temperatures};
+use crate::gcs::{observe_gcs_heartbeat, prune_gcs_peers};
+use crate::housekeeping::{Job, Schedule};
+use crate::link::{Link, SharedLink, Transport};
+use crate::mission;
+use crate::odometer::{FlightLog, Odometer};
+use crate::remote_id::{merge_remote_id, REMOTE_ID_PERIOD};
+use crate::state::{
+    AutopilotType, BatteryInfo, FirmwareVersion, GpsFixType, LandedState, LinkState, StateWriters,
+    SystemStatus, Telemetry, VehicleIdentity, VehicleState, VehicleType,
+};
+use crate::traffic::{merge_adsb, prune_traffic, traffic_advisories};
+use handlers::{send_message, HandlerContext};
+use mavlink::ardupilotmega::{self as common, MavCmd, MavModeFlag};
+use mavlink::MavHeader;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, trace, warn};
+
+const HOME_POSITION_MSG_ID: f32 = 242.0;
+const SMART_BATTERY_INFO_MSG_ID: f32 = 370.0;
+const AUTOPILOT_VERSION_MSG_ID: f32 = 148.0;
+/// How often silent GCS peers and their control claims are expired.
+const PEER_EXPIRY_PERIOD: Duration = Duration::from_secs(1);
+/// How often silent ADS-B targets are expired and advisories re-checked.
+const TRAFFIC_EXPIRY_PERIOD: Duration = Duration::from_secs(1);
+/// STATUSTEXT.text capacity.
+const STATUSTEXT_MAX_LEN: usize = 50;
+/// How long a disconnect waits for the goodbye messages to go out.
+const GOODBYE_FLUSH_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Internal tracking of the remote vehicle identity (from heartbeats).
+#[derive(Debug, Clone, Copy)]
+struct VehicleTarget {
+    system_id: u8,
+    component_id: u8,
+    autopilot: common::MavAutopilot,
+    vehicle_type: common::MavType,
+}
+
+/// What an event loop talks over.
+pub(crate) enum LoopLink {
+    /// A connection of its own, closed when the loop ends.
+    Own(Transport),
+    /// One system id's share of a link carrying several vehicles. Pacing and
+    /// closing belong to the `SharedLink`.
+    Shared(SharedLink, u8),
+}
+
+impl LoopLink {
+    pub(crate) fn udp_peer(&self) -> Option<watch::Receiver<Option<SocketAddr>>> {
+        match self {
+            LoopLink::Own(transport) => transport.udp_peer(),
+            LoopLink::Shared(shared, _) => shared.udp_peer(),
+        }
+    }
+}
+
+/// Runs commands one at a time. Incoming messages are handled by a separate
+/// state updater task; both read their own `Link` handle, so a command waiting
+/// for its reply sees every message without starving telemetry.
+pub(crate) async fn run_event_loop(
+    link: LoopLink,
+    mut command_rx: mpsc::Receiver<Command>,
+    urgent_rx: mpsc::Receiver<Command>,
+    state_writers: StateWriters,
+    mut config: VehicleConfig,
+    cancel: CancellationToken,
+) {
+    // Holding the shared link keeps it open while this vehicle runs
+    let (connection, link_tasks, _shared) = match link {
+        LoopLink::Own(transport) => {
+            let (connection, tasks) = Link::new(transport, config.link_pacing, cancel.clone());
+            (connection, Some(tasks), None)
+        }
+        LoopLink::Shared(shared, system_id) => (shared.handle_for(system_id), None, Some(shared)),
+    };
+    let mut connection = connection.deduplicated(config.link_dedup_window);
+    let state_writers = Arc::new(state_writers);
+    let (target_tx, target_rx) = watch::channel(None);
+    let (config_tx, config_rx) = watch::channel(config.clone());
+    // Time base of our TIMESYNC requests
+    let clock_epoch = Instant::now();
+    let housekeeping_task = tokio::spawn(run_housekeeping(
+        connection.clone(),
+        state_writers.clone(),
+        config_rx.clone(),
+        target_rx.clone(),
+        clock_epoch,
+    ));
+    let urgent_task = tokio::spawn(run_urgent_commands(
+        connection.clone(),
+        state_writers.clone(),
+        urgent_rx,
+        target_rx.clone(),
+        config_rx.clone(),
+        cancel.clone(),
+    ));
+    let mut state_task = tokio::spawn(run_state_updater(
+        connection.clone(),
+        state_writers.clone(),
+        target_tx,
+        config_rx,
+        clock_epoch,
+    ));
+
+    let _ = state_writers.link_state.send(LinkState::Connected);
+
+    loop {
+        tokio::select! {
+            biased;
+
+            _ = cancel.cancelled() => {
+                debug!("event loop cancelled");
+                let _ = state_writers.link_state.send(LinkState::Disconnected);
+                break;
+            }
+            _ = &mut state_task => {
+                // Receive failed; the updater has already reported it
+                break;
+            }
+            Some(cmd) = command_rx.recv() => {
+                match cmd {
+                    Command::Shutdown => {
+                        debug!("event loop shutdown requested");
+                        if let Some(notice) = &config.shutdown_notice {
+                            say_goodbye(&connection, &config, notice).await;
+                            if let Some(tasks) = &link_tasks {
+                                tasks.flush(GOODBYE_FLUSH_TIMEOUT).await;
+                            }
+                        }
+                        let _ = state_writers.link_state.send(LinkState::Disconnected);
+                        break;
+                    }
+                    Command::UpdateConfig { patch, reply } => {
+                        // Between commands, so no transfer sees a mix of old and new settings
+                        patch.apply(&mut config);
+                        if let Some(tasks) = &link_tasks {
+                            tasks.set_pacing(config.link_pacing);
+                        }
+                        let _ = config_tx.send(config.clone());
+                        let _ = reply.send(Ok(()));
+                    }
+                    cmd => {
+                        // Replies are only looked for in traffic after the request
+                        connection.skip_pending();
+                        let mut vehicle_target = *target_rx.borrow();
+                        let identity = (config.gcs_system_id, config.gcs_component_id);
+                        let mut ctx = HandlerContext {
+                            connection: &mut connection,
+                            writers: &state_writers,
+                            vehicle_target: &mut vehicle_target,
+                            config: &mut config,
+                            cancel: &cancel,
+                        };
+                        handlers::route(cmd, &mut ctx).await;
+                        if identity != (config.gcs_system_id, config.gcs_component_id) {
+                            let _ = config_tx.send(config.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    state_task.abort();
+    housekeeping_task.abort();
+    urgent_task.abort();
+    if let Some(tasks) = link_tasks {
+        tasks.close().await;
+    }
+}
+
+/// Runs the commands of the priority lane (see `Command::is_urgent`) beside
+/// the main command loop, so a mode change or emergency stop doesn't wait
+/// for a mission upload to finish. Their messages also go out ahead of the
+/// transfer's, as `SendPriority::Control`.
+async fn run_urgent_commands(
+    mut connection: Link,
+    writers: Arc<StateWriters>,
+    mut urgent_rx: mpsc::Receiver<Command>,
+    target_rx: watch::Receiver<Option<VehicleTarget>>,
+    config_rx: watch::Receiver<VehicleConfig>,
+    cancel: CancellationToken,
+) {
+    while let Some(cmd) = urgent_rx.recv().await {
+        connection.skip_pending();
+        let mut vehicle_target = *target_rx.borrow();
+        let mut config = config_rx.borrow().clone();
+        let mut ctx = HandlerContext {
+            connection: &mut connection,
+            writers: &writers,
+            vehicle_target: &mut vehicle_target,
+            config: &mut config,
+            cancel: &cancel,
+        };
+        handlers::route(cmd, &mut ctx).await;
+    }
+}
+
+/// Runs the periodic jobs, independently of whatever command is in progress.
+async fn run_housekeeping(
+    connection: Link,
+    writers: Arc<StateWriters>,
+    config_rx: watch::Receiver<VehicleConfig>,
+    target_rx: watch::Receiver<Option<VehicleTarget>>,
+    clock_epoch: Instant,
+) {
+    let config = config_rx.borrow().clone();
+    let mut schedule = Schedule::default();
+    let now = Instant::now();
+    if let Some(period) = config.gcs_heartbeat_interval {
+        schedule.every(period, Job::GcsHeartbeat, now);
+    }
+    schedule.every(PEER_EXPIRY_PERIOD, Job::ExpirePeers, now);
+    schedule.every(TRAFFIC_EXPIRY_PERIOD, Job::ExpireTraffic, now);
+    if let Some(period) = config.timesync_interval {
+        schedule.every(period, Job::Timesync, now);
+    }
+    // Always scheduled: the operator data can be set on a live connection
+    schedule.every(REMOTE_ID_PERIOD, Job::RemoteIdOperator, now);
+
+    let mut tick = tokio::time::interval(config.tick_interval);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        let now = Instant::now();
+        for job in schedule.due(now) {
+            match job {
+                Job::GcsHeartbeat => {
+                    // Follows GCS identity changes
+                    let config = config_rx.borrow().clone();
+                    let _ = send_message(&connection, &config, gcs_heartbeat()).await;
+                }
+                Job::ExpirePeers => expire_peers(&writers, now),
+                Job::ExpireTraffic => {
+                    // Also follows our own track between ADS-B reports
+                    writers
+                        .traffic
+                        .send_if_modified(|traffic| prune_traffic(traffic, now));
+                    refresh_traffic_advisories(&writers);
+                }
+                Job::Timesync => {
+                    let config = config_rx.borrow().clone();
+                    let ts1 = local_ns(clock_epoch, Instant::now());
+                    let _ = send_message(&connection, &config, timesync_request(ts1)).await;
+                }
+                Job::RemoteIdOperator => {
+                    let config = config_rx.borrow().clone();
+                    let target = *target_rx.borrow();
+                    if let Some(target) = target {
+                        for message in config.remote_id.messages(target.system_id) {
+                            let _ = send_message(&connection, &config, message).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn gcs_heartbeat() -> common::MavMessage {
+    common::MavMessage::HEARTBEAT(common::HEARTBEAT_DATA {
+        mavtype: common::MavType::MAV_TYPE_GCS,
+        autopilot: common::MavAutopilot::MAV_AUTOPILOT_INVALID,
+        system_status: common::MavState::MAV_STATE_ACTIVE,
+        mavlink_version: 3,
+        ..Default::default()
+    })
+}
+
+/// Final STATUSTEXT, then a GCS HEARTBEAT announcing that we power off.
+async fn say_goodbye(connection: &Link, config: &VehicleConfig, notice: &str) {
+    let mut end = notice.len().min(STATUSTEXT_MAX_LEN);
+    while !notice.is_char_boundary(end) {
+        end -= 1;
+    }
+    let statustext = common::MavMessage::STATUSTEXT(common::STATUSTEXT_DATA {
+        severity: common::MavSeverity::MAV_SEVERITY_NOTICE,
+        text: notice[..end].into(),
+        ..Default::default()
+    });
+    let heartbeat = common::MavMessage::HEARTBEAT(common::HEARTBEAT_DATA {
+        mavtype: common::MavType::MAV_TYPE_GCS,
+        autopilot: common::MavAutopilot::MAV_AUTOPILOT_INVALID,
+        system_status: common::MavState::MAV_STATE_POWEROFF,
+        mavlink_version: 3,
+        ..Default::default()
+    });
+    let _ = send_message(connection, config, statustext).await;
+    let _ = send_message(connection, config, heartbeat).await;
+}
+
+fn timesync_request(ts1: i64) -> common::MavMessage {
+    common::MavMessage::TIMESYNC(common::TIMESYNC_DATA {
+        tc1: 0,
+        ts1,
+        ..Default::default()
+    })
+}
+
+/// Drop GCS peers whose heartbeat has gone silent and any control they held.
+fn expire_peers(writers: &StateWriters, now: Instant) {
+    writers
+        .gcs_peers
+        .send_if_modified(|peers| prune_gcs_peers(peers, now));
+    let peers = writers.gcs_peers.borrow().clone();
+    writers
+        .control
+        .send_if_modified(|control| expire_peer_control(control, &peers, now));
+}
+
+/// Re-run the conflict prediction against the current traffic table.
+fn refresh_traffic_advisories(writers: &StateWriters) {
+    let advisories = {
+        let telemetry = writers.telemetry.borrow();
+        traffic_advisories(&telemetry, &writers.traffic.borrow())
+    };
+    writers.traffic_advisories.send_if_modified(|current| {
+        let changed = *current != advisories;
+        *current = advisories;
+        changed
+    });
+}
+
+/// Applies every received message to the vehicle target and state channels,
+/// independently of whatever command is in progress.
+async fn run_state_updater(
+    mut connection: Link,
+    writers: Arc<StateWriters>,
+    target_tx: watch::Sender<Option<VehicleTarget>>,
+    mut config_rx: watch::Receiver<VehicleConfig>,
+    clock_epoch: Instant,
+) {
+    let mut config = config_rx.borrow_and_update().clone();
+    let mut vehicle_target: Option<VehicleTarget> = None;
+    let mut clock = VehicleClock::new(clock_epoch);
+    let mut odometer = Odometer::default();
+    let mut boot_watch = BootWatch::default();
+    let mut initial_requests_sent = false;
+
+    loop {
+        let received = match connection.recv().await {
+            Ok(received) => received,
+            Err(VehicleError::LinkLagged { skipped }) => {
+                warn!("state updater fell behind, skipped {skipped} messages");
+                continue;
+            }
+            Err(err) => {
+                warn!("MAVLink recv error: {err}");
+                let _ = writers.link_state.send(LinkState::Error(err.to_string()));
+                return;
+            }
+        };
+        let (header, msg) = &*received;
+        if config_rx.has_changed().unwrap_or(false) {
+            config = config_rx.borrow_and_update().clone();
+        }
+
+        update_vehicle_target(&mut vehicle_target, connection.system_id(), header, msg);
+        target_tx.send_replace(vehicle_target);
+        let boot_ms = autopilot_boot_ms(header, msg, &vehicle_target);
+        if boot_ms.is_some_and(|boot_ms| boot_watch.observe(boot_ms)) {
+            warn!("vehicle rebooted");
+            forget_vehicle_state(&writers);
+            // Home, battery and version are asked for again
+            initial_requests_sent = false;
+        }
+        if !initial_requests_sent {
+            if let Some(ref target) = vehicle_target {
+                if config.auto_request_home {
+                    request_message(&connection, target, &config, HOME_POSITION_MSG_ID).await;
+                }
+                request_message(&connection, target, &config, SMART_BATTERY_INFO_MSG_ID).await;
+                request_message(&connection, target, &config, AUTOPILOT_VERSION_MSG_ID).await;
+                initial_requests_sent = true;
+            }
+        }
+        update_state(
+            header,
+            msg,
+            &writers,
+            &vehicle_target,
+            &config,
+            &mut clock,
+            &mut odometer,
+        );
+    }
+}
+
+/// `time_boot_ms` of a message from the autopilot itself; other components
+/// run their own boot clocks.
+fn autopilot_boot_ms(
+    header: &MavHeader,
+    message: &common::MavMessage,
+    vehicle_target: &Option<VehicleTarget>,
+) -> Option<u32> {
+    let target = vehicle_target.as_ref()?;
+    if (header.system_id, header.component_id) != (target.system_id, target.component_id) {
+        return None;
+    }
+    match message {
+        common::MavMessage::SYSTEM_TIME(data) => Some(data.time_boot_ms),
+        common::MavMessage::ATTITUDE(data) => Some(data.time_boot_ms),
+        common::MavMessage::GLOBAL_POSITION_INT(data) => Some(data.time_boot_ms),
+        _ => None,
+    }
+}
+
+/// A rebooted vehicle has a new home to set and may come back with other
+/// parameters or plans, so what we knew of them is dropped.
+fn forget_vehicle_state(writers: &StateWriters) {
+    let _ = writers.home_position.send(None);
+    let _ = writers.fence_status.send(None);
+    writers.param_store.send_replace(Arc::default());
+    writers
+        .current_vehicle_plan
+        .send_replace(mission::OnboardPlans::default());
+    writers
+        .plan_epochs
+        .send_replace(mission::PlanEpochs::default());
+    writers.reboots.send_modify(|count| *count += 1);
+}
+
+async fn request_message(
+    connection: &Link,
+    target: &VehicleTarget,
+    config: &VehicleConfig,
+    message_id: f32,
+) {
+    let _ = connection
+        .send(
+            &MavHeader {
+                system_id: config.gcs_system_id,
+                component_id: config.gcs_component_id,
+                sequence: 0,
+            },
+            &common::MavMessage::COMMAND_LONG(common::COMMAND_LONG_DATA {
+                target_system: target.system_id,
+                target_component: target.component_id,
+                command: MavCmd::MAV_CMD_REQUEST_MESSAGE,
+                confirmation: 0,
+                param1: message_id,
+                param2: 0.0,
+                param3: 0.0,
+                param4: 0.0,
+                param5: 0.0,
+                param6: 0.0,
+                param7: 0.0,
+            }),
+        )
+        .await;
+}
+
+/// `system_id` pins the target to one vehicle on a shared link; otherwise the
+/// last vehicle heartbeat wins.
+fn update_vehicle_target(
+    vehicle_target: &mut Option<VehicleTarget>,
+    system_id: Option<u8>,
+    header: &MavHeader,
+    message: &common::MavMessage,
+) {
+    if header.system_id == 0 || is_gcs_heartbeat(message) {
+        return;
+    }
+    if system_id.is_some_and(|id| id != header.system_id) {
+        return;
+    }
+
+    if let common::MavMessage::HEARTBEAT(hb) = message {
+        *vehicle_target = Some(VehicleTarget {
+            system_id: header.system_id,
+            component_id: header.component_id,
+            autopilot: hb.autopilot,
+            vehicle_type: hb.mavtype,
+        });
+    } else if vehicle_target.is_none() {
+        *vehicle_target = Some(VehicleTarget {
+            system_id: header.system_id,
+            component_id: header.component_id,
+            autopilot: common::MavAutopilot::MAV_AUTOPILOT_GENERIC,
+            vehicle_type: common::MavType::MAV_TYPE_GENERIC,
+        });
+    }
+}
+
+fn is_gcs_heartbeat(message: &common::MavMessage) -> bool {
+    matches!(message, common::MavMessage::HEARTBEAT(hb) if hb.mavtype == common::MavType::MAV_TYPE_GCS)
+}
+
+/// Messages that steer the vehicle; a peer sending one takes control.
+fn is_peer_takeover(message: &common::MavMessage) -> bool {
+    match message {
+        common::MavMessage::COMMAND_LONG(data) => is_control_command(data.command),
+        common::MavMessage::COMMAND_INT(data) => is_control_command(data.command),
+        common::MavMessage::SET_MODE(_)
+        | common::MavMessage::SET_POSITION_TARGET_GLOBAL_INT(_)
+        | common::MavMessage::SET_POSITION_TARGET_LOCAL_NED(_)
+        | common::MavMessage::MANUAL_CONTROL(_)
+        | common::MavMessage::RC_CHANNELS_OVERRIDE(_) => true,
+        _ => false,
+    }
+}
+
+/// Track peer GCS control traffic to the vehicle.
+fn update_control(
+    header: &MavHeader,
+    message: &common::MavMessage,
+    writers: &StateWriters,
+    vehicle_target: &Option<VehicleTarget>,
+    now: Instant,
+) {
+    let from_peer = writers
+        .gcs_peers
+        .borrow()
+        .iter()
+        .any(|p| p.system_id == header.system_id && p.component_id == header.component_id);
+    let from_vehicle = vehicle_target
+        .as_ref()
+        .is_some_and(|t| t.system_id == header.system_id);
+
+    if from_peer && !from_vehicle && is_peer_takeover(message) {
+        writers.control.send_if_modified(|control| {
+            let changed = observe_peer_command(control, header.system_id, header.component_id, now);
+            if changed {
+                warn!(
+                    "GCS sysid {} has taken control of the vehicle",
+                    header.system_id
+                );
+            }
+            changed
+        });
+    }
+}
+
+fn update_state(
+    header: &MavHeader,
+    message: &common::MavMessage,
+    writers: &StateWriters,
+    vehicle_target: &Option<VehicleTarget>,
+    config: &VehicleConfig,
+    clock: &mut VehicleClock,
+    odometer: &mut Odometer,
+) {
+    let now = Instant::now();
+    update_control(header, message, writers, vehicle_target, now);
+    // Other ground stations send SYSTEM_TIME and TIMESYNC too
+    let from_vehicle = vehicle_target.is_some_and(|target| target.system_id == header.system_id);
+    if handlers::observe(message, from_vehicle, writers, config) {
+        return;
+    }
+
+    match message {
+        common::MavMessage::HEARTBEAT(_) if is_gcs_heartbeat(message) => {
+            writers.gcs_peers.send_if_modified(|peers| {
+                let changed = observe_gcs_heartbeat(
+                    peers,
+                    header.system_id,
+                    header.component_id,
+                    config.gcs_system_id,
+                    now,
+                );
+                if changed {
+                    warn!(
+                        "another GCS is connected: sysid {} compid {}",
+                        header.system_id, header.component_id
+                    );
+                }
+                changed
+            });
+        }
+        common::MavMessage::HEARTBEAT(hb) => {
+            if let Some(target) = vehicle_target {
+                let autopilot_type = AutopilotType::from_mav(target.autopilot);
+                let vtype = VehicleType::from_mav(target.vehicle_type);
+                let armed = hb
+                    .base_mode
+                    .contains(MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED);
+                let mode_name =
+                    config
+                        .mode_overrides
+                        .mode_name(autopilot_type, vtype, hb.custom_mode);
+                if odometer.set_armed(armed, now) {
+                    save_flight_totals(writers, config, odometer, now);
+                }
+                update_telemetry(writers, clock, None, now, |t| {
+                    set_odometer(t, odometer, now)
+                });
+
+                writers.identity.send_if_modified(|identity| {
+                    let firmware = identity.as_ref().and_then(|id| id.firmware);
+                    let next = VehicleIdentity {
+                        system_id: target.system_id,
+                        component_id: target.component_id,
+                        autopilot: autopilot_type,
+                        vehicle_type: vtype,
+                        firmware,
+                    };
+                    let changed = identity.as_ref() != Some(&next);
+                    *identity = Some(next);
+                    changed
+                });
+
+                let _ = writers.vehicle_state.send(VehicleState {
+                    armed,
+                    custom_mode: hb.custom_mode,
+                    mode_name,
+                    system_status: SystemStatus::from_mav(hb.system_status),
+                    vehicle_type: vtype,
+                    autopilot: autopilot_type,
+                });
+            }
+        }
+        common::MavMessage::SYSTEM_TIME(data) if from_vehicle => {
+            clock.observe_system_time(data.time_unix_usec, data.time_boot_ms, now);
+        }
+        common::MavMessage::TIMESYNC(data) if from_vehicle => {
+            clock.observe_timesync(data.tc1, data.ts1, now);
+        }
+        common::MavMessage::VFR_HUD(data) => {
+            // VFR_HUD.alt is AMSL on current ArduPilot but relative on some
+            // firmwares; altitudes come from GLOBAL_POSITION_INT instead.
+            update_telemetry(writers, clock, None, now, |t| {
+                t.speed_mps = Some(data.groundspeed as f64);
+                t.heading_deg = Some(data.heading as f64);
+                t.climb_rate_mps = Some(data.climb as f64);
+                t.throttle_pct = Some(data.throttle as f64);
+                t.airspeed_mps = Some(data.airspeed as f64);
+            });
+        }
+        common::MavMessage::GLOBAL_POSITION_INT(data) => {
+            odometer.observe_position(data.lat as f64 / 1e7, data.lon as f64 / 1e7, now);
+            update_telemetry(writers, clock, Some(data.time_boot_ms), now, |t| {
+                t.altitude_m = Some(data.relative_alt as f64 / 1000.0);
+                t.altitude_amsl_m = Some(data.alt as f64 / 1000.0);
+                t.latitude_deg = Some(data.lat as f64 / 1e7);
+                t.longitude_deg = Some(data.lon as f64 / 1e7);
+                let vx = data.vx as f64 / 100.0;
+                let vy = data.vy as f64 / 100.0;
+                t.speed_mps = Some((vx * vx + vy * vy).sqrt());
+                if data.hdg != u16::MAX {
+                    t.heading_deg = Some(data.hdg as f64 / 100.0);
+                }
+                set_odometer(t, odometer, now);
+            });
+        }
+        common::MavMessage::EXTENDED_SYS_STATE(data) if from_vehicle => {
+            update_telemetry(writers, clock, None, now, |t| {
+                t.landed_state = LandedState::from_mav(data.landed_state);
+            });
+        }
+        common::MavMessage::SYS_STATUS(data) => {
+            update_telemetry(writers, clock, None, now, |t| {
+                if data.battery_remaining >= 0 {
+                    t.battery_pct = Some(data.battery_remaining as f64);
+                }
+                if data.voltage_battery != u16::MAX {
+                    t.battery_voltage_v = Some(data.voltage_battery as f64 / 1000.0);
+                }
+                if data.current_battery >= 0 {
+                    t.battery_current_a = Some(data.current_battery as f64 / 100.0);
+                }
+            });
+        }
+        common::MavMessage::GPS_RAW_INT(data) => {
+            update_telemetry(writers, clock, None, now, |t| {
+                t.gps_fix_type = Some(GpsFixType::from_raw(data.fix_type as u8));
+                if data.satellites_visible != u8::MAX {
+                    t.gps_satellites = Some(data.satellites_visible);
+                }
+                if data.eph != u16::MAX {
+                    t.gps_hdop = Some(data.eph as f64 / 100.0);
+                }
+            });
+        }
+        common::MavMessage::FENCE_STATUS(data) if from_vehicle => {
+            let _ = writers
+                .fence_status
+                .send(Some(crate::geofence::FenceStatus::from_mav(data)));
+        }
+        common::MavMessage::HOME_POSITION(data) => {
+            let _ =
+                writers
+                    .home_position
+                    .send(Some(mission::HomePosition::from_home_position_msg(
+                        data.latitude,
+                        data.longitude,
+                        data.altitude,
+                    )));
+        }
+        common::MavMessage::ATTITUDE(data) => {
+            update_telemetry(writers, clock, Some(data.time_boot_ms), now, |t| {
+                t.roll_deg = Some(data.roll.to_degrees() as f64);
+                t.pitch_deg = Some(data.pitch.to_degrees() as f64);
+                t.yaw_deg = Some(data.yaw.to_degrees() as f64);
+            });
+        }
+        common::MavMessage::NAV_CONTROLLER_OUTPUT(data) => {
+            update_telemetry(writers, clock, None, now, |t| {
+                t.wp_dist_m = Some(data.wp_dist as f64);
+                t.nav_bearing_deg = Some(data.nav_bearing as f64);
+                t.target_bearing_deg = Some(data.target_bearing as f64);
+                t.xtrack_error_m = Some(data.xtrack_error as f64);
+                t.alt_error_m = Some(data.alt_error as f64);
+                t.airspeed_error_mps = Some(data.aspd_error as f64);
+            });
+        }
+        common::MavMessage::VIBRATION(data) if from_vehicle => {
+            update_telemetry(writers, clock, None, now, |t| {
+                t.vibration_mps2 = Some([
+                    data.vibration_x as f64,
+                    data.vibration_y as f64,
+                    data.vibration_z as f64,
+                ]);
+                t.accel_clipping = Some([data.clipping_0, data.clipping_1, data.clipping_2]);
+            });
+        }
+        common::MavMessage::TERRAIN_REPORT(data) => {
+            update_telemetry(writers, clock, None, now, |t| {
+                t.terrain_height_m = Some(data.terrain_height as f64);
+                t.height_above_terrain_m = Some(data.current_height as f64);
+            });
+        }
+        common::MavMessage::BATTERY_STATUS(data) => {
+            update_telemetry(writers, clock, None, now, |t| {
+                let cells: Vec<f64> = data
+                    .voltages
+                    .iter()
+                    .filter(|&&v| v != u16::MAX)
+                    .map(|&v| v as f64 / 1000.0)
+                    .collect();
+                if !cells.is_empty() {
+                    t.battery_voltage_cells = Some(cells);
+                }
+                if data.energy_consumed >= 0 {
+                    t.energy_consumed_wh = Some(data.energy_consumed as f64 / 36.0);
+                }
+                if data.time_remaining > 0 {
+                    t.battery_time_remaining_s = Some(data.time_remaining);
+                }
+            });
+        }
+        common::MavMessage::SMART_BATTERY_INFO(data) => {
+            let info = battery_info_from_mav(data);
+            writers.battery_info.send_modify(|all| {
+                match all.iter_mut().find(|b| b.id == info.id) {
+                    Some(existing) => *existing = info,
+                    None => {
+                        all.push(info);
+                        all.sort_by_key(|b| b.id);
+                    }
+                }
+            });
+        }
+        common::MavMessage::OPEN_DRONE_ID_BASIC_ID(_)
+        | common::MavMessage::OPEN_DRONE_ID_LOCATION(_)
+        | common::MavMessage::OPEN_DRONE_ID_SYSTEM(_)
+        | common::MavMessage::OPEN_DRONE_ID_OPERATOR_ID(_)
+        | common::MavMessage::OPEN_DRONE_ID_SELF_ID(_)
+        | common::MavMessage::OPEN_DRONE_ID_ARM_STATUS(_)
+            if from_vehicle =>
+        {
+            writers
+                .remote_id
+                .send_if_modified(|state| merge_remote_id(state, message));
+        }
+        common::MavMessage::ADSB_VEHICLE(data) if from_vehicle => {
+            if writers
+                .traffic
+                .send_if_modified(|traffic| merge_adsb(traffic, data, now))
+            {
+                refresh_traffic_advisories(writers);
+            }
+        }
+        common::MavMessage::AUTOPILOT_VERSION(data) if from_vehicle => {
+            // Can beat the first heartbeat; that heartbeat keeps the version
+            let firmware = FirmwareVersion::from_mav(data.flight_sw_version);
+            if let Some(target) = vehicle_target {
+                writers.identity.send_if_modified(|identity| {
+                    let inserted = identity.is_none();
+                    let id = identity.get_or_insert_with(|| VehicleIdentity {
+                        system_id: target.system_id,
+                        component_id: target.component_id,
+                        autopilot: AutopilotType::from_mav(target.autopilot),
+                        vehicle_type: VehicleType::from_mav(target.vehicle_type),
+                        firmware: None,
+                    });
+                    let changed = inserted || id.firmware != firmware;
+                    id.firmware = firmware;
+                    changed
+                });
+            }
+            if let Some(uid) = autopilot_uid(data.uid, &data.uid2) {
+                if !odometer.has_previous() {
+                    load_flight_totals(config, odometer, &uid);
+                }
+                writers.vehicle_uid.send_if_modified(|current| {
+                    let changed = current.as_deref() != Some(uid.as_str());
+                    *current = Some(uid);
+                    changed
+                });
+            }
+        }
+        common::MavMessage::ESC_STATUS(data) => {
+            writers.esc_telemetry.send_modify(|all| {
+                merge_esc_status(all, data.index, &data.rpm, &data.voltage, &data.current);
+            });
+        }
+        common::MavMessage::ESC_INFO(data) => {
+            writers.esc_telemetry.send_modify(|all| {
+                merge_esc_temperatures(
+                    all,
+                    data.index,
+                    data.count,
+                    &data.temperature,
+                    config.esc_max_temperature_c,
+                );
+            });
+        }
+        common::MavMessage::ESC_TELEMETRY_1_TO_4(data) => {
+            writers.esc_telemetry.send_modify(|all| {
+                merge_esc_telemetry(
+                    all,
+                    0,
+                    &data.temperature,
+                    &data.voltage,
+                    &data.current,
+                    &data.rpm,
+                    config.esc_max_temperature_c,
+                );
+            });
+        }
+        common::MavMessage::ESC_TELEMETRY_5_TO_8(data) => {
+            writers.esc_telemetry.send_modify(|all| {
+                merge_esc_telemetry(
+                    all,
+                    4,
+                    &data.temperature,
+                    &data.voltage,
+                    &data.current,
+                    &data.rpm,
+                    config.esc_max_temperature_c,
+                );
+            });
+        }
+        common::MavMessage::ESC_TELEMETRY_9_TO_12(data) => {
+            writers.esc_telemetry.send_modify(|all| {
+                merge_esc_telemetry(
+                    all,
+                    8,
+                    &data.temperature,
+                    &data.voltage,
+                    &data.current,
+                    &data.rpm,
+                    config.esc_max_temperature_c,
+                );
+            });
+        }
+        common::MavMessage::RC_CHANNELS(data) => {
+            let count = data.chancount.min(18) as usize;
+            let all = [
+                data.chan1_raw,
+                data.chan2_raw,
+                data.chan3_raw,
+                data.chan4_raw,
+                data.chan5_raw,
+                data.chan6_raw,
+                data.chan7_raw,
+                data.chan8_raw,
+                data.chan9_raw,
+                data.chan10_raw,
+                data.chan11_raw,
+                data.chan12_raw,
+                data.chan13_raw,
+                data.chan14_raw,
+                data.chan15_raw,
+                data.chan16_raw,
+                data.chan17_raw,
+                data.chan18_raw,
+            ];
+            let channels = all[..count].to_vec();
+            writers.rc_channels.send_replace(channels.clone());
+            update_telemetry(writers, clock, Some(data.time_boot_ms), now, |t| {
+                t.rc_channels = Some(channels);
+                if data.rssi != u8::MAX {
+                    t.rc_rssi = Some(data.rssi);
+                }
+            });
+        }
+        common::MavMessage::SERVO_OUTPUT_RAW(data) => {
+            update_telemetry(writers, clock, Some(data.time_usec / 1000), now, |t| {
+                t.servo_outputs = Some(vec![
+                    data.servo1_raw,
+                    data.servo2_raw,
+                    data.servo3_raw,
+                    data.servo4_raw,
+                    data.servo5_raw,
+                    data.servo6_raw,
+                    data.servo7_raw,
+                    data.servo8_raw,
+                    data.servo9_raw,
+                    data.servo10_raw,
+                    data.servo11_raw,
+                    data.servo12_raw,
+                    data.servo13_raw,
+                    data.servo14_raw,
+                    data.servo15_raw,
+                    data.servo16_raw,
+                ]);
+            });
+        }
+        _ => {
+            trace!("unhandled message type");
+        }
+    }
+}
+
+/// Apply `modify` to the telemetry and stamp it with the vehicle's time: the
+/// message's own `time_boot_ms` when it has one, else the clock estimate.
+fn update_telemetry(
+    writers: &StateWriters,
+    clock: &VehicleClock,
+    time_boot_ms: Option<u32>,
+    now: Instant,
+    modify: impl FnOnce(&mut Telemetry),
+) {
+    let time = clock.stamp(time_boot_ms, now);
+    writers.telemetry.send_modify(|t| {
+        modify(t);
+        t.time_boot_ms = time.boot_ms;
+        t.time_utc_us = time.utc_us;
+    });
+}
+
+fn battery_info_from_mav(data: &common::SMART_BATTERY_INFO_DATA) -> BatteryInfo {
+    let capacity = |mah: i32| (mah > 0).then_some(mah as u32);
+    let text = |s: Option<&str>| {
+        s.map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    let design_capacity_mah = capacity(data.capacity_full_specification);
+    let full_capacity_mah = capacity(data.capacity_full);
+    BatteryInfo {
+        id: data.id,
+        design_capacity_mah,
+        full_capacity_mah,
+        cycle_count: (data.cycle_count != u16::MAX).then_some(data.cycle_count),
+        serial_number: text(data.serial_number.to_str().ok()),
+        device_name: text(data.device_name.to_str().ok()),
+        manufacture_date: text(data.manufacture_date.to_str().ok()),
+        cells_in_series: (data.cells_in_series > 0).then_some(data.cells_in_series),
+        health_pct: match (full_capacity_mah, design_capacity_mah) {
+            (Some(full), Some(design)) => Some(full as f64 / design as f64 * 100.0),
+            _ => None,
+        },
+    }
+}
+
+fn set_odometer(t: &mut Telemetry, odometer: &Odometer, now: Instant) {
+    let session = odometer.session(now);
+    t.armed_time_s = Some(session.armed_time_s);
+    t.distance_flown_m = Some(session.distance_m);
+    t.flights = Some(session.flights);
+    let lifetime = odometer.lifetime(now);
+    t.total_armed_time_s = lifetime.map(|totals| totals.armed_time_s);
+    t.total_distance_flown_m = lifetime.map(|totals| totals.distance_m);
+    t.total_flights = lifetime.map(|totals| totals.flights);
+}
+
+fn load_flight_totals(config: &VehicleConfig, odometer: &mut Odometer, uid: &str) {
+    let Some(dir) = &config.flight_log_dir else {
+        return;
+    };
+    match FlightLog::open(dir).and_then(|log| log.load(uid)) {
+        Ok(previous) => odometer.set_previous(previous),
+        Err(err) => warn!("flight log for {uid} unreadable: {err}"),
+    }
+}
+
+/// Write the airframe's totals after a flight, so they survive a crash of
+/// the GCS later in the session.
+fn save_flight_totals(
+    writers: &StateWriters,
+    config: &VehicleConfig,
+    odometer: &Odometer,
+    now: Instant,
+) {
+    let (Some(dir), Some(totals)) = (&config.flight_log_dir, odometer.lifetime(now)) else {
+        return;
+    };
+    let Some(uid) = writers.vehicle_uid.borrow().clone() else {
+        return;
+    };
+    if let Err(err) = FlightLog::open(dir).and_then(|log| log.save(&uid, &totals)) {
+        warn!("could not log flight totals of {uid}: {err}");
+    }
+}
+
+/// Hex form of AUTOPILOT_VERSION's `uid`, or of `uid2` on boards that
+/// only fill the longer field. `None` when the board reports neither.
+fn autopilot_uid(uid: u64, uid2: &[u8; 18]) -> Option<String> {
+    if uid != 0 {
+        return Some(format!("{uid:016x}"));
+    }
+    uid2.iter()
+        .any(|&b| b != 0)
+        .then(|| uid2.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::Inbound;
+    use crate::state::create_channels;
+    use tokio::sync::oneshot;
+
+    fn from_vehicle(message: common::MavMessage) -> Inbound {
+        let header = MavHeader {
+            system_id: 1,
+            component_id: 1,
+            sequence: 0,
+        };
+        Ok(Arc::new((header, message)))
+    }
+
+    /// A vehicle that only acknowledges a command once the GCS has shown
+    /// the position sent while the command waits.
+    #[tokio::test]
+    async fn state_updates_while_a_command_waits_for_its_ack() {
+        let (mut link, mut sent, inbound) = Link::loopback();
+        let (writers, channels) = create_channels();
+        let writers = Arc::new(writers);
+        let (target_tx, _target_rx) = watch::channel(None);
+        let (_config_tx, config_rx) = watch::channel(VehicleConfig::default());
+        let updater = tokio::spawn(run_state_updater(
+            link.clone(),
+            writers.clone(),
+            target_tx,
+            config_rx,
+            Instant::now(),
+        ));
+        let _ = inbound.send(from_vehicle(common::MavMessage::HEARTBEAT(
+            common::HEARTBEAT_DATA::default(),
+        )));
+
+        let mut telemetry = channels.telemetry;
+        let vehicle = tokio::spawn(async move {
+            while let Some((_, _, message)) = sent.recv().await {
+                let common::MavMessage::COMMAND_LONG(data) = message else {
+                    continue;
+                };
+                if data.command != MavCmd::MAV_CMD_DO_SET_SERVO {
+                    continue;
+                }
+                let position = common::GLOBAL_POSITION_INT_DATA {
+                    relative_alt: 12_000,
+                    ..Default::default()
+                };
+                let _ = inbound.send(from_vehicle(common::MavMessage::GLOBAL_POSITION_INT(
+                    position,
+                )));
+                let shown = telemetry.wait_for(|t| t.altitude_m == Some(12.0));
+                if tokio::time::timeout(Duration::from_secs(1), shown)
+                    .await
+                    .is_ok()
+                {
+                    let ack = common::COMMAND_ACK_DATA {
+                        command: data.command,
+                        result: common::MavResult::MAV_RESULT_ACCEPTED,
+                        ..Default::default()
+                    };
+                    let _ = inbound.send(from_vehicle(common::MavMessage::COMMAND_ACK(ack)));
+                }
+            }
+        });
+
+        let (reply, result) = oneshot::channel();
+        let command = Command::CommandLong {
+            command: MavCmd::MAV_CMD_DO_SET_SERVO,
+            params: [9.0, 1900.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            reply,
+        };
+        let mut vehicle_target = Some(VehicleTarget {
+            system_id: 1,
+            component_id: 1,
+            autopilot: common::MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA,
+            vehicle_type: common::MavType::MAV_TYPE_QUADROTOR,
+        });
+        let mut config = VehicleConfig::default();
+        let cancel = CancellationToken::new();
+        let mut ctx = HandlerContext {
+            connection: &mut link,
+            writers: &writers,
+            vehicle_target: &mut vehicle_target,
+            config: &mut config,
+            cancel: &cancel,
+        };
+        handlers::route(command, &mut ctx).await;
+        assert!(result.await.unwrap().is_ok());
+
+        updater.abort();
+        vehicle.abort();
+    }
+}
@@ -0,0 +1,355 @@
+//! Bounded event queue between a session and its consumers.
+//!
+//! A consumer that cannot keep up must not stall the session that feeds it,
+//! and must not make it buffer without limit either. Events are queued in
+//! two classes: telemetry-class events are superseded by newer ones, so when
+//! the queue is full the oldest queued telemetry event is dropped; result-
+//! class events (command results, link state changes) are always delivered,
+//! even past the capacity. Dropped events are counted for diagnostics.
+//!
+//! Sending never blocks. The receiving side can wait either from async code
+//! or on a plain thread.
+
+use crate::audit::AuditEntry;
+use crate::mission::TransferProgress;
+use crate::state::{LinkState, Telemetry, VehicleState};
+use crate::traffic::TrafficAdvisory;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use tokio::sync::Notify;
+
+/// Queue size used when the caller does not pick one.
+pub const DEFAULT_EVENT_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventClass {
+    /// Superseded by the next one; dropped oldest-first when the queue is full.
+    Telemetry,
+    /// Always delivered.
+    Result,
+}
+
+/// Counters of one event queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventStats {
+    /// Events handed to the consumer.
+    pub delivered: u64,
+    /// Telemetry-class events dropped because the queue was full.
+    pub dropped: u64,
+    /// Events waiting in the queue.
+    pub queued: usize,
+}
+
+/// Events of one `Vehicle`, as delivered by `Vehicle::events`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum VehicleEvent {
+    Telemetry(Telemetry),
+    State(VehicleState),
+    LinkState(LinkState),
+    MissionProgress(Option<TransferProgress>),
+    /// Predicted conflicts with ADS-B traffic, soonest first; empty once
+    /// they are resolved.
+    TrafficAdvisories(Vec<TrafficAdvisory>),
+    /// A command finished, with the same entry the audit log records.
+    CommandResult(AuditEntry),
+}
+
+impl VehicleEvent {
+    pub fn class(&self) -> EventClass {
+        match self {
+            Self::Telemetry(_)
+            | Self::State(_)
+            | Self::MissionProgress(_)
+            | Self::TrafficAdvisories(_) => EventClass::Telemetry,
+            Self::LinkState(_) | Self::CommandResult(_) => EventClass::Result,
+        }
+    }
+}
+
+/// Live view of a queue's counters, usable after the queue is handed off.
+#[derive(Debug, Clone, Default)]
+pub struct EventMonitor {
+    counters: Arc<Counters>,
+}
+
+impl EventMonitor {
+    pub fn stats(&self) -> EventStats {
+        EventStats {
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            queued: self.counters.queued.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    queued: AtomicUsize,
+}
+
+struct Queue<T> {
+    events: VecDeque<(EventClass, T)>,
+    senders: usize,
+    /// Set when the receiver is dropped or a sender closes the queue.
+    closed: bool,
+}
+
+struct Shared<T> {
+    queue: Mutex<Queue<T>>,
+    capacity: usize,
+    counters: Arc<Counters>,
+    /// Wakes a receiver blocked on a thread.
+    ready: Condvar,
+    /// Wakes a receiver awaiting in async code.
+    notify: Notify,
+}
+
+impl<T> Shared<T> {
+    fn wake(&self) {
+        self.ready.notify_one();
+        self.notify.notify_one();
+    }
+}
+
+/// Create a queue holding up to `capacity` telemetry-class events.
+pub fn event_channel<T>(capacity: usize) -> (EventSender<T>, EventReceiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Queue {
+            events: VecDeque::new(),
+            senders: 1,
+            closed: false,
+        }),
+        capacity: capacity.max(1),
+        counters: Arc::default(),
+        ready: Condvar::new(),
+        notify: Notify::new(),
+    });
+    (
+        EventSender {
+            shared: shared.clone(),
+        },
+        EventReceiver { shared },
+    )
+}
+
+pub struct EventSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> EventSender<T> {
+    /// Queue `event` without waiting. Returns `false` once the queue is
+    /// closed, so producers know to stop.
+    pub fn send(&self, class: EventClass, event: T) -> bool {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.closed {
+            return false;
+        }
+        let counters = &self.shared.counters;
+        if class == EventClass::Telemetry && queue.events.len() >= self.shared.capacity {
+            counters.dropped.fetch_add(1, Ordering::Relaxed);
+            let oldest = queue
+                .events
+                .iter()
+                .position(|(class, _)| *class == EventClass::Telemetry);
+            match oldest {
+                Some(index) => {
+                    queue.events.remove(index);
+                }
+                // Only results are queued; the new event is the oldest.
+                None => return true,
+            }
+        }
+        queue.events.push_back((class, event));
+        counters.queued.store(queue.events.len(), Ordering::Relaxed);
+        drop(queue);
+        self.shared.wake();
+        true
+    }
+
+    /// Close the queue for every sender. The receiver still gets the events
+    /// already queued.
+    pub fn close(&self) {
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.wake();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.queue.lock().unwrap().closed
+    }
+
+    pub fn monitor(&self) -> EventMonitor {
+        EventMonitor {
+            counters: self.shared.counters.clone(),
+        }
+    }
+}
+
+impl<T> Clone for EventSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.queue.lock().unwrap().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for EventSender<T> {
+    fn drop(&mut self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.senders -= 1;
+        if queue.senders == 0 {
+            drop(queue);
+            self.shared.wake();
+        }
+    }
+}
+
+pub struct EventReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> EventReceiver<T> {
+    /// The next queued event, if any.
+    pub fn try_recv(&mut self) -> Option<T> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        self.pop(&mut queue)
+    }
+
+    /// Wait for the next event. `None` once the queue is drained and closed
+    /// or every sender is gone.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            {
+                let mut queue = self.shared.queue.lock().unwrap();
+                if let Some(event) = self.pop(&mut queue) {
+                    return Some(event);
+                }
+                if queue.closed || queue.senders == 0 {
+                    return None;
+                }
+            }
+            // `notify_one` stores a permit, so a send between the check and
+            // this await is not missed.
+            self.shared.notify.notified().await;
+        }
+    }
+
+    /// Like `recv`, blocking the current thread. Must not be called from
+    /// async code.
+    pub fn recv_blocking(&mut self) -> Option<T> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        loop {
+            if let Some(event) = self.pop(&mut queue) {
+                return Some(event);
+            }
+            if queue.closed || queue.senders == 0 {
+                return None;
+            }
+            queue = self.shared.ready.wait(queue).unwrap();
+        }
+    }
+
+    pub fn monitor(&self) -> EventMonitor {
+        EventMonitor {
+            counters: self.shared.counters.clone(),
+        }
+    }
+
+    pub fn stats(&self) -> EventStats {
+        self.monitor().stats()
+    }
+
+    fn pop(&self, queue: &mut Queue<T>) -> Option<T> {
+        let (_, event) = queue.events.pop_front()?;
+        let counters = &self.shared.counters;
+        counters.delivered.fetch_add(1, Ordering::Relaxed);
+        counters.queued.store(queue.events.len(), Ordering::Relaxed);
+        Some(event)
+    }
+}
+
+impl<T> Drop for EventReceiver<T> {
+    fn drop(&mut self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.closed = true;
+        queue.events.clear();
+        self.shared.counters.queued.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn full_queue_drops_oldest_telemetry_but_keeps_results() {
+        let (tx, mut rx) = event_channel(3);
+        tx.send(EventClass::Telemetry, 1);
+        tx.send(EventClass::Result, 2);
+        tx.send(EventClass::Telemetry, 3);
+        tx.send(EventClass::Telemetry, 4);
+        tx.send(EventClass::Result, 5);
+        let received: Vec<_> = std::iter::from_fn(|| rx.try_recv()).collect();
+        assert_eq!(received, vec![2, 3, 4, 5]);
+        assert_eq!(
+            rx.stats(),
+            EventStats {
+                delivered: 4,
+                dropped: 1,
+                queued: 0
+            }
+        );
+    }
+
+    #[test]
+    fn telemetry_is_dropped_when_only_results_are_queued() {
+        let (tx, mut rx) = event_channel(1);
+        tx.send(EventClass::Result, 1);
+        tx.send(EventClass::Telemetry, 2);
+        tx.send(EventClass::Result, 3);
+        assert_eq!(rx.try_recv(), Some(1));
+        assert_eq!(rx.try_recv(), Some(3));
+        assert_eq!(rx.try_recv(), None);
+        assert_eq!(rx.stats().dropped, 1);
+    }
+
+    #[test]
+    fn receiver_sees_the_end_of_the_queue() {
+        let (tx, mut rx) = event_channel(4);
+        let monitor = rx.monitor();
+        let producer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx.send(EventClass::Result, 7);
+        });
+        assert_eq!(rx.recv_blocking(), Some(7));
+        assert_eq!(rx.recv_blocking(), None);
+        producer.join().unwrap();
+        assert_eq!(monitor.stats().delivered, 1);
+
+        let (tx, rx) = event_channel::<u8>(4);
+        drop(rx);
+        assert!(!tx.send(EventClass::Result, 1));
+        assert!(tx.is_closed());
+    }
+
+    #[tokio::test]
+    async fn async_receiver_wakes_on_send_and_close() {
+        let (tx, mut rx) = event_channel(4);
+        let closer = tx.clone();
+        tokio::spawn(async move {
+            tokio::task::yield_now().await;
+            tx.send(EventClass::Telemetry, "fix");
+            closer.close();
+        });
+        assert_eq!(rx.recv().await, Some("fix"));
+        assert_eq!(rx.recv().await, None);
+    }
+}
@@ -0,0 +1,294 @@
+//! The PX4/ArduPilot serial bootloader protocol, as spoken by `uploader.py`.
+//!
+//! Every request ends with `EOC`; every reply ends with `INSYNC` followed by
+//! a status byte. The port must time out reads (see `READ_TIMEOUT` in the
+//! parent module) so a silent board surfaces as an error instead of a hang.
+//! Replies are read a byte at a time, so a read that times out part way
+//! through a reply can be resumed without losing its place.
+
+use crate::error::VehicleError;
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+const INSYNC: u8 = 0x12;
+const EOC: u8 = 0x20;
+const OK: u8 = 0x10;
+const FAILED: u8 = 0x11;
+const INVALID: u8 = 0x13;
+const BAD_SILICON_REV: u8 = 0x14;
+
+const GET_SYNC: u8 = 0x21;
+const GET_DEVICE: u8 = 0x22;
+const CHIP_ERASE: u8 = 0x23;
+const PROG_MULTI: u8 = 0x27;
+const GET_CRC: u8 = 0x29;
+const REBOOT: u8 = 0x30;
+
+const INFO_BL_REV: u8 = 1;
+const INFO_BOARD_ID: u8 = 2;
+const INFO_BOARD_REV: u8 = 3;
+const INFO_FLASH_SIZE: u8 = 4;
+
+/// Oldest and newest bootloader protocol revisions understood here.
+const BL_REV_MIN: u32 = 3;
+const BL_REV_MAX: u32 = 5;
+
+/// Largest PROG_MULTI payload; must be a multiple of four.
+pub(crate) const PROG_CHUNK: usize = 252;
+
+const ERASE_TIMEOUT: Duration = Duration::from_secs(20);
+const CRC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the bootloader reports about the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BoardInfo {
+    pub bootloader_rev: u32,
+    pub board_id: u32,
+    pub board_rev: u32,
+    pub flash_size: u32,
+}
+
+pub(crate) struct Bootloader<P> {
+    port: P,
+}
+
+impl<P: Read + Write> Bootloader<P> {
+    pub(crate) fn new(port: P) -> Self {
+        Self { port }
+    }
+
+    pub(crate) fn sync(&mut self) -> Result<(), VehicleError> {
+        self.send(&[GET_SYNC, EOC])?;
+        self.get_sync()
+    }
+
+    pub(crate) fn identify(&mut self) -> Result<BoardInfo, VehicleError> {
+        let bootloader_rev = self.device_info(INFO_BL_REV)?;
+        if !(BL_REV_MIN..=BL_REV_MAX).contains(&bootloader_rev) {
+            return Err(VehicleError::Flash(format!(
+                "unsupported bootloader protocol revision {bootloader_rev}"
+            )));
+        }
+        Ok(BoardInfo {
+            bootloader_rev,
+            board_id: self.device_info(INFO_BOARD_ID)?,
+            board_rev: self.device_info(INFO_BOARD_REV)?,
+            flash_size: self.device_info(INFO_FLASH_SIZE)?,
+        })
+    }
+
+    pub(crate) fn erase(&mut self) -> Result<(), VehicleError> {
+        self.send(&[CHIP_ERASE, EOC])?;
+        self.wait_for_sync(Instant::now() + ERASE_TIMEOUT)
+    }
+
+    /// Write one chunk of at most `PROG_CHUNK` bytes at the next address.
+    pub(crate) fn program(&mut self, chunk: &[u8]) -> Result<(), VehicleError> {
+        debug_assert!(chunk.len() <= PROG_CHUNK && chunk.len().is_multiple_of(4));
+        let mut request = Vec::with_capacity(chunk.len() + 3);
+        request.extend_from_slice(&[PROG_MULTI, chunk.len() as u8]);
+        request.extend_from_slice(chunk);
+        request.push(EOC);
+        self.send(&request)?;
+        self.get_sync()
+    }
+
+    pub(crate) fn crc(&mut self) -> Result<u32, VehicleError> {
+        self.send(&[GET_CRC, EOC])?;
+        let deadline = Instant::now() + CRC_TIMEOUT;
+        let crc = self.read_u32(deadline)?;
+        self.wait_for_sync(deadline)?;
+        Ok(crc)
+    }
+
+    /// Leave the bootloader and start the new firmware. The board may reset
+    /// before replying, so a missing reply is not an error.
+    pub(crate) fn reboot(&mut self) -> Result<(), VehicleError> {
+        self.send(&[REBOOT, EOC])?;
+        match self.get_sync() {
+            Ok(()) | Err(VehicleError::Timeout) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn device_info(&mut self, param: u8) -> Result<u32, VehicleError> {
+        self.send(&[GET_DEVICE, param, EOC])?;
+        let value = self.read_u32(Instant::now())?;
+        self.get_sync()?;
+        Ok(value)
+    }
+
+    fn send(&mut self, bytes: &[u8]) -> Result<(), VehicleError> {
+        self.port
+            .write_all(bytes)
+            .and_then(|()| self.port.flush())
+            .map_err(VehicleError::Io)
+    }
+
+    fn read_u32(&mut self, deadline: Instant) -> Result<u32, VehicleError> {
+        let mut bytes = [0u8; 4];
+        for byte in &mut bytes {
+            *byte = self.read_byte(deadline)?;
+        }
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Read one byte, retrying read timeouts until `deadline`. A deadline
+    /// already passed allows a single read.
+    fn read_byte(&mut self, deadline: Instant) -> Result<u8, VehicleError> {
+        let mut byte = [0u8];
+        loop {
+            match self.port.read(&mut byte) {
+                Ok(1) => return Ok(byte[0]),
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {}
+                Err(err) => return Err(VehicleError::Io(err)),
+            }
+            if Instant::now() >= deadline {
+                return Err(VehicleError::Timeout);
+            }
+        }
+    }
+
+    fn get_sync(&mut self) -> Result<(), VehicleError> {
+        self.wait_for_sync(Instant::now())
+    }
+
+    /// `get_sync` for operations that take longer than one read timeout.
+    /// Bytes before `INSYNC` (the rest of a reply that timed out earlier, or
+    /// line noise) are skipped, as `uploader.py` does.
+    fn wait_for_sync(&mut self, deadline: Instant) -> Result<(), VehicleError> {
+        while self.read_byte(deadline)? != INSYNC {}
+        match self.read_byte(deadline)? {
+            OK => Ok(()),
+            INVALID => Err(VehicleError::Flash(
+                "bootloader rejected the request".into(),
+            )),
+            FAILED => Err(VehicleError::Flash(
+                "bootloader reports the operation failed".into(),
+            )),
+            BAD_SILICON_REV => Err(VehicleError::Flash(
+                "board has a silicon revision the bootloader cannot program".into(),
+            )),
+            status => Err(VehicleError::Flash(format!(
+                "unexpected status {status:#04x} after INSYNC"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replies with a scripted byte stream and records what was written.
+    struct ScriptedPort {
+        replies: io::Cursor<Vec<u8>>,
+        written: Vec<u8>,
+        /// Offset in `replies` where one read times out.
+        stall_at: Option<u64>,
+    }
+
+    impl ScriptedPort {
+        fn new(replies: &[u8]) -> Self {
+            Self {
+                replies: io::Cursor::new(replies.to_vec()),
+                written: Vec::new(),
+                stall_at: None,
+            }
+        }
+    }
+
+    impl Read for ScriptedPort {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.stall_at == Some(self.replies.position()) {
+                self.stall_at = None;
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for ScriptedPort {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn info(value: u32) -> Vec<u8> {
+        let mut reply = value.to_le_bytes().to_vec();
+        reply.extend_from_slice(&[INSYNC, OK]);
+        reply
+    }
+
+    #[test]
+    fn identify_reads_board_info() {
+        let replies = [info(5), info(140), info(0), info(2_064_384)].concat();
+        let mut bootloader = Bootloader::new(ScriptedPort::new(&replies));
+        let board = bootloader.identify().unwrap();
+        assert_eq!(
+            board,
+            BoardInfo {
+                bootloader_rev: 5,
+                board_id: 140,
+                board_rev: 0,
+                flash_size: 2_064_384,
+            }
+        );
+        assert_eq!(
+            &bootloader.port.written[..3],
+            &[GET_DEVICE, INFO_BL_REV, EOC]
+        );
+    }
+
+    #[test]
+    fn old_bootloader_is_rejected() {
+        let mut bootloader = Bootloader::new(ScriptedPort::new(&info(2)));
+        assert!(matches!(bootloader.identify(), Err(VehicleError::Flash(_))));
+    }
+
+    #[test]
+    fn program_frames_chunk_and_reports_failure() {
+        let mut bootloader = Bootloader::new(ScriptedPort::new(&[INSYNC, OK, INSYNC, FAILED]));
+        bootloader.program(&[1, 2, 3, 4]).unwrap();
+        assert_eq!(
+            bootloader.port.written,
+            vec![PROG_MULTI, 4, 1, 2, 3, 4, EOC]
+        );
+        assert!(matches!(
+            bootloader.program(&[5, 6, 7, 8]),
+            Err(VehicleError::Flash(_))
+        ));
+    }
+
+    #[test]
+    fn reply_split_by_a_timeout_is_resumed() {
+        let mut replies = 0xDEAD_BEEF_u32.to_le_bytes().to_vec();
+        replies.extend_from_slice(&[INSYNC, OK]);
+        let mut port = ScriptedPort::new(&replies);
+        port.stall_at = Some(2);
+        let mut bootloader = Bootloader::new(port);
+        assert_eq!(bootloader.crc().unwrap(), 0xDEAD_BEEF);
+
+        // Leftovers before INSYNC are skipped
+        let mut port = ScriptedPort::new(&[0x00, 0x55, INSYNC, OK]);
+        port.stall_at = Some(3);
+        let mut bootloader = Bootloader::new(port);
+        bootloader.erase().unwrap();
+    }
+
+    #[test]
+    fn silent_board_times_out() {
+        let mut bootloader = Bootloader::new(ScriptedPort::new(&[]));
+        assert!(matches!(bootloader.sync(), Err(VehicleError::Timeout)));
+        // A reboot reply may never come
+        assert!(bootloader.reboot().is_ok());
+    }
+}
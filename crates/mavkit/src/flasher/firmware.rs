@@ -0,0 +1,151 @@
+use crate::error::VehicleError;
+use base64::Engine;
+use serde::Deserialize;
+use std::io::Read;
+
+/// A firmware image from an ArduPilot `.apj` or PX4 `.px4` file.
+///
+/// Both are JSON documents carrying the target board id and the flash image,
+/// zlib-compressed and base64-encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Firmware {
+    pub board_id: u32,
+    pub board_revision: Option<u32>,
+    /// Board or vehicle name, e.g. "CubeOrange".
+    pub summary: Option<String>,
+    pub version: Option<String>,
+    pub git_identity: Option<String>,
+    /// Decompressed image, padded with 0xFF to a multiple of four bytes.
+    pub image: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct FirmwareFile {
+    board_id: u32,
+    #[serde(default)]
+    board_revision: Option<u32>,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    git_identity: Option<String>,
+    image: String,
+    #[serde(default)]
+    image_size: Option<usize>,
+}
+
+impl Firmware {
+    /// Parse the contents of an `.apj` or `.px4` file.
+    pub fn parse(contents: &[u8]) -> Result<Self, VehicleError> {
+        let invalid = |message: String| VehicleError::InvalidFirmware(message);
+        let file: FirmwareFile =
+            serde_json::from_slice(contents).map_err(|err| invalid(err.to_string()))?;
+        // Some tools wrap the base64 text across lines
+        let encoded: String = file.image.split_whitespace().collect();
+        let compressed = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|err| invalid(format!("image is not base64: {err}")))?;
+        let mut image = Vec::new();
+        flate2::read::ZlibDecoder::new(compressed.as_slice())
+            .read_to_end(&mut image)
+            .map_err(|err| invalid(format!("image is not zlib data: {err}")))?;
+        if image.is_empty() {
+            return Err(invalid("image is empty".into()));
+        }
+        if let Some(size) = file.image_size.filter(|size| *size != image.len()) {
+            return Err(invalid(format!(
+                "image is {} bytes, file header says {size}",
+                image.len()
+            )));
+        }
+        while !image.len().is_multiple_of(4) {
+            image.push(0xFF);
+        }
+        Ok(Self {
+            board_id: file.board_id,
+            board_revision: file.board_revision,
+            summary: file.summary,
+            version: file.version,
+            git_identity: file.git_identity,
+            image,
+        })
+    }
+
+    /// CRC the bootloader reports for a flash of `flash_size` bytes holding
+    /// this image, with the unused space erased to 0xFF.
+    pub fn crc(&self, flash_size: u32) -> u32 {
+        let mut state = crc32(0, &self.image);
+        let mut len = self.image.len();
+        while len < flash_size as usize {
+            state = crc32(state, &[0xFF; 4]);
+            len += 4;
+        }
+        state
+    }
+}
+
+/// The bootloader's CRC32: the zlib polynomial without the initial and
+/// final inversion.
+fn crc32(mut state: u32, bytes: &[u8]) -> u32 {
+    for &byte in bytes {
+        state ^= u32::from(byte);
+        for _ in 0..8 {
+            state = if state & 1 != 0 {
+                (state >> 1) ^ 0xEDB8_8320
+            } else {
+                state >> 1
+            };
+        }
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn apj(image: &[u8], extra: &str) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(image).unwrap();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap());
+        format!(r#"{{"board_id": 140, "summary": "CubeOrange", "image": "{encoded}"{extra}}}"#)
+            .into_bytes()
+    }
+
+    #[test]
+    fn parses_and_pads_image() {
+        let firmware = Firmware::parse(&apj(&[1, 2, 3, 4, 5], r#", "image_size": 5"#)).unwrap();
+        assert_eq!(firmware.board_id, 140);
+        assert_eq!(firmware.summary.as_deref(), Some("CubeOrange"));
+        assert_eq!(firmware.image, vec![1, 2, 3, 4, 5, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn rejects_size_mismatch_and_garbage() {
+        assert!(matches!(
+            Firmware::parse(&apj(&[1, 2, 3, 4], r#", "image_size": 8"#)),
+            Err(VehicleError::InvalidFirmware(_))
+        ));
+        assert!(Firmware::parse(b"not json").is_err());
+        assert!(Firmware::parse(br#"{"board_id": 9, "image": "!!"}"#).is_err());
+    }
+
+    #[test]
+    fn crc_matches_reference_and_covers_padding() {
+        // Same result as the table-driven version in the upload scripts
+        assert_eq!(crc32(0, b"123456789"), 0x2DFD_2D88);
+        let firmware = Firmware {
+            board_id: 9,
+            board_revision: None,
+            summary: None,
+            version: None,
+            git_identity: None,
+            image: vec![0xFF; 8],
+        };
+        assert_eq!(firmware.crc(8), crc32(0, &[0xFF; 8]));
+        assert_eq!(firmware.crc(16), crc32(0, &[0xFF; 16]));
+    }
+}
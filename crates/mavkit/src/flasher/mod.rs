@@ -0,0 +1,203 @@
+//! Firmware flashing through the PX4/ArduPilot serial bootloader.
+//!
+//! `flash` waits for the bootloader to answer on the port (the board is
+//! usually rebooted into it or replugged just before), checks that the image
+//! was built for this board, then erases, programs, verifies the flash CRC
+//! and starts the new firmware. Progress is published on a watch channel, as
+//! for mission and parameter transfers.
+//!
+//! The port is opened directly, not through a `Vehicle`; any MAVLink
+//! connection on the same port must be closed first.
+
+mod bootloader;
+pub mod firmware;
+
+pub use bootloader::BoardInfo;
+pub use firmware::Firmware;
+
+use crate::error::VehicleError;
+use bootloader::{Bootloader, PROG_CHUNK};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// USB bootloaders ignore the rate; UART ones expect this.
+const BOOTLOADER_BAUD: u32 = 115_200;
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+/// How long to keep trying to reach the bootloader.
+const SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+const RETRY_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashPhase {
+    #[default]
+    WaitingForBootloader,
+    Erasing,
+    Programming,
+    Verifying,
+    Rebooting,
+    Done,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FlashProgress {
+    pub phase: FlashPhase,
+    pub bytes_written: usize,
+    pub bytes_total: usize,
+    /// Known once the bootloader has answered.
+    pub board: Option<BoardInfo>,
+}
+
+/// Flash `firmware` to the board on `port`, returning what the bootloader
+/// reported about it.
+///
+/// Cancelling while programming leaves the board in its bootloader with an
+/// incomplete image; it can be flashed again.
+pub async fn flash(
+    port: &str,
+    firmware: Firmware,
+    progress: watch::Sender<FlashProgress>,
+    cancel: &CancellationToken,
+) -> Result<BoardInfo, VehicleError> {
+    let port = port.to_string();
+    let cancel = cancel.clone();
+    tokio::task::spawn_blocking(move || flash_blocking(&port, &firmware, &progress, &cancel))
+        .await
+        .map_err(|err| VehicleError::Flash(err.to_string()))?
+}
+
+fn flash_blocking(
+    port: &str,
+    firmware: &Firmware,
+    progress: &watch::Sender<FlashProgress>,
+    cancel: &CancellationToken,
+) -> Result<BoardInfo, VehicleError> {
+    let mut state = FlashProgress {
+        bytes_total: firmware.image.len(),
+        ..FlashProgress::default()
+    };
+    progress.send_replace(state.clone());
+
+    let mut bootloader = open_bootloader(port, cancel)?;
+    let board = bootloader.identify()?;
+    state.board = Some(board);
+    check_compatible(firmware, &board)?;
+
+    state.phase = FlashPhase::Erasing;
+    progress.send_replace(state.clone());
+    bootloader.erase()?;
+
+    state.phase = FlashPhase::Programming;
+    progress.send_replace(state.clone());
+    for chunk in firmware.image.chunks(PROG_CHUNK) {
+        if cancel.is_cancelled() {
+            return Err(VehicleError::Cancelled);
+        }
+        bootloader.program(chunk)?;
+        state.bytes_written += chunk.len();
+        progress.send_replace(state.clone());
+    }
+
+    state.phase = FlashPhase::Verifying;
+    progress.send_replace(state.clone());
+    let crc = bootloader.crc()?;
+    let expected = firmware.crc(board.flash_size);
+    if crc != expected {
+        return Err(VehicleError::Flash(format!(
+            "verification failed: board CRC {crc:#010x}, expected {expected:#010x}"
+        )));
+    }
+
+    state.phase = FlashPhase::Rebooting;
+    progress.send_replace(state.clone());
+    bootloader.reboot()?;
+
+    state.phase = FlashPhase::Done;
+    progress.send_replace(state);
+    Ok(board)
+}
+
+/// Open `port` and sync with the bootloader, retrying until it appears.
+fn open_bootloader(
+    port: &str,
+    cancel: &CancellationToken,
+) -> Result<Bootloader<Box<dyn serialport::SerialPort>>, VehicleError> {
+    let deadline = Instant::now() + SYNC_TIMEOUT;
+    loop {
+        if cancel.is_cancelled() {
+            return Err(VehicleError::Cancelled);
+        }
+        // The port disappears while the board re-enumerates, so open errors
+        // are retried as well
+        let opened = serialport::new(port, BOOTLOADER_BAUD)
+            .timeout(READ_TIMEOUT)
+            .open();
+        if let Ok(serial) = opened {
+            let _ = serial.clear(serialport::ClearBuffer::Input);
+            let mut bootloader = Bootloader::new(serial);
+            if bootloader.sync().is_ok() {
+                return Ok(bootloader);
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(VehicleError::Flash(format!(
+                "no bootloader answered on {port}"
+            )));
+        }
+        std::thread::sleep(RETRY_INTERVAL);
+    }
+}
+
+fn check_compatible(firmware: &Firmware, board: &BoardInfo) -> Result<(), VehicleError> {
+    if firmware.board_id != board.board_id {
+        return Err(VehicleError::InvalidFirmware(format!(
+            "firmware is for board id {}, this board is {}",
+            firmware.board_id, board.board_id
+        )));
+    }
+    if firmware.image.len() > board.flash_size as usize {
+        return Err(VehicleError::InvalidFirmware(format!(
+            "image is {} bytes, the board has {} bytes of flash",
+            firmware.image.len(),
+            board.flash_size
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn firmware(board_id: u32, len: usize) -> Firmware {
+        Firmware {
+            board_id,
+            board_revision: None,
+            summary: None,
+            version: None,
+            git_identity: None,
+            image: vec![0; len],
+        }
+    }
+
+    #[test]
+    fn rejects_other_boards_and_oversized_images() {
+        let board = BoardInfo {
+            bootloader_rev: 5,
+            board_id: 140,
+            board_rev: 0,
+            flash_size: 1024,
+        };
+        assert!(check_compatible(&firmware(140, 1024), &board).is_ok());
+        assert!(matches!(
+            check_compatible(&firmware(9, 512), &board),
+            Err(VehicleError::InvalidFirmware(_))
+        ));
+        assert!(matches!(
+            check_compatible(&firmware(140, 2048), &board),
+            Err(VehicleError::InvalidFirmware(_))
+        ));
+    }
+}
@@ -0,0 +1,411 @@
+//! Multi-vehicle command fan-out.
+//!
+//! A [`Fleet`] holds named vehicle connections and sends one command to all
+//! of them concurrently. Every member's outcome is kept, so one vehicle
+//! refusing to arm does not hide which others did, and the running tally is
+//! published on [`Fleet::progress`].
+
+use crate::error::VehicleError;
+use crate::mission::MissionPlan;
+use crate::odometer::FlightTotals;
+use crate::state::Telemetry;
+use crate::vehicle::Vehicle;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+/// Progress of the most recent fleet operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FleetProgress {
+    pub operation: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+impl FleetProgress {
+    pub fn is_done(&self) -> bool {
+        self.succeeded + self.failed >= self.total
+    }
+}
+
+/// A member's flight timer and odometer.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemberFlightTotals {
+    pub name: String,
+    /// Since connecting.
+    pub session: FlightTotals,
+    /// Over every session of the airframe, when its flight log is kept.
+    pub lifetime: Option<FlightTotals>,
+}
+
+/// Outcome of an operation on one member.
+#[derive(Debug)]
+pub struct MemberResult<T> {
+    pub name: String,
+    pub result: Result<T, VehicleError>,
+}
+
+/// Per-member outcomes of a fleet operation, in fleet order.
+#[derive(Debug)]
+pub struct FleetResult<T> {
+    pub results: Vec<MemberResult<T>>,
+}
+
+impl<T> FleetResult<T> {
+    pub fn all_ok(&self) -> bool {
+        self.results.iter().all(|member| member.result.is_ok())
+    }
+
+    /// Members whose operation failed, with the error.
+    pub fn failures(&self) -> impl Iterator<Item = (&str, &VehicleError)> {
+        self.results
+            .iter()
+            .filter_map(|member| match &member.result {
+                Ok(_) => None,
+                Err(err) => Some((member.name.as_str(), err)),
+            })
+    }
+}
+
+/// Named set of vehicle connections.
+#[derive(Clone)]
+pub struct Fleet {
+    members: Vec<(String, Vehicle)>,
+    progress: Arc<watch::Sender<FleetProgress>>,
+}
+
+impl Default for Fleet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fleet {
+    pub fn new() -> Self {
+        Self::from_members(Vec::new())
+    }
+
+    fn from_members(members: Vec<(String, Vehicle)>) -> Self {
+        let (progress, _) = watch::channel(FleetProgress::default());
+        Self {
+            members,
+            progress: Arc::new(progress),
+        }
+    }
+
+    /// Add a vehicle, replacing (and returning) any member with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, vehicle: Vehicle) -> Option<Vehicle> {
+        let name = name.into();
+        match self.members.iter_mut().find(|(member, _)| *member == name) {
+            Some((_, existing)) => Some(std::mem::replace(existing, vehicle)),
+            None => {
+                self.members.push((name, vehicle));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Vehicle> {
+        let index = self.members.iter().position(|(member, _)| member == name)?;
+        Some(self.members.remove(index).1)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Vehicle> {
+        self.members
+            .iter()
+            .find(|(member, _)| member == name)
+            .map(|(_, vehicle)| vehicle)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// A fleet of just the named members, in the given order. Unknown names
+    /// are skipped. The subset shares the connections but reports its own
+    /// progress.
+    pub fn select(&self, names: &[&str]) -> Fleet {
+        Self::from_members(
+            names
+                .iter()
+                .filter_map(|name| Some((name.to_string(), self.get(name)?.clone())))
+                .collect(),
+        )
+    }
+
+    /// Armed time and distance flown of every member, for logging airframe
+    /// hours across the fleet.
+    pub fn flight_totals(&self) -> Vec<MemberFlightTotals> {
+        self.members
+            .iter()
+            .map(|(name, vehicle)| member_flight_totals(name, &vehicle.telemetry().borrow()))
+            .collect()
+    }
+
+    pub fn progress(&self) -> watch::Receiver<FleetProgress> {
+        self.progress.subscribe()
+    }
+
+    /// Run `command` on every member concurrently. The closure gets the
+    /// member's position in the fleet and its vehicle.
+    pub async fn broadcast<T, F, Fut>(&self, operation: &str, command: F) -> FleetResult<T>
+    where
+        T: Send + 'static,
+        F: Fn(usize, Vehicle) -> Fut,
+        Fut: Future<Output = Result<T, VehicleError>> + Send + 'static,
+    {
+        fan_out(&self.members, &self.progress, operation, command).await
+    }
+
+    pub async fn arm(&self, force: bool) -> FleetResult<()> {
+        self.broadcast(
+            "arm",
+            move |_, vehicle| async move { vehicle.arm(force).await },
+        )
+        .await
+    }
+
+    pub async fn disarm(&self, force: bool) -> FleetResult<()> {
+        self.broadcast("disarm", move |_, vehicle| async move {
+            vehicle.disarm(force).await
+        })
+        .await
+    }
+
+    pub async fn set_mode_by_name(&self, mode: &str) -> FleetResult<()> {
+        self.broadcast("set_mode", |_, vehicle| {
+            let mode = mode.to_string();
+            async move { vehicle.set_mode_by_name(&mode).await }
+        })
+        .await
+    }
+
+    /// Take off to `altitude_m`, member `i` after `i * stagger`. A zero
+    /// stagger launches everyone together.
+    pub async fn takeoff(&self, altitude_m: f32, stagger: Duration) -> FleetResult<()> {
+        self.broadcast("takeoff", move |index, vehicle| async move {
+            tokio::time::sleep(stagger * index as u32).await;
+            vehicle.takeoff(altitude_m).await
+        })
+        .await
+    }
+
+    /// Upload one plan per member, matched by name. Members without a plan
+    /// are left alone.
+    pub async fn upload_missions(&self, plans: &[(&str, MissionPlan)]) -> FleetResult<()> {
+        let selected: Vec<&str> = plans.iter().map(|(name, _)| *name).collect();
+        let fleet = self.select(&selected);
+        let plans: Vec<MissionPlan> = plans
+            .iter()
+            .filter(|(name, _)| self.get(name).is_some())
+            .map(|(_, plan)| plan.clone())
+            .collect();
+        fan_out(
+            &fleet.members,
+            &self.progress,
+            "upload_mission",
+            |index, vehicle| {
+                let plan = plans[index].clone();
+                async move { vehicle.mission().upload(plan).await }
+            },
+        )
+        .await
+    }
+}
+
+fn member_flight_totals(name: &str, telemetry: &Telemetry) -> MemberFlightTotals {
+    let session = FlightTotals {
+        armed_time_s: telemetry.armed_time_s.unwrap_or(0.0),
+        distance_m: telemetry.distance_flown_m.unwrap_or(0.0),
+        flights: telemetry.flights.unwrap_or(0),
+    };
+    let lifetime = match (
+        telemetry.total_armed_time_s,
+        telemetry.total_distance_flown_m,
+        telemetry.total_flights,
+    ) {
+        (Some(armed_time_s), Some(distance_m), Some(flights)) => Some(FlightTotals {
+            armed_time_s,
+            distance_m,
+            flights,
+        }),
+        _ => None,
+    };
+    MemberFlightTotals {
+        name: name.to_string(),
+        session,
+        lifetime,
+    }
+}
+
+async fn fan_out<M, T, F, Fut>(
+    members: &[(String, M)],
+    progress: &watch::Sender<FleetProgress>,
+    operation: &str,
+    command: F,
+) -> FleetResult<T>
+where
+    M: Clone,
+    T: Send + 'static,
+    F: Fn(usize, M) -> Fut,
+    Fut: Future<Output = Result<T, VehicleError>> + Send + 'static,
+{
+    progress.send_replace(FleetProgress {
+        operation: operation.to_string(),
+        total: members.len(),
+        succeeded: 0,
+        failed: 0,
+    });
+    let mut tasks = JoinSet::new();
+    for (index, (_, member)) in members.iter().enumerate() {
+        let task = command(index, member.clone());
+        tasks.spawn(async move { (index, task.await) });
+    }
+
+    let mut results: Vec<Option<Result<T, VehicleError>>> = members.iter().map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        let (index, result) = match joined {
+            Ok(done) => done,
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(_) => continue,
+        };
+        progress.send_modify(|progress| {
+            if result.is_ok() {
+                progress.succeeded += 1;
+            } else {
+                progress.failed += 1;
+            }
+        });
+        results[index] = Some(result);
+    }
+
+    FleetResult {
+        results: members
+            .iter()
+            .zip(results)
+            .map(|((name, _), result)| MemberResult {
+                name: name.clone(),
+                result: result.unwrap_or(Err(VehicleError::Cancelled)),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn run<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn members(count: u32) -> Vec<(String, u32)> {
+        (0..count).map(|i| (format!("uav{i}"), i)).collect()
+    }
+
+    #[test]
+    fn keeps_every_member_result_in_fleet_order() {
+        let (progress, rx) = watch::channel(FleetProgress::default());
+        let result = run(fan_out(&members(3), &progress, "arm", |_, id| async move {
+            // Finish in reverse order
+            tokio::time::sleep(Duration::from_millis(u64::from(3 - id) * 10)).await;
+            if id == 1 {
+                Err(VehicleError::CommandRejected {
+                    command: "arm".into(),
+                    result: "denied".into(),
+                })
+            } else {
+                Ok(id)
+            }
+        }));
+
+        let names: Vec<_> = result
+            .results
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect();
+        assert_eq!(names, ["uav0", "uav1", "uav2"]);
+        assert_eq!(*result.results[2].result.as_ref().unwrap(), 2);
+        assert!(!result.all_ok());
+        let failed: Vec<_> = result.failures().map(|(name, _)| name).collect();
+        assert_eq!(failed, ["uav1"]);
+
+        let done = rx.borrow().clone();
+        assert_eq!(
+            done,
+            FleetProgress {
+                operation: "arm".into(),
+                total: 3,
+                succeeded: 2,
+                failed: 1,
+            }
+        );
+        assert!(done.is_done());
+    }
+
+    #[test]
+    fn flight_totals_need_the_whole_lifetime() {
+        let session_only = Telemetry {
+            armed_time_s: Some(90.0),
+            distance_flown_m: Some(400.0),
+            flights: Some(1),
+            ..Telemetry::default()
+        };
+        let member = member_flight_totals("uav0", &session_only);
+        assert_eq!(member.session.armed_time_s, 90.0);
+        assert_eq!(member.session.flights, 1);
+        assert_eq!(member.lifetime, None);
+
+        let logged = Telemetry {
+            total_armed_time_s: Some(3690.0),
+            total_distance_flown_m: Some(12_400.0),
+            total_flights: Some(12),
+            ..session_only
+        };
+        let lifetime = member_flight_totals("uav0", &logged).lifetime.unwrap();
+        assert_eq!(lifetime.flights, 12);
+        assert_eq!(lifetime.distance_m, 12_400.0);
+    }
+
+    #[test]
+    fn members_run_concurrently_with_stagger() {
+        let (progress, _) = watch::channel(FleetProgress::default());
+        let started = Instant::now();
+        let result = run(fan_out(
+            &members(4),
+            &progress,
+            "takeoff",
+            |index, _| async move {
+                tokio::time::sleep(Duration::from_millis(20) * index as u32).await;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(started.elapsed())
+            },
+        ));
+        assert!(result.all_ok());
+        let finished: Vec<Duration> = result
+            .results
+            .into_iter()
+            .map(|member| member.result.unwrap())
+            .collect();
+        assert!(finished.windows(2).all(|pair| pair[0] < pair[1]));
+        // Sequential would take 4 * 50 ms plus the stagger
+        assert!(started.elapsed() < Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,191 @@
+//! Other ground stations seen on the link.
+//!
+//! Any HEARTBEAT with `MAV_TYPE_GCS` is another operator station (or a
+//! companion app acting as one). Peers are listed until their heartbeat has
+//! been silent for `GCS_PEER_TIMEOUT`; a peer using our own system id is
+//! flagged as a conflict, since the vehicle cannot tell our commands apart.
+
+use crate::setup::{FailsafeAction, FailsafeConfig};
+use crate::state::{Telemetry, VehicleState};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// A peer is dropped once its heartbeat has been silent this long.
+pub const GCS_PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GcsPeer {
+    pub system_id: u8,
+    pub component_id: u8,
+    /// The peer uses our GCS system id.
+    pub conflicts: bool,
+    #[serde(skip)]
+    pub(crate) last_seen: Option<Instant>,
+}
+
+/// Record a GCS heartbeat. Returns true when the peer list or a conflict
+/// flag changed (timestamp refreshes alone do not count).
+pub(crate) fn observe_gcs_heartbeat(
+    peers: &mut Vec<GcsPeer>,
+    system_id: u8,
+    component_id: u8,
+    own_system_id: u8,
+    now: Instant,
+) -> bool {
+    let conflicts = system_id == own_system_id;
+    match peers
+        .iter_mut()
+        .find(|p| p.system_id == system_id && p.component_id == component_id)
+    {
+        Some(peer) => {
+            peer.last_seen = Some(now);
+            let changed = peer.conflicts != conflicts;
+            peer.conflicts = conflicts;
+            changed
+        }
+        None => {
+            peers.push(GcsPeer {
+                system_id,
+                component_id,
+                conflicts,
+                last_seen: Some(now),
+            });
+            peers.sort_by_key(|p| (p.system_id, p.component_id));
+            true
+        }
+    }
+}
+
+/// Drop peers not heard from within `GCS_PEER_TIMEOUT`. Returns true if any were removed.
+pub(crate) fn prune_gcs_peers(peers: &mut Vec<GcsPeer>, now: Instant) -> bool {
+    let before = peers.len();
+    peers.retain(|p| {
+        p.last_seen
+            .is_some_and(|seen| now.saturating_duration_since(seen) < GCS_PEER_TIMEOUT)
+    });
+    peers.len() != before
+}
+
+/// Re-evaluate conflict flags after our own system id changed.
+pub(crate) fn refresh_gcs_conflicts(peers: &mut [GcsPeer], own_system_id: u8) -> bool {
+    let mut changed = false;
+    for peer in peers {
+        let conflicts = peer.system_id == own_system_id;
+        changed |= peer.conflicts != conflicts;
+        peer.conflicts = conflicts;
+    }
+    changed
+}
+
+/// Why closing the ground station now could trigger the vehicle's GCS
+/// failsafe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShutdownWarning {
+    /// What the vehicle does once our heartbeat stops; `None` when the
+    /// failsafe parameters have not been downloaded.
+    pub gcs_loss_action: Option<FailsafeAction>,
+    pub gcs_loss_timeout_s: Option<f32>,
+    pub message: String,
+}
+
+/// A warning when the vehicle is armed and airborne and its GCS failsafe is
+/// not known to be off.
+pub(crate) fn shutdown_warning(
+    state: &VehicleState,
+    telemetry: &Telemetry,
+    failsafe: &FailsafeConfig,
+) -> Option<ShutdownWarning> {
+    if !state.armed || !telemetry.is_airborne() {
+        return None;
+    }
+    let message = match failsafe.gcs_loss_action {
+        Some(FailsafeAction::Disabled) => return None,
+        Some(_) => "vehicle is armed and airborne with the GCS failsafe enabled",
+        None => "vehicle is armed and airborne and its GCS failsafe setting is unknown",
+    };
+    Some(ShutdownWarning {
+        gcs_loss_action: failsafe.gcs_loss_action,
+        gcs_loss_timeout_s: failsafe.gcs_loss_timeout_s,
+        message: message.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_peer_is_reported_once() {
+        let now = Instant::now();
+        let mut peers = Vec::new();
+        assert!(observe_gcs_heartbeat(&mut peers, 254, 190, 255, now));
+        assert!(!observe_gcs_heartbeat(
+            &mut peers,
+            254,
+            190,
+            255,
+            now + Duration::from_secs(1)
+        ));
+        assert_eq!(peers.len(), 1);
+        assert!(!peers[0].conflicts);
+    }
+
+    #[test]
+    fn same_sysid_is_a_conflict() {
+        let now = Instant::now();
+        let mut peers = Vec::new();
+        observe_gcs_heartbeat(&mut peers, 255, 0, 255, now);
+        assert!(peers[0].conflicts);
+
+        // Moving ourselves to another sysid clears it
+        assert!(refresh_gcs_conflicts(&mut peers, 250));
+        assert!(!peers[0].conflicts);
+        assert!(!refresh_gcs_conflicts(&mut peers, 250));
+    }
+
+    #[test]
+    fn silent_peers_expire() {
+        let now = Instant::now();
+        let mut peers = Vec::new();
+        observe_gcs_heartbeat(&mut peers, 254, 190, 255, now);
+        observe_gcs_heartbeat(&mut peers, 253, 190, 255, now + Duration::from_secs(4));
+
+        assert!(!prune_gcs_peers(&mut peers, now + Duration::from_secs(3)));
+        assert!(prune_gcs_peers(&mut peers, now + Duration::from_secs(6)));
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].system_id, 253);
+    }
+
+    #[test]
+    fn warns_only_when_airborne_with_the_failsafe_possibly_on() {
+        let armed = VehicleState {
+            armed: true,
+            ..VehicleState::default()
+        };
+        let flying = Telemetry {
+            landed_state: Some(crate::state::LandedState::InAir),
+            ..Telemetry::default()
+        };
+        let on_ground = Telemetry {
+            landed_state: Some(crate::state::LandedState::OnGround),
+            ..Telemetry::default()
+        };
+        let rtl = FailsafeConfig {
+            gcs_loss_action: Some(FailsafeAction::Rtl),
+            gcs_loss_timeout_s: Some(5.0),
+            ..FailsafeConfig::default()
+        };
+        let warning = shutdown_warning(&armed, &flying, &rtl).unwrap();
+        assert_eq!(warning.gcs_loss_action, Some(FailsafeAction::Rtl));
+        assert_eq!(warning.gcs_loss_timeout_s, Some(5.0));
+        assert!(shutdown_warning(&armed, &flying, &FailsafeConfig::default()).is_some());
+
+        let disabled = FailsafeConfig {
+            gcs_loss_action: Some(FailsafeAction::Disabled),
+            ..FailsafeConfig::default()
+        };
+        assert!(shutdown_warning(&armed, &flying, &disabled).is_none());
+        assert!(shutdown_warning(&armed, &on_ground, &rtl).is_none());
+        assert!(shutdown_warning(&VehicleState::default(), &flying, &rtl).is_none());
+    }
+}
@@ -0,0 +1,141 @@
+//! Turning the autopilot's geofence on and off.
+//!
+//! An uploaded fence does nothing until the fence is enabled, and forgetting
+//! to enable it is an easy mistake to make. `FenceHandle::enable` sends
+//! MAV_CMD_DO_FENCE_ENABLE and, when enabling, waits for FENCE_STATUS to
+//! confirm the autopilot is checking the fence; ArduPilot only reports it
+//! while the fence is enabled. `UploadOptions::enable_fence` does the same
+//! after a fence upload.
+
+use crate::error::VehicleError;
+use crate::Vehicle;
+use mavlink::ardupilotmega::{self as common, MavCmd};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const FENCE_STATUS_MSG_ID: f32 = 162.0;
+/// How long `enable` waits for FENCE_STATUS after the command is accepted.
+const FENCE_STATUS_WAIT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FenceBreachType {
+    #[default]
+    None,
+    MinAltitude,
+    MaxAltitude,
+    Boundary,
+}
+
+impl FenceBreachType {
+    pub(crate) fn from_mav(breach: common::FenceBreach) -> Self {
+        match breach {
+            common::FenceBreach::FENCE_BREACH_NONE => FenceBreachType::None,
+            common::FenceBreach::FENCE_BREACH_MINALT => FenceBreachType::MinAltitude,
+            common::FenceBreach::FENCE_BREACH_MAXALT => FenceBreachType::MaxAltitude,
+            common::FenceBreach::FENCE_BREACH_BOUNDARY => FenceBreachType::Boundary,
+        }
+    }
+}
+
+/// The latest FENCE_STATUS from the vehicle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FenceStatus {
+    /// The vehicle is outside the fence right now.
+    pub breached: bool,
+    /// Breaches since boot.
+    pub breach_count: u16,
+    /// Kind of the last breach.
+    pub breach_type: FenceBreachType,
+    /// Vehicle time of the last breach.
+    pub breach_time_ms: u32,
+}
+
+impl FenceStatus {
+    pub(crate) fn from_mav(data: &common::FENCE_STATUS_DATA) -> Self {
+        Self {
+            breached: data.breach_status != 0,
+            breach_count: data.breach_count,
+            breach_type: FenceBreachType::from_mav(data.breach_type),
+            breach_time_ms: data.breach_time,
+        }
+    }
+}
+
+/// Handle to the geofence of a `Vehicle`.
+pub struct FenceHandle<'a> {
+    vehicle: &'a Vehicle,
+}
+
+impl<'a> FenceHandle<'a> {
+    pub(crate) fn new(vehicle: &'a Vehicle) -> Self {
+        Self { vehicle }
+    }
+
+    /// Enable or disable the fence. Enabling succeeds only once a
+    /// FENCE_STATUS arrives after the command was accepted, and returns it;
+    /// a fence that is enabled with the vehicle already outside it is
+    /// reported as `breached`. Disabling returns `None`.
+    pub async fn enable(&self, enable: bool) -> Result<Option<FenceStatus>, VehicleError> {
+        let mut status_rx = self.vehicle.fence_status();
+        status_rx.borrow_and_update();
+        self.vehicle
+            .command_long(
+                MavCmd::MAV_CMD_DO_FENCE_ENABLE,
+                [if enable { 1.0 } else { 0.0 }, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            )
+            .await?;
+        if !enable {
+            return Ok(None);
+        }
+
+        // Autopilots that refuse the request still stream it
+        let _ = self
+            .vehicle
+            .command_long(
+                MavCmd::MAV_CMD_REQUEST_MESSAGE,
+                [FENCE_STATUS_MSG_ID, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            )
+            .await;
+        let next_status = async {
+            loop {
+                status_rx
+                    .changed()
+                    .await
+                    .map_err(|_| VehicleError::Disconnected)?;
+                // Cleared when the vehicle reboots
+                if let Some(status) = *status_rx.borrow_and_update() {
+                    return Ok(Some(status));
+                }
+            }
+        };
+        tokio::time::timeout(FENCE_STATUS_WAIT, next_status)
+            .await
+            .map_err(|_| VehicleError::FenceNotEnabled)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breach_is_read_from_fence_status() {
+        let data = common::FENCE_STATUS_DATA {
+            breach_time: 120_500,
+            breach_count: 2,
+            breach_status: 1,
+            breach_type: common::FenceBreach::FENCE_BREACH_MAXALT,
+            ..Default::default()
+        };
+        assert_eq!(
+            FenceStatus::from_mav(&data),
+            FenceStatus {
+                breached: true,
+                breach_count: 2,
+                breach_type: FenceBreachType::MaxAltitude,
+                breach_time_ms: 120_500,
+            }
+        );
+    }
+}
@@ -0,0 +1,715 @@
+use crate::error::VehicleError;
+use crate::mission::builder::{global_item, resequence, MAV_CMD_NAV_WAYPOINT};
+use crate::mission::geo::{bearing_deg, destination, distance_m};
+use crate::mission::{
+    deg_to_e7, fence_breaches, path_clearance, Fence, HomePosition, MissionPlan, MissionType,
+    PathClearance, TerrainSource,
+};
+use crate::state::{AutopilotType, Telemetry, VehicleType};
+use crate::Vehicle;
+use mavlink::ardupilotmega::{MavCmd, MavFrame};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+use tracing::warn;
+
+/// Guided targets are snapped to multiples of this altitude band.
+pub const GOTO_ALT_BAND_M: f32 = 5.0;
+/// Proposals with less estimated terrain clearance carry a warning.
+pub const MIN_TERRAIN_CLEARANCE_M: f64 = 10.0;
+/// How long a proposal can be confirmed after it was made.
+pub const GOTO_PROPOSAL_TTL: Duration = Duration::from_secs(30);
+/// Speed assumed for the ETA when the vehicle is (nearly) stationary.
+const DEFAULT_GOTO_SPEED_MPS: f64 = 5.0;
+
+/// A computed guided target awaiting confirmation via `Vehicle::confirm_goto`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GotoProposal {
+    pub token: u64,
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    /// Snapped target altitude, relative to home.
+    pub altitude_m: f32,
+    /// Horizontal distance from the current position, if known.
+    pub distance_m: Option<f64>,
+    pub eta_s: Option<f64>,
+    /// Estimated clearance above the terrain currently under the vehicle.
+    pub terrain_clearance_m: Option<f64>,
+    /// Lowest clearance along the straight path to the target, when a
+    /// terrain source is set (see `Vehicle::set_terrain`).
+    #[serde(default)]
+    pub path_clearance_m: Option<f64>,
+    pub warnings: Vec<String>,
+}
+
+/// What a guided goto does when the straight path to the target passes
+/// less than `MIN_TERRAIN_CLEARANCE_M` above the terrain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerrainCheck {
+    Off,
+    /// Fly anyway and log a warning.
+    #[default]
+    Warn,
+    /// Refuse the goto with `VehicleError::TerrainConflict`.
+    Refuse,
+}
+
+/// Snap `alt_m` to the nearest altitude band, never below the first band.
+pub fn snap_altitude(alt_m: f32) -> f32 {
+    ((alt_m / GOTO_ALT_BAND_M).round() * GOTO_ALT_BAND_M).max(GOTO_ALT_BAND_M)
+}
+
+/// Compute a goto proposal from the current telemetry. `alt_m` defaults to
+/// the current altitude.
+pub(crate) fn plan_goto(
+    token: u64,
+    telemetry: &Telemetry,
+    home: Option<&HomePosition>,
+    terrain: Option<&dyn TerrainSource>,
+    lat_deg: f64,
+    lon_deg: f64,
+    alt_m: Option<f32>,
+) -> GotoProposal {
+    let requested_alt = alt_m
+        .or(telemetry.altitude_m.map(|a| a as f32))
+        .unwrap_or(GOTO_ALT_BAND_M);
+    let altitude_m = snap_altitude(requested_alt);
+
+    let distance = match (telemetry.latitude_deg, telemetry.longitude_deg) {
+        (Some(lat), Some(lon)) => Some(distance_m(lat, lon, lat_deg, lon_deg)),
+        _ => None,
+    };
+    let speed = telemetry
+        .speed_mps
+        .filter(|s| *s > 1.0)
+        .unwrap_or(DEFAULT_GOTO_SPEED_MPS);
+    let eta_s = distance.map(|d| d / speed);
+
+    // TERRAIN_REPORT gives terrain AMSL under the vehicle; goto altitude is
+    // relative to home
+    let home_amsl = home.and_then(|home| home.altitude_amsl_m(None));
+    let terrain_clearance_m = match (home_amsl, telemetry.terrain_height_m) {
+        (Some(home_amsl), Some(terrain)) => Some(home_amsl as f64 + altitude_m as f64 - terrain),
+        _ => None,
+    };
+
+    let mut warnings = Vec::new();
+    if (altitude_m - requested_alt).abs() > f32::EPSILON {
+        warnings.push(format!(
+            "altitude snapped from {requested_alt:.1} m to {altitude_m:.0} m"
+        ));
+    }
+    if distance.is_none() {
+        warnings.push("vehicle position unknown".to_string());
+    }
+    match terrain_clearance_m {
+        Some(clearance) if clearance < MIN_TERRAIN_CLEARANCE_M => warnings.push(format!(
+            "estimated terrain clearance {clearance:.0} m is below {MIN_TERRAIN_CLEARANCE_M:.0} m"
+        )),
+        None => warnings.push("terrain clearance unknown".to_string()),
+        _ => {}
+    }
+    let path = terrain.and_then(|terrain| {
+        goto_path_clearance(telemetry, home, terrain, lat_deg, lon_deg, altitude_m)
+    });
+    if let Some(warning) = path.as_ref().and_then(path_warning) {
+        warnings.push(warning);
+    }
+
+    GotoProposal {
+        token,
+        latitude_deg: lat_deg,
+        longitude_deg: lon_deg,
+        altitude_m,
+        distance_m: distance,
+        eta_s,
+        terrain_clearance_m,
+        path_clearance_m: path.map(|path| path.min_clearance_m),
+        warnings,
+    }
+}
+
+/// Lowest terrain clearance on the straight line from the vehicle to a goto
+/// target `alt_m` above home. `None` without the vehicle's AMSL position, the
+/// home altitude or terrain data.
+pub(crate) fn goto_path_clearance(
+    telemetry: &Telemetry,
+    home: Option<&HomePosition>,
+    terrain: &dyn TerrainSource,
+    lat_deg: f64,
+    lon_deg: f64,
+    alt_m: f32,
+) -> Option<PathClearance> {
+    let from = (
+        telemetry.latitude_deg?,
+        telemetry.longitude_deg?,
+        telemetry.altitude_amsl_m?,
+    );
+    let target_amsl = home?.altitude_amsl_m(None)? as f64 + alt_m as f64;
+    path_clearance(from, (lat_deg, lon_deg, target_amsl), terrain)
+}
+
+/// Why `path` is too close to the terrain, if it is.
+pub(crate) fn path_warning(path: &PathClearance) -> Option<String> {
+    (path.min_clearance_m < MIN_TERRAIN_CLEARANCE_M).then(|| {
+        format!(
+            "path to the target passes {:.0} m above terrain {:.0} m along the way",
+            path.min_clearance_m, path.along_m
+        )
+    })
+}
+
+/// Bounds on a single nudge; larger requests are clamped to these.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NudgeLimits {
+    pub max_step_m: f64,
+    pub max_alt_step_m: f32,
+}
+
+impl Default for NudgeLimits {
+    fn default() -> Self {
+        Self {
+            max_step_m: 20.0,
+            max_alt_step_m: 10.0,
+        }
+    }
+}
+
+/// Direction of a horizontal nudge, relative to the vehicle's heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NudgeDirection {
+    Forward,
+    Back,
+    Left,
+    Right,
+}
+
+impl NudgeDirection {
+    fn offset_deg(self) -> f64 {
+        match self {
+            NudgeDirection::Forward => 0.0,
+            NudgeDirection::Right => 90.0,
+            NudgeDirection::Back => 180.0,
+            NudgeDirection::Left => 270.0,
+        }
+    }
+}
+
+/// Guided target `distance_m` (clamped to the step limit) from the current
+/// position at the current altitude. Refused when the target or the way there
+/// breaches `fence`.
+pub(crate) fn plan_nudge(
+    telemetry: &Telemetry,
+    fence: Option<&Fence>,
+    limits: &NudgeLimits,
+    direction: NudgeDirection,
+    distance_m: f64,
+) -> Result<(f64, f64, f32), VehicleError> {
+    let (Some(lat), Some(lon), Some(alt)) = (
+        telemetry.latitude_deg,
+        telemetry.longitude_deg,
+        telemetry.altitude_m,
+    ) else {
+        return Err(VehicleError::NudgeRefused(
+            "vehicle position unknown".to_string(),
+        ));
+    };
+    let step = distance_m.abs().min(limits.max_step_m);
+    // Without a heading, forward is north
+    let bearing = telemetry.heading_deg.unwrap_or(0.0) + direction.offset_deg();
+    let (target_lat, target_lon) = destination(lat, lon, bearing, step);
+    let alt_m = alt as f32;
+
+    if let Some(fence) = fence {
+        let mut leg = MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items: vec![
+                global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, alt_m),
+                global_item(MAV_CMD_NAV_WAYPOINT, target_lat, target_lon, alt_m),
+            ],
+            metadata: Default::default(),
+        };
+        resequence(&mut leg.items);
+        // Only the target and the leg to it; the vehicle may already be
+        // outside the fence and nudging back in
+        if fence_breaches(&leg, fence)
+            .iter()
+            .any(|breach| breach.seq == 1)
+        {
+            return Err(VehicleError::NudgeRefused(
+                "target breaches the geofence".to_string(),
+            ));
+        }
+    }
+    Ok((target_lat, target_lon, alt_m))
+}
+
+/// Guided target `delta_m` (clamped to the step limit) above or below the
+/// current position. A descent is refused when it would leave less than
+/// `MIN_TERRAIN_CLEARANCE_M` above the terrain under the vehicle.
+pub(crate) fn plan_altitude_change(
+    telemetry: &Telemetry,
+    home: Option<&HomePosition>,
+    limits: &NudgeLimits,
+    delta_m: f32,
+) -> Result<(f64, f64, f32), VehicleError> {
+    let (Some(lat), Some(lon), Some(alt)) = (
+        telemetry.latitude_deg,
+        telemetry.longitude_deg,
+        telemetry.altitude_m,
+    ) else {
+        return Err(VehicleError::NudgeRefused(
+            "vehicle position unknown".to_string(),
+        ));
+    };
+    let step = delta_m.clamp(-limits.max_alt_step_m, limits.max_alt_step_m);
+    let alt_m = alt as f32 + step;
+
+    if step < 0.0 {
+        let home_amsl = home.and_then(|home| home.altitude_amsl_m(None));
+        if let (Some(home_amsl), Some(terrain)) = (home_amsl, telemetry.terrain_height_m) {
+            let clearance = home_amsl as f64 + alt_m as f64 - terrain;
+            if clearance < MIN_TERRAIN_CLEARANCE_M {
+                return Err(VehicleError::NudgeRefused(format!(
+                    "terrain clearance would drop to {clearance:.0} m"
+                )));
+            }
+        }
+    }
+    Ok((lat, lon, alt_m))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrbitDirection {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl OrbitDirection {
+    fn sign(self) -> f32 {
+        match self {
+            OrbitDirection::Clockwise => 1.0,
+            OrbitDirection::CounterClockwise => -1.0,
+        }
+    }
+}
+
+/// Distance to the circle entry point at which the vehicle counts as arrived.
+const ORBIT_ENTRY_ACCEPTANCE_M: f64 = 3.0;
+/// Extra time allowed on top of the estimated transit to the entry point.
+const ORBIT_ENTRY_MARGIN: Duration = Duration::from_secs(30);
+/// MAV_DO_REPOSITION_FLAGS_CHANGE_MODE
+const REPOSITION_CHANGE_MODE: f32 = 1.0;
+
+/// ArduCopter `CIRCLE_RATE` (deg/s, positive clockwise) for a ground speed on
+/// a circle of `radius_m`.
+pub fn circle_rate_deg_s(radius_m: f32, speed_mps: f32, direction: OrbitDirection) -> f32 {
+    (speed_mps / radius_m.max(1.0)).to_degrees() * direction.sign()
+}
+
+/// Point on the circle closest to the vehicle. ArduCopter's CIRCLE mode places
+/// the centre `CIRCLE_RADIUS` ahead of the vehicle, so arriving here while
+/// facing the centre makes the requested centre the actual one.
+pub fn orbit_entry_point(
+    center_lat: f64,
+    center_lon: f64,
+    radius_m: f32,
+    vehicle_lat: f64,
+    vehicle_lon: f64,
+) -> (f64, f64) {
+    let bearing = bearing_deg(center_lat, center_lon, vehicle_lat, vehicle_lon);
+    destination(center_lat, center_lon, bearing, radius_m as f64)
+}
+
+/// State of an ArduPilot orbit: the parameters it overwrote, restored by
+/// `stop_orbit`, and the task that switches to CIRCLE at the entry point.
+pub(crate) struct OrbitSession {
+    saved_params: Vec<(String, f32)>,
+    entry: Option<AbortHandle>,
+}
+
+/// Start a new session, keeping the values saved by an orbit that is still
+/// active so that `stop_orbit` restores what was there before either.
+fn begin_session(vehicle: &Vehicle, names: &[&str]) -> Result<(), VehicleError> {
+    let mut session = vehicle.inner.orbit.lock().unwrap();
+    if let Some(previous) = session.as_mut() {
+        if let Some(entry) = previous.entry.take() {
+            entry.abort();
+        }
+        if names
+            .iter()
+            .all(|name| previous.saved_params.iter().any(|(saved, _)| saved == name))
+        {
+            return Ok(());
+        }
+    }
+    let store = vehicle.param_store().borrow().clone();
+    let saved_params = names
+        .iter()
+        .map(|&name| {
+            store
+                .params
+                .get(name)
+                .map(|param| (name.to_string(), param.value))
+                .ok_or_else(|| VehicleError::ParamUnknown(name.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    *session = Some(OrbitSession {
+        saved_params,
+        entry: None,
+    });
+    Ok(())
+}
+
+/// Start orbiting. Returns once the vehicle has accepted the orbit or, on
+/// ArduCopter, the transit to the circle entry point; CIRCLE mode is entered
+/// in the background on arrival.
+pub(crate) async fn orbit(
+    vehicle: &Vehicle,
+    center_lat: f64,
+    center_lon: f64,
+    radius_m: f32,
+    speed_mps: f32,
+    direction: OrbitDirection,
+) -> Result<(), VehicleError> {
+    let state = vehicle.state().borrow().clone();
+    match state.autopilot {
+        AutopilotType::Px4 => {
+            vehicle
+                .command_int(
+                    MavCmd::MAV_CMD_DO_ORBIT,
+                    MavFrame::MAV_FRAME_GLOBAL,
+                    [
+                        radius_m * direction.sign(),
+                        speed_mps,
+                        // Yaw: front to circle centre
+                        0.0,
+                        // Orbits: 0 = forever
+                        0.0,
+                    ],
+                    deg_to_e7(center_lat),
+                    deg_to_e7(center_lon),
+                    // Keep current altitude
+                    f32::NAN,
+                )
+                .await
+        }
+        AutopilotType::ArduPilotMega if state.vehicle_type == VehicleType::FixedWing => {
+            // Planes loiter around the guided target at WP_LOITER_RAD (negative = CCW)
+            begin_session(vehicle, &["WP_LOITER_RAD"])?;
+            vehicle
+                .params()
+                .write("WP_LOITER_RAD".to_string(), radius_m * direction.sign())
+                .await?;
+            let alt_m = current_alt_m(vehicle);
+            vehicle.set_mode_by_name("GUIDED").await?;
+            vehicle.goto(center_lat, center_lon, alt_m).await
+        }
+        AutopilotType::ArduPilotMega => {
+            let telemetry = vehicle.telemetry().borrow().clone();
+            let (Some(lat), Some(lon)) = (telemetry.latitude_deg, telemetry.longitude_deg) else {
+                return Err(VehicleError::CommandNotSupported(
+                    "orbit without a position fix".to_string(),
+                ));
+            };
+
+            begin_session(vehicle, &["CIRCLE_RADIUS", "CIRCLE_RATE"])?;
+            let params = vehicle.params();
+            params
+                .write("CIRCLE_RADIUS".to_string(), radius_m * 100.0)
+                .await?;
+            params
+                .write(
+                    "CIRCLE_RATE".to_string(),
+                    circle_rate_deg_s(radius_m, speed_mps, direction),
+                )
+                .await?;
+
+            let (entry_lat, entry_lon) =
+                orbit_entry_point(center_lat, center_lon, radius_m, lat, lon);
+            let transit_s = distance_m(lat, lon, entry_lat, entry_lon) / speed_mps.max(1.0) as f64;
+
+            vehicle.set_mode_by_name("GUIDED").await?;
+            vehicle
+                .goto(entry_lat, entry_lon, current_alt_m(vehicle))
+                .await?;
+
+            let entry_vehicle = vehicle.clone();
+            let timeout = Duration::from_secs_f64(transit_s) + ORBIT_ENTRY_MARGIN;
+            let entry = tokio::spawn(async move {
+                let result = async {
+                    wait_for_arrival(&entry_vehicle, entry_lat, entry_lon, timeout).await?;
+                    entry_vehicle.set_mode_by_name("CIRCLE").await
+                }
+                .await;
+                if let Err(err) = result {
+                    warn!("orbit: entering CIRCLE failed: {err}");
+                }
+            });
+            if let Some(session) = vehicle.inner.orbit.lock().unwrap().as_mut() {
+                session.entry = Some(entry.abort_handle());
+            }
+            Ok(())
+        }
+        AutopilotType::Generic | AutopilotType::Unknown => {
+            Err(VehicleError::CommandNotSupported("orbit".to_string()))
+        }
+    }
+}
+
+/// Stop an orbit, hold at the current position and restore the parameters
+/// the orbit changed.
+pub(crate) async fn stop_orbit(vehicle: &Vehicle) -> Result<(), VehicleError> {
+    let telemetry = vehicle.telemetry().borrow().clone();
+    let (Some(lat), Some(lon)) = (telemetry.latitude_deg, telemetry.longitude_deg) else {
+        return Err(VehicleError::CommandNotSupported(
+            "stop orbit without a position fix".to_string(),
+        ));
+    };
+    let session = vehicle.inner.orbit.lock().unwrap().take();
+    if let Some(entry) = session.as_ref().and_then(|session| session.entry.as_ref()) {
+        entry.abort();
+    }
+    let held = reposition(vehicle, lat, lon, current_alt_m(vehicle)).await;
+    let saved_params = session.map(|session| session.saved_params);
+    for (name, value) in saved_params.unwrap_or_default() {
+        vehicle.params().write(name, value).await?;
+    }
+    held
+}
+
+/// Hold at `lat`/`lon`, `alt_m` above home: MAV_CMD_DO_REPOSITION on PX4, a
+/// goto in GUIDED elsewhere.
+pub(crate) async fn reposition(
+    vehicle: &Vehicle,
+    lat: f64,
+    lon: f64,
+    alt_m: f32,
+) -> Result<(), VehicleError> {
+    let autopilot = vehicle.state().borrow().autopilot;
+    match autopilot {
+        AutopilotType::Px4 => {
+            vehicle
+                .command_int(
+                    MavCmd::MAV_CMD_DO_REPOSITION,
+                    MavFrame::MAV_FRAME_GLOBAL_RELATIVE_ALT,
+                    [-1.0, REPOSITION_CHANGE_MODE, 0.0, f32::NAN],
+                    deg_to_e7(lat),
+                    deg_to_e7(lon),
+                    alt_m,
+                )
+                .await
+        }
+        _ => {
+            vehicle.set_mode_by_name("GUIDED").await?;
+            vehicle.goto(lat, lon, alt_m).await
+        }
+    }
+}
+
+fn current_alt_m(vehicle: &Vehicle) -> f32 {
+    vehicle
+        .telemetry()
+        .borrow()
+        .altitude_m
+        .map(|a| a as f32)
+        .unwrap_or(GOTO_ALT_BAND_M)
+}
+
+async fn wait_for_arrival(
+    vehicle: &Vehicle,
+    lat: f64,
+    lon: f64,
+    timeout: Duration,
+) -> Result<(), VehicleError> {
+    let mut telemetry = vehicle.telemetry();
+    let arrived = async {
+        loop {
+            {
+                let t = telemetry.borrow_and_update();
+                if let (Some(vlat), Some(vlon)) = (t.latitude_deg, t.longitude_deg) {
+                    if distance_m(vlat, vlon, lat, lon) <= ORBIT_ENTRY_ACCEPTANCE_M {
+                        return Ok(());
+                    }
+                }
+            }
+            telemetry
+                .changed()
+                .await
+                .map_err(|_| VehicleError::Disconnected)?;
+        }
+    };
+    tokio::time::timeout(timeout, arrived)
+        .await
+        .map_err(|_| VehicleError::Timeout)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn telemetry() -> Telemetry {
+        Telemetry {
+            latitude_deg: Some(47.0),
+            longitude_deg: Some(8.0),
+            altitude_m: Some(23.4),
+            speed_mps: Some(0.2),
+            terrain_height_m: Some(500.0),
+            ..Telemetry::default()
+        }
+    }
+
+    const HOME: HomePosition = HomePosition::amsl(47.0, 8.0, 500.0);
+
+    #[test]
+    fn snaps_to_altitude_bands() {
+        assert_eq!(snap_altitude(23.4), 25.0);
+        assert_eq!(snap_altitude(22.0), 20.0);
+        assert_eq!(snap_altitude(0.5), GOTO_ALT_BAND_M);
+    }
+
+    #[test]
+    fn proposal_uses_current_altitude_and_default_speed() {
+        let proposal = plan_goto(7, &telemetry(), Some(&HOME), None, 47.001, 8.0, None);
+        assert_eq!(proposal.token, 7);
+        assert_eq!(proposal.altitude_m, 25.0);
+        let distance = proposal.distance_m.unwrap();
+        assert!((distance - 111.2).abs() < 0.5, "got {distance}");
+        let eta = proposal.eta_s.unwrap();
+        assert!((eta - distance / DEFAULT_GOTO_SPEED_MPS).abs() < 1e-9);
+        assert_eq!(proposal.terrain_clearance_m, Some(25.0));
+        assert_eq!(proposal.warnings.len(), 1);
+    }
+
+    #[test]
+    fn warns_on_low_terrain_clearance() {
+        let mut t = telemetry();
+        t.terrain_height_m = Some(520.0);
+        let proposal = plan_goto(1, &t, Some(&HOME), None, 47.001, 8.0, Some(25.0));
+        assert_eq!(proposal.terrain_clearance_m, Some(5.0));
+        assert!(proposal
+            .warnings
+            .iter()
+            .any(|w| w.contains("terrain clearance")));
+    }
+
+    #[test]
+    fn nudge_is_clamped_and_relative_to_heading() {
+        let mut t = telemetry();
+        t.heading_deg = Some(90.0);
+        let (lat, lon, alt) = plan_nudge(
+            &t,
+            None,
+            &NudgeLimits::default(),
+            NudgeDirection::Forward,
+            50.0,
+        )
+        .unwrap();
+        assert!((distance_m(47.0, 8.0, lat, lon) - 20.0).abs() < 0.01);
+        assert!((bearing_deg(47.0, 8.0, lat, lon) - 90.0).abs() < 0.1);
+        assert_eq!(alt, 23.4);
+
+        let (lat, lon, _) =
+            plan_nudge(&t, None, &NudgeLimits::default(), NudgeDirection::Left, 5.0).unwrap();
+        assert!((bearing_deg(47.0, 8.0, lat, lon) - 0.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn nudge_out_of_the_fence_is_refused() {
+        let fence = Fence {
+            zones: vec![crate::mission::FenceZone {
+                kind: crate::mission::FenceZoneKind::Inclusion,
+                shape: crate::mission::FenceShape::Circle {
+                    center: (47.0, 8.0),
+                    radius_m: 30.0,
+                },
+                seq: 0,
+            }],
+            skipped: Vec::new(),
+        };
+        let limits = NudgeLimits {
+            max_step_m: 50.0,
+            ..NudgeLimits::default()
+        };
+        let t = telemetry();
+        assert!(plan_nudge(&t, Some(&fence), &limits, NudgeDirection::Forward, 20.0).is_ok());
+        assert!(matches!(
+            plan_nudge(&t, Some(&fence), &limits, NudgeDirection::Forward, 40.0),
+            Err(VehicleError::NudgeRefused(_))
+        ));
+    }
+
+    #[test]
+    fn descent_keeps_terrain_clearance() {
+        let limits = NudgeLimits::default();
+        let (_, _, alt) = plan_altitude_change(&telemetry(), Some(&HOME), &limits, -15.0).unwrap();
+        assert!((alt - 13.4).abs() < 1e-4);
+
+        let mut t = telemetry();
+        t.terrain_height_m = Some(505.0);
+        assert!(plan_altitude_change(&t, Some(&HOME), &limits, -10.0).is_err());
+        assert!(plan_altitude_change(&t, Some(&HOME), &limits, 5.0).is_ok());
+        assert!(plan_altitude_change(&Telemetry::default(), None, &limits, 5.0).is_err());
+    }
+
+    #[test]
+    fn circle_rate_sign_follows_direction() {
+        let cw = circle_rate_deg_s(20.0, 5.0, OrbitDirection::Clockwise);
+        assert!((cw - 0.25f32.to_degrees()).abs() < 1e-4);
+        assert_eq!(
+            circle_rate_deg_s(20.0, 5.0, OrbitDirection::CounterClockwise),
+            -cw
+        );
+    }
+
+    #[test]
+    fn entry_point_lies_on_circle_towards_vehicle() {
+        let (vlat, vlon) = destination(47.0, 8.0, 120.0, 300.0);
+        let (lat, lon) = orbit_entry_point(47.0, 8.0, 40.0, vlat, vlon);
+        assert!((distance_m(47.0, 8.0, lat, lon) - 40.0).abs() < 0.01);
+        assert!((distance_m(lat, lon, vlat, vlon) - 260.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn warns_when_the_path_to_the_target_grazes_terrain() {
+        let t = Telemetry {
+            altitude_amsl_m: Some(525.0),
+            ..telemetry()
+        };
+        // A 20 m mound halfway to the target
+        let mound = |lat: f64, _lon: f64| {
+            Some(if (lat - 47.0005).abs() < 0.0001 {
+                520.0
+            } else {
+                500.0
+            })
+        };
+        let proposal = plan_goto(1, &t, Some(&HOME), Some(&mound), 47.001, 8.0, Some(25.0));
+        let clearance = proposal.path_clearance_m.unwrap();
+        assert!((clearance - 5.0).abs() < 1e-6, "{clearance}");
+        assert!(proposal
+            .warnings
+            .iter()
+            .any(|w| w.contains("path to the target")));
+
+        let flat = |_: f64, _: f64| Some(500.0);
+        let proposal = plan_goto(1, &t, Some(&HOME), Some(&flat), 47.001, 8.0, Some(25.0));
+        assert_eq!(proposal.path_clearance_m, Some(25.0));
+        assert!(!proposal
+            .warnings
+            .iter()
+            .any(|w| w.contains("path to the target")));
+    }
+
+    #[test]
+    fn unknown_position_has_no_eta() {
+        let proposal = plan_goto(1, &Telemetry::default(), None, None, 47.0, 8.0, Some(30.0));
+        assert_eq!(proposal.distance_m, None);
+        assert_eq!(proposal.eta_s, None);
+        assert_eq!(proposal.terrain_clearance_m, None);
+    }
+}
@@ -0,0 +1,104 @@
+//! Periodic jobs run alongside the event loop.
+//!
+//! `run_event_loop` spawns one housekeeping task that wakes every
+//! `VehicleConfig::tick_interval` and runs whichever jobs in its `Schedule`
+//! are due. Time-based features register a `Job` here rather than spawning
+//! their own timer task.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Job {
+    /// Send our GCS HEARTBEAT.
+    GcsHeartbeat,
+    /// Drop silent GCS peers and their stale control claims.
+    ExpirePeers,
+    /// Drop ADS-B targets that went silent and re-check advisories.
+    ExpireTraffic,
+    /// Send a TIMESYNC request to measure the vehicle clock.
+    Timesync,
+    /// Repeat the Remote ID operator ID and self-ID.
+    RemoteIdOperator,
+}
+
+struct Entry {
+    job: Job,
+    period: Duration,
+    due: Instant,
+}
+
+#[derive(Default)]
+pub(crate) struct Schedule {
+    entries: Vec<Entry>,
+}
+
+impl Schedule {
+    /// Run `job` every `period`, first at `now`.
+    pub(crate) fn every(&mut self, period: Duration, job: Job, now: Instant) {
+        self.entries.push(Entry {
+            job,
+            period,
+            due: now,
+        });
+    }
+
+    /// Jobs due at `now`, in registration order. Each is rescheduled one
+    /// period after it was due, or after `now` if it fell a whole period
+    /// behind, so a stalled tick does not cause a burst of catch-up runs.
+    pub(crate) fn due(&mut self, now: Instant) -> Vec<Job> {
+        let mut due = Vec::new();
+        for entry in &mut self.entries {
+            if entry.due > now {
+                continue;
+            }
+            due.push(entry.job);
+            entry.due += entry.period;
+            if entry.due <= now {
+                entry.due = now + entry.period;
+            }
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn runs_each_job_at_its_own_period() {
+        let start = Instant::now();
+        let mut schedule = Schedule::default();
+        schedule.every(SECOND, Job::GcsHeartbeat, start);
+        schedule.every(SECOND * 5, Job::ExpirePeers, start);
+
+        assert_eq!(schedule.due(start), [Job::GcsHeartbeat, Job::ExpirePeers]);
+        assert!(schedule.due(start + SECOND / 2).is_empty());
+        assert_eq!(schedule.due(start + SECOND), [Job::GcsHeartbeat]);
+        // A late tick keeps the original phase
+        assert_eq!(
+            schedule.due(start + SECOND * 2 + SECOND / 10),
+            [Job::GcsHeartbeat]
+        );
+        assert!(schedule.due(start + SECOND * 2 + SECOND / 2).is_empty());
+        assert_eq!(
+            schedule.due(start + SECOND * 5),
+            [Job::GcsHeartbeat, Job::ExpirePeers]
+        );
+    }
+
+    #[test]
+    fn stalled_tick_does_not_burst() {
+        let start = Instant::now();
+        let mut schedule = Schedule::default();
+        schedule.every(SECOND, Job::GcsHeartbeat, start);
+        schedule.due(start);
+
+        let late = start + SECOND * 10;
+        assert_eq!(schedule.due(late), [Job::GcsHeartbeat]);
+        assert!(schedule.due(late).is_empty());
+        assert_eq!(schedule.due(late + SECOND), [Job::GcsHeartbeat]);
+    }
+}
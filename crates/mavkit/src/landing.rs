@@ -0,0 +1,164 @@
+//! Land-now: put the vehicle down where it is and watch the descent.
+//!
+//! `Vehicle::land_here` switches to the autopilot's land mode, or sends
+//! MAV_CMD_NAV_LAND when already in GUIDED, then follows the descent through
+//! `Vehicle::landing_state` until touchdown. A descent that is too fast or
+//! has stopped is flagged, so the operator can decide whether to
+//! `Vehicle::abort_landing` and climb back out.
+
+use crate::state::Telemetry;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Sinking faster than this is flagged as `LandingWarning::FastDescent`.
+pub const MAX_DESCENT_RATE_MPS: f64 = 3.0;
+/// Losing less than `MIN_DESCENT_STEP_M` for this long is flagged as
+/// `LandingWarning::Stalled`.
+pub const DESCENT_STALL_TIMEOUT: Duration = Duration::from_secs(10);
+/// Height loss that counts as progress; smaller changes are baro noise.
+const MIN_DESCENT_STEP_M: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LandingPhase {
+    /// No landing started by `Vehicle::land_here`.
+    #[default]
+    Idle,
+    Descending,
+    /// On the ground or disarmed; monitoring has ended.
+    Landed,
+    /// Stopped by `Vehicle::abort_landing`.
+    Aborted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LandingWarning {
+    /// Sinking faster than `MAX_DESCENT_RATE_MPS`.
+    FastDescent,
+    /// No height lost for `DESCENT_STALL_TIMEOUT`, e.g. landing on an
+    /// obstacle or held up by the pilot's throttle.
+    Stalled,
+}
+
+/// Progress of a landing started by `Vehicle::land_here`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LandingState {
+    pub phase: LandingPhase,
+    /// Height above home.
+    pub altitude_m: Option<f64>,
+    /// Positive while sinking.
+    pub descent_rate_mps: Option<f64>,
+    pub warning: Option<LandingWarning>,
+}
+
+/// Follows the descent from telemetry.
+#[derive(Debug, Default)]
+pub(crate) struct DescentMonitor {
+    /// Lowest height seen and when it was last lowered.
+    progress: Option<(f64, Instant)>,
+}
+
+impl DescentMonitor {
+    pub(crate) fn observe(
+        &mut self,
+        telemetry: &Telemetry,
+        armed: bool,
+        now: Instant,
+    ) -> LandingState {
+        let altitude_m = telemetry.altitude_m;
+        let descent_rate_mps = telemetry.climb_rate_mps.map(|climb| -climb);
+        if !armed || !telemetry.is_airborne() {
+            return LandingState {
+                phase: LandingPhase::Landed,
+                altitude_m,
+                descent_rate_mps,
+                warning: None,
+            };
+        }
+
+        let mut warning = descent_rate_mps
+            .is_some_and(|rate| rate > MAX_DESCENT_RATE_MPS)
+            .then_some(LandingWarning::FastDescent);
+        if let Some(alt) = altitude_m {
+            match self.progress {
+                Some((lowest, _)) if alt > lowest - MIN_DESCENT_STEP_M => {}
+                _ => self.progress = Some((alt, now)),
+            }
+        }
+        if let Some((_, since)) = self.progress {
+            if warning.is_none() && now.saturating_duration_since(since) >= DESCENT_STALL_TIMEOUT {
+                warning = Some(LandingWarning::Stalled);
+            }
+        }
+        LandingState {
+            phase: LandingPhase::Descending,
+            altitude_m,
+            descent_rate_mps,
+            warning,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::LandedState;
+
+    fn flying(alt: f64, climb: f64) -> Telemetry {
+        Telemetry {
+            altitude_m: Some(alt),
+            climb_rate_mps: Some(climb),
+            landed_state: Some(LandedState::Landing),
+            ..Telemetry::default()
+        }
+    }
+
+    #[test]
+    fn follows_the_descent_to_touchdown() {
+        let start = Instant::now();
+        let mut monitor = DescentMonitor::default();
+        let state = monitor.observe(&flying(30.0, -1.5), true, start);
+        assert_eq!(state.phase, LandingPhase::Descending);
+        assert_eq!(state.descent_rate_mps, Some(1.5));
+        assert_eq!(state.warning, None);
+
+        let fast = monitor.observe(&flying(25.0, -4.5), true, start + Duration::from_secs(1));
+        assert_eq!(fast.warning, Some(LandingWarning::FastDescent));
+
+        let on_ground = Telemetry {
+            landed_state: Some(LandedState::OnGround),
+            ..flying(0.1, 0.0)
+        };
+        let landed = monitor.observe(&on_ground, true, start + Duration::from_secs(20));
+        assert_eq!(landed.phase, LandingPhase::Landed);
+        assert_eq!(
+            monitor.observe(&flying(0.1, 0.0), false, start).phase,
+            LandingPhase::Landed
+        );
+    }
+
+    #[test]
+    fn flags_a_stalled_descent() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut monitor = DescentMonitor::default();
+        monitor.observe(&flying(12.0, -1.0), true, at(0));
+        monitor.observe(&flying(10.0, -1.0), true, at(2));
+        // Hovering with baro noise
+        monitor.observe(&flying(10.3, 0.0), true, at(6));
+        assert_eq!(
+            monitor.observe(&flying(9.8, 0.0), true, at(11)).warning,
+            None
+        );
+        assert_eq!(
+            monitor.observe(&flying(9.7, 0.0), true, at(12)).warning,
+            Some(LandingWarning::Stalled)
+        );
+        // Descending again
+        assert_eq!(
+            monitor.observe(&flying(9.0, -0.7), true, at(13)).warning,
+            None
+        );
+    }
+}
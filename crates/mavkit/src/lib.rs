@@ -1,31 +1,230 @@
+#[cfg(feature = "mission")]
+pub mod airspace;
+#[cfg(feature = "link")]
+pub mod audit;
+#[cfg(feature = "link")]
+pub mod battery_health;
+#[cfg(feature = "link")]
+pub mod blocking;
+#[cfg(feature = "link")]
+pub mod clock;
+#[cfg(feature = "link")]
 pub mod command;
+pub mod coords;
+#[cfg(feature = "link")]
 pub mod config;
+#[cfg(feature = "link")]
+pub mod control;
 pub mod error;
+#[cfg(feature = "link")]
+pub(crate) mod dedup;
+#[cfg(feature = "link")]
+pub mod esc;
+#[cfg(feature = "link")]
 pub mod event_loop;
+#[cfg(feature = "link")]
+pub mod events;
+#[cfg(feature = "link")]
+pub mod fleet;
+#[cfg(feature = "flasher")]
+pub mod flasher;
+#[cfg(feature = "link")]
+pub mod gcs;
+#[cfg(feature = "link")]
+pub mod geofence;
+#[cfg(feature = "link")]
+pub mod guided;
+#[cfg(feature = "link")]
+pub mod housekeeping;
+#[cfg(feature = "link")]
+pub mod landing;
+#[cfg(feature = "link")]
+pub mod link;
+#[cfg(feature = "mission")]
 pub mod mission;
 #[cfg(feature = "ardupilot")]
+#[cfg_attr(not(feature = "link"), allow(dead_code))]
 pub mod modes;
+#[cfg(feature = "link")]
+pub mod odometer;
+#[cfg(feature = "params")]
 pub mod params;
+#[cfg(feature = "link")]
+pub mod payload;
+#[cfg(feature = "udp")]
+pub mod pose_output;
+#[cfg(feature = "link")]
+pub mod rate_benchmark;
+#[cfg(feature = "link")]
+pub mod raw;
+#[cfg(feature = "link")]
+pub mod remote_id;
+#[cfg(feature = "link")]
+pub mod return_to_me;
+#[cfg(feature = "link")]
+pub mod send_queue;
+#[cfg(feature = "serial")]
+pub mod serial;
+#[cfg(feature = "link")]
+pub mod setup;
+#[cfg(feature = "serial")]
+pub mod sik;
+#[cfg(feature = "link")]
+pub mod sitl;
+#[cfg(feature = "sitl-harness")]
+pub mod sitl_harness;
+#[cfg(feature = "link")]
+pub mod smoothing;
 pub mod state;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "link")]
+pub mod traffic;
+#[cfg(feature = "udp")]
+pub(crate) mod udp_server;
+pub mod units;
+#[cfg(feature = "link")]
 pub mod vehicle;
+#[cfg(feature = "link")]
+pub mod vibration;
+#[cfg(feature = "mission")]
+pub mod weather;
 
-pub use config::VehicleConfig;
+#[cfg(feature = "mission")]
+pub use airspace::{
+    Airspace, AirspaceError, AirspaceProximity, AirspaceSet, AltitudeLimit, AltitudeReference,
+    AltitudeUnit,
+};
+#[cfg(feature = "link")]
+pub use audit::AuditEntry;
+#[cfg(feature = "link")]
+pub use battery_health::{BatteryAlert, BatteryChemistry, BatteryHealth, CellLimits};
+#[cfg(feature = "link")]
+pub use config::{ConfigPatch, VehicleConfig};
+#[cfg(feature = "link")]
+pub use control::{ControlOwner, ControlState};
+pub use coords::{format_coordinate, parse_coordinate, CoordinateError, CoordinateFormat, Utm};
 pub use error::VehicleError;
+#[cfg(feature = "link")]
+pub use esc::EscTelemetry;
+#[cfg(feature = "link")]
+pub use events::{
+    event_channel, EventClass, EventMonitor, EventReceiver, EventSender, EventStats, VehicleEvent,
+    DEFAULT_EVENT_CAPACITY,
+};
+#[cfg(feature = "link")]
+pub use fleet::{Fleet, FleetProgress, FleetResult, MemberFlightTotals, MemberResult};
+#[cfg(feature = "link")]
+pub use gcs::{GcsPeer, ShutdownWarning};
+#[cfg(feature = "link")]
+pub use geofence::{FenceBreachType, FenceHandle, FenceStatus};
+#[cfg(feature = "link")]
+pub use landing::{LandingPhase, LandingState, LandingWarning};
+#[cfg(feature = "link")]
+pub use link::SharedLink;
+#[cfg(feature = "ardupilot")]
+pub use modes::{ModeCheck, ModeHazard, ModeOverrides};
+#[cfg(feature = "link")]
+pub use odometer::{FlightLog, FlightTotals};
+#[cfg(feature = "link")]
+pub use rate_benchmark::{MessageRate, RateBenchmarkOptions, RateReport};
+#[cfg(feature = "link")]
+pub use raw::raw_message_template;
+#[cfg(feature = "udp")]
+pub use pose_output::{encode_pose, PoseFormat, PoseOutput, PoseOutputOptions};
+#[cfg(feature = "link")]
+pub use remote_id::{
+    RemoteIdLocation, RemoteIdOperator, RemoteIdState, RemoteIdStatus, UasId, UasIdType,
+};
+#[cfg(feature = "link")]
+pub use return_to_me::{ReturnToMeOptions, ReturnToMeState, StaleAction};
+#[cfg(feature = "link")]
+pub use send_queue::{LinkPacing, SendPriority};
+#[cfg(feature = "serial")]
+pub use serial::{FlowControl, Parity, SerialOptions, StopBits};
+#[cfg(feature = "link")]
+pub use setup::{
+    airframe_presets, failsafe_config, failsafe_options, failsafe_writes, frame_params,
+    sensor_rotations, sensor_setup, AirframeCategory, AirframePreset, BusType, Compass, DeviceId,
+    FailsafeAction, FailsafeConfig, FailsafeOptions, FrameParams, Imu, PresetParam, RcCalibration,
+    RcCalibrationSession, RcChannelCalibration, SensorRotation, SensorSetup, SetupHandle,
+};
+#[cfg(feature = "link")]
+pub use sitl::{is_sitl, RcFailure, SimAction, SitlHandle};
+#[cfg(feature = "sitl-harness")]
+pub use sitl_harness::{SitlInstance, SitlOptions, SitlVehicle};
+#[cfg(feature = "link")]
+pub use smoothing::{SmoothedTelemetry, TelemetrySmoothing};
+#[cfg(feature = "tls")]
+pub use tls::TlsOptions;
+#[cfg(feature = "link")]
+pub use traffic::{traffic_advisories, AvoidanceSuggestion, TrafficAdvisory, TrafficTarget};
+pub use units::{display_telemetry, DisplayTelemetry, DisplayValue, Units};
+#[cfg(feature = "link")]
+pub use guided::{
+    snap_altitude, GotoProposal, NudgeDirection, NudgeLimits, OrbitDirection, TerrainCheck,
+};
+#[cfg(feature = "link")]
+pub use tokio_util::sync::CancellationToken;
+#[cfg(feature = "link")]
 pub use vehicle::Vehicle;
+#[cfg(feature = "link")]
+pub use vibration::{ClipAlert, VibrationAxis, VibrationState, VibrationSummary};
+#[cfg(feature = "mission")]
+pub use weather::{
+    open_meteo_url, parse_open_meteo, wind_adjusted_estimate, FixedWind, LegWind, WeatherError,
+    WeatherProvider, WindEstimate, WindForecast, WindProfile, WindSample,
+};
 
 pub use state::{
-    AutopilotType, FlightMode, GpsFixType, LinkState, MissionState, SystemStatus, Telemetry,
-    VehicleIdentity, VehicleState, VehicleType,
+    AutopilotType, BatteryInfo, FirmwareVersion, FlightMode, GpsFixType, LandedState, LinkState,
+    MissionRunState, MissionState, SystemStatus, Telemetry, VehicleIdentity, VehicleState,
+    VehicleType,
 };
 
+#[cfg(feature = "mission")]
+pub use mission::{
+    audit_float_download, audit_imported_coordinates, deg_to_e7, detect_conflicts, diff_plans,
+    e7_to_deg, fence_breaches, generate_search_pattern, generate_structure_scan,
+    items_for_wire_upload, normalize_for_compare, path_clearance, plan_checksum,
+    plan_from_wire_download, plan_sync_status, plans_equivalent, preview_rtl, retarget_agl,
+    rtl_alt_from_params, simplify, simulate, snap_altitudes, snap_to_grid, sun_position, sun_times,
+    sun_warnings, sync_progress, validate_against_fence, validate_ardupilot_acceptance,
+    validate_plan, validate_plan_report, validate_vtol_transitions, wire_item_count,
+    wrap_vtol_block, AglReport, AglRetarget, AltitudeDatum, ArduPilotProfile, ClampedPoint,
+    CompareTolerance, Conflict, Fence, FenceBreach, FenceShape, FenceZone, FenceZoneKind,
+    FieldMismatch, FlatTerrain, HomePosition, IssueSeverity, ItemDiff, MissionFrame, MissionIssue,
+    MissionItem, MissionPlan, MissionTransferMachine, MissionType, OnboardPlans, PathClearance,
+    PlanDiff, PlanMarkers, PlanSyncMarker, PlanSyncStatus, PlannedFlight, RetryPolicy, RtlAltitude,
+    RtlPoint, RtlPreview, RtlSegment, RtlSegmentKind, SearchPattern, SearchPatternParams,
+    Separation, SimSample, SimTimeline, Simplified, StructureScanParams, SunPosition, SunTimes,
+    SyncOutcome, SyncPart, SyncProgress, SyncReport, TerrainSource, TransferDirection,
+    TransferError, TransferEvent, TransferPhase, TransferProgress, UnreachablePoint,
+    UnreachableReason, UploadOptions, ValidationOptions, ValidationReport, VehicleProfile,
+    VtolProfile, VtolWrapParams,
+};
+#[cfg(all(feature = "mission", not(target_arch = "wasm32")))]
+pub use mission::{PlanHistory, PlanSnapshot};
+#[cfg(feature = "link")]
+pub use mission::MissionHandle;
+#[cfg(feature = "sealing")]
 pub use mission::{
-    items_for_wire_upload, normalize_for_compare, plan_from_wire_download, plans_equivalent,
-    validate_plan, CompareTolerance, HomePosition, IssueSeverity, MissionFrame, MissionHandle,
-    MissionItem, MissionIssue, MissionPlan, MissionTransferMachine, MissionType, RetryPolicy,
-    TransferDirection, TransferError, TransferEvent, TransferPhase, TransferProgress,
+    open_plan, parse_signing_key, parse_verifying_key, seal_plan, PlanIdentity, PlanKeyring,
+    PlanRecipient, StaticKeyring,
+};
+
+#[cfg(feature = "link")]
+pub use payload::{
+    payload_capabilities, GripperAction, PayloadCapabilities, PayloadHandle, WinchAction,
 };
 
+#[cfg(feature = "params")]
 pub use params::{
-    format_param_file, parse_param_file, Param, ParamProgress, ParamStore, ParamTransferPhase,
-    ParamType, ParamsHandle,
+    format_param_file, param_prefix, parse_param_file, plan_transaction, Param, ParamChange,
+    ParamDownloadStage, ParamGroup, ParamProgress, ParamStore, ParamTransferPhase, ParamType,
+    ParamUpdate,
 };
+#[cfg(all(feature = "params", not(target_arch = "wasm32")))]
+pub use params::{CachedParams, ParamCache};
+#[cfg(feature = "link")]
+pub use params::ParamsHandle;
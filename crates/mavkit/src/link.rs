@@ -0,0 +1,643 @@
+//! Send and receive paths between the event loop and the connection.
+//!
+//! Every send goes through `Link::send`, which classifies the message and
+//! hands it to a sender task. That task drains control traffic before
+//! heartbeats, transfers and bulk messages, and paces writes to the link's
+//! byte budget so a burst of mission items cannot starve guided or arm
+//! commands on a slow radio.
+//!
+//! A single reader task owns `recv()` on the connection and broadcasts each
+//! message to every `Link` handle. Each handle sees the full stream, so the
+//! state updater and a command waiting for its ACK never steal messages from
+//! one another.
+//!
+//! A handle that falls more than `INBOUND_CAPACITY` messages behind loses
+//! the oldest ones; its next `recv` reports `VehicleError::LinkLagged` so a
+//! waiter knows its reply may be gone instead of timing out blind.
+//!
+//! The two tasks hold the only references to the connection. `LinkTasks`
+//! stops them and waits, so the socket or serial port is closed by the time
+//! the event loop exits and a reconnect can bind the same address.
+//!
+//! A handle can drop duplicate packets (see `dedup`), for streams that
+//! arrive over two routes.
+//!
+//! A `SharedLink` carries several vehicles, e.g. behind a telemetry radio or
+//! a router. Each attached vehicle reads through a handle that only passes
+//! its own system id and ground stations' traffic, so one vehicle's messages
+//! never reach another's state or command replies.
+//!
+//! The connection is either one opened by mavlink or, for serial ports with
+//! custom line settings, a `serial::SerialTransport` read on a blocking task,
+//! a `tls::TlsTransport` for encrypted TCP, or a `udp_server` socket for
+//! `udpin` that follows the vehicle's address.
+
+use crate::dedup::Deduplicator;
+use crate::error::VehicleError;
+use crate::send_queue::{LinkPacing, PriorityQueue, SendPriority, TokenBucket};
+#[cfg(feature = "serial")]
+use crate::serial::{self, PortWriter, SerialTransport};
+#[cfg(feature = "tls")]
+use crate::tls::{self, StreamWriter, TlsTransport};
+#[cfg(feature = "udp")]
+use crate::udp_server::{self, PeerWriter, UdpServerTransport};
+use mavlink::ardupilotmega::{MavMessage, MavType};
+use mavlink::{AsyncMavConnection, MavHeader, MavlinkVersion, Message};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// MAVLink v2 header (10 bytes) plus checksum (2 bytes).
+const V2_FRAMING_BYTES: usize = 12;
+
+/// Messages buffered per handle before a slow reader starts skipping.
+const INBOUND_CAPACITY: usize = 1024;
+
+/// Links speak ArduPilot's dialect, a superset of common; a common-only
+/// parser drops messages such as ESC_TELEMETRY_1_TO_4 on the checksum.
+type Connection = dyn AsyncMavConnection<MavMessage> + Sync + Send;
+
+/// A received message, shared between every handle that sees it.
+pub(crate) type Received = Arc<(MavHeader, MavMessage)>;
+
+/// Reader output: a message, or the error that ended the reader.
+pub(crate) type Inbound = Result<Received, String>;
+
+/// What the link tasks read from and write to.
+pub(crate) enum Transport {
+    /// A connection opened by `mavlink::connect_async`.
+    Mavlink(Box<Connection>),
+    #[cfg(feature = "serial")]
+    Serial(SerialTransport),
+    #[cfg(feature = "tls")]
+    Tls(TlsTransport),
+    #[cfg(feature = "udp")]
+    UdpServer(UdpServerTransport),
+}
+
+impl Transport {
+    /// Open a mavlink address string; `udpin` addresses get a socket that
+    /// follows the vehicle's address (see `udp_server`).
+    pub(crate) async fn open(address: &str) -> Result<Self, VehicleError> {
+        #[cfg(feature = "udp")]
+        if let Some(bind_addr) = address.strip_prefix("udpin:") {
+            return Ok(Transport::UdpServer(udp_server::bind(bind_addr).await?));
+        }
+        let connection = mavlink::connect_async::<MavMessage>(address)
+            .await
+            .map_err(|err| crate::vehicle::connect_error(address, &err))?;
+        Ok(Transport::Mavlink(connection))
+    }
+
+    /// Where replies go on a `udpin` socket; `None` for other transports.
+    pub(crate) fn udp_peer(&self) -> Option<watch::Receiver<Option<SocketAddr>>> {
+        match self {
+            #[cfg(feature = "udp")]
+            Transport::UdpServer(socket) => Some(socket.peer()),
+            _ => None,
+        }
+    }
+}
+
+/// Write side of a `Transport`, owned by the sender task.
+enum Writer {
+    Mavlink(Arc<Connection>),
+    #[cfg(feature = "serial")]
+    Serial(PortWriter),
+    #[cfg(feature = "tls")]
+    Tls(StreamWriter),
+    #[cfg(feature = "udp")]
+    UdpServer(PeerWriter),
+}
+
+impl Writer {
+    async fn send(&self, header: &MavHeader, message: &MavMessage) -> Result<(), String> {
+        match self {
+            Writer::Mavlink(connection) => connection
+                .send(header, message)
+                .await
+                .map(drop)
+                .map_err(|err| err.to_string()),
+            #[cfg(feature = "serial")]
+            Writer::Serial(port) => port.send(header, message).await,
+            #[cfg(feature = "tls")]
+            Writer::Tls(stream) => stream.send(header, message).await,
+            #[cfg(feature = "udp")]
+            Writer::UdpServer(socket) => socket.send(header, message).await,
+        }
+    }
+}
+
+pub(crate) struct Link {
+    outgoing: mpsc::UnboundedSender<(SendPriority, MavHeader, MavMessage)>,
+    incoming: broadcast::Receiver<Inbound>,
+    /// Messages handed to the sender task so far, shared by every handle.
+    queued: Arc<AtomicU64>,
+    /// Set on handles of a vehicle attached to a shared link.
+    filter: Option<SystemFilter>,
+    dedup: Option<Deduplicator>,
+}
+
+/// Passes one vehicle's messages, and those of ground stations, on a link
+/// shared by several vehicles.
+#[derive(Clone)]
+struct SystemFilter {
+    system_id: u8,
+    /// System ids seen sending GCS heartbeats.
+    gcs: HashSet<u8>,
+}
+
+impl SystemFilter {
+    fn accepts(&mut self, header: &MavHeader, message: &MavMessage) -> bool {
+        if header.system_id == self.system_id {
+            return true;
+        }
+        if matches!(message, MavMessage::HEARTBEAT(hb) if hb.mavtype == MavType::MAV_TYPE_GCS) {
+            self.gcs.insert(header.system_id);
+            return true;
+        }
+        self.gcs.contains(&header.system_id)
+    }
+}
+
+/// The sender and reader tasks behind a `Link`.
+pub(crate) struct LinkTasks {
+    sender: JoinHandle<()>,
+    reader: JoinHandle<()>,
+    /// Stops a blocking serial reader, which `abort` cannot interrupt.
+    stop: CancellationToken,
+    pacing: watch::Sender<LinkPacing>,
+    queued: Arc<AtomicU64>,
+    /// Messages the sender task has written (or failed to write).
+    written: watch::Receiver<u64>,
+}
+
+impl LinkTasks {
+    /// Change the outgoing bandwidth budget; applies to the next message sent.
+    pub(crate) fn set_pacing(&self, pacing: LinkPacing) {
+        self.pacing.send_replace(pacing);
+    }
+
+    /// Wait until every message queued so far has been written, giving up
+    /// after `timeout` (e.g. on a link paced far below the backlog).
+    pub(crate) async fn flush(&self, timeout: Duration) {
+        let target = self.queued.load(Ordering::Relaxed);
+        let mut written = self.written.clone();
+        let _ = tokio::time::timeout(timeout, written.wait_for(|n| *n >= target)).await;
+    }
+
+    /// Stop both tasks and wait for them to exit, dropping the connection.
+    /// Messages still queued for sending are discarded; `flush` first to
+    /// send them.
+    pub(crate) async fn close(self) {
+        self.stop.cancel();
+        self.sender.abort();
+        self.reader.abort();
+        let _ = self.sender.await;
+        let _ = self.reader.await;
+    }
+}
+
+impl Link {
+    /// Wrap `transport` and spawn its sender and reader tasks, which stop on
+    /// `cancel` or `LinkTasks::close`.
+    pub(crate) fn new(
+        transport: Transport,
+        pacing: LinkPacing,
+        cancel: CancellationToken,
+    ) -> (Self, LinkTasks) {
+        let stop = cancel.child_token();
+        let (outgoing, rx) = mpsc::unbounded_channel();
+        let (tx, incoming) = broadcast::channel(INBOUND_CAPACITY);
+        let (pacing_tx, pacing_rx) = watch::channel(pacing);
+        let (written_tx, written) = watch::channel(0);
+        let queued = Arc::new(AtomicU64::new(0));
+        let (writer, reader) = match transport {
+            Transport::Mavlink(connection) => {
+                let connection: Arc<Connection> = Arc::from(connection);
+                let reader = tokio::spawn(run_reader(connection.clone(), tx, stop.clone()));
+                (Writer::Mavlink(connection), reader)
+            }
+            #[cfg(feature = "serial")]
+            Transport::Serial(port) => {
+                let (port, writer) = port.split();
+                let reader_stop = stop.clone();
+                let reader =
+                    tokio::task::spawn_blocking(move || serial::read_loop(port, tx, reader_stop));
+                (Writer::Serial(writer), reader)
+            }
+            #[cfg(feature = "tls")]
+            Transport::Tls(stream) => {
+                let (stream, writer) = stream.split();
+                let reader = tokio::spawn(tls::read_loop(stream, tx, stop.clone()));
+                (Writer::Tls(writer), reader)
+            }
+            #[cfg(feature = "udp")]
+            Transport::UdpServer(socket) => {
+                let (socket, writer) = socket.split();
+                let reader = tokio::spawn(udp_server::read_loop(socket, tx, stop.clone()));
+                (Writer::UdpServer(writer), reader)
+            }
+        };
+        let tasks = LinkTasks {
+            sender: tokio::spawn(run_sender(writer, rx, pacing_rx, written_tx, stop.clone())),
+            reader,
+            stop,
+            pacing: pacing_tx,
+            queued: queued.clone(),
+            written,
+        };
+        let link = Self {
+            outgoing,
+            incoming,
+            queued,
+            filter: None,
+            dedup: None,
+        };
+        (link, tasks)
+    }
+
+    /// A handle with no transport behind it, for tests: what is sent on it
+    /// comes out of the receiver, and what goes into the sender is received.
+    #[cfg(test)]
+    pub(crate) fn loopback() -> (
+        Self,
+        mpsc::UnboundedReceiver<(SendPriority, MavHeader, MavMessage)>,
+        broadcast::Sender<Inbound>,
+    ) {
+        let (outgoing, sent) = mpsc::unbounded_channel();
+        let (inbound, incoming) = broadcast::channel(INBOUND_CAPACITY);
+        let link = Self {
+            outgoing,
+            incoming,
+            queued: Arc::default(),
+            filter: None,
+            dedup: None,
+        };
+        (link, sent, inbound)
+    }
+
+    /// A handle that only receives `system_id`'s messages and those of
+    /// ground stations, for a vehicle on a shared link.
+    pub(crate) fn for_system(&self, system_id: u8) -> Self {
+        Self {
+            filter: Some(SystemFilter {
+                system_id,
+                gcs: HashSet::new(),
+            }),
+            ..self.clone()
+        }
+    }
+
+    /// This handle, and clones made from it, drop a packet repeated within
+    /// `window`; `None` keeps every packet.
+    pub(crate) fn deduplicated(self, window: Option<Duration>) -> Self {
+        Self {
+            dedup: window.map(Deduplicator::new),
+            ..self
+        }
+    }
+
+    /// The vehicle this handle is filtered to, if any.
+    pub(crate) fn system_id(&self) -> Option<u8> {
+        self.filter.as_ref().map(|filter| filter.system_id)
+    }
+
+    /// Next message received after this handle was created or last
+    /// `skip_pending` call, or `LinkLagged` once if messages were dropped
+    /// since the last call. Cancel-safe.
+    pub(crate) async fn recv(&mut self) -> Result<Received, VehicleError> {
+        loop {
+            let received = match self.incoming.recv().await {
+                Ok(Ok(received)) => received,
+                Ok(Err(err)) => return Err(VehicleError::Io(std::io::Error::other(err))),
+                Err(RecvError::Lagged(skipped)) => {
+                    return Err(VehicleError::LinkLagged { skipped })
+                }
+                Err(RecvError::Closed) => return Err(VehicleError::Disconnected),
+            };
+            let (header, message) = &*received;
+            let accepted = self
+                .filter
+                .as_mut()
+                .is_none_or(|filter| filter.accepts(header, message))
+                && self
+                    .dedup
+                    .as_mut()
+                    .is_none_or(|dedup| dedup.accepts(header, message, Instant::now()));
+            if accepted {
+                return Ok(received);
+            }
+        }
+    }
+
+    /// Drop everything queued on this handle, so the next `recv` only returns
+    /// messages that arrive from now on.
+    pub(crate) fn skip_pending(&mut self) {
+        self.incoming = self.incoming.resubscribe();
+    }
+
+    /// Queue `message` for sending. Write errors are reported by the sender
+    /// task, not here.
+    pub(crate) async fn send(
+        &self,
+        header: &MavHeader,
+        message: &MavMessage,
+    ) -> Result<(), VehicleError> {
+        self.outgoing
+            .send((classify(message), *header, message.clone()))
+            .map_err(|_| VehicleError::Disconnected)?;
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Clone for Link {
+    /// A handle sharing the send queue that receives messages arriving from
+    /// now on.
+    fn clone(&self) -> Self {
+        Self {
+            outgoing: self.outgoing.clone(),
+            incoming: self.incoming.resubscribe(),
+            queued: self.queued.clone(),
+            filter: self.filter.clone(),
+            dedup: self.dedup.clone(),
+        }
+    }
+}
+
+/// One connection carrying several vehicles, each told apart by system id.
+/// Attach a [`Vehicle`](crate::Vehicle) per system id with
+/// [`Vehicle::attach`](crate::Vehicle::attach).
+///
+/// Clones share the connection. It stays open while any clone or attached
+/// vehicle is alive, or until [`SharedLink::close`].
+#[derive(Clone)]
+pub struct SharedLink {
+    inner: Arc<SharedLinkInner>,
+}
+
+struct SharedLinkInner {
+    link: Link,
+    /// Taken by `close`.
+    tasks: Mutex<Option<LinkTasks>>,
+    cancel: CancellationToken,
+    udp_peer: Option<watch::Receiver<Option<SocketAddr>>>,
+}
+
+impl Drop for SharedLinkInner {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+impl SharedLink {
+    /// Open a mavlink address string (e.g. `udpin:0.0.0.0:14550`), pacing
+    /// outgoing traffic of every attached vehicle together.
+    pub async fn open(address: &str, pacing: LinkPacing) -> Result<Self, VehicleError> {
+        let transport = Transport::open(address).await?;
+        Ok(Self::from_transport(transport, pacing))
+    }
+
+    pub(crate) fn from_transport(transport: Transport, pacing: LinkPacing) -> Self {
+        let cancel = CancellationToken::new();
+        let udp_peer = transport.udp_peer();
+        let (link, tasks) = Link::new(transport, pacing, cancel.clone());
+        Self {
+            inner: Arc::new(SharedLinkInner {
+                link,
+                tasks: Mutex::new(Some(tasks)),
+                cancel,
+                udp_peer,
+            }),
+        }
+    }
+
+    /// Change the outgoing bandwidth budget shared by every attached vehicle.
+    pub fn set_pacing(&self, pacing: LinkPacing) {
+        if let Some(tasks) = &*self.inner.tasks.lock().unwrap() {
+            tasks.set_pacing(pacing);
+        }
+    }
+
+    /// Close the connection; every attached vehicle disconnects.
+    pub async fn close(&self) {
+        let tasks = self.inner.tasks.lock().unwrap().take();
+        if let Some(tasks) = tasks {
+            tasks.close().await;
+        }
+    }
+
+    /// A handle for `system_id`'s event loop.
+    pub(crate) fn handle_for(&self, system_id: u8) -> Link {
+        self.inner.link.for_system(system_id)
+    }
+
+    pub(crate) fn udp_peer(&self) -> Option<watch::Receiver<Option<SocketAddr>>> {
+        self.inner.udp_peer.clone()
+    }
+}
+
+#[allow(deprecated)]
+pub(crate) fn classify(message: &MavMessage) -> SendPriority {
+    match message {
+        MavMessage::COMMAND_LONG(_)
+        | MavMessage::COMMAND_INT(_)
+        | MavMessage::SET_MODE(_)
+        | MavMessage::SET_POSITION_TARGET_GLOBAL_INT(_)
+        | MavMessage::SET_POSITION_TARGET_LOCAL_NED(_)
+        | MavMessage::MANUAL_CONTROL(_)
+        | MavMessage::RC_CHANNELS_OVERRIDE(_) => SendPriority::Control,
+        MavMessage::HEARTBEAT(_) => SendPriority::Heartbeat,
+        MavMessage::MISSION_COUNT(_)
+        | MavMessage::MISSION_ITEM(_)
+        | MavMessage::MISSION_ITEM_INT(_)
+        | MavMessage::MISSION_REQUEST(_)
+        | MavMessage::MISSION_REQUEST_INT(_)
+        | MavMessage::MISSION_REQUEST_LIST(_)
+        | MavMessage::MISSION_ACK(_)
+        | MavMessage::MISSION_CLEAR_ALL(_)
+        | MavMessage::MISSION_SET_CURRENT(_)
+        | MavMessage::PARAM_REQUEST_LIST(_)
+        | MavMessage::PARAM_REQUEST_READ(_)
+        | MavMessage::PARAM_SET(_) => SendPriority::Transfer,
+        _ => SendPriority::Bulk,
+    }
+}
+
+fn wire_size(message: &MavMessage) -> usize {
+    let mut payload = [0u8; 255];
+    message.ser(MavlinkVersion::V2, &mut payload) + V2_FRAMING_BYTES
+}
+
+async fn run_reader(
+    connection: Arc<Connection>,
+    tx: broadcast::Sender<Inbound>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let result = tokio::select! {
+            _ = cancel.cancelled() => return,
+            result = connection.recv() => result,
+        };
+        let inbound = result.map(Arc::new).map_err(|err| err.to_string());
+        let failed = inbound.is_err();
+        // Stop once every handle is gone, or after reporting a read error
+        if tx.send(inbound).is_err() || failed {
+            return;
+        }
+    }
+}
+
+async fn run_sender(
+    writer: Writer,
+    mut rx: mpsc::UnboundedReceiver<(SendPriority, MavHeader, MavMessage)>,
+    mut pacing: watch::Receiver<LinkPacing>,
+    written: watch::Sender<u64>,
+    cancel: CancellationToken,
+) {
+    let mut queue: PriorityQueue<(MavHeader, MavMessage)> = PriorityQueue::default();
+    let mut bucket = TokenBucket::new(*pacing.borrow_and_update(), Instant::now());
+
+    loop {
+        if pacing.has_changed().unwrap_or(false) {
+            bucket.set_pacing(*pacing.borrow_and_update(), Instant::now());
+        }
+        while let Ok((priority, header, message)) = rx.try_recv() {
+            queue.push(priority, (header, message));
+        }
+
+        let delay = match queue.peek() {
+            Some((_, message)) => bucket.delay_for(wire_size(message), Instant::now()),
+            None => {
+                tokio::select! {
+                    _ = cancel.cancelled() => return,
+                    item = rx.recv() => match item {
+                        Some((priority, header, message)) => queue.push(priority, (header, message)),
+                        None => return,
+                    },
+                }
+                continue;
+            }
+        };
+
+        if !delay.is_zero() {
+            // Wake early if something new arrives; it may outrank the head
+            tokio::select! {
+                _ = cancel.cancelled() => return,
+                _ = tokio::time::sleep(delay) => {}
+                item = rx.recv() => match item {
+                    Some((priority, header, message)) => queue.push(priority, (header, message)),
+                    None => return,
+                },
+            }
+            continue;
+        }
+
+        let Some((header, message)) = queue.pop() else {
+            continue;
+        };
+        bucket.consume(wire_size(&message), Instant::now());
+        if let Err(err) = writer.send(&header, &message).await {
+            warn!("MAVLink send error ({}): {err}", message.message_name());
+        }
+        written.send_modify(|n| *n += 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mavlink::ardupilotmega as common;
+
+    #[test]
+    fn classifies_control_above_transfer() {
+        let command = MavMessage::COMMAND_LONG(common::COMMAND_LONG_DATA::default());
+        let item = MavMessage::MISSION_ITEM_INT(common::MISSION_ITEM_INT_DATA::default());
+        let heartbeat = MavMessage::HEARTBEAT(common::HEARTBEAT_DATA::default());
+        let status = MavMessage::STATUSTEXT(common::STATUSTEXT_DATA::default());
+        assert_eq!(classify(&command), SendPriority::Control);
+        assert_eq!(classify(&heartbeat), SendPriority::Heartbeat);
+        assert_eq!(classify(&item), SendPriority::Transfer);
+        assert_eq!(classify(&status), SendPriority::Bulk);
+        assert!(classify(&command) < classify(&item));
+    }
+
+    #[tokio::test]
+    async fn lagged_handle_reports_dropped_messages() {
+        let (outgoing, _queued) = mpsc::unbounded_channel();
+        let (tx, incoming) = broadcast::channel(2);
+        let mut link = Link {
+            outgoing,
+            incoming,
+            queued: Arc::default(),
+            filter: None,
+            dedup: None,
+        };
+        let heartbeat = MavMessage::HEARTBEAT(common::HEARTBEAT_DATA::default());
+        let received = Arc::new((MavHeader::default(), heartbeat));
+        for _ in 0..3 {
+            tx.send(Ok(received.clone())).unwrap();
+        }
+        assert!(matches!(
+            link.recv().await,
+            Err(VehicleError::LinkLagged { skipped: 1 })
+        ));
+        // The messages still queued follow
+        assert!(link.recv().await.is_ok());
+        assert!(link.recv().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn system_handle_skips_other_vehicles() {
+        let (outgoing, _queued) = mpsc::unbounded_channel();
+        let (tx, incoming) = broadcast::channel(8);
+        let link = Link {
+            outgoing,
+            incoming,
+            queued: Arc::default(),
+            filter: None,
+            dedup: None,
+        };
+        let mut vehicle = link.for_system(2);
+        let from = |system_id, mavtype| {
+            let heartbeat = MavMessage::HEARTBEAT(common::HEARTBEAT_DATA {
+                mavtype,
+                ..Default::default()
+            });
+            let header = MavHeader {
+                system_id,
+                ..MavHeader::default()
+            };
+            Ok(Arc::new((header, heartbeat)))
+        };
+        tx.send(from(1, MavType::MAV_TYPE_QUADROTOR)).unwrap();
+        tx.send(from(2, MavType::MAV_TYPE_QUADROTOR)).unwrap();
+        tx.send(from(255, MavType::MAV_TYPE_GCS)).unwrap();
+        tx.send(from(3, MavType::MAV_TYPE_FIXED_WING)).unwrap();
+
+        assert_eq!(vehicle.recv().await.unwrap().0.system_id, 2);
+        assert_eq!(vehicle.recv().await.unwrap().0.system_id, 255);
+        assert_eq!(vehicle.system_id(), Some(2));
+        drop(tx);
+        assert!(matches!(
+            vehicle.recv().await,
+            Err(VehicleError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn wire_size_includes_framing() {
+        let heartbeat = MavMessage::HEARTBEAT(common::HEARTBEAT_DATA::default());
+        // HEARTBEAT payload is 9 bytes, but v2 truncates trailing zeros
+        assert!(wire_size(&heartbeat) >= V2_FRAMING_BYTES + 1);
+        assert!(wire_size(&heartbeat) <= V2_FRAMING_BYTES + 9);
+    }
+}
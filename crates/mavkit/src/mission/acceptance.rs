@@ -0,0 +1,325 @@
+//! Pre-upload check against ArduPilot's acceptance rules.
+//!
+//! Replicates the common reasons ArduPilot denies an upload (or accepts it
+//! but refuses to fly it), so the operator gets an error naming the item
+//! instead of a bare `MISSION_ACK` denial.
+
+use super::builder::{
+    MAV_CMD_DO_SET_ROI_LOCATION, MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION,
+    MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION, MAV_CMD_NAV_FENCE_RETURN_POINT, MAV_CMD_NAV_TAKEOFF,
+    MAV_CMD_NAV_VTOL_TAKEOFF,
+};
+use super::fence::Fence;
+use super::types::{IssueSeverity, MissionFrame, MissionIssue, MissionPlan, MissionType};
+use crate::params::ParamStore;
+use crate::state::VehicleType;
+use serde::{Deserialize, Serialize};
+
+const MAV_CMD_DO_SET_HOME: u16 = 179;
+const MAV_CMD_DO_LAND_START: u16 = 189;
+const MAV_CMD_DO_SET_ROI: u16 = 201;
+/// First non-NAV command id; ArduPilot treats everything below as NAV.
+const MAV_CMD_NAV_LAST: u16 = 95;
+/// FENCE_TYPE bit for the maximum altitude fence.
+const FENCE_TYPE_ALT_MAX: u32 = 1;
+
+/// What the target autopilot enforces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArduPilotProfile {
+    pub vehicle_type: VehicleType,
+    /// Altitude fence ceiling relative to home, when enabled.
+    pub fence_alt_max_m: Option<f32>,
+}
+
+impl ArduPilotProfile {
+    /// Profile from the vehicle's parameters. The ceiling is only set when
+    /// FENCE_TYPE enables the altitude fence.
+    pub fn from_params(store: &ParamStore, vehicle_type: VehicleType) -> Self {
+        let value = |name: &str| store.params.get(name).map(|p| p.value);
+        let fence_alt_max_m = value("FENCE_TYPE")
+            .filter(|fence_type| *fence_type as u32 & FENCE_TYPE_ALT_MAX != 0)
+            .and(value("FENCE_ALT_MAX"));
+        Self {
+            vehicle_type,
+            fence_alt_max_m,
+        }
+    }
+}
+
+/// ArduPilot stores these commands with a location, so their frame must be
+/// one it can convert.
+fn has_location(command: u16) -> bool {
+    command < MAV_CMD_NAV_LAST
+        || matches!(
+            command,
+            MAV_CMD_DO_SET_HOME
+                | MAV_CMD_DO_LAND_START
+                | MAV_CMD_DO_SET_ROI_LOCATION
+                | MAV_CMD_DO_SET_ROI
+        )
+}
+
+fn issue(code: &str, message: String, seq: Option<u16>) -> MissionIssue {
+    MissionIssue {
+        code: code.to_string(),
+        message,
+        seq,
+        severity: IssueSeverity::Error,
+    }
+}
+
+/// Issues ArduPilot would raise for `plan`, either by denying the upload or
+/// by refusing to run it.
+pub fn validate_ardupilot_acceptance(
+    plan: &MissionPlan,
+    profile: &ArduPilotProfile,
+) -> Vec<MissionIssue> {
+    let mut issues: Vec<MissionIssue> = plan
+        .items
+        .iter()
+        .filter(|item| {
+            has_location(item.command)
+                && matches!(item.frame, MissionFrame::LocalNed | MissionFrame::Other)
+        })
+        .map(|item| {
+            issue(
+                "ardupilot.unsupported_frame",
+                format!(
+                    "Command {} needs a global frame; ArduPilot rejects {:?}",
+                    item.command, item.frame
+                ),
+                Some(item.seq),
+            )
+        })
+        .collect();
+
+    match plan.mission_type {
+        MissionType::Mission => {
+            if profile.vehicle_type == VehicleType::FixedWing {
+                let first_nav = plan
+                    .items
+                    .iter()
+                    .find(|item| item.command < MAV_CMD_NAV_LAST);
+                if let Some(item) = first_nav.filter(|item| {
+                    !matches!(item.command, MAV_CMD_NAV_TAKEOFF | MAV_CMD_NAV_VTOL_TAKEOFF)
+                }) {
+                    issues.push(issue(
+                        "ardupilot.plane_missing_takeoff",
+                        "Plane AUTO needs a takeoff as the first navigation item".to_string(),
+                        Some(item.seq),
+                    ));
+                }
+            }
+        }
+        MissionType::Fence => {
+            for seq in Fence::from_plan(plan).skipped {
+                let circle = plan.items.iter().any(|item| {
+                    item.seq == seq
+                        && matches!(
+                            item.command,
+                            MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION | MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION
+                        )
+                });
+                issues.push(if circle {
+                    issue(
+                        "ardupilot.fence_circle_radius",
+                        "Fence circle needs a positive radius".to_string(),
+                        Some(seq),
+                    )
+                } else {
+                    issue(
+                        "ardupilot.fence_polygon_not_closed",
+                        "Fence polygon vertex count does not match its vertices (at least 3 needed)"
+                            .to_string(),
+                        Some(seq),
+                    )
+                });
+            }
+            let mut return_points = plan
+                .items
+                .iter()
+                .filter(|item| item.command == MAV_CMD_NAV_FENCE_RETURN_POINT)
+                .skip(1);
+            if let Some(item) = return_points.next() {
+                issues.push(issue(
+                    "ardupilot.fence_multiple_return_points",
+                    "Fence has more than one return point".to_string(),
+                    Some(item.seq),
+                ));
+            }
+        }
+        MissionType::Rally => {
+            let Some(ceiling) = profile.fence_alt_max_m else {
+                return issues;
+            };
+            let home_amsl = plan
+                .home
+                .as_ref()
+                .and_then(|home| home.altitude_amsl_m(None));
+            for item in &plan.items {
+                let alt_m = match item.frame {
+                    MissionFrame::GlobalRelativeAltInt => item.z,
+                    MissionFrame::GlobalInt | MissionFrame::Mission => match home_amsl {
+                        Some(home) => item.z - home,
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                if alt_m > ceiling {
+                    issues.push(issue(
+                        "ardupilot.rally_above_fence_ceiling",
+                        format!(
+                            "Rally point is {alt_m:.0} m above home, over the {ceiling:.0} m fence ceiling"
+                        ),
+                        Some(item.seq),
+                    ));
+                }
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{
+        command_item, global_item, MAV_CMD_DO_CHANGE_SPEED,
+        MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION, MAV_CMD_NAV_WAYPOINT,
+    };
+    use crate::mission::HomePosition;
+    use crate::params::{Param, ParamType};
+
+    fn home() -> Option<HomePosition> {
+        Some(HomePosition::amsl(47.0, 8.0, 400.0))
+    }
+
+    fn codes(issues: &[MissionIssue]) -> Vec<(&str, Option<u16>)> {
+        issues
+            .iter()
+            .map(|issue| (issue.code.as_str(), issue.seq))
+            .collect()
+    }
+
+    fn plane() -> ArduPilotProfile {
+        ArduPilotProfile {
+            vehicle_type: VehicleType::FixedWing,
+            fence_alt_max_m: None,
+        }
+    }
+
+    #[test]
+    fn plane_mission_must_start_with_takeoff() {
+        let waypoint = global_item(MAV_CMD_NAV_WAYPOINT, 47.001, 8.0, 50.0);
+        let speed = command_item(MAV_CMD_DO_CHANGE_SPEED, [0.0, 20.0, -1.0, 0.0]);
+        let missing = MissionPlan::for_test(
+            MissionType::Mission,
+            home(),
+            vec![speed.clone(), waypoint.clone()],
+        );
+        assert_eq!(
+            codes(&validate_ardupilot_acceptance(&missing, &plane())),
+            [("ardupilot.plane_missing_takeoff", Some(1))]
+        );
+
+        let copter = ArduPilotProfile {
+            vehicle_type: VehicleType::Quadrotor,
+            ..plane()
+        };
+        assert!(validate_ardupilot_acceptance(&missing, &copter).is_empty());
+
+        let takeoff = global_item(MAV_CMD_NAV_TAKEOFF, 0.0, 0.0, 30.0);
+        let ok =
+            MissionPlan::for_test(MissionType::Mission, home(), vec![speed, takeoff, waypoint]);
+        assert!(validate_ardupilot_acceptance(&ok, &plane()).is_empty());
+    }
+
+    #[test]
+    fn location_commands_need_a_global_frame() {
+        let mut local = global_item(MAV_CMD_NAV_WAYPOINT, 47.001, 8.0, 50.0);
+        local.frame = MissionFrame::LocalNed;
+        let mut speed = command_item(MAV_CMD_DO_CHANGE_SPEED, [0.0, 20.0, -1.0, 0.0]);
+        speed.frame = MissionFrame::Other;
+        let mission = MissionPlan::for_test(MissionType::Mission, home(), vec![local, speed]);
+        assert_eq!(
+            codes(&validate_ardupilot_acceptance(
+                &mission,
+                &ArduPilotProfile::default()
+            )),
+            [("ardupilot.unsupported_frame", Some(0))]
+        );
+    }
+
+    #[test]
+    fn fence_polygons_must_be_closed_with_one_return_point() {
+        let vertex = |lat, lon, count| {
+            let mut item = global_item(MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION, lat, lon, 0.0);
+            item.param1 = count;
+            item
+        };
+        let return_point = global_item(MAV_CMD_NAV_FENCE_RETURN_POINT, 47.0, 8.0, 0.0);
+        let mut circle = global_item(MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION, 47.0, 8.0, 0.0);
+        circle.param1 = 0.0;
+        let fence = MissionPlan::for_test(
+            MissionType::Fence,
+            home(),
+            vec![
+                return_point.clone(),
+                vertex(47.0, 8.0, 4.0),
+                vertex(47.01, 8.0, 4.0),
+                vertex(47.01, 8.01, 4.0),
+                circle,
+                return_point,
+            ],
+        );
+        assert_eq!(
+            codes(&validate_ardupilot_acceptance(
+                &fence,
+                &ArduPilotProfile::default()
+            )),
+            [
+                ("ardupilot.fence_polygon_not_closed", Some(1)),
+                ("ardupilot.fence_polygon_not_closed", Some(2)),
+                ("ardupilot.fence_polygon_not_closed", Some(3)),
+                ("ardupilot.fence_circle_radius", Some(4)),
+                ("ardupilot.fence_multiple_return_points", Some(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rally_points_stay_below_the_fence_ceiling() {
+        let mut store = ParamStore::default();
+        for (name, value) in [("FENCE_TYPE", 7.0), ("FENCE_ALT_MAX", 100.0)] {
+            store.params.insert(
+                name.to_string(),
+                Param {
+                    name: name.to_string(),
+                    value,
+                    param_type: ParamType::Real32,
+                    index: 0,
+                },
+            );
+        }
+        let profile = ArduPilotProfile::from_params(&store, VehicleType::Quadrotor);
+        assert_eq!(profile.fence_alt_max_m, Some(100.0));
+
+        let low = global_item(MAV_CMD_NAV_WAYPOINT, 47.001, 8.0, 80.0);
+        let high = global_item(MAV_CMD_NAV_WAYPOINT, 47.002, 8.0, 120.0);
+        let mut absolute = global_item(MAV_CMD_NAV_WAYPOINT, 47.003, 8.0, 550.0);
+        absolute.frame = MissionFrame::GlobalInt;
+        let rally = MissionPlan::for_test(MissionType::Rally, home(), vec![low, high, absolute]);
+        assert_eq!(
+            codes(&validate_ardupilot_acceptance(&rally, &profile)),
+            [
+                ("ardupilot.rally_above_fence_ceiling", Some(1)),
+                ("ardupilot.rally_above_fence_ceiling", Some(2)),
+            ]
+        );
+
+        // Altitude fence disabled
+        store.params.get_mut("FENCE_TYPE").unwrap().value = 2.0;
+        let profile = ArduPilotProfile::from_params(&store, VehicleType::Quadrotor);
+        assert!(validate_ardupilot_acceptance(&rally, &profile).is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+//! Helpers for constructing mission items in the planning generators.
+
+use super::precision::deg_to_e7;
+#[cfg(test)]
+use super::types::{HomePosition, MissionPlan, MissionType};
+use super::types::{MissionFrame, MissionItem};
+
+pub const MAV_CMD_NAV_WAYPOINT: u16 = 16;
+pub const MAV_CMD_NAV_LOITER_UNLIM: u16 = 17;
+pub const MAV_CMD_NAV_LOITER_TIME: u16 = 19;
+pub const MAV_CMD_NAV_RETURN_TO_LAUNCH: u16 = 20;
+pub const MAV_CMD_NAV_LAND: u16 = 21;
+pub const MAV_CMD_NAV_TAKEOFF: u16 = 22;
+pub const MAV_CMD_NAV_VTOL_TAKEOFF: u16 = 84;
+pub const MAV_CMD_NAV_VTOL_LAND: u16 = 85;
+pub const MAV_CMD_DO_CHANGE_SPEED: u16 = 178;
+pub const MAV_CMD_DO_SET_ROI_LOCATION: u16 = 195;
+pub const MAV_CMD_DO_SET_ROI_NONE: u16 = 197;
+pub const MAV_CMD_DO_MOUNT_CONTROL: u16 = 205;
+pub const MAV_CMD_DO_SET_CAM_TRIGG_DIST: u16 = 206;
+pub const MAV_CMD_DO_VTOL_TRANSITION: u16 = 3000;
+pub const MAV_CMD_NAV_FENCE_RETURN_POINT: u16 = 5000;
+pub const MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION: u16 = 5001;
+pub const MAV_CMD_NAV_FENCE_POLYGON_VERTEX_EXCLUSION: u16 = 5002;
+pub const MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION: u16 = 5003;
+pub const MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION: u16 = 5004;
+
+/// MAV_VTOL_STATE values used as DO_VTOL_TRANSITION param1.
+pub const MAV_VTOL_STATE_MC: f32 = 3.0;
+pub const MAV_VTOL_STATE_FW: f32 = 4.0;
+
+/// Build an item at a global position with relative altitude.
+pub fn global_item(command: u16, lat_deg: f64, lon_deg: f64, alt_m: f32) -> MissionItem {
+    MissionItem {
+        seq: 0,
+        command,
+        frame: MissionFrame::GlobalRelativeAltInt,
+        current: false,
+        autocontinue: true,
+        param1: 0.0,
+        param2: 0.0,
+        param3: 0.0,
+        param4: 0.0,
+        x: deg_to_e7(lat_deg),
+        y: deg_to_e7(lon_deg),
+        z: alt_m,
+        label: None,
+        notes: None,
+        downloaded_as_float: false,
+    }
+}
+
+/// Build a non-positional (DO_*) item with the given params 1-4.
+pub fn command_item(command: u16, params: [f32; 4]) -> MissionItem {
+    MissionItem {
+        seq: 0,
+        command,
+        frame: MissionFrame::Mission,
+        current: false,
+        autocontinue: true,
+        param1: params[0],
+        param2: params[1],
+        param3: params[2],
+        param4: params[3],
+        x: 0,
+        y: 0,
+        z: 0.0,
+        label: None,
+        notes: None,
+        downloaded_as_float: false,
+    }
+}
+
+/// Renumber items from 0 and mark the first as current.
+pub fn resequence(items: &mut [MissionItem]) {
+    for (i, item) in items.iter_mut().enumerate() {
+        item.seq = i as u16;
+        item.current = i == 0;
+    }
+}
+
+#[cfg(test)]
+impl MissionPlan {
+    /// Plan of `items`, resequenced, for tests.
+    pub(crate) fn for_test(
+        mission_type: MissionType,
+        home: Option<HomePosition>,
+        mut items: Vec<MissionItem>,
+    ) -> Self {
+        resequence(&mut items);
+        Self {
+            mission_type,
+            home,
+            items,
+            metadata: Default::default(),
+        }
+    }
+}
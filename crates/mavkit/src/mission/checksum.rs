@@ -0,0 +1,217 @@
+//! Whether the vehicle still holds the plan last exchanged with it, without
+//! downloading it again.
+//!
+//! Uploads and downloads record the plan's checksum together with the opaque
+//! id the vehicle reported for it (in MISSION_ACK or MISSION_COUNT). The
+//! vehicle changes that id whenever its stored plan changes and reports the
+//! current ids in MISSION_CURRENT, so comparing ids catches changes made by
+//! another GCS or over RC, and comparing checksums catches local edits.
+
+use super::types::{MissionFrame, MissionPlan, MissionType};
+use serde::{Deserialize, Serialize};
+
+/// The plan of one type as last uploaded to or downloaded from the vehicle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanSyncMarker {
+    /// The vehicle's opaque id for the plan; 0 when it doesn't report one.
+    pub opaque_id: u32,
+    /// `plan_checksum` of the plan.
+    pub checksum: u32,
+}
+
+/// One marker per mission type; `None` until a transfer of that type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanMarkers {
+    pub mission: Option<PlanSyncMarker>,
+    pub fence: Option<PlanSyncMarker>,
+    pub rally: Option<PlanSyncMarker>,
+}
+
+impl PlanMarkers {
+    pub fn get(&self, mission_type: MissionType) -> Option<PlanSyncMarker> {
+        match mission_type {
+            MissionType::Mission => self.mission,
+            MissionType::Fence => self.fence,
+            MissionType::Rally => self.rally,
+        }
+    }
+
+    pub fn set(&mut self, mission_type: MissionType, marker: PlanSyncMarker) {
+        let slot = match mission_type {
+            MissionType::Mission => &mut self.mission,
+            MissionType::Fence => &mut self.fence,
+            MissionType::Rally => &mut self.rally,
+        };
+        *slot = Some(marker);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanSyncStatus {
+    /// The vehicle holds exactly this plan.
+    InSync,
+    /// The plan was edited since it was last uploaded or downloaded.
+    LocalChanges,
+    /// The vehicle's plan changed since it was last uploaded or downloaded.
+    VehicleChanged,
+    /// Nothing was exchanged yet, or the vehicle doesn't report opaque ids.
+    Unknown,
+}
+
+/// CRC32 over the fields of `plan` that reach the vehicle. Home, sequence
+/// numbers and operator annotations are left out, so a plan checksums the
+/// same before upload and after download.
+pub fn plan_checksum(plan: &MissionPlan) -> u32 {
+    let mut crc = Crc32::default();
+    for item in &plan.items {
+        crc.update(&item.command.to_le_bytes());
+        crc.update(&[frame_code(item.frame), u8::from(item.autocontinue)]);
+        for param in [item.param1, item.param2, item.param3, item.param4] {
+            crc.update(&param.to_le_bytes());
+        }
+        crc.update(&item.x.to_le_bytes());
+        crc.update(&item.y.to_le_bytes());
+        crc.update(&item.z.to_le_bytes());
+    }
+    crc.finish()
+}
+
+/// Compare `plan` with what was last exchanged (`marker`) and with the
+/// vehicle's current opaque id for its type (0 when unknown).
+pub fn plan_sync_status(
+    plan: &MissionPlan,
+    marker: Option<PlanSyncMarker>,
+    vehicle_opaque_id: u32,
+) -> PlanSyncStatus {
+    let Some(marker) = marker else {
+        return PlanSyncStatus::Unknown;
+    };
+    if plan_checksum(plan) != marker.checksum {
+        return PlanSyncStatus::LocalChanges;
+    }
+    if marker.opaque_id == 0 || vehicle_opaque_id == 0 {
+        return PlanSyncStatus::Unknown;
+    }
+    if vehicle_opaque_id == marker.opaque_id {
+        PlanSyncStatus::InSync
+    } else {
+        PlanSyncStatus::VehicleChanged
+    }
+}
+
+fn frame_code(frame: MissionFrame) -> u8 {
+    match frame {
+        MissionFrame::Mission => 0,
+        MissionFrame::GlobalInt => 1,
+        MissionFrame::GlobalRelativeAltInt => 2,
+        MissionFrame::GlobalTerrainAltInt => 3,
+        MissionFrame::LocalNed => 4,
+        MissionFrame::Other => 5,
+    }
+}
+
+/// The zlib CRC32.
+struct Crc32(u32);
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+}
+
+impl Crc32 {
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u32::from(byte);
+            for _ in 0..8 {
+                self.0 = if self.0 & 1 != 0 {
+                    (self.0 >> 1) ^ 0xEDB8_8320
+                } else {
+                    self.0 >> 1
+                };
+            }
+        }
+    }
+
+    fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{global_item, MAV_CMD_NAV_WAYPOINT};
+
+    fn plan() -> MissionPlan {
+        MissionPlan::for_test(
+            MissionType::Mission,
+            None,
+            vec![
+                global_item(MAV_CMD_NAV_WAYPOINT, 47.0, 8.0, 30.0),
+                global_item(MAV_CMD_NAV_WAYPOINT, 47.001, 8.0, 30.0),
+            ],
+        )
+    }
+
+    #[test]
+    fn crc_matches_zlib() {
+        let mut crc = Crc32::default();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn checksum_ignores_annotations_but_not_positions() {
+        let original = plan();
+        let mut labelled = original.clone();
+        labelled.items[0].label = Some("start".to_string());
+        assert_eq!(plan_checksum(&original), plan_checksum(&labelled));
+
+        let mut moved = original.clone();
+        moved.items[1].z = 31.0;
+        assert_ne!(plan_checksum(&original), plan_checksum(&moved));
+    }
+
+    #[test]
+    fn status_compares_checksum_then_opaque_id() {
+        let plan = plan();
+        let marker = PlanSyncMarker {
+            opaque_id: 42,
+            checksum: plan_checksum(&plan),
+        };
+        assert_eq!(plan_sync_status(&plan, None, 42), PlanSyncStatus::Unknown);
+        assert_eq!(
+            plan_sync_status(&plan, Some(marker), 42),
+            PlanSyncStatus::InSync
+        );
+        assert_eq!(
+            plan_sync_status(&plan, Some(marker), 43),
+            PlanSyncStatus::VehicleChanged
+        );
+        assert_eq!(
+            plan_sync_status(&plan, Some(marker), 0),
+            PlanSyncStatus::Unknown
+        );
+
+        let mut edited = plan.clone();
+        edited.items.pop();
+        assert_eq!(
+            plan_sync_status(&edited, Some(marker), 42),
+            PlanSyncStatus::LocalChanges
+        );
+    }
+
+    #[test]
+    fn markers_are_kept_per_type() {
+        let mut markers = PlanMarkers::default();
+        let marker = PlanSyncMarker {
+            opaque_id: 7,
+            checksum: 1,
+        };
+        markers.set(MissionType::Fence, marker);
+        assert_eq!(markers.get(MissionType::Fence), Some(marker));
+        assert_eq!(markers.get(MissionType::Mission), None);
+    }
+}
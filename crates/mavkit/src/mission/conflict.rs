@@ -0,0 +1,272 @@
+//! Pre-launch deconfliction for multi-vehicle operations.
+//!
+//! Each plan is simulated and the timelines are compared second by second;
+//! two vehicles conflict while they are both airborne and closer than the
+//! separation both horizontally and vertically.
+
+use super::geo::distance_m;
+use super::simulate::{simulate, SimSample, SimTimeline, VehicleProfile};
+use super::types::MissionPlan;
+use crate::weather::WindProfile;
+use serde::{Deserialize, Serialize};
+
+/// Below this height above home a vehicle counts as on the ground.
+const AIRBORNE_ALT_M: f32 = 1.0;
+
+/// Minimum separation between two vehicles.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Separation {
+    pub horizontal_m: f64,
+    pub vertical_m: f64,
+}
+
+impl Default for Separation {
+    fn default() -> Self {
+        Self {
+            horizontal_m: 30.0,
+            vertical_m: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedFlight {
+    pub name: String,
+    pub plan: MissionPlan,
+    pub profile: VehicleProfile,
+    /// Launch time relative to the start of the operation, seconds.
+    #[serde(default)]
+    pub start_offset_s: u32,
+}
+
+/// Two vehicles closer than the separation for a stretch of time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    /// Indices into the checked flights.
+    pub flights: (usize, usize),
+    /// First and last conflicting second, from the start of the operation.
+    pub start_s: u32,
+    pub end_s: u32,
+    /// Second of closest approach.
+    pub closest_s: u32,
+    pub horizontal_m: f64,
+    pub vertical_m: f64,
+    /// Items each vehicle is flying to at the closest approach.
+    pub seqs: (Option<u16>, Option<u16>),
+    /// Midpoint of the two vehicles at the closest approach.
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+}
+
+/// Simulate every flight (through `wind` if given) and report each pair of
+/// vehicles that loses `separation`.
+///
+/// Altitudes are compared above mean sea level when every plan has an AMSL
+/// home, otherwise relative to each vehicle's home.
+pub fn detect_conflicts(
+    flights: &[PlannedFlight],
+    separation: &Separation,
+    wind: Option<&WindProfile>,
+) -> Vec<Conflict> {
+    let timelines: Vec<SimTimeline> = flights
+        .iter()
+        .map(|flight| simulate(&flight.plan, &flight.profile, wind))
+        .collect();
+    let home_amsl: Option<Vec<f64>> = flights
+        .iter()
+        .map(|flight| {
+            let home = flight.plan.home.as_ref()?;
+            home.altitude_amsl_m(None).map(f64::from)
+        })
+        .collect();
+
+    let mut conflicts = Vec::new();
+    for a in 0..flights.len() {
+        for b in a + 1..flights.len() {
+            let base = |index: usize| home_amsl.as_ref().map_or(0.0, |alts| alts[index]);
+            conflicts.extend(pair_conflicts(
+                (a, b),
+                [
+                    (&timelines[a], flights[a].start_offset_s, base(a)),
+                    (&timelines[b], flights[b].start_offset_s, base(b)),
+                ],
+                separation,
+            ));
+        }
+    }
+    conflicts
+}
+
+/// Airborne sample of a timeline at operation time `t_s`, with its altitude
+/// on the common datum.
+fn airborne(
+    (timeline, offset_s, base_alt): (&SimTimeline, u32, f64),
+    t_s: u32,
+) -> Option<(&SimSample, f64)> {
+    let sample = timeline.samples.get(t_s.checked_sub(offset_s)? as usize)?;
+    if sample.altitude_m < AIRBORNE_ALT_M {
+        return None;
+    }
+    Some((sample, base_alt + f64::from(sample.altitude_m)))
+}
+
+fn pair_conflicts(
+    flights: (usize, usize),
+    timelines: [(&SimTimeline, u32, f64); 2],
+    separation: &Separation,
+) -> Vec<Conflict> {
+    let end_s = timelines
+        .iter()
+        .map(|(timeline, offset_s, _)| offset_s + timeline.samples.len() as u32)
+        .min()
+        .unwrap_or(0);
+    let start_s = timelines
+        .iter()
+        .map(|(_, offset_s, _)| *offset_s)
+        .max()
+        .unwrap_or(0);
+
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    let mut open = false;
+    for t_s in start_s..end_s {
+        let (Some((a, alt_a)), Some((b, alt_b))) =
+            (airborne(timelines[0], t_s), airborne(timelines[1], t_s))
+        else {
+            open = false;
+            continue;
+        };
+        let horizontal = distance_m(
+            a.latitude_deg,
+            a.longitude_deg,
+            b.latitude_deg,
+            b.longitude_deg,
+        );
+        let vertical = (alt_a - alt_b).abs();
+        if horizontal >= separation.horizontal_m || vertical >= separation.vertical_m {
+            open = false;
+            continue;
+        }
+        let closest = Conflict {
+            flights,
+            start_s: t_s,
+            end_s: t_s,
+            closest_s: t_s,
+            horizontal_m: horizontal,
+            vertical_m: vertical,
+            seqs: (a.seq, b.seq),
+            latitude_deg: (a.latitude_deg + b.latitude_deg) / 2.0,
+            longitude_deg: (a.longitude_deg + b.longitude_deg) / 2.0,
+        };
+        match conflicts.last_mut() {
+            Some(current) if open => {
+                current.end_s = t_s;
+                // Closest by 3D distance
+                if horizontal.hypot(vertical) < current.horizontal_m.hypot(current.vertical_m) {
+                    *current = Conflict {
+                        start_s: current.start_s,
+                        ..closest
+                    };
+                }
+            }
+            _ => conflicts.push(closest),
+        }
+        open = true;
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{
+        global_item, resequence, MAV_CMD_NAV_TAKEOFF, MAV_CMD_NAV_WAYPOINT,
+    };
+    use crate::mission::geo::destination;
+    use crate::mission::{HomePosition, MissionType};
+
+    const ORIGIN: (f64, f64) = (47.0, 8.0);
+
+    /// Take off at `from` and fly `distance` metres along `bearing`.
+    fn flight(from: (f64, f64), bearing: f64, distance: f64, alt_m: f32) -> PlannedFlight {
+        let (lat, lon) = destination(from.0, from.1, bearing, distance);
+        let mut items = vec![
+            global_item(MAV_CMD_NAV_TAKEOFF, 0.0, 0.0, alt_m),
+            global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, alt_m),
+        ];
+        resequence(&mut items);
+        PlannedFlight {
+            name: String::new(),
+            plan: MissionPlan {
+                mission_type: MissionType::Mission,
+                home: Some(HomePosition::amsl(from.0, from.1, 400.0)),
+                items,
+                metadata: Default::default(),
+            },
+            profile: VehicleProfile::default(),
+            start_offset_s: 0,
+        }
+    }
+
+    #[test]
+    fn head_on_flights_conflict_midway() {
+        let east = destination(ORIGIN.0, ORIGIN.1, 90.0, 1000.0);
+        let flights = [
+            flight(ORIGIN, 90.0, 1000.0, 40.0),
+            flight(east, 270.0, 1000.0, 40.0),
+        ];
+        let conflicts = detect_conflicts(&flights, &Separation::default(), None);
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.flights, (0, 1));
+        // 16 s climb, then closing at 20 m/s from 1000 m
+        assert!((conflict.closest_s as i64 - 66).abs() <= 1, "{conflict:?}");
+        assert!(conflict.end_s - conflict.start_s <= 3);
+        assert!(conflict.horizontal_m < 20.0);
+        assert_eq!(conflict.seqs, (Some(1), Some(1)));
+        let midpoint = destination(ORIGIN.0, ORIGIN.1, 90.0, 500.0);
+        assert!(
+            distance_m(
+                conflict.latitude_deg,
+                conflict.longitude_deg,
+                midpoint.0,
+                midpoint.1
+            ) < 20.0
+        );
+    }
+
+    #[test]
+    fn altitude_or_time_separation_clears_conflict() {
+        let east = destination(ORIGIN.0, ORIGIN.1, 90.0, 1000.0);
+        let separation = Separation::default();
+
+        let stacked = [
+            flight(ORIGIN, 90.0, 1000.0, 40.0),
+            flight(east, 270.0, 1000.0, 60.0),
+        ];
+        assert!(detect_conflicts(&stacked, &separation, None).is_empty());
+
+        let mut staggered = [
+            flight(ORIGIN, 90.0, 1000.0, 40.0),
+            flight(east, 270.0, 1000.0, 40.0),
+        ];
+        staggered[1].start_offset_s = 200;
+        assert!(detect_conflicts(&staggered, &separation, None).is_empty());
+    }
+
+    #[test]
+    fn vehicles_on_the_ground_do_not_conflict() {
+        // Both climb from the same pad for 12 s, then split
+        let first = flight(ORIGIN, 0.0, 500.0, 30.0);
+        let mut second = flight(ORIGIN, 180.0, 500.0, 30.0);
+        let conflicts = detect_conflicts(
+            &[first.clone(), second.clone()],
+            &Separation::default(),
+            None,
+        );
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].start_s, 1);
+
+        second.start_offset_s = 60;
+        assert!(detect_conflicts(&[first, second], &Separation::default(), None).is_empty());
+    }
+}
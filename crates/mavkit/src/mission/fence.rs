@@ -0,0 +1,437 @@
+//! Cross-check of a mission against a geofence before upload.
+//!
+//! Zones are read from a fence plan (`MAV_CMD_NAV_FENCE_*` items). Each
+//! positioned mission item must lie inside every inclusion zone and outside
+//! every exclusion zone, as ArduPilot enforces them, and so must the straight
+//! legs between consecutive positioned items. Altitude limits live in
+//! parameters, not in the fence plan, and are not checked here.
+
+use super::builder::{
+    MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION, MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION,
+    MAV_CMD_NAV_FENCE_POLYGON_VERTEX_EXCLUSION, MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION,
+};
+use super::geo::{
+    local_distance, point_in_polygon, point_segment_distance, polygon_edges, segment_intersection,
+    LocalFrame,
+};
+use super::types::{IssueSeverity, MissionIssue, MissionPlan};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FenceZoneKind {
+    Inclusion,
+    Exclusion,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FenceShape {
+    /// Vertices as `(lat_deg, lon_deg)`.
+    Polygon {
+        vertices: Vec<(f64, f64)>,
+    },
+    Circle {
+        center: (f64, f64),
+        radius_m: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FenceZone {
+    pub kind: FenceZoneKind,
+    pub shape: FenceShape,
+    /// Sequence number of the zone's first item in the fence plan.
+    pub seq: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Fence {
+    pub zones: Vec<FenceZone>,
+    /// Fence items that could not be read into a zone (e.g. a polygon whose
+    /// vertex count does not match its items).
+    pub skipped: Vec<u16>,
+}
+
+impl Fence {
+    /// Zones described by a fence plan. The return point is ignored.
+    pub fn from_plan(plan: &MissionPlan) -> Self {
+        let mut fence = Fence::default();
+        let mut index = 0;
+        while index < plan.items.len() {
+            let item = &plan.items[index];
+            let kind =
+                match item.command {
+                    MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION
+                    | MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION => FenceZoneKind::Inclusion,
+                    MAV_CMD_NAV_FENCE_POLYGON_VERTEX_EXCLUSION
+                    | MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION => FenceZoneKind::Exclusion,
+                    _ => {
+                        index += 1;
+                        continue;
+                    }
+                };
+            if matches!(
+                item.command,
+                MAV_CMD_NAV_FENCE_CIRCLE_INCLUSION | MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION
+            ) {
+                if item.param1 > 0.0 {
+                    fence.zones.push(FenceZone {
+                        kind,
+                        shape: FenceShape::Circle {
+                            center: item.latlon_deg(),
+                            radius_m: item.param1 as f64,
+                        },
+                        seq: item.seq,
+                    });
+                } else {
+                    fence.skipped.push(item.seq);
+                }
+                index += 1;
+                continue;
+            }
+
+            // Polygon: param1 on every vertex holds the vertex count
+            let count = item.param1 as usize;
+            let vertices = plan.items[index..]
+                .iter()
+                .take(count)
+                .take_while(|vertex| vertex.command == item.command && vertex.param1 == item.param1)
+                .collect::<Vec<_>>();
+            if count >= 3 && vertices.len() == count {
+                fence.zones.push(FenceZone {
+                    kind,
+                    shape: FenceShape::Polygon {
+                        vertices: vertices.iter().map(|vertex| vertex.latlon_deg()).collect(),
+                    },
+                    seq: item.seq,
+                });
+                index += count;
+            } else {
+                let taken = vertices.len().max(1);
+                fence
+                    .skipped
+                    .extend(plan.items[index..index + taken].iter().map(|v| v.seq));
+                index += taken;
+            }
+        }
+        fence
+    }
+}
+
+/// A mission item or leg that violates a fence zone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FenceBreach {
+    /// The item in breach, or the item ending the leg.
+    pub seq: u16,
+    /// Item starting the leg, for a leg that leaves or enters a zone between
+    /// two compliant items.
+    pub leg_from: Option<u16>,
+    /// Index into `Fence::zones`.
+    pub zone: usize,
+    pub zone_kind: FenceZoneKind,
+    /// For an item, its distance to the zone boundary. For a leg, how far
+    /// along it the boundary is first crossed.
+    pub distance_m: f64,
+}
+
+/// Shape projected into a local frame, in metres.
+enum LocalShape {
+    Polygon(Vec<(f64, f64)>),
+    Circle((f64, f64), f64),
+}
+
+impl LocalShape {
+    fn contains(&self, p: (f64, f64)) -> bool {
+        match self {
+            LocalShape::Polygon(vertices) => point_in_polygon(p, vertices),
+            LocalShape::Circle(center, radius) => local_distance(p, *center) <= *radius,
+        }
+    }
+
+    fn boundary_distance(&self, p: (f64, f64)) -> f64 {
+        match self {
+            LocalShape::Polygon(vertices) => polygon_edges(vertices)
+                .map(|(a, b)| point_segment_distance(p, a, b))
+                .fold(f64::INFINITY, f64::min),
+            LocalShape::Circle(center, radius) => (local_distance(p, *center) - radius).abs(),
+        }
+    }
+
+    /// Fraction along `a`-`b` at which the boundary is first crossed.
+    fn first_crossing(&self, a: (f64, f64), b: (f64, f64)) -> Option<f64> {
+        match self {
+            LocalShape::Polygon(vertices) => polygon_edges(vertices)
+                .filter_map(|(c, d)| segment_intersection(a, b, c, d))
+                .min_by(f64::total_cmp),
+            LocalShape::Circle(center, radius) => {
+                // |a + t(b - a) - c|² = r²
+                let d = (b.0 - a.0, b.1 - a.1);
+                let f = (a.0 - center.0, a.1 - center.1);
+                let qa = d.0 * d.0 + d.1 * d.1;
+                let qb = 2.0 * (f.0 * d.0 + f.1 * d.1);
+                let qc = f.0 * f.0 + f.1 * f.1 - radius * radius;
+                let disc = qb * qb - 4.0 * qa * qc;
+                if qa == 0.0 || disc < 0.0 {
+                    return None;
+                }
+                let t = (-qb - disc.sqrt()) / (2.0 * qa);
+                (0.0..=1.0).contains(&t).then_some(t)
+            }
+        }
+    }
+}
+
+/// Items and legs of `plan` that breach `fence`. A leg is only reported when
+/// both of its items comply with the zone, so each violation appears once.
+pub fn fence_breaches(plan: &MissionPlan, fence: &Fence) -> Vec<FenceBreach> {
+    let Some(origin) = fence.zones.first().map(|zone| match &zone.shape {
+        FenceShape::Polygon { vertices } => vertices[0],
+        FenceShape::Circle { center, .. } => *center,
+    }) else {
+        return Vec::new();
+    };
+    let frame = LocalFrame::new(origin.0, origin.1);
+    let local = |(lat, lon): (f64, f64)| frame.to_local(lat, lon);
+    let shapes: Vec<LocalShape> = fence
+        .zones
+        .iter()
+        .map(|zone| match &zone.shape {
+            FenceShape::Polygon { vertices } => {
+                LocalShape::Polygon(vertices.iter().copied().map(local).collect())
+            }
+            FenceShape::Circle { center, radius_m } => {
+                LocalShape::Circle(local(*center), *radius_m)
+            }
+        })
+        .collect();
+
+    let positions: Vec<(u16, (f64, f64))> = plan
+        .items
+        .iter()
+        .filter(|item| item.is_nav_position())
+        .map(|item| (item.seq, local(item.latlon_deg())))
+        .collect();
+
+    let mut breaches = Vec::new();
+    for (zone_index, (zone, shape)) in fence.zones.iter().zip(&shapes).enumerate() {
+        let complies = |p| shape.contains(p) == (zone.kind == FenceZoneKind::Inclusion);
+        for (seq, p) in &positions {
+            if !complies(*p) {
+                breaches.push(FenceBreach {
+                    seq: *seq,
+                    leg_from: None,
+                    zone: zone_index,
+                    zone_kind: zone.kind,
+                    distance_m: shape.boundary_distance(*p),
+                });
+            }
+        }
+        for pair in positions.windows(2) {
+            let [(from_seq, a), (to_seq, b)] = pair else {
+                continue;
+            };
+            if !complies(*a) || !complies(*b) {
+                continue;
+            }
+            if let Some(t) = shape.first_crossing(*a, *b) {
+                breaches.push(FenceBreach {
+                    seq: *to_seq,
+                    leg_from: Some(*from_seq),
+                    zone: zone_index,
+                    zone_kind: zone.kind,
+                    distance_m: t * local_distance(*a, *b),
+                });
+            }
+        }
+    }
+    breaches.sort_by_key(|breach| (breach.seq, breach.leg_from.is_some(), breach.zone));
+    breaches
+}
+
+/// `fence_breaches` as validation issues, plus a warning for fence items that
+/// could not be read.
+pub fn validate_against_fence(plan: &MissionPlan, fence_plan: &MissionPlan) -> Vec<MissionIssue> {
+    let fence = Fence::from_plan(fence_plan);
+    let mut issues: Vec<MissionIssue> = fence
+        .skipped
+        .iter()
+        .map(|seq| MissionIssue {
+            code: "fence.malformed_zone".to_string(),
+            message: format!("Fence item {seq} does not form a valid zone and was ignored"),
+            seq: None,
+            severity: IssueSeverity::Warning,
+        })
+        .collect();
+    for breach in fence_breaches(plan, &fence) {
+        let zone_seq = fence.zones[breach.zone].seq;
+        let (code, message) = match (breach.leg_from, breach.zone_kind) {
+            (None, FenceZoneKind::Inclusion) => (
+                "fence.item_outside_inclusion",
+                format!(
+                    "Item is {:.0} m outside the inclusion zone at fence item {zone_seq}",
+                    breach.distance_m
+                ),
+            ),
+            (None, FenceZoneKind::Exclusion) => (
+                "fence.item_inside_exclusion",
+                format!(
+                    "Item is {:.0} m inside the exclusion zone at fence item {zone_seq}",
+                    breach.distance_m
+                ),
+            ),
+            (Some(from), FenceZoneKind::Inclusion) => (
+                "fence.leg_exits_inclusion",
+                format!(
+                    "Leg from item {from} leaves the inclusion zone at fence item {zone_seq} after {:.0} m",
+                    breach.distance_m
+                ),
+            ),
+            (Some(from), FenceZoneKind::Exclusion) => (
+                "fence.leg_enters_exclusion",
+                format!(
+                    "Leg from item {from} enters the exclusion zone at fence item {zone_seq} after {:.0} m",
+                    breach.distance_m
+                ),
+            ),
+        };
+        issues.push(MissionIssue {
+            code: code.to_string(),
+            message,
+            seq: Some(breach.seq),
+            severity: IssueSeverity::Error,
+        });
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{global_item, resequence, MAV_CMD_NAV_WAYPOINT};
+    use crate::mission::types::{MissionItem, MissionType};
+
+    const ORIGIN: (f64, f64) = (47.0, 8.0);
+
+    fn at(north_m: f64, east_m: f64) -> (f64, f64) {
+        let frame = LocalFrame::new(ORIGIN.0, ORIGIN.1);
+        frame.to_global(east_m, north_m)
+    }
+
+    fn waypoints(points: &[(f64, f64)]) -> MissionPlan {
+        MissionPlan::for_test(
+            MissionType::Mission,
+            None,
+            points
+                .iter()
+                .map(|(n, e)| {
+                    let (lat, lon) = at(*n, *e);
+                    global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, 50.0)
+                })
+                .collect(),
+        )
+    }
+
+    /// A 200 m square inclusion polygon centred on the origin and an
+    /// exclusion circle of 20 m radius 50 m north of it.
+    fn fence_plan() -> MissionPlan {
+        let mut items: Vec<MissionItem> = [
+            (-100.0, -100.0),
+            (-100.0, 100.0),
+            (100.0, 100.0),
+            (100.0, -100.0),
+        ]
+        .iter()
+        .map(|(n, e)| {
+            let (lat, lon) = at(*n, *e);
+            let mut item = global_item(MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION, lat, lon, 0.0);
+            item.param1 = 4.0;
+            item
+        })
+        .collect();
+        let (lat, lon) = at(50.0, 0.0);
+        let mut circle = global_item(MAV_CMD_NAV_FENCE_CIRCLE_EXCLUSION, lat, lon, 0.0);
+        circle.param1 = 20.0;
+        items.push(circle);
+        MissionPlan::for_test(MissionType::Fence, None, items)
+    }
+
+    #[test]
+    fn reads_zones_and_skips_malformed_polygons() {
+        let fence = Fence::from_plan(&fence_plan());
+        assert_eq!(fence.zones.len(), 2);
+        assert_eq!(fence.zones[1].kind, FenceZoneKind::Exclusion);
+        assert!(fence.skipped.is_empty());
+
+        let mut broken = fence_plan();
+        broken.items.remove(1);
+        resequence(&mut broken.items);
+        let fence = Fence::from_plan(&broken);
+        assert_eq!(fence.zones.len(), 1);
+        assert_eq!(fence.skipped, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn compliant_mission_has_no_breaches() {
+        let fence = Fence::from_plan(&fence_plan());
+        let mission = waypoints(&[(-50.0, -50.0), (-50.0, 50.0), (0.0, 80.0)]);
+        assert!(fence_breaches(&mission, &fence).is_empty());
+    }
+
+    #[test]
+    fn reports_items_outside_and_legs_through_zones() {
+        let fence = Fence::from_plan(&fence_plan());
+        // The first leg crosses the exclusion circle, touching it 70 m along;
+        // item 2 is 50 m outside the square
+        let mission = waypoints(&[(50.0, -90.0), (50.0, 90.0), (0.0, 150.0)]);
+        let breaches = fence_breaches(&mission, &fence);
+        assert_eq!(breaches.len(), 2, "{breaches:?}");
+
+        assert_eq!((breaches[0].seq, breaches[0].leg_from), (1, Some(0)));
+        assert_eq!(breaches[0].zone_kind, FenceZoneKind::Exclusion);
+        assert!((breaches[0].distance_m - 70.0).abs() < 0.5);
+
+        assert_eq!((breaches[1].seq, breaches[1].leg_from), (2, None));
+        assert_eq!(breaches[1].zone_kind, FenceZoneKind::Inclusion);
+        assert!((breaches[1].distance_m - 50.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn leg_leaving_concave_inclusion_is_reported() {
+        // U shape: the notch between the arms is outside the fence
+        let vertices = [
+            (0.0, 0.0),
+            (0.0, 300.0),
+            (200.0, 300.0),
+            (200.0, 200.0),
+            (50.0, 200.0),
+            (50.0, 100.0),
+            (200.0, 100.0),
+            (200.0, 0.0),
+        ];
+        let fence = MissionPlan::for_test(
+            MissionType::Fence,
+            None,
+            vertices
+                .iter()
+                .map(|(n, e)| {
+                    let (lat, lon) = at(*n, *e);
+                    let mut item =
+                        global_item(MAV_CMD_NAV_FENCE_POLYGON_VERTEX_INCLUSION, lat, lon, 0.0);
+                    item.param1 = vertices.len() as f32;
+                    item
+                })
+                .collect(),
+        );
+        let mission = waypoints(&[(150.0, 50.0), (150.0, 250.0)]);
+        let issues = validate_against_fence(&mission, &fence);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].code, "fence.leg_exits_inclusion");
+        assert_eq!(issues[0].seq, Some(1));
+        assert!(
+            issues[0].message.contains("after 50 m"),
+            "{}",
+            issues[0].message
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Small geodesy helpers shared by the planning generators.
+//!
+//! Distances use a spherical earth, which is accurate to well under 0.5% for
+//! the few-kilometre extents a mission covers.
+
+pub const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Great-circle distance between two points in degrees, in metres.
+pub fn distance_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let dphi = (lat2 - lat1).to_radians();
+    let dlambda = (lon2 - lon1).to_radians();
+    let a = (dphi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (dlambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
+/// Initial bearing from point 1 to point 2, degrees clockwise from north in [0, 360).
+pub fn bearing_deg(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let dlambda = (lon2 - lon1).to_radians();
+    let y = dlambda.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * dlambda.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Point reached by travelling `distance_m` along `bearing_deg` from a start point.
+pub fn destination(lat: f64, lon: f64, bearing_deg: f64, distance_m: f64) -> (f64, f64) {
+    let delta = distance_m / EARTH_RADIUS_M;
+    let theta = bearing_deg.to_radians();
+    let phi1 = lat.to_radians();
+    let lambda1 = lon.to_radians();
+    let phi2 = (phi1.sin() * delta.cos() + phi1.cos() * delta.sin() * theta.cos()).asin();
+    let lambda2 = lambda1
+        + (theta.sin() * delta.sin() * phi1.cos()).atan2(delta.cos() - phi1.sin() * phi2.sin());
+    let lon2 = (lambda2.to_degrees() + 540.0).rem_euclid(360.0) - 180.0;
+    (phi2.to_degrees(), lon2)
+}
+
+/// Local east/north tangent-plane projection around an origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalFrame {
+    origin_lat: f64,
+    origin_lon: f64,
+    cos_lat: f64,
+}
+
+impl LocalFrame {
+    pub fn new(origin_lat: f64, origin_lon: f64) -> Self {
+        Self {
+            origin_lat,
+            origin_lon,
+            cos_lat: origin_lat.to_radians().cos(),
+        }
+    }
+
+    /// Degrees to `(east_m, north_m)`.
+    pub fn to_local(&self, lat: f64, lon: f64) -> (f64, f64) {
+        let north = (lat - self.origin_lat).to_radians() * EARTH_RADIUS_M;
+        let east = (lon - self.origin_lon).to_radians() * EARTH_RADIUS_M * self.cos_lat;
+        (east, north)
+    }
+
+    /// `(east_m, north_m)` to degrees `(lat, lon)`.
+    pub fn to_global(&self, east: f64, north: f64) -> (f64, f64) {
+        let lat = self.origin_lat + (north / EARTH_RADIUS_M).to_degrees();
+        let lon = self.origin_lon + (east / (EARTH_RADIUS_M * self.cos_lat)).to_degrees();
+        (lat, lon)
+    }
+}
+
+/// Straight-line distance between two local `(east, north)` points, metres.
+pub fn local_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Edges of a closed polygon, last vertex back to the first.
+pub fn polygon_edges(
+    vertices: &[(f64, f64)],
+) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+/// Even-odd point-in-polygon test on local coordinates.
+pub fn point_in_polygon(p: (f64, f64), vertices: &[(f64, f64)]) -> bool {
+    let mut inside = false;
+    for (a, b) in polygon_edges(vertices) {
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) * (b.0 - a.0) / (b.1 - a.1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Distance from `p` to the segment `a`-`b`, local coordinates.
+pub fn point_segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let d = (b.0 - a.0, b.1 - a.1);
+    let len2 = d.0 * d.0 + d.1 * d.1;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * d.0 + (p.1 - a.1) * d.1) / len2).clamp(0.0, 1.0)
+    };
+    local_distance(p, (a.0 + t * d.0, a.1 + t * d.1))
+}
+
+/// Fraction along `a`-`b` where it crosses `c`-`d`, if it does.
+pub fn segment_intersection(
+    a: (f64, f64),
+    b: (f64, f64),
+    c: (f64, f64),
+    d: (f64, f64),
+) -> Option<f64> {
+    let r = (b.0 - a.0, b.1 - a.1);
+    let s = (d.0 - c.0, d.1 - c.1);
+    let denom = r.0 * s.1 - r.1 * s.0;
+    if denom == 0.0 {
+        return None;
+    }
+    let q = (c.0 - a.0, c.1 - a.1);
+    let t = (q.0 * s.1 - q.1 * s.0) / denom;
+    let u = (q.0 * r.1 - q.1 * r.0) / denom;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_one_degree_latitude() {
+        let d = distance_m(0.0, 0.0, 1.0, 0.0);
+        assert!((d - 111_195.0).abs() < 10.0, "got {d}");
+    }
+
+    #[test]
+    fn bearing_cardinal_directions() {
+        assert!((bearing_deg(0.0, 0.0, 1.0, 0.0) - 0.0).abs() < 1e-6);
+        assert!((bearing_deg(0.0, 0.0, 0.0, 1.0) - 90.0).abs() < 1e-6);
+        assert!((bearing_deg(0.0, 0.0, -1.0, 0.0) - 180.0).abs() < 1e-6);
+        assert!((bearing_deg(0.0, 0.0, 0.0, -1.0) - 270.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn destination_roundtrips_distance_and_bearing() {
+        let (lat, lon) = destination(47.397742, 8.545594, 63.0, 1234.0);
+        assert!((distance_m(47.397742, 8.545594, lat, lon) - 1234.0).abs() < 0.01);
+        assert!((bearing_deg(47.397742, 8.545594, lat, lon) - 63.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn local_frame_roundtrip() {
+        let frame = LocalFrame::new(47.397742, 8.545594);
+        let (lat, lon) = frame.to_global(120.0, -80.0);
+        let (east, north) = frame.to_local(lat, lon);
+        assert!((east - 120.0).abs() < 1e-6);
+        assert!((north + 80.0).abs() < 1e-6);
+        let d = distance_m(47.397742, 8.545594, lat, lon);
+        assert!((d - (120.0f64.hypot(80.0))).abs() < 0.1);
+    }
+}
@@ -0,0 +1,318 @@
+use super::{
+    diff_plans, normalize_for_compare, plan_sync_status, CompareTolerance, Fence, MissionPlan,
+    MissionType, PlanDiff, PlanSyncStatus, SyncOutcome, SyncPart, SyncReport, UploadOptions,
+};
+use crate::error::VehicleError;
+use crate::state::{Telemetry, VehicleState};
+use crate::Vehicle;
+use mavlink::ardupilotmega::MavCmd;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+const MISSION_CURRENT_MSG_ID: f32 = 42.0;
+/// How long `sync_status` waits for the requested MISSION_CURRENT.
+const MISSION_CURRENT_WAIT: Duration = Duration::from_secs(1);
+
+/// Handle to mission operations on a `Vehicle`.
+pub struct MissionHandle<'a> {
+    vehicle: &'a Vehicle,
+    allow_inflight_update: bool,
+    cancel: CancellationToken,
+}
+
+impl<'a> MissionHandle<'a> {
+    pub(crate) fn new(vehicle: &'a Vehicle) -> Self {
+        Self {
+            vehicle,
+            allow_inflight_update: false,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Allow `upload` to replace the mission while the vehicle is flying it.
+    pub fn allow_inflight_update(mut self, allow: bool) -> Self {
+        self.allow_inflight_update = allow;
+        self
+    }
+
+    /// End the transfers started through this handle with
+    /// `VehicleError::Cancelled` once `cancel` fires.
+    pub fn cancel_on(mut self, cancel: &CancellationToken) -> Self {
+        self.cancel = cancel.clone();
+        self
+    }
+
+    /// Upload a plan. Replacing the mission of a vehicle that is armed,
+    /// airborne and in AUTO is refused unless `allow_inflight_update` is set.
+    pub async fn upload(&self, plan: MissionPlan) -> Result<(), VehicleError> {
+        if plan.mission_type == MissionType::Mission && !self.allow_inflight_update {
+            let state = self.vehicle.state().borrow().clone();
+            let telemetry = self.vehicle.telemetry().borrow().clone();
+            if is_flying_mission(&state, &telemetry) {
+                return Err(VehicleError::MissionInFlight);
+            }
+        }
+        let fence = (plan.mission_type == MissionType::Fence).then(|| Fence::from_plan(&plan));
+        let cancel = self.cancel.clone();
+        self.vehicle
+            .send_command(|reply| crate::command::Command::MissionUpload {
+                plan,
+                cancel,
+                reply,
+            })
+            .await?;
+        if fence.is_some() {
+            self.vehicle.set_geofence(fence);
+        }
+        Ok(())
+    }
+
+    /// Upload a plan according to `options`. With `verify` set, the plan is
+    /// read back afterwards and the differences are returned (empty when the
+    /// vehicle holds exactly what was sent); otherwise returns `None`. With
+    /// `enable_fence`, a fence plan is then enabled; an error there means
+    /// the fence was uploaded but may not be active.
+    pub async fn upload_with(
+        &self,
+        plan: MissionPlan,
+        options: UploadOptions,
+    ) -> Result<Option<PlanDiff>, VehicleError> {
+        let enable_fence = options.enable_fence && plan.mission_type == MissionType::Fence;
+        let diff = if options.verify {
+            self.upload(plan.clone()).await?;
+            Some(self.read_back_diff(&plan).await?)
+        } else {
+            self.upload(plan).await?;
+            None
+        };
+        let read_back_matches = diff.as_ref().is_none_or(PlanDiff::is_empty);
+        if enable_fence && read_back_matches {
+            self.vehicle.fence().enable(true).await?;
+        }
+        Ok(diff)
+    }
+
+    /// Upload mission, then fence, then rally. `None` leaves that type on the
+    /// vehicle untouched. Stops at the first failure; the report says which
+    /// parts were uploaded.
+    pub async fn upload_all(
+        &self,
+        mission: MissionPlan,
+        fence: Option<MissionPlan>,
+        rally: Option<MissionPlan>,
+    ) -> SyncReport {
+        let plans = [
+            (MissionType::Mission, Some(mission)),
+            (MissionType::Fence, fence),
+            (MissionType::Rally, rally),
+        ];
+        let mut parts = Vec::with_capacity(plans.len());
+        let mut failed = false;
+        for (mission_type, plan) in plans {
+            let outcome = match plan {
+                None => SyncOutcome::Skipped,
+                Some(_) if failed => SyncOutcome::NotAttempted,
+                Some(plan) if plan.mission_type != mission_type => SyncOutcome::Failed {
+                    error: format!(
+                        "expected a {mission_type:?} plan, got {:?}",
+                        plan.mission_type
+                    ),
+                },
+                Some(plan) => match self.upload(plan).await {
+                    Ok(()) => SyncOutcome::Uploaded,
+                    Err(err) => SyncOutcome::Failed {
+                        error: err.to_string(),
+                    },
+                },
+            };
+            failed |= matches!(outcome, SyncOutcome::Failed { .. });
+            parts.push(SyncPart {
+                mission_type,
+                outcome,
+            });
+        }
+        SyncReport { parts }
+    }
+
+    pub async fn download(&self, mission_type: MissionType) -> Result<MissionPlan, VehicleError> {
+        let plan = self
+            .vehicle
+            .send_command(|reply| crate::command::Command::MissionDownload {
+                mission_type,
+                cancel: self.cancel.clone(),
+                reply,
+            })
+            .await?;
+        if mission_type == MissionType::Fence {
+            self.vehicle.set_geofence(Some(Fence::from_plan(&plan)));
+        }
+        Ok(plan)
+    }
+
+    pub async fn clear(&self, mission_type: MissionType) -> Result<(), VehicleError> {
+        self.vehicle
+            .send_command(|reply| crate::command::Command::MissionClear {
+                mission_type,
+                reply,
+            })
+            .await?;
+        if mission_type == MissionType::Fence {
+            self.vehicle.set_geofence(None);
+        }
+        Ok(())
+    }
+
+    pub async fn verify_roundtrip(&self, plan: MissionPlan) -> Result<bool, VehicleError> {
+        Ok(self.verify_roundtrip_detailed(plan).await?.is_empty())
+    }
+
+    /// Upload `plan`, download it again and report every item and field
+    /// that came back different.
+    pub async fn verify_roundtrip_detailed(
+        &self,
+        plan: MissionPlan,
+    ) -> Result<PlanDiff, VehicleError> {
+        self.upload(plan.clone()).await?;
+        self.read_back_diff(&plan).await
+    }
+
+    async fn read_back_diff(&self, plan: &MissionPlan) -> Result<PlanDiff, VehicleError> {
+        let readback = self.download(plan.mission_type).await?;
+        let mut expected = normalize_for_compare(plan);
+        let mut actual = normalize_for_compare(&readback);
+        // Autopilot may overwrite home position; compare items only
+        expected.home = None;
+        actual.home = None;
+        Ok(diff_plans(&expected, &actual, CompareTolerance::default()))
+    }
+
+    /// Whether the vehicle still holds `plan`, judged by the opaque id it
+    /// reports for the plan's type in MISSION_CURRENT rather than by
+    /// downloading. MISSION_CURRENT is requested first so the id is fresh;
+    /// autopilots that refuse the request still stream it.
+    pub async fn sync_status(&self, plan: &MissionPlan) -> Result<PlanSyncStatus, VehicleError> {
+        let mut state_rx = self.vehicle.mission_state();
+        state_rx.borrow_and_update();
+        let requested = self
+            .vehicle
+            .command_long(
+                MavCmd::MAV_CMD_REQUEST_MESSAGE,
+                [MISSION_CURRENT_MSG_ID, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            )
+            .await;
+        if requested.is_ok() {
+            let _ = tokio::time::timeout(MISSION_CURRENT_WAIT, state_rx.changed()).await;
+        }
+
+        let state = state_rx.borrow().clone();
+        let vehicle_id = match plan.mission_type {
+            MissionType::Mission => state.mission_id,
+            MissionType::Fence => state.fence_id,
+            MissionType::Rally => state.rally_points_id,
+        };
+        let marker = self.vehicle.plan_markers().borrow().get(plan.mission_type);
+        Ok(plan_sync_status(plan, marker, vehicle_id))
+    }
+
+    pub async fn set_current(&self, seq: u16) -> Result<(), VehicleError> {
+        self.vehicle
+            .send_command(|reply| crate::command::Command::MissionSetCurrent { seq, reply })
+            .await
+    }
+
+    /// Continue the mission from item `seq`: make it the current item, then
+    /// switch to the autopilot's mission mode unless already flying in it.
+    /// Together with the `mission_state` of MISSION_CURRENT this pauses and
+    /// resumes a mission without re-uploading it.
+    pub async fn resume_from(&self, seq: u16) -> Result<(), VehicleError> {
+        self.set_current(seq).await?;
+        let state = self.vehicle.state().borrow().clone();
+        if crate::modes::is_mission_mode(state.autopilot, state.vehicle_type, state.custom_mode) {
+            return Ok(());
+        }
+        let mode = crate::modes::mission_mode(state.autopilot, state.vehicle_type)
+            .ok_or_else(|| VehicleError::ModeNotAvailable("AUTO".to_string()))?;
+        self.vehicle.set_mode(mode).await
+    }
+}
+
+/// Armed, airborne (see `Telemetry::is_airborne`) and executing the mission
+/// in the autopilot's mission mode.
+fn is_flying_mission(state: &VehicleState, telemetry: &Telemetry) -> bool {
+    state.armed
+        && telemetry.is_airborne()
+        && crate::modes::is_mission_mode(state.autopilot, state.vehicle_type, state.custom_mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AutopilotType, LandedState, VehicleType};
+
+    fn copter_auto() -> VehicleState {
+        VehicleState {
+            armed: true,
+            custom_mode: 3,
+            mode_name: "AUTO".to_string(),
+            vehicle_type: VehicleType::Quadrotor,
+            autopilot: AutopilotType::ArduPilotMega,
+            ..VehicleState::default()
+        }
+    }
+
+    fn landed(state: LandedState) -> Telemetry {
+        Telemetry {
+            landed_state: Some(state),
+            ..Telemetry::default()
+        }
+    }
+
+    #[test]
+    fn armed_airborne_auto_is_flying_mission() {
+        assert!(is_flying_mission(
+            &copter_auto(),
+            &landed(LandedState::InAir)
+        ));
+        let px4_mission = VehicleState {
+            custom_mode: 0x0404_0000,
+            mode_name: "MODE(67371008)".to_string(),
+            autopilot: AutopilotType::Px4,
+            ..copter_auto()
+        };
+        assert!(is_flying_mission(&px4_mission, &landed(LandedState::InAir)));
+    }
+
+    #[test]
+    fn armed_on_the_ground_is_not_flying_mission() {
+        assert!(!is_flying_mission(
+            &copter_auto(),
+            &landed(LandedState::OnGround)
+        ));
+        let low = Telemetry {
+            altitude_m: Some(0.2),
+            ..Telemetry::default()
+        };
+        assert!(!is_flying_mission(&copter_auto(), &low));
+        let high = Telemetry {
+            altitude_m: Some(30.0),
+            ..Telemetry::default()
+        };
+        assert!(is_flying_mission(&copter_auto(), &high));
+    }
+
+    #[test]
+    fn disarmed_or_other_modes_are_not_flying_mission() {
+        let in_air = landed(LandedState::InAir);
+        let disarmed = VehicleState {
+            armed: false,
+            ..copter_auto()
+        };
+        let loiter = VehicleState {
+            custom_mode: 5,
+            mode_name: "LOITER".to_string(),
+            ..copter_auto()
+        };
+        assert!(!is_flying_mission(&disarmed, &in_air));
+        assert!(!is_flying_mission(&loiter, &in_air));
+    }
+}
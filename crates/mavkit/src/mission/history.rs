@@ -0,0 +1,242 @@
+use super::types::MissionPlan;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HISTORY_DIR: &str = ".plan-history";
+const INDEX_FILE: &str = "index.json";
+const DEFAULT_MAX_SNAPSHOTS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanSnapshot {
+    pub id: u64,
+    /// FNV-1a hash of the serialized plan, hex encoded.
+    pub hash: String,
+    pub created_at_ms: u64,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryIndex {
+    next_id: u64,
+    snapshots: Vec<PlanSnapshot>,
+}
+
+/// Snapshot history of a working `MissionPlan`, persisted under
+/// `<project_dir>/.plan-history/`.
+///
+/// Consecutive snapshots with identical content are deduplicated by hash, and
+/// the oldest snapshots are pruned once `max_snapshots` is exceeded.
+pub struct PlanHistory {
+    dir: PathBuf,
+    max_snapshots: usize,
+    index: HistoryIndex,
+}
+
+impl PlanHistory {
+    /// Open (or create) the history for a project directory.
+    pub fn open(project_dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = project_dir.as_ref().join(HISTORY_DIR);
+        fs::create_dir_all(&dir)?;
+        let index_path = dir.join(INDEX_FILE);
+        let index = if index_path.exists() {
+            let contents = fs::read_to_string(&index_path)?;
+            serde_json::from_str(&contents).map_err(invalid_data)?
+        } else {
+            HistoryIndex::default()
+        };
+        Ok(Self {
+            dir,
+            max_snapshots: DEFAULT_MAX_SNAPSHOTS,
+            index,
+        })
+    }
+
+    pub fn with_max_snapshots(mut self, max_snapshots: usize) -> Self {
+        self.max_snapshots = max_snapshots.max(1);
+        self
+    }
+
+    /// Snapshots, oldest first.
+    pub fn list(&self) -> &[PlanSnapshot] {
+        &self.index.snapshots
+    }
+
+    pub fn latest(&self) -> Option<&PlanSnapshot> {
+        self.index.snapshots.last()
+    }
+
+    /// Record `plan` as a new snapshot. Returns `None` when the plan is
+    /// identical to the most recent snapshot.
+    pub fn snapshot(
+        &mut self,
+        plan: &MissionPlan,
+        label: Option<String>,
+    ) -> io::Result<Option<PlanSnapshot>> {
+        let json = serde_json::to_string_pretty(plan).map_err(invalid_data)?;
+        let hash = format!("{:016x}", fnv1a(json.as_bytes()));
+        if self.latest().is_some_and(|latest| latest.hash == hash) {
+            return Ok(None);
+        }
+
+        let snapshot = PlanSnapshot {
+            id: self.index.next_id,
+            hash,
+            created_at_ms: now_ms(),
+            label,
+        };
+        fs::write(self.snapshot_path(snapshot.id), json)?;
+        self.index.next_id += 1;
+        self.index.snapshots.push(snapshot.clone());
+
+        while self.index.snapshots.len() > self.max_snapshots {
+            let removed = self.index.snapshots.remove(0);
+            let _ = fs::remove_file(self.snapshot_path(removed.id));
+        }
+
+        self.write_index()?;
+        Ok(Some(snapshot))
+    }
+
+    /// Load the plan stored in snapshot `id`.
+    pub fn restore(&self, id: u64) -> io::Result<MissionPlan> {
+        if !self.index.snapshots.iter().any(|s| s.id == id) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no plan snapshot with id {id}"),
+            ));
+        }
+        let contents = fs::read_to_string(self.snapshot_path(id))?;
+        serde_json::from_str(&contents).map_err(invalid_data)
+    }
+
+    fn snapshot_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{id:06}.json"))
+    }
+
+    fn write_index(&self) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.index).map_err(invalid_data)?;
+        // Write-then-rename so a crash mid-write never leaves a truncated index
+        let tmp = self.dir.join(format!("{INDEX_FILE}.tmp"));
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, self.dir.join(INDEX_FILE))
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn invalid_data(err: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::{MissionFrame, MissionItem, MissionType};
+
+    fn temp_project(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "mavkit-history-{name}-{}-{}",
+            std::process::id(),
+            now_ms()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn plan_with_alt(z: f32) -> MissionPlan {
+        MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items: vec![MissionItem {
+                seq: 0,
+                command: 16,
+                frame: MissionFrame::GlobalRelativeAltInt,
+                current: true,
+                autocontinue: true,
+                param1: 0.0,
+                param2: 0.0,
+                param3: 0.0,
+                param4: 0.0,
+                x: 473977420,
+                y: 85455940,
+                z,
+                label: None,
+                notes: None,
+                downloaded_as_float: false,
+            }],
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn identical_consecutive_snapshots_are_deduplicated() {
+        let dir = temp_project("dedup");
+        let mut history = PlanHistory::open(&dir).unwrap();
+        assert!(history
+            .snapshot(&plan_with_alt(10.0), None)
+            .unwrap()
+            .is_some());
+        assert!(history
+            .snapshot(&plan_with_alt(10.0), None)
+            .unwrap()
+            .is_none());
+        assert!(history
+            .snapshot(&plan_with_alt(20.0), None)
+            .unwrap()
+            .is_some());
+        assert_eq!(history.list().len(), 2);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn restore_returns_snapshot_contents_after_reopen() {
+        let dir = temp_project("restore");
+        let first_id = {
+            let mut history = PlanHistory::open(&dir).unwrap();
+            let first = history
+                .snapshot(&plan_with_alt(10.0), Some("before edit".to_string()))
+                .unwrap()
+                .unwrap();
+            history.snapshot(&plan_with_alt(55.0), None).unwrap();
+            first.id
+        };
+
+        let history = PlanHistory::open(&dir).unwrap();
+        assert_eq!(history.list().len(), 2);
+        assert_eq!(history.list()[0].label.as_deref(), Some("before edit"));
+        let restored = history.restore(first_id).unwrap();
+        assert_eq!(restored, plan_with_alt(10.0));
+        assert!(history.restore(999).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn prunes_oldest_beyond_limit() {
+        let dir = temp_project("prune");
+        let mut history = PlanHistory::open(&dir).unwrap().with_max_snapshots(2);
+        for alt in [10.0, 20.0, 30.0] {
+            history.snapshot(&plan_with_alt(alt), None).unwrap();
+        }
+        let ids: Vec<u64> = history.list().iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert!(history.restore(0).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,177 @@
+//! Plan linting for CI.
+//!
+//! `validate_plan_report` runs every offline check that applies to a plan
+//! and summarises the result, so a pipeline can store the JSON report and
+//! fail the build on errors. Issue codes are stable identifiers
+//! (`item.latitude_out_of_range`, `fence.leg_exits_inclusion`, ...) and may
+//! be listed in `ignore` to waive a known finding.
+
+use super::acceptance::{validate_ardupilot_acceptance, ArduPilotProfile};
+use super::fence::validate_against_fence;
+use super::types::{IssueSeverity, MissionIssue, MissionPlan, MissionType};
+use super::validation::validate_plan;
+use super::vtol::{validate_vtol_transitions, VtolProfile};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationOptions {
+    /// Fence plan to check mission legs against.
+    pub fence: Option<MissionPlan>,
+    /// Check VTOL transitions with this airframe profile.
+    pub vtol: Option<VtolProfile>,
+    /// Check the plan against ArduPilot's upload acceptance rules.
+    pub ardupilot: Option<ArduPilotProfile>,
+    /// Issue codes left out of the report.
+    pub ignore: Vec<String>,
+    /// Fail on warnings as well as errors.
+    pub warnings_as_errors: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Ordered by item, then code.
+    pub issues: Vec<MissionIssue>,
+    pub errors: usize,
+    pub warnings: usize,
+    /// Issue count per code.
+    pub codes: BTreeMap<String, usize>,
+    pub passed: bool,
+}
+
+impl ValidationReport {
+    /// Process exit code for a lint run: 0 when passed, 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        if self.passed {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+/// Validate `plan` with every check `options` enables and summarise the
+/// result.
+pub fn validate_plan_report(plan: &MissionPlan, options: &ValidationOptions) -> ValidationReport {
+    let mut issues = validate_plan(plan);
+    if plan.mission_type == MissionType::Mission {
+        if let Some(fence) = &options.fence {
+            issues.extend(validate_against_fence(plan, fence));
+        }
+        if let Some(profile) = &options.vtol {
+            issues.extend(validate_vtol_transitions(plan, profile));
+        }
+    }
+    if let Some(profile) = &options.ardupilot {
+        issues.extend(validate_ardupilot_acceptance(plan, profile));
+    }
+    issues.retain(|issue| !options.ignore.contains(&issue.code));
+    // Plan-level issues first, then by item
+    issues.sort_by(|a, b| {
+        a.seq
+            .cmp(&b.seq)
+            .then_with(|| a.code.cmp(&b.code))
+            .then_with(|| a.message.cmp(&b.message))
+    });
+
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == IssueSeverity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    let mut codes = BTreeMap::new();
+    for issue in &issues {
+        *codes.entry(issue.code.clone()).or_insert(0) += 1;
+    }
+    ValidationReport {
+        passed: errors == 0 && !(options.warnings_as_errors && warnings > 0),
+        issues,
+        errors,
+        warnings,
+        codes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{global_item, MAV_CMD_NAV_WAYPOINT};
+    use crate::mission::{AltitudeDatum, HomePosition};
+
+    fn waypoints(points: &[(f64, f64)]) -> MissionPlan {
+        MissionPlan::for_test(
+            MissionType::Mission,
+            None,
+            points
+                .iter()
+                .map(|&(lat, lon)| global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, 50.0))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn clean_plan_passes() {
+        let report = validate_plan_report(
+            &waypoints(&[(47.0, 8.0), (47.001, 8.0)]),
+            &ValidationOptions::default(),
+        );
+        assert!(report.passed);
+        assert_eq!(
+            (report.errors, report.warnings, report.exit_code()),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn counts_sorts_and_fails_on_errors() {
+        let mut bad = waypoints(&[(47.0, 8.0), (47.0, 8.0)]);
+        bad.items[1].z = f32::NAN;
+        bad.items[0].x = 950_000_000;
+        bad.home = Some(HomePosition {
+            latitude_deg: 47.0,
+            longitude_deg: 8.0,
+            altitude_m: 500.0,
+            altitude_datum: AltitudeDatum::Ellipsoid,
+        });
+        let report = validate_plan_report(&bad, &ValidationOptions::default());
+        assert!(!report.passed);
+        assert_eq!(report.exit_code(), 1);
+        assert_eq!((report.errors, report.warnings), (2, 1));
+        let order: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.seq, issue.code.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (None, "home.altitude_not_amsl"),
+                (Some(0), "item.latitude_out_of_range"),
+                (Some(1), "item.non_finite_value"),
+            ]
+        );
+        assert_eq!(report.codes["item.non_finite_value"], 1);
+    }
+
+    #[test]
+    fn ignored_codes_and_strict_warnings() {
+        let mut warned = waypoints(&[(47.0, 8.0)]);
+        warned.home = Some(HomePosition {
+            latitude_deg: 47.0,
+            longitude_deg: 8.0,
+            altitude_m: 500.0,
+            altitude_datum: AltitudeDatum::Ellipsoid,
+        });
+        let mut options = ValidationOptions::default();
+        assert!(validate_plan_report(&warned, &options).passed);
+
+        options.warnings_as_errors = true;
+        assert!(!validate_plan_report(&warned, &options).passed);
+
+        options.ignore = vec!["home.altitude_not_amsl".to_string()];
+        let report = validate_plan_report(&warned, &options);
+        assert!(report.passed);
+        assert!(report.issues.is_empty());
+    }
+}
@@ -1,75 +1,94 @@
+pub mod acceptance;
+pub mod builder;
+pub mod checksum;
+pub mod conflict;
+pub mod fence;
+pub mod geo;
+#[cfg(feature = "link")]
+mod handle;
+// Persists snapshots to disk; browser consumers keep their own history.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod history;
+pub mod lint;
+pub mod onboard;
+pub mod precision;
+pub mod rtl;
+#[cfg(feature = "sealing")]
+pub mod sealed;
+pub mod search;
+pub mod simplify;
+pub mod simulate;
+pub mod structure_scan;
+pub mod sun;
+pub mod sync;
+pub mod terrain;
 pub mod transfer;
 pub mod types;
 pub mod validation;
+pub mod vtol;
 pub mod wire;
 
+pub use acceptance::{validate_ardupilot_acceptance, ArduPilotProfile};
+pub use checksum::{plan_checksum, plan_sync_status, PlanMarkers, PlanSyncMarker, PlanSyncStatus};
+pub use conflict::{detect_conflicts, Conflict, PlannedFlight, Separation};
+pub use fence::{
+    fence_breaches, validate_against_fence, Fence, FenceBreach, FenceShape, FenceZone,
+    FenceZoneKind,
+};
+#[cfg(feature = "link")]
+pub use handle::MissionHandle;
+#[cfg(not(target_arch = "wasm32"))]
+pub use history::{PlanHistory, PlanSnapshot};
+pub use lint::{validate_plan_report, ValidationOptions, ValidationReport};
+pub use onboard::{ExternalPlanChange, OnboardPlans, PlanEpochs};
+pub use precision::{
+    audit_float_download, audit_imported_coordinates, deg_to_e7, e7_to_deg, exceeds_e7_precision,
+    f32_precision_loss_m,
+};
+pub use rtl::{
+    preview_rtl, rtl_alt_from_params, RtlAltitude, RtlPoint, RtlPreview, RtlSegment, RtlSegmentKind,
+};
+#[cfg(feature = "sealing")]
+pub use sealed::{
+    key_id_of, open_plan, parse_signing_key, parse_verifying_key, seal_plan, PlanIdentity,
+    PlanKeyring, PlanRecipient, StaticKeyring,
+};
+pub use search::{generate_search_pattern, SearchPattern, SearchPatternParams};
+pub use simplify::{simplify, snap_altitudes, snap_to_grid, Simplified};
+pub use simulate::{simulate, SimSample, SimTimeline, VehicleProfile};
+pub use structure_scan::{generate_structure_scan, StructureScanParams};
+pub use sun::{sun_position, sun_times, sun_warnings, SunPosition, SunTimes};
+pub use sync::{sync_progress, wire_item_count, SyncOutcome, SyncPart, SyncProgress, SyncReport};
+pub use terrain::{
+    path_clearance, retarget_agl, AglReport, AglRetarget, ClampedPoint, FlatTerrain, PathClearance,
+    TerrainSource, UnreachablePoint, UnreachableReason,
+};
 pub use transfer::{
     MissionTransferMachine, RetryPolicy, TransferDirection, TransferError, TransferEvent,
     TransferPhase, TransferProgress,
 };
-pub use types::{HomePosition, IssueSeverity, MissionFrame, MissionItem, MissionIssue, MissionPlan, MissionType};
-pub use validation::{normalize_for_compare, plans_equivalent, validate_plan, CompareTolerance};
+pub use types::{
+    AltitudeDatum, HomePosition, IssueSeverity, MissionFrame, MissionIssue, MissionItem,
+    MissionPlan, MissionType,
+};
+pub use validation::{
+    diff_plans, normalize_for_compare, plans_equivalent, validate_plan, CompareTolerance,
+    FieldMismatch, ItemDiff, PlanDiff,
+};
+pub use vtol::{validate_vtol_transitions, wrap_vtol_block, VtolProfile, VtolWrapParams};
 pub use wire::{items_for_wire_upload, plan_from_wire_download};
 
-use crate::error::VehicleError;
-use crate::Vehicle;
-
-/// Handle to mission operations on a `Vehicle`.
-pub struct MissionHandle<'a> {
-    vehicle: &'a Vehicle,
-}
-
-impl<'a> MissionHandle<'a> {
-    pub(crate) fn new(vehicle: &'a Vehicle) -> Self {
-        Self { vehicle }
-    }
-
-    pub async fn upload(&self, plan: MissionPlan) -> Result<(), VehicleError> {
-        self.vehicle
-            .send_command(|reply| crate::command::Command::MissionUpload { plan, reply })
-            .await
-    }
-
-    pub async fn download(&self, mission_type: MissionType) -> Result<MissionPlan, VehicleError> {
-        self.vehicle
-            .send_command(|reply| crate::command::Command::MissionDownload {
-                mission_type,
-                reply,
-            })
-            .await
-    }
-
-    pub async fn clear(&self, mission_type: MissionType) -> Result<(), VehicleError> {
-        self.vehicle
-            .send_command(|reply| crate::command::Command::MissionClear {
-                mission_type,
-                reply,
-            })
-            .await
-    }
-
-    pub async fn verify_roundtrip(&self, plan: MissionPlan) -> Result<bool, VehicleError> {
-        self.upload(plan.clone()).await?;
-        let readback = self.download(plan.mission_type).await?;
-        let mut lhs = normalize_for_compare(&plan);
-        let mut rhs = normalize_for_compare(&readback);
-        // Autopilot may overwrite home position; compare items only
-        lhs.home = None;
-        rhs.home = None;
-        Ok(plans_equivalent(&lhs, &rhs, CompareTolerance::default()))
-    }
-
-    pub async fn set_current(&self, seq: u16) -> Result<(), VehicleError> {
-        self.vehicle
-            .send_command(|reply| crate::command::Command::MissionSetCurrent { seq, reply })
-            .await
-    }
+use serde::{Deserialize, Serialize};
 
-    pub fn cancel_transfer(&self) {
-        let _ = self
-            .vehicle
-            .inner
-            .command_tx
-            .try_send(crate::command::Command::MissionCancelTransfer);
-    }
+/// Options for `MissionHandle::upload_with`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadOptions {
+    /// Download the plan again after uploading and compare it field by field.
+    #[serde(default)]
+    pub verify: bool,
+    /// After a fence upload, enable the fence and wait for FENCE_STATUS
+    /// (see `FenceHandle::enable`). With `verify`, only a fence that read
+    /// back unchanged is enabled.
+    #[serde(default)]
+    pub enable_fence: bool,
 }
@@ -0,0 +1,212 @@
+//! The GCS's copy of the plans stored on the vehicle.
+//!
+//! Every upload, download and clear replaces the copy of its type. Once the
+//! vehicle reports that a plan changed elsewhere, through an opaque id that
+//! no longer matches or a MISSION_ACK sent to another GCS, the copy is
+//! dropped rather than shown out of date.
+//!
+//! `PlanEpochs` keeps the opaque id last known for each type, from our own
+//! transfers and from every id the vehicle reports (MISSION_CURRENT, and
+//! the MISSION_COUNT and MISSION_ACK of other GCSs' transfers). A reported
+//! id that differs is an `ExternalPlanChange`, announced on
+//! `Vehicle::mission_changed_externally` so the plan can be downloaded
+//! again.
+
+use super::checksum::PlanMarkers;
+use super::types::{MissionPlan, MissionType};
+use serde::{Deserialize, Serialize};
+
+/// Plans stored on the vehicle, one per type; `None` while unknown.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OnboardPlans {
+    pub mission: Option<MissionPlan>,
+    pub fence: Option<MissionPlan>,
+    pub rally: Option<MissionPlan>,
+}
+
+impl OnboardPlans {
+    pub fn get(&self, mission_type: MissionType) -> Option<&MissionPlan> {
+        match mission_type {
+            MissionType::Mission => self.mission.as_ref(),
+            MissionType::Fence => self.fence.as_ref(),
+            MissionType::Rally => self.rally.as_ref(),
+        }
+    }
+
+    pub fn set(&mut self, plan: MissionPlan) {
+        let mission_type = plan.mission_type;
+        *self.slot(mission_type) = Some(plan);
+    }
+
+    /// Forget the plan of `mission_type`. Returns whether one was known.
+    pub fn invalidate(&mut self, mission_type: MissionType) -> bool {
+        self.slot(mission_type).take().is_some()
+    }
+
+    /// Forget plans whose opaque id, as reported in MISSION_CURRENT, differs
+    /// from the one recorded when they were exchanged. Ids of 0 mean the
+    /// autopilot doesn't track them and are ignored. Returns whether
+    /// anything was dropped.
+    pub fn invalidate_changed(
+        &mut self,
+        markers: &PlanMarkers,
+        vehicle_ids: [(MissionType, u32); 3],
+    ) -> bool {
+        let mut changed = false;
+        for (mission_type, vehicle_id) in vehicle_ids {
+            let stale = markers.get(mission_type).is_some_and(|marker| {
+                marker.opaque_id != 0 && vehicle_id != 0 && marker.opaque_id != vehicle_id
+            });
+            if stale {
+                changed |= self.invalidate(mission_type);
+            }
+        }
+        changed
+    }
+
+    fn slot(&mut self, mission_type: MissionType) -> &mut Option<MissionPlan> {
+        match mission_type {
+            MissionType::Mission => &mut self.mission,
+            MissionType::Fence => &mut self.fence,
+            MissionType::Rally => &mut self.rally,
+        }
+    }
+}
+
+/// A plan on the vehicle changed without a transfer of ours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalPlanChange {
+    pub mission_type: MissionType,
+    /// The id the plan was last known by.
+    pub previous_id: u32,
+    pub opaque_id: u32,
+}
+
+/// The opaque id last known for each plan type; 0 while none is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlanEpochs {
+    mission: u32,
+    fence: u32,
+    rally: u32,
+}
+
+impl PlanEpochs {
+    pub fn get(&self, mission_type: MissionType) -> u32 {
+        match mission_type {
+            MissionType::Mission => self.mission,
+            MissionType::Fence => self.fence,
+            MissionType::Rally => self.rally,
+        }
+    }
+
+    /// The id of a plan we just uploaded, downloaded or cleared.
+    pub fn record(&mut self, mission_type: MissionType, opaque_id: u32) {
+        if opaque_id != 0 {
+            *self.slot(mission_type) = opaque_id;
+        }
+    }
+
+    /// An id the vehicle reported. Returns the change when a different id
+    /// was known; the first id seen is only remembered.
+    pub fn observe(
+        &mut self,
+        mission_type: MissionType,
+        opaque_id: u32,
+    ) -> Option<ExternalPlanChange> {
+        if opaque_id == 0 {
+            return None;
+        }
+        let known = std::mem::replace(self.slot(mission_type), opaque_id);
+        (known != 0 && known != opaque_id).then_some(ExternalPlanChange {
+            mission_type,
+            previous_id: known,
+            opaque_id,
+        })
+    }
+
+    fn slot(&mut self, mission_type: MissionType) -> &mut u32 {
+        match mission_type {
+            MissionType::Mission => &mut self.mission,
+            MissionType::Fence => &mut self.fence,
+            MissionType::Rally => &mut self.rally,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::PlanSyncMarker;
+
+    #[test]
+    fn plans_are_kept_per_type() {
+        let mut plans = OnboardPlans::default();
+        plans.set(MissionPlan::for_test(MissionType::Rally, None, Vec::new()));
+        assert!(plans.get(MissionType::Rally).is_some());
+        assert!(plans.get(MissionType::Mission).is_none());
+        assert!(plans.invalidate(MissionType::Rally));
+        assert!(!plans.invalidate(MissionType::Rally));
+    }
+
+    #[test]
+    fn changed_opaque_id_drops_only_that_plan() {
+        let mut plans = OnboardPlans::default();
+        plans.set(MissionPlan::for_test(
+            MissionType::Mission,
+            None,
+            Vec::new(),
+        ));
+        plans.set(MissionPlan::for_test(MissionType::Fence, None, Vec::new()));
+        let mut markers = PlanMarkers::default();
+        for (mission_type, opaque_id) in [(MissionType::Mission, 10), (MissionType::Fence, 20)] {
+            markers.set(
+                mission_type,
+                PlanSyncMarker {
+                    opaque_id,
+                    checksum: 0,
+                },
+            );
+        }
+
+        let unchanged = [
+            (MissionType::Mission, 10),
+            (MissionType::Fence, 20),
+            (MissionType::Rally, 0),
+        ];
+        assert!(!plans.invalidate_changed(&markers, unchanged));
+
+        // An autopilot that stops reporting ids doesn't count as a change
+        let untracked = [
+            (MissionType::Mission, 0),
+            (MissionType::Fence, 20),
+            (MissionType::Rally, 0),
+        ];
+        assert!(!plans.invalidate_changed(&markers, untracked));
+
+        let mission_edited = [
+            (MissionType::Mission, 11),
+            (MissionType::Fence, 20),
+            (MissionType::Rally, 0),
+        ];
+        assert!(plans.invalidate_changed(&markers, mission_edited));
+        assert!(plans.get(MissionType::Mission).is_none());
+        assert!(plans.get(MissionType::Fence).is_some());
+    }
+
+    #[test]
+    fn epochs_report_ids_that_differ_from_the_known_one() {
+        let mut epochs = PlanEpochs::default();
+        assert_eq!(epochs.observe(MissionType::Mission, 10), None);
+        assert_eq!(epochs.observe(MissionType::Mission, 10), None);
+
+        // Our own upload moves the epoch without a change being reported
+        epochs.record(MissionType::Mission, 11);
+        assert_eq!(epochs.observe(MissionType::Mission, 11), None);
+        assert_eq!(epochs.observe(MissionType::Mission, 0), None);
+
+        let change = epochs.observe(MissionType::Mission, 12).unwrap();
+        assert_eq!((change.previous_id, change.opaque_id), (11, 12));
+        assert_eq!(epochs.get(MissionType::Mission), 12);
+        assert_eq!(epochs.get(MissionType::Fence), 0);
+    }
+}
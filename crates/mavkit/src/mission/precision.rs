@@ -0,0 +1,128 @@
+use super::geo::distance_m;
+use super::types::{IssueSeverity, MissionIssue, MissionPlan};
+
+/// Float-transfer error above which an item is flagged.
+const FLOAT_LOSS_WARN_M: f64 = 0.1;
+
+/// Degrees to the MAVLink `int32` degE7 representation, rounded to nearest.
+pub fn deg_to_e7(deg: f64) -> i32 {
+    (deg * 1e7).round() as i32
+}
+
+pub fn e7_to_deg(e7: i32) -> f64 {
+    e7 as f64 / 1e7
+}
+
+/// True when `deg` carries more precision than degE7 can represent
+/// (i.e. rounding to E7 changes it by more than float noise).
+pub fn exceeds_e7_precision(deg: f64) -> bool {
+    let scaled = deg * 1e7;
+    (scaled - scaled.round()).abs() > 1e-3
+}
+
+/// Horizontal error introduced by passing a position through `f32` degrees,
+/// as the float MISSION_ITEM message does.
+pub fn f32_precision_loss_m(lat_deg: f64, lon_deg: f64) -> f64 {
+    distance_m(
+        lat_deg,
+        lon_deg,
+        lat_deg as f32 as f64,
+        lon_deg as f32 as f64,
+    )
+}
+
+/// Flag imported `(lat, lon)` coordinates that will be truncated by degE7.
+/// Issue `seq` is the index into `coords`.
+pub fn audit_imported_coordinates(coords: &[(f64, f64)]) -> Vec<MissionIssue> {
+    coords
+        .iter()
+        .enumerate()
+        .filter(|(_, &(lat, lon))| exceeds_e7_precision(lat) || exceeds_e7_precision(lon))
+        .map(|(i, &(lat, lon))| MissionIssue {
+            code: "coord.precision_truncated".to_string(),
+            message: format!(
+                "Coordinate {lat}, {lon} has more precision than 1e-7 degrees and will be rounded"
+            ),
+            seq: Some(i as u16),
+            severity: IssueSeverity::Warning,
+        })
+        .collect()
+}
+
+/// Warn about items that went through a float MISSION_ITEM download (see
+/// `MissionItem::downloaded_as_float`) and may have lost position precision.
+pub fn audit_float_download(plan: &MissionPlan) -> Vec<MissionIssue> {
+    plan.items
+        .iter()
+        .filter(|item| item.downloaded_as_float && item.frame.is_global_position())
+        .filter_map(|item| {
+            let (lat, lon) = item.latlon_deg();
+            // The float value was already rounded once; measure the worst case
+            let loss = f32_precision_loss_m(lat, lon);
+            (loss > FLOAT_LOSS_WARN_M).then(|| MissionIssue {
+                code: "coord.float_precision_loss".to_string(),
+                message: format!(
+                    "Item was downloaded as float MISSION_ITEM; position may be off by up to {loss:.2} m"
+                ),
+                seq: Some(item.seq),
+                severity: IssueSeverity::Warning,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{global_item, MAV_CMD_NAV_WAYPOINT};
+    use crate::mission::MissionType;
+
+    #[test]
+    fn e7_roundtrip() {
+        assert_eq!(deg_to_e7(47.3977419), 473977419);
+        assert_eq!(deg_to_e7(-122.0840575), -1220840575);
+        assert_eq!(e7_to_deg(473977419), 47.3977419);
+    }
+
+    #[test]
+    fn detects_excess_precision() {
+        assert!(!exceeds_e7_precision(47.3977419));
+        assert!(exceeds_e7_precision(47.39774195));
+        let issues = audit_imported_coordinates(&[(47.3977419, 8.5455938), (47.0, 8.123456789)]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].seq, Some(1));
+    }
+
+    #[test]
+    fn f32_loss_is_decimetres_at_mid_latitudes() {
+        let loss = f32_precision_loss_m(47.3977419, 8.5455938);
+        assert!(loss > 0.0 && loss < 1.0, "got {loss}");
+    }
+
+    #[test]
+    fn float_download_items_are_flagged() {
+        let mut plan = MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items: vec![
+                global_item(MAV_CMD_NAV_WAYPOINT, 47.3977419, 8.5455938, 20.0),
+                global_item(MAV_CMD_NAV_WAYPOINT, 47.3987437, 8.5465938, 20.0),
+            ],
+            metadata: Default::default(),
+        };
+        plan.items[1].seq = 1;
+        assert!(audit_float_download(&plan).is_empty());
+
+        plan.items[1].downloaded_as_float = true;
+        let issues = audit_float_download(&plan);
+        assert!(!issues.is_empty());
+        assert!(issues.iter().all(|i| i.seq == Some(1)));
+
+        // The flag follows the item, and moving it clears the flag
+        plan.items.remove(0);
+        plan.items[0].seq = 0;
+        assert_eq!(audit_float_download(&plan)[0].seq, Some(0));
+        plan.items[0].set_latlon_deg(47.3987437, 8.5465938);
+        assert!(audit_float_download(&plan).is_empty());
+    }
+}
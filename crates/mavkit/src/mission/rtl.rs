@@ -0,0 +1,246 @@
+use super::geo::{bearing_deg, destination, distance_m};
+use super::types::HomePosition;
+use crate::params::ParamStore;
+use crate::state::VehicleType;
+use serde::{Deserialize, Serialize};
+
+/// Default RTL altitude when the vehicle parameters are unknown.
+pub const DEFAULT_RTL_ALT_M: f32 = 15.0;
+/// ArduPlane WP_LOITER_RAD default, used for the loiter circle at home.
+const PLANE_LOITER_RADIUS_M: f64 = 60.0;
+const LOITER_POINTS: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RtlSegmentKind {
+    Climb,
+    Cruise,
+    Descend,
+    Loiter,
+}
+
+/// Altitude an RTL returns at, as configured on the vehicle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RtlAltitude {
+    /// Metres relative to home.
+    Fixed(f32),
+    /// Return at whatever altitude the vehicle is at when RTL starts
+    /// (ArduPlane `ALT_HOLD_RTL = -1`).
+    Current,
+}
+
+impl RtlAltitude {
+    /// Altitude relative to home for a vehicle now at `current_alt_m`.
+    pub fn resolve(self, current_alt_m: f32) -> f32 {
+        match self {
+            RtlAltitude::Fixed(alt_m) => alt_m,
+            RtlAltitude::Current => current_alt_m,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RtlPoint {
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    /// Altitude relative to home.
+    pub altitude_m: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RtlSegment {
+    pub kind: RtlSegmentKind,
+    pub points: Vec<RtlPoint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RtlPreview {
+    pub segments: Vec<RtlSegment>,
+    /// Horizontal distance from the current position back to home.
+    pub distance_m: f64,
+}
+
+/// Predict the path the vehicle would take if RTL were triggered now.
+///
+/// `alt_m` and `rtl_alt_m` are relative to home. Multirotors climb to
+/// `rtl_alt_m` (never descending first), fly home and descend to land; planes
+/// change altitude along the return leg and loiter over home; rovers drive
+/// straight back.
+pub fn preview_rtl(
+    lat_deg: f64,
+    lon_deg: f64,
+    alt_m: f32,
+    home: &HomePosition,
+    rtl_alt_m: f32,
+    vehicle_type: VehicleType,
+) -> RtlPreview {
+    let point = |latitude_deg, longitude_deg, altitude_m| RtlPoint {
+        latitude_deg,
+        longitude_deg,
+        altitude_m,
+    };
+    let (home_lat, home_lon) = (home.latitude_deg, home.longitude_deg);
+    let distance = distance_m(lat_deg, lon_deg, home_lat, home_lon);
+
+    let segments = match vehicle_type {
+        VehicleType::GroundRover => vec![RtlSegment {
+            kind: RtlSegmentKind::Cruise,
+            points: vec![point(lat_deg, lon_deg, 0.0), point(home_lat, home_lon, 0.0)],
+        }],
+        VehicleType::FixedWing => {
+            let bearing = bearing_deg(home_lat, home_lon, lat_deg, lon_deg);
+            let (entry_lat, entry_lon) =
+                destination(home_lat, home_lon, bearing, PLANE_LOITER_RADIUS_M);
+            let loiter = (0..=LOITER_POINTS)
+                .map(|i| {
+                    let b = bearing + 360.0 * i as f64 / LOITER_POINTS as f64;
+                    let (lat, lon) = destination(home_lat, home_lon, b, PLANE_LOITER_RADIUS_M);
+                    point(lat, lon, rtl_alt_m)
+                })
+                .collect();
+            vec![
+                RtlSegment {
+                    kind: RtlSegmentKind::Cruise,
+                    points: vec![
+                        point(lat_deg, lon_deg, alt_m),
+                        point(entry_lat, entry_lon, rtl_alt_m),
+                    ],
+                },
+                RtlSegment {
+                    kind: RtlSegmentKind::Loiter,
+                    points: loiter,
+                },
+            ]
+        }
+        _ => {
+            let cruise_alt = alt_m.max(rtl_alt_m);
+            let mut segments = Vec::with_capacity(3);
+            if cruise_alt > alt_m {
+                segments.push(RtlSegment {
+                    kind: RtlSegmentKind::Climb,
+                    points: vec![
+                        point(lat_deg, lon_deg, alt_m),
+                        point(lat_deg, lon_deg, cruise_alt),
+                    ],
+                });
+            }
+            segments.push(RtlSegment {
+                kind: RtlSegmentKind::Cruise,
+                points: vec![
+                    point(lat_deg, lon_deg, cruise_alt),
+                    point(home_lat, home_lon, cruise_alt),
+                ],
+            });
+            segments.push(RtlSegment {
+                kind: RtlSegmentKind::Descend,
+                points: vec![
+                    point(home_lat, home_lon, cruise_alt),
+                    point(home_lat, home_lon, 0.0),
+                ],
+            });
+            segments
+        }
+    };
+
+    RtlPreview {
+        segments,
+        distance_m: distance,
+    }
+}
+
+/// RTL altitude from the vehicle parameters (`RTL_ALT` on copters,
+/// `ALT_HOLD_RTL` on planes, both in centimetres).
+pub fn rtl_alt_from_params(store: &ParamStore, vehicle_type: VehicleType) -> Option<RtlAltitude> {
+    let (name, current_below_zero) = match vehicle_type {
+        VehicleType::FixedWing => ("ALT_HOLD_RTL", true),
+        VehicleType::GroundRover => return None,
+        _ => ("RTL_ALT", false),
+    };
+    let cm = store.params.get(name)?.value;
+    if cm >= 0.0 {
+        Some(RtlAltitude::Fixed(cm / 100.0))
+    } else if current_below_zero {
+        // ALT_HOLD_RTL = -1 means "return at the current altitude"
+        Some(RtlAltitude::Current)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: HomePosition = HomePosition::amsl(47.0, 8.0, 500.0);
+
+    fn kinds(preview: &RtlPreview) -> Vec<RtlSegmentKind> {
+        preview.segments.iter().map(|s| s.kind).collect()
+    }
+
+    #[test]
+    fn copter_climbs_cruises_and_descends() {
+        let (lat, lon) = destination(HOME.latitude_deg, HOME.longitude_deg, 45.0, 200.0);
+        let preview = preview_rtl(lat, lon, 5.0, &HOME, 15.0, VehicleType::Quadrotor);
+        assert_eq!(
+            kinds(&preview),
+            vec![
+                RtlSegmentKind::Climb,
+                RtlSegmentKind::Cruise,
+                RtlSegmentKind::Descend
+            ]
+        );
+        assert_eq!(preview.segments[1].points[0].altitude_m, 15.0);
+        assert_eq!(preview.segments[2].points[1].altitude_m, 0.0);
+        assert!((preview.distance_m - 200.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn copter_above_rtl_alt_keeps_altitude() {
+        let (lat, lon) = destination(HOME.latitude_deg, HOME.longitude_deg, 0.0, 100.0);
+        let preview = preview_rtl(lat, lon, 40.0, &HOME, 15.0, VehicleType::Hexarotor);
+        assert_eq!(
+            kinds(&preview),
+            vec![RtlSegmentKind::Cruise, RtlSegmentKind::Descend]
+        );
+        assert_eq!(preview.segments[0].points[1].altitude_m, 40.0);
+    }
+
+    #[test]
+    fn plane_loiters_over_home_at_rtl_alt() {
+        let (lat, lon) = destination(HOME.latitude_deg, HOME.longitude_deg, 90.0, 1000.0);
+        let preview = preview_rtl(lat, lon, 80.0, &HOME, 100.0, VehicleType::FixedWing);
+        assert_eq!(
+            kinds(&preview),
+            vec![RtlSegmentKind::Cruise, RtlSegmentKind::Loiter]
+        );
+        for p in &preview.segments[1].points {
+            let r = distance_m(
+                HOME.latitude_deg,
+                HOME.longitude_deg,
+                p.latitude_deg,
+                p.longitude_deg,
+            );
+            assert!((r - PLANE_LOITER_RADIUS_M).abs() < 0.1);
+            assert_eq!(p.altitude_m, 100.0);
+        }
+    }
+
+    #[test]
+    fn rtl_alt_read_from_params_in_metres() {
+        let store = ParamStore::from_values(&[("RTL_ALT", 3000.0)]);
+        assert_eq!(
+            rtl_alt_from_params(&store, VehicleType::Quadrotor),
+            Some(RtlAltitude::Fixed(30.0))
+        );
+        assert_eq!(rtl_alt_from_params(&store, VehicleType::FixedWing), None);
+    }
+
+    #[test]
+    fn plane_alt_hold_rtl_minus_one_returns_at_current_altitude() {
+        let store = ParamStore::from_values(&[("ALT_HOLD_RTL", -1.0)]);
+        let rtl_alt = rtl_alt_from_params(&store, VehicleType::FixedWing);
+        assert_eq!(rtl_alt, Some(RtlAltitude::Current));
+        assert_eq!(rtl_alt.unwrap().resolve(120.0), 120.0);
+    }
+}
@@ -0,0 +1,266 @@
+//! Signed and optionally encrypted plan files.
+//!
+//! A sealed plan is a small JSON envelope around the plan JSON. The payload
+//! can be encrypted to one or more age X25519 recipients (the field tablets)
+//! and is always signed with the planning desk's Ed25519 key, so a route
+//! cannot be changed or swapped between planning and flight without the
+//! import failing. The signature covers the stored payload, the encryption
+//! flag and the signer's key, so it can be checked before decrypting.
+//! Signed-only plans keep the plan JSON readable inside the envelope.
+//!
+//! Keys are not managed here: `PlanKeyring` is the hook through which an
+//! application supplies the signers it trusts and its decryption identity,
+//! whether they come from files, an OS keychain or an HSM-backed service.
+
+use super::types::MissionPlan;
+use crate::error::VehicleError;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+pub use age::x25519::{Identity as PlanIdentity, Recipient as PlanRecipient};
+
+const FORMAT: &str = "missionplanner-sealed-plan";
+const VERSION: u32 = 1;
+/// Domain separation for the signed bytes.
+const SIGNATURE_CONTEXT: &[u8] = b"missionplanner-sealed-plan/v1\0";
+
+/// Keys trusted when opening a sealed plan.
+pub trait PlanKeyring {
+    /// The public key that may sign plans under `key_id` (base64 Ed25519
+    /// public key), or `None` if that signer is not trusted.
+    fn trusted_signer(&self, key_id: &str) -> Option<VerifyingKey>;
+    /// Identity used to decrypt plans addressed to this station.
+    fn identity(&self) -> Option<&PlanIdentity>;
+}
+
+/// A fixed set of trusted signers and an optional decryption identity.
+#[derive(Default)]
+pub struct StaticKeyring {
+    pub trusted: Vec<VerifyingKey>,
+    pub identity: Option<PlanIdentity>,
+}
+
+impl PlanKeyring for StaticKeyring {
+    fn trusted_signer(&self, key_id: &str) -> Option<VerifyingKey> {
+        self.trusted
+            .iter()
+            .find(|key| key_id_of(key) == key_id)
+            .copied()
+    }
+
+    fn identity(&self) -> Option<&PlanIdentity> {
+        self.identity.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u32,
+    /// Base64 Ed25519 public key of the signer.
+    key_id: String,
+    encrypted: bool,
+    /// The plan JSON, or base64 age ciphertext when `encrypted`.
+    payload: String,
+    /// Base64 Ed25519 signature.
+    signature: String,
+}
+
+/// Identifier of a signer: its public key in base64.
+pub fn key_id_of(key: &VerifyingKey) -> String {
+    BASE64.encode(key.as_bytes())
+}
+
+/// Parse a base64 Ed25519 secret key (32-byte seed).
+pub fn parse_signing_key(text: &str) -> Result<SigningKey, VehicleError> {
+    let seed: [u8; 32] = decode(text.trim(), "signing key")?
+        .try_into()
+        .map_err(|_| sealed_error("signing key must be 32 bytes"))?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Parse a base64 Ed25519 public key, as produced by `key_id_of`.
+pub fn parse_verifying_key(text: &str) -> Result<VerifyingKey, VehicleError> {
+    let bytes: [u8; 32] = decode(text.trim(), "public key")?
+        .try_into()
+        .map_err(|_| sealed_error("public key must be 32 bytes"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| sealed_error(&format!("public key: {e}")))
+}
+
+/// Serialize, optionally encrypt and sign `plan`. With no `recipients` the
+/// plan is only signed and stays readable.
+pub fn seal_plan(
+    plan: &MissionPlan,
+    signing_key: &SigningKey,
+    recipients: &[PlanRecipient],
+) -> Result<String, VehicleError> {
+    let json = serde_json::to_string(plan).map_err(|e| sealed_error(&e.to_string()))?;
+    let encrypted = !recipients.is_empty();
+    let payload = if encrypted {
+        BASE64.encode(encrypt(json.as_bytes(), recipients)?)
+    } else {
+        json
+    };
+    let key_id = key_id_of(&signing_key.verifying_key());
+    let signature = signing_key.sign(&signed_bytes(&key_id, encrypted, payload.as_bytes()));
+    let envelope = Envelope {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        key_id,
+        encrypted,
+        payload,
+        signature: BASE64.encode(signature.to_bytes()),
+    };
+    serde_json::to_string_pretty(&envelope).map_err(|e| sealed_error(&e.to_string()))
+}
+
+/// Verify and, if needed, decrypt a sealed plan file. Fails unless the
+/// signer is trusted by `keyring` and the signature matches.
+pub fn open_plan(contents: &str, keyring: &impl PlanKeyring) -> Result<MissionPlan, VehicleError> {
+    let envelope: Envelope = serde_json::from_str(contents)
+        .map_err(|e| sealed_error(&format!("not a sealed plan: {e}")))?;
+    if envelope.format != FORMAT || envelope.version != VERSION {
+        return Err(sealed_error(&format!(
+            "unsupported format {} v{}",
+            envelope.format, envelope.version
+        )));
+    }
+    let signer = keyring
+        .trusted_signer(&envelope.key_id)
+        .ok_or_else(|| sealed_error(&format!("signer {} is not trusted", envelope.key_id)))?;
+    let signature: [u8; 64] = decode(&envelope.signature, "signature")?
+        .try_into()
+        .map_err(|_| sealed_error("signature must be 64 bytes"))?;
+    signer
+        .verify_strict(
+            &signed_bytes(
+                &envelope.key_id,
+                envelope.encrypted,
+                envelope.payload.as_bytes(),
+            ),
+            &Signature::from_bytes(&signature),
+        )
+        .map_err(|_| sealed_error("signature does not match; the plan was modified"))?;
+
+    let json = if envelope.encrypted {
+        let identity = keyring
+            .identity()
+            .ok_or_else(|| sealed_error("plan is encrypted and no identity is configured"))?;
+        let ciphertext = decode(&envelope.payload, "payload")?;
+        age::decrypt(identity, &ciphertext).map_err(|e| sealed_error(&format!("decrypt: {e}")))?
+    } else {
+        envelope.payload.into_bytes()
+    };
+    serde_json::from_slice(&json).map_err(|e| sealed_error(&format!("plan: {e}")))
+}
+
+fn encrypt(plaintext: &[u8], recipients: &[PlanRecipient]) -> Result<Vec<u8>, VehicleError> {
+    use std::io::Write;
+
+    let encryptor = age::Encryptor::with_recipients(
+        recipients
+            .iter()
+            .map(|recipient| recipient as &dyn age::Recipient),
+    )
+    .map_err(|e| sealed_error(&format!("encrypt: {e}")))?;
+    let mut ciphertext = Vec::new();
+    let io_error = |e: std::io::Error| sealed_error(&format!("encrypt: {e}"));
+    let mut writer = encryptor.wrap_output(&mut ciphertext).map_err(io_error)?;
+    writer.write_all(plaintext).map_err(io_error)?;
+    writer.finish().map_err(io_error)?;
+    Ok(ciphertext)
+}
+
+fn signed_bytes(key_id: &str, encrypted: bool, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = SIGNATURE_CONTEXT.to_vec();
+    bytes.extend_from_slice(key_id.as_bytes());
+    bytes.push(0);
+    bytes.push(encrypted as u8);
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn decode(text: &str, what: &str) -> Result<Vec<u8>, VehicleError> {
+    BASE64
+        .decode(text)
+        .map_err(|e| sealed_error(&format!("{what}: {e}")))
+}
+
+fn sealed_error(message: &str) -> VehicleError {
+    VehicleError::SealedPlan(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::global_item;
+    use crate::mission::types::MissionType;
+
+    fn plan() -> MissionPlan {
+        MissionPlan::for_test(
+            MissionType::Mission,
+            None,
+            vec![global_item(16, 47.0, 8.0, 50.0)],
+        )
+    }
+
+    fn desk_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn keyring(identity: Option<PlanIdentity>) -> StaticKeyring {
+        StaticKeyring {
+            trusted: vec![desk_key().verifying_key()],
+            identity,
+        }
+    }
+
+    #[test]
+    fn signed_plan_round_trips_and_detects_tampering() {
+        let sealed = seal_plan(&plan(), &desk_key(), &[]).unwrap();
+        assert!(
+            sealed.contains("470000000"),
+            "signed-only plans stay readable"
+        );
+        assert_eq!(open_plan(&sealed, &keyring(None)).unwrap(), plan());
+
+        let mut envelope: Envelope = serde_json::from_str(&sealed).unwrap();
+        let mut moved = plan();
+        moved.items[0].x += 1000;
+        envelope.payload = serde_json::to_string(&moved).unwrap();
+        let tampered = serde_json::to_string(&envelope).unwrap();
+        assert!(matches!(
+            open_plan(&tampered, &keyring(None)),
+            Err(VehicleError::SealedPlan(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_untrusted_signers() {
+        let other = SigningKey::from_bytes(&[9; 32]);
+        let sealed = seal_plan(&plan(), &other, &[]).unwrap();
+        let err = open_plan(&sealed, &keyring(None)).unwrap_err();
+        assert!(err.to_string().contains("not trusted"), "{err}");
+    }
+
+    #[test]
+    fn encrypted_plan_needs_the_recipient_identity() {
+        let tablet = PlanIdentity::generate();
+        let sealed = seal_plan(&plan(), &desk_key(), &[tablet.to_public()]).unwrap();
+        assert!(!sealed.contains("470000000"));
+        assert!(open_plan(&sealed, &keyring(None)).is_err());
+        assert!(open_plan(&sealed, &keyring(Some(PlanIdentity::generate()))).is_err());
+        assert_eq!(open_plan(&sealed, &keyring(Some(tablet))).unwrap(), plan());
+    }
+
+    #[test]
+    fn parses_keys_from_base64() {
+        let secret = BASE64.encode([7u8; 32]);
+        let key = parse_signing_key(&secret).unwrap();
+        let public = key_id_of(&key.verifying_key());
+        assert_eq!(parse_verifying_key(&public).unwrap(), key.verifying_key());
+        assert!(parse_signing_key("AAAA").is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! Standard search-and-rescue patterns flown from a datum point.
+//!
+//! The patterns follow the IAMSAR manual: the expanding square spirals out
+//! from the datum with legs growing by one track spacing every second turn,
+//! the sector search flies equilateral triangles through the datum, and the
+//! creeping line sweeps parallel legs advancing along the search track.
+
+use super::builder::{
+    command_item, global_item, resequence, MAV_CMD_DO_CHANGE_SPEED, MAV_CMD_NAV_WAYPOINT,
+};
+use super::geo::LocalFrame;
+use super::simulate::VehicleProfile;
+use super::types::{MissionPlan, MissionType};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SearchPattern {
+    /// Square spiral out from the datum; legs are `spacing_m` long for the
+    /// first two legs, then grow by `spacing_m` every second leg.
+    ExpandingSquare,
+    /// Triangles of side `spacing_m` that each return to the datum; nine
+    /// legs make the full three-triangle pattern.
+    SectorSearch,
+    /// Parallel legs of `leg_length_m` across the search track, `spacing_m`
+    /// apart, starting at the datum.
+    CreepingLine { leg_length_m: f64 },
+}
+
+/// Parameters for `generate_search_pattern`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SearchPatternParams {
+    pub pattern: SearchPattern,
+    /// Datum (expanding square, sector search) or commence search point
+    /// (creeping line), as `(lat_deg, lon_deg)`.
+    pub datum: (f64, f64),
+    /// Direction of the first leg, or of advance for the creeping line.
+    pub track_deg: f64,
+    /// Track spacing; the leg length of the sector search.
+    pub spacing_m: f64,
+    /// Number of legs to fly.
+    pub legs: u32,
+    /// Search altitude, relative to home.
+    pub alt_m: f32,
+    /// Turn left instead of right after each leg.
+    #[serde(default)]
+    pub left_turns: bool,
+    /// Ground speed while searching; the profile's cruise speed when unset.
+    #[serde(default)]
+    pub speed_mps: Option<f64>,
+}
+
+/// Generate a `MissionPlan` flying the search pattern.
+///
+/// The plan starts with a `DO_CHANGE_SPEED` to the search speed, which may not
+/// exceed the profile's cruise speed, followed by one waypoint at the datum
+/// and one at the end of every leg.
+pub fn generate_search_pattern(
+    params: &SearchPatternParams,
+    profile: &VehicleProfile,
+) -> Result<MissionPlan, String> {
+    if !is_positive(params.spacing_m) {
+        return Err("spacing_m must be positive".to_string());
+    }
+    if params.legs == 0 {
+        return Err("legs must be at least 1".to_string());
+    }
+    if let SearchPattern::CreepingLine { leg_length_m } = params.pattern {
+        if !is_positive(leg_length_m) {
+            return Err("leg_length_m must be positive".to_string());
+        }
+    }
+    let speed = params.speed_mps.unwrap_or(profile.cruise_speed_mps);
+    if !is_positive(speed) {
+        return Err("speed_mps must be positive".to_string());
+    }
+    if speed > profile.cruise_speed_mps {
+        return Err(format!(
+            "search speed {speed} m/s exceeds the profile's cruise speed of {} m/s",
+            profile.cruise_speed_mps
+        ));
+    }
+
+    let turn = if params.left_turns { -1.0 } else { 1.0 };
+    let path = match params.pattern {
+        SearchPattern::ExpandingSquare => expanding_square(params, turn),
+        SearchPattern::SectorSearch => sector_search(params, turn),
+        SearchPattern::CreepingLine { leg_length_m } => creeping_line(params, leg_length_m, turn),
+    };
+
+    let frame = LocalFrame::new(params.datum.0, params.datum.1);
+    let mut items = vec![command_item(
+        MAV_CMD_DO_CHANGE_SPEED,
+        [1.0, speed as f32, -1.0, 0.0],
+    )];
+    for (east, north) in path {
+        let (lat, lon) = frame.to_global(east, north);
+        items.push(global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, params.alt_m));
+    }
+    resequence(&mut items);
+
+    Ok(MissionPlan {
+        mission_type: MissionType::Mission,
+        home: None,
+        items,
+        metadata: Default::default(),
+    })
+}
+
+fn is_positive(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// Move `distance` along `heading_deg` in the local east/north frame.
+fn step(from: (f64, f64), heading_deg: f64, distance: f64) -> (f64, f64) {
+    let (sin, cos) = heading_deg.to_radians().sin_cos();
+    (from.0 + distance * sin, from.1 + distance * cos)
+}
+
+fn expanding_square(params: &SearchPatternParams, turn: f64) -> Vec<(f64, f64)> {
+    let mut path = vec![(0.0, 0.0)];
+    let mut at = (0.0, 0.0);
+    for leg in 0..params.legs {
+        let length = params.spacing_m * f64::from(leg / 2 + 1);
+        at = step(at, params.track_deg + turn * 90.0 * f64::from(leg), length);
+        path.push(at);
+    }
+    path
+}
+
+fn sector_search(params: &SearchPatternParams, turn: f64) -> Vec<(f64, f64)> {
+    let mut path = vec![(0.0, 0.0)];
+    let mut at = (0.0, 0.0);
+    let mut heading = params.track_deg;
+    for leg in 0..params.legs {
+        at = step(at, heading, params.spacing_m);
+        // Every third leg ends back over the datum, where the next triangle
+        // starts by carrying straight on
+        if leg % 3 == 2 {
+            at = (0.0, 0.0);
+        } else {
+            heading += turn * 120.0;
+        }
+        path.push(at);
+    }
+    path
+}
+
+fn creeping_line(params: &SearchPatternParams, leg_length_m: f64, turn: f64) -> Vec<(f64, f64)> {
+    let mut path = vec![(0.0, 0.0)];
+    let mut at = (0.0, 0.0);
+    for leg in 0..params.legs {
+        let side = if leg.is_multiple_of(2) { turn } else { -turn };
+        at = step(at, params.track_deg + side * 90.0, leg_length_m);
+        path.push(at);
+        if leg + 1 < params.legs {
+            at = step(at, params.track_deg, params.spacing_m);
+            path.push(at);
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::geo::{bearing_deg, distance_m};
+    use crate::mission::validate_plan;
+
+    fn params(pattern: SearchPattern, legs: u32) -> SearchPatternParams {
+        SearchPatternParams {
+            pattern,
+            datum: (47.0, 8.0),
+            track_deg: 0.0,
+            spacing_m: 100.0,
+            legs,
+            alt_m: 60.0,
+            left_turns: false,
+            speed_mps: None,
+        }
+    }
+
+    fn waypoints(plan: &MissionPlan) -> Vec<(f64, f64)> {
+        plan.items
+            .iter()
+            .filter(|i| i.command == MAV_CMD_NAV_WAYPOINT)
+            .map(|i| (i.x as f64 / 1e7, i.y as f64 / 1e7))
+            .collect()
+    }
+
+    fn leg_lengths(points: &[(f64, f64)]) -> Vec<f64> {
+        points
+            .windows(2)
+            .map(|p| distance_m(p[0].0, p[0].1, p[1].0, p[1].1))
+            .collect()
+    }
+
+    #[test]
+    fn expanding_square_grows_every_second_leg() {
+        let plan = generate_search_pattern(
+            &params(SearchPattern::ExpandingSquare, 6),
+            &VehicleProfile::default(),
+        )
+        .unwrap();
+        assert!(validate_plan(&plan).is_empty());
+        assert_eq!(plan.items[0].command, MAV_CMD_DO_CHANGE_SPEED);
+        assert_eq!(plan.items[0].param2, 10.0);
+
+        let wps = waypoints(&plan);
+        assert_eq!(wps.len(), 7);
+        let lengths = leg_lengths(&wps);
+        for (got, want) in lengths
+            .iter()
+            .zip([100.0, 100.0, 200.0, 200.0, 300.0, 300.0])
+        {
+            assert!((got - want).abs() < 0.5, "{lengths:?}");
+        }
+        // North first, then a right turn to the east
+        let second = bearing_deg(wps[1].0, wps[1].1, wps[2].0, wps[2].1);
+        assert!((second - 90.0).abs() < 0.5, "{second}");
+    }
+
+    #[test]
+    fn sector_search_returns_to_datum_every_third_leg() {
+        let plan = generate_search_pattern(
+            &params(SearchPattern::SectorSearch, 9),
+            &VehicleProfile::default(),
+        )
+        .unwrap();
+        let wps = waypoints(&plan);
+        assert_eq!(wps.len(), 10);
+        for i in [0, 3, 6, 9] {
+            assert_eq!(wps[i], wps[0], "leg {i}");
+        }
+        for d in leg_lengths(&wps) {
+            assert!((d - 100.0).abs() < 0.5, "{d}");
+        }
+    }
+
+    #[test]
+    fn creeping_line_alternates_across_the_track() {
+        let mut p = params(
+            SearchPattern::CreepingLine {
+                leg_length_m: 500.0,
+            },
+            3,
+        );
+        p.left_turns = true;
+        let plan = generate_search_pattern(&p, &VehicleProfile::default()).unwrap();
+        let wps = waypoints(&plan);
+        // Start, then leg end and step for each leg but the last
+        assert_eq!(wps.len(), 6);
+        let frame = LocalFrame::new(47.0, 8.0);
+        let local: Vec<(f64, f64)> = wps
+            .iter()
+            .map(|&(lat, lon)| frame.to_local(lat, lon))
+            .collect();
+        assert!(local[1].0 < -499.0, "first leg goes west: {:?}", local[1]);
+        assert!((local[2].1 - 100.0).abs() < 0.5);
+        assert!(
+            local[3].0.abs() < 0.5,
+            "second leg back east: {:?}",
+            local[3]
+        );
+        assert!((local[5].0 + 500.0).abs() < 0.5 && (local[5].1 - 200.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn search_speed_is_limited_by_the_profile() {
+        let mut p = params(SearchPattern::ExpandingSquare, 4);
+        p.speed_mps = Some(6.0);
+        let plan = generate_search_pattern(&p, &VehicleProfile::default()).unwrap();
+        assert_eq!(plan.items[0].param2, 6.0);
+
+        p.speed_mps = Some(25.0);
+        assert!(generate_search_pattern(&p, &VehicleProfile::default()).is_err());
+    }
+
+    #[test]
+    fn rejects_degenerate_parameters() {
+        let profile = VehicleProfile::default();
+        assert!(
+            generate_search_pattern(&params(SearchPattern::SectorSearch, 0), &profile).is_err()
+        );
+        let mut p = params(SearchPattern::ExpandingSquare, 4);
+        p.spacing_m = 0.0;
+        assert!(generate_search_pattern(&p, &profile).is_err());
+        let p = params(SearchPattern::CreepingLine { leg_length_m: -1.0 }, 4);
+        assert!(generate_search_pattern(&p, &profile).is_err());
+    }
+}
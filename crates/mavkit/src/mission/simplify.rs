@@ -0,0 +1,338 @@
+//! Shrink dense plans and tidy their coordinates.
+//!
+//! Plans derived from GPS traces carry a waypoint every few metres and easily
+//! exceed the autopilot's item limit. `simplify` runs Douglas-Peucker over
+//! each run of plain waypoints, in 3D so climbs and descents survive. DO and
+//! condition items run after the waypoint before them; when that waypoint is
+//! removed they move to the nearest waypoint that is kept, so camera triggers
+//! and servo actions still happen close to where they were planned.
+
+use super::builder::resequence;
+use super::geo::LocalFrame;
+use super::types::{MissionFrame, MissionItem, MissionPlan};
+use serde::{Deserialize, Serialize};
+
+const MAV_CMD_NAV_WAYPOINT: u16 = 16;
+/// NAV commands are below this; DO and condition commands are above.
+const MAV_CMD_NAV_LAST: u16 = 95;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Simplified {
+    pub plan: MissionPlan,
+    /// Original `seq` of every removed waypoint.
+    pub removed: Vec<u16>,
+    /// Original `seq` of DO/condition items moved to another waypoint.
+    pub reanchored: Vec<u16>,
+}
+
+/// A NAV item followed by the DO/condition items that run after it.
+struct Group {
+    items: Vec<MissionItem>,
+}
+
+impl Group {
+    fn leader(&self) -> &MissionItem {
+        &self.items[0]
+    }
+
+    /// Plain pass-through waypoints are the only candidates for removal.
+    fn is_track_point(&self) -> bool {
+        let leader = self.leader();
+        leader.command == MAV_CMD_NAV_WAYPOINT && leader.param1 == 0.0 && leader.is_nav_position()
+    }
+}
+
+/// Remove waypoints that deviate less than `tolerance_m` from the straight
+/// track between their neighbours.
+///
+/// Takeoff, loiter and land items, waypoints with a hold time and anything
+/// before the first NAV item are never removed.
+pub fn simplify(plan: &MissionPlan, tolerance_m: f64) -> Simplified {
+    let mut head = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    for item in &plan.items {
+        match groups.last_mut() {
+            Some(group) if item.command > MAV_CMD_NAV_LAST => group.items.push(item.clone()),
+            None if item.command > MAV_CMD_NAV_LAST => head.push(item.clone()),
+            _ => groups.push(Group {
+                items: vec![item.clone()],
+            }),
+        }
+    }
+
+    let mut keep = vec![true; groups.len()];
+    let mut start = 0;
+    while start < groups.len() {
+        let frame = groups[start].leader().frame;
+        let same_frame = |group: &Group| group.leader().frame == frame;
+        let mut end = start;
+        while end + 1 < groups.len()
+            && groups[end + 1].is_track_point()
+            && same_frame(&groups[end + 1])
+        {
+            end += 1;
+        }
+        // The run's end points are kept; they may be any positioned item.
+        if end + 1 < groups.len()
+            && groups[end + 1].leader().is_nav_position()
+            && same_frame(&groups[end + 1])
+        {
+            end += 1;
+        }
+        if end > start + 1 && groups[start].leader().is_nav_position() {
+            let (lat, lon) = groups[start].leader().latlon_deg();
+            let local = LocalFrame::new(lat, lon);
+            let points: Vec<[f64; 3]> = groups[start..=end]
+                .iter()
+                .map(|group| {
+                    let leader = group.leader();
+                    let (lat, lon) = leader.latlon_deg();
+                    let (east, north) = local.to_local(lat, lon);
+                    [east, north, leader.z as f64]
+                })
+                .collect();
+            douglas_peucker(&points, tolerance_m, &mut keep[start..=end]);
+        }
+        start = end.max(start + 1);
+    }
+
+    let mut removed = Vec::new();
+    let mut reanchored = Vec::new();
+    let mut moved: Vec<Vec<MissionItem>> = vec![Vec::new(); groups.len()];
+    for index in (0..groups.len()).filter(|&i| !keep[i]) {
+        let target = nearest_kept(&groups, &keep, index);
+        removed.push(groups[index].leader().seq);
+        for item in &groups[index].items[1..] {
+            reanchored.push(item.seq);
+            moved[target].push(item.clone());
+        }
+    }
+
+    let mut items = head;
+    for ((group, kept), extra) in groups.into_iter().zip(keep).zip(moved) {
+        if kept {
+            items.extend(group.items);
+            items.extend(extra);
+        }
+    }
+    resequence(&mut items);
+    reanchored.sort_unstable();
+    Simplified {
+        plan: MissionPlan {
+            items,
+            ..plan.clone()
+        },
+        removed,
+        reanchored,
+    }
+}
+
+/// Kept group closest to the removed group `index`, looking both ways along
+/// the track. Ties go to the earlier waypoint.
+fn nearest_kept(groups: &[Group], keep: &[bool], index: usize) -> usize {
+    let (lat, lon) = groups[index].leader().latlon_deg();
+    let frame = LocalFrame::new(lat, lon);
+    let distance = |i: usize| {
+        let (lat, lon) = groups[i].leader().latlon_deg();
+        let (east, north) = frame.to_local(lat, lon);
+        east.hypot(north)
+    };
+    let before = (0..index).rev().find(|&i| keep[i]);
+    let after = (index + 1..groups.len()).find(|&i| keep[i]);
+    match (before, after) {
+        (Some(b), Some(a)) if distance(a) < distance(b) => a,
+        (Some(b), _) => b,
+        (None, Some(a)) => a,
+        (None, None) => index,
+    }
+}
+
+fn douglas_peucker(points: &[[f64; 3]], tolerance_m: f64, keep: &mut [bool]) {
+    let last = points.len() - 1;
+    let (mut worst, mut worst_distance) = (0, 0.0);
+    for (i, point) in points.iter().enumerate().take(last).skip(1) {
+        let distance = point_segment_distance_3d(point, &points[0], &points[last]);
+        if distance > worst_distance {
+            (worst, worst_distance) = (i, distance);
+        }
+    }
+    if worst_distance > tolerance_m {
+        douglas_peucker(&points[..=worst], tolerance_m, &mut keep[..=worst]);
+        douglas_peucker(&points[worst..], tolerance_m, &mut keep[worst..]);
+    } else {
+        keep[1..last].iter_mut().for_each(|k| *k = false);
+    }
+}
+
+fn point_segment_distance_3d(p: &[f64; 3], a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    let d: Vec<f64> = (0..3).map(|i| b[i] - a[i]).collect();
+    let len2: f64 = d.iter().map(|v| v * v).sum();
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        ((0..3).map(|i| (p[i] - a[i]) * d[i]).sum::<f64>() / len2).clamp(0.0, 1.0)
+    };
+    (0..3)
+        .map(|i| (p[i] - (a[i] + t * d[i])).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Move every positioned item onto a `spacing_m` grid aligned with the plan
+/// home (or the first positioned item when there is no home).
+pub fn snap_to_grid(plan: &MissionPlan, spacing_m: f64) -> MissionPlan {
+    let origin = plan
+        .home
+        .as_ref()
+        .map(|home| (home.latitude_deg, home.longitude_deg))
+        .or_else(|| {
+            plan.items
+                .iter()
+                .find(|item| item.is_nav_position())
+                .map(MissionItem::latlon_deg)
+        });
+    let mut out = plan.clone();
+    let Some((lat, lon)) = origin.filter(|_| spacing_m > 0.0) else {
+        return out;
+    };
+    let frame = LocalFrame::new(lat, lon);
+    for item in out.items.iter_mut().filter(|item| item.is_nav_position()) {
+        let (lat, lon) = item.latlon_deg();
+        let (east, north) = frame.to_local(lat, lon);
+        let snap = |v: f64| (v / spacing_m).round() * spacing_m;
+        let (lat, lon) = frame.to_global(snap(east), snap(north));
+        item.set_latlon_deg(lat, lon);
+    }
+    out
+}
+
+/// Round positioned items' altitudes to multiples of `step_m`. Terrain-frame
+/// altitudes are rounded up so snapping never lowers the height above ground.
+pub fn snap_altitudes(plan: &MissionPlan, step_m: f32) -> MissionPlan {
+    let mut out = plan.clone();
+    if step_m <= 0.0 {
+        return out;
+    }
+    for item in out.items.iter_mut().filter(|item| item.is_nav_position()) {
+        let steps = item.z / step_m;
+        let steps = if item.frame == MissionFrame::GlobalTerrainAltInt {
+            steps.ceil()
+        } else {
+            steps.round()
+        };
+        item.z = steps * step_m;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{command_item, global_item};
+    use crate::mission::types::MissionType;
+
+    const MAV_CMD_DO_SET_SERVO: u16 = 183;
+
+    /// Points ~11 m apart heading north, with a small sideways wobble.
+    fn wobbly_track(count: usize) -> Vec<MissionItem> {
+        (0..count)
+            .map(|n| {
+                let wobble = if n % 2 == 0 { 0.0 } else { 0.00001 };
+                global_item(16, 47.0 + n as f64 * 0.0001, 8.0 + wobble, 50.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn collapses_a_straight_track_to_its_ends() {
+        let result = simplify(
+            &MissionPlan::for_test(MissionType::Mission, None, wobbly_track(20)),
+            2.0,
+        );
+        assert_eq!(result.plan.items.len(), 2);
+        assert_eq!(result.removed.len(), 18);
+        assert_eq!(result.plan.items[1].seq, 1);
+
+        // The 0.75 m wobble is partly kept with a tighter tolerance
+        let tight = simplify(
+            &MissionPlan::for_test(MissionType::Mission, None, wobbly_track(20)),
+            0.5,
+        );
+        assert!(tight.plan.items.len() > 2);
+    }
+
+    #[test]
+    fn keeps_corners_and_altitude_changes() {
+        let mut items = wobbly_track(5);
+        items.push(global_item(16, 47.0004, 8.001, 50.0));
+        items.push(global_item(16, 47.0004, 8.002, 50.0));
+        items.push(global_item(16, 47.0004, 8.003, 80.0));
+        let result = simplify(
+            &MissionPlan::for_test(MissionType::Mission, None, items),
+            2.0,
+        );
+        let kept: Vec<(f64, f64)> = result
+            .plan
+            .items
+            .iter()
+            .map(MissionItem::latlon_deg)
+            .collect();
+        assert_eq!(kept.len(), 4, "{kept:?}");
+        assert!((kept[1].0 - 47.0004).abs() < 1e-7 && (kept[1].1 - 8.0).abs() < 1e-7);
+        assert_eq!(result.plan.items[3].z, 80.0);
+    }
+
+    #[test]
+    fn reanchors_commands_of_removed_waypoints() {
+        let mut items = wobbly_track(10);
+        // Servo after waypoint 8, closer to the final waypoint than the first
+        items.insert(
+            9,
+            command_item(MAV_CMD_DO_SET_SERVO, [9.0, 1900.0, 0.0, 0.0]),
+        );
+        let result = simplify(
+            &MissionPlan::for_test(MissionType::Mission, None, items),
+            2.0,
+        );
+        let commands: Vec<u16> = result.plan.items.iter().map(|i| i.command).collect();
+        assert_eq!(commands, vec![16, 16, MAV_CMD_DO_SET_SERVO]);
+        assert_eq!(result.reanchored, vec![9]);
+        assert_eq!(result.plan.items[2].param1, 9.0);
+    }
+
+    #[test]
+    fn never_removes_hold_waypoints_or_leading_commands() {
+        let mut items = wobbly_track(5);
+        items[2].param1 = 5.0;
+        items.insert(
+            0,
+            command_item(MAV_CMD_DO_SET_SERVO, [9.0, 1100.0, 0.0, 0.0]),
+        );
+        let result = simplify(
+            &MissionPlan::for_test(MissionType::Mission, None, items),
+            2.0,
+        );
+        let commands: Vec<u16> = result.plan.items.iter().map(|i| i.command).collect();
+        assert_eq!(commands, vec![MAV_CMD_DO_SET_SERVO, 16, 16, 16]);
+        assert_eq!(result.plan.items[2].param1, 5.0);
+    }
+
+    #[test]
+    fn snaps_positions_and_altitudes() {
+        let mut items = vec![global_item(16, 47.00012, 8.00013, 47.4)];
+        items[0].frame = MissionFrame::GlobalTerrainAltInt;
+        items.push(global_item(16, 47.0, 8.0, 47.4));
+        let mut plan = MissionPlan::for_test(MissionType::Mission, None, items);
+        plan.home = Some(crate::mission::types::HomePosition::amsl(47.0, 8.0, 400.0));
+
+        let snapped = snap_to_grid(&plan, 10.0);
+        let frame = LocalFrame::new(47.0, 8.0);
+        let (lat, lon) = snapped.items[0].latlon_deg();
+        let (east, north) = frame.to_local(lat, lon);
+        assert!((east - 10.0).abs() < 0.02 && (north - 10.0).abs() < 0.02);
+
+        let altitudes = snap_altitudes(&plan, 5.0);
+        assert_eq!(altitudes.items[0].z, 50.0);
+        assert_eq!(altitudes.items[1].z, 45.0);
+    }
+}
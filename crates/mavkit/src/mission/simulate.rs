@@ -0,0 +1,447 @@
+//! Kinematic flight preview.
+//!
+//! `simulate` flies a plan point to point at constant speeds and samples the
+//! result once a second, for the preview-flight scrubber and for anything
+//! that needs "where is the vehicle at time t". Turns, acceleration and
+//! autopilot tuning are not modelled; altitudes are item `z` (normally
+//! relative to home).
+
+use super::builder::{
+    MAV_CMD_DO_CHANGE_SPEED, MAV_CMD_NAV_LAND, MAV_CMD_NAV_LOITER_TIME, MAV_CMD_NAV_LOITER_UNLIM,
+    MAV_CMD_NAV_RETURN_TO_LAUNCH, MAV_CMD_NAV_TAKEOFF, MAV_CMD_NAV_VTOL_LAND,
+    MAV_CMD_NAV_VTOL_TAKEOFF, MAV_CMD_NAV_WAYPOINT,
+};
+use super::geo::{bearing_deg, distance_m};
+use super::types::MissionPlan;
+use crate::weather::{wind_triangle, WindProfile};
+use serde::{Deserialize, Serialize};
+
+/// Speeds the simulated vehicle flies at.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VehicleProfile {
+    /// Airspeed between waypoints, until a DO_CHANGE_SPEED changes it.
+    pub cruise_speed_mps: f64,
+    pub climb_rate_mps: f64,
+    pub descent_rate_mps: f64,
+}
+
+impl Default for VehicleProfile {
+    /// ArduCopter WPNAV_SPEED, WPNAV_SPEED_UP and WPNAV_SPEED_DN defaults.
+    fn default() -> Self {
+        Self {
+            cruise_speed_mps: 10.0,
+            climb_rate_mps: 2.5,
+            descent_rate_mps: 1.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SimSample {
+    /// Seconds since the start of the flight.
+    pub t_s: u32,
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    pub altitude_m: f32,
+    /// Nose heading, crabbed into the crosswind, degrees clockwise from north.
+    pub heading_deg: f64,
+    /// Flight path angle; zero while climbing or descending in place.
+    pub pitch_deg: f64,
+    pub ground_speed_mps: f64,
+    pub climb_rate_mps: f64,
+    /// The item being flown to; `None` before the first one.
+    pub seq: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimTimeline {
+    /// One sample per second, from the start position to the end of the
+    /// flight inclusive.
+    pub samples: Vec<SimSample>,
+    pub duration_s: f64,
+    /// Horizontal distance flown.
+    pub distance_m: f64,
+    /// Item whose leg cannot be flown against the wind at the cruise speed;
+    /// the timeline stops before it.
+    pub blocked_at: Option<u16>,
+}
+
+impl SimTimeline {
+    /// The sample at `t_s` seconds, clamped to the end of the flight.
+    pub fn at(&self, t_s: f64) -> Option<&SimSample> {
+        let index = (t_s.max(0.0).round() as usize).min(self.samples.len().checked_sub(1)?);
+        self.samples.get(index)
+    }
+}
+
+/// End of one constant-velocity stretch of the flight.
+#[derive(Debug, Clone, Copy)]
+struct Keyframe {
+    t_s: f64,
+    lat: f64,
+    lon: f64,
+    alt: f64,
+    seq: Option<u16>,
+    heading_deg: f64,
+    pitch_deg: f64,
+    ground_speed_mps: f64,
+    climb_rate_mps: f64,
+}
+
+struct Flight<'a> {
+    profile: VehicleProfile,
+    wind: Option<&'a WindProfile>,
+    keyframes: Vec<Keyframe>,
+    distance_m: f64,
+}
+
+impl Flight<'_> {
+    fn last(&self) -> Keyframe {
+        self.keyframes[self.keyframes.len() - 1]
+    }
+
+    /// Fly in a straight line to `(lat, lon, alt)`. Returns false if the
+    /// wind makes the leg impossible.
+    fn fly_to(&mut self, seq: u16, lat: f64, lon: f64, alt: f64) -> bool {
+        let from = self.last();
+        let horizontal = distance_m(from.lat, from.lon, lat, lon);
+        let climb = alt - from.alt;
+        let airspeed = self.profile.cruise_speed_mps;
+        let (mut heading_deg, mut ground_speed) = (from.heading_deg, 0.0);
+        let mut duration = 0.0_f64;
+        if horizontal > 0.0 {
+            let track_deg = bearing_deg(from.lat, from.lon, lat, lon);
+            let wind = self.wind.and_then(|profile| profile.at(alt));
+            let (_, crosswind, speed) = wind_triangle(wind, track_deg, airspeed);
+            let Some(speed) = speed else {
+                return false;
+            };
+            heading_deg =
+                (track_deg + (crosswind / airspeed).asin().to_degrees()).rem_euclid(360.0);
+            duration = horizontal / speed;
+        }
+        let rate = if climb >= 0.0 {
+            self.profile.climb_rate_mps
+        } else {
+            self.profile.descent_rate_mps
+        };
+        if climb != 0.0 && rate > 0.0 {
+            duration = duration.max(climb.abs() / rate);
+        }
+        if duration <= 0.0 {
+            return true;
+        }
+        if horizontal > 0.0 {
+            ground_speed = horizontal / duration;
+        }
+        let climb_rate = climb / duration;
+        self.distance_m += horizontal;
+        self.keyframes.push(Keyframe {
+            t_s: from.t_s + duration,
+            lat,
+            lon,
+            alt,
+            seq: Some(seq),
+            heading_deg,
+            pitch_deg: if ground_speed > 0.0 {
+                climb_rate.atan2(ground_speed).to_degrees()
+            } else {
+                0.0
+            },
+            ground_speed_mps: ground_speed,
+            climb_rate_mps: climb_rate,
+        });
+        true
+    }
+
+    fn hold(&mut self, seq: u16, seconds: f64) {
+        if seconds > 0.0 {
+            let from = self.last();
+            self.keyframes.push(Keyframe {
+                t_s: from.t_s + seconds,
+                seq: Some(seq),
+                pitch_deg: 0.0,
+                ground_speed_mps: 0.0,
+                climb_rate_mps: 0.0,
+                ..from
+            });
+        }
+    }
+}
+
+/// Fly `plan` with `profile`, through `wind` if given, and sample the flight
+/// once a second.
+///
+/// The flight starts on the ground at home, or at the first positioned item
+/// when the plan has no home. Waypoint and LOITER_TIME holds, takeoff, land
+/// and RTL (when home is known) are flown; the flight ends at a
+/// LOITER_UNLIM.
+pub fn simulate(
+    plan: &MissionPlan,
+    profile: &VehicleProfile,
+    wind: Option<&WindProfile>,
+) -> SimTimeline {
+    let home = plan
+        .home
+        .as_ref()
+        .map(|home| (home.latitude_deg, home.longitude_deg));
+    let start = home.map(|(lat, lon)| (lat, lon, 0.0)).or_else(|| {
+        plan.items
+            .iter()
+            .find(|item| item.is_nav_position())
+            .map(|item| {
+                let (lat, lon) = item.latlon_deg();
+                (lat, lon, f64::from(item.z))
+            })
+    });
+    let Some((lat, lon, alt)) = start else {
+        return SimTimeline {
+            samples: Vec::new(),
+            duration_s: 0.0,
+            distance_m: 0.0,
+            blocked_at: None,
+        };
+    };
+    let mut flight = Flight {
+        profile: *profile,
+        wind,
+        keyframes: vec![Keyframe {
+            t_s: 0.0,
+            lat,
+            lon,
+            alt,
+            seq: None,
+            heading_deg: 0.0,
+            pitch_deg: 0.0,
+            ground_speed_mps: 0.0,
+            climb_rate_mps: 0.0,
+        }],
+        distance_m: 0.0,
+    };
+
+    let mut blocked_at = None;
+    for item in &plan.items {
+        let here = flight.last();
+        let positioned = item.is_nav_position();
+        let (item_lat, item_lon) = if positioned {
+            item.latlon_deg()
+        } else {
+            (here.lat, here.lon)
+        };
+        let z = f64::from(item.z);
+        let flown = match item.command {
+            MAV_CMD_DO_CHANGE_SPEED => {
+                if item.param2 > 0.0 {
+                    flight.profile.cruise_speed_mps = f64::from(item.param2);
+                }
+                true
+            }
+            MAV_CMD_NAV_TAKEOFF | MAV_CMD_NAV_VTOL_TAKEOFF => {
+                flight.fly_to(item.seq, item_lat, item_lon, z)
+            }
+            MAV_CMD_NAV_LAND | MAV_CMD_NAV_VTOL_LAND => {
+                flight.fly_to(item.seq, item_lat, item_lon, here.alt)
+                    && flight.fly_to(item.seq, item_lat, item_lon, 0.0)
+            }
+            MAV_CMD_NAV_RETURN_TO_LAUNCH => match home {
+                Some((home_lat, home_lon)) => {
+                    flight.fly_to(item.seq, home_lat, home_lon, here.alt)
+                        && flight.fly_to(item.seq, home_lat, home_lon, 0.0)
+                }
+                None => true,
+            },
+            MAV_CMD_NAV_LOITER_UNLIM => {
+                if positioned {
+                    flight.fly_to(item.seq, item_lat, item_lon, z);
+                }
+                break;
+            }
+            _ if positioned => {
+                let flown = flight.fly_to(item.seq, item_lat, item_lon, z);
+                if flown && matches!(item.command, MAV_CMD_NAV_WAYPOINT | MAV_CMD_NAV_LOITER_TIME) {
+                    flight.hold(item.seq, f64::from(item.param1));
+                }
+                flown
+            }
+            _ => true,
+        };
+        if !flown {
+            blocked_at = Some(item.seq);
+            break;
+        }
+    }
+
+    let duration_s = flight.last().t_s;
+    SimTimeline {
+        samples: sample(&flight.keyframes, duration_s),
+        duration_s,
+        distance_m: flight.distance_m,
+        blocked_at,
+    }
+}
+
+/// Sample the keyframed flight at every whole second up to `duration_s`,
+/// plus the end position.
+fn sample(keyframes: &[Keyframe], duration_s: f64) -> Vec<SimSample> {
+    let seconds = duration_s.ceil() as u32;
+    let mut next = 1;
+    (0..=seconds)
+        .map(|t_s| {
+            let t = f64::from(t_s).min(duration_s);
+            while next < keyframes.len() - 1 && keyframes[next].t_s < t {
+                next += 1;
+            }
+            let Some(to) = keyframes.get(next) else {
+                return sample_at(t_s, &keyframes[0], &keyframes[0], 0.0);
+            };
+            let from = &keyframes[next - 1];
+            let span = to.t_s - from.t_s;
+            let fraction = if span > 0.0 {
+                ((t - from.t_s) / span).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            sample_at(t_s, from, to, fraction)
+        })
+        .collect()
+}
+
+fn sample_at(t_s: u32, from: &Keyframe, to: &Keyframe, fraction: f64) -> SimSample {
+    let lerp = |a: f64, b: f64| a + (b - a) * fraction;
+    SimSample {
+        t_s,
+        latitude_deg: lerp(from.lat, to.lat),
+        longitude_deg: lerp(from.lon, to.lon),
+        altitude_m: lerp(from.alt, to.alt) as f32,
+        heading_deg: to.heading_deg,
+        pitch_deg: to.pitch_deg,
+        ground_speed_mps: to.ground_speed_mps,
+        climb_rate_mps: to.climb_rate_mps,
+        seq: to.seq,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{command_item, global_item};
+    use crate::mission::geo::destination;
+    use crate::mission::{HomePosition, MissionType};
+    use crate::weather::WindSample;
+
+    const HOME: (f64, f64) = (47.0, 8.0);
+
+    fn home() -> Option<HomePosition> {
+        Some(HomePosition::amsl(HOME.0, HOME.1, 400.0))
+    }
+
+    fn north(distance: f64) -> (f64, f64) {
+        destination(HOME.0, HOME.1, 0.0, distance)
+    }
+
+    #[test]
+    fn takeoff_cruise_hold_and_rtl() {
+        let (lat, lon) = north(500.0);
+        let mut waypoint = global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, 50.0);
+        waypoint.param1 = 10.0;
+        let plan = MissionPlan::for_test(
+            MissionType::Mission,
+            home(),
+            vec![
+                global_item(MAV_CMD_NAV_TAKEOFF, 0.0, 0.0, 50.0),
+                waypoint,
+                command_item(MAV_CMD_NAV_RETURN_TO_LAUNCH, [0.0; 4]),
+            ],
+        );
+        let timeline = simulate(&plan, &VehicleProfile::default(), None);
+
+        // 20 s climb, 50 s out, 10 s hold, 50 s back, 33.3 s descent
+        assert!(
+            (timeline.duration_s - 163.33).abs() < 0.1,
+            "{}",
+            timeline.duration_s
+        );
+        assert!((timeline.distance_m - 1000.0).abs() < 1.0);
+        assert_eq!(timeline.samples.len(), 165);
+        assert_eq!(timeline.blocked_at, None);
+
+        let climbing = &timeline.samples[10];
+        assert!((climbing.altitude_m - 25.0).abs() < 0.01);
+        assert_eq!(climbing.ground_speed_mps, 0.0);
+        assert_eq!(climbing.seq, Some(0));
+
+        let cruising = timeline.at(45.0).unwrap();
+        assert!((cruising.ground_speed_mps - 10.0).abs() < 1e-6);
+        assert!(cruising.heading_deg.min(360.0 - cruising.heading_deg) < 1e-6);
+        assert!(
+            (distance_m(
+                HOME.0,
+                HOME.1,
+                cruising.latitude_deg,
+                cruising.longitude_deg
+            ) - 250.0)
+                .abs()
+                < 1.0
+        );
+
+        let holding = timeline.at(75.0).unwrap();
+        assert_eq!((holding.seq, holding.ground_speed_mps), (Some(1), 0.0));
+
+        let end = timeline.samples.last().unwrap();
+        assert_eq!(end.altitude_m, 0.0);
+        assert!(distance_m(HOME.0, HOME.1, end.latitude_deg, end.longitude_deg) < 0.01);
+    }
+
+    #[test]
+    fn wind_changes_speed_and_heading() {
+        let (lat, lon) = north(1000.0);
+        let plan = MissionPlan::for_test(
+            MissionType::Mission,
+            home(),
+            vec![global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, 0.0)],
+        );
+        let headwind = WindProfile::new(vec![WindSample {
+            altitude_m: 0.0,
+            speed_mps: 5.0,
+            direction_deg: 0.0,
+        }]);
+        let timeline = simulate(&plan, &VehicleProfile::default(), Some(&headwind));
+        assert!((timeline.duration_s - 200.0).abs() < 0.1);
+
+        let crosswind = WindProfile::new(vec![WindSample {
+            altitude_m: 0.0,
+            speed_mps: 5.0,
+            direction_deg: 90.0,
+        }]);
+        let timeline = simulate(&plan, &VehicleProfile::default(), Some(&crosswind));
+        assert!((timeline.samples[1].heading_deg - 30.0).abs() < 0.1);
+
+        let gale = WindProfile::new(vec![WindSample {
+            altitude_m: 0.0,
+            speed_mps: 15.0,
+            direction_deg: 0.0,
+        }]);
+        let timeline = simulate(&plan, &VehicleProfile::default(), Some(&gale));
+        assert_eq!(timeline.blocked_at, Some(0));
+        assert_eq!(timeline.samples.len(), 1);
+    }
+
+    #[test]
+    fn change_speed_and_loiter_unlim_end() {
+        let (lat1, lon1) = north(200.0);
+        let (lat2, lon2) = north(400.0);
+        let plan = MissionPlan::for_test(
+            MissionType::Mission,
+            home(),
+            vec![
+                command_item(MAV_CMD_DO_CHANGE_SPEED, [1.0, 5.0, -1.0, 0.0]),
+                global_item(MAV_CMD_NAV_WAYPOINT, lat1, lon1, 0.0),
+                global_item(MAV_CMD_NAV_LOITER_UNLIM, lat2, lon2, 0.0),
+                global_item(MAV_CMD_NAV_WAYPOINT, HOME.0, HOME.1, 0.0),
+            ],
+        );
+        let timeline = simulate(&plan, &VehicleProfile::default(), None);
+        assert!((timeline.duration_s - 80.0).abs() < 0.1);
+        assert_eq!(timeline.samples.last().unwrap().seq, Some(2));
+    }
+}
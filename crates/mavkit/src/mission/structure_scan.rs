@@ -0,0 +1,386 @@
+use super::builder::{
+    command_item, global_item, resequence, MAV_CMD_DO_SET_ROI_LOCATION, MAV_CMD_DO_SET_ROI_NONE,
+    MAV_CMD_NAV_WAYPOINT,
+};
+use super::geo::LocalFrame;
+use super::sun::sun_position;
+use super::types::{MissionPlan, MissionType};
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on scan layers, far above any real structure.
+const MAX_LAYERS: usize = 1000;
+
+/// Parameters for an orbit-layered vertical facade scan around a structure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureScanParams {
+    /// Structure footprint as `(lat_deg, lon_deg)` vertices, in any winding order.
+    pub footprint: Vec<(f64, f64)>,
+    /// Altitude of the lowest layer, relative to home.
+    pub bottom_alt_m: f32,
+    /// Altitude of the highest layer, relative to home.
+    pub top_alt_m: f32,
+    /// Horizontal distance kept between the flight path and the facade.
+    pub standoff_m: f64,
+    /// Vertical distance between layers.
+    pub layer_spacing_m: f32,
+    /// Maximum distance between consecutive waypoints along a layer.
+    pub max_point_spacing_m: f64,
+    /// Gimbal pitch per layer, bottom first. The last value is reused for any
+    /// further layers; empty means level (0 degrees).
+    pub gimbal_pitch_deg: Vec<f32>,
+    /// Planned start time (unix seconds). When set and the sun is up, each
+    /// orbit starts on the sunlit side of the structure.
+    #[serde(default)]
+    pub start_unix_s: Option<i64>,
+}
+
+/// Generate a `MissionPlan` that orbits the structure once per layer.
+///
+/// Each layer starts with a `DO_SET_ROI_LOCATION` over the footprint
+/// centroid, followed by the waypoints of the offset footprint. The ROI sits
+/// below (or above) the layer so that, seen from the orbit's average
+/// distance, the gimbal points at the layer's pitch; ArduPilot aims the
+/// mount at the ROI and ignores any separate pitch. The plan ends with
+/// `DO_SET_ROI_NONE`.
+pub fn generate_structure_scan(params: &StructureScanParams) -> Result<MissionPlan, String> {
+    if params.footprint.len() < 3 {
+        return Err("footprint needs at least 3 vertices".to_string());
+    }
+    if !is_positive(params.standoff_m) {
+        return Err("standoff_m must be positive".to_string());
+    }
+    if !is_positive(params.layer_spacing_m as f64) {
+        return Err("layer_spacing_m must be positive".to_string());
+    }
+    if !is_positive(params.max_point_spacing_m) {
+        return Err("max_point_spacing_m must be positive".to_string());
+    }
+    if params.top_alt_m < params.bottom_alt_m {
+        return Err("top_alt_m must not be below bottom_alt_m".to_string());
+    }
+
+    let (origin_lat, origin_lon) = params.footprint[0];
+    let frame = LocalFrame::new(origin_lat, origin_lon);
+    let mut local: Vec<(f64, f64)> = params
+        .footprint
+        .iter()
+        .map(|&(lat, lon)| frame.to_local(lat, lon))
+        .collect();
+    if signed_area(&local) < 0.0 {
+        local.reverse();
+    }
+
+    let centroid = {
+        let n = local.len() as f64;
+        let (sx, sy) = local
+            .iter()
+            .fold((0.0, 0.0), |(ax, ay), &(x, y)| (ax + x, ay + y));
+        frame.to_global(sx / n, sy / n)
+    };
+
+    let mut path = resample_closed(
+        &offset_polygon(&local, params.standoff_m),
+        params.max_point_spacing_m,
+    );
+    if let Some(start) = params.start_unix_s {
+        let sun = sun_position(centroid.0, centroid.1, start);
+        if sun.elevation_deg > 0.0 {
+            let (cx, cy) = frame.to_local(centroid.0, centroid.1);
+            let off_sun = |&(east, north): &(f64, f64)| {
+                let bearing = (east - cx).atan2(north - cy).to_degrees();
+                let diff = (bearing - sun.azimuth_deg).rem_euclid(360.0);
+                diff.min(360.0 - diff)
+            };
+            if let Some(first) =
+                (0..path.len()).min_by(|a, b| off_sun(&path[*a]).total_cmp(&off_sun(&path[*b])))
+            {
+                path.rotate_left(first);
+            }
+        }
+    }
+
+    // Horizontal distance the ROI is seen from
+    let reach = {
+        let (cx, cy) = frame.to_local(centroid.0, centroid.1);
+        let total: f64 = path
+            .iter()
+            .map(|&(east, north)| (east - cx).hypot(north - cy))
+            .sum();
+        total / path.len() as f64
+    };
+
+    let mut items = Vec::new();
+    for (layer, alt) in layer_altitudes(params)?.into_iter().enumerate() {
+        let pitch = params
+            .gimbal_pitch_deg
+            .get(layer)
+            .or(params.gimbal_pitch_deg.last())
+            .copied()
+            .unwrap_or(0.0);
+
+        let roi_alt = alt as f64 + reach * (pitch as f64).to_radians().tan();
+        items.push(global_item(
+            MAV_CMD_DO_SET_ROI_LOCATION,
+            centroid.0,
+            centroid.1,
+            roi_alt as f32,
+        ));
+
+        // Close the orbit by returning to the layer's first point
+        for &(east, north) in path.iter().chain(path.first()) {
+            let (lat, lon) = frame.to_global(east, north);
+            items.push(global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, alt));
+        }
+    }
+    items.push(command_item(MAV_CMD_DO_SET_ROI_NONE, [0.0; 4]));
+    if items.len() > u16::MAX as usize {
+        return Err(format!(
+            "scan needs {} mission items, more than a mission can hold",
+            items.len()
+        ));
+    }
+    resequence(&mut items);
+
+    Ok(MissionPlan {
+        mission_type: MissionType::Mission,
+        home: None,
+        items,
+        metadata: Default::default(),
+    })
+}
+
+fn is_positive(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// Layers from `bottom_alt_m` every `layer_spacing_m`, plus one at
+/// `top_alt_m` unless a layer already lies within 1 cm of it.
+fn layer_altitudes(params: &StructureScanParams) -> Result<Vec<f32>, String> {
+    let bottom = params.bottom_alt_m as f64;
+    let spacing = params.layer_spacing_m as f64;
+    let span = params.top_alt_m as f64 - bottom - 0.01;
+    let below_top = if span > 0.0 {
+        (span / spacing).ceil()
+    } else {
+        0.0
+    };
+    if below_top >= MAX_LAYERS as f64 {
+        return Err(format!(
+            "layer_spacing_m gives more than {MAX_LAYERS} layers"
+        ));
+    }
+    let mut alts: Vec<f32> = (0..below_top as usize)
+        .map(|i| (bottom + i as f64 * spacing) as f32)
+        .collect();
+    alts.push(params.top_alt_m);
+    Ok(alts)
+}
+
+/// Positive for counter-clockwise polygons.
+fn signed_area(points: &[(f64, f64)]) -> f64 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let (x1, y1) = points[i];
+        let (x2, y2) = points[(i + 1) % points.len()];
+        area += x1 * y2 - x2 * y1;
+    }
+    area / 2.0
+}
+
+/// Offset a counter-clockwise polygon outward by `distance` using mitred
+/// corners. Miters are clamped to twice the distance so sharp corners don't
+/// produce far-away spikes.
+fn offset_polygon(points: &[(f64, f64)], distance: f64) -> Vec<(f64, f64)> {
+    let n = points.len();
+    let outward_normal = |a: (f64, f64), b: (f64, f64)| {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len = dx.hypot(dy).max(f64::EPSILON);
+        // Right-hand normal points outward for CCW winding
+        (dy / len, -dx / len)
+    };
+
+    (0..n)
+        .map(|i| {
+            let prev = points[(i + n - 1) % n];
+            let cur = points[i];
+            let next = points[(i + 1) % n];
+            let n1 = outward_normal(prev, cur);
+            let n2 = outward_normal(cur, next);
+            let (mx, my) = (n1.0 + n2.0, n1.1 + n2.1);
+            let mlen = mx.hypot(my);
+            if mlen < 1e-9 {
+                return (cur.0 + n1.0 * distance, cur.1 + n1.1 * distance);
+            }
+            let (mx, my) = (mx / mlen, my / mlen);
+            let cos_half = (mx * n1.0 + my * n1.1).max(0.5);
+            let miter = distance / cos_half;
+            (cur.0 + mx * miter, cur.1 + my * miter)
+        })
+        .collect()
+}
+
+/// Insert intermediate points so no edge of the closed polygon exceeds `max_spacing`.
+fn resample_closed(points: &[(f64, f64)], max_spacing: f64) -> Vec<(f64, f64)> {
+    let mut out = Vec::new();
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let len = (b.0 - a.0).hypot(b.1 - a.1);
+        let segments = (len / max_spacing).ceil().max(1.0) as usize;
+        for s in 0..segments {
+            let t = s as f64 / segments as f64;
+            out.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::geo::{distance_m, LocalFrame};
+    use crate::mission::{e7_to_deg, validate_plan};
+
+    fn square_params() -> StructureScanParams {
+        // 20 m x 20 m square footprint
+        let frame = LocalFrame::new(47.0, 8.0);
+        let footprint = [(0.0, 0.0), (20.0, 0.0), (20.0, 20.0), (0.0, 20.0)]
+            .iter()
+            .map(|&(e, n)| frame.to_global(e, n))
+            .collect();
+        StructureScanParams {
+            footprint,
+            bottom_alt_m: 10.0,
+            top_alt_m: 30.0,
+            standoff_m: 5.0,
+            layer_spacing_m: 10.0,
+            max_point_spacing_m: 100.0,
+            gimbal_pitch_deg: vec![0.0, -15.0],
+            start_unix_s: None,
+        }
+    }
+
+    #[test]
+    fn generates_one_orbit_per_layer() {
+        let plan = generate_structure_scan(&square_params()).unwrap();
+        assert!(validate_plan(&plan).is_empty());
+
+        let waypoint_alts: Vec<f32> = plan
+            .items
+            .iter()
+            .filter(|i| i.command == MAV_CMD_NAV_WAYPOINT)
+            .map(|i| i.z)
+            .collect();
+        // 3 layers (10, 20, 30) x (4 corners + closing point)
+        assert_eq!(waypoint_alts.len(), 15);
+        assert_eq!(waypoint_alts[0], 10.0);
+        assert_eq!(waypoint_alts[14], 30.0);
+        assert_eq!(plan.items.last().unwrap().command, MAV_CMD_DO_SET_ROI_NONE);
+    }
+
+    #[test]
+    fn roi_altitude_gives_gimbal_pitch() {
+        let plan = generate_structure_scan(&square_params()).unwrap();
+        let pitches: Vec<f64> = plan
+            .items
+            .windows(2)
+            .filter(|pair| pair[0].command == MAV_CMD_DO_SET_ROI_LOCATION)
+            .map(|pair| {
+                let (roi, waypoint) = (&pair[0], &pair[1]);
+                // Every corner of the square orbit is equally far from the centroid
+                let reach = distance_m(
+                    e7_to_deg(roi.x),
+                    e7_to_deg(roi.y),
+                    e7_to_deg(waypoint.x),
+                    e7_to_deg(waypoint.y),
+                );
+                ((roi.z - waypoint.z) as f64 / reach).atan().to_degrees()
+            })
+            .collect();
+        assert_eq!(pitches.len(), 3);
+        for (pitch, expected) in pitches.iter().zip([0.0, -15.0, -15.0]) {
+            assert!((pitch - expected).abs() < 0.1, "{pitch} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn waypoints_keep_standoff_from_facade() {
+        let params = square_params();
+        let plan = generate_structure_scan(&params).unwrap();
+        let (lat0, lon0) = params.footprint[0];
+        let first_wp = plan
+            .items
+            .iter()
+            .find(|i| i.command == MAV_CMD_NAV_WAYPOINT)
+            .unwrap();
+        // Corner offset by a 45 degree miter: sqrt(2) * standoff
+        let d = distance_m(lat0, lon0, first_wp.x as f64 / 1e7, first_wp.y as f64 / 1e7);
+        assert!((d - 5.0 * 2f64.sqrt()).abs() < 0.1, "got {d}");
+    }
+
+    #[test]
+    fn resampling_respects_max_spacing() {
+        let mut params = square_params();
+        params.max_point_spacing_m = 8.0;
+        params.top_alt_m = params.bottom_alt_m;
+        let plan = generate_structure_scan(&params).unwrap();
+        let wps: Vec<_> = plan
+            .items
+            .iter()
+            .filter(|i| i.command == MAV_CMD_NAV_WAYPOINT)
+            .collect();
+        for pair in wps.windows(2) {
+            let d = distance_m(
+                pair[0].x as f64 / 1e7,
+                pair[0].y as f64 / 1e7,
+                pair[1].x as f64 / 1e7,
+                pair[1].y as f64 / 1e7,
+            );
+            assert!(d <= 8.01, "spacing {d}");
+        }
+    }
+
+    #[test]
+    fn orbit_starts_on_sunlit_side() {
+        let mut params = square_params();
+        // Mid-morning at 47N 8E in June: sun to the south-east
+        params.start_unix_s = Some(1_718_960_400);
+        let plan = generate_structure_scan(&params).unwrap();
+        let first_wp = plan
+            .items
+            .iter()
+            .find(|i| i.command == MAV_CMD_NAV_WAYPOINT)
+            .unwrap();
+        let frame = LocalFrame::new(47.0, 8.0);
+        let (east, north) = frame.to_local(first_wp.x as f64 / 1e7, first_wp.y as f64 / 1e7);
+        // South-east corner of the 20 m square, offset outward
+        assert!(east > 20.0 && north < 0.0, "({east}, {north})");
+    }
+
+    #[test]
+    fn layers_step_from_bottom_and_end_at_top() {
+        let params = StructureScanParams {
+            top_alt_m: 25.0,
+            ..square_params()
+        };
+        assert_eq!(layer_altitudes(&params).unwrap(), vec![10.0, 20.0, 25.0]);
+    }
+
+    #[test]
+    fn rejects_spacing_too_small_for_the_span() {
+        let params = StructureScanParams {
+            bottom_alt_m: 100.0,
+            top_alt_m: 120.0,
+            layer_spacing_m: 1e-7,
+            ..square_params()
+        };
+        assert!(generate_structure_scan(&params).is_err());
+    }
+
+    #[test]
+    fn rejects_degenerate_footprint() {
+        let mut params = square_params();
+        params.footprint.truncate(2);
+        assert!(generate_structure_scan(&params).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Solar geometry for survey planning.
+//!
+//! Low sun casts long shadows that hide detail and confuse photogrammetry
+//! matching, so surveys are checked against the sun elevation over the
+//! flight. Positions use the NOAA low-precision formulas, good to a fraction
+//! of a degree, without atmospheric refraction.
+
+use super::types::{IssueSeverity, MissionIssue};
+use serde::{Deserialize, Serialize};
+
+/// Below this elevation shadows are longer than ~2.7x the object height.
+pub const LOW_SUN_ELEVATION_DEG: f64 = 20.0;
+/// Upper edge of the golden hour.
+const GOLDEN_HOUR_ELEVATION_DEG: f64 = 6.0;
+/// Sunrise and sunset: the upper limb on the horizon, with refraction.
+const HORIZON_ELEVATION_DEG: f64 = -0.833;
+const SEARCH_STEP_S: i64 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SunPosition {
+    /// Degrees clockwise from north.
+    pub azimuth_deg: f64,
+    /// Degrees above the horizon; negative at night.
+    pub elevation_deg: f64,
+}
+
+/// Sun position at `unix_s` seen from `lat`/`lon` degrees.
+pub fn sun_position(lat: f64, lon: f64, unix_s: i64) -> SunPosition {
+    // Days since J2000.0
+    let n = unix_s as f64 / 86_400.0 - 10_957.5;
+    let mean_longitude = (280.460 + 0.985_647_4 * n).rem_euclid(360.0);
+    let mean_anomaly = (357.528 + 0.985_600_3 * n).to_radians();
+    let ecliptic_longitude =
+        (mean_longitude + 1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin())
+            .to_radians();
+    let obliquity = (23.439 - 0.000_000_4 * n).to_radians();
+
+    let right_ascension = (obliquity.cos() * ecliptic_longitude.sin())
+        .atan2(ecliptic_longitude.cos())
+        .to_degrees();
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+    let sidereal_deg = (280.460_618_37 + 360.985_647_366_29 * n + lon).rem_euclid(360.0);
+    let hour_angle = (sidereal_deg - right_ascension).to_radians();
+
+    let phi = lat.to_radians();
+    let elevation =
+        (phi.sin() * declination.sin() + phi.cos() * declination.cos() * hour_angle.cos()).asin();
+    let azimuth =
+        (-hour_angle.sin()).atan2(declination.tan() * phi.cos() - phi.sin() * hour_angle.cos());
+    SunPosition {
+        azimuth_deg: azimuth.to_degrees().rem_euclid(360.0),
+        elevation_deg: elevation.to_degrees(),
+    }
+}
+
+/// Sun events of one local solar day, as unix seconds. Events the sun never
+/// reaches that day (polar day or night) are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SunTimes {
+    pub sunrise: Option<i64>,
+    /// End of the morning golden hour, when the sun climbs past 6 degrees.
+    pub morning_golden_hour_end: Option<i64>,
+    pub solar_noon: i64,
+    /// Start of the evening golden hour.
+    pub evening_golden_hour_start: Option<i64>,
+    pub sunset: Option<i64>,
+}
+
+/// Sun events for the local solar day containing `unix_s`.
+pub fn sun_times(lat: f64, lon: f64, unix_s: i64) -> SunTimes {
+    let offset_s = (lon / 15.0 * 3600.0) as i64;
+    let midnight = (unix_s + offset_s).div_euclid(86_400) * 86_400 - offset_s;
+    let elevation = |t: i64| sun_position(lat, lon, t).elevation_deg;
+
+    let samples: Vec<i64> = (0..=86_400 / SEARCH_STEP_S)
+        .map(|i| midnight + i * SEARCH_STEP_S)
+        .collect();
+    let solar_noon = samples
+        .iter()
+        .copied()
+        .max_by(|a, b| elevation(*a).total_cmp(&elevation(*b)))
+        .unwrap_or(midnight);
+
+    // First crossing of `threshold` in the given direction
+    let crossing = |threshold: f64, rising: bool| {
+        samples.windows(2).find_map(|pair| {
+            let (a, b) = (
+                elevation(pair[0]) - threshold,
+                elevation(pair[1]) - threshold,
+            );
+            if (rising && a < 0.0 && b >= 0.0) || (!rising && a >= 0.0 && b < 0.0) {
+                Some(bisect(pair[0], pair[1], |t| {
+                    (elevation(t) >= threshold) == rising
+                }))
+            } else {
+                None
+            }
+        })
+    };
+    SunTimes {
+        sunrise: crossing(HORIZON_ELEVATION_DEG, true),
+        morning_golden_hour_end: crossing(GOLDEN_HOUR_ELEVATION_DEG, true),
+        solar_noon,
+        evening_golden_hour_start: crossing(GOLDEN_HOUR_ELEVATION_DEG, false),
+        sunset: crossing(HORIZON_ELEVATION_DEG, false),
+    }
+}
+
+/// First second in `[lo, hi]` where `reached` becomes true.
+fn bisect(mut lo: i64, mut hi: i64, reached: impl Fn(i64) -> bool) -> i64 {
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if reached(mid) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    hi
+}
+
+/// Warnings for a survey at `lat`/`lon` flown from `start_unix_s` for
+/// `duration_s`: the sun below the horizon, or low enough for long shadows,
+/// at the start or end of the flight.
+pub fn sun_warnings(lat: f64, lon: f64, start_unix_s: i64, duration_s: i64) -> Vec<MissionIssue> {
+    let lowest = [start_unix_s, start_unix_s + duration_s.max(0)]
+        .into_iter()
+        .map(|t| sun_position(lat, lon, t).elevation_deg)
+        .fold(f64::INFINITY, f64::min);
+    let (code, message) = if lowest < 0.0 {
+        (
+            "sun.below_horizon",
+            "The sun is below the horizon during part of the survey".to_string(),
+        )
+    } else if lowest < LOW_SUN_ELEVATION_DEG {
+        (
+            "sun.low_elevation",
+            format!("Sun elevation drops to {lowest:.0}° during the survey; expect long shadows"),
+        )
+    } else {
+        return Vec::new();
+    };
+    vec![MissionIssue {
+        code: code.to_string(),
+        message,
+        seq: None,
+        severity: IssueSeverity::Warning,
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-06-21 12:00:00 UTC
+    const SOLSTICE_NOON: i64 = 1_718_971_200;
+    const GREENWICH: (f64, f64) = (51.4769, 0.0);
+
+    #[test]
+    fn solstice_noon_at_greenwich() {
+        let sun = sun_position(GREENWICH.0, GREENWICH.1, SOLSTICE_NOON);
+        assert!((sun.elevation_deg - 61.96).abs() < 0.3, "{sun:?}");
+        assert!((sun.azimuth_deg - 180.0).abs() < 2.0, "{sun:?}");
+
+        let morning = sun_position(GREENWICH.0, GREENWICH.1, SOLSTICE_NOON - 6 * 3600);
+        assert!(morning.azimuth_deg > 45.0 && morning.azimuth_deg < 90.0);
+    }
+
+    #[test]
+    fn solstice_sunrise_and_sunset_at_greenwich() {
+        let times = sun_times(GREENWICH.0, GREENWICH.1, SOLSTICE_NOON);
+        // 03:43 and 20:21 UTC
+        let sunrise = times.sunrise.unwrap() - (SOLSTICE_NOON - 12 * 3600);
+        let sunset = times.sunset.unwrap() - (SOLSTICE_NOON - 12 * 3600);
+        assert!((sunrise - (3 * 3600 + 43 * 60)).abs() < 300, "{sunrise}");
+        assert!((sunset - (20 * 3600 + 21 * 60)).abs() < 300, "{sunset}");
+        assert!(times.morning_golden_hour_end.unwrap() > times.sunrise.unwrap());
+        assert!(times.evening_golden_hour_start.unwrap() < times.sunset.unwrap());
+        assert!((times.solar_noon - SOLSTICE_NOON).abs() <= SEARCH_STEP_S);
+    }
+
+    #[test]
+    fn midnight_sun_has_no_sunset() {
+        let times = sun_times(80.0, 15.0, SOLSTICE_NOON);
+        assert_eq!((times.sunrise, times.sunset), (None, None));
+    }
+
+    #[test]
+    fn warns_about_low_sun() {
+        let (lat, lon) = GREENWICH;
+        assert!(sun_warnings(lat, lon, SOLSTICE_NOON, 1800).is_empty());
+        let evening = sun_warnings(lat, lon, SOLSTICE_NOON + 6 * 3600, 1800);
+        assert_eq!(evening[0].code, "sun.low_elevation");
+        let night = sun_warnings(lat, lon, SOLSTICE_NOON + 12 * 3600, 600);
+        assert_eq!(night[0].code, "sun.below_horizon");
+    }
+}
@@ -0,0 +1,160 @@
+//! "Send everything": mission, fence and rally uploaded as one operation.
+//!
+//! The three transfers run one after another. There is no protocol-level
+//! rollback, so a failure stops the sequence and the `SyncReport` records
+//! which parts reached the vehicle and which were never attempted.
+
+use serde::{Deserialize, Serialize};
+
+use super::transfer::TransferProgress;
+use super::types::{MissionPlan, MissionType};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SyncOutcome {
+    Uploaded,
+    /// No plan was given for this type; the vehicle's copy is untouched.
+    Skipped,
+    Failed {
+        error: String,
+    },
+    /// An earlier part failed, so this one was not sent.
+    NotAttempted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncPart {
+    pub mission_type: MissionType,
+    pub outcome: SyncOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub parts: Vec<SyncPart>,
+}
+
+impl SyncReport {
+    /// Every part was either uploaded or deliberately skipped.
+    pub fn is_complete(&self) -> bool {
+        self.parts
+            .iter()
+            .all(|p| matches!(p.outcome, SyncOutcome::Uploaded | SyncOutcome::Skipped))
+    }
+
+    pub fn uploaded(&self) -> Vec<MissionType> {
+        self.parts
+            .iter()
+            .filter(|p| p.outcome == SyncOutcome::Uploaded)
+            .map(|p| p.mission_type)
+            .collect()
+    }
+}
+
+/// Progress across all parts of a sync, in wire items.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncProgress {
+    pub mission_type: MissionType,
+    /// 0-based index of the part being transferred.
+    pub part_index: usize,
+    pub part_count: usize,
+    pub completed_items: u32,
+    pub total_items: u32,
+}
+
+/// Items each plan puts on the wire (Mission adds the home slot).
+pub fn wire_item_count(plan: &MissionPlan) -> u16 {
+    let home = u16::from(plan.mission_type == MissionType::Mission);
+    plan.items.len() as u16 + home
+}
+
+/// Combine the progress of the active transfer with the sizes of the parts
+/// being synced (`(type, wire items)` in upload order). Returns `None` if
+/// `current` is for a type not in `parts`.
+pub fn sync_progress(
+    parts: &[(MissionType, u16)],
+    current: &TransferProgress,
+) -> Option<SyncProgress> {
+    let part_index = parts
+        .iter()
+        .position(|(mission_type, _)| *mission_type == current.mission_type)?;
+    let done_before: u32 = parts[..part_index].iter().map(|(_, n)| *n as u32).sum();
+    let total_items = parts.iter().map(|(_, n)| *n as u32).sum();
+    Some(SyncProgress {
+        mission_type: current.mission_type,
+        part_index,
+        part_count: parts.len(),
+        completed_items: done_before + current.completed_items as u32,
+        total_items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::transfer::{TransferDirection, TransferPhase};
+
+    fn progress(mission_type: MissionType, completed_items: u16) -> TransferProgress {
+        TransferProgress {
+            direction: TransferDirection::Upload,
+            mission_type,
+            phase: TransferPhase::TransferItems,
+            completed_items,
+            total_items: 0,
+            retries_used: 0,
+        }
+    }
+
+    #[test]
+    fn combined_progress_counts_earlier_parts() {
+        let parts = [
+            (MissionType::Mission, 11),
+            (MissionType::Fence, 5),
+            (MissionType::Rally, 2),
+        ];
+        let p = sync_progress(&parts, &progress(MissionType::Fence, 3)).unwrap();
+        assert_eq!(p.part_index, 1);
+        assert_eq!(p.completed_items, 14);
+        assert_eq!(p.total_items, 18);
+        assert!(sync_progress(&parts[..1], &progress(MissionType::Rally, 0)).is_none());
+    }
+
+    #[test]
+    fn report_tracks_partial_success() {
+        let report = SyncReport {
+            parts: vec![
+                SyncPart {
+                    mission_type: MissionType::Mission,
+                    outcome: SyncOutcome::Uploaded,
+                },
+                SyncPart {
+                    mission_type: MissionType::Fence,
+                    outcome: SyncOutcome::Failed {
+                        error: "timeout".into(),
+                    },
+                },
+                SyncPart {
+                    mission_type: MissionType::Rally,
+                    outcome: SyncOutcome::NotAttempted,
+                },
+            ],
+        };
+        assert!(!report.is_complete());
+        assert_eq!(report.uploaded(), vec![MissionType::Mission]);
+    }
+
+    #[test]
+    fn mission_wire_count_includes_home() {
+        let plan = MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items: Vec::new(),
+            metadata: Default::default(),
+        };
+        assert_eq!(wire_item_count(&plan), 1);
+        let fence = MissionPlan {
+            mission_type: MissionType::Fence,
+            ..plan
+        };
+        assert_eq!(wire_item_count(&fence), 0);
+    }
+}
@@ -0,0 +1,359 @@
+//! Retarget a plan to a constant height above terrain.
+//!
+//! Legacy plans often fly one AMSL or home-relative altitude over rising
+//! ground. `retarget_agl` recomputes every positioned waypoint so that it sits
+//! `target_agl_m` above the terrain under it, keeping each item's frame:
+//! AMSL items get terrain + AGL, home-relative items get that minus the home
+//! AMSL altitude and terrain-frame items get the AGL height directly.
+//!
+//! Terrain is only sampled at the waypoints by the autopilot's altitude
+//! controller, so the legs are sampled too: when a ridge between two waypoints
+//! would bring the straight leg below the target height, the leg's end
+//! waypoint is raised (clamped) until the whole leg clears it.
+
+use super::geo::{bearing_deg, destination, distance_m};
+use super::types::{MissionFrame, MissionPlan};
+use serde::{Deserialize, Serialize};
+
+/// Spacing of terrain samples along each leg.
+const LEG_SAMPLE_SPACING_M: f64 = 30.0;
+/// Raises smaller than this are rounding noise, not a clamp.
+const CLAMP_TOLERANCE_M: f64 = 0.1;
+
+/// Ground elevation lookup (SRTM tiles, a DEM file, an online service...).
+pub trait TerrainSource {
+    /// Terrain height above mean sea level, or `None` where there is no data.
+    fn elevation_amsl_m(&self, lat_deg: f64, lon_deg: f64) -> Option<f64>;
+}
+
+/// The same elevation everywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FlatTerrain {
+    pub elevation_amsl_m: f64,
+}
+
+impl TerrainSource for FlatTerrain {
+    fn elevation_amsl_m(&self, _lat_deg: f64, _lon_deg: f64) -> Option<f64> {
+        Some(self.elevation_amsl_m)
+    }
+}
+
+impl<F: Fn(f64, f64) -> Option<f64>> TerrainSource for F {
+    fn elevation_amsl_m(&self, lat_deg: f64, lon_deg: f64) -> Option<f64> {
+        self(lat_deg, lon_deg)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnreachableReason {
+    /// No terrain data at the waypoint.
+    NoTerrainData,
+    /// A home-relative item, but the plan has no home with an AMSL altitude.
+    NoHomeAltitude,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClampedPoint {
+    pub seq: u16,
+    /// Altitude that would keep the waypoint itself at the target height.
+    pub computed_m: f32,
+    /// Altitude written to the plan so the leg into it clears the terrain.
+    pub applied_m: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UnreachablePoint {
+    pub seq: u16,
+    pub reason: UnreachableReason,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AglReport {
+    /// Items whose altitude was recomputed.
+    pub retargeted: Vec<u16>,
+    pub clamped: Vec<ClampedPoint>,
+    /// Items left unchanged because their altitude could not be computed.
+    pub unreachable: Vec<UnreachablePoint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AglRetarget {
+    pub plan: MissionPlan,
+    pub report: AglReport,
+}
+
+/// Recompute waypoint altitudes to fly `target_agl_m` above `terrain`.
+///
+/// Only positioned NAV items in a global frame are touched. Items whose
+/// altitude cannot be computed are left as they were and listed as
+/// unreachable; they still anchor the leg checks of their neighbours.
+pub fn retarget_agl(
+    plan: &MissionPlan,
+    target_agl_m: f32,
+    terrain: &impl TerrainSource,
+) -> AglRetarget {
+    let home_amsl = plan
+        .home
+        .as_ref()
+        .and_then(|home| home.altitude_amsl_m(None))
+        .map(f64::from);
+    let agl = target_agl_m as f64;
+    let mut out = plan.clone();
+    let mut report = AglReport::default();
+    // Previous waypoint: position and AMSL altitude.
+    let mut previous: Option<(f64, f64, f64)> = None;
+
+    for item in &mut out.items {
+        if !item.is_nav_position() {
+            continue;
+        }
+        let (lat, lon) = item.latlon_deg();
+        let to_amsl = |z: f64| match item.frame {
+            MissionFrame::GlobalInt => Some(z),
+            MissionFrame::GlobalRelativeAltInt => home_amsl.map(|home| home + z),
+            _ => terrain.elevation_amsl_m(lat, lon).map(|ground| ground + z),
+        };
+        let unreachable = match (terrain.elevation_amsl_m(lat, lon), item.frame) {
+            (None, _) => Some(UnreachableReason::NoTerrainData),
+            (Some(_), MissionFrame::GlobalRelativeAltInt) if home_amsl.is_none() => {
+                Some(UnreachableReason::NoHomeAltitude)
+            }
+            _ => None,
+        };
+        if let Some(reason) = unreachable {
+            report.unreachable.push(UnreachablePoint {
+                seq: item.seq,
+                reason,
+            });
+            previous = to_amsl(item.z as f64).map(|amsl| (lat, lon, amsl));
+            continue;
+        }
+        let ground = terrain.elevation_amsl_m(lat, lon).unwrap_or_default();
+        let computed = ground + agl;
+        // The autopilot follows terrain itself in the terrain frame, so
+        // only the other frames need their legs checked.
+        let applied = match (item.frame, previous) {
+            (MissionFrame::GlobalTerrainAltInt, _) | (_, None) => computed,
+            (_, Some(from)) => match leg_floor(from, (lat, lon), agl, terrain) {
+                floor if floor > computed + CLAMP_TOLERANCE_M => floor,
+                _ => computed,
+            },
+        };
+        let from_amsl = |amsl: f64| match item.frame {
+            MissionFrame::GlobalInt => amsl,
+            MissionFrame::GlobalRelativeAltInt => amsl - home_amsl.unwrap_or_default(),
+            _ => amsl - ground,
+        } as f32;
+        if applied > computed {
+            report.clamped.push(ClampedPoint {
+                seq: item.seq,
+                computed_m: from_amsl(computed),
+                applied_m: from_amsl(applied),
+            });
+        }
+        item.z = from_amsl(applied);
+        report.retargeted.push(item.seq);
+        previous = Some((lat, lon, applied));
+    }
+
+    AglRetarget { plan: out, report }
+}
+
+/// The lowest point of a straight flight path relative to the terrain.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PathClearance {
+    /// Height of the path above the terrain there; negative when the path
+    /// runs into the ground.
+    pub min_clearance_m: f64,
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    /// Distance from the start of the path.
+    pub along_m: f64,
+}
+
+/// Sample the terrain under the straight path between two `(lat, lon,
+/// altitude AMSL)` points, both ends included, with the altitude changing
+/// linearly along it. `None` when no sample has terrain data.
+pub fn path_clearance(
+    from: (f64, f64, f64),
+    to: (f64, f64, f64),
+    terrain: &(impl TerrainSource + ?Sized),
+) -> Option<PathClearance> {
+    let (from_lat, from_lon, from_alt) = from;
+    let length = distance_m(from_lat, from_lon, to.0, to.1);
+    let bearing = bearing_deg(from_lat, from_lon, to.0, to.1);
+    let samples = (length / LEG_SAMPLE_SPACING_M).ceil().max(1.0) as usize;
+    let mut lowest: Option<PathClearance> = None;
+    for n in 0..=samples {
+        let fraction = n as f64 / samples as f64;
+        let along = fraction * length;
+        let (lat, lon) = destination(from_lat, from_lon, bearing, along);
+        let Some(ground) = terrain.elevation_amsl_m(lat, lon) else {
+            continue;
+        };
+        let clearance = from_alt + fraction * (to.2 - from_alt) - ground;
+        if lowest.is_none_or(|lowest| clearance < lowest.min_clearance_m) {
+            lowest = Some(PathClearance {
+                min_clearance_m: clearance,
+                latitude_deg: lat,
+                longitude_deg: lon,
+                along_m: along,
+            });
+        }
+    }
+    lowest
+}
+
+/// Lowest AMSL end altitude for which the straight leg from `from` keeps
+/// `agl` above every terrain sample along it.
+fn leg_floor(from: (f64, f64, f64), to: (f64, f64), agl: f64, terrain: &impl TerrainSource) -> f64 {
+    let (from_lat, from_lon, from_alt) = from;
+    let length = distance_m(from_lat, from_lon, to.0, to.1);
+    let bearing = bearing_deg(from_lat, from_lon, to.0, to.1);
+    let samples = (length / LEG_SAMPLE_SPACING_M).floor() as usize;
+    let mut floor = f64::NEG_INFINITY;
+    for n in 1..=samples {
+        let along = n as f64 * LEG_SAMPLE_SPACING_M;
+        if along >= length {
+            break;
+        }
+        let (lat, lon) = destination(from_lat, from_lon, bearing, along);
+        let Some(ground) = terrain.elevation_amsl_m(lat, lon) else {
+            continue;
+        };
+        // from_alt + fraction * (end - from_alt) >= ground + agl
+        let fraction = along / length;
+        floor = floor.max(from_alt + (ground + agl - from_alt) / fraction);
+    }
+    floor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::global_item;
+    use crate::mission::types::{HomePosition, MissionType};
+
+    fn waypoints(frame: MissionFrame, points: &[(f64, f64)]) -> MissionPlan {
+        MissionPlan::for_test(
+            MissionType::Mission,
+            Some(HomePosition::amsl(47.0, 8.0, 400.0)),
+            points
+                .iter()
+                .map(|&(lat, lon)| {
+                    let mut item = global_item(16, lat, lon, 50.0);
+                    item.frame = frame;
+                    item
+                })
+                .collect(),
+        )
+    }
+
+    // Ground rises 1 m per 0.0001 degree of latitude north of 47.0.
+    fn slope(lat: f64, _lon: f64) -> Option<f64> {
+        Some(400.0 + (lat - 47.0) * 10_000.0)
+    }
+
+    #[test]
+    fn path_clearance_finds_the_ridge_between_the_ends() {
+        // 600 m ridge 500 m north of the start, 400 m elsewhere
+        let ridge = |lat: f64, _lon: f64| {
+            let north_m = (lat - 47.0) * 111_195.0;
+            Some(if (north_m - 500.0).abs() < 40.0 {
+                600.0
+            } else {
+                400.0
+            })
+        };
+        let to = destination(47.0, 8.0, 0.0, 1000.0);
+        let low = path_clearance((47.0, 8.0, 450.0), (to.0, to.1, 550.0), &ridge).unwrap();
+        assert!(low.min_clearance_m < -90.0, "{low:?}");
+        assert!((low.along_m - 500.0).abs() < 40.0, "{low:?}");
+
+        let high = path_clearance((47.0, 8.0, 650.0), (to.0, to.1, 650.0), &ridge).unwrap();
+        assert!((high.min_clearance_m - 50.0).abs() < 1e-6, "{high:?}");
+        assert_eq!(
+            path_clearance((47.0, 8.0, 450.0), (47.0, 8.0, 450.0), &|_, _| None),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_each_frame_at_the_target_height() {
+        let points = [(47.0, 8.0), (47.001, 8.0)];
+        let relative = retarget_agl(
+            &waypoints(MissionFrame::GlobalRelativeAltInt, &points),
+            30.0,
+            &slope,
+        );
+        let z: Vec<f32> = relative.plan.items.iter().map(|i| i.z).collect();
+        assert!(
+            (z[0] - 30.0).abs() < 0.01 && (z[1] - 40.0).abs() < 0.01,
+            "{z:?}"
+        );
+
+        let amsl = retarget_agl(&waypoints(MissionFrame::GlobalInt, &points), 30.0, &slope);
+        assert!((amsl.plan.items[1].z - 440.0).abs() < 0.01);
+
+        let terrain = retarget_agl(
+            &waypoints(MissionFrame::GlobalTerrainAltInt, &points),
+            30.0,
+            &slope,
+        );
+        assert!(terrain.plan.items.iter().all(|i| i.z == 30.0));
+        assert_eq!(terrain.report.retargeted, vec![0, 1]);
+        assert!(terrain.report.clamped.is_empty());
+    }
+
+    #[test]
+    fn raises_the_end_of_a_leg_over_a_ridge() {
+        // 40 m ridge halfway along a ~220 m leg between two flat ends
+        let ridge = |lat: f64, _lon: f64| {
+            let mid = 47.001;
+            Some(if (lat - mid).abs() < 0.0003 {
+                440.0
+            } else {
+                400.0
+            })
+        };
+        let points = [(47.0, 8.0), (47.002, 8.0)];
+        let result = retarget_agl(&waypoints(MissionFrame::GlobalInt, &points), 30.0, &ridge);
+        assert_eq!(result.report.clamped.len(), 1);
+        let clamped = result.report.clamped[0];
+        assert_eq!(clamped.seq, 1);
+        assert!((clamped.computed_m - 430.0).abs() < 0.01);
+        assert!(clamped.applied_m > 470.0, "{clamped:?}");
+        assert_eq!(result.plan.items[1].z, clamped.applied_m);
+    }
+
+    #[test]
+    fn reports_points_that_cannot_be_computed() {
+        let patchy = |lat: f64, _lon: f64| (lat < 47.0005).then_some(400.0);
+        let points = [(47.0, 8.0), (47.001, 8.0)];
+        let result = retarget_agl(&waypoints(MissionFrame::GlobalInt, &points), 30.0, &patchy);
+        assert_eq!(result.report.retargeted, vec![0]);
+        assert_eq!(
+            result.report.unreachable,
+            vec![UnreachablePoint {
+                seq: 1,
+                reason: UnreachableReason::NoTerrainData
+            }]
+        );
+        assert_eq!(result.plan.items[1].z, 50.0);
+
+        let mut homeless = waypoints(MissionFrame::GlobalRelativeAltInt, &points);
+        homeless.home = None;
+        let flat = FlatTerrain {
+            elevation_amsl_m: 400.0,
+        };
+        let result = retarget_agl(&homeless, 30.0, &flat);
+        assert!(result.report.retargeted.is_empty());
+        assert_eq!(result.report.unreachable.len(), 2);
+        assert!(result
+            .report
+            .unreachable
+            .iter()
+            .all(|p| p.reason == UnreachableReason::NoHomeAltitude));
+    }
+}
@@ -209,12 +209,16 @@ mod tests {
                 x: 0,
                 y: 0,
                 z: 10.0,
+                label: None,
+                notes: None,
+                downloaded_as_float: false,
             });
         }
         MissionPlan {
             mission_type: MissionType::Mission,
             home: None,
             items,
+            metadata: Default::default(),
         }
     }
 
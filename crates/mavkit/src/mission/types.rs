@@ -1,4 +1,6 @@
+use super::precision::{deg_to_e7, e7_to_deg};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -44,16 +46,105 @@ pub struct MissionItem {
     pub x: i32,
     pub y: i32,
     pub z: f32,
+    /// Operator-facing name (e.g. "Bridge survey start"). Never sent over MAVLink
+    /// and ignored by plan comparison.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Free-form operator notes. Never sent over MAVLink and ignored by plan comparison.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// The position arrived in a float MISSION_ITEM and may be off by
+    /// decimetres (see `audit_float_download`). Cleared when the item is moved.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub downloaded_as_float: bool,
 }
 
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl MissionItem {
+    /// Position in degrees, decoded from the degE7 `x`/`y` fields.
+    pub fn latlon_deg(&self) -> (f64, f64) {
+        (e7_to_deg(self.x), e7_to_deg(self.y))
+    }
+
+    /// Set position from degrees, rounding to the nearest 1e-7 degree.
+    pub fn set_latlon_deg(&mut self, lat_deg: f64, lon_deg: f64) {
+        self.x = deg_to_e7(lat_deg);
+        self.y = deg_to_e7(lon_deg);
+        self.downloaded_as_float = false;
+    }
+
+    /// A NAV command at a global position the vehicle flies to. Items with
+    /// a zero position (e.g. "land here") are not counted.
+    pub fn is_nav_position(&self) -> bool {
+        self.command < 100 && self.frame.is_global_position() && (self.x != 0 || self.y != 0)
+    }
+}
+
+/// Vertical datum of an absolute altitude.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AltitudeDatum {
+    /// Above mean sea level (geoid). What HOME_POSITION, GLOBAL_POSITION_INT
+    /// and mission item 0 carry.
+    #[default]
+    Amsl,
+    /// Above the WGS84 ellipsoid, as reported by some GNSS receivers and
+    /// survey tools. Differs from AMSL by the local geoid separation.
+    Ellipsoid,
+}
+
+/// Home position. Latitude/longitude are WGS84; `altitude_m` is absolute in
+/// the given `altitude_datum`, never relative to anything.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct HomePosition {
     pub latitude_deg: f64,
     pub longitude_deg: f64,
     pub altitude_m: f32,
+    #[serde(default)]
+    pub altitude_datum: AltitudeDatum,
 }
 
 impl HomePosition {
+    pub const fn amsl(latitude_deg: f64, longitude_deg: f64, altitude_m: f32) -> Self {
+        Self {
+            latitude_deg,
+            longitude_deg,
+            altitude_m,
+            altitude_datum: AltitudeDatum::Amsl,
+        }
+    }
+
+    /// From the `HOME_POSITION` message fields (degE7, degE7, mm AMSL).
+    pub fn from_home_position_msg(latitude_e7: i32, longitude_e7: i32, altitude_mm: i32) -> Self {
+        Self::amsl(
+            e7_to_deg(latitude_e7),
+            e7_to_deg(longitude_e7),
+            (altitude_mm as f64 / 1000.0) as f32,
+        )
+    }
+
+    /// Altitude above mean sea level. An ellipsoidal home needs the geoid
+    /// separation (ellipsoid height minus AMSL height) at the home location;
+    /// without it the result is `None`.
+    pub fn altitude_amsl_m(&self, geoid_separation_m: Option<f32>) -> Option<f32> {
+        match self.altitude_datum {
+            AltitudeDatum::Amsl => Some(self.altitude_m),
+            AltitudeDatum::Ellipsoid => geoid_separation_m.map(|n| self.altitude_m - n),
+        }
+    }
+
+    /// Height of an AMSL altitude above this home, or `None` if the home
+    /// altitude cannot be expressed as AMSL.
+    pub fn relative_altitude_m(&self, altitude_amsl_m: f64) -> Option<f64> {
+        self.altitude_amsl_m(None)
+            .map(|home| altitude_amsl_m - home as f64)
+    }
+
+    /// Mission item 0. The autopilot reads its altitude as AMSL, so an
+    /// ellipsoidal home is sent unconverted; `validate_plan` flags that case.
     pub fn to_mission_item(&self, seq: u16) -> MissionItem {
         MissionItem {
             seq,
@@ -65,19 +156,23 @@ impl HomePosition {
             param2: 0.0,
             param3: 0.0,
             param4: 0.0,
-            x: (self.latitude_deg * 1e7) as i32,
-            y: (self.longitude_deg * 1e7) as i32,
+            x: deg_to_e7(self.latitude_deg),
+            y: deg_to_e7(self.longitude_deg),
             z: self.altitude_m,
+            label: None,
+            notes: None,
+            downloaded_as_float: false,
         }
     }
 
+    /// Home from a downloaded mission item 0 (AMSL).
+    pub fn from_wire_item(item: &MissionItem) -> Self {
+        Self::amsl(e7_to_deg(item.x), e7_to_deg(item.y), item.z)
+    }
+
     pub fn from_mission_item(item: &MissionItem) -> Option<Self> {
         if item.command == 16 && item.frame == MissionFrame::GlobalInt {
-            Some(HomePosition {
-                latitude_deg: item.x as f64 / 1e7,
-                longitude_deg: item.y as f64 / 1e7,
-                altitude_m: item.z,
-            })
+            Some(Self::from_wire_item(item))
         } else {
             None
         }
@@ -89,6 +184,10 @@ pub struct MissionPlan {
     pub mission_type: MissionType,
     pub home: Option<HomePosition>,
     pub items: Vec<MissionItem>,
+    /// Arbitrary plan-level annotations (author, site, project...). Preserved
+    /// through file import/export; not part of the wire transfer.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -105,3 +204,89 @@ pub struct MissionIssue {
     pub seq: Option<u16>,
     pub severity: IssueSeverity,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labelled_plan() -> MissionPlan {
+        let mut item = HomePosition::amsl(47.397742, 8.545594, 30.0).to_mission_item(0);
+        item.frame = MissionFrame::GlobalRelativeAltInt;
+        item.label = Some("Bridge survey start".to_string());
+        item.notes = Some("Check clearance under span".to_string());
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("site".to_string(), "North bridge".to_string());
+
+        MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items: vec![item],
+            metadata,
+        }
+    }
+
+    #[test]
+    fn relative_altitude_needs_amsl_home() {
+        let home = HomePosition::amsl(47.0, 8.0, 500.0);
+        assert_eq!(home.relative_altitude_m(530.0), Some(30.0));
+
+        let ellipsoid = HomePosition {
+            altitude_datum: AltitudeDatum::Ellipsoid,
+            ..HomePosition::amsl(47.0, 8.0, 548.0)
+        };
+        assert_eq!(ellipsoid.relative_altitude_m(530.0), None);
+        assert_eq!(ellipsoid.altitude_amsl_m(Some(48.0)), Some(500.0));
+    }
+
+    #[test]
+    fn home_position_msg_is_millimetres_amsl() {
+        let home = HomePosition::from_home_position_msg(473977420, 85455940, 488_250);
+        assert_eq!(home.altitude_m, 488.25);
+        assert_eq!(home.altitude_datum, AltitudeDatum::Amsl);
+        assert!((home.latitude_deg - 47.397742).abs() < 1e-9);
+    }
+
+    #[test]
+    fn annotations_survive_serde_roundtrip() {
+        let plan = labelled_plan();
+        let json = serde_json::to_string(&plan).unwrap();
+        let parsed: MissionPlan = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, plan);
+        assert_eq!(
+            parsed.items[0].label.as_deref(),
+            Some("Bridge survey start")
+        );
+        assert_eq!(parsed.metadata["site"], "North bridge");
+    }
+
+    #[test]
+    fn plan_without_annotations_deserializes() {
+        let json = r#"{
+            "mission_type": "mission",
+            "home": null,
+            "items": [{
+                "seq": 0, "command": 16, "frame": "global_relative_alt_int",
+                "current": true, "autocontinue": true,
+                "param1": 0.0, "param2": 0.0, "param3": 0.0, "param4": 0.0,
+                "x": 473977420, "y": 85455940, "z": 30.0
+            }]
+        }"#;
+        let plan: MissionPlan = serde_json::from_str(json).unwrap();
+        assert!(plan.metadata.is_empty());
+        assert!(plan.items[0].label.is_none());
+        assert!(plan.items[0].notes.is_none());
+    }
+
+    #[test]
+    fn empty_annotations_are_not_serialized() {
+        let mut plan = labelled_plan();
+        plan.metadata.clear();
+        plan.items[0].label = None;
+        plan.items[0].notes = None;
+        let json = serde_json::to_string(&plan).unwrap();
+        assert!(!json.contains("metadata"));
+        assert!(!json.contains("label"));
+        assert!(!json.contains("notes"));
+    }
+}
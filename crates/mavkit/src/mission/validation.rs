@@ -1,4 +1,9 @@
-use super::types::{IssueSeverity, MissionIssue, MissionPlan};
+use serde::{Deserialize, Serialize};
+
+use super::precision::audit_float_download;
+use super::types::{
+    AltitudeDatum, HomePosition, IssueSeverity, MissionIssue, MissionItem, MissionPlan,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct CompareTolerance {
@@ -38,6 +43,15 @@ pub fn validate_plan(plan: &MissionPlan) -> Vec<MissionIssue> {
                 severity: IssueSeverity::Error,
             });
         }
+        if home.altitude_datum == AltitudeDatum::Ellipsoid {
+            issues.push(MissionIssue {
+                code: "home.altitude_not_amsl".to_string(),
+                message: "Home altitude is above the ellipsoid; the autopilot expects AMSL"
+                    .to_string(),
+                seq: None,
+                severity: IssueSeverity::Warning,
+            });
+        }
     }
 
     if plan.items.len() > 4096 {
@@ -100,6 +114,7 @@ pub fn validate_plan(plan: &MissionPlan) -> Vec<MissionIssue> {
         }
     }
 
+    issues.extend(audit_float_download(plan));
     issues
 }
 
@@ -119,46 +134,147 @@ pub fn normalize_for_compare(plan: &MissionPlan) -> MissionPlan {
     normalized
 }
 
-pub fn plans_equivalent(
-    lhs: &MissionPlan,
-    rhs: &MissionPlan,
-    tolerance: CompareTolerance,
-) -> bool {
-    if lhs.mission_type != rhs.mission_type {
-        return false;
+/// One field that differs between the expected and the actual plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldMismatch {
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Mismatched fields of the item at `seq`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemDiff {
+    pub seq: u16,
+    pub fields: Vec<FieldMismatch>,
+}
+
+/// Everything that differs between two plans, as reported by `diff_plans`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanDiff {
+    /// Plan-level differences: mission type and home position.
+    pub plan: Vec<FieldMismatch>,
+    pub items: Vec<ItemDiff>,
+    /// Expected item count when the item counts differ; items past the
+    /// shorter plan are not compared field by field.
+    pub expected_count: Option<usize>,
+    pub actual_count: Option<usize>,
+}
+
+impl PlanDiff {
+    pub fn is_empty(&self) -> bool {
+        self.plan.is_empty() && self.items.is_empty() && self.expected_count.is_none()
+    }
+}
+
+struct FieldCollector(Vec<FieldMismatch>);
+
+impl FieldCollector {
+    fn check<T: PartialEq + std::fmt::Debug>(&mut self, field: &str, expected: T, actual: T) {
+        if expected != actual {
+            self.push(field, expected, actual);
+        }
+    }
+
+    fn check_f32(&mut self, field: &str, expected: f32, actual: f32, epsilon: f32) {
+        if !float_eq(expected, actual, epsilon) {
+            self.push(field, expected, actual);
+        }
+    }
+
+    fn push<T: std::fmt::Debug>(&mut self, field: &str, expected: T, actual: T) {
+        self.0.push(FieldMismatch {
+            field: field.to_string(),
+            expected: format!("{expected:?}"),
+            actual: format!("{actual:?}"),
+        });
     }
+}
 
-    match (&lhs.home, &rhs.home) {
+fn diff_home(
+    fields: &mut FieldCollector,
+    expected: Option<&HomePosition>,
+    actual: Option<&HomePosition>,
+    tolerance: CompareTolerance,
+) {
+    match (expected, actual) {
         (Some(lh), Some(rh)) => {
-            if lh.latitude_deg != rh.latitude_deg
-                || lh.longitude_deg != rh.longitude_deg
-                || !float_eq(lh.altitude_m, rh.altitude_m, tolerance.altitude_epsilon_m)
-            {
-                return false;
-            }
+            fields.check("home.latitude_deg", lh.latitude_deg, rh.latitude_deg);
+            fields.check("home.longitude_deg", lh.longitude_deg, rh.longitude_deg);
+            fields.check("home.altitude_datum", lh.altitude_datum, rh.altitude_datum);
+            fields.check_f32(
+                "home.altitude_m",
+                lh.altitude_m,
+                rh.altitude_m,
+                tolerance.altitude_epsilon_m,
+            );
         }
         (None, None) => {}
-        _ => return false,
+        _ => fields.check("home", expected.is_some(), actual.is_some()),
     }
+}
 
-    if lhs.items.len() != rhs.items.len() {
-        return false;
+fn diff_item(
+    left: &MissionItem,
+    right: &MissionItem,
+    tolerance: CompareTolerance,
+) -> Vec<FieldMismatch> {
+    let mut fields = FieldCollector(Vec::new());
+    fields.check("seq", left.seq, right.seq);
+    fields.check("command", left.command, right.command);
+    fields.check("frame", left.frame, right.frame);
+    fields.check("current", left.current, right.current);
+    fields.check("autocontinue", left.autocontinue, right.autocontinue);
+    fields.check_f32("param1", left.param1, right.param1, tolerance.param_epsilon);
+    fields.check_f32("param2", left.param2, right.param2, tolerance.param_epsilon);
+    fields.check_f32("param3", left.param3, right.param3, tolerance.param_epsilon);
+    fields.check_f32("param4", left.param4, right.param4, tolerance.param_epsilon);
+    fields.check("x", left.x, right.x);
+    fields.check("y", left.y, right.y);
+    fields.check_f32("z", left.z, right.z, tolerance.altitude_epsilon_m);
+    fields.0
+}
+
+/// Field-by-field comparison of `expected` against `actual`. Annotations
+/// (labels, notes, metadata) are ignored, as in `plans_equivalent`.
+pub fn diff_plans(
+    expected: &MissionPlan,
+    actual: &MissionPlan,
+    tolerance: CompareTolerance,
+) -> PlanDiff {
+    let mut plan = FieldCollector(Vec::new());
+    plan.check("mission_type", expected.mission_type, actual.mission_type);
+    diff_home(
+        &mut plan,
+        expected.home.as_ref(),
+        actual.home.as_ref(),
+        tolerance,
+    );
+
+    let items = expected
+        .items
+        .iter()
+        .zip(&actual.items)
+        .filter_map(|(left, right)| {
+            let fields = diff_item(left, right, tolerance);
+            (!fields.is_empty()).then_some(ItemDiff {
+                seq: left.seq,
+                fields,
+            })
+        })
+        .collect();
+
+    let counts_differ = expected.items.len() != actual.items.len();
+    PlanDiff {
+        plan: plan.0,
+        items,
+        expected_count: counts_differ.then_some(expected.items.len()),
+        actual_count: counts_differ.then_some(actual.items.len()),
     }
+}
 
-    lhs.items.iter().zip(&rhs.items).all(|(left, right)| {
-        left.seq == right.seq
-            && left.command == right.command
-            && left.frame == right.frame
-            && left.current == right.current
-            && left.autocontinue == right.autocontinue
-            && float_eq(left.param1, right.param1, tolerance.param_epsilon)
-            && float_eq(left.param2, right.param2, tolerance.param_epsilon)
-            && float_eq(left.param3, right.param3, tolerance.param_epsilon)
-            && float_eq(left.param4, right.param4, tolerance.param_epsilon)
-            && left.x == right.x
-            && left.y == right.y
-            && float_eq(left.z, right.z, tolerance.altitude_epsilon_m)
-    })
+pub fn plans_equivalent(lhs: &MissionPlan, rhs: &MissionPlan, tolerance: CompareTolerance) -> bool {
+    diff_plans(lhs, rhs, tolerance).is_empty()
 }
 
 fn float_eq(a: f32, b: f32, epsilon: f32) -> bool {
@@ -188,6 +304,9 @@ mod tests {
             x: 473977420,
             y: 85455970,
             z: 42.123456,
+            label: None,
+            notes: None,
+            downloaded_as_float: false,
         }
     }
 
@@ -205,6 +324,7 @@ mod tests {
                 },
                 second,
             ],
+            metadata: Default::default(),
         };
 
         let issues = validate_plan(&plan);
@@ -221,6 +341,7 @@ mod tests {
             mission_type: MissionType::Mission,
             home: None,
             items: vec![item],
+            metadata: Default::default(),
         };
 
         let issues = validate_plan(&plan);
@@ -236,12 +357,9 @@ mod tests {
     fn validates_home_latitude_range() {
         let plan = MissionPlan {
             mission_type: MissionType::Mission,
-            home: Some(HomePosition {
-                latitude_deg: 95.0,
-                longitude_deg: 8.0,
-                altitude_m: 0.0,
-            }),
+            home: Some(HomePosition::amsl(95.0, 8.0, 0.0)),
             items: Vec::new(),
+            metadata: Default::default(),
         };
 
         let issues = validate_plan(&plan);
@@ -250,6 +368,24 @@ mod tests {
             .any(|issue| issue.code == "home.latitude_out_of_range"));
     }
 
+    #[test]
+    fn warns_on_ellipsoidal_home_altitude() {
+        let plan = MissionPlan {
+            mission_type: MissionType::Mission,
+            home: Some(HomePosition {
+                altitude_datum: AltitudeDatum::Ellipsoid,
+                ..HomePosition::amsl(47.0, 8.0, 540.0)
+            }),
+            items: Vec::new(),
+            metadata: Default::default(),
+        };
+        let issues = validate_plan(&plan);
+        assert!(issues
+            .iter()
+            .any(|issue| issue.code == "home.altitude_not_amsl"
+                && issue.severity == IssueSeverity::Warning));
+    }
+
     #[test]
     fn normalize_and_equivalent_tolerates_small_float_drift() {
         let mut base = sample_item(0);
@@ -263,11 +399,13 @@ mod tests {
             mission_type: MissionType::Mission,
             home: None,
             items: vec![base],
+            metadata: Default::default(),
         };
         let rhs = MissionPlan {
             mission_type: MissionType::Mission,
             home: None,
             items: vec![changed],
+            metadata: Default::default(),
         };
 
         assert!(plans_equivalent(&lhs, &rhs, CompareTolerance::default()));
@@ -278,26 +416,20 @@ mod tests {
 
     #[test]
     fn plans_equivalent_compares_home() {
-        let home_a = Some(HomePosition {
-            latitude_deg: 47.397742,
-            longitude_deg: 8.545594,
-            altitude_m: 0.0,
-        });
-        let home_b = Some(HomePosition {
-            latitude_deg: 47.397742,
-            longitude_deg: 8.545594,
-            altitude_m: 0.005,
-        });
+        let home_a = Some(HomePosition::amsl(47.397742, 8.545594, 0.0));
+        let home_b = Some(HomePosition::amsl(47.397742, 8.545594, 0.005));
 
         let plan_a = MissionPlan {
             mission_type: MissionType::Mission,
             home: home_a,
             items: Vec::new(),
+            metadata: Default::default(),
         };
         let plan_b = MissionPlan {
             mission_type: MissionType::Mission,
             home: home_b,
             items: Vec::new(),
+            metadata: Default::default(),
         };
 
         assert!(plans_equivalent(
@@ -306,4 +438,91 @@ mod tests {
             CompareTolerance::default()
         ));
     }
+
+    #[test]
+    fn diff_reports_mismatched_fields_per_item() {
+        let mut first = sample_item(0);
+        first.param4 = 0.0;
+        let mut second = sample_item(1);
+        second.param4 = 0.0;
+
+        let expected = MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items: vec![first.clone(), second.clone()],
+            metadata: Default::default(),
+        };
+        let mut actual = expected.clone();
+        actual.items[1].command = 17;
+        actual.items[1].z += 1.0;
+
+        let diff = diff_plans(&expected, &actual, CompareTolerance::default());
+        assert!(diff.plan.is_empty());
+        assert_eq!(diff.items.len(), 1);
+        assert_eq!(diff.items[0].seq, 1);
+        let fields: Vec<&str> = diff.items[0]
+            .fields
+            .iter()
+            .map(|f| f.field.as_str())
+            .collect();
+        assert_eq!(fields, vec!["command", "z"]);
+        assert_eq!(diff.items[0].fields[0].expected, "16");
+        assert_eq!(diff.items[0].fields[0].actual, "17");
+        assert!(!plans_equivalent(
+            &expected,
+            &actual,
+            CompareTolerance::default()
+        ));
+    }
+
+    #[test]
+    fn diff_reports_count_and_home_mismatch() {
+        let mut item = sample_item(0);
+        item.param4 = 0.0;
+        let expected = MissionPlan {
+            mission_type: MissionType::Mission,
+            home: Some(HomePosition::amsl(47.397742, 8.545594, 0.0)),
+            items: vec![item],
+            metadata: Default::default(),
+        };
+        let actual = MissionPlan {
+            home: None,
+            items: Vec::new(),
+            ..expected.clone()
+        };
+
+        let diff = diff_plans(&expected, &actual, CompareTolerance::default());
+        assert_eq!(diff.plan[0].field, "home");
+        assert_eq!(diff.expected_count, Some(1));
+        assert_eq!(diff.actual_count, Some(0));
+        assert!(!diff.is_empty());
+        assert!(diff_plans(&expected, &expected, CompareTolerance::default()).is_empty());
+    }
+
+    #[test]
+    fn plans_equivalent_ignores_annotations() {
+        let mut base = sample_item(0);
+        base.param4 = 0.0;
+
+        let mut labelled = base.clone();
+        labelled.label = Some("Tower base".to_string());
+        labelled.notes = Some("Approach from the east".to_string());
+
+        let lhs = MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items: vec![base],
+            metadata: Default::default(),
+        };
+        let mut rhs = MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items: vec![labelled],
+            metadata: Default::default(),
+        };
+        rhs.metadata
+            .insert("author".to_string(), "field crew".to_string());
+
+        assert!(plans_equivalent(&lhs, &rhs, CompareTolerance::default()));
+    }
 }
@@ -0,0 +1,271 @@
+use super::builder::{
+    command_item, global_item, resequence, MAV_CMD_DO_VTOL_TRANSITION, MAV_CMD_NAV_VTOL_LAND,
+    MAV_CMD_NAV_VTOL_TAKEOFF, MAV_VTOL_STATE_FW, MAV_VTOL_STATE_MC,
+};
+use super::geo::distance_m;
+use super::types::{IssueSeverity, MissionIssue, MissionItem, MissionPlan, MissionType};
+use serde::{Deserialize, Serialize};
+
+/// Airframe figures used to size quadplane transitions.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VtolProfile {
+    /// Airspeed at which the forward transition is complete (e.g. ARSPD_FBW_MIN).
+    pub transition_airspeed_mps: f32,
+    /// Average forward acceleration during the forward transition.
+    pub transition_accel_mps2: f32,
+    /// Deceleration used for the back transition (e.g. Q_TRANS_DECEL).
+    pub back_transition_decel_mps2: f32,
+    /// Largest climb tolerated on the leg leaving a forward transition.
+    pub max_climb_near_transition_m: f32,
+}
+
+impl Default for VtolProfile {
+    fn default() -> Self {
+        Self {
+            transition_airspeed_mps: 16.0,
+            transition_accel_mps2: 2.0,
+            back_transition_decel_mps2: 2.0,
+            max_climb_near_transition_m: 10.0,
+        }
+    }
+}
+
+impl VtolProfile {
+    /// Ground distance needed to accelerate to transition airspeed (still air).
+    pub fn forward_transition_distance_m(&self) -> f64 {
+        let v = self.transition_airspeed_mps as f64;
+        v * v / (2.0 * (self.transition_accel_mps2 as f64).max(0.1))
+    }
+
+    /// Ground distance needed to decelerate from transition airspeed to a hover.
+    pub fn back_transition_distance_m(&self) -> f64 {
+        let v = self.transition_airspeed_mps as f64;
+        v * v / (2.0 * (self.back_transition_decel_mps2 as f64).max(0.1))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VtolWrapParams {
+    /// Launch point `(lat_deg, lon_deg)`.
+    pub launch: (f64, f64),
+    pub takeoff_alt_m: f32,
+    /// Landing point; defaults to the launch point.
+    pub landing: Option<(f64, f64)>,
+}
+
+/// Wrap a fixed-wing block (e.g. a survey) with a VTOL takeoff, forward
+/// transition and VTOL landing.
+///
+/// No back transition is inserted: NAV_VTOL_LAND flies the approach in
+/// fixed-wing mode and transitions on its own once it is close enough.
+pub fn wrap_vtol_block(block: &MissionPlan, params: &VtolWrapParams) -> MissionPlan {
+    let (launch_lat, launch_lon) = params.launch;
+    let (land_lat, land_lon) = params.landing.unwrap_or(params.launch);
+
+    let mut items = Vec::with_capacity(block.items.len() + 3);
+    items.push(global_item(
+        MAV_CMD_NAV_VTOL_TAKEOFF,
+        launch_lat,
+        launch_lon,
+        params.takeoff_alt_m,
+    ));
+    items.push(command_item(
+        MAV_CMD_DO_VTOL_TRANSITION,
+        [MAV_VTOL_STATE_FW, 0.0, 0.0, 0.0],
+    ));
+    items.extend(block.items.iter().cloned());
+    items.push(global_item(MAV_CMD_NAV_VTOL_LAND, land_lat, land_lon, 0.0));
+    resequence(&mut items);
+
+    MissionPlan {
+        mission_type: MissionType::Mission,
+        home: block.home.clone(),
+        items,
+        metadata: block.metadata.clone(),
+    }
+}
+
+/// Check transition geometry of a quadplane mission against `profile`.
+///
+/// Reports forward transitions whose next leg is shorter than the distance
+/// needed to reach transition airspeed, explicit back transitions that leave
+/// a long leg to the VTOL landing point to be flown in hover, and large climbs
+/// right after a forward transition.
+pub fn validate_vtol_transitions(plan: &MissionPlan, profile: &VtolProfile) -> Vec<MissionIssue> {
+    let mut issues = Vec::new();
+    let forward_needed = profile.forward_transition_distance_m();
+    let back_needed = profile.back_transition_distance_m();
+
+    let mut last_position: Option<&MissionItem> = None;
+    // Position at which a pending transition starts
+    let mut pending_forward: Option<(u16, &MissionItem)> = None;
+    let mut pending_back: Option<(u16, &MissionItem)> = None;
+
+    for item in &plan.items {
+        if item.command == MAV_CMD_DO_VTOL_TRANSITION {
+            if let Some(from) = last_position {
+                if item.param1 == MAV_VTOL_STATE_FW {
+                    pending_forward = Some((item.seq, from));
+                } else if item.param1 == MAV_VTOL_STATE_MC {
+                    pending_back = Some((item.seq, from));
+                }
+            }
+            continue;
+        }
+
+        if !item.is_nav_position() {
+            continue;
+        }
+
+        if let Some((seq, from)) = pending_forward.take() {
+            let leg = leg_distance_m(from, item);
+            if leg < forward_needed {
+                issues.push(MissionIssue {
+                    code: "vtol.transition_distance_short".to_string(),
+                    message: format!(
+                        "Leg after forward transition is {leg:.0} m; {forward_needed:.0} m needed to reach {:.1} m/s",
+                        profile.transition_airspeed_mps
+                    ),
+                    seq: Some(seq),
+                    severity: IssueSeverity::Warning,
+                });
+            }
+            let climb = item.z - from.z;
+            if climb > profile.max_climb_near_transition_m && leg < 2.0 * forward_needed {
+                issues.push(MissionIssue {
+                    code: "vtol.climb_near_transition".to_string(),
+                    message: format!(
+                        "Climb of {climb:.0} m within {leg:.0} m of a forward transition"
+                    ),
+                    seq: Some(item.seq),
+                    severity: IssueSeverity::Warning,
+                });
+            }
+        }
+
+        if item.command == MAV_CMD_NAV_VTOL_LAND {
+            if let Some((seq, from)) = pending_back.take() {
+                let leg = leg_distance_m(from, item);
+                if leg > 2.0 * back_needed {
+                    issues.push(MissionIssue {
+                        code: "vtol.long_hover_leg".to_string(),
+                        message: format!(
+                            "Back transition starts {leg:.0} m from the landing point; the rest is flown in hover"
+                        ),
+                        seq: Some(seq),
+                        severity: IssueSeverity::Warning,
+                    });
+                }
+            }
+        }
+
+        last_position = Some(item);
+    }
+
+    issues
+}
+
+fn leg_distance_m(a: &MissionItem, b: &MissionItem) -> f64 {
+    distance_m(
+        a.x as f64 / 1e7,
+        a.y as f64 / 1e7,
+        b.x as f64 / 1e7,
+        b.y as f64 / 1e7,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::MAV_CMD_NAV_WAYPOINT;
+    use crate::mission::geo::destination;
+
+    const LAUNCH: (f64, f64) = (47.0, 8.0);
+
+    fn block_at(distance_m: f64, alt_m: f32) -> MissionPlan {
+        let (lat, lon) = destination(LAUNCH.0, LAUNCH.1, 90.0, distance_m);
+        let (lat2, lon2) = destination(lat, lon, 90.0, 500.0);
+        MissionPlan::for_test(
+            MissionType::Mission,
+            None,
+            vec![
+                global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, alt_m),
+                global_item(MAV_CMD_NAV_WAYPOINT, lat2, lon2, alt_m),
+            ],
+        )
+    }
+
+    fn wrap(block: &MissionPlan) -> MissionPlan {
+        wrap_vtol_block(
+            block,
+            &VtolWrapParams {
+                launch: LAUNCH,
+                takeoff_alt_m: 30.0,
+                landing: None,
+            },
+        )
+    }
+
+    #[test]
+    fn transition_distances_from_profile() {
+        let profile = VtolProfile::default();
+        assert!((profile.forward_transition_distance_m() - 64.0).abs() < 1e-6);
+        assert!((profile.back_transition_distance_m() - 64.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn wrap_inserts_takeoff_transition_and_land() {
+        let plan = wrap(&block_at(300.0, 30.0));
+        let commands: Vec<u16> = plan.items.iter().map(|i| i.command).collect();
+        assert_eq!(
+            commands,
+            vec![
+                MAV_CMD_NAV_VTOL_TAKEOFF,
+                MAV_CMD_DO_VTOL_TRANSITION,
+                MAV_CMD_NAV_WAYPOINT,
+                MAV_CMD_NAV_WAYPOINT,
+                MAV_CMD_NAV_VTOL_LAND,
+            ]
+        );
+        assert_eq!(plan.items[1].param1, MAV_VTOL_STATE_FW);
+        assert!(crate::mission::validate_plan(&plan).is_empty());
+        assert!(validate_vtol_transitions(&plan, &VtolProfile::default()).is_empty());
+    }
+
+    #[test]
+    fn flags_short_forward_transition() {
+        let plan = wrap(&block_at(30.0, 30.0));
+        let issues = validate_vtol_transitions(&plan, &VtolProfile::default());
+        assert!(issues
+            .iter()
+            .any(|i| i.code == "vtol.transition_distance_short" && i.seq == Some(1)));
+    }
+
+    #[test]
+    fn flags_climb_near_transition() {
+        let plan = wrap(&block_at(100.0, 80.0));
+        let issues = validate_vtol_transitions(&plan, &VtolProfile::default());
+        assert!(issues
+            .iter()
+            .any(|i| i.code == "vtol.climb_near_transition"));
+    }
+
+    #[test]
+    fn flags_long_hover_leg() {
+        // Explicit back transition at the end of a block 800 m out
+        let mut plan = wrap(&block_at(300.0, 30.0));
+        let land = plan.items.len() - 1;
+        plan.items.insert(
+            land,
+            command_item(
+                MAV_CMD_DO_VTOL_TRANSITION,
+                [MAV_VTOL_STATE_MC, 0.0, 0.0, 0.0],
+            ),
+        );
+        resequence(&mut plan.items);
+        let issues = validate_vtol_transitions(&plan, &VtolProfile::default());
+        assert!(issues
+            .iter()
+            .any(|i| i.code == "vtol.long_hover_leg" && i.seq == Some(land as u16)));
+    }
+}
@@ -25,6 +25,9 @@ pub fn items_for_wire_upload(plan: &MissionPlan) -> Vec<MissionItem> {
             x: 0,
             y: 0,
             z: 0.0,
+            label: None,
+            notes: None,
+            downloaded_as_float: false,
         },
     };
 
@@ -33,7 +36,7 @@ pub fn items_for_wire_upload(plan: &MissionPlan) -> Vec<MissionItem> {
     for (i, item) in plan.items.iter().enumerate() {
         wire.push(MissionItem {
             seq: (i + 1) as u16,
-            ..*item
+            ..item.clone()
         });
     }
     wire
@@ -53,15 +56,11 @@ pub fn plan_from_wire_download(
             mission_type,
             home: None,
             items: wire_items,
+            metadata: Default::default(),
         };
     }
 
-    let first = &wire_items[0];
-    let home = Some(HomePosition {
-        latitude_deg: first.x as f64 / 1e7,
-        longitude_deg: first.y as f64 / 1e7,
-        altitude_m: first.z,
-    });
+    let home = Some(HomePosition::from_wire_item(&wire_items[0]));
 
     let items: Vec<MissionItem> = wire_items[1..]
         .iter()
@@ -69,7 +68,7 @@ pub fn plan_from_wire_download(
         .map(|(i, item)| MissionItem {
             seq: i as u16,
             current: i == 0,
-            ..*item
+            ..item.clone()
         })
         .collect();
 
@@ -77,6 +76,7 @@ pub fn plan_from_wire_download(
         mission_type,
         home,
         items,
+        metadata: Default::default(),
     }
 }
 
@@ -99,6 +99,9 @@ mod tests {
             x: 473977420,
             y: 85455970,
             z: 42.123456,
+            label: None,
+            notes: None,
+            downloaded_as_float: false,
         }
     }
 
@@ -106,11 +109,7 @@ mod tests {
     fn wire_upload_prepends_home_for_mission_type() {
         let plan = MissionPlan {
             mission_type: MissionType::Mission,
-            home: Some(HomePosition {
-                latitude_deg: 47.397742,
-                longitude_deg: 8.545594,
-                altitude_m: 100.0,
-            }),
+            home: Some(HomePosition::amsl(47.397742, 8.545594, 100.0)),
             items: vec![
                 MissionItem {
                     param4: 0.0,
@@ -121,6 +120,7 @@ mod tests {
                     ..sample_item(1)
                 },
             ],
+            metadata: Default::default(),
         };
 
         let wire = items_for_wire_upload(&plan);
@@ -140,6 +140,7 @@ mod tests {
                 param4: 0.0,
                 ..sample_item(0)
             }],
+            metadata: Default::default(),
         };
 
         let wire = items_for_wire_upload(&plan);
@@ -157,6 +158,7 @@ mod tests {
                 param4: 0.0,
                 ..sample_item(0)
             }],
+            metadata: Default::default(),
         };
 
         let wire = items_for_wire_upload(&plan);
@@ -179,6 +181,9 @@ mod tests {
                 x: 473977420,
                 y: 85455970,
                 z: 100.0,
+                label: None,
+                notes: None,
+                downloaded_as_float: false,
             },
             MissionItem {
                 seq: 1,
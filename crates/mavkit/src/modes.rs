@@ -1,4 +1,12 @@
-use crate::state::{AutopilotType, FlightMode, VehicleType};
+use crate::state::{
+    AutopilotType, FlightMode, GpsFixType, MissionRunState, MissionState, Telemetry, VehicleState,
+    VehicleType,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum VehicleClass {
@@ -34,6 +42,7 @@ const COPTER_MODES: &[(u32, &str)] = &[
     (9, "LAND"),
     (11, "DRIFT"),
     (13, "SPORT"),
+    (14, "FLIP"),
     (15, "AUTOTUNE"),
     (16, "POSHOLD"),
     (17, "BRAKE"),
@@ -76,49 +85,228 @@ const ROVER_MODES: &[(u32, &str)] = &[
     (15, "GUIDED"),
 ];
 
-fn mode_table(autopilot: AutopilotType, vehicle_type: VehicleType) -> &'static [(u32, &'static str)] {
-    if autopilot != AutopilotType::ArduPilotMega {
-        return &[];
+/// Mode tables for custom ArduPilot builds, merged over the built-in ones:
+/// an entry renames the mode with its `custom_mode`, or adds it when the
+/// number is new. Usually kept in a JSON file, see `load`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModeOverrides {
+    pub copter: Vec<FlightMode>,
+    pub plane: Vec<FlightMode>,
+    pub rover: Vec<FlightMode>,
+}
+
+impl ModeOverrides {
+    /// Read overrides from a JSON file such as
+    /// `{"copter": [{"custom_mode": 27, "name": "AUTO_RTL"}]}`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// The modes of the vehicle, ordered by number; empty for autopilots
+    /// other than ArduPilot.
+    pub fn available_modes(
+        &self,
+        autopilot: AutopilotType,
+        vehicle_type: VehicleType,
+    ) -> Vec<FlightMode> {
+        if autopilot != AutopilotType::ArduPilotMega {
+            return Vec::new();
+        }
+        let (builtin, overrides) = match vehicle_class(vehicle_type) {
+            VehicleClass::Copter | VehicleClass::Unknown => (COPTER_MODES, &self.copter),
+            VehicleClass::Plane => (PLANE_MODES, &self.plane),
+            VehicleClass::Rover => (ROVER_MODES, &self.rover),
+        };
+        let mut modes: Vec<FlightMode> = builtin
+            .iter()
+            .map(|&(num, name)| FlightMode {
+                custom_mode: num,
+                name: name.to_string(),
+            })
+            .collect();
+        for mode in overrides {
+            match modes
+                .iter_mut()
+                .find(|known| known.custom_mode == mode.custom_mode)
+            {
+                Some(known) => known.name = mode.name.clone(),
+                None => modes.push(mode.clone()),
+            }
+        }
+        modes.sort_by_key(|mode| mode.custom_mode);
+        modes
+    }
+
+    pub fn mode_name(
+        &self,
+        autopilot: AutopilotType,
+        vehicle_type: VehicleType,
+        custom_mode: u32,
+    ) -> String {
+        if autopilot != AutopilotType::ArduPilotMega {
+            return format!("MODE({custom_mode})");
+        }
+        self.available_modes(autopilot, vehicle_type)
+            .into_iter()
+            .find(|mode| mode.custom_mode == custom_mode)
+            .map(|mode| mode.name)
+            .unwrap_or_else(|| format!("UNKNOWN({custom_mode})"))
     }
-    match vehicle_class(vehicle_type) {
-        VehicleClass::Copter | VehicleClass::Unknown => COPTER_MODES,
-        VehicleClass::Plane => PLANE_MODES,
-        VehicleClass::Rover => ROVER_MODES,
+
+    /// The number of the mode called `name`, ignoring case.
+    pub fn mode_number(
+        &self,
+        autopilot: AutopilotType,
+        vehicle_type: VehicleType,
+        name: &str,
+    ) -> Option<u32> {
+        self.available_modes(autopilot, vehicle_type)
+            .into_iter()
+            .find(|mode| mode.name.eq_ignore_ascii_case(name))
+            .map(|mode| mode.custom_mode)
     }
 }
 
+/// No overrides: the mode semantics below go by the stock names, which
+/// renamed modes keep their numbers for.
+const BUILTIN: ModeOverrides = ModeOverrides {
+    copter: Vec::new(),
+    plane: Vec::new(),
+    rover: Vec::new(),
+};
+
 pub(crate) fn mode_name(autopilot: AutopilotType, vehicle_type: VehicleType, custom_mode: u32) -> String {
-    if autopilot != AutopilotType::ArduPilotMega {
-        return format!("MODE({custom_mode})");
+    BUILTIN.mode_name(autopilot, vehicle_type, custom_mode)
+}
+
+/// PX4 packs main mode into bits 16..24 and sub mode into bits 24..32.
+const PX4_MAIN_MODE_AUTO: u32 = 4;
+const PX4_AUTO_MISSION: u32 = 4;
+
+/// Whether `custom_mode` is the mode in which the autopilot flies its
+/// uploaded mission: AUTO on ArduPilot, AUTO.MISSION on PX4.
+pub(crate) fn is_mission_mode(autopilot: AutopilotType, vehicle_type: VehicleType, custom_mode: u32) -> bool {
+    match autopilot {
+        AutopilotType::ArduPilotMega => mode_name(autopilot, vehicle_type, custom_mode) == "AUTO",
+        AutopilotType::Px4 => {
+            (custom_mode >> 16) & 0xFF == PX4_MAIN_MODE_AUTO
+                && (custom_mode >> 24) & 0xFF == PX4_AUTO_MISSION
+        }
+        _ => false,
+    }
+}
+
+/// The `custom_mode` that flies the uploaded mission, if the autopilot has one.
+pub(crate) fn mission_mode(autopilot: AutopilotType, vehicle_type: VehicleType) -> Option<u32> {
+    match autopilot {
+        AutopilotType::ArduPilotMega => mode_number(autopilot, vehicle_type, "AUTO"),
+        AutopilotType::Px4 => Some(PX4_MAIN_MODE_AUTO << 16 | PX4_AUTO_MISSION << 24),
+        _ => None,
     }
-    let table = mode_table(autopilot, vehicle_type);
-    for &(num, name) in table {
-        if num == custom_mode {
-            return name.to_string();
+}
+
+const PX4_AUTO_RTL: u32 = 5;
+const PX4_AUTO_LAND: u32 = 6;
+
+/// The `custom_mode` that lands where the vehicle is: LAND on copters and
+/// QLAND on planes (VTOL only) with ArduPilot, AUTO.LAND on PX4.
+pub(crate) fn land_mode(autopilot: AutopilotType, vehicle_type: VehicleType) -> Option<u32> {
+    match (autopilot, vehicle_class(vehicle_type)) {
+        (AutopilotType::ArduPilotMega, VehicleClass::Plane) => {
+            mode_number(autopilot, vehicle_type, "QLAND")
         }
+        (AutopilotType::ArduPilotMega, _) => mode_number(autopilot, vehicle_type, "LAND"),
+        (AutopilotType::Px4, _) => Some(PX4_MAIN_MODE_AUTO << 16 | PX4_AUTO_LAND << 24),
+        _ => None,
     }
-    format!("UNKNOWN({custom_mode})")
 }
 
-pub(crate) fn mode_number(autopilot: AutopilotType, vehicle_type: VehicleType, name: &str) -> Option<u32> {
-    let table = mode_table(autopilot, vehicle_type);
-    let upper = name.to_uppercase();
-    for &(num, mode_name) in table {
-        if mode_name == upper {
-            return Some(num);
+/// Modes that return or land the vehicle, which may be selected even while
+/// another GCS holds control.
+pub(crate) fn is_recovery_mode(autopilot: AutopilotType, vehicle_type: VehicleType, custom_mode: u32) -> bool {
+    match autopilot {
+        AutopilotType::ArduPilotMega => matches!(
+            mode_name(autopilot, vehicle_type, custom_mode).as_str(),
+            "RTL" | "SMART_RTL" | "LAND" | "QRTL" | "QLAND"
+        ),
+        AutopilotType::Px4 => {
+            (custom_mode >> 16) & 0xFF == PX4_MAIN_MODE_AUTO
+                && matches!((custom_mode >> 24) & 0xFF, PX4_AUTO_RTL | PX4_AUTO_LAND)
         }
+        _ => false,
     }
-    None
 }
 
-pub(crate) fn available_modes(autopilot: AutopilotType, vehicle_type: VehicleType) -> Vec<FlightMode> {
-    mode_table(autopilot, vehicle_type)
-        .iter()
-        .map(|&(num, name)| FlightMode {
-            custom_mode: num,
-            name: name.to_string(),
+pub(crate) fn mode_number(autopilot: AutopilotType, vehicle_type: VehicleType, name: &str) -> Option<u32> {
+    BUILTIN.mode_number(autopilot, vehicle_type, name)
+}
+
+/// Why switching to a mode looks like a mistake in the vehicle's current
+/// state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModeHazard {
+    /// The mission mode with no mission stored on the vehicle.
+    NoMission,
+    /// GUIDED without a 3D GPS fix, so global-frame targets can't be flown.
+    NoGpsFix,
+    /// FLIP or ACRO while disarmed, which would arm straight into them.
+    Disarmed,
+}
+
+impl fmt::Display for ModeHazard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ModeHazard::NoMission => "no mission is uploaded",
+            ModeHazard::NoGpsFix => "no 3D GPS fix",
+            ModeHazard::Disarmed => "the vehicle is disarmed",
         })
-        .collect()
+    }
+}
+
+/// What `Vehicle::set_mode` does when the new mode has a `ModeHazard`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModeCheck {
+    Off,
+    /// Switch anyway and log a warning.
+    Warn,
+    /// Refuse with `VehicleError::ModeRefused`.
+    #[default]
+    Refuse,
+}
+
+/// The hazard of switching to `custom_mode`, if any.
+pub(crate) fn mode_hazard(
+    state: &VehicleState,
+    telemetry: &Telemetry,
+    mission: &MissionState,
+    custom_mode: u32,
+) -> Option<ModeHazard> {
+    let (autopilot, vehicle_type) = (state.autopilot, state.vehicle_type);
+    if is_mission_mode(autopilot, vehicle_type, custom_mode) {
+        // MISSION_CURRENT.total is UINT16_MAX when no mission is present
+        let no_mission =
+            mission.mission_state == MissionRunState::NoMission || mission.total_items == u16::MAX;
+        return no_mission.then_some(ModeHazard::NoMission);
+    }
+    if autopilot != AutopilotType::ArduPilotMega {
+        return None;
+    }
+    match mode_name(autopilot, vehicle_type, custom_mode).as_str() {
+        "GUIDED" => {
+            let no_fix = matches!(
+                telemetry.gps_fix_type,
+                None | Some(GpsFixType::NoFix | GpsFixType::Fix2d)
+            );
+            no_fix.then_some(ModeHazard::NoGpsFix)
+        }
+        "FLIP" | "ACRO" => (!state.armed).then_some(ModeHazard::Disarmed),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
@@ -159,7 +347,7 @@ mod tests {
 
     #[test]
     fn available_modes_copter_length() {
-        let modes = available_modes(AutopilotType::ArduPilotMega, VehicleType::Quadrotor);
+        let modes = BUILTIN.available_modes(AutopilotType::ArduPilotMega, VehicleType::Quadrotor);
         assert_eq!(modes.len(), COPTER_MODES.len());
     }
 
@@ -173,10 +361,49 @@ mod tests {
 
     #[test]
     fn non_ardupilot_available_modes_empty() {
-        let modes = available_modes(AutopilotType::Generic, VehicleType::Quadrotor);
+        let modes = BUILTIN.available_modes(AutopilotType::Generic, VehicleType::Quadrotor);
         assert!(modes.is_empty());
     }
 
+    #[test]
+    fn mission_mode_per_autopilot() {
+        assert!(is_mission_mode(AutopilotType::ArduPilotMega, VehicleType::Quadrotor, 3));
+        assert!(is_mission_mode(AutopilotType::ArduPilotMega, VehicleType::FixedWing, 10));
+        assert!(!is_mission_mode(AutopilotType::ArduPilotMega, VehicleType::Quadrotor, 4));
+        assert!(is_mission_mode(AutopilotType::Px4, VehicleType::Quadrotor, 0x0404_0000));
+        // AUTO.LOITER
+        assert!(!is_mission_mode(AutopilotType::Px4, VehicleType::Quadrotor, 0x0304_0000));
+
+        for (autopilot, vehicle_type) in [
+            (AutopilotType::ArduPilotMega, VehicleType::Quadrotor),
+            (AutopilotType::ArduPilotMega, VehicleType::FixedWing),
+            (AutopilotType::Px4, VehicleType::Quadrotor),
+        ] {
+            let mode = mission_mode(autopilot, vehicle_type).unwrap();
+            assert!(is_mission_mode(autopilot, vehicle_type, mode));
+        }
+        assert_eq!(mission_mode(AutopilotType::Generic, VehicleType::Quadrotor), None);
+    }
+
+    #[test]
+    fn land_mode_per_vehicle() {
+        let ardupilot = AutopilotType::ArduPilotMega;
+        assert_eq!(land_mode(ardupilot, VehicleType::Quadrotor), Some(9));
+        assert_eq!(land_mode(ardupilot, VehicleType::FixedWing), Some(20));
+        assert_eq!(land_mode(ardupilot, VehicleType::GroundRover), None);
+        let px4 = land_mode(AutopilotType::Px4, VehicleType::Quadrotor).unwrap();
+        assert!(is_recovery_mode(AutopilotType::Px4, VehicleType::Quadrotor, px4));
+    }
+
+    #[test]
+    fn recovery_modes() {
+        assert!(is_recovery_mode(AutopilotType::ArduPilotMega, VehicleType::Quadrotor, 6));
+        assert!(is_recovery_mode(AutopilotType::ArduPilotMega, VehicleType::Quadrotor, 9));
+        assert!(!is_recovery_mode(AutopilotType::ArduPilotMega, VehicleType::Quadrotor, 4));
+        assert!(is_recovery_mode(AutopilotType::Px4, VehicleType::Quadrotor, 0x0504_0000));
+        assert!(!is_recovery_mode(AutopilotType::Px4, VehicleType::Quadrotor, 0x0404_0000));
+    }
+
     #[test]
     fn rover_guided_number() {
         assert_eq!(
@@ -184,4 +411,72 @@ mod tests {
             Some(15)
         );
     }
+
+    #[test]
+    fn overrides_rename_and_add_modes() {
+        let overrides: ModeOverrides = serde_json::from_str(
+            r#"{"copter": [
+                {"custom_mode": 27, "name": "AUTO_RTL"},
+                {"custom_mode": 16, "name": "PosHold2"}
+            ]}"#,
+        )
+        .unwrap();
+        let (autopilot, copter) = (AutopilotType::ArduPilotMega, VehicleType::Quadrotor);
+        let modes = overrides.available_modes(autopilot, copter);
+        assert_eq!(modes.len(), COPTER_MODES.len() + 1);
+        assert_eq!(modes.last().unwrap().name, "AUTO_RTL");
+        assert_eq!(overrides.mode_name(autopilot, copter, 27), "AUTO_RTL");
+        assert_eq!(overrides.mode_name(autopilot, copter, 16), "PosHold2");
+        assert_eq!(overrides.mode_number(autopilot, copter, "poshold2"), Some(16));
+        assert_eq!(overrides.mode_number(autopilot, copter, "POSHOLD"), None);
+        // Other vehicle classes keep the stock table
+        assert_eq!(
+            overrides.mode_name(autopilot, VehicleType::FixedWing, 11),
+            "RTL"
+        );
+        // Renamed modes keep their meaning
+        assert!(is_recovery_mode(autopilot, copter, 6));
+    }
+
+    #[test]
+    fn hazards_follow_the_vehicle_state() {
+        let copter = VehicleState {
+            autopilot: AutopilotType::ArduPilotMega,
+            vehicle_type: VehicleType::Quadrotor,
+            ..VehicleState::default()
+        };
+        let fixed = Telemetry {
+            gps_fix_type: Some(GpsFixType::Fix3d),
+            ..Telemetry::default()
+        };
+        let uploaded = MissionState {
+            total_items: 5,
+            mission_state: MissionRunState::NotStarted,
+            ..MissionState::default()
+        };
+        let empty = MissionState {
+            mission_state: MissionRunState::NoMission,
+            ..MissionState::default()
+        };
+
+        // AUTO
+        assert_eq!(mode_hazard(&copter, &fixed, &uploaded, 3), None);
+        assert_eq!(mode_hazard(&copter, &fixed, &empty, 3), Some(ModeHazard::NoMission));
+        // GUIDED
+        assert_eq!(mode_hazard(&copter, &fixed, &uploaded, 4), None);
+        assert_eq!(
+            mode_hazard(&copter, &Telemetry::default(), &uploaded, 4),
+            Some(ModeHazard::NoGpsFix)
+        );
+        // FLIP and ACRO
+        assert_eq!(mode_hazard(&copter, &fixed, &uploaded, 14), Some(ModeHazard::Disarmed));
+        assert_eq!(mode_hazard(&copter, &fixed, &uploaded, 1), Some(ModeHazard::Disarmed));
+        let armed = VehicleState {
+            armed: true,
+            ..copter.clone()
+        };
+        assert_eq!(mode_hazard(&armed, &fixed, &uploaded, 14), None);
+        // Always fine
+        assert_eq!(mode_hazard(&copter, &Telemetry::default(), &empty, 6), None);
+    }
 }
@@ -0,0 +1,240 @@
+//! Flight timer and odometer.
+//!
+//! Armed time and distance flown are tracked for the current session and,
+//! when `VehicleConfig::flight_log_dir` is set, added to per-airframe totals
+//! kept on disk under the autopilot's unique ID, so airframe hours are
+//! logged without manual bookkeeping.
+
+use crate::mission::geo::distance_m;
+use crate::params::cache::file_stem;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Position steps shorter than this are GPS jitter, not flight.
+const MIN_STEP_M: f64 = 1.0;
+/// Steps implying a faster ground speed are position glitches.
+const MAX_SPEED_MPS: f64 = 150.0;
+
+/// Armed time and distance flown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FlightTotals {
+    pub armed_time_s: f64,
+    /// Distance flown while armed, over ground.
+    pub distance_m: f64,
+    /// Number of times the vehicle was armed.
+    pub flights: u32,
+}
+
+impl FlightTotals {
+    pub fn add(&self, other: &FlightTotals) -> FlightTotals {
+        FlightTotals {
+            armed_time_s: self.armed_time_s + other.armed_time_s,
+            distance_m: self.distance_m + other.distance_m,
+            flights: self.flights + other.flights,
+        }
+    }
+}
+
+/// Accumulates the session's totals from arming state and positions.
+#[derive(Debug, Default)]
+pub(crate) struct Odometer {
+    /// Completed flights of this session.
+    session: FlightTotals,
+    armed_since: Option<Instant>,
+    /// Where the distance was last advanced to.
+    anchor: Option<(f64, f64, Instant)>,
+    /// Totals of earlier sessions, once loaded for this airframe.
+    previous: Option<FlightTotals>,
+}
+
+impl Odometer {
+    /// Follow the arming state. Returns true when a flight just ended.
+    pub(crate) fn set_armed(&mut self, armed: bool, now: Instant) -> bool {
+        match (self.armed_since, armed) {
+            (None, true) => {
+                self.armed_since = Some(now);
+                self.session.flights += 1;
+                self.anchor = None;
+                false
+            }
+            (Some(since), false) => {
+                self.session.armed_time_s += now.saturating_duration_since(since).as_secs_f64();
+                self.armed_since = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn observe_position(&mut self, lat: f64, lon: f64, now: Instant) {
+        if self.armed_since.is_none() {
+            return;
+        }
+        let Some((anchor_lat, anchor_lon, anchor_at)) = self.anchor else {
+            self.anchor = Some((lat, lon, now));
+            return;
+        };
+        let step = distance_m(anchor_lat, anchor_lon, lat, lon);
+        if step < MIN_STEP_M {
+            return;
+        }
+        let elapsed = now.saturating_duration_since(anchor_at).as_secs_f64();
+        if step <= MAX_SPEED_MPS * elapsed {
+            self.session.distance_m += step;
+        }
+        self.anchor = Some((lat, lon, now));
+    }
+
+    /// This session's totals, including the flight in progress.
+    pub(crate) fn session(&self, now: Instant) -> FlightTotals {
+        let mut totals = self.session;
+        if let Some(since) = self.armed_since {
+            totals.armed_time_s += now.saturating_duration_since(since).as_secs_f64();
+        }
+        totals
+    }
+
+    /// Totals over every session of the airframe; `None` until loaded.
+    pub(crate) fn lifetime(&self, now: Instant) -> Option<FlightTotals> {
+        Some(self.previous?.add(&self.session(now)))
+    }
+
+    pub(crate) fn has_previous(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    pub(crate) fn set_previous(&mut self, previous: FlightTotals) {
+        self.previous = Some(previous);
+    }
+}
+
+/// On-disk flight totals with one file per airframe, keyed by the
+/// autopilot's unique ID.
+pub struct FlightLog {
+    dir: PathBuf,
+}
+
+impl FlightLog {
+    /// Open (or create) the log directory.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// The totals of `uid`; zero for an airframe never logged.
+    pub fn load(&self, uid: &str) -> io::Result<FlightTotals> {
+        let path = self.path(uid);
+        if !path.exists() {
+            return Ok(FlightTotals::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(invalid_data)
+    }
+
+    pub fn save(&self, uid: &str, totals: &FlightTotals) -> io::Result<()> {
+        let json = serde_json::to_string(totals).map_err(invalid_data)?;
+        let tmp = self.dir.join(format!("{}.tmp", file_stem(uid)));
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, self.path(uid))
+    }
+
+    fn path(&self, uid: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", file_stem(uid)))
+    }
+}
+
+fn invalid_data(err: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// About 11.1 m of latitude.
+    const STEP_DEG: f64 = 0.0001;
+
+    #[test]
+    fn counts_armed_time_and_flights() {
+        let start = Instant::now();
+        let mut odometer = Odometer::default();
+        assert!(!odometer.set_armed(false, start));
+        assert!(!odometer.set_armed(true, start));
+        assert!(!odometer.set_armed(true, start + Duration::from_secs(5)));
+        let running = odometer.session(start + Duration::from_secs(30));
+        assert_eq!(running.armed_time_s, 30.0);
+
+        assert!(odometer.set_armed(false, start + Duration::from_secs(60)));
+        odometer.set_armed(true, start + Duration::from_secs(100));
+        let totals = odometer.session(start + Duration::from_secs(110));
+        assert_eq!(totals.armed_time_s, 70.0);
+        assert_eq!(totals.flights, 2);
+    }
+
+    #[test]
+    fn distance_skips_jitter_glitches_and_disarmed_movement() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut odometer = Odometer::default();
+        // Carried on the ground while disarmed
+        odometer.observe_position(47.0, 8.0, at(0));
+        odometer.set_armed(true, at(1));
+        odometer.observe_position(47.0, 8.0, at(1));
+        // Jitter around the start
+        odometer.observe_position(47.000_002, 8.0, at(2));
+        assert_eq!(odometer.session(at(2)).distance_m, 0.0);
+
+        odometer.observe_position(47.0 + STEP_DEG, 8.0, at(3));
+        odometer.observe_position(47.0 + 2.0 * STEP_DEG, 8.0, at(4));
+        let flown = odometer.session(at(4)).distance_m;
+        assert!((flown - 22.2).abs() < 0.2, "{flown}");
+
+        // 1.1 km in one second
+        odometer.observe_position(47.01, 8.0, at(5));
+        assert_eq!(odometer.session(at(5)).distance_m, flown);
+    }
+
+    #[test]
+    fn lifetime_adds_the_logged_totals() {
+        let start = Instant::now();
+        let mut odometer = Odometer::default();
+        odometer.set_armed(true, start);
+        assert_eq!(odometer.lifetime(start), None);
+        odometer.set_previous(FlightTotals {
+            armed_time_s: 3600.0,
+            distance_m: 1000.0,
+            flights: 4,
+        });
+        let lifetime = odometer.lifetime(start + Duration::from_secs(60)).unwrap();
+        assert_eq!(
+            lifetime,
+            FlightTotals {
+                armed_time_s: 3660.0,
+                distance_m: 1000.0,
+                flights: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn log_round_trips_per_airframe() {
+        let dir = std::env::temp_dir().join(format!("mavkit-flight-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let log = FlightLog::open(&dir).unwrap();
+        assert_eq!(log.load("00aa").unwrap(), FlightTotals::default());
+        let totals = FlightTotals {
+            armed_time_s: 12.5,
+            distance_m: 80.0,
+            flights: 1,
+        };
+        log.save("00aa", &totals).unwrap();
+        assert_eq!(log.load("00aa").unwrap(), totals);
+        assert_eq!(log.load("00bb").unwrap(), FlightTotals::default());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,148 @@
+use super::types::ParamStore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parameters last downloaded from one vehicle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedParams {
+    pub store: ParamStore,
+    /// The autopilot's `_HASH_CHECK` over all values when they were
+    /// downloaded; `None` when it did not answer.
+    pub hash: Option<u32>,
+    pub saved_at_ms: u64,
+}
+
+impl CachedParams {
+    pub fn new(store: ParamStore, hash: Option<u32>) -> Self {
+        Self {
+            store,
+            hash,
+            saved_at_ms: now_ms(),
+        }
+    }
+
+    /// Whether the vehicle still holds these values, judged by its current
+    /// parameter count and `_HASH_CHECK`. Without a hash on both sides the
+    /// count alone can't tell, so the cache is treated as stale.
+    pub fn is_current(&self, count: u16, hash: u32) -> bool {
+        self.store.expected_count == count && self.hash == Some(hash)
+    }
+}
+
+/// On-disk parameter cache with one file per vehicle, keyed by the
+/// autopilot's unique ID, so reconnects can show parameters right away.
+pub struct ParamCache {
+    dir: PathBuf,
+}
+
+impl ParamCache {
+    /// Open (or create) the cache directory.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// The cached parameters of `uid`, if any.
+    pub fn load(&self, uid: &str) -> io::Result<Option<CachedParams>> {
+        let path = self.path(uid);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(invalid_data)
+    }
+
+    pub fn save(&self, uid: &str, cached: &CachedParams) -> io::Result<()> {
+        let json = serde_json::to_string(cached).map_err(invalid_data)?;
+        // Write-then-rename so a crash mid-write never leaves a truncated cache
+        let tmp = self.dir.join(format!("{}.tmp", file_stem(uid)));
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, self.path(uid))
+    }
+
+    pub fn remove(&self, uid: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(uid)) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn path(&self, uid: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", file_stem(uid)))
+    }
+}
+
+/// Keep only characters that are safe in file names on every platform.
+pub(crate) fn file_stem(uid: &str) -> String {
+    uid.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn invalid_data(err: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "mavkit-param-cache-{name}-{}-{}",
+            std::process::id(),
+            now_ms()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn staleness_needs_matching_count_and_hash() {
+        let cached = CachedParams::new(
+            ParamStore::from_values(&[("SYSID_THISMAV", 1.0)]),
+            Some(0xdead_beef),
+        );
+        assert!(cached.is_current(1, 0xdead_beef));
+        assert!(!cached.is_current(2, 0xdead_beef));
+        assert!(!cached.is_current(1, 0x1234));
+
+        let unhashed = CachedParams::new(ParamStore::from_values(&[("SYSID_THISMAV", 1.0)]), None);
+        assert!(!unhashed.is_current(1, 0));
+    }
+
+    #[test]
+    fn saved_params_load_per_vehicle() {
+        let dir = temp_cache("roundtrip");
+        let cache = ParamCache::open(&dir).unwrap();
+        assert_eq!(cache.load("0011aabb").unwrap(), None);
+
+        let cached = CachedParams::new(ParamStore::from_values(&[("SYSID_THISMAV", 1.0)]), Some(7));
+        cache.save("0011aabb", &cached).unwrap();
+        assert_eq!(cache.load("0011aabb").unwrap(), Some(cached));
+        assert_eq!(cache.load("ffff").unwrap(), None);
+
+        cache.remove("0011aabb").unwrap();
+        cache.remove("0011aabb").unwrap();
+        assert_eq!(cache.load("0011aabb").unwrap(), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn uid_is_sanitised_for_the_file_name() {
+        assert_eq!(file_stem("../a:b"), "___a_b");
+    }
+}
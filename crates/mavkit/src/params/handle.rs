@@ -0,0 +1,150 @@
+use super::transaction::{plan_transaction, ParamChange};
+use super::{CachedParams, Param, ParamCache, ParamStore};
+use crate::error::VehicleError;
+use crate::Vehicle;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// How long `download_cached` waits for AUTOPILOT_VERSION before falling
+/// back to a plain download.
+const UID_WAIT: Duration = Duration::from_secs(3);
+
+/// Handle to parameter operations on a `Vehicle`.
+pub struct ParamsHandle<'a> {
+    vehicle: &'a Vehicle,
+}
+
+impl<'a> ParamsHandle<'a> {
+    pub(crate) fn new(vehicle: &'a Vehicle) -> Self {
+        Self { vehicle }
+    }
+
+    pub async fn download_all(&self) -> Result<ParamStore, VehicleError> {
+        self.download_all_with_cancel(&CancellationToken::new())
+            .await
+    }
+
+    /// Like `download_all`, ending with `VehicleError::Cancelled` once
+    /// `cancel` fires.
+    pub async fn download_all_with_cancel(
+        &self,
+        cancel: &CancellationToken,
+    ) -> Result<ParamStore, VehicleError> {
+        let cancel = cancel.clone();
+        self.vehicle
+            .send_command(|reply| crate::command::Command::ParamDownloadAll { cancel, reply })
+            .await
+    }
+
+    /// Like `download_all`, but publish this vehicle's cached parameters
+    /// first and skip the download when the vehicle's parameter count and
+    /// `_HASH_CHECK` still match them. A fresh download replaces the cache
+    /// entry. Vehicles without a board ID are always downloaded.
+    pub async fn download_cached(&self, cache: &ParamCache) -> Result<ParamStore, VehicleError> {
+        let mut uid_rx = self.vehicle.uid();
+        let uid = match tokio::time::timeout(UID_WAIT, uid_rx.wait_for(Option::is_some)).await {
+            Ok(Ok(uid)) => uid.clone(),
+            _ => None,
+        };
+        let Some(uid) = uid else {
+            return self.download_all().await;
+        };
+
+        let cached = cache.load(&uid).unwrap_or_else(|err| {
+            warn!("param cache for {uid} unreadable, downloading: {err}");
+            None
+        });
+        if let Some(cached) = cached {
+            let store = cached.store.clone();
+            self.vehicle
+                .send_command(|reply| crate::command::Command::ParamSeed { store, reply })
+                .await?;
+            if let Ok((count, hash)) = self.hash_check().await {
+                if cached.is_current(count, hash) {
+                    return Ok(cached.store);
+                }
+            }
+        }
+
+        let store = self.download_all().await?;
+        let hash = self.hash_check().await.ok().map(|(_, hash)| hash);
+        if let Err(err) = cache.save(&uid, &CachedParams::new(store.clone(), hash)) {
+            warn!("could not cache params of {uid}: {err}");
+        }
+        Ok(store)
+    }
+
+    pub async fn write(&self, name: String, value: f32) -> Result<Param, VehicleError> {
+        self.vehicle
+            .send_command(|reply| crate::command::Command::ParamWrite { name, value, reply })
+            .await
+    }
+
+    /// Write `name` and check the value the vehicle echoes back.
+    pub(crate) async fn write_verified(
+        &self,
+        name: &str,
+        value: f32,
+    ) -> Result<Param, VehicleError> {
+        let param = self.write(name.to_string(), value).await?;
+        let actual = param.value;
+        if (actual - value).abs() > f32::EPSILON * value.abs().max(1.0) {
+            return Err(VehicleError::ParamMismatch {
+                name: name.to_string(),
+                requested: value,
+                actual,
+            });
+        }
+        Ok(param)
+    }
+
+    /// Write `changes` as one: checked and ordered by `plan_transaction`,
+    /// each verified against its echo. When one fails, the ones already
+    /// written get their earlier values back before the error is returned.
+    /// Needs the parameters downloaded first.
+    pub async fn write_transaction(
+        &self,
+        changes: &[ParamChange],
+    ) -> Result<Vec<Param>, VehicleError> {
+        let store = self.vehicle.param_store().borrow().clone();
+        let ordered = plan_transaction(&store, changes)?;
+
+        let mut written = Vec::with_capacity(ordered.len());
+        for change in &ordered {
+            match self.write_verified(&change.name, change.value).await {
+                Ok(param) => written.push(param),
+                Err(err) => {
+                    return Err(VehicleError::ParamTransactionRolledBack {
+                        name: change.name.clone(),
+                        reason: err.to_string(),
+                        not_restored: self.restore(&store, &written).await,
+                    });
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    /// Set `written` back to their values in `before`, newest first, and
+    /// return the names that could not be.
+    async fn restore(&self, before: &ParamStore, written: &[Param]) -> Vec<String> {
+        let mut not_restored = Vec::new();
+        for param in written.iter().rev() {
+            let Some(previous) = before.params.get(&param.name) else {
+                continue;
+            };
+            if let Err(err) = self.write_verified(&param.name, previous.value).await {
+                warn!("could not restore {}: {err}", param.name);
+                not_restored.push(param.name.clone());
+            }
+        }
+        not_restored
+    }
+
+    async fn hash_check(&self) -> Result<(u16, u32), VehicleError> {
+        self.vehicle
+            .send_command(|reply| crate::command::Command::ParamHashCheck { reply })
+            .await
+    }
+}
@@ -1,35 +1,23 @@
+// Persists to disk; browser consumers keep their own cache.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache;
 pub mod file;
+#[cfg(feature = "link")]
+mod handle;
+#[cfg(feature = "link")]
+pub(crate) mod progress;
+pub mod search;
+pub mod transaction;
 pub mod types;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache::{CachedParams, ParamCache};
 pub use file::{format_param_file, parse_param_file};
-pub use types::{Param, ParamProgress, ParamStore, ParamTransferPhase, ParamType};
-
-use crate::error::VehicleError;
-use crate::Vehicle;
-
-/// Handle to parameter operations on a `Vehicle`.
-pub struct ParamsHandle<'a> {
-    vehicle: &'a Vehicle,
-}
-
-impl<'a> ParamsHandle<'a> {
-    pub(crate) fn new(vehicle: &'a Vehicle) -> Self {
-        Self { vehicle }
-    }
-
-    pub async fn download_all(&self) -> Result<ParamStore, VehicleError> {
-        self.vehicle
-            .send_command(|reply| crate::command::Command::ParamDownloadAll { reply })
-            .await
-    }
-
-    pub async fn write(&self, name: String, value: f32) -> Result<Param, VehicleError> {
-        self.vehicle
-            .send_command(|reply| crate::command::Command::ParamWrite {
-                name,
-                value,
-                reply,
-            })
-            .await
-    }
-}
+#[cfg(feature = "link")]
+pub use handle::ParamsHandle;
+pub use search::{param_prefix, ParamGroup};
+pub use transaction::{plan_transaction, ParamChange};
+pub use types::{
+    Param, ParamDownloadStage, ParamProgress, ParamStore, ParamTransferPhase, ParamType,
+    ParamUpdate,
+};
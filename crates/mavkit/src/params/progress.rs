@@ -0,0 +1,170 @@
+use super::types::{ParamDownloadStage, ParamProgress, ParamTransferPhase};
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+/// Book-keeping for a full parameter download, reported as `ParamProgress`.
+#[derive(Debug)]
+pub(crate) struct ParamDownloadTracker {
+    received: BTreeSet<u16>,
+    expected: Option<u16>,
+    stage: ParamDownloadStage,
+    requested: Vec<u16>,
+    retries: u32,
+    max_retries: u32,
+    started: Instant,
+}
+
+impl ParamDownloadTracker {
+    pub(crate) fn new(max_retries: u32, now: Instant) -> Self {
+        Self {
+            received: BTreeSet::new(),
+            expected: None,
+            stage: ParamDownloadStage::Bulk,
+            requested: Vec::new(),
+            retries: 0,
+            max_retries,
+            started: now,
+        }
+    }
+
+    /// Record the first non-zero `param_count` seen.
+    pub(crate) fn set_expected(&mut self, count: u16) {
+        if self.expected.is_none() && count > 0 {
+            self.expected = Some(count);
+        }
+    }
+
+    pub(crate) fn expected(&self) -> Option<u16> {
+        self.expected
+    }
+
+    /// Mark `index` received. Returns false for duplicates.
+    pub(crate) fn record(&mut self, index: u16) -> bool {
+        self.requested.retain(|&i| i != index);
+        self.received.insert(index)
+    }
+
+    pub(crate) fn received_count(&self) -> u16 {
+        self.received.len() as u16
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        self.expected
+            .is_some_and(|expected| self.received_count() >= expected)
+    }
+
+    pub(crate) fn missing_count(&self) -> u16 {
+        self.expected
+            .map_or(0, |expected| expected.saturating_sub(self.received_count()))
+    }
+
+    /// Count a timeout; `got_new` resets the counter. Returns true once
+    /// `max_retries` consecutive timeouts passed without new data.
+    pub(crate) fn on_timeout(&mut self, got_new: bool) -> bool {
+        if got_new {
+            self.retries = 0;
+        } else {
+            self.retries += 1;
+        }
+        self.retries > self.max_retries
+    }
+
+    pub(crate) fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Switch to gap-fill and pick up to `limit` missing indices to re-request.
+    pub(crate) fn next_gap_batch(&mut self, limit: usize) -> Vec<u16> {
+        let Some(expected) = self.expected else {
+            return Vec::new();
+        };
+        self.stage = ParamDownloadStage::GapFill;
+        self.requested = (0..expected)
+            .filter(|idx| !self.received.contains(idx))
+            .take(limit)
+            .collect();
+        self.requested.clone()
+    }
+
+    pub(crate) fn progress(&self, phase: ParamTransferPhase, now: Instant) -> ParamProgress {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f32();
+        ParamProgress {
+            phase,
+            received: self.received_count(),
+            expected: self.expected.unwrap_or(0),
+            stage: self.stage,
+            missing_count: self.missing_count(),
+            requested: self.requested.clone(),
+            retries: self.retries,
+            max_retries: self.max_retries,
+            rate_per_sec: if elapsed > 0.0 {
+                self.received_count() as f32 / elapsed
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn gap_fill_requests_missing_indices() {
+        let now = Instant::now();
+        let mut tracker = ParamDownloadTracker::new(3, now);
+        tracker.set_expected(6);
+        for idx in [0, 1, 3, 5] {
+            assert!(tracker.record(idx));
+        }
+        assert!(!tracker.record(1));
+        assert_eq!(tracker.missing_count(), 2);
+
+        assert_eq!(tracker.next_gap_batch(10), vec![2, 4]);
+        let progress = tracker.progress(ParamTransferPhase::Downloading, now);
+        assert_eq!(progress.stage, ParamDownloadStage::GapFill);
+        assert_eq!(progress.requested, vec![2, 4]);
+
+        tracker.record(2);
+        let progress = tracker.progress(ParamTransferPhase::Downloading, now);
+        assert_eq!(progress.requested, vec![4]);
+        assert_eq!(progress.missing_count, 1);
+        assert!(!tracker.is_complete());
+    }
+
+    #[test]
+    fn batch_is_limited() {
+        let mut tracker = ParamDownloadTracker::new(3, Instant::now());
+        tracker.set_expected(100);
+        assert_eq!(tracker.next_gap_batch(10).len(), 10);
+    }
+
+    #[test]
+    fn gives_up_after_consecutive_empty_timeouts() {
+        let mut tracker = ParamDownloadTracker::new(2, Instant::now());
+        assert!(!tracker.on_timeout(false));
+        assert!(!tracker.on_timeout(true));
+        assert_eq!(tracker.retries(), 0);
+        assert!(!tracker.on_timeout(false));
+        assert!(!tracker.on_timeout(false));
+        assert!(tracker.on_timeout(false));
+    }
+
+    #[test]
+    fn rate_is_params_per_second() {
+        let now = Instant::now();
+        let mut tracker = ParamDownloadTracker::new(3, now);
+        tracker.set_expected(900);
+        for idx in 0..300 {
+            tracker.record(idx);
+        }
+        let progress = tracker.progress(
+            ParamTransferPhase::Downloading,
+            now + Duration::from_secs(2),
+        );
+        assert!((progress.rate_per_sec - 150.0).abs() < 0.01);
+        assert_eq!(progress.stage, ParamDownloadStage::Bulk);
+    }
+}
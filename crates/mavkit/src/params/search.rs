@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use super::types::{Param, ParamStore};
+
+/// Parameters sharing a subsystem prefix, e.g. `ATC_` or `BATT_`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamGroup {
+    /// Prefix including the trailing underscore; a parameter without an
+    /// underscore is its own group, named after itself.
+    pub prefix: String,
+    /// Member parameter names, sorted.
+    pub names: Vec<String>,
+}
+
+/// Subsystem prefix of a parameter name (`"ATC_RAT_RLL_P"` -> `"ATC_"`).
+pub fn param_prefix(name: &str) -> &str {
+    match name.find('_') {
+        Some(i) => &name[..=i],
+        None => name,
+    }
+}
+
+impl ParamStore {
+    /// Case-insensitive search by name. Exact matches come first, then
+    /// prefix matches, then substring matches; each tier sorted by name.
+    /// An empty query returns every parameter.
+    pub fn search(&self, query: &str) -> Vec<&Param> {
+        let query = query.trim().to_ascii_uppercase();
+        let mut hits: Vec<(u8, &Param)> = self
+            .params
+            .values()
+            .filter_map(|param| {
+                let name = param.name.to_ascii_uppercase();
+                let rank = if name == query {
+                    0
+                } else if name.starts_with(&query) {
+                    1
+                } else if name.contains(&query) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, param))
+            })
+            .collect();
+        hits.sort_by(|(ra, a), (rb, b)| ra.cmp(rb).then_with(|| a.name.cmp(&b.name)));
+        hits.into_iter().map(|(_, param)| param).collect()
+    }
+
+    /// Group all parameters by subsystem prefix, sorted by prefix.
+    pub fn group_by_prefix(&self) -> Vec<ParamGroup> {
+        let mut names: Vec<&str> = self.params.keys().map(String::as_str).collect();
+        names.sort_unstable();
+
+        let mut groups: Vec<ParamGroup> = Vec::new();
+        for name in names {
+            let prefix = param_prefix(name);
+            match groups.last_mut() {
+                Some(group) if group.prefix == prefix => group.names.push(name.to_string()),
+                _ => groups.push(ParamGroup {
+                    prefix: prefix.to_string(),
+                    names: vec![name.to_string()],
+                }),
+            }
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(params: Vec<&Param>) -> Vec<&str> {
+        params.into_iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn search_ranks_exact_then_prefix_then_substring() {
+        let store = ParamStore::from_values(&[
+            ("RTL_ALT", 0.0),
+            ("ALT_HOLD_RTL", 0.0),
+            ("RTL_ALT_FINAL", 0.0),
+            ("BATT_CAPACITY", 0.0),
+        ]);
+        assert_eq!(
+            names(store.search("rtl_alt")),
+            vec!["RTL_ALT", "RTL_ALT_FINAL"]
+        );
+        assert_eq!(
+            names(store.search("rtl")),
+            vec!["RTL_ALT", "RTL_ALT_FINAL", "ALT_HOLD_RTL"]
+        );
+        assert!(store.search("compass").is_empty());
+        assert_eq!(store.search("").len(), 4);
+    }
+
+    #[test]
+    fn groups_by_subsystem_prefix() {
+        let store = ParamStore::from_values(&[
+            ("ATC_RAT_RLL_P", 0.0),
+            ("BATT_MONITOR", 0.0),
+            ("ATC_ACCEL_P_MAX", 0.0),
+            ("SYSID_THISMAV", 0.0),
+            ("FORMAT_VERSION", 0.0),
+            ("ARMING", 0.0),
+        ]);
+        let groups = store.group_by_prefix();
+        let prefixes: Vec<&str> = groups.iter().map(|g| g.prefix.as_str()).collect();
+        assert_eq!(
+            prefixes,
+            vec!["ARMING", "ATC_", "BATT_", "FORMAT_", "SYSID_"]
+        );
+        assert_eq!(groups[1].names, vec!["ATC_ACCEL_P_MAX", "ATC_RAT_RLL_P"]);
+    }
+}
@@ -0,0 +1,177 @@
+//! Writing parameters that only make sense together.
+//!
+//! Setting up a battery monitor means changing `BATT_MONITOR`, the capacity
+//! and the failsafe voltages at once; stopping halfway leaves the vehicle
+//! with failsafes that fire too early or never. `ParamsHandle::write_transaction`
+//! checks such a set of changes with `plan_transaction`, writes them in the
+//! order it returns, verifies each echo, and restores the earlier values of
+//! the ones already written as soon as one is rejected.
+
+use super::types::ParamStore;
+use crate::error::VehicleError;
+use serde::{Deserialize, Serialize};
+
+/// Groups of parameters that do nothing until their enable parameter is
+/// nonzero, as (prefix, enable parameter).
+const GATES: &[(&str, &str)] = &[
+    ("BATT_", "BATT_MONITOR"),
+    ("BATT2_", "BATT2_MONITOR"),
+    ("ARSPD_", "ARSPD_TYPE"),
+    ("RNGFND1_", "RNGFND1_TYPE"),
+    ("FLOW_", "FLOW_TYPE"),
+    ("Q_", "Q_ENABLE"),
+    ("SCR_", "SCR_ENABLE"),
+];
+
+/// Thresholds that must stay below another, as (lower, higher); ArduPilot
+/// treats 0 as unset for both.
+const BELOW: &[(&str, &str)] = &[
+    ("BATT_CRT_VOLT", "BATT_LOW_VOLT"),
+    ("BATT_CRT_MAH", "BATT_LOW_MAH"),
+    ("BATT2_CRT_VOLT", "BATT2_LOW_VOLT"),
+    ("BATT2_CRT_MAH", "BATT2_LOW_MAH"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamChange {
+    pub name: String,
+    pub value: f32,
+}
+
+impl ParamChange {
+    pub fn new(name: impl Into<String>, value: f32) -> Self {
+        Self {
+            name: name.into(),
+            value,
+        }
+    }
+}
+
+fn gate_of(name: &str) -> Option<&'static str> {
+    GATES
+        .iter()
+        .find(|(prefix, enable)| name.starts_with(prefix) && name != *enable)
+        .map(|(_, enable)| *enable)
+}
+
+/// Check `changes` against `store` and return them in the order to write:
+/// enable parameters first, then the rest as given.
+///
+/// Every parameter must be in `store`, both to be sure the vehicle has it
+/// and to know the value to restore. A parameter of a gated group needs
+/// its enable parameter nonzero after the transaction, and thresholds must
+/// keep their order.
+pub fn plan_transaction(
+    store: &ParamStore,
+    changes: &[ParamChange],
+) -> Result<Vec<ParamChange>, VehicleError> {
+    let invalid = |message: String| Err(VehicleError::ParamTransaction(message));
+    for (i, change) in changes.iter().enumerate() {
+        if !store.params.contains_key(&change.name) {
+            return Err(VehicleError::ParamUnknown(change.name.clone()));
+        }
+        if changes[..i].iter().any(|c| c.name == change.name) {
+            return invalid(format!("{} is changed twice", change.name));
+        }
+    }
+    // Values as they will be once every change is written
+    let after = |name: &str| {
+        changes
+            .iter()
+            .find(|change| change.name == name)
+            .map(|change| change.value)
+            .or_else(|| store.params.get(name).map(|param| param.value))
+    };
+
+    for change in changes {
+        let Some(enable) = gate_of(&change.name) else {
+            continue;
+        };
+        if after(enable).unwrap_or(0.0) == 0.0 {
+            return invalid(format!(
+                "{} has no effect unless {enable} is set",
+                change.name
+            ));
+        }
+    }
+    for (lower, higher) in BELOW {
+        let touched = changes
+            .iter()
+            .any(|c| c.name == *lower || c.name == *higher);
+        let (Some(low), Some(high)) = (after(lower), after(higher)) else {
+            continue;
+        };
+        if touched && low > 0.0 && high > 0.0 && low >= high {
+            return invalid(format!("{lower} ({low}) must be below {higher} ({high})"));
+        }
+    }
+
+    let is_enable = |change: &&ParamChange| GATES.iter().any(|(_, enable)| change.name == *enable);
+    let (enables, rest): (Vec<&ParamChange>, Vec<&ParamChange>) =
+        changes.iter().partition(is_enable);
+    Ok(enables.into_iter().chain(rest).cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battery_setup_enables_the_monitor_first() {
+        let store = ParamStore::from_values(&[
+            ("BATT_MONITOR", 0.0),
+            ("BATT_CAPACITY", 0.0),
+            ("BATT_LOW_VOLT", 0.0),
+            ("BATT_CRT_VOLT", 0.0),
+        ]);
+        let changes = [
+            ParamChange::new("BATT_CAPACITY", 5200.0),
+            ParamChange::new("BATT_LOW_VOLT", 14.0),
+            ParamChange::new("BATT_CRT_VOLT", 13.2),
+            ParamChange::new("BATT_MONITOR", 4.0),
+        ];
+        let order: Vec<String> = plan_transaction(&store, &changes)
+            .unwrap()
+            .into_iter()
+            .map(|change| change.name)
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "BATT_MONITOR",
+                "BATT_CAPACITY",
+                "BATT_LOW_VOLT",
+                "BATT_CRT_VOLT"
+            ]
+        );
+
+        // Without the monitor the capacity does nothing
+        assert!(matches!(
+            plan_transaction(&store, &changes[..1]),
+            Err(VehicleError::ParamTransaction(_))
+        ));
+    }
+
+    #[test]
+    fn thresholds_keep_their_order_and_names_must_be_known() {
+        let store = ParamStore::from_values(&[
+            ("BATT_MONITOR", 4.0),
+            ("BATT_LOW_VOLT", 14.0),
+            ("BATT_CRT_VOLT", 13.2),
+        ]);
+        let crt_above_low = [ParamChange::new("BATT_CRT_VOLT", 14.4)];
+        assert!(matches!(
+            plan_transaction(&store, &crt_above_low),
+            Err(VehicleError::ParamTransaction(_))
+        ));
+        let both = [
+            ParamChange::new("BATT_CRT_VOLT", 14.4),
+            ParamChange::new("BATT_LOW_VOLT", 15.0),
+        ];
+        assert!(plan_transaction(&store, &both).is_ok());
+        assert!(matches!(
+            plan_transaction(&store, &[ParamChange::new("BATT_FS_LOW_ACT", 2.0)]),
+            Err(VehicleError::ParamUnknown(_))
+        ));
+    }
+}
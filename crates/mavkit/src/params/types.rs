@@ -21,12 +21,45 @@ pub struct Param {
     pub index: u16,
 }
 
+/// One parameter value as received from the vehicle, published as it
+/// arrives instead of with the whole store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamUpdate {
+    pub name: String,
+    pub value: f32,
+    pub index: u16,
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ParamStore {
     pub params: HashMap<String, Param>,
     pub expected_count: u16,
 }
 
+/// Test fixture: a fully downloaded store of REAL32 params, indexed in order.
+#[cfg(test)]
+impl ParamStore {
+    pub(crate) fn from_values(values: &[(&str, f32)]) -> Self {
+        let params = values
+            .iter()
+            .enumerate()
+            .map(|(index, (name, value))| {
+                let param = Param {
+                    name: name.to_string(),
+                    value: *value,
+                    param_type: ParamType::Real32,
+                    index: index as u16,
+                };
+                (name.to_string(), param)
+            })
+            .collect();
+        ParamStore {
+            params,
+            expected_count: values.len() as u16,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ParamTransferPhase {
@@ -42,11 +75,38 @@ impl Default for ParamTransferPhase {
     }
 }
 
+/// What a running download is doing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamDownloadStage {
+    /// Receiving the PARAM_REQUEST_LIST stream.
+    #[default]
+    Bulk,
+    /// Re-requesting individual indices the stream dropped.
+    GapFill,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ParamProgress {
     pub phase: ParamTransferPhase,
     pub received: u16,
     pub expected: u16,
+    #[serde(default)]
+    pub stage: ParamDownloadStage,
+    /// Indices not yet received (0 until the count is known).
+    #[serde(default)]
+    pub missing_count: u16,
+    /// Indices re-requested in the current gap-fill batch.
+    #[serde(default)]
+    pub requested: Vec<u16>,
+    /// Consecutive timeouts without new params; the download gives up after `max_retries`.
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default)]
+    pub max_retries: u32,
+    /// Average receive rate since the download started.
+    #[serde(default)]
+    pub rate_per_sec: f32,
 }
 
 impl Default for ParamProgress {
@@ -55,6 +115,12 @@ impl Default for ParamProgress {
             phase: ParamTransferPhase::Idle,
             received: 0,
             expected: 0,
+            stage: ParamDownloadStage::Bulk,
+            missing_count: 0,
+            requested: Vec::new(),
+            retries: 0,
+            max_retries: 0,
+            rate_per_sec: 0.0,
         }
     }
 }
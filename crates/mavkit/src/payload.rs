@@ -0,0 +1,203 @@
+use crate::error::VehicleError;
+use crate::state::{AutopilotType, VehicleType};
+use crate::Vehicle;
+use mavlink::ardupilotmega::MavCmd;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GripperAction {
+    Release,
+    Grab,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WinchAction {
+    Relax,
+    Deliver,
+    Hold,
+    Retract,
+}
+
+impl WinchAction {
+    /// Value of the MAVLink `WINCH_ACTIONS` enum.
+    fn to_raw(self) -> f32 {
+        match self {
+            WinchAction::Relax => 0.0,
+            WinchAction::Deliver => 4.0,
+            WinchAction::Hold => 5.0,
+            WinchAction::Retract => 6.0,
+        }
+    }
+}
+
+/// Which payload commands the connected vehicle is expected to accept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadCapabilities {
+    pub servo: bool,
+    pub relay: bool,
+    pub gripper: bool,
+    pub winch: bool,
+}
+
+/// Derive payload command availability from the autopilot and frame type.
+pub fn payload_capabilities(
+    autopilot: AutopilotType,
+    vehicle_type: VehicleType,
+) -> PayloadCapabilities {
+    let is_copter = matches!(
+        vehicle_type,
+        VehicleType::Quadrotor
+            | VehicleType::Hexarotor
+            | VehicleType::Octorotor
+            | VehicleType::Tricopter
+            | VehicleType::Coaxial
+            | VehicleType::Helicopter
+    );
+    match autopilot {
+        AutopilotType::ArduPilotMega => PayloadCapabilities {
+            servo: true,
+            relay: true,
+            gripper: true,
+            // AP_Winch is only built into Copter
+            winch: is_copter,
+        },
+        AutopilotType::Px4 => PayloadCapabilities {
+            servo: false,
+            relay: false,
+            gripper: true,
+            winch: false,
+        },
+        AutopilotType::Generic | AutopilotType::Unknown => PayloadCapabilities::default(),
+    }
+}
+
+/// Handle to payload actuation (servos, relays, gripper, winch) on a `Vehicle`.
+pub struct PayloadHandle<'a> {
+    vehicle: &'a Vehicle,
+}
+
+impl<'a> PayloadHandle<'a> {
+    pub(crate) fn new(vehicle: &'a Vehicle) -> Self {
+        Self { vehicle }
+    }
+
+    pub fn capabilities(&self) -> PayloadCapabilities {
+        let state = self.vehicle.state().borrow().clone();
+        payload_capabilities(state.autopilot, state.vehicle_type)
+    }
+
+    /// Set servo output `channel` (1-based) to `pwm` microseconds.
+    pub async fn set_servo(&self, channel: u8, pwm: u16) -> Result<(), VehicleError> {
+        self.require(self.capabilities().servo, "DO_SET_SERVO")?;
+        self.vehicle
+            .command_long(
+                MavCmd::MAV_CMD_DO_SET_SERVO,
+                [channel as f32, pwm as f32, 0.0, 0.0, 0.0, 0.0, 0.0],
+            )
+            .await
+    }
+
+    /// Switch relay `index` (0-based) on or off.
+    pub async fn set_relay(&self, index: u8, on: bool) -> Result<(), VehicleError> {
+        self.require(self.capabilities().relay, "DO_SET_RELAY")?;
+        self.vehicle
+            .command_long(
+                MavCmd::MAV_CMD_DO_SET_RELAY,
+                [
+                    index as f32,
+                    if on { 1.0 } else { 0.0 },
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+            )
+            .await
+    }
+
+    pub async fn gripper(&self, action: GripperAction) -> Result<(), VehicleError> {
+        self.require(self.capabilities().gripper, "DO_GRIPPER")?;
+        let action = match action {
+            GripperAction::Release => 0.0,
+            GripperAction::Grab => 1.0,
+        };
+        self.vehicle
+            .command_long(
+                MavCmd::MAV_CMD_DO_GRIPPER,
+                [1.0, action, 0.0, 0.0, 0.0, 0.0, 0.0],
+            )
+            .await
+    }
+
+    /// Drive winch 1. `length_m` and `rate_mps` are only used by firmware for
+    /// actions that need them; pass 0 otherwise.
+    pub async fn winch(
+        &self,
+        action: WinchAction,
+        length_m: f32,
+        rate_mps: f32,
+    ) -> Result<(), VehicleError> {
+        self.require(self.capabilities().winch, "DO_WINCH")?;
+        self.vehicle
+            .command_long(
+                MavCmd::MAV_CMD_DO_WINCH,
+                [1.0, action.to_raw(), length_m, rate_mps, 0.0, 0.0, 0.0],
+            )
+            .await
+    }
+
+    fn require(&self, available: bool, command: &str) -> Result<(), VehicleError> {
+        if available {
+            Ok(())
+        } else {
+            Err(VehicleError::CommandNotSupported(command.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ardupilot_copter_supports_everything() {
+        let caps = payload_capabilities(AutopilotType::ArduPilotMega, VehicleType::Quadrotor);
+        assert!(caps.servo && caps.relay && caps.gripper && caps.winch);
+    }
+
+    #[test]
+    fn ardupilot_plane_has_no_winch() {
+        let caps = payload_capabilities(AutopilotType::ArduPilotMega, VehicleType::FixedWing);
+        assert!(caps.gripper);
+        assert!(!caps.winch);
+    }
+
+    #[test]
+    fn px4_only_gripper() {
+        let caps = payload_capabilities(AutopilotType::Px4, VehicleType::Quadrotor);
+        assert_eq!(
+            caps,
+            PayloadCapabilities {
+                servo: false,
+                relay: false,
+                gripper: true,
+                winch: false,
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_autopilot_supports_nothing() {
+        let caps = payload_capabilities(AutopilotType::Unknown, VehicleType::Quadrotor);
+        assert_eq!(caps, PayloadCapabilities::default());
+    }
+
+    #[test]
+    fn winch_action_raw_values() {
+        assert_eq!(WinchAction::Deliver.to_raw(), 4.0);
+        assert_eq!(WinchAction::Retract.to_raw(), 6.0);
+    }
+}
@@ -0,0 +1,192 @@
+//! Vehicle pose streamed over UDP for external 3D viewers.
+//!
+//! A `PoseOutput` samples the vehicle's telemetry at a fixed rate and sends
+//! one datagram per sample to a viewer, either as a small JSON object or as a
+//! line for FlightGear's generic protocol. Samples without a position fix are
+//! skipped. Dropping the `PoseOutput` stops the stream.
+//!
+//! For FlightGear, define a generic input protocol with `,` as the variable
+//! separator and a newline as the line separator, reading in order
+//! `/position/latitude-deg`, `/position/longitude-deg`,
+//! `/position/altitude-ft`, `/orientation/roll-deg`,
+//! `/orientation/pitch-deg` and `/orientation/heading-deg`, all as floats.
+
+use crate::error::VehicleError;
+use crate::state::Telemetry;
+use crate::vehicle::Vehicle;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::AbortHandle;
+use tracing::warn;
+
+const FEET_PER_METER: f64 = 3.280_839_9;
+/// Viewers interpolate; faster than this only loads the link.
+const MAX_RATE_HZ: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoseFormat {
+    /// `{"lat_deg", "lon_deg", "alt_amsl_m", "roll_deg", "pitch_deg",
+    /// "yaw_deg", "time_boot_ms"}`.
+    #[default]
+    Json,
+    /// A CSV line for a FlightGear generic protocol, see the module docs.
+    FlightGear,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoseOutputOptions {
+    /// Viewer address, `host:port`.
+    pub target: String,
+    pub format: PoseFormat,
+    /// Datagrams per second, at most 60.
+    pub rate_hz: f64,
+}
+
+#[derive(Serialize)]
+struct JsonPose {
+    lat_deg: f64,
+    lon_deg: f64,
+    alt_amsl_m: Option<f64>,
+    roll_deg: f64,
+    pitch_deg: f64,
+    yaw_deg: f64,
+    time_boot_ms: Option<u32>,
+}
+
+/// One datagram for `telemetry`, or `None` without a position fix. Missing
+/// attitude is sent as level, and a missing AMSL altitude as 0 ft to
+/// FlightGear.
+pub fn encode_pose(telemetry: &Telemetry, format: PoseFormat) -> Option<Vec<u8>> {
+    let (Some(lat_deg), Some(lon_deg)) = (telemetry.latitude_deg, telemetry.longitude_deg) else {
+        return None;
+    };
+    let roll_deg = telemetry.roll_deg.unwrap_or(0.0);
+    let pitch_deg = telemetry.pitch_deg.unwrap_or(0.0);
+    let yaw_deg = telemetry.yaw_deg.or(telemetry.heading_deg).unwrap_or(0.0);
+    match format {
+        PoseFormat::Json => serde_json::to_vec(&JsonPose {
+            lat_deg,
+            lon_deg,
+            alt_amsl_m: telemetry.altitude_amsl_m,
+            roll_deg,
+            pitch_deg,
+            yaw_deg,
+            time_boot_ms: telemetry.time_boot_ms,
+        })
+        .ok(),
+        PoseFormat::FlightGear => {
+            let alt_ft = telemetry.altitude_amsl_m.unwrap_or(0.0) * FEET_PER_METER;
+            let line = format!(
+                "{lat_deg:.7},{lon_deg:.7},{alt_ft:.1},{roll_deg:.2},{pitch_deg:.2},{:.2}\n",
+                yaw_deg.rem_euclid(360.0)
+            );
+            Some(line.into_bytes())
+        }
+    }
+}
+
+/// A running pose stream; stops when dropped.
+pub struct PoseOutput {
+    task: AbortHandle,
+}
+
+impl PoseOutput {
+    /// Resolve `options.target` and start streaming `vehicle`'s pose to it.
+    /// The stream ends by itself when the vehicle disconnects.
+    pub async fn start(
+        vehicle: &Vehicle,
+        options: &PoseOutputOptions,
+    ) -> Result<Self, VehicleError> {
+        let valid_rate = options.rate_hz > 0.0 && options.rate_hz <= MAX_RATE_HZ;
+        if !valid_rate {
+            return Err(VehicleError::PoseOutput(format!(
+                "rate must be above 0 and at most {MAX_RATE_HZ} Hz"
+            )));
+        }
+        let target = tokio::net::lookup_host(&options.target)
+            .await?
+            .next()
+            .ok_or_else(|| {
+                VehicleError::PoseOutput(format!("{} does not resolve", options.target))
+            })?;
+        let bind = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(bind).await?;
+
+        let mut telemetry = vehicle.telemetry();
+        let format = options.format;
+        let period = Duration::from_secs_f64(1.0 / options.rate_hz);
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                if telemetry.has_changed().is_err() {
+                    break;
+                }
+                let Some(datagram) = encode_pose(&telemetry.borrow_and_update(), format) else {
+                    continue;
+                };
+                if let Err(err) = socket.send_to(&datagram, target).await {
+                    warn!("pose output to {target} failed: {err}");
+                }
+            }
+        });
+        Ok(Self {
+            task: task.abort_handle(),
+        })
+    }
+
+    /// False once the vehicle has disconnected.
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl Drop for PoseOutput {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_a_position_fix() {
+        let telemetry = Telemetry {
+            altitude_amsl_m: Some(120.0),
+            ..Telemetry::default()
+        };
+        assert_eq!(encode_pose(&telemetry, PoseFormat::Json), None);
+    }
+
+    #[test]
+    fn flightgear_line_is_in_feet_and_wrapped_degrees() {
+        let telemetry = Telemetry {
+            latitude_deg: Some(47.3977419),
+            longitude_deg: Some(8.5455938),
+            altitude_amsl_m: Some(100.0),
+            roll_deg: Some(-5.0),
+            pitch_deg: Some(2.5),
+            yaw_deg: Some(-90.0),
+            ..Telemetry::default()
+        };
+        let line = encode_pose(&telemetry, PoseFormat::FlightGear).unwrap();
+        assert_eq!(
+            String::from_utf8(line).unwrap(),
+            "47.3977419,8.5455938,328.1,-5.00,2.50,270.00\n"
+        );
+
+        let json = encode_pose(&telemetry, PoseFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["alt_amsl_m"], 100.0);
+        assert_eq!(value["yaw_deg"], -90.0);
+    }
+}
@@ -0,0 +1,238 @@
+//! Measuring how often each message actually arrives.
+//!
+//! A HUD that stutters on one radio but not another is usually down to
+//! telemetry arriving late or in bursts, or to stream rates the autopilot
+//! never applied. `Vehicle::benchmark_rates` listens to the autopilot for a
+//! window and reports, per message type, the rate, the jitter of the
+//! intervals between arrivals and the longest gap. Rates requested with
+//! SET_MESSAGE_INTERVAL can be passed in to check they are honored.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// How far a measured rate may fall from the requested one and still count
+/// as honored, as a fraction of the requested rate.
+pub const RATE_TOLERANCE: f64 = 0.2;
+/// An interval this many times the expected one counts as a gap.
+const GAP_FACTOR: f64 = 2.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateBenchmarkOptions {
+    /// How long to listen.
+    pub window_ms: u64,
+    /// Rates asked for with SET_MESSAGE_INTERVAL, by message name (e.g.
+    /// `ATTITUDE`).
+    #[serde(default)]
+    pub expected_hz: BTreeMap<String, f64>,
+}
+
+/// Arrivals of one message type over the window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageRate {
+    pub message: String,
+    pub count: u32,
+    pub rate_hz: f64,
+    /// Standard deviation of the intervals between arrivals; `None` with
+    /// fewer than three arrivals.
+    pub jitter_ms: Option<f64>,
+    /// Longest time without this message, counting from the start and to
+    /// the end of the window.
+    pub max_gap_ms: f64,
+    /// Intervals over twice the expected one (or the mean interval, when no
+    /// rate was requested).
+    pub gaps: u32,
+    pub expected_hz: Option<f64>,
+    /// Whether the rate is within `RATE_TOLERANCE` of `expected_hz`.
+    pub honored: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateReport {
+    pub window_ms: u64,
+    /// Messages from the autopilot over the window.
+    pub total: u32,
+    /// By message name; requested messages that never arrived are included
+    /// with a count of 0.
+    pub messages: Vec<MessageRate>,
+}
+
+impl RateReport {
+    /// Requested rates the autopilot did not keep to.
+    pub fn not_honored(&self) -> impl Iterator<Item = &MessageRate> {
+        self.messages
+            .iter()
+            .filter(|rate| rate.honored == Some(false))
+    }
+}
+
+/// Arrival times per message name.
+#[derive(Debug, Clone)]
+pub(crate) struct RateMeter {
+    start: Instant,
+    arrivals: HashMap<&'static str, Vec<Instant>>,
+}
+
+impl RateMeter {
+    pub(crate) fn new(start: Instant) -> Self {
+        Self {
+            start,
+            arrivals: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, message: &'static str, now: Instant) {
+        self.arrivals.entry(message).or_default().push(now);
+    }
+
+    pub(crate) fn report(&self, end: Instant, options: &RateBenchmarkOptions) -> RateReport {
+        let window = end.saturating_duration_since(self.start);
+        let expected_hz: BTreeMap<String, f64> = options
+            .expected_hz
+            .iter()
+            .map(|(name, hz)| (name.to_ascii_uppercase(), *hz))
+            .collect();
+        let mut messages: BTreeMap<String, MessageRate> = self
+            .arrivals
+            .iter()
+            .map(|(name, arrivals)| {
+                let expected = expected_hz.get(*name).copied();
+                (
+                    name.to_string(),
+                    self.measure(name, arrivals, end, window, expected),
+                )
+            })
+            .collect();
+        for (name, expected) in expected_hz {
+            if !messages.contains_key(&name) {
+                let missing = self.measure(&name, &[], end, window, Some(expected));
+                messages.insert(name, missing);
+            }
+        }
+        RateReport {
+            window_ms: window.as_millis() as u64,
+            total: self.arrivals.values().map(|a| a.len() as u32).sum(),
+            messages: messages.into_values().collect(),
+        }
+    }
+
+    fn measure(
+        &self,
+        name: &str,
+        arrivals: &[Instant],
+        end: Instant,
+        window: Duration,
+        expected_hz: Option<f64>,
+    ) -> MessageRate {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let intervals: Vec<f64> = arrivals
+            .windows(2)
+            .map(|pair| ms(pair[1].saturating_duration_since(pair[0])))
+            .collect();
+        let mean =
+            (!intervals.is_empty()).then(|| intervals.iter().sum::<f64>() / intervals.len() as f64);
+        let jitter_ms = (intervals.len() >= 2).then(|| {
+            let mean = mean.unwrap_or_default();
+            let variance =
+                intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
+            variance.sqrt()
+        });
+
+        let first_gap = arrivals
+            .first()
+            .map_or(window, |first| first.saturating_duration_since(self.start));
+        let last_gap = arrivals
+            .last()
+            .map_or(Duration::ZERO, |last| end.saturating_duration_since(*last));
+        let max_gap_ms = intervals
+            .iter()
+            .copied()
+            .fold(ms(first_gap).max(ms(last_gap)), f64::max);
+
+        let gap_threshold = expected_hz
+            .filter(|hz| *hz > 0.0)
+            .map(|hz| 1000.0 / hz)
+            .or(mean)
+            .map(|interval| interval * GAP_FACTOR);
+        let gaps = gap_threshold.map_or(0, |threshold| {
+            intervals.iter().filter(|i| **i > threshold).count() as u32
+        });
+
+        let seconds = window.as_secs_f64();
+        let rate_hz = if seconds > 0.0 {
+            arrivals.len() as f64 / seconds
+        } else {
+            0.0
+        };
+        let honored = expected_hz
+            .map(|expected| (rate_hz - expected).abs() <= expected.abs() * RATE_TOLERANCE);
+        MessageRate {
+            message: name.to_string(),
+            count: arrivals.len() as u32,
+            rate_hz,
+            jitter_ms,
+            max_gap_ms,
+            gaps,
+            expected_hz,
+            honored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(expected: &[(&str, f64)]) -> RateBenchmarkOptions {
+        RateBenchmarkOptions {
+            window_ms: 1000,
+            expected_hz: expected
+                .iter()
+                .map(|(name, hz)| (name.to_string(), *hz))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn steady_stream_is_honored() {
+        let start = Instant::now();
+        let mut meter = RateMeter::new(start);
+        for i in 0..10 {
+            meter.record("ATTITUDE", start + Duration::from_millis(50 + 100 * i));
+        }
+        let report = meter.report(
+            start + Duration::from_secs(1),
+            &options(&[("ATTITUDE", 10.0)]),
+        );
+        let attitude = &report.messages[0];
+        assert_eq!(attitude.count, 10);
+        assert!((attitude.rate_hz - 10.0).abs() < 1e-9);
+        assert!(attitude.jitter_ms.unwrap() < 1e-6);
+        assert!((attitude.max_gap_ms - 100.0).abs() < 1e-6);
+        assert_eq!(attitude.gaps, 0);
+        assert_eq!(attitude.honored, Some(true));
+        assert_eq!(report.not_honored().count(), 0);
+    }
+
+    #[test]
+    fn bursts_and_missing_streams_are_reported() {
+        let start = Instant::now();
+        let mut meter = RateMeter::new(start);
+        // Four messages in a burst, then silence
+        for i in 0..4 {
+            meter.record("VFR_HUD", start + Duration::from_millis(10 * i));
+        }
+        let report = meter.report(
+            start + Duration::from_secs(1),
+            &options(&[("VFR_HUD", 4.0), ("GPS_RAW_INT", 2.0)]),
+        );
+        assert_eq!(report.total, 4);
+        let gps = &report.messages[0];
+        assert_eq!(gps.message, "GPS_RAW_INT");
+        assert_eq!((gps.count, gps.honored), (0, Some(false)));
+        let hud = &report.messages[1];
+        assert_eq!(hud.honored, Some(true));
+        assert!((hud.max_gap_ms - 970.0).abs() < 1e-6);
+        assert_eq!(report.not_honored().count(), 1);
+    }
+}
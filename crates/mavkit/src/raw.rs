@@ -0,0 +1,109 @@
+//! Building arbitrary MAVLink messages from JSON, for the raw message console.
+//!
+//! Messages use the serde representation generated from the dialect
+//! definitions: an object with a `type` tag holding the message name and one
+//! key per field.
+
+use crate::error::VehicleError;
+use mavlink::ardupilotmega::MavMessage;
+use mavlink::Message;
+use serde_json::{Map, Value};
+
+fn default_message(message_name: &str) -> Result<MavMessage, VehicleError> {
+    let name = message_name.trim().to_ascii_uppercase();
+    MavMessage::message_id_from_name(&name)
+        .and_then(MavMessage::default_message_from_id)
+        .ok_or_else(|| VehicleError::InvalidMessage(format!("unknown message '{message_name}'")))
+}
+
+fn to_object(message: &MavMessage) -> Result<Map<String, Value>, VehicleError> {
+    match serde_json::to_value(message) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err(VehicleError::InvalidMessage(
+            "message did not serialize to an object".to_string(),
+        )),
+        Err(err) => Err(VehicleError::InvalidMessage(err.to_string())),
+    }
+}
+
+/// JSON object with every field of `message_name` set to its default.
+pub fn raw_message_template(message_name: &str) -> Result<String, VehicleError> {
+    let template = to_object(&default_message(message_name)?)?;
+    serde_json::to_string_pretty(&template)
+        .map_err(|err| VehicleError::InvalidMessage(err.to_string()))
+}
+
+/// Build `message_name` from a JSON object of fields. Fields not given keep
+/// their default value.
+pub fn build_raw_message(
+    message_name: &str,
+    fields_json: &str,
+) -> Result<MavMessage, VehicleError> {
+    let default = default_message(message_name)?;
+    let mut object = to_object(&default)?;
+
+    let fields: Value = serde_json::from_str(fields_json)
+        .map_err(|err| VehicleError::InvalidMessage(format!("fields: {err}")))?;
+    let Value::Object(fields) = fields else {
+        return Err(VehicleError::InvalidMessage(
+            "fields must be a JSON object".to_string(),
+        ));
+    };
+    for (key, value) in fields {
+        if key == "type" {
+            continue;
+        }
+        if !object.contains_key(&key) {
+            return Err(VehicleError::InvalidMessage(format!(
+                "{} has no field '{key}'",
+                default.message_name()
+            )));
+        }
+        object.insert(key, value);
+    }
+
+    serde_json::from_value(Value::Object(object))
+        .map_err(|err| VehicleError::InvalidMessage(err.to_string()))
+}
+
+/// Serialize a received message for display in the console.
+pub fn raw_message_to_json(message: &MavMessage) -> Result<String, VehicleError> {
+    serde_json::to_string_pretty(message)
+        .map_err(|err| VehicleError::InvalidMessage(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_lists_all_fields() {
+        let template: Value =
+            serde_json::from_str(&raw_message_template("heartbeat").unwrap()).unwrap();
+        assert_eq!(template["type"], "HEARTBEAT");
+        assert!(template.get("custom_mode").is_some());
+        assert!(template.get("mavlink_version").is_some());
+    }
+
+    #[test]
+    fn builds_message_with_partial_fields() {
+        let msg = build_raw_message(
+            "COMMAND_LONG",
+            r#"{"target_system": 1, "target_component": 1, "param1": 242.0}"#,
+        )
+        .unwrap();
+        let MavMessage::COMMAND_LONG(data) = msg else {
+            panic!("expected COMMAND_LONG");
+        };
+        assert_eq!(data.target_system, 1);
+        assert_eq!(data.param1, 242.0);
+        assert_eq!(data.param2, 0.0);
+    }
+
+    #[test]
+    fn rejects_unknown_message_and_field() {
+        assert!(build_raw_message("NOT_A_MESSAGE", "{}").is_err());
+        assert!(build_raw_message("HEARTBEAT", r#"{"bogus": 1}"#).is_err());
+        assert!(build_raw_message("HEARTBEAT", "[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,333 @@
+//! Remote ID (ASTM F3411 / ASD-STAN prEN 4709-002) through the
+//! OPEN_DRONE_ID_* messages.
+//!
+//! The vehicle's Remote ID transponder, or the autopilot on its behalf,
+//! reports what it broadcasts; `merge_remote_id` folds those messages into a
+//! `RemoteIdState`. Some of the broadcast has to come from the ground
+//! station: the operator ID and the free-text self-ID. Those are set in
+//! `VehicleConfig::remote_id` and sent every `REMOTE_ID_PERIOD`.
+
+use crate::error::VehicleError;
+use mavlink::ardupilotmega::{self as common, MavOdidArmStatus, MavOdidIdType, MavOdidStatus};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How often the operator messages are repeated; transponders treat them as
+/// stale after a few seconds.
+pub(crate) const REMOTE_ID_PERIOD: Duration = Duration::from_secs(1);
+/// OPEN_DRONE_ID_OPERATOR_ID.operator_id capacity.
+pub const OPERATOR_ID_MAX_LEN: usize = 20;
+/// OPEN_DRONE_ID_SELF_ID.description capacity.
+pub const SELF_ID_MAX_LEN: usize = 23;
+
+// Invalid/unknown markers of OPEN_DRONE_ID_LOCATION
+const ALTITUDE_UNKNOWN_M: f32 = -1000.0;
+const DIRECTION_UNKNOWN_CDEG: u16 = 36100;
+const SPEED_UNKNOWN_CMS: u16 = 25500;
+const VERTICAL_SPEED_UNKNOWN_CMS: i16 = 6300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UasIdType {
+    None,
+    SerialNumber,
+    CaaRegistration,
+    UtmAssigned,
+    SpecificSession,
+}
+
+/// One of the (up to two) IDs the vehicle broadcasts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UasId {
+    pub id_type: UasIdType,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteIdStatus {
+    Undeclared,
+    Ground,
+    Airborne,
+    Emergency,
+    /// The transponder reports a failure of the Remote ID system itself.
+    SystemFailure,
+}
+
+/// Position as broadcast, which is what observers on the ground see.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RemoteIdLocation {
+    pub status: RemoteIdStatus,
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    pub altitude_geodetic_m: Option<f32>,
+    pub height_m: Option<f32>,
+    pub direction_deg: Option<f32>,
+    pub speed_horizontal_mps: Option<f32>,
+    pub speed_vertical_mps: Option<f32>,
+}
+
+/// What the vehicle's Remote ID reports, from OPEN_DRONE_ID_* messages.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RemoteIdState {
+    pub uas_ids: Vec<UasId>,
+    pub location: Option<RemoteIdLocation>,
+    /// Operator position the transponder broadcasts.
+    pub operator_latitude_deg: Option<f64>,
+    pub operator_longitude_deg: Option<f64>,
+    pub operator_id: Option<String>,
+    pub self_id: Option<String>,
+    /// From OPEN_DRONE_ID_ARM_STATUS; `None` until one arrives.
+    pub ready_to_arm: Option<bool>,
+    /// Why the transponder blocks arming.
+    pub arm_error: Option<String>,
+}
+
+/// The part of the broadcast supplied by the ground station. Empty fields
+/// are not sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteIdOperator {
+    /// Operator registration number issued by the civil aviation authority.
+    pub operator_id: String,
+    /// Free-text purpose of the flight, e.g. "Survey".
+    pub self_id: String,
+}
+
+impl RemoteIdOperator {
+    /// Both fields are ASCII and fit their message fields.
+    pub fn validate(&self) -> Result<(), VehicleError> {
+        for (name, value, max) in [
+            ("operator ID", &self.operator_id, OPERATOR_ID_MAX_LEN),
+            ("self-ID", &self.self_id, SELF_ID_MAX_LEN),
+        ] {
+            if !value.is_ascii() {
+                return Err(VehicleError::InvalidRemoteId(format!(
+                    "{name} must be ASCII"
+                )));
+            }
+            if value.len() > max {
+                return Err(VehicleError::InvalidRemoteId(format!(
+                    "{name} is longer than {max} characters"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Messages to send, addressed to every component of `target_system`.
+    pub(crate) fn messages(&self, target_system: u8) -> Vec<common::MavMessage> {
+        let mut messages = Vec::new();
+        if !self.operator_id.is_empty() {
+            messages.push(common::MavMessage::OPEN_DRONE_ID_OPERATOR_ID(
+                common::OPEN_DRONE_ID_OPERATOR_ID_DATA {
+                    target_system,
+                    target_component: 0,
+                    operator_id_type: common::MavOdidOperatorIdType::MAV_ODID_OPERATOR_ID_TYPE_CAA,
+                    operator_id: self.operator_id.as_str().into(),
+                    ..Default::default()
+                },
+            ));
+        }
+        if !self.self_id.is_empty() {
+            messages.push(common::MavMessage::OPEN_DRONE_ID_SELF_ID(
+                common::OPEN_DRONE_ID_SELF_ID_DATA {
+                    target_system,
+                    target_component: 0,
+                    description_type: common::MavOdidDescType::MAV_ODID_DESC_TYPE_TEXT,
+                    description: self.self_id.as_str().into(),
+                    ..Default::default()
+                },
+            ));
+        }
+        messages
+    }
+}
+
+/// Fold an OPEN_DRONE_ID_* message into `state`. Returns whether it was
+/// one; other messages leave `state` unchanged.
+pub(crate) fn merge_remote_id(state: &mut RemoteIdState, message: &common::MavMessage) -> bool {
+    match message {
+        common::MavMessage::OPEN_DRONE_ID_BASIC_ID(data) => {
+            let id = UasId {
+                id_type: uas_id_type(data.id_type),
+                id: text(&data.uas_id),
+            };
+            match state.uas_ids.iter_mut().find(|u| u.id_type == id.id_type) {
+                Some(existing) => *existing = id,
+                None => state.uas_ids.push(id),
+            }
+        }
+        common::MavMessage::OPEN_DRONE_ID_LOCATION(data) => {
+            let altitude = |m: f32| (m != ALTITUDE_UNKNOWN_M).then_some(m);
+            state.location = Some(RemoteIdLocation {
+                status: remote_id_status(data.status),
+                latitude_deg: data.latitude as f64 / 1e7,
+                longitude_deg: data.longitude as f64 / 1e7,
+                altitude_geodetic_m: altitude(data.altitude_geodetic),
+                height_m: altitude(data.height),
+                direction_deg: (data.direction != DIRECTION_UNKNOWN_CDEG)
+                    .then_some(data.direction as f32 / 100.0),
+                speed_horizontal_mps: (data.speed_horizontal != SPEED_UNKNOWN_CMS)
+                    .then_some(data.speed_horizontal as f32 / 100.0),
+                speed_vertical_mps: (data.speed_vertical != VERTICAL_SPEED_UNKNOWN_CMS)
+                    .then_some(data.speed_vertical as f32 / 100.0),
+            });
+        }
+        common::MavMessage::OPEN_DRONE_ID_SYSTEM(data) => {
+            let known = data.operator_latitude != 0 || data.operator_longitude != 0;
+            state.operator_latitude_deg = known.then_some(data.operator_latitude as f64 / 1e7);
+            state.operator_longitude_deg = known.then_some(data.operator_longitude as f64 / 1e7);
+        }
+        common::MavMessage::OPEN_DRONE_ID_OPERATOR_ID(data) => {
+            state.operator_id = non_empty(data.operator_id.to_str().unwrap_or(""));
+        }
+        common::MavMessage::OPEN_DRONE_ID_SELF_ID(data) => {
+            state.self_id = non_empty(data.description.to_str().unwrap_or(""));
+        }
+        common::MavMessage::OPEN_DRONE_ID_ARM_STATUS(data) => {
+            let ready = data.status == MavOdidArmStatus::MAV_ODID_ARM_STATUS_GOOD_TO_ARM;
+            state.ready_to_arm = Some(ready);
+            state.arm_error = if ready {
+                None
+            } else {
+                non_empty(data.error.to_str().unwrap_or(""))
+            };
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn uas_id_type(id_type: MavOdidIdType) -> UasIdType {
+    match id_type {
+        MavOdidIdType::MAV_ODID_ID_TYPE_SERIAL_NUMBER => UasIdType::SerialNumber,
+        MavOdidIdType::MAV_ODID_ID_TYPE_CAA_REGISTRATION_ID => UasIdType::CaaRegistration,
+        MavOdidIdType::MAV_ODID_ID_TYPE_UTM_ASSIGNED_UUID => UasIdType::UtmAssigned,
+        MavOdidIdType::MAV_ODID_ID_TYPE_SPECIFIC_SESSION_ID => UasIdType::SpecificSession,
+        _ => UasIdType::None,
+    }
+}
+
+fn remote_id_status(status: MavOdidStatus) -> RemoteIdStatus {
+    match status {
+        MavOdidStatus::MAV_ODID_STATUS_GROUND => RemoteIdStatus::Ground,
+        MavOdidStatus::MAV_ODID_STATUS_AIRBORNE => RemoteIdStatus::Airborne,
+        MavOdidStatus::MAV_ODID_STATUS_EMERGENCY => RemoteIdStatus::Emergency,
+        MavOdidStatus::MAV_ODID_STATUS_REMOTE_ID_SYSTEM_FAILURE => RemoteIdStatus::SystemFailure,
+        _ => RemoteIdStatus::Undeclared,
+    }
+}
+
+/// A NUL-padded byte field as text.
+fn text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn padded<const N: usize>(s: &str) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn merges_ids_location_and_arm_status() {
+        let mut state = RemoteIdState::default();
+        for (id_type, id) in [
+            (MavOdidIdType::MAV_ODID_ID_TYPE_SERIAL_NUMBER, "1581F5FJC2"),
+            (
+                MavOdidIdType::MAV_ODID_ID_TYPE_CAA_REGISTRATION_ID,
+                "FIN87astrdge12k8",
+            ),
+            (MavOdidIdType::MAV_ODID_ID_TYPE_SERIAL_NUMBER, "1581F5FJC3"),
+        ] {
+            let message =
+                common::MavMessage::OPEN_DRONE_ID_BASIC_ID(common::OPEN_DRONE_ID_BASIC_ID_DATA {
+                    id_type,
+                    uas_id: padded(id),
+                    ..Default::default()
+                });
+            assert!(merge_remote_id(&mut state, &message));
+        }
+        assert_eq!(state.uas_ids.len(), 2);
+        assert_eq!(state.uas_ids[0].id, "1581F5FJC3");
+
+        let location =
+            common::MavMessage::OPEN_DRONE_ID_LOCATION(common::OPEN_DRONE_ID_LOCATION_DATA {
+                status: MavOdidStatus::MAV_ODID_STATUS_AIRBORNE,
+                latitude: 603_000_000,
+                longitude: 247_000_000,
+                altitude_geodetic: 120.5,
+                height: ALTITUDE_UNKNOWN_M,
+                direction: DIRECTION_UNKNOWN_CDEG,
+                speed_horizontal: 550,
+                speed_vertical: -120,
+                ..Default::default()
+            });
+        merge_remote_id(&mut state, &location);
+        let location = state.location.unwrap();
+        assert_eq!(location.status, RemoteIdStatus::Airborne);
+        assert!((location.latitude_deg - 60.3).abs() < 1e-9);
+        assert_eq!(location.altitude_geodetic_m, Some(120.5));
+        assert_eq!(location.height_m, None);
+        assert_eq!(location.direction_deg, None);
+        assert_eq!(location.speed_horizontal_mps, Some(5.5));
+        assert_eq!(location.speed_vertical_mps, Some(-1.2));
+
+        let arm =
+            common::MavMessage::OPEN_DRONE_ID_ARM_STATUS(common::OPEN_DRONE_ID_ARM_STATUS_DATA {
+                status: MavOdidArmStatus::MAV_ODID_ARM_STATUS_PRE_ARM_FAIL_GENERIC,
+                error: "operator ID missing".into(),
+            });
+        merge_remote_id(&mut state, &arm);
+        assert_eq!(state.ready_to_arm, Some(false));
+        assert_eq!(state.arm_error.as_deref(), Some("operator ID missing"));
+
+        let heartbeat = common::MavMessage::HEARTBEAT(common::HEARTBEAT_DATA::default());
+        assert!(!merge_remote_id(&mut state, &heartbeat));
+    }
+
+    #[test]
+    fn operator_sends_only_filled_in_fields() {
+        let operator = RemoteIdOperator {
+            operator_id: "FIN87astrdge12k8".into(),
+            self_id: String::new(),
+        };
+        operator.validate().unwrap();
+        let messages = operator.messages(1);
+        assert_eq!(messages.len(), 1);
+        let common::MavMessage::OPEN_DRONE_ID_OPERATOR_ID(data) = &messages[0] else {
+            panic!("expected OPERATOR_ID, got {:?}", messages[0]);
+        };
+        assert_eq!(data.target_system, 1);
+        assert_eq!(data.operator_id.to_str().unwrap(), "FIN87astrdge12k8");
+        assert!(RemoteIdOperator::default().messages(1).is_empty());
+    }
+
+    #[test]
+    fn operator_fields_must_fit() {
+        let long = RemoteIdOperator {
+            self_id: "Powerline inspection, sector 7".into(),
+            ..RemoteIdOperator::default()
+        };
+        assert!(matches!(
+            long.validate(),
+            Err(VehicleError::InvalidRemoteId(_))
+        ));
+        let unicode = RemoteIdOperator {
+            operator_id: "FIN-ÄÖ".into(),
+            ..RemoteIdOperator::default()
+        };
+        assert!(unicode.validate().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Return-to-me: keep the guided target on the operator's moving position.
+//!
+//! The application feeds the GCS position (phone GPS, boat NMEA...) through
+//! `Vehicle::update_gcs_position`. While enabled, the vehicle is flown in
+//! GUIDED towards the latest fix, re-targeted whenever the operator has moved
+//! far enough. If no fresh fix arrives within `stale_after_ms`, following stops
+//! and the configured failsafe action is taken.
+
+use crate::mission::geo::distance_m;
+use crate::state::Telemetry;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleAction {
+    /// Hold at the vehicle's current position.
+    Hold,
+    /// Switch to RTL.
+    Rtl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReturnToMeOptions {
+    /// Guided altitude relative to home.
+    pub altitude_m: f32,
+    /// How often the target is re-evaluated.
+    pub update_interval_ms: u64,
+    /// The operator position counts as lost after this long without a fix.
+    pub stale_after_ms: u64,
+    pub on_stale: StaleAction,
+    /// Re-target only when the operator has moved at least this far.
+    pub min_move_m: f64,
+}
+
+impl Default for ReturnToMeOptions {
+    fn default() -> Self {
+        Self {
+            altitude_m: 30.0,
+            update_interval_ms: 1000,
+            stale_after_ms: 5000,
+            on_stale: StaleAction::Hold,
+            min_move_m: 3.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnToMeState {
+    #[default]
+    Off,
+    Following,
+    /// Stopped because the operator position went stale.
+    PositionLost,
+}
+
+/// An operator position fix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct GcsFix {
+    pub lat_deg: f64,
+    pub lon_deg: f64,
+    pub received: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Step {
+    Goto { lat_deg: f64, lon_deg: f64 },
+    Wait,
+    Failsafe,
+}
+
+impl GcsFix {
+    /// Whether the fix is recent enough to follow.
+    pub(crate) fn is_fresh(&self, options: &ReturnToMeOptions, now: Instant) -> bool {
+        now.saturating_duration_since(self.received) < Duration::from_millis(options.stale_after_ms)
+    }
+}
+
+/// Decide what to do on one tick, given the target last sent.
+pub(crate) fn next_step(
+    fix: Option<&GcsFix>,
+    last_target: Option<(f64, f64)>,
+    options: &ReturnToMeOptions,
+    now: Instant,
+) -> Step {
+    let Some(fix) = fix.filter(|fix| fix.is_fresh(options, now)) else {
+        return Step::Failsafe;
+    };
+    let moved = last_target.is_none_or(|(lat, lon)| {
+        distance_m(lat, lon, fix.lat_deg, fix.lon_deg) >= options.min_move_m
+    });
+    if moved {
+        Step::Goto {
+            lat_deg: fix.lat_deg,
+            lon_deg: fix.lon_deg,
+        }
+    } else {
+        Step::Wait
+    }
+}
+
+/// Hold target for `StaleAction::Hold`: where the vehicle is now.
+pub(crate) fn hold_position(
+    telemetry: &Telemetry,
+    options: &ReturnToMeOptions,
+) -> Option<(f64, f64, f32)> {
+    let alt = telemetry
+        .altitude_m
+        .map(|alt| alt as f32)
+        .unwrap_or(options.altitude_m);
+    Some((telemetry.latitude_deg?, telemetry.longitude_deg?, alt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(lat_deg: f64, received: Instant) -> GcsFix {
+        GcsFix {
+            lat_deg,
+            lon_deg: 8.0,
+            received,
+        }
+    }
+
+    #[test]
+    fn follows_the_operator_once_moved_far_enough() {
+        let now = Instant::now();
+        let options = ReturnToMeOptions::default();
+        let first = next_step(Some(&fix(47.0, now)), None, &options, now);
+        assert_eq!(
+            first,
+            Step::Goto {
+                lat_deg: 47.0,
+                lon_deg: 8.0
+            }
+        );
+        // ~1 m: not worth a new target
+        let small = next_step(Some(&fix(47.00001, now)), Some((47.0, 8.0)), &options, now);
+        assert_eq!(small, Step::Wait);
+        // ~11 m
+        let large = next_step(Some(&fix(47.0001, now)), Some((47.0, 8.0)), &options, now);
+        assert!(matches!(large, Step::Goto { .. }));
+    }
+
+    #[test]
+    fn stale_or_missing_fix_triggers_failsafe() {
+        let now = Instant::now();
+        let options = ReturnToMeOptions::default();
+        assert_eq!(next_step(None, None, &options, now), Step::Failsafe);
+        let old = fix(47.0, now);
+        let later = now + Duration::from_millis(options.stale_after_ms);
+        assert_eq!(
+            next_step(Some(&old), Some((47.0, 8.0)), &options, later),
+            Step::Failsafe
+        );
+    }
+
+    #[test]
+    fn hold_uses_current_position_and_altitude() {
+        let options = ReturnToMeOptions::default();
+        let telemetry = Telemetry {
+            latitude_deg: Some(47.1),
+            longitude_deg: Some(8.1),
+            altitude_m: Some(42.0),
+            ..Telemetry::default()
+        };
+        assert_eq!(hold_position(&telemetry, &options), Some((47.1, 8.1, 42.0)));
+        assert_eq!(hold_position(&Telemetry::default(), &options), None);
+    }
+}
@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Outgoing message classes, highest priority first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SendPriority {
+    /// Arm/disarm, mode changes, guided targets, manual control.
+    Control,
+    Heartbeat,
+    /// Mission and parameter transfer.
+    Transfer,
+    Bulk,
+}
+
+impl SendPriority {
+    const ALL: [SendPriority; 4] = [
+        SendPriority::Control,
+        SendPriority::Heartbeat,
+        SendPriority::Transfer,
+        SendPriority::Bulk,
+    ];
+
+    fn slot(self) -> usize {
+        self as usize
+    }
+}
+
+/// Outgoing bandwidth budget for a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LinkPacing {
+    /// Maximum sustained send rate; `None` sends as fast as the link accepts.
+    pub bytes_per_sec: Option<u32>,
+    /// Bytes that may be sent back-to-back before pacing kicks in.
+    pub burst_bytes: u32,
+}
+
+impl LinkPacing {
+    pub const UNLIMITED: LinkPacing = LinkPacing {
+        bytes_per_sec: None,
+        burst_bytes: 0,
+    };
+
+    /// Pacing for a serial radio: half the raw byte rate (8N1), leaving room
+    /// for the vehicle's own telemetry on half-duplex links.
+    pub fn serial(baud: u32) -> Self {
+        let bytes_per_sec = (baud / 10 / 2).max(1);
+        LinkPacing {
+            bytes_per_sec: Some(bytes_per_sec),
+            burst_bytes: (bytes_per_sec / 10).max(280),
+        }
+    }
+}
+
+/// FIFO per priority class; always drains higher classes first.
+#[derive(Debug)]
+pub(crate) struct PriorityQueue<T> {
+    queues: [VecDeque<T>; 4],
+}
+
+impl<T> Default for PriorityQueue<T> {
+    fn default() -> Self {
+        Self {
+            queues: Default::default(),
+        }
+    }
+}
+
+impl<T> PriorityQueue<T> {
+    pub(crate) fn push(&mut self, priority: SendPriority, item: T) {
+        self.queues[priority.slot()].push_back(item);
+    }
+
+    pub(crate) fn peek(&self) -> Option<&T> {
+        self.queues.iter().find_map(|q| q.front())
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        SendPriority::ALL
+            .iter()
+            .find_map(|p| self.queues[p.slot()].pop_front())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+}
+
+/// Token bucket measured in bytes.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    pacing: LinkPacing,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(pacing: LinkPacing, now: Instant) -> Self {
+        Self {
+            pacing,
+            tokens: pacing.burst_bytes as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if let Some(rate) = self.pacing.bytes_per_sec {
+            let elapsed = now
+                .saturating_duration_since(self.last_refill)
+                .as_secs_f64();
+            let capacity = self.pacing.burst_bytes.max(1) as f64;
+            self.tokens = (self.tokens + elapsed * rate as f64).min(capacity);
+        }
+        self.last_refill = now;
+    }
+
+    /// Time to wait before `bytes` may be sent; zero when they can go now.
+    pub(crate) fn delay_for(&mut self, bytes: usize, now: Instant) -> Duration {
+        let Some(rate) = self.pacing.bytes_per_sec else {
+            return Duration::ZERO;
+        };
+        self.refill(now);
+        // A message larger than the burst goes out once the bucket is full
+        let needed = (bytes as f64).min(self.pacing.burst_bytes.max(1) as f64);
+        if self.tokens >= needed {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((needed - self.tokens) / rate as f64)
+        }
+    }
+
+    /// Switch to `pacing`, keeping the tokens saved so far up to the new
+    /// burst. Leaving unlimited pacing starts with a full bucket.
+    pub(crate) fn set_pacing(&mut self, pacing: LinkPacing, now: Instant) {
+        self.refill(now);
+        let burst = pacing.burst_bytes as f64;
+        self.tokens = match self.pacing.bytes_per_sec {
+            Some(_) => self.tokens.min(burst),
+            None => burst,
+        };
+        self.pacing = pacing;
+    }
+
+    pub(crate) fn consume(&mut self, bytes: usize, now: Instant) {
+        if self.pacing.bytes_per_sec.is_some() {
+            self.refill(now);
+            self.tokens -= bytes as f64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_priority_drains_first() {
+        let mut queue = PriorityQueue::default();
+        queue.push(SendPriority::Bulk, "bulk");
+        queue.push(SendPriority::Transfer, "item1");
+        queue.push(SendPriority::Transfer, "item2");
+        queue.push(SendPriority::Control, "arm");
+        queue.push(SendPriority::Heartbeat, "hb");
+
+        assert_eq!(queue.peek(), Some(&"arm"));
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(order, vec!["arm", "hb", "item1", "item2", "bulk"]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn unlimited_pacing_never_delays() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(LinkPacing::UNLIMITED, now);
+        bucket.consume(10_000, now);
+        assert_eq!(bucket.delay_for(10_000, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn pacing_delays_after_burst() {
+        let now = Instant::now();
+        let pacing = LinkPacing {
+            bytes_per_sec: Some(1000),
+            burst_bytes: 100,
+        };
+        let mut bucket = TokenBucket::new(pacing, now);
+        assert_eq!(bucket.delay_for(100, now), Duration::ZERO);
+        bucket.consume(100, now);
+
+        let delay = bucket.delay_for(50, now);
+        assert!((delay.as_secs_f64() - 0.05).abs() < 1e-6, "got {delay:?}");
+
+        let later = now + Duration::from_millis(50);
+        assert_eq!(bucket.delay_for(50, later), Duration::ZERO);
+    }
+
+    #[test]
+    fn pacing_can_change_on_a_live_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(LinkPacing::UNLIMITED, now);
+        bucket.set_pacing(
+            LinkPacing {
+                bytes_per_sec: Some(1000),
+                burst_bytes: 100,
+            },
+            now,
+        );
+        bucket.consume(100, now);
+        assert!(bucket.delay_for(50, now) > Duration::ZERO);
+
+        bucket.set_pacing(LinkPacing::UNLIMITED, now);
+        assert_eq!(bucket.delay_for(50, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn serial_pacing_scales_with_baud() {
+        let slow = LinkPacing::serial(57_600);
+        assert_eq!(slow.bytes_per_sec, Some(2880));
+        assert_eq!(slow.burst_bytes, 288);
+        assert!(LinkPacing::serial(921_600).bytes_per_sec > slow.bytes_per_sec);
+    }
+}
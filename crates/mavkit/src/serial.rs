@@ -0,0 +1,194 @@
+//! Serial transport with configurable line settings.
+//!
+//! mavlink's own `serial:` connection always opens the port 8N1 without flow
+//! control and leaves DTR/RTS at the driver default. Radios that need RTS/CTS,
+//! or boards that reset when DTR toggles, are opened here instead. The port is
+//! blocking, so reads run on a dedicated blocking task and each write is
+//! handed to the blocking pool; `Link` drives both like any other connection.
+
+use crate::error::VehicleError;
+use crate::link::Inbound;
+use mavlink::ardupilotmega::MavMessage;
+use mavlink::error::MessageReadError;
+use mavlink::peek_reader::PeekReader;
+use mavlink::MavHeader;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+/// How long a blocking read or write waits before the reader checks for
+/// shutdown. Also bounds how long a write blocks on a deasserted CTS.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowControl {
+    #[default]
+    None,
+    /// XON/XOFF.
+    Software,
+    /// RTS/CTS.
+    Hardware,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Parity {
+    #[default]
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopBits {
+    #[default]
+    One,
+    Two,
+}
+
+/// Line settings applied when the port is opened. The default matches
+/// mavlink's `serial:` connection (8N1, no flow control).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerialOptions {
+    pub flow_control: FlowControl,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    /// Level to drive DTR to after opening; `None` leaves the driver default.
+    /// Some boards reset while DTR is asserted.
+    pub dtr: Option<bool>,
+    /// Level to drive RTS to after opening. Ignored by most drivers while
+    /// hardware flow control is enabled.
+    pub rts: Option<bool>,
+}
+
+/// An open serial port, before it is split between the link tasks.
+pub(crate) struct SerialTransport {
+    reader: Box<dyn serialport::SerialPort>,
+    writer: PortWriter,
+}
+
+/// Open `port` at `baud` with `options`, then set DTR and RTS.
+pub(crate) fn open(
+    port: &str,
+    baud: u32,
+    options: &SerialOptions,
+) -> Result<SerialTransport, VehicleError> {
+    let failed = |err: serialport::Error| VehicleError::ConnectionFailed(format!("{port}: {err}"));
+    let mut reader = serialport::new(port, baud)
+        .flow_control(match options.flow_control {
+            FlowControl::None => serialport::FlowControl::None,
+            FlowControl::Software => serialport::FlowControl::Software,
+            FlowControl::Hardware => serialport::FlowControl::Hardware,
+        })
+        .parity(match options.parity {
+            Parity::None => serialport::Parity::None,
+            Parity::Odd => serialport::Parity::Odd,
+            Parity::Even => serialport::Parity::Even,
+        })
+        .stop_bits(match options.stop_bits {
+            StopBits::One => serialport::StopBits::One,
+            StopBits::Two => serialport::StopBits::Two,
+        })
+        .timeout(POLL_INTERVAL)
+        .open()
+        .map_err(failed)?;
+    if let Some(level) = options.dtr {
+        reader.write_data_terminal_ready(level).map_err(failed)?;
+    }
+    if let Some(level) = options.rts {
+        reader.write_request_to_send(level).map_err(failed)?;
+    }
+    let writer = reader.try_clone().map_err(failed)?;
+    Ok(SerialTransport {
+        reader,
+        writer: PortWriter(Arc::new(Mutex::new(writer))),
+    })
+}
+
+impl SerialTransport {
+    pub(crate) fn split(self) -> (Box<dyn serialport::SerialPort>, PortWriter) {
+        (self.reader, self.writer)
+    }
+}
+
+/// Read MAVLink v2 frames from `port` until `stop` fires, every receiver is
+/// gone, or the port fails. Runs on a blocking thread.
+pub(crate) fn read_loop(
+    port: Box<dyn serialport::SerialPort>,
+    tx: broadcast::Sender<Inbound>,
+    stop: CancellationToken,
+) {
+    let mut reader = PeekReader::new(port);
+    while !stop.is_cancelled() {
+        let inbound = match mavlink::read_v2_msg::<MavMessage, _>(&mut reader) {
+            Ok(received) => Ok(Arc::new(received)),
+            Err(MessageReadError::Io(err)) if err.kind() == io::ErrorKind::TimedOut => {
+                continue;
+            }
+            // A corrupt frame; the next read resynchronizes on the magic byte
+            Err(MessageReadError::Parse(_)) => continue,
+            Err(err) => Err(err.to_string()),
+        };
+        let failed = inbound.is_err();
+        if tx.send(inbound).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Write half of a serial port, shared by the sender task.
+#[derive(Clone)]
+pub(crate) struct PortWriter(Arc<Mutex<Box<dyn serialport::SerialPort>>>);
+
+impl PortWriter {
+    pub(crate) async fn send(
+        &self,
+        header: &MavHeader,
+        message: &MavMessage,
+    ) -> Result<(), String> {
+        let port = self.0.clone();
+        let (header, message) = (*header, message.clone());
+        tokio::task::spawn_blocking(move || {
+            let mut port = port
+                .lock()
+                .map_err(|_| "serial port lock poisoned".to_string())?;
+            mavlink::write_v2_msg(&mut *port, header, &message)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        })
+        .await
+        .map_err(|err| err.to_string())?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_default_to_8n1_without_flow_control() {
+        let options: SerialOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options, SerialOptions::default());
+        assert_eq!(options.flow_control, FlowControl::None);
+        assert_eq!(options.dtr, None);
+    }
+
+    #[test]
+    fn options_deserialize_snake_case() {
+        let options: SerialOptions = serde_json::from_str(
+            r#"{"flow_control":"hardware","parity":"even","stop_bits":"two","dtr":false}"#,
+        )
+        .unwrap();
+        assert_eq!(options.flow_control, FlowControl::Hardware);
+        assert_eq!(options.parity, Parity::Even);
+        assert_eq!(options.stop_bits, StopBits::Two);
+        assert_eq!(options.dtr, Some(false));
+        assert_eq!(options.rts, None);
+    }
+}
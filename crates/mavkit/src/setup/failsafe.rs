@@ -0,0 +1,707 @@
+//! Failsafe configuration.
+//!
+//! Battery, RC-loss and GCS-loss failsafes are spread over a dozen
+//! firmware-specific parameters. `FailsafeConfig` names them once; the tables
+//! here map each field to its parameter and each action to its value.
+//!
+//! Some firmwares switch a failsafe on with one parameter and pick its
+//! action with another (Plane, Rover), and some share an action or timeout
+//! between RC and GCS loss. Writes that would set a shared parameter to two
+//! different values are rejected rather than silently picking one.
+
+use super::frame::{ardupilot_firmware, ArduFirmware};
+use crate::error::VehicleError;
+use crate::params::ParamStore;
+use crate::state::{AutopilotType, VehicleType};
+use serde::{Deserialize, Serialize};
+use FailsafeAction::*;
+
+/// What the vehicle does when a failsafe triggers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailsafeAction {
+    /// No action beyond a warning, or the failsafe switched off.
+    Disabled,
+    Hold,
+    Rtl,
+    /// SmartRTL, falling back to RTL.
+    SmartRtl,
+    Land,
+    ContinueMission,
+    Terminate,
+    /// A firmware value without a name here; kept so it survives a
+    /// read-modify-write.
+    Other(i32),
+}
+
+/// Failsafe settings. `None` fields are not available on the connected
+/// firmware, or left unchanged when writing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FailsafeConfig {
+    pub battery_low_action: Option<FailsafeAction>,
+    pub battery_low_voltage: Option<f32>,
+    /// Remaining capacity (ArduPilot).
+    pub battery_low_mah: Option<f32>,
+    /// Remaining charge (PX4).
+    pub battery_low_percent: Option<f32>,
+    pub battery_critical_action: Option<FailsafeAction>,
+    pub battery_critical_voltage: Option<f32>,
+    pub battery_critical_mah: Option<f32>,
+    pub battery_critical_percent: Option<f32>,
+    pub rc_loss_action: Option<FailsafeAction>,
+    pub rc_loss_timeout_s: Option<f32>,
+    pub gcs_loss_action: Option<FailsafeAction>,
+    pub gcs_loss_timeout_s: Option<f32>,
+}
+
+/// Actions the connected firmware offers for each failsafe; empty when the
+/// failsafe cannot be configured.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FailsafeOptions {
+    pub battery_low: Vec<FailsafeAction>,
+    pub battery_critical: Vec<FailsafeAction>,
+    pub rc_loss: Vec<FailsafeAction>,
+    pub gcs_loss: Vec<FailsafeAction>,
+}
+
+/// Parameter selecting a failsafe's action.
+struct ActionParam {
+    name: &'static str,
+    /// Parameter switching the failsafe on (with the value written to enable
+    /// it) where that is separate from the action; off is 0.
+    enable: Option<(&'static str, f32)>,
+    actions: &'static [(FailsafeAction, f32)],
+}
+
+/// Parameter holding a value field; the field is the parameter times
+/// `scale`.
+struct ValueParam {
+    name: &'static str,
+    scale: f32,
+}
+
+const fn value(name: &'static str) -> Option<ValueParam> {
+    Some(ValueParam { name, scale: 1.0 })
+}
+
+#[derive(Default)]
+struct FailsafeParams {
+    battery_low_action: Option<ActionParam>,
+    battery_low_voltage: Option<ValueParam>,
+    battery_low_mah: Option<ValueParam>,
+    battery_low_percent: Option<ValueParam>,
+    battery_critical_action: Option<ActionParam>,
+    battery_critical_voltage: Option<ValueParam>,
+    battery_critical_mah: Option<ValueParam>,
+    battery_critical_percent: Option<ValueParam>,
+    rc_loss_action: Option<ActionParam>,
+    rc_loss_timeout: Option<ValueParam>,
+    gcs_loss_action: Option<ActionParam>,
+    gcs_loss_timeout: Option<ValueParam>,
+}
+
+const COPTER_BATTERY: &[(FailsafeAction, f32)] = &[
+    (Disabled, 0.0),
+    (Land, 1.0),
+    (Rtl, 2.0),
+    (SmartRtl, 3.0),
+    (Terminate, 5.0),
+];
+const COPTER_RC: &[(FailsafeAction, f32)] = &[
+    (Disabled, 0.0),
+    (Rtl, 1.0),
+    (ContinueMission, 2.0),
+    (Land, 3.0),
+    (SmartRtl, 4.0),
+];
+const COPTER_GCS: &[(FailsafeAction, f32)] = &[
+    (Disabled, 0.0),
+    (Rtl, 1.0),
+    (ContinueMission, 2.0),
+    (SmartRtl, 3.0),
+    (Land, 5.0),
+];
+const PLANE_BATTERY: &[(FailsafeAction, f32)] =
+    &[(Disabled, 0.0), (Rtl, 1.0), (Land, 2.0), (Terminate, 3.0)];
+/// `FS_LONG_ACTN`; "continue" returns home unless flying a mission.
+const PLANE_LONG: &[(FailsafeAction, f32)] = &[(ContinueMission, 0.0), (Rtl, 1.0)];
+const ROVER_BATTERY: &[(FailsafeAction, f32)] = &[
+    (Disabled, 0.0),
+    (Rtl, 1.0),
+    (Hold, 2.0),
+    (SmartRtl, 3.0),
+    (Terminate, 5.0),
+];
+const ROVER_ACTION: &[(FailsafeAction, f32)] =
+    &[(Disabled, 0.0), (Rtl, 1.0), (Hold, 2.0), (SmartRtl, 3.0)];
+/// `COM_LOW_BAT_ACT`. 0 only warns; 3 returns at critical charge and lands at
+/// emergency. The old "return" value 1 is gone from current releases.
+const PX4_BATTERY: &[(FailsafeAction, f32)] = &[(Disabled, 0.0), (Land, 2.0), (Rtl, 3.0)];
+const PX4_LINK: &[(FailsafeAction, f32)] = &[
+    (Disabled, 0.0),
+    (Hold, 1.0),
+    (Rtl, 2.0),
+    (Land, 3.0),
+    (Terminate, 5.0),
+];
+
+fn ardupilot_battery(actions: &'static [(FailsafeAction, f32)]) -> FailsafeParams {
+    FailsafeParams {
+        battery_low_action: Some(ActionParam {
+            name: "BATT_FS_LOW_ACT",
+            enable: None,
+            actions,
+        }),
+        battery_low_voltage: value("BATT_LOW_VOLT"),
+        battery_low_mah: value("BATT_LOW_MAH"),
+        battery_critical_action: Some(ActionParam {
+            name: "BATT_FS_CRT_ACT",
+            enable: None,
+            actions,
+        }),
+        battery_critical_voltage: value("BATT_CRT_VOLT"),
+        battery_critical_mah: value("BATT_CRT_MAH"),
+        ..FailsafeParams::default()
+    }
+}
+
+fn failsafe_params(
+    autopilot: AutopilotType,
+    vehicle_type: VehicleType,
+    store: &ParamStore,
+) -> Option<FailsafeParams> {
+    let params = match autopilot {
+        AutopilotType::ArduPilotMega => match ardupilot_firmware(vehicle_type, store)? {
+            ArduFirmware::Copter => FailsafeParams {
+                rc_loss_action: Some(ActionParam {
+                    name: "FS_THR_ENABLE",
+                    enable: None,
+                    actions: COPTER_RC,
+                }),
+                rc_loss_timeout: value("RC_FS_TIMEOUT"),
+                gcs_loss_action: Some(ActionParam {
+                    name: "FS_GCS_ENABLE",
+                    enable: None,
+                    actions: COPTER_GCS,
+                }),
+                gcs_loss_timeout: value("FS_GCS_TIMEOUT"),
+                ..ardupilot_battery(COPTER_BATTERY)
+            },
+            ArduFirmware::Plane => FailsafeParams {
+                rc_loss_action: Some(ActionParam {
+                    name: "FS_LONG_ACTN",
+                    enable: Some(("THR_FAILSAFE", 1.0)),
+                    actions: PLANE_LONG,
+                }),
+                rc_loss_timeout: value("FS_LONG_TIMEOUT"),
+                gcs_loss_action: Some(ActionParam {
+                    name: "FS_LONG_ACTN",
+                    enable: Some(("FS_GCS_ENABL", 1.0)),
+                    actions: PLANE_LONG,
+                }),
+                gcs_loss_timeout: value("FS_LONG_TIMEOUT"),
+                ..ardupilot_battery(PLANE_BATTERY)
+            },
+            ArduFirmware::Rover => FailsafeParams {
+                rc_loss_action: Some(ActionParam {
+                    name: "FS_ACTION",
+                    enable: Some(("FS_THR_ENABLE", 1.0)),
+                    actions: ROVER_ACTION,
+                }),
+                rc_loss_timeout: value("FS_TIMEOUT"),
+                gcs_loss_action: Some(ActionParam {
+                    name: "FS_ACTION",
+                    enable: Some(("FS_GCS_ENABLE", 1.0)),
+                    actions: ROVER_ACTION,
+                }),
+                gcs_loss_timeout: value("FS_TIMEOUT"),
+                ..ardupilot_battery(ROVER_BATTERY)
+            },
+        },
+        AutopilotType::Px4 => FailsafeParams {
+            battery_low_action: Some(ActionParam {
+                name: "COM_LOW_BAT_ACT",
+                enable: None,
+                actions: PX4_BATTERY,
+            }),
+            // Fractions of full charge on the vehicle
+            battery_low_percent: Some(ValueParam {
+                name: "BAT_LOW_THR",
+                scale: 100.0,
+            }),
+            battery_critical_percent: Some(ValueParam {
+                name: "BAT_CRIT_THR",
+                scale: 100.0,
+            }),
+            rc_loss_action: Some(ActionParam {
+                name: "NAV_RCL_ACT",
+                enable: None,
+                actions: PX4_LINK,
+            }),
+            rc_loss_timeout: value("COM_RC_LOSS_T"),
+            gcs_loss_action: Some(ActionParam {
+                name: "NAV_DLL_ACT",
+                enable: None,
+                actions: PX4_LINK,
+            }),
+            gcs_loss_timeout: value("COM_DL_LOSS_T"),
+            ..FailsafeParams::default()
+        },
+        AutopilotType::Generic | AutopilotType::Unknown => return None,
+    };
+    Some(params)
+}
+
+fn stored(store: &ParamStore, name: &str) -> Option<f32> {
+    store.params.get(name).map(|param| param.value)
+}
+
+fn read_action(store: &ParamStore, param: &Option<ActionParam>) -> Option<FailsafeAction> {
+    let param = param.as_ref()?;
+    if let Some((enable, _)) = param.enable {
+        if stored(store, enable)? == 0.0 {
+            return Some(Disabled);
+        }
+    }
+    let raw = stored(store, param.name)?;
+    let action = param
+        .actions
+        .iter()
+        .find(|(_, value)| *value == raw)
+        .map_or(Other(raw as i32), |(action, _)| *action);
+    Some(action)
+}
+
+fn read_value(store: &ParamStore, param: &Option<ValueParam>) -> Option<f32> {
+    let param = param.as_ref()?;
+    stored(store, param.name).map(|value| value * param.scale)
+}
+
+fn action_options(store: &ParamStore, param: &Option<ActionParam>) -> Vec<FailsafeAction> {
+    let Some(param) = param else {
+        return Vec::new();
+    };
+    let present = store.params.contains_key(param.name)
+        && param
+            .enable
+            .is_none_or(|(enable, _)| store.params.contains_key(enable));
+    if !present {
+        return Vec::new();
+    }
+    let mut actions: Vec<_> = param.actions.iter().map(|(action, _)| *action).collect();
+    if param.enable.is_some() && !actions.contains(&Disabled) {
+        actions.insert(0, Disabled);
+    }
+    actions
+}
+
+/// Failsafe settings as currently stored on the vehicle.
+pub fn failsafe_config(
+    autopilot: AutopilotType,
+    vehicle_type: VehicleType,
+    store: &ParamStore,
+) -> FailsafeConfig {
+    let Some(params) = failsafe_params(autopilot, vehicle_type, store) else {
+        return FailsafeConfig::default();
+    };
+    FailsafeConfig {
+        battery_low_action: read_action(store, &params.battery_low_action),
+        battery_low_voltage: read_value(store, &params.battery_low_voltage),
+        battery_low_mah: read_value(store, &params.battery_low_mah),
+        battery_low_percent: read_value(store, &params.battery_low_percent),
+        battery_critical_action: read_action(store, &params.battery_critical_action),
+        battery_critical_voltage: read_value(store, &params.battery_critical_voltage),
+        battery_critical_mah: read_value(store, &params.battery_critical_mah),
+        battery_critical_percent: read_value(store, &params.battery_critical_percent),
+        rc_loss_action: read_action(store, &params.rc_loss_action),
+        rc_loss_timeout_s: read_value(store, &params.rc_loss_timeout),
+        gcs_loss_action: read_action(store, &params.gcs_loss_action),
+        gcs_loss_timeout_s: read_value(store, &params.gcs_loss_timeout),
+    }
+}
+
+/// Actions offered by the connected firmware for each failsafe.
+pub fn failsafe_options(
+    autopilot: AutopilotType,
+    vehicle_type: VehicleType,
+    store: &ParamStore,
+) -> FailsafeOptions {
+    let Some(params) = failsafe_params(autopilot, vehicle_type, store) else {
+        return FailsafeOptions::default();
+    };
+    FailsafeOptions {
+        battery_low: action_options(store, &params.battery_low_action),
+        battery_critical: action_options(store, &params.battery_critical_action),
+        rc_loss: action_options(store, &params.rc_loss_action),
+        gcs_loss: action_options(store, &params.gcs_loss_action),
+    }
+}
+
+/// Parameter writes collected for one config, checked for conflicts.
+struct Writes<'a> {
+    store: &'a ParamStore,
+    writes: Vec<(&'static str, f32, &'static str)>,
+}
+
+impl Writes<'_> {
+    fn push(
+        &mut self,
+        field: &'static str,
+        name: &'static str,
+        value: f32,
+    ) -> Result<(), VehicleError> {
+        if !self.store.params.contains_key(name) {
+            return Err(invalid(format!("{field}: {name} is not on this vehicle")));
+        }
+        match self
+            .writes
+            .iter()
+            .find(|(existing, _, _)| *existing == name)
+        {
+            Some((_, existing, _)) if (existing - value).abs() < 1e-4 => Ok(()),
+            Some((_, _, other)) => Err(invalid(format!(
+                "{field} and {other} share {name} on this vehicle and must agree"
+            ))),
+            None => {
+                self.writes.push((name, value, field));
+                Ok(())
+            }
+        }
+    }
+
+    fn action(
+        &mut self,
+        field: &'static str,
+        param: &Option<ActionParam>,
+        action: Option<FailsafeAction>,
+    ) -> Result<(), VehicleError> {
+        let Some(action) = action else {
+            return Ok(());
+        };
+        let param = param
+            .as_ref()
+            .ok_or_else(|| invalid(format!("{field} is not configurable on this vehicle")))?;
+        if let (Some((enable, _)), Disabled) = (param.enable, action) {
+            return self.push(field, enable, 0.0);
+        }
+        let value = match action {
+            Other(raw) => raw as f32,
+            _ => param
+                .actions
+                .iter()
+                .find(|(candidate, _)| *candidate == action)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "{field}: {action:?} is not available on this vehicle"
+                    ))
+                })?,
+        };
+        if let Some((enable, on)) = param.enable {
+            self.push(field, enable, on)?;
+        }
+        self.push(field, param.name, value)
+    }
+
+    fn value(
+        &mut self,
+        field: &'static str,
+        param: &Option<ValueParam>,
+        value: Option<f32>,
+    ) -> Result<(), VehicleError> {
+        let Some(value) = value else {
+            return Ok(());
+        };
+        let param = param
+            .as_ref()
+            .ok_or_else(|| invalid(format!("{field} is not configurable on this vehicle")))?;
+        self.push(field, param.name, value / param.scale)
+    }
+}
+
+fn invalid(message: String) -> VehicleError {
+    VehicleError::InvalidFailsafe(message)
+}
+
+/// Check `config` against itself and the firmware, returning the parameter
+/// writes needed to apply it: only the values that differ from `store`.
+pub fn failsafe_writes(
+    autopilot: AutopilotType,
+    vehicle_type: VehicleType,
+    store: &ParamStore,
+    config: &FailsafeConfig,
+) -> Result<Vec<(&'static str, f32)>, VehicleError> {
+    validate(config)?;
+    let params = failsafe_params(autopilot, vehicle_type, store)
+        .ok_or_else(|| invalid("failsafe parameters are not known for this vehicle".into()))?;
+    let mut writes = Writes {
+        store,
+        writes: Vec::new(),
+    };
+    let c = config;
+    let p = &params;
+    writes.action(
+        "battery_low_action",
+        &p.battery_low_action,
+        c.battery_low_action,
+    )?;
+    writes.value(
+        "battery_low_voltage",
+        &p.battery_low_voltage,
+        c.battery_low_voltage,
+    )?;
+    writes.value("battery_low_mah", &p.battery_low_mah, c.battery_low_mah)?;
+    writes.value(
+        "battery_low_percent",
+        &p.battery_low_percent,
+        c.battery_low_percent,
+    )?;
+    writes.action(
+        "battery_critical_action",
+        &p.battery_critical_action,
+        c.battery_critical_action,
+    )?;
+    writes.value(
+        "battery_critical_voltage",
+        &p.battery_critical_voltage,
+        c.battery_critical_voltage,
+    )?;
+    writes.value(
+        "battery_critical_mah",
+        &p.battery_critical_mah,
+        c.battery_critical_mah,
+    )?;
+    writes.value(
+        "battery_critical_percent",
+        &p.battery_critical_percent,
+        c.battery_critical_percent,
+    )?;
+    writes.action("rc_loss_action", &p.rc_loss_action, c.rc_loss_action)?;
+    writes.value("rc_loss_timeout_s", &p.rc_loss_timeout, c.rc_loss_timeout_s)?;
+    writes.action("gcs_loss_action", &p.gcs_loss_action, c.gcs_loss_action)?;
+    writes.value(
+        "gcs_loss_timeout_s",
+        &p.gcs_loss_timeout,
+        c.gcs_loss_timeout_s,
+    )?;
+
+    Ok(writes
+        .writes
+        .into_iter()
+        .filter(|(name, value, _)| {
+            stored(store, name).is_none_or(|current| (current - value).abs() >= 1e-4)
+        })
+        .map(|(name, value, _)| (name, value))
+        .collect())
+}
+
+/// Firmware-independent checks. Thresholds of 0 disable that trigger.
+fn validate(c: &FailsafeConfig) -> Result<(), VehicleError> {
+    let non_negative = [
+        ("battery_low_voltage", c.battery_low_voltage),
+        ("battery_low_mah", c.battery_low_mah),
+        ("battery_critical_voltage", c.battery_critical_voltage),
+        ("battery_critical_mah", c.battery_critical_mah),
+    ];
+    for (field, value) in non_negative {
+        if value.is_some_and(|v| v.is_nan() || v < 0.0) {
+            return Err(invalid(format!("{field} must not be negative")));
+        }
+    }
+    for (field, value) in [
+        ("battery_low_percent", c.battery_low_percent),
+        ("battery_critical_percent", c.battery_critical_percent),
+    ] {
+        if value.is_some_and(|v| !(0.0..=100.0).contains(&v)) {
+            return Err(invalid(format!("{field} must be between 0 and 100")));
+        }
+    }
+    for (field, value) in [
+        ("rc_loss_timeout_s", c.rc_loss_timeout_s),
+        ("gcs_loss_timeout_s", c.gcs_loss_timeout_s),
+    ] {
+        if value.is_some_and(|v| v.is_nan() || v <= 0.0) {
+            return Err(invalid(format!("{field} must be positive")));
+        }
+    }
+    let ordered = [
+        ("voltage", c.battery_low_voltage, c.battery_critical_voltage),
+        ("mah", c.battery_low_mah, c.battery_critical_mah),
+        ("percent", c.battery_low_percent, c.battery_critical_percent),
+    ];
+    for (unit, low, critical) in ordered {
+        if let (Some(low), Some(critical)) = (low, critical) {
+            if low > 0.0 && critical > 0.0 && critical >= low {
+                return Err(invalid(format!(
+                    "battery_critical_{unit} must be below battery_low_{unit}"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn copter() -> ParamStore {
+        ParamStore::from_values(&[
+            ("BATT_FS_LOW_ACT", 2.0),
+            ("BATT_LOW_VOLT", 10.5),
+            ("BATT_LOW_MAH", 0.0),
+            ("BATT_FS_CRT_ACT", 1.0),
+            ("BATT_CRT_VOLT", 10.0),
+            ("BATT_CRT_MAH", 0.0),
+            ("FS_THR_ENABLE", 1.0),
+            ("RC_FS_TIMEOUT", 1.0),
+            ("FS_GCS_ENABLE", 0.0),
+            ("FS_GCS_TIMEOUT", 5.0),
+        ])
+    }
+
+    #[test]
+    fn reads_copter_config() {
+        let config = failsafe_config(
+            AutopilotType::ArduPilotMega,
+            VehicleType::Quadrotor,
+            &copter(),
+        );
+        assert_eq!(config.battery_low_action, Some(Rtl));
+        assert_eq!(config.battery_critical_action, Some(Land));
+        assert_eq!(config.rc_loss_action, Some(Rtl));
+        assert_eq!(config.gcs_loss_action, Some(Disabled));
+        assert_eq!(config.gcs_loss_timeout_s, Some(5.0));
+        assert_eq!(config.battery_low_percent, None);
+    }
+
+    #[test]
+    fn unchanged_config_writes_nothing() {
+        let store = copter();
+        let config = failsafe_config(AutopilotType::ArduPilotMega, VehicleType::Quadrotor, &store);
+        let writes = failsafe_writes(
+            AutopilotType::ArduPilotMega,
+            VehicleType::Quadrotor,
+            &store,
+            &config,
+        )
+        .unwrap();
+        assert!(writes.is_empty());
+
+        let changed = FailsafeConfig {
+            gcs_loss_action: Some(SmartRtl),
+            ..config
+        };
+        let writes = failsafe_writes(
+            AutopilotType::ArduPilotMega,
+            VehicleType::Quadrotor,
+            &store,
+            &changed,
+        )
+        .unwrap();
+        assert_eq!(writes, vec![("FS_GCS_ENABLE", 3.0)]);
+    }
+
+    #[test]
+    fn rejects_unavailable_actions_and_inverted_thresholds() {
+        let store = copter();
+        let write = |config: FailsafeConfig| {
+            failsafe_writes(
+                AutopilotType::ArduPilotMega,
+                VehicleType::Quadrotor,
+                &store,
+                &config,
+            )
+        };
+        assert!(matches!(
+            write(FailsafeConfig {
+                rc_loss_action: Some(Hold),
+                ..FailsafeConfig::default()
+            }),
+            Err(VehicleError::InvalidFailsafe(_))
+        ));
+        assert!(write(FailsafeConfig {
+            battery_low_voltage: Some(10.0),
+            battery_critical_voltage: Some(10.5),
+            ..FailsafeConfig::default()
+        })
+        .is_err());
+        assert!(write(FailsafeConfig {
+            battery_low_percent: Some(20.0),
+            ..FailsafeConfig::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn plane_shares_long_action_between_rc_and_gcs() {
+        let store = ParamStore::from_values(&[
+            ("Q_ENABLE", 0.0),
+            ("THR_FAILSAFE", 1.0),
+            ("FS_GCS_ENABL", 0.0),
+            ("FS_LONG_ACTN", 0.0),
+            ("FS_LONG_TIMEOUT", 5.0),
+        ]);
+        let vehicle = (AutopilotType::ArduPilotMega, VehicleType::FixedWing);
+        let config = failsafe_config(vehicle.0, vehicle.1, &store);
+        assert_eq!(config.rc_loss_action, Some(ContinueMission));
+        assert_eq!(config.gcs_loss_action, Some(Disabled));
+
+        let conflicting = FailsafeConfig {
+            rc_loss_action: Some(Rtl),
+            gcs_loss_action: Some(ContinueMission),
+            ..FailsafeConfig::default()
+        };
+        assert!(failsafe_writes(vehicle.0, vehicle.1, &store, &conflicting).is_err());
+
+        let both = FailsafeConfig {
+            rc_loss_action: Some(Rtl),
+            gcs_loss_action: Some(Rtl),
+            ..FailsafeConfig::default()
+        };
+        assert_eq!(
+            failsafe_writes(vehicle.0, vehicle.1, &store, &both).unwrap(),
+            vec![("FS_LONG_ACTN", 1.0), ("FS_GCS_ENABL", 1.0)]
+        );
+    }
+
+    #[test]
+    fn px4_percentages_are_scaled() {
+        let store = ParamStore::from_values(&[
+            ("COM_LOW_BAT_ACT", 1.0),
+            ("BAT_LOW_THR", 0.15),
+            ("BAT_CRIT_THR", 0.07),
+        ]);
+        let config = failsafe_config(AutopilotType::Px4, VehicleType::Quadrotor, &store);
+        assert!((config.battery_low_percent.unwrap() - 15.0).abs() < 1e-4);
+        // Set by an older release; kept as is
+        assert_eq!(config.battery_low_action, Some(Other(1)));
+
+        let writes = failsafe_writes(
+            AutopilotType::Px4,
+            VehicleType::Quadrotor,
+            &store,
+            &FailsafeConfig {
+                battery_low_percent: Some(20.0),
+                ..FailsafeConfig::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].0, "BAT_LOW_THR");
+        assert!((writes[0].1 - 0.2).abs() < 1e-6);
+
+        let writes = failsafe_writes(
+            AutopilotType::Px4,
+            VehicleType::Quadrotor,
+            &store,
+            &FailsafeConfig {
+                battery_low_action: Some(Rtl),
+                ..FailsafeConfig::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(writes, vec![("COM_LOW_BAT_ACT", 3.0)]);
+    }
+}
@@ -0,0 +1,372 @@
+//! Airframe selection for initial setup.
+//!
+//! ArduPilot picks the frame with `FRAME_CLASS`/`FRAME_TYPE` (Copter, Rover)
+//! or `Q_ENABLE`/`Q_FRAME_CLASS`/`Q_FRAME_TYPE` (Plane). PX4 selects a whole
+//! airframe file with `SYS_AUTOSTART` and loads its defaults on the next boot
+//! when `SYS_AUTOCONFIG` is set. Either way the vehicle must be rebooted for
+//! the new frame to take effect.
+
+use crate::params::ParamStore;
+use crate::state::{AutopilotType, VehicleType};
+use serde::Serialize;
+
+/// Parameters that trigger an action instead of describing the frame; a
+/// vehicle resets them after acting, so they are ignored when matching.
+const APPLY_ONLY: &[&str] = &["SYS_AUTOCONFIG"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AirframeCategory {
+    Multirotor,
+    Helicopter,
+    Plane,
+    Vtol,
+    Rover,
+    Boat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PresetParam {
+    pub name: &'static str,
+    pub value: f32,
+}
+
+/// A named airframe and the parameters that select it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AirframePreset {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub category: AirframeCategory,
+    pub params: Vec<PresetParam>,
+}
+
+/// Frame-selecting parameters as currently set on the vehicle.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FrameParams {
+    pub frame_class: Option<f32>,
+    pub frame_type: Option<f32>,
+    /// PX4 airframe id.
+    pub sys_autostart: Option<f32>,
+    /// Id of the preset the parameters match, if any.
+    pub preset: Option<&'static str>,
+}
+
+/// ArduPilot firmware flavour; each uses different frame and failsafe
+/// parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ArduFirmware {
+    Copter,
+    Plane,
+    Rover,
+}
+
+type PresetRow = (
+    &'static str,
+    &'static str,
+    AirframeCategory,
+    &'static [(&'static str, f32)],
+);
+
+const COPTER_PRESETS: &[PresetRow] = &[
+    (
+        "copter_quad_x",
+        "Quad X",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 1.0), ("FRAME_TYPE", 1.0)],
+    ),
+    (
+        "copter_quad_plus",
+        "Quad +",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 1.0), ("FRAME_TYPE", 0.0)],
+    ),
+    (
+        "copter_quad_h",
+        "Quad H",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 1.0), ("FRAME_TYPE", 3.0)],
+    ),
+    (
+        "copter_hexa_x",
+        "Hexa X",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 2.0), ("FRAME_TYPE", 1.0)],
+    ),
+    (
+        "copter_hexa_plus",
+        "Hexa +",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 2.0), ("FRAME_TYPE", 0.0)],
+    ),
+    (
+        "copter_octa_x",
+        "Octa X",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 3.0), ("FRAME_TYPE", 1.0)],
+    ),
+    (
+        "copter_octa_plus",
+        "Octa +",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 3.0), ("FRAME_TYPE", 0.0)],
+    ),
+    (
+        "copter_octaquad_x",
+        "OctaQuad X",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 4.0), ("FRAME_TYPE", 1.0)],
+    ),
+    (
+        "copter_y6b",
+        "Y6B",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 5.0), ("FRAME_TYPE", 10.0)],
+    ),
+    (
+        "copter_tri",
+        "Tricopter",
+        AirframeCategory::Multirotor,
+        &[("FRAME_CLASS", 7.0)],
+    ),
+    (
+        "copter_heli",
+        "Traditional helicopter",
+        AirframeCategory::Helicopter,
+        &[("FRAME_CLASS", 6.0)],
+    ),
+];
+
+const PLANE_PRESETS: &[PresetRow] = &[
+    (
+        "plane",
+        "Fixed wing",
+        AirframeCategory::Plane,
+        &[("Q_ENABLE", 0.0)],
+    ),
+    (
+        "quadplane_quad_x",
+        "QuadPlane, Quad X lift",
+        AirframeCategory::Vtol,
+        &[
+            ("Q_ENABLE", 1.0),
+            ("Q_FRAME_CLASS", 1.0),
+            ("Q_FRAME_TYPE", 1.0),
+        ],
+    ),
+    (
+        "quadplane_quad_plus",
+        "QuadPlane, Quad + lift",
+        AirframeCategory::Vtol,
+        &[
+            ("Q_ENABLE", 1.0),
+            ("Q_FRAME_CLASS", 1.0),
+            ("Q_FRAME_TYPE", 0.0),
+        ],
+    ),
+];
+
+const ROVER_PRESETS: &[PresetRow] = &[
+    (
+        "rover",
+        "Rover",
+        AirframeCategory::Rover,
+        &[("FRAME_CLASS", 1.0)],
+    ),
+    (
+        "boat",
+        "Boat",
+        AirframeCategory::Boat,
+        &[("FRAME_CLASS", 2.0)],
+    ),
+    (
+        "balance_bot",
+        "Balance bot",
+        AirframeCategory::Rover,
+        &[("FRAME_CLASS", 3.0)],
+    ),
+];
+
+const PX4_PRESETS: &[PresetRow] = &[
+    (
+        "px4_quad_x",
+        "Generic Quadcopter X",
+        AirframeCategory::Multirotor,
+        &[("SYS_AUTOSTART", 4001.0), ("SYS_AUTOCONFIG", 1.0)],
+    ),
+    (
+        "px4_quad_plus",
+        "Generic Quadcopter +",
+        AirframeCategory::Multirotor,
+        &[("SYS_AUTOSTART", 5001.0), ("SYS_AUTOCONFIG", 1.0)],
+    ),
+    (
+        "px4_hexa_x",
+        "Generic Hexacopter X",
+        AirframeCategory::Multirotor,
+        &[("SYS_AUTOSTART", 6001.0), ("SYS_AUTOCONFIG", 1.0)],
+    ),
+    (
+        "px4_octa_x",
+        "Generic Octocopter X",
+        AirframeCategory::Multirotor,
+        &[("SYS_AUTOSTART", 8001.0), ("SYS_AUTOCONFIG", 1.0)],
+    ),
+    (
+        "px4_plane",
+        "Generic Standard Plane",
+        AirframeCategory::Plane,
+        &[("SYS_AUTOSTART", 2100.0), ("SYS_AUTOCONFIG", 1.0)],
+    ),
+    (
+        "px4_vtol_standard",
+        "Generic Standard VTOL",
+        AirframeCategory::Vtol,
+        &[("SYS_AUTOSTART", 13000.0), ("SYS_AUTOCONFIG", 1.0)],
+    ),
+    (
+        "px4_rover",
+        "Generic Ground Vehicle",
+        AirframeCategory::Rover,
+        &[("SYS_AUTOSTART", 50000.0), ("SYS_AUTOCONFIG", 1.0)],
+    ),
+];
+
+/// Presets that apply to the connected firmware. `store` helps tell ArduPilot
+/// firmwares apart when the heartbeat type is not conclusive (VTOL, boats).
+pub fn airframe_presets(
+    autopilot: AutopilotType,
+    vehicle_type: VehicleType,
+    store: &ParamStore,
+) -> Vec<AirframePreset> {
+    let rows = match autopilot {
+        AutopilotType::ArduPilotMega => match ardupilot_firmware(vehicle_type, store) {
+            Some(ArduFirmware::Copter) => COPTER_PRESETS,
+            Some(ArduFirmware::Plane) => PLANE_PRESETS,
+            Some(ArduFirmware::Rover) => ROVER_PRESETS,
+            None => &[],
+        },
+        AutopilotType::Px4 => PX4_PRESETS,
+        AutopilotType::Generic | AutopilotType::Unknown => &[],
+    };
+    rows.iter()
+        .map(|(id, name, category, params)| AirframePreset {
+            id,
+            name,
+            category: *category,
+            params: params
+                .iter()
+                .map(|(name, value)| PresetParam {
+                    name,
+                    value: *value,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Current frame parameters and the first of `presets` they match.
+pub fn frame_params(store: &ParamStore, presets: &[AirframePreset]) -> FrameParams {
+    let value = |name: &str| store.params.get(name).map(|param| param.value);
+    let preset = presets
+        .iter()
+        .find(|preset| {
+            preset
+                .params
+                .iter()
+                .filter(|param| !APPLY_ONLY.contains(&param.name))
+                .all(|param| value(param.name).is_some_and(|v| (v - param.value).abs() < 0.5))
+        })
+        .map(|preset| preset.id);
+    FrameParams {
+        frame_class: value("FRAME_CLASS"),
+        frame_type: value("FRAME_TYPE"),
+        sys_autostart: value("SYS_AUTOSTART"),
+        preset,
+    }
+}
+
+pub(super) fn ardupilot_firmware(
+    vehicle_type: VehicleType,
+    store: &ParamStore,
+) -> Option<ArduFirmware> {
+    match vehicle_type {
+        VehicleType::Quadrotor
+        | VehicleType::Hexarotor
+        | VehicleType::Octorotor
+        | VehicleType::Tricopter
+        | VehicleType::Helicopter
+        | VehicleType::Coaxial => Some(ArduFirmware::Copter),
+        VehicleType::FixedWing => Some(ArduFirmware::Plane),
+        VehicleType::GroundRover => Some(ArduFirmware::Rover),
+        VehicleType::Generic | VehicleType::Unknown => {
+            if store.params.contains_key("Q_ENABLE") {
+                Some(ArduFirmware::Plane)
+            } else if store.params.contains_key("CRUISE_SPEED") {
+                Some(ArduFirmware::Rover)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_follow_firmware() {
+        let empty = ParamStore::default();
+        let copter = airframe_presets(AutopilotType::ArduPilotMega, VehicleType::Quadrotor, &empty);
+        assert!(copter.iter().any(|preset| preset.id == "copter_hexa_x"));
+        let px4 = airframe_presets(AutopilotType::Px4, VehicleType::Quadrotor, &empty);
+        assert!(px4.iter().all(|preset| preset.id.starts_with("px4_")));
+        assert!(
+            airframe_presets(AutopilotType::Unknown, VehicleType::Quadrotor, &empty).is_empty()
+        );
+    }
+
+    #[test]
+    fn vtol_heartbeat_falls_back_to_params() {
+        let quadplane = ParamStore::from_values(&[("Q_ENABLE", 1.0)]);
+        let presets = airframe_presets(
+            AutopilotType::ArduPilotMega,
+            VehicleType::Unknown,
+            &quadplane,
+        );
+        assert_eq!(presets[0].id, "plane");
+    }
+
+    #[test]
+    fn frame_params_match_preset() {
+        let presets = airframe_presets(
+            AutopilotType::ArduPilotMega,
+            VehicleType::Hexarotor,
+            &ParamStore::default(),
+        );
+        let current = frame_params(
+            &ParamStore::from_values(&[("FRAME_CLASS", 2.0), ("FRAME_TYPE", 1.0)]),
+            &presets,
+        );
+        assert_eq!(current.preset, Some("copter_hexa_x"));
+        assert_eq!(current.frame_class, Some(2.0));
+        assert_eq!(
+            frame_params(&ParamStore::from_values(&[("FRAME_CLASS", 2.0)]), &presets).preset,
+            None
+        );
+    }
+
+    #[test]
+    fn px4_match_ignores_autoconfig() {
+        let presets = airframe_presets(
+            AutopilotType::Px4,
+            VehicleType::Quadrotor,
+            &ParamStore::default(),
+        );
+        let current = frame_params(
+            &ParamStore::from_values(&[("SYS_AUTOSTART", 4001.0), ("SYS_AUTOCONFIG", 0.0)]),
+            &presets,
+        );
+        assert_eq!(current.preset, Some("px4_quad_x"));
+    }
+}
@@ -0,0 +1,202 @@
+pub mod failsafe;
+pub mod frame;
+pub mod rc;
+pub mod sensors;
+
+pub use failsafe::{
+    failsafe_config, failsafe_options, failsafe_writes, FailsafeAction, FailsafeConfig,
+    FailsafeOptions,
+};
+pub use frame::{
+    airframe_presets, frame_params, AirframeCategory, AirframePreset, FrameParams, PresetParam,
+};
+pub use rc::{RcCalibration, RcCalibrationSession, RcChannelCalibration};
+pub use sensors::{
+    board_orientation_write, compass_orientation_write, compass_priority_writes, sensor_rotations,
+    sensor_setup, BusType, Compass, DeviceId, Imu, SensorRotation, SensorSetup,
+};
+
+use crate::error::VehicleError;
+use crate::params::Param;
+use crate::state::AutopilotType;
+use crate::Vehicle;
+use mavlink::ardupilotmega::MavCmd;
+
+/// Handle to initial-setup helpers (airframe selection, radio calibration,
+/// failsafes, sensor layout) on a `Vehicle`.
+///
+/// Reads use the parameter store, so parameters must have been downloaded
+/// first.
+pub struct SetupHandle<'a> {
+    vehicle: &'a Vehicle,
+}
+
+impl<'a> SetupHandle<'a> {
+    pub(crate) fn new(vehicle: &'a Vehicle) -> Self {
+        Self { vehicle }
+    }
+
+    /// Airframe presets for the connected autopilot and firmware.
+    pub fn airframe_presets(&self) -> Vec<AirframePreset> {
+        let state = self.vehicle.state().borrow().clone();
+        let store = self.vehicle.param_store().borrow().clone();
+        airframe_presets(state.autopilot, state.vehicle_type, &store)
+    }
+
+    /// Current frame parameters and the preset they match.
+    pub fn frame(&self) -> FrameParams {
+        let store = self.vehicle.param_store().borrow().clone();
+        frame_params(&store, &self.airframe_presets())
+    }
+
+    /// Write the parameters of preset `id`, checking each against the value
+    /// the vehicle reports back. Reboot the vehicle afterwards to apply.
+    pub async fn apply_airframe(&self, id: &str) -> Result<Vec<Param>, VehicleError> {
+        let preset = self
+            .airframe_presets()
+            .into_iter()
+            .find(|preset| preset.id == id)
+            .ok_or_else(|| VehicleError::UnknownPreset(id.to_string()))?;
+        let mut written = Vec::with_capacity(preset.params.len());
+        for param in &preset.params {
+            written.push(self.write_verified(param.name, param.value).await?);
+        }
+        Ok(written)
+    }
+
+    /// Set ArduPilot `FRAME_CLASS` and, when given, `FRAME_TYPE` directly.
+    pub async fn set_frame(
+        &self,
+        frame_class: u8,
+        frame_type: Option<u8>,
+    ) -> Result<Vec<Param>, VehicleError> {
+        let mut written = vec![
+            self.write_verified("FRAME_CLASS", frame_class.into())
+                .await?,
+        ];
+        if let Some(frame_type) = frame_type {
+            written.push(self.write_verified("FRAME_TYPE", frame_type.into()).await?);
+        }
+        Ok(written)
+    }
+
+    /// Failsafe settings as currently stored on the vehicle.
+    pub fn failsafe(&self) -> FailsafeConfig {
+        let state = self.vehicle.state().borrow().clone();
+        let store = self.vehicle.param_store().borrow().clone();
+        failsafe_config(state.autopilot, state.vehicle_type, &store)
+    }
+
+    /// Actions the connected firmware offers for each failsafe.
+    pub fn failsafe_options(&self) -> FailsafeOptions {
+        let state = self.vehicle.state().borrow().clone();
+        let store = self.vehicle.param_store().borrow().clone();
+        failsafe_options(state.autopilot, state.vehicle_type, &store)
+    }
+
+    /// Validate `config` and write the parameters that differ from the
+    /// vehicle's, verified. `None` fields are left as they are, so a config
+    /// read with `failsafe` can be edited and written back.
+    pub async fn set_failsafe(&self, config: &FailsafeConfig) -> Result<Vec<Param>, VehicleError> {
+        let state = self.vehicle.state().borrow().clone();
+        let store = self.vehicle.param_store().borrow().clone();
+        let writes = failsafe_writes(state.autopilot, state.vehicle_type, &store, config)?;
+        let mut written = Vec::with_capacity(writes.len());
+        for (name, value) in writes {
+            written.push(self.write_verified(name, value).await?);
+        }
+        Ok(written)
+    }
+
+    /// Compasses, IMUs and board orientation.
+    pub fn sensors(&self) -> SensorSetup {
+        let autopilot = self.vehicle.state().borrow().autopilot;
+        let store = self.vehicle.param_store().borrow().clone();
+        sensor_setup(autopilot, &store)
+    }
+
+    /// Make `dev_ids` the compass order, primary first. Reboot to apply.
+    pub async fn set_compass_priority(&self, dev_ids: &[u32]) -> Result<Vec<Param>, VehicleError> {
+        let autopilot = self.vehicle.state().borrow().autopilot;
+        let store = self.vehicle.param_store().borrow().clone();
+        let writes = compass_priority_writes(autopilot, &store, dev_ids)?;
+        let mut written = Vec::with_capacity(writes.len());
+        for (name, value) in writes {
+            written.push(self.write_verified(&name, value).await?);
+        }
+        Ok(written)
+    }
+
+    /// Set the orientation of the compass in calibration `slot`.
+    pub async fn set_compass_orientation(
+        &self,
+        slot: u8,
+        rotation: u8,
+    ) -> Result<Param, VehicleError> {
+        let autopilot = self.vehicle.state().borrow().autopilot;
+        let store = self.vehicle.param_store().borrow().clone();
+        let (name, value) = compass_orientation_write(autopilot, &store, slot, rotation)?;
+        self.write_verified(&name, value).await
+    }
+
+    /// Set the autopilot board's mounting orientation.
+    pub async fn set_board_orientation(&self, rotation: u8) -> Result<Param, VehicleError> {
+        let autopilot = self.vehicle.state().borrow().autopilot;
+        let (name, value) = board_orientation_write(autopilot, rotation)?;
+        self.write_verified(name, value).await
+    }
+
+    /// Calibrate compasses from a known heading instead of rotating the
+    /// vehicle ("large vehicle" calibration, ArduPilot only). The vehicle
+    /// needs a GPS fix to look up the expected field. `compass_mask` selects
+    /// compasses by slot bit; 0 means all.
+    pub async fn fixed_yaw_mag_cal(
+        &self,
+        yaw_deg: f32,
+        compass_mask: u8,
+    ) -> Result<(), VehicleError> {
+        if self.vehicle.state().borrow().autopilot != AutopilotType::ArduPilotMega {
+            return Err(VehicleError::CommandNotSupported(
+                "MAV_CMD_FIXED_MAG_CAL_YAW".into(),
+            ));
+        }
+        if !(0.0..360.0).contains(&yaw_deg) {
+            return Err(VehicleError::SensorSetup(format!(
+                "yaw {yaw_deg} is outside 0-360 degrees"
+            )));
+        }
+        // Latitude and longitude 0 use the vehicle's own position
+        self.vehicle
+            .command_long(
+                MavCmd::MAV_CMD_FIXED_MAG_CAL_YAW,
+                [yaw_deg, compass_mask.into(), 0.0, 0.0, 0.0, 0.0, 0.0],
+            )
+            .await
+    }
+
+    /// Start recording RC channel ranges. Move every stick and switch through
+    /// its full travel, capture trims, then finish the session and pass the
+    /// result to `write_rc_calibration`.
+    pub fn start_rc_calibration(&self) -> RcCalibrationSession {
+        RcCalibrationSession::start(self.vehicle.rc_channels())
+    }
+
+    /// Write `RCx_MIN`, `RCx_MAX` and `RCx_TRIM` for each channel, checking
+    /// each against the value the vehicle reports back.
+    pub async fn write_rc_calibration(
+        &self,
+        channels: &[RcChannelCalibration],
+    ) -> Result<Vec<Param>, VehicleError> {
+        let mut written = Vec::with_capacity(channels.len() * 3);
+        for channel in channels {
+            for (name, value) in channel.params() {
+                written.push(self.write_verified(&name, value).await?);
+            }
+        }
+        Ok(written)
+    }
+
+    async fn write_verified(&self, name: &str, value: f32) -> Result<Param, VehicleError> {
+        self.vehicle.params().write_verified(name, value).await
+    }
+}
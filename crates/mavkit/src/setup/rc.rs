@@ -0,0 +1,215 @@
+//! Radio (RC) calibration.
+//!
+//! While recording, the operator moves every stick and switch through its
+//! full travel; each channel's lowest and highest PWM become `RCx_MIN` and
+//! `RCx_MAX`. Trims are captured separately with the sticks centred (and the
+//! throttle low), then everything is written as `RCx_MIN/MAX/TRIM`, names
+//! shared by ArduPilot and PX4.
+
+use crate::error::VehicleError;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Channels that moved less than this (µs) are left uncalibrated.
+const MIN_TRAVEL_US: u16 = 200;
+
+/// Limits accepted by both autopilots.
+const PWM_MIN_US: u16 = 800;
+const PWM_MAX_US: u16 = 2200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RcChannelCalibration {
+    /// 1-based, as in `RC1_MIN`.
+    pub channel: u8,
+    pub min: u16,
+    pub max: u16,
+    pub trim: u16,
+}
+
+impl RcChannelCalibration {
+    /// Parameters to write, as (name, value) pairs.
+    pub fn params(&self) -> [(String, f32); 3] {
+        let n = self.channel;
+        [
+            (format!("RC{n}_MIN"), self.min.into()),
+            (format!("RC{n}_MAX"), self.max.into()),
+            (format!("RC{n}_TRIM"), self.trim.into()),
+        ]
+    }
+}
+
+/// Calibration in progress: the range seen so far on each channel.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RcCalibration {
+    /// Index `i` is channel `i + 1`. `trim` is the latest value until trims
+    /// are captured.
+    pub channels: Vec<RcChannelCalibration>,
+    pub trims_captured: bool,
+}
+
+impl RcCalibration {
+    /// Widen each channel's range with one RC_CHANNELS sample.
+    pub fn record(&mut self, values: &[u16]) {
+        for (index, &value) in values.iter().enumerate() {
+            if !(PWM_MIN_US..=PWM_MAX_US).contains(&value) {
+                // 0 and UINT16_MAX mark unused channels
+                continue;
+            }
+            if index >= self.channels.len() {
+                self.channels
+                    .extend((self.channels.len()..=index).map(|i| RcChannelCalibration {
+                        channel: i as u8 + 1,
+                        min: u16::MAX,
+                        max: 0,
+                        trim: 0,
+                    }));
+            }
+            let channel = &mut self.channels[index];
+            channel.min = channel.min.min(value);
+            channel.max = channel.max.max(value);
+            if !self.trims_captured {
+                channel.trim = value;
+            }
+        }
+    }
+
+    /// Take `values` (sticks centred, throttle low) as the trims.
+    pub fn capture_trims(&mut self, values: &[u16]) {
+        self.record(values);
+        for (channel, &value) in self.channels.iter_mut().zip(values) {
+            if (PWM_MIN_US..=PWM_MAX_US).contains(&value) {
+                channel.trim = value;
+            }
+        }
+        self.trims_captured = true;
+    }
+
+    /// The channels that moved enough to calibrate, with trims clamped into
+    /// their range.
+    pub fn finish(&self) -> Result<Vec<RcChannelCalibration>, VehicleError> {
+        if !self.trims_captured {
+            return Err(VehicleError::RcCalibration(
+                "trims have not been captured".into(),
+            ));
+        }
+        let moved: Vec<_> = self
+            .channels
+            .iter()
+            .filter(|c| c.max >= c.min && c.max - c.min >= MIN_TRAVEL_US)
+            .map(|c| RcChannelCalibration {
+                trim: c.trim.clamp(c.min, c.max),
+                ..*c
+            })
+            .collect();
+        if moved.is_empty() {
+            return Err(VehicleError::RcCalibration(
+                "no channel moved through its range".into(),
+            ));
+        }
+        Ok(moved)
+    }
+}
+
+/// A running calibration fed by the vehicle's RC channel updates. Recording
+/// stops when the session is finished or dropped.
+pub struct RcCalibrationSession {
+    calibration: Arc<watch::Sender<RcCalibration>>,
+    rc_channels: watch::Receiver<Vec<u16>>,
+    task: JoinHandle<()>,
+}
+
+impl RcCalibrationSession {
+    pub(crate) fn start(rc_channels: watch::Receiver<Vec<u16>>) -> Self {
+        let calibration = Arc::new(watch::Sender::new(RcCalibration::default()));
+        let recorder = calibration.clone();
+        let mut rx = rc_channels.clone();
+        let task = tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let values = rx.borrow_and_update().clone();
+                recorder.send_modify(|c| c.record(&values));
+            }
+        });
+        Self {
+            calibration,
+            rc_channels,
+            task,
+        }
+    }
+
+    pub fn progress(&self) -> watch::Receiver<RcCalibration> {
+        self.calibration.subscribe()
+    }
+
+    /// Capture the current channel values as trims.
+    pub fn capture_trims(&self) {
+        let values = self.rc_channels.borrow().clone();
+        self.calibration.send_modify(|c| c.capture_trims(&values));
+    }
+
+    /// Stop recording and return the calibrated channels.
+    pub fn finish(self) -> Result<Vec<RcChannelCalibration>, VehicleError> {
+        self.task.abort();
+        self.calibration.borrow().finish()
+    }
+}
+
+impl Drop for RcCalibrationSession {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_range_and_ignores_unused_channels() {
+        let mut calibration = RcCalibration::default();
+        calibration.record(&[1500, 1500, 0, u16::MAX]);
+        calibration.record(&[1000, 1900, 0, u16::MAX]);
+        calibration.record(&[2000, 1100, 0, u16::MAX]);
+        assert_eq!(calibration.channels.len(), 2);
+        assert_eq!(
+            (calibration.channels[0].min, calibration.channels[0].max),
+            (1000, 2000)
+        );
+        assert_eq!(
+            (calibration.channels[1].min, calibration.channels[1].max),
+            (1100, 1900)
+        );
+    }
+
+    #[test]
+    fn finish_needs_trims_and_movement() {
+        let mut calibration = RcCalibration::default();
+        calibration.record(&[1000, 1500]);
+        calibration.record(&[2000, 1550]);
+        assert!(calibration.finish().is_err());
+        calibration.capture_trims(&[1495, 1520]);
+        let channels = calibration.finish().unwrap();
+        // Channel 2 barely moved
+        assert_eq!(
+            channels,
+            vec![RcChannelCalibration {
+                channel: 1,
+                min: 1000,
+                max: 2000,
+                trim: 1495,
+            }]
+        );
+        assert_eq!(channels[0].params()[2], ("RC1_TRIM".to_string(), 1495.0));
+    }
+
+    #[test]
+    fn nothing_moved_is_an_error() {
+        let mut calibration = RcCalibration::default();
+        calibration.capture_trims(&[1500, 1500]);
+        assert!(matches!(
+            calibration.finish(),
+            Err(VehicleError::RcCalibration(_))
+        ));
+    }
+}
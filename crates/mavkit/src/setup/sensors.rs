@@ -0,0 +1,555 @@
+//! Compass and IMU inventory, priority and orientation.
+//!
+//! Both autopilots describe sensors with parameters: a device id per
+//! calibration slot (which bus, address and driver the sensor was found on),
+//! an orientation and, for compasses, a priority. ArduPilot keeps the
+//! priority as an ordered list of device ids (`COMPASS_PRIOn_ID`); PX4 gives
+//! each slot a priority level (`CAL_MAGn_PRIO`). Changes take effect after a
+//! reboot.
+//!
+//! Offsets come from the calibration parameters, since `SENSOR_OFFSETS` is
+//! not part of the common dialect this crate decodes.
+
+use crate::error::VehicleError;
+use crate::params::ParamStore;
+use crate::state::AutopilotType;
+use serde::Serialize;
+
+/// Calibration slots per sensor kind.
+const SLOTS: usize = 3;
+
+/// PX4 `CAL_MAGn_PRIO` levels assigned by rank; unranked compasses get 0
+/// (disabled).
+const PX4_PRIORITIES: [f32; SLOTS] = [100.0, 75.0, 50.0];
+
+/// Autopilot rotation values (ArduPilot `ROTATION_*`, PX4 `Rotation`),
+/// shared by board and sensor orientation parameters.
+const ROTATIONS: &[(u8, &str)] = &[
+    (0, "None"),
+    (1, "Yaw 45"),
+    (2, "Yaw 90"),
+    (3, "Yaw 135"),
+    (4, "Yaw 180"),
+    (5, "Yaw 225"),
+    (6, "Yaw 270"),
+    (7, "Yaw 315"),
+    (8, "Roll 180"),
+    (9, "Roll 180, Yaw 45"),
+    (10, "Roll 180, Yaw 90"),
+    (11, "Roll 180, Yaw 135"),
+    (12, "Pitch 180"),
+    (13, "Roll 180, Yaw 225"),
+    (14, "Roll 180, Yaw 270"),
+    (15, "Roll 180, Yaw 315"),
+    (16, "Roll 90"),
+    (17, "Roll 90, Yaw 45"),
+    (18, "Roll 90, Yaw 90"),
+    (19, "Roll 90, Yaw 135"),
+    (20, "Roll 270"),
+    (21, "Roll 270, Yaw 45"),
+    (22, "Roll 270, Yaw 90"),
+    (23, "Roll 270, Yaw 135"),
+    (24, "Pitch 90"),
+    (25, "Pitch 270"),
+    (26, "Pitch 180, Yaw 90"),
+    (27, "Pitch 180, Yaw 270"),
+    (28, "Roll 90, Pitch 90"),
+    (29, "Roll 180, Pitch 90"),
+    (30, "Roll 270, Pitch 90"),
+    (31, "Roll 90, Pitch 180"),
+    (32, "Roll 270, Pitch 180"),
+    (33, "Roll 90, Pitch 270"),
+    (34, "Roll 180, Pitch 270"),
+    (35, "Roll 270, Pitch 270"),
+    (36, "Roll 90, Pitch 180, Yaw 90"),
+    (37, "Roll 90, Yaw 270"),
+];
+
+/// ArduPilot compass driver ids (`AP_Compass_Backend::DevTypes`).
+const ARDUPILOT_COMPASS_DRIVERS: &[(u8, &str)] = &[
+    (0x01, "HMC5883 (old)"),
+    (0x07, "HMC5883"),
+    (0x08, "LSM303D"),
+    (0x09, "AK8963"),
+    (0x0A, "BMM150"),
+    (0x0B, "LSM9DS1"),
+    (0x0C, "LIS3MDL"),
+    (0x0D, "AK09916"),
+    (0x0E, "IST8310"),
+    (0x0F, "ICM20948"),
+    (0x10, "MMC3416"),
+    (0x11, "QMC5883L"),
+    (0x12, "MAG3110"),
+    (0x13, "SITL"),
+    (0x14, "IST8308"),
+    (0x15, "RM3100"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SensorRotation {
+    pub value: u8,
+    pub name: &'static str,
+}
+
+/// Orientations accepted by `set_compass_orientation` and
+/// `set_board_orientation`.
+pub fn sensor_rotations() -> Vec<SensorRotation> {
+    ROTATIONS
+        .iter()
+        .map(|(value, name)| SensorRotation {
+            value: *value,
+            name,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BusType {
+    Unknown,
+    I2c,
+    Spi,
+    Uavcan,
+    Sitl,
+    Msp,
+    Serial,
+}
+
+/// A sensor device id, decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceId {
+    pub raw: u32,
+    pub bus_type: BusType,
+    pub bus: u8,
+    pub address: u8,
+    pub devtype: u8,
+    /// Driver name, where known.
+    pub driver: Option<&'static str>,
+}
+
+impl DeviceId {
+    /// Decode the bus type (bits 0-2), bus (3-7), address (8-15) and driver
+    /// type (16-23) packed into `raw`.
+    pub fn decode(raw: u32) -> Self {
+        let bus_type = match raw & 0x07 {
+            1 => BusType::I2c,
+            2 => BusType::Spi,
+            3 => BusType::Uavcan,
+            4 => BusType::Sitl,
+            5 => BusType::Msp,
+            6 => BusType::Serial,
+            _ => BusType::Unknown,
+        };
+        Self {
+            raw,
+            bus_type,
+            bus: ((raw >> 3) & 0x1f) as u8,
+            address: (raw >> 8) as u8,
+            devtype: (raw >> 16) as u8,
+            driver: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Compass {
+    /// Calibration slot, 0-based.
+    pub slot: u8,
+    pub device: DeviceId,
+    /// 1 for the primary compass; `None` when not in use.
+    pub priority: Option<u8>,
+    pub orientation: Option<u8>,
+    pub external: Option<bool>,
+    /// Calibrated offsets (mGauss).
+    pub offsets: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Imu {
+    pub slot: u8,
+    pub accel: Option<DeviceId>,
+    pub gyro: Option<DeviceId>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SensorSetup {
+    /// Compasses with a calibration slot, in slot order.
+    pub compasses: Vec<Compass>,
+    /// Compasses detected but not given a slot (ArduPilot `COMPASS_DEV_ID4`
+    /// to `COMPASS_DEV_ID8`).
+    pub unassigned_compasses: Vec<DeviceId>,
+    pub imus: Vec<Imu>,
+    pub board_orientation: Option<u8>,
+}
+
+/// Parameter names for one compass slot.
+struct CompassParams {
+    dev_id: String,
+    orientation: String,
+    external: Option<String>,
+    offsets: [String; 3],
+}
+
+fn compass_params(autopilot: AutopilotType, slot: usize) -> Option<CompassParams> {
+    match autopilot {
+        AutopilotType::ArduPilotMega => {
+            let n = if slot == 0 {
+                String::new()
+            } else {
+                (slot + 1).to_string()
+            };
+            let extern_name = if slot == 0 { "EXTERNAL" } else { "EXTERN" };
+            Some(CompassParams {
+                dev_id: format!("COMPASS_DEV_ID{n}"),
+                orientation: format!("COMPASS_ORIENT{n}"),
+                external: Some(format!("COMPASS_{extern_name}{n}")),
+                offsets: ["X", "Y", "Z"].map(|axis| format!("COMPASS_OFS{n}_{axis}")),
+            })
+        }
+        AutopilotType::Px4 => Some(CompassParams {
+            dev_id: format!("CAL_MAG{slot}_ID"),
+            orientation: format!("CAL_MAG{slot}_ROT"),
+            external: None,
+            offsets: ["X", "Y", "Z"].map(|axis| format!("CAL_MAG{slot}_{axis}OFF")),
+        }),
+        AutopilotType::Generic | AutopilotType::Unknown => None,
+    }
+}
+
+fn board_orientation_param(autopilot: AutopilotType) -> Option<&'static str> {
+    match autopilot {
+        AutopilotType::ArduPilotMega => Some("AHRS_ORIENT"),
+        AutopilotType::Px4 => Some("SENS_BOARD_ROT"),
+        AutopilotType::Generic | AutopilotType::Unknown => None,
+    }
+}
+
+fn stored(store: &ParamStore, name: &str) -> Option<f32> {
+    store.params.get(name).map(|param| param.value)
+}
+
+fn device(store: &ParamStore, name: &str) -> Option<DeviceId> {
+    stored(store, name)
+        .map(|value| value as u32)
+        .filter(|raw| *raw != 0)
+        .map(DeviceId::decode)
+}
+
+fn compass_device(autopilot: AutopilotType, store: &ParamStore, name: &str) -> Option<DeviceId> {
+    let mut device = device(store, name)?;
+    if autopilot == AutopilotType::ArduPilotMega {
+        device.driver = ARDUPILOT_COMPASS_DRIVERS
+            .iter()
+            .find(|(devtype, _)| *devtype == device.devtype)
+            .map(|(_, name)| *name);
+    }
+    Some(device)
+}
+
+/// Compasses, IMUs and board orientation described by the parameters.
+pub fn sensor_setup(autopilot: AutopilotType, store: &ParamStore) -> SensorSetup {
+    let mut setup = SensorSetup {
+        board_orientation: board_orientation_param(autopilot)
+            .and_then(|name| stored(store, name))
+            .map(|value| value as u8),
+        ..SensorSetup::default()
+    };
+
+    for slot in 0..SLOTS {
+        let Some(names) = compass_params(autopilot, slot) else {
+            break;
+        };
+        let Some(device) = compass_device(autopilot, store, &names.dev_id) else {
+            continue;
+        };
+        let offsets = names.offsets.each_ref().map(|name| stored(store, name));
+        // PX4 uses -1 for internal compasses, which follow the board
+        let orientation = stored(store, &names.orientation);
+        setup.compasses.push(Compass {
+            slot: slot as u8,
+            device,
+            priority: None,
+            orientation: orientation.filter(|v| *v >= 0.0).map(|v| v as u8),
+            external: match &names.external {
+                Some(name) => stored(store, name).map(|v| v != 0.0),
+                None => orientation.map(|v| v >= 0.0),
+            },
+            offsets: match offsets {
+                [Some(x), Some(y), Some(z)] => Some([x, y, z]),
+                _ => None,
+            },
+        });
+    }
+    rank_compasses(autopilot, store, &mut setup.compasses);
+
+    if autopilot == AutopilotType::ArduPilotMega {
+        setup.unassigned_compasses = (4..=8)
+            .filter_map(|n| compass_device(autopilot, store, &format!("COMPASS_DEV_ID{n}")))
+            .collect();
+    }
+
+    for slot in 0..SLOTS {
+        let (accel, gyro) = match autopilot {
+            AutopilotType::ArduPilotMega => {
+                let n = if slot == 0 {
+                    String::new()
+                } else {
+                    (slot + 1).to_string()
+                };
+                (format!("INS_ACC{n}_ID"), format!("INS_GYR{n}_ID"))
+            }
+            AutopilotType::Px4 => (format!("CAL_ACC{slot}_ID"), format!("CAL_GYRO{slot}_ID")),
+            AutopilotType::Generic | AutopilotType::Unknown => break,
+        };
+        let imu = Imu {
+            slot: slot as u8,
+            accel: device(store, &accel),
+            gyro: device(store, &gyro),
+        };
+        if imu.accel.is_some() || imu.gyro.is_some() {
+            setup.imus.push(imu);
+        }
+    }
+    setup
+}
+
+fn rank_compasses(autopilot: AutopilotType, store: &ParamStore, compasses: &mut [Compass]) {
+    match autopilot {
+        AutopilotType::ArduPilotMega => {
+            for rank in 1..=SLOTS {
+                let Some(dev_id) = stored(store, &format!("COMPASS_PRIO{rank}_ID")) else {
+                    continue;
+                };
+                let used = compasses.iter_mut().find(|compass| {
+                    compass.device.raw == dev_id as u32
+                        && stored(store, &compass_use_param(compass.slot as usize)) != Some(0.0)
+                });
+                if let Some(compass) = used {
+                    compass.priority = Some(rank as u8);
+                }
+            }
+        }
+        AutopilotType::Px4 => {
+            let mut levels: Vec<(usize, f32)> = compasses
+                .iter()
+                .enumerate()
+                .filter_map(|(index, compass)| {
+                    let level = stored(store, &format!("CAL_MAG{}_PRIO", compass.slot))?;
+                    (level > 0.0).then_some((index, level))
+                })
+                .collect();
+            // Stable, so equal levels keep slot order
+            levels.sort_by(|a, b| b.1.total_cmp(&a.1));
+            for (rank, (index, _)) in levels.into_iter().enumerate() {
+                compasses[index].priority = Some(rank as u8 + 1);
+            }
+        }
+        AutopilotType::Generic | AutopilotType::Unknown => {}
+    }
+}
+
+fn compass_use_param(slot: usize) -> String {
+    if slot == 0 {
+        "COMPASS_USE".to_string()
+    } else {
+        format!("COMPASS_USE{}", slot + 1)
+    }
+}
+
+fn invalid(message: String) -> VehicleError {
+    VehicleError::SensorSetup(message)
+}
+
+fn check_rotation(rotation: u8) -> Result<(), VehicleError> {
+    if ROTATIONS.iter().any(|(value, _)| *value == rotation) {
+        Ok(())
+    } else {
+        Err(invalid(format!("unknown rotation {rotation}")))
+    }
+}
+
+/// Parameter writes making `dev_ids` the compass order, primary first.
+/// ArduPilot compasses not listed are dropped from the priority list; PX4
+/// ones are disabled.
+pub fn compass_priority_writes(
+    autopilot: AutopilotType,
+    store: &ParamStore,
+    dev_ids: &[u32],
+) -> Result<Vec<(String, f32)>, VehicleError> {
+    if dev_ids.is_empty() || dev_ids.len() > SLOTS {
+        return Err(invalid(format!("list 1 to {SLOTS} compasses")));
+    }
+    let setup = sensor_setup(autopilot, store);
+    for (index, dev_id) in dev_ids.iter().enumerate() {
+        if dev_ids[..index].contains(dev_id) {
+            return Err(invalid(format!("compass {dev_id} is listed twice")));
+        }
+        if !setup.compasses.iter().any(|c| c.device.raw == *dev_id) {
+            return Err(invalid(format!("no compass with device id {dev_id}")));
+        }
+    }
+    match autopilot {
+        AutopilotType::ArduPilotMega => Ok((0..SLOTS)
+            .map(|rank| {
+                let dev_id = dev_ids.get(rank).copied().unwrap_or(0);
+                (format!("COMPASS_PRIO{}_ID", rank + 1), dev_id as f32)
+            })
+            .collect()),
+        AutopilotType::Px4 => Ok(setup
+            .compasses
+            .iter()
+            .map(|compass| {
+                let level = dev_ids
+                    .iter()
+                    .position(|dev_id| *dev_id == compass.device.raw)
+                    .map_or(0.0, |rank| PX4_PRIORITIES[rank]);
+                (format!("CAL_MAG{}_PRIO", compass.slot), level)
+            })
+            .collect()),
+        AutopilotType::Generic | AutopilotType::Unknown => Err(invalid(
+            "compass priority is not known for this autopilot".into(),
+        )),
+    }
+}
+
+/// Parameter write setting compass `slot`'s orientation.
+pub fn compass_orientation_write(
+    autopilot: AutopilotType,
+    store: &ParamStore,
+    slot: u8,
+    rotation: u8,
+) -> Result<(String, f32), VehicleError> {
+    check_rotation(rotation)?;
+    let names = compass_params(autopilot, slot as usize)
+        .filter(|names| store.params.contains_key(&names.orientation))
+        .ok_or_else(|| invalid(format!("compass slot {slot} has no orientation parameter")))?;
+    if autopilot == AutopilotType::Px4 && stored(store, &names.orientation) == Some(-1.0) {
+        return Err(invalid(format!(
+            "compass slot {slot} is internal and follows the board orientation"
+        )));
+    }
+    Ok((names.orientation, rotation.into()))
+}
+
+/// Parameter write setting the autopilot board's orientation.
+pub fn board_orientation_write(
+    autopilot: AutopilotType,
+    rotation: u8,
+) -> Result<(&'static str, f32), VehicleError> {
+    check_rotation(rotation)?;
+    let name = board_orientation_param(autopilot)
+        .ok_or_else(|| invalid("board orientation is not known for this autopilot".into()))?;
+    Ok((name, rotation.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IST8310 on I2C bus 0, address 0x0e; RM3100 on SPI bus 1, address 0x20
+    const IST8310: u32 = 0x0E_0E_01;
+    const RM3100: u32 = 0x15_20_0A;
+
+    fn ardupilot() -> ParamStore {
+        ParamStore::from_values(&[
+            ("COMPASS_DEV_ID", IST8310 as f32),
+            ("COMPASS_ORIENT", 0.0),
+            ("COMPASS_EXTERNAL", 1.0),
+            ("COMPASS_USE", 1.0),
+            ("COMPASS_OFS_X", 10.0),
+            ("COMPASS_OFS_Y", -5.0),
+            ("COMPASS_OFS_Z", 2.0),
+            ("COMPASS_DEV_ID2", RM3100 as f32),
+            ("COMPASS_ORIENT2", 4.0),
+            ("COMPASS_EXTERN2", 0.0),
+            ("COMPASS_USE2", 1.0),
+            ("COMPASS_DEV_ID3", 0.0),
+            ("COMPASS_PRIO1_ID", RM3100 as f32),
+            ("COMPASS_PRIO2_ID", IST8310 as f32),
+            ("COMPASS_PRIO3_ID", 0.0),
+            ("COMPASS_DEV_ID4", 0x0D_0C_01 as f32),
+            ("INS_ACC_ID", 0x2F_00_0A as f32),
+            ("INS_GYR_ID", 0x2F_00_0A as f32),
+            ("AHRS_ORIENT", 0.0),
+        ])
+    }
+
+    #[test]
+    fn decodes_device_ids() {
+        let device = DeviceId::decode(RM3100);
+        assert_eq!(device.bus_type, BusType::Spi);
+        assert_eq!(
+            (device.bus, device.address, device.devtype),
+            (1, 0x20, 0x15)
+        );
+    }
+
+    #[test]
+    fn reads_ardupilot_compasses_in_priority_order() {
+        let setup = sensor_setup(AutopilotType::ArduPilotMega, &ardupilot());
+        assert_eq!(setup.compasses.len(), 2);
+        let [first, second] = &setup.compasses[..] else {
+            unreachable!()
+        };
+        assert_eq!(first.device.driver, Some("IST8310"));
+        assert_eq!(first.priority, Some(2));
+        assert_eq!(first.external, Some(true));
+        assert_eq!(first.offsets, Some([10.0, -5.0, 2.0]));
+        assert_eq!(second.priority, Some(1));
+        assert_eq!(second.orientation, Some(4));
+        assert_eq!(setup.unassigned_compasses[0].driver, Some("AK09916"));
+        assert_eq!(setup.imus.len(), 1);
+        assert_eq!(setup.board_orientation, Some(0));
+    }
+
+    #[test]
+    fn priority_writes_are_validated() {
+        let store = ardupilot();
+        let writes =
+            compass_priority_writes(AutopilotType::ArduPilotMega, &store, &[IST8310]).unwrap();
+        assert_eq!(
+            writes,
+            vec![
+                ("COMPASS_PRIO1_ID".to_string(), IST8310 as f32),
+                ("COMPASS_PRIO2_ID".to_string(), 0.0),
+                ("COMPASS_PRIO3_ID".to_string(), 0.0),
+            ]
+        );
+        assert!(compass_priority_writes(AutopilotType::ArduPilotMega, &store, &[42]).is_err());
+        assert!(
+            compass_priority_writes(AutopilotType::ArduPilotMega, &store, &[IST8310, IST8310])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn px4_priority_levels_and_internal_orientation() {
+        let store = ParamStore::from_values(&[
+            ("CAL_MAG0_ID", IST8310 as f32),
+            ("CAL_MAG0_ROT", -1.0),
+            ("CAL_MAG0_PRIO", 50.0),
+            ("CAL_MAG1_ID", RM3100 as f32),
+            ("CAL_MAG1_ROT", 0.0),
+            ("CAL_MAG1_PRIO", 75.0),
+        ]);
+        let setup = sensor_setup(AutopilotType::Px4, &store);
+        assert_eq!(setup.compasses[0].priority, Some(2));
+        assert_eq!(setup.compasses[0].external, Some(false));
+        assert_eq!(setup.compasses[1].priority, Some(1));
+
+        let writes = compass_priority_writes(AutopilotType::Px4, &store, &[IST8310]).unwrap();
+        assert_eq!(
+            writes,
+            vec![
+                ("CAL_MAG0_PRIO".to_string(), 100.0),
+                ("CAL_MAG1_PRIO".to_string(), 0.0),
+            ]
+        );
+        assert!(compass_orientation_write(AutopilotType::Px4, &store, 0, 2).is_err());
+        assert_eq!(
+            compass_orientation_write(AutopilotType::Px4, &store, 1, 2).unwrap(),
+            ("CAL_MAG1_ROT".to_string(), 2.0)
+        );
+        assert!(board_orientation_write(AutopilotType::Px4, 200).is_err());
+    }
+}
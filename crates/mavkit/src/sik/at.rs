@@ -0,0 +1,272 @@
+//! The Hayes-style command mode of SiK radios.
+//!
+//! `+++`, with a second of silence before and after, switches the local
+//! radio from passing data to taking commands; it answers `OK`. Commands are
+//! echoed and then answered line by line. Multi-line replies such as `ATI5`
+//! have no terminator, so a reply is taken to be complete once the radio has
+//! been quiet for a while. Commands starting with `RT` instead of `AT` are
+//! forwarded over the air to the remote radio and answered from there, so
+//! they wait longer.
+
+use super::SikTarget;
+use crate::error::VehicleError;
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Pause between reads when the port returned nothing without waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timing {
+    /// Silence required around `+++`.
+    pub guard: Duration,
+    /// A local reply is complete after this long without data.
+    pub quiet: Duration,
+    /// The same for replies relayed from the remote radio.
+    pub remote_quiet: Duration,
+    /// Longest wait for the first byte of a reply.
+    pub timeout: Duration,
+}
+
+/// Timing of the SiK firmware.
+pub(crate) const RADIO_TIMING: Timing = Timing {
+    guard: Duration::from_millis(1100),
+    quiet: Duration::from_millis(200),
+    remote_quiet: Duration::from_millis(800),
+    timeout: Duration::from_secs(3),
+};
+
+pub(crate) struct AtSession<P> {
+    port: P,
+    timing: Timing,
+}
+
+impl<P: Read + Write> AtSession<P> {
+    pub(crate) fn new(port: P, timing: Timing) -> Self {
+        Self { port, timing }
+    }
+
+    /// Switch the local radio to command mode. One already in command mode
+    /// ignores `+++` but answers a plain `AT`.
+    pub(crate) fn enter(&mut self) -> Result<(), VehicleError> {
+        thread::sleep(self.timing.guard);
+        self.write(b"+++")?;
+        thread::sleep(self.timing.guard);
+        if self
+            .read_lines(self.timing.quiet)?
+            .iter()
+            .any(|l| l == "OK")
+        {
+            return Ok(());
+        }
+        // End whatever line the `+++` started before trying a plain `AT`
+        self.write(b"\r\n")?;
+        self.read_lines(self.timing.quiet)?;
+        match self.command(SikTarget::Local, "") {
+            Ok(reply) if reply.iter().any(|l| l == "OK") => Ok(()),
+            _ => Err(radio_error("the radio did not enter command mode")),
+        }
+    }
+
+    /// Send `AT<command>`, or `RT<command>` to the remote radio, and return
+    /// the reply lines without the echo.
+    pub(crate) fn command(
+        &mut self,
+        target: SikTarget,
+        command: &str,
+    ) -> Result<Vec<String>, VehicleError> {
+        let line = self.send(target, command)?;
+        let quiet = match target {
+            SikTarget::Local => self.timing.quiet,
+            SikTarget::Remote => self.timing.remote_quiet,
+        };
+        let mut reply = self.read_lines(quiet)?;
+        reply.retain(|l| *l != line);
+        if reply.is_empty() {
+            return Err(radio_error(&format!("no reply to {line}")));
+        }
+        Ok(reply)
+    }
+
+    /// Send a command that is not answered, e.g. `ATZ`, which reboots the
+    /// radio. Returns the line sent.
+    pub(crate) fn send(
+        &mut self,
+        target: SikTarget,
+        command: &str,
+    ) -> Result<String, VehicleError> {
+        let line = format!("{}{command}", target.prefix());
+        self.write(format!("{line}\r\n").as_bytes())?;
+        Ok(line)
+    }
+
+    /// Lines received until `quiet` passes without data after the first
+    /// byte, or `timeout` passes without any.
+    fn read_lines(&mut self, quiet: Duration) -> Result<Vec<String>, VehicleError> {
+        let started = Instant::now();
+        let mut last_data: Option<Instant> = None;
+        let mut received = Vec::new();
+        let mut buf = [0u8; 256];
+        loop {
+            match self.port.read(&mut buf) {
+                Ok(n) if n > 0 => {
+                    received.extend_from_slice(&buf[..n]);
+                    last_data = Some(Instant::now());
+                    continue;
+                }
+                Ok(_) => thread::sleep(POLL_INTERVAL),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                    ) => {}
+                Err(e) => return Err(e.into()),
+            }
+            let done = match last_data {
+                Some(at) => at.elapsed() >= quiet,
+                None => started.elapsed() >= self.timing.timeout,
+            };
+            if done {
+                break;
+            }
+        }
+        Ok(String::from_utf8_lossy(&received)
+            .split(['\r', '\n'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn into_port(self) -> P {
+        self.port
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), VehicleError> {
+        self.port.write_all(bytes)?;
+        self.port.flush()?;
+        Ok(())
+    }
+}
+
+pub(crate) fn radio_error(message: &str) -> VehicleError {
+    VehicleError::Radio(message.to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::{HashMap, VecDeque};
+
+    pub(crate) const FAST: Timing = Timing {
+        guard: Duration::ZERO,
+        quiet: Duration::from_millis(20),
+        remote_quiet: Duration::from_millis(20),
+        timeout: Duration::from_millis(50),
+    };
+
+    /// Answers commands from a table, echoing them like the firmware, and
+    /// records every line written.
+    #[derive(Default)]
+    pub(crate) struct ScriptedRadio {
+        pub replies: HashMap<String, String>,
+        pub sent: Vec<String>,
+        /// Whether `+++` is answered.
+        pub answers_escape: bool,
+        input: Vec<u8>,
+        output: VecDeque<u8>,
+    }
+
+    impl ScriptedRadio {
+        pub(crate) fn new(replies: &[(&str, &str)]) -> Self {
+            Self {
+                replies: replies
+                    .iter()
+                    .map(|(command, reply)| (command.to_string(), reply.to_string()))
+                    .collect(),
+                answers_escape: true,
+                ..Self::default()
+            }
+        }
+    }
+
+    impl Read for ScriptedRadio {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.output.is_empty() {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let n = buf.len().min(self.output.len());
+            for (slot, byte) in buf.iter_mut().zip(self.output.drain(..n)) {
+                *slot = byte;
+            }
+            Ok(n)
+        }
+    }
+
+    impl Write for ScriptedRadio {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.input.extend_from_slice(buf);
+            if self.input == b"+++" {
+                self.input.clear();
+                self.sent.push("+++".to_string());
+                if self.answers_escape {
+                    self.output.extend(b"OK\r\n");
+                }
+            }
+            while let Some(end) = self.input.windows(2).position(|w| w == b"\r\n") {
+                let line = String::from_utf8_lossy(&self.input[..end]).to_string();
+                self.input.drain(..end + 2);
+                self.output.extend(format!("{line}\r\n").bytes());
+                if let Some(reply) = self.replies.get(&line) {
+                    self.output.extend(reply.bytes());
+                }
+                self.sent.push(line);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn enters_command_mode_and_strips_the_echo() {
+        let radio = ScriptedRadio::new(&[("ATI", "SiK 2.2 on HM-TRP\r\n")]);
+        let mut session = AtSession::new(radio, FAST);
+        session.enter().unwrap();
+        assert_eq!(
+            session.command(SikTarget::Local, "I").unwrap(),
+            vec!["SiK 2.2 on HM-TRP"]
+        );
+        assert_eq!(session.port.sent, vec!["+++", "ATI"]);
+    }
+
+    #[test]
+    fn falls_back_to_at_when_already_in_command_mode() {
+        let mut radio = ScriptedRadio::new(&[("AT", "OK\r\n")]);
+        radio.answers_escape = false;
+        let mut session = AtSession::new(radio, FAST);
+        session.enter().unwrap();
+
+        let mut silent = ScriptedRadio::new(&[]);
+        silent.answers_escape = false;
+        assert!(matches!(
+            AtSession::new(silent, FAST).enter(),
+            Err(VehicleError::Radio(_))
+        ));
+    }
+
+    #[test]
+    fn remote_commands_use_the_rt_prefix() {
+        let radio = ScriptedRadio::new(&[("RTI", "SiK 2.2 on RFD900\r\n")]);
+        let mut session = AtSession::new(radio, FAST);
+        assert_eq!(
+            session.command(SikTarget::Remote, "I").unwrap(),
+            vec!["SiK 2.2 on RFD900"]
+        );
+        assert!(session.command(SikTarget::Remote, "I5").is_err());
+    }
+}
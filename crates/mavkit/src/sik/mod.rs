@@ -0,0 +1,362 @@
+//! SiK telemetry radio setup through the radio's AT command mode.
+//!
+//! Reads the firmware version, the parameter registers (`ATI5`) and link
+//! quality (`ATI7`) of the local radio or, over the air, of the remote one,
+//! and writes the settings both ends must agree on. Written settings are
+//! saved and the radio is rebooted to apply them.
+//!
+//! The port is opened directly, not through a `Vehicle`; any MAVLink
+//! connection on the same port must be closed first. When changing a
+//! setting both radios share (net id, air speed, ECC...), write the remote
+//! radio first: once the local one changes, the remote is out of reach
+//! until it changes too.
+
+mod at;
+
+use crate::error::VehicleError;
+use at::{radio_error, AtSession, RADIO_TIMING};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::time::Duration;
+
+/// How long a blocking read waits; `AtSession` times replies itself.
+const READ_TIMEOUT: Duration = Duration::from_millis(50);
+
+const NET_ID: &str = "NETID";
+const AIR_SPEED: &str = "AIR_SPEED";
+const TX_POWER: &str = "TXPOWER";
+const ECC: &str = "ECC";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SikTarget {
+    /// The radio on the port.
+    Local,
+    /// The radio at the other end of the link.
+    Remote,
+}
+
+impl SikTarget {
+    fn prefix(self) -> &'static str {
+        match self {
+            SikTarget::Local => "AT",
+            SikTarget::Remote => "RT",
+        }
+    }
+}
+
+/// One `S` register, as listed by `ATI5`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SikParam {
+    pub register: u8,
+    pub name: String,
+    pub value: i64,
+}
+
+/// The settings most radios need changed. `None` fields are not present on
+/// the radio, or left unchanged when writing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SikSettings {
+    /// Radios only talk to radios with the same net id.
+    pub net_id: Option<i64>,
+    pub air_speed_kbps: Option<i64>,
+    pub tx_power_dbm: Option<i64>,
+    /// Golay error correcting code; halves the usable air speed.
+    pub ecc: Option<bool>,
+}
+
+impl SikSettings {
+    pub fn from_params(params: &[SikParam]) -> Self {
+        let value = |name: &str| params.iter().find(|p| p.name == name).map(|p| p.value);
+        Self {
+            net_id: value(NET_ID),
+            air_speed_kbps: value(AIR_SPEED),
+            tx_power_dbm: value(TX_POWER),
+            ecc: value(ECC).map(|v| v != 0),
+        }
+    }
+
+    /// Register names and values to write.
+    fn writes(&self) -> Vec<(&'static str, i64)> {
+        [
+            (NET_ID, self.net_id),
+            (AIR_SPEED, self.air_speed_kbps),
+            (TX_POWER, self.tx_power_dbm),
+            (ECC, self.ecc.map(i64::from)),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|v| (name, v)))
+        .collect()
+    }
+}
+
+/// Link quality as reported by `ATI7`. RSSI and noise are in the radio's
+/// raw units (roughly 0.5 dB steps); the local values are measured here,
+/// the remote ones at the other radio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SikRssi {
+    pub local_rssi: u16,
+    pub remote_rssi: u16,
+    pub local_noise: u16,
+    pub remote_noise: u16,
+    pub packets: u32,
+    pub tx_errors: u32,
+    pub rx_errors: u32,
+    /// Packets repaired by ECC.
+    pub ecc_corrected: u32,
+    pub temperature_c: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SikReport {
+    pub target: SikTarget,
+    /// `ATI`, e.g. "SiK 2.2 on HM-TRP".
+    pub version: String,
+    pub params: Vec<SikParam>,
+    pub settings: SikSettings,
+    pub rssi: Option<SikRssi>,
+}
+
+/// Read the version, parameters and link quality of the `target` radio
+/// through the radio on `port`, then return the local radio to data mode.
+pub async fn read_radio(
+    port: &str,
+    baud: u32,
+    target: SikTarget,
+) -> Result<SikReport, VehicleError> {
+    let port = port.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut session = open(&port, baud)?;
+        let report = read_report(&mut session, target)?;
+        session.send(SikTarget::Local, "O")?;
+        Ok(report)
+    })
+    .await
+    .map_err(|err| radio_error(&err.to_string()))?
+}
+
+/// Write `settings` to the `target` radio, save them and reboot it to apply
+/// them. Returns the report read back before the reboot.
+pub async fn write_radio(
+    port: &str,
+    baud: u32,
+    target: SikTarget,
+    settings: SikSettings,
+) -> Result<SikReport, VehicleError> {
+    let port = port.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut session = open(&port, baud)?;
+        let report = write_settings(&mut session, target, &settings)?;
+        if target == SikTarget::Remote {
+            // The local radio is still in command mode
+            session.send(SikTarget::Local, "O")?;
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|err| radio_error(&err.to_string()))?
+}
+
+fn open(port: &str, baud: u32) -> Result<AtSession<Box<dyn serialport::SerialPort>>, VehicleError> {
+    let serial = serialport::new(port, baud)
+        .timeout(READ_TIMEOUT)
+        .open()
+        .map_err(|err| radio_error(&format!("{port}: {err}")))?;
+    let _ = serial.clear(serialport::ClearBuffer::Input);
+    let mut session = AtSession::new(serial, RADIO_TIMING);
+    session.enter()?;
+    Ok(session)
+}
+
+fn read_report<P: Read + Write>(
+    session: &mut AtSession<P>,
+    target: SikTarget,
+) -> Result<SikReport, VehicleError> {
+    let version = session.command(target, "I")?.join(" ");
+    let params = parse_params(&session.command(target, "I5")?);
+    if params.is_empty() {
+        return Err(radio_error("the radio listed no parameters"));
+    }
+    let rssi = session
+        .command(target, "I7")
+        .ok()
+        .and_then(|reply| reply.iter().find_map(|line| parse_rssi(line)));
+    Ok(SikReport {
+        target,
+        version,
+        settings: SikSettings::from_params(&params),
+        params,
+        rssi,
+    })
+}
+
+fn write_settings<P: Read + Write>(
+    session: &mut AtSession<P>,
+    target: SikTarget,
+    settings: &SikSettings,
+) -> Result<SikReport, VehicleError> {
+    let before = read_report(session, target)?;
+    for (name, value) in settings.writes() {
+        let param = before
+            .params
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| radio_error(&format!("the radio has no {name} parameter")))?;
+        let reply = session.command(target, &format!("S{}={value}", param.register))?;
+        if !reply.iter().any(|line| line == "OK") {
+            return Err(radio_error(&format!(
+                "{name}={value} was refused: {}",
+                reply.join(" ")
+            )));
+        }
+    }
+    let after = read_report(session, target)?;
+    let saved = session.command(target, "&W")?;
+    if !saved.iter().any(|line| line == "OK") {
+        return Err(radio_error("saving the parameters failed"));
+    }
+    session.send(target, "Z")?;
+    Ok(after)
+}
+
+/// `S3:NETID=25` lines of an `ATI5` listing.
+fn parse_params(lines: &[String]) -> Vec<SikParam> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let (register, rest) = line.strip_prefix('S')?.split_once(':')?;
+            let (name, value) = rest.split_once('=')?;
+            Some(SikParam {
+                register: register.trim().parse().ok()?,
+                name: name.trim().to_string(),
+                value: value.trim().parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// An `ATI7` line:
+/// `L/R RSSI: 200/190  L/R noise: 50/48 pkts: 10  txe=0 rxe=0 stx=0 srx=0 ecc=0/0 temp=41 dco=0`
+fn parse_rssi(line: &str) -> Option<SikRssi> {
+    let pair = |text: &str| -> Option<(u16, u16)> {
+        let (a, b) = text.split_once('/')?;
+        Some((a.parse().ok()?, b.parse().ok()?))
+    };
+    let mut tokens = line.split_whitespace();
+    let mut rssi = SikRssi::default();
+    let mut seen_rssi = false;
+    while let Some(token) = tokens.next() {
+        match token {
+            "RSSI:" => {
+                (rssi.local_rssi, rssi.remote_rssi) = pair(tokens.next()?)?;
+                seen_rssi = true;
+            }
+            "noise:" => (rssi.local_noise, rssi.remote_noise) = pair(tokens.next()?)?,
+            "pkts:" => rssi.packets = tokens.next()?.parse().ok()?,
+            _ => match token.split_once('=') {
+                Some(("txe", v)) => rssi.tx_errors = v.parse().ok()?,
+                Some(("rxe", v)) => rssi.rx_errors = v.parse().ok()?,
+                Some(("ecc", v)) => {
+                    rssi.ecc_corrected = v.split('/').next()?.parse().ok()?;
+                }
+                Some(("temp", v)) => rssi.temperature_c = v.parse().ok(),
+                _ => {}
+            },
+        }
+    }
+    seen_rssi.then_some(rssi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::at::tests::{ScriptedRadio, FAST};
+    use super::*;
+
+    const PARAMS: &str = "S0:FORMAT=25\r\nS1:SERIAL_SPEED=57\r\nS2:AIR_SPEED=64\r\n\
+        S3:NETID=25\r\nS4:TXPOWER=20\r\nS5:ECC=0\r\n";
+    const RSSI: &str = "L/R RSSI: 200/190  L/R noise: 50/48 pkts: 10  txe=1 rxe=2 stx=0 \
+        srx=0 ecc=3/9 temp=41 dco=0\r\n";
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.trim().to_string()).collect()
+    }
+
+    #[test]
+    fn parses_parameter_listing() {
+        let params = parse_params(&lines(PARAMS));
+        assert_eq!(params.len(), 6);
+        assert_eq!(
+            params[3],
+            SikParam {
+                register: 3,
+                name: "NETID".into(),
+                value: 25
+            }
+        );
+        assert_eq!(
+            SikSettings::from_params(&params),
+            SikSettings {
+                net_id: Some(25),
+                air_speed_kbps: Some(64),
+                tx_power_dbm: Some(20),
+                ecc: Some(false),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_link_quality() {
+        let rssi = parse_rssi(RSSI.trim()).unwrap();
+        assert_eq!((rssi.local_rssi, rssi.remote_rssi), (200, 190));
+        assert_eq!((rssi.local_noise, rssi.remote_noise), (50, 48));
+        assert_eq!((rssi.packets, rssi.tx_errors, rssi.rx_errors), (10, 1, 2));
+        assert_eq!(rssi.ecc_corrected, 3);
+        assert_eq!(rssi.temperature_c, Some(41));
+        assert_eq!(parse_rssi("OK"), None);
+    }
+
+    #[test]
+    fn writes_by_register_then_saves_and_reboots() {
+        let radio = ScriptedRadio::new(&[
+            ("RTI", "SiK 2.2 on HM-TRP\r\n"),
+            ("RTI5", PARAMS),
+            ("RTI7", RSSI),
+            ("RTS3=42", "OK\r\n"),
+            ("RTS5=1", "OK\r\n"),
+            ("RT&W", "OK\r\n"),
+        ]);
+        let mut session = AtSession::new(radio, FAST);
+        let settings = SikSettings {
+            net_id: Some(42),
+            ecc: Some(true),
+            ..SikSettings::default()
+        };
+        let report = write_settings(&mut session, SikTarget::Remote, &settings).unwrap();
+        assert_eq!(report.version, "SiK 2.2 on HM-TRP");
+        assert!(report.rssi.is_some());
+        let sent = session.into_port().sent;
+        let writes: Vec<_> = sent.iter().skip(3).take(2).collect();
+        assert_eq!(writes, ["RTS3=42", "RTS5=1"]);
+        assert_eq!(sent[sent.len() - 2..], ["RT&W", "RTZ"]);
+    }
+
+    #[test]
+    fn refuses_settings_the_radio_lacks() {
+        let radio = ScriptedRadio::new(&[
+            ("ATI", "SiK 1.9\r\n"),
+            ("ATI5", "S3:NETID=25\r\n"),
+            ("ATI7", "ERROR\r\n"),
+        ]);
+        let mut session = AtSession::new(radio, FAST);
+        let settings = SikSettings {
+            tx_power_dbm: Some(30),
+            ..SikSettings::default()
+        };
+        let err = write_settings(&mut session, SikTarget::Local, &settings).unwrap_err();
+        assert!(
+            matches!(&err, VehicleError::Radio(m) if m.contains("no TXPOWER")),
+            "{err:?}"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! ArduPilot SITL control: simulation speed, wind and failure injection.
+//!
+//! Everything here is driven through the simulator's `SIM_*` parameters, so
+//! it works over the normal MAVLink link without a MAVProxy console. Several
+//! parameters were renamed in ArduPilot 4.5 (e.g. `SIM_GPS_GLITCH_X` became
+//! `SIM_GPS1_GLTCH_X`); the name is picked from the downloaded parameter
+//! store, falling back to the current spelling.
+
+use crate::error::VehicleError;
+use crate::params::ParamStore;
+use crate::Vehicle;
+use serde::{Deserialize, Serialize};
+
+/// How the simulated RC receiver fails (`SIM_RC_FAIL`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RcFailure {
+    None,
+    /// Receiver stops sending pulses.
+    NoPulses,
+    /// Receiver keeps sending, but all channels at neutral.
+    Neutral,
+}
+
+/// One simulator change, suitable for scripting failure scenarios.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimAction {
+    Speedup {
+        factor: f32,
+    },
+    Wind {
+        speed_mps: f32,
+        direction_deg: f32,
+        turbulence: f32,
+    },
+    /// Offset the simulated GPS position (degrees lat/lon, metres up). All
+    /// zero clears the glitch.
+    GpsGlitch {
+        lat_deg: f32,
+        lon_deg: f32,
+        alt_m: f32,
+    },
+    GpsEnabled {
+        enabled: bool,
+    },
+    RcFailure {
+        mode: RcFailure,
+    },
+    /// Return wind, GPS and RC to nominal.
+    ClearFailures,
+}
+
+/// Whether the parameter store looks like it came from SITL.
+pub fn is_sitl(store: &ParamStore) -> bool {
+    store.params.contains_key("SIM_SPEEDUP")
+}
+
+/// First of `names` present in `store`, else the first (current) spelling.
+fn param_name<'a>(store: &ParamStore, names: &[&'a str]) -> &'a str {
+    names
+        .iter()
+        .copied()
+        .find(|name| store.params.contains_key(*name))
+        .unwrap_or(names[0])
+}
+
+fn gps_enabled_write(store: &ParamStore, enabled: bool) -> (String, f32) {
+    if store.params.contains_key("SIM_GPS_DISABLE") {
+        ("SIM_GPS_DISABLE".into(), if enabled { 0.0 } else { 1.0 })
+    } else {
+        ("SIM_GPS1_ENABLE".into(), if enabled { 1.0 } else { 0.0 })
+    }
+}
+
+/// Parameter writes that carry out `action`.
+pub fn sim_param_writes(action: &SimAction, store: &ParamStore) -> Vec<(String, f32)> {
+    let glitch = |lat: f32, lon: f32, alt: f32| {
+        vec![
+            (
+                param_name(store, &["SIM_GPS1_GLTCH_X", "SIM_GPS_GLITCH_X"]).into(),
+                lat,
+            ),
+            (
+                param_name(store, &["SIM_GPS1_GLTCH_Y", "SIM_GPS_GLITCH_Y"]).into(),
+                lon,
+            ),
+            (
+                param_name(store, &["SIM_GPS1_GLTCH_Z", "SIM_GPS_GLITCH_Z"]).into(),
+                alt,
+            ),
+        ]
+    };
+    let rc = |mode: RcFailure| {
+        let value = match mode {
+            RcFailure::None => 0.0,
+            RcFailure::NoPulses => 1.0,
+            RcFailure::Neutral => 2.0,
+        };
+        ("SIM_RC_FAIL".to_string(), value)
+    };
+
+    match *action {
+        SimAction::Speedup { factor } => vec![("SIM_SPEEDUP".into(), factor.max(0.1))],
+        SimAction::Wind {
+            speed_mps,
+            direction_deg,
+            turbulence,
+        } => vec![
+            ("SIM_WIND_SPD".into(), speed_mps.max(0.0)),
+            ("SIM_WIND_DIR".into(), direction_deg.rem_euclid(360.0)),
+            ("SIM_WIND_TURB".into(), turbulence.max(0.0)),
+        ],
+        SimAction::GpsGlitch {
+            lat_deg,
+            lon_deg,
+            alt_m,
+        } => glitch(lat_deg, lon_deg, alt_m),
+        SimAction::GpsEnabled { enabled } => vec![gps_enabled_write(store, enabled)],
+        SimAction::RcFailure { mode } => vec![rc(mode)],
+        SimAction::ClearFailures => {
+            let mut writes = vec![("SIM_WIND_SPD".into(), 0.0), ("SIM_WIND_TURB".into(), 0.0)];
+            writes.extend(glitch(0.0, 0.0, 0.0));
+            writes.push(gps_enabled_write(store, true));
+            writes.push(rc(RcFailure::None));
+            writes
+        }
+    }
+}
+
+/// Handle to SITL simulation controls on a `Vehicle`.
+pub struct SitlHandle<'a> {
+    vehicle: &'a Vehicle,
+}
+
+impl<'a> SitlHandle<'a> {
+    pub(crate) fn new(vehicle: &'a Vehicle) -> Self {
+        Self { vehicle }
+    }
+
+    /// Apply `action`. Requires a downloaded parameter store containing
+    /// `SIM_SPEEDUP`, so a real vehicle is never sent `SIM_*` writes.
+    pub async fn apply(&self, action: &SimAction) -> Result<(), VehicleError> {
+        let store = self.vehicle.param_store().borrow().clone();
+        if !is_sitl(&store) {
+            return Err(VehicleError::CommandNotSupported(
+                "SITL simulation control".to_string(),
+            ));
+        }
+        for (name, value) in sim_param_writes(action, &store) {
+            self.vehicle.params().write(name, value).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_speedup(&self, factor: f32) -> Result<(), VehicleError> {
+        self.apply(&SimAction::Speedup { factor }).await
+    }
+
+    pub async fn set_wind(
+        &self,
+        speed_mps: f32,
+        direction_deg: f32,
+        turbulence: f32,
+    ) -> Result<(), VehicleError> {
+        self.apply(&SimAction::Wind {
+            speed_mps,
+            direction_deg,
+            turbulence,
+        })
+        .await
+    }
+
+    pub async fn gps_glitch(
+        &self,
+        lat_deg: f32,
+        lon_deg: f32,
+        alt_m: f32,
+    ) -> Result<(), VehicleError> {
+        self.apply(&SimAction::GpsGlitch {
+            lat_deg,
+            lon_deg,
+            alt_m,
+        })
+        .await
+    }
+
+    pub async fn set_gps_enabled(&self, enabled: bool) -> Result<(), VehicleError> {
+        self.apply(&SimAction::GpsEnabled { enabled }).await
+    }
+
+    pub async fn set_rc_failure(&self, mode: RcFailure) -> Result<(), VehicleError> {
+        self.apply(&SimAction::RcFailure { mode }).await
+    }
+
+    pub async fn clear_failures(&self) -> Result<(), VehicleError> {
+        self.apply(&SimAction::ClearFailures).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_sitl_from_params() {
+        assert!(is_sitl(&ParamStore::from_values(&[("SIM_SPEEDUP", 0.0)])));
+        assert!(!is_sitl(&ParamStore::from_values(&[("RTL_ALT", 0.0)])));
+    }
+
+    #[test]
+    fn glitch_uses_legacy_names_when_present() {
+        let action = SimAction::GpsGlitch {
+            lat_deg: 0.001,
+            lon_deg: 0.0,
+            alt_m: 0.0,
+        };
+        let legacy = sim_param_writes(
+            &action,
+            &ParamStore::from_values(&[("SIM_GPS_GLITCH_X", 0.0)]),
+        );
+        assert_eq!(legacy[0], ("SIM_GPS_GLITCH_X".to_string(), 0.001));
+
+        let current = sim_param_writes(&action, &ParamStore::default());
+        assert_eq!(current[0].0, "SIM_GPS1_GLTCH_X");
+    }
+
+    #[test]
+    fn wind_direction_is_wrapped() {
+        let writes = sim_param_writes(
+            &SimAction::Wind {
+                speed_mps: 8.0,
+                direction_deg: -90.0,
+                turbulence: 0.0,
+            },
+            &ParamStore::default(),
+        );
+        assert_eq!(writes[1], ("SIM_WIND_DIR".to_string(), 270.0));
+    }
+
+    #[test]
+    fn clear_failures_restores_gps_and_rc() {
+        let writes = sim_param_writes(
+            &SimAction::ClearFailures,
+            &ParamStore::from_values(&[("SIM_GPS_DISABLE", 0.0)]),
+        );
+        assert!(writes.contains(&("SIM_GPS_DISABLE".to_string(), 0.0)));
+        assert!(writes.contains(&("SIM_RC_FAIL".to_string(), 0.0)));
+        assert!(writes.contains(&("SIM_WIND_SPD".to_string(), 0.0)));
+    }
+}
@@ -0,0 +1,345 @@
+//! Launching ArduPilot SITL for integration tests.
+//!
+//! `SitlInstance::launch` starts a SITL binary with the chosen frame and
+//! home in a scratch directory of its own, waits until it accepts MAVLink
+//! connections and kills it when dropped, so `cargo test --features
+//! sitl-harness` runs against a simulator nobody had to start by hand. Each
+//! instance takes the next free SITL instance number (`-I`), and with it
+//! its own ports, so tests can run in parallel.
+//!
+//! Unless `SitlOptions::binary` names a local build, the stable binary for
+//! the vehicle is downloaded once from firmware.ardupilot.org into
+//! `SitlOptions::cache_dir`, together with the default parameters the
+//! ArduPilot autotests fly with. Downloading uses `curl`.
+
+use crate::error::VehicleError;
+use crate::mission::HomePosition;
+use crate::Vehicle;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// TCP port of SERIAL0 of instance 0; each instance adds 10.
+const BASE_TCP_PORT: u16 = 5760;
+const PORTS_PER_INSTANCE: u16 = 10;
+/// Instance numbers tried before giving up on finding free ports.
+const MAX_INSTANCES: u16 = 64;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Lines of SITL output quoted in startup errors.
+const LOG_TAIL_LINES: usize = 20;
+const FIRMWARE_URL: &str = "https://firmware.ardupilot.org";
+const DEFAULT_PARAMS_URL: &str =
+    "https://raw.githubusercontent.com/ArduPilot/ardupilot/master/Tools/autotest/default_params";
+
+/// Next instance number to try, shared by every launch in the process.
+static NEXT_INSTANCE: AtomicU16 = AtomicU16::new(0);
+/// Held while downloading, so launches in one process fetch each file once.
+static DOWNLOADS: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SitlVehicle {
+    Copter,
+    Plane,
+    Rover,
+}
+
+impl SitlVehicle {
+    fn binary_name(self) -> &'static str {
+        match self {
+            SitlVehicle::Copter => "arducopter",
+            SitlVehicle::Plane => "arduplane",
+            SitlVehicle::Rover => "ardurover",
+        }
+    }
+
+    fn firmware_dir(self) -> &'static str {
+        match self {
+            SitlVehicle::Copter => "Copter",
+            SitlVehicle::Plane => "Plane",
+            SitlVehicle::Rover => "Rover",
+        }
+    }
+
+    fn default_params(self) -> &'static str {
+        match self {
+            SitlVehicle::Copter => "copter.parm",
+            SitlVehicle::Plane => "plane.parm",
+            SitlVehicle::Rover => "rover.parm",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SitlOptions {
+    pub vehicle: SitlVehicle,
+    /// Frame, passed as `--model` (e.g. `+`, `x`, `quadplane`, `rover`).
+    pub model: String,
+    pub home: HomePosition,
+    pub home_heading_deg: f32,
+    pub speedup: u32,
+    /// Local SITL build to run instead of downloading one.
+    pub binary: Option<PathBuf>,
+    /// Parameter file passed as `--defaults`, instead of the downloaded
+    /// autotest defaults.
+    pub defaults: Option<PathBuf>,
+    /// Where downloads are kept between runs.
+    pub cache_dir: PathBuf,
+    /// How long `launch` waits for the simulator to listen, and `connect`
+    /// for it to get a position.
+    pub ready_timeout: Duration,
+}
+
+impl SitlOptions {
+    /// A `+` quad at the home the Makefile's docker SITL uses.
+    pub fn copter() -> Self {
+        Self {
+            vehicle: SitlVehicle::Copter,
+            model: String::from("+"),
+            home: HomePosition {
+                latitude_deg: 42.3898,
+                longitude_deg: -71.1476,
+                altitude_m: 14.0,
+                altitude_datum: Default::default(),
+            },
+            home_heading_deg: 270.0,
+            speedup: 1,
+            binary: None,
+            defaults: None,
+            cache_dir: std::env::temp_dir().join("mavkit-sitl"),
+            ready_timeout: Duration::from_secs(90),
+        }
+    }
+
+    pub fn plane() -> Self {
+        Self {
+            vehicle: SitlVehicle::Plane,
+            model: String::from("plane"),
+            ..Self::copter()
+        }
+    }
+
+    pub fn rover() -> Self {
+        Self {
+            vehicle: SitlVehicle::Rover,
+            model: String::from("rover"),
+            ..Self::copter()
+        }
+    }
+}
+
+fn harness_error(message: impl Into<String>) -> VehicleError {
+    VehicleError::SitlHarness(message.into())
+}
+
+/// `url` saved as `path`, downloaded unless already there.
+fn download(url: &str, path: &Path, executable: bool) -> Result<(), VehicleError> {
+    if path.exists() {
+        return Ok(());
+    }
+    // Fetched next to the target, so concurrent launches never run a
+    // half-written file
+    let partial = path.with_extension(format!("{}.part", std::process::id()));
+    let status = Command::new("curl")
+        .args(["-fsSL", "--retry", "3", "-o"])
+        .arg(&partial)
+        .arg(url)
+        .status()
+        .map_err(|err| harness_error(format!("could not run curl: {err}")))?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&partial);
+        return Err(harness_error(format!(
+            "downloading {url} failed ({status})"
+        )));
+    }
+    if executable {
+        make_executable(&partial)?;
+    }
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), VehicleError> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), VehicleError> {
+    Ok(())
+}
+
+/// The binary and default parameters to run, downloading what the options
+/// leave out.
+fn resolve_files(options: &SitlOptions) -> Result<(PathBuf, PathBuf), VehicleError> {
+    let _downloading = DOWNLOADS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    std::fs::create_dir_all(&options.cache_dir)?;
+    let vehicle = options.vehicle;
+    let binary = match &options.binary {
+        Some(binary) => binary.clone(),
+        None => {
+            let path = options.cache_dir.join(vehicle.binary_name());
+            let url = format!(
+                "{FIRMWARE_URL}/{}/stable/SITL_x86_64_linux_gnu/{}",
+                vehicle.firmware_dir(),
+                vehicle.binary_name()
+            );
+            download(&url, &path, true)?;
+            path
+        }
+    };
+    let defaults = match &options.defaults {
+        Some(defaults) => defaults.clone(),
+        None => {
+            let path = options.cache_dir.join(vehicle.default_params());
+            let url = format!("{DEFAULT_PARAMS_URL}/{}", vehicle.default_params());
+            download(&url, &path, false)?;
+            path
+        }
+    };
+    Ok((binary, defaults))
+}
+
+/// An instance number whose SERIAL0 port nothing else listens on.
+fn free_instance() -> Result<u16, VehicleError> {
+    for _ in 0..MAX_INSTANCES {
+        let instance = NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed) % MAX_INSTANCES;
+        let port = BASE_TCP_PORT + instance * PORTS_PER_INSTANCE;
+        if std::net::TcpListener::bind(("127.0.0.1", port)).is_ok() {
+            return Ok(instance);
+        }
+    }
+    Err(harness_error("no free SITL instance ports"))
+}
+
+/// A running SITL process, killed when dropped.
+#[derive(Debug)]
+pub struct SitlInstance {
+    child: Child,
+    instance: u16,
+    work_dir: PathBuf,
+    ready_timeout: Duration,
+}
+
+impl SitlInstance {
+    /// Start SITL and wait until it accepts connections on SERIAL0.
+    pub async fn launch(options: &SitlOptions) -> Result<Self, VehicleError> {
+        let files = {
+            let options = options.clone();
+            tokio::task::spawn_blocking(move || resolve_files(&options))
+                .await
+                .map_err(|err| harness_error(err.to_string()))??
+        };
+        let (binary, defaults) = files;
+        let instance = free_instance()?;
+        let work_dir =
+            std::env::temp_dir().join(format!("mavkit-sitl-{}-{instance}", std::process::id()));
+        std::fs::create_dir_all(&work_dir)?;
+        let log = File::create(work_dir.join("sitl.log"))?;
+
+        let home = &options.home;
+        let child = Command::new(&binary)
+            .current_dir(&work_dir)
+            .arg("--model")
+            .arg(&options.model)
+            .arg("--speedup")
+            .arg(options.speedup.to_string())
+            .arg("--defaults")
+            .arg(&defaults)
+            .arg("--home")
+            .arg(format!(
+                "{},{},{},{}",
+                home.latitude_deg, home.longitude_deg, home.altitude_m, options.home_heading_deg
+            ))
+            .arg(format!("-I{instance}"))
+            // Start from the defaults rather than a previous run's eeprom
+            .arg("-w")
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()
+            .map_err(|err| harness_error(format!("could not start {}: {err}", binary.display())))?;
+        let mut sitl = SitlInstance {
+            child,
+            instance,
+            work_dir,
+            ready_timeout: options.ready_timeout,
+        };
+        sitl.wait_listening().await?;
+        Ok(sitl)
+    }
+
+    async fn wait_listening(&mut self) -> Result<(), VehicleError> {
+        let address = format!("127.0.0.1:{}", self.tcp_port());
+        let deadline = tokio::time::Instant::now() + self.ready_timeout;
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                return Err(harness_error(format!(
+                    "SITL exited during startup ({status}):\n{}",
+                    self.log_tail()
+                )));
+            }
+            // SITL waits for this first client before it starts simulating
+            if tokio::net::TcpStream::connect(&address).await.is_ok() {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(harness_error(format!(
+                    "SITL did not listen on {address} within {:?}:\n{}",
+                    self.ready_timeout,
+                    self.log_tail()
+                )));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// The end of SITL's output, for errors; the log itself goes with the
+    /// working directory.
+    fn log_tail(&self) -> String {
+        let log = std::fs::read_to_string(self.work_dir.join("sitl.log")).unwrap_or_default();
+        let lines: Vec<&str> = log.lines().collect();
+        lines[lines.len().saturating_sub(LOG_TAIL_LINES)..].join("\n")
+    }
+
+    pub fn instance(&self) -> u16 {
+        self.instance
+    }
+
+    /// Port of SERIAL0, the link a GCS uses.
+    pub fn tcp_port(&self) -> u16 {
+        BASE_TCP_PORT + self.instance * PORTS_PER_INSTANCE
+    }
+
+    /// Address for `Vehicle::connect`.
+    pub fn address(&self) -> String {
+        format!("tcpout:127.0.0.1:{}", self.tcp_port())
+    }
+
+    /// Connect to the simulator and wait for its first position, which
+    /// takes until the simulated GPS and EKF have settled.
+    pub async fn connect(&self) -> Result<Vehicle, VehicleError> {
+        let vehicle = Vehicle::connect(&self.address()).await?;
+        let mut telemetry = vehicle.telemetry();
+        let positioned = telemetry.wait_for(|t| t.latitude_deg.is_some());
+        tokio::time::timeout(self.ready_timeout, positioned)
+            .await
+            .map_err(|_| harness_error("no position from SITL"))?
+            .map_err(|_| VehicleError::Disconnected)?;
+        Ok(vehicle)
+    }
+}
+
+impl Drop for SitlInstance {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.work_dir);
+    }
+}
@@ -0,0 +1,346 @@
+//! Smoothed attitude and position for displays.
+//!
+//! On a slow radio ATTITUDE may arrive at 4 Hz and GLOBAL_POSITION_INT at
+//! 2 Hz, which makes an artificial horizon or map marker step rather than
+//! move. `Vehicle::telemetry_smoothed` publishes the same quantities at a
+//! steady rate, optionally extrapolated from their latest rate of change and
+//! passed through a first-order low-pass filter, so each frontend doesn't
+//! have to filter on its own.
+//!
+//! Prediction runs at most `MAX_PREDICTION` past the latest sample, so a
+//! lost link freezes the display instead of letting it drift away.
+
+use crate::error::VehicleError;
+use crate::state::Telemetry;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Longest extrapolation past the latest sample.
+const MAX_PREDICTION: Duration = Duration::from_millis(500);
+/// Samples further apart than this don't give a usable rate.
+const MAX_RATE_GAP: Duration = Duration::from_secs(2);
+const MAX_RATE_HZ: f64 = 120.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TelemetrySmoothing {
+    /// Low-pass time constant in seconds; 0 follows the (predicted) samples
+    /// exactly.
+    pub time_constant_s: f64,
+    /// Extrapolate each quantity from its latest rate of change between
+    /// samples.
+    pub predict: bool,
+    /// How often `Vehicle::telemetry_smoothed` updates, at most 120 Hz.
+    pub rate_hz: f64,
+}
+
+impl Default for TelemetrySmoothing {
+    fn default() -> Self {
+        Self {
+            time_constant_s: 0.1,
+            predict: true,
+            rate_hz: 30.0,
+        }
+    }
+}
+
+impl TelemetrySmoothing {
+    pub fn validate(&self) -> Result<(), VehicleError> {
+        let valid_rate = self.rate_hz > 0.0 && self.rate_hz <= MAX_RATE_HZ;
+        if !valid_rate {
+            return Err(VehicleError::InvalidSmoothing(format!(
+                "rate must be above 0 and at most {MAX_RATE_HZ} Hz"
+            )));
+        }
+        let valid_time_constant = self.time_constant_s >= 0.0 && self.time_constant_s.is_finite();
+        if !valid_time_constant {
+            return Err(VehicleError::InvalidSmoothing(
+                "time constant must be a finite number of seconds, 0 or more".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Attitude and position as shown, see `Vehicle::telemetry_smoothed`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SmoothedTelemetry {
+    pub roll_deg: Option<f64>,
+    pub pitch_deg: Option<f64>,
+    /// Wrapped to -180..180.
+    pub yaw_deg: Option<f64>,
+    pub latitude_deg: Option<f64>,
+    pub longitude_deg: Option<f64>,
+    /// Height above home.
+    pub altitude_m: Option<f64>,
+    pub altitude_amsl_m: Option<f64>,
+    /// Vehicle time the values are for, past the latest sample while
+    /// predicting.
+    pub time_boot_ms: Option<u32>,
+}
+
+impl SmoothedTelemetry {
+    /// The raw values, unfiltered.
+    fn from_telemetry(telemetry: &Telemetry) -> Self {
+        Self {
+            roll_deg: telemetry.roll_deg,
+            pitch_deg: telemetry.pitch_deg,
+            yaw_deg: telemetry.yaw_deg,
+            latitude_deg: telemetry.latitude_deg,
+            longitude_deg: telemetry.longitude_deg,
+            altitude_m: telemetry.altitude_m,
+            altitude_amsl_m: telemetry.altitude_amsl_m,
+            time_boot_ms: telemetry.time_boot_ms,
+        }
+    }
+}
+
+/// Difference `to - from`, the short way round for angles.
+fn difference(from: f64, to: f64, wraps: bool) -> f64 {
+    let delta = to - from;
+    if wraps {
+        (delta + 180.0).rem_euclid(360.0) - 180.0
+    } else {
+        delta
+    }
+}
+
+fn wrap(value: f64, wraps: bool) -> f64 {
+    if wraps {
+        difference(0.0, value, true)
+    } else {
+        value
+    }
+}
+
+/// One smoothed quantity.
+#[derive(Debug, Clone, Copy)]
+struct Channel {
+    /// Angles that wrap at ±180°.
+    wraps: bool,
+    /// Latest sample, its rate of change per second, and when it arrived.
+    sample: f64,
+    rate: f64,
+    sampled_at: Instant,
+    /// Filter output and when it was last advanced.
+    output: f64,
+    output_at: Instant,
+}
+
+impl Channel {
+    fn new(value: f64, wraps: bool, now: Instant) -> Self {
+        Self {
+            wraps,
+            sample: value,
+            rate: 0.0,
+            sampled_at: now,
+            output: value,
+            output_at: now,
+        }
+    }
+
+    fn sample(&mut self, value: f64, now: Instant) {
+        let dt = now.saturating_duration_since(self.sampled_at);
+        self.rate = if dt.is_zero() || dt > MAX_RATE_GAP {
+            0.0
+        } else {
+            difference(self.sample, value, self.wraps) / dt.as_secs_f64()
+        };
+        self.sample = value;
+        self.sampled_at = now;
+    }
+
+    fn value_at(&mut self, now: Instant, settings: &TelemetrySmoothing) -> f64 {
+        let mut target = self.sample;
+        if settings.predict {
+            let ahead = now
+                .saturating_duration_since(self.sampled_at)
+                .min(MAX_PREDICTION);
+            target += self.rate * ahead.as_secs_f64();
+        }
+        let dt = now.saturating_duration_since(self.output_at).as_secs_f64();
+        let alpha = if settings.time_constant_s > 0.0 {
+            1.0 - (-dt / settings.time_constant_s).exp()
+        } else {
+            1.0
+        };
+        self.output = wrap(
+            self.output + alpha * difference(self.output, target, self.wraps),
+            self.wraps,
+        );
+        self.output_at = now;
+        self.output
+    }
+}
+
+/// Filter state for every smoothed quantity.
+#[derive(Debug, Clone)]
+pub(crate) struct Smoother {
+    settings: TelemetrySmoothing,
+    /// In `SmoothedTelemetry` field order, without the timestamp.
+    channels: [Option<Channel>; 7],
+    /// The latest vehicle timestamp and when it arrived.
+    time_boot: Option<(u32, Instant)>,
+}
+
+impl Smoother {
+    pub(crate) fn new(settings: TelemetrySmoothing) -> Self {
+        Self {
+            settings,
+            channels: [None; 7],
+            time_boot: None,
+        }
+    }
+
+    fn raw(telemetry: &Telemetry) -> [(Option<f64>, bool); 7] {
+        [
+            (telemetry.roll_deg, true),
+            (telemetry.pitch_deg, false),
+            (telemetry.yaw_deg, true),
+            (telemetry.latitude_deg, false),
+            (telemetry.longitude_deg, true),
+            (telemetry.altitude_m, false),
+            (telemetry.altitude_amsl_m, false),
+        ]
+    }
+
+    /// Take in a telemetry update; only values that changed count as new
+    /// samples.
+    pub(crate) fn update(&mut self, telemetry: &Telemetry, now: Instant) {
+        for (channel, (value, wraps)) in self.channels.iter_mut().zip(Self::raw(telemetry)) {
+            let Some(value) = value else {
+                *channel = None;
+                continue;
+            };
+            match channel {
+                Some(channel) if channel.sample != value => channel.sample(value, now),
+                Some(_) => {}
+                None => *channel = Some(Channel::new(value, wraps, now)),
+            }
+        }
+        let time_changed = self.time_boot.map(|(ms, _)| ms) != telemetry.time_boot_ms;
+        if time_changed {
+            self.time_boot = telemetry.time_boot_ms.map(|ms| (ms, now));
+        }
+    }
+
+    pub(crate) fn value_at(&mut self, now: Instant) -> SmoothedTelemetry {
+        let settings = self.settings;
+        let [roll_deg, pitch_deg, yaw_deg, latitude_deg, longitude_deg, altitude_m, altitude_amsl_m] =
+            self.channels
+                .each_mut()
+                .map(|channel| channel.as_mut().map(|c| c.value_at(now, &settings)));
+        let time_boot_ms = self.time_boot.map(|(ms, at)| {
+            let ahead = if settings.predict {
+                now.saturating_duration_since(at).min(MAX_PREDICTION)
+            } else {
+                Duration::ZERO
+            };
+            ms.wrapping_add(ahead.as_millis() as u32)
+        });
+        SmoothedTelemetry {
+            roll_deg,
+            pitch_deg,
+            yaw_deg,
+            latitude_deg,
+            longitude_deg,
+            altitude_m,
+            altitude_amsl_m,
+            time_boot_ms,
+        }
+    }
+}
+
+/// Feed `out` from `telemetry` until the vehicle disconnects: smoothed at a
+/// fixed rate with `settings`, or the raw values on every update without.
+pub(crate) async fn run_smoothing(
+    mut telemetry: watch::Receiver<Telemetry>,
+    out: watch::Sender<SmoothedTelemetry>,
+    settings: Option<TelemetrySmoothing>,
+) {
+    let Some(settings) = settings else {
+        while telemetry.changed().await.is_ok() {
+            let raw = SmoothedTelemetry::from_telemetry(&telemetry.borrow_and_update());
+            out.send_if_modified(|shown| {
+                let changed = *shown != raw;
+                *shown = raw;
+                changed
+            });
+        }
+        return;
+    };
+
+    let mut smoother = Smoother::new(settings);
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / settings.rate_hz));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            changed = telemetry.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                smoother.update(&telemetry.borrow_and_update(), Instant::now());
+            }
+            _ = interval.tick() => {
+                let smoothed = smoother.value_at(Instant::now());
+                out.send_if_modified(|shown| {
+                    let changed = *shown != smoothed;
+                    *shown = smoothed;
+                    changed
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attitude(roll_deg: f64, yaw_deg: f64) -> Telemetry {
+        Telemetry {
+            roll_deg: Some(roll_deg),
+            yaw_deg: Some(yaw_deg),
+            ..Telemetry::default()
+        }
+    }
+
+    #[test]
+    fn predicts_between_samples_up_to_a_limit() {
+        let settings = TelemetrySmoothing {
+            time_constant_s: 0.0,
+            predict: true,
+            rate_hz: 30.0,
+        };
+        let mut smoother = Smoother::new(settings);
+        let start = Instant::now();
+        smoother.update(&attitude(0.0, 0.0), start);
+        smoother.update(&attitude(10.0, 0.0), start + Duration::from_millis(250));
+
+        let halfway = smoother.value_at(start + Duration::from_millis(375));
+        assert!((halfway.roll_deg.unwrap() - 15.0).abs() < 1e-9);
+        let stale = smoother.value_at(start + Duration::from_secs(5));
+        assert!((stale.roll_deg.unwrap() - 30.0).abs() < 1e-9);
+        assert_eq!(stale.pitch_deg, None);
+    }
+
+    #[test]
+    fn yaw_turns_the_short_way_across_north() {
+        let settings = TelemetrySmoothing {
+            time_constant_s: 0.2,
+            predict: false,
+            rate_hz: 30.0,
+        };
+        let mut smoother = Smoother::new(settings);
+        let start = Instant::now();
+        smoother.update(&attitude(0.0, 170.0), start);
+        smoother.update(&attitude(0.0, -170.0), start + Duration::from_millis(100));
+
+        let yaw = smoother
+            .value_at(start + Duration::from_millis(200))
+            .yaw_deg
+            .unwrap();
+        // Part of the 20° step past 180, not back through 0
+        assert!(yaw > 170.0 || yaw < -170.0, "yaw {yaw}");
+    }
+}
@@ -13,7 +13,11 @@ pub struct VehicleState {
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Telemetry {
     // Existing
+    /// Height above home (GLOBAL_POSITION_INT.relative_alt).
     pub altitude_m: Option<f64>,
+    /// Altitude above mean sea level (GLOBAL_POSITION_INT.alt).
+    #[serde(default)]
+    pub altitude_amsl_m: Option<f64>,
     pub speed_mps: Option<f64>,
     pub heading_deg: Option<f64>,
     pub latitude_deg: Option<f64>,
@@ -40,10 +44,20 @@ pub struct Telemetry {
     pub yaw_deg: Option<f64>,
 
     // From NAV_CONTROLLER_OUTPUT
+    /// Distance to the active waypoint.
     pub wp_dist_m: Option<f64>,
+    /// Heading the controller is steering towards.
     pub nav_bearing_deg: Option<f64>,
+    /// Bearing to the active waypoint.
     pub target_bearing_deg: Option<f64>,
+    /// Distance off the line between the previous and active waypoint.
     pub xtrack_error_m: Option<f64>,
+    /// Target altitude minus the current one, positive when below it.
+    #[serde(default)]
+    pub alt_error_m: Option<f64>,
+    /// Target airspeed minus the current one.
+    #[serde(default)]
+    pub airspeed_error_mps: Option<f64>,
 
     // From TERRAIN_REPORT
     pub terrain_height_m: Option<f64>,
@@ -60,12 +74,73 @@ pub struct Telemetry {
 
     // From SERVO_OUTPUT_RAW
     pub servo_outputs: Option<Vec<u16>>,
+
+    // From VIBRATION
+    /// Vibration level along x, y and z, m/s/s.
+    #[serde(default)]
+    pub vibration_mps2: Option<[f64; 3]>,
+    /// Clip counts of accelerometers 0-2 since boot.
+    #[serde(default)]
+    pub accel_clipping: Option<[u32; 3]>,
+
+    // Vehicle clock at the latest update (SYSTEM_TIME / TIMESYNC)
+    /// Milliseconds since vehicle boot, the time base of the onboard log.
+    #[serde(default)]
+    pub time_boot_ms: Option<u32>,
+    /// UTC by the vehicle's clock, microseconds since the Unix epoch.
+    #[serde(default)]
+    pub time_utc_us: Option<u64>,
+
+    // From EXTENDED_SYS_STATE
+    #[serde(default)]
+    pub landed_state: Option<LandedState>,
+
+    // Flight timer and odometer, since connecting
+    #[serde(default)]
+    pub armed_time_s: Option<f64>,
+    #[serde(default)]
+    pub distance_flown_m: Option<f64>,
+    #[serde(default)]
+    pub flights: Option<u32>,
+    /// Totals over every session of this airframe, when
+    /// `VehicleConfig::flight_log_dir` is set and the board has a unique ID.
+    #[serde(default)]
+    pub total_armed_time_s: Option<f64>,
+    #[serde(default)]
+    pub total_distance_flown_m: Option<f64>,
+    #[serde(default)]
+    pub total_flights: Option<u32>,
+}
+
+/// Static pack information from SMART_BATTERY_INFO, keyed by battery `id`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatteryInfo {
+    pub id: u8,
+    pub design_capacity_mah: Option<u32>,
+    pub full_capacity_mah: Option<u32>,
+    pub cycle_count: Option<u16>,
+    pub serial_number: Option<String>,
+    pub device_name: Option<String>,
+    pub manufacture_date: Option<String>,
+    pub cells_in_series: Option<u8>,
+    /// Full-charge capacity as a percentage of design capacity.
+    pub health_pct: Option<f64>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MissionState {
     pub current_seq: u16,
     pub total_items: u16,
+    /// Opaque ids of the stored mission, fence and rally plans; they change
+    /// whenever the plan does. 0 when the autopilot doesn't report them.
+    #[serde(default)]
+    pub mission_id: u32,
+    #[serde(default)]
+    pub fence_id: u32,
+    #[serde(default)]
+    pub rally_points_id: u32,
+    #[serde(default)]
+    pub mission_state: MissionRunState,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -77,18 +152,101 @@ pub enum LinkState {
     Error(String),
 }
 
+/// Height above home beyond which a vehicle without EXTENDED_SYS_STATE is
+/// taken to be off the ground.
+const AIRBORNE_MIN_ALT_M: f64 = 1.0;
+/// Vertical speed beyond which a vehicle is taken to be flying whatever its
+/// reported height.
+const AIRBORNE_MIN_CLIMB_MPS: f64 = 0.5;
+/// Below this ground speed there is no meaningful time to the waypoint.
+const ETA_MIN_SPEED_MPS: f64 = 1.0;
+
+impl Telemetry {
+    /// Off the ground, from EXTENDED_SYS_STATE when the autopilot reports
+    /// it, otherwise from the height above home. With neither known the
+    /// vehicle is assumed to be flying.
+    pub fn is_airborne(&self) -> bool {
+        match (self.landed_state, self.altitude_m) {
+            (Some(landed), _) => landed.is_airborne(),
+            (None, Some(alt)) => alt > AIRBORNE_MIN_ALT_M,
+            (None, None) => true,
+        }
+    }
+
+    /// Off the ground by the evidence at hand: EXTENDED_SYS_STATE, or else
+    /// the height above home or the vertical speed. Unlike `is_airborne`,
+    /// missing telemetry does not count as flying.
+    pub fn is_clearly_airborne(&self) -> bool {
+        if let Some(landed) = self.landed_state {
+            return landed.is_airborne();
+        }
+        self.altitude_m.is_some_and(|alt| alt > AIRBORNE_MIN_ALT_M)
+            || self
+                .climb_rate_mps
+                .is_some_and(|climb| climb.abs() > AIRBORNE_MIN_CLIMB_MPS)
+    }
+
+    /// Seconds to the active waypoint at the current ground speed, from the
+    /// autopilot's own distance (NAV_CONTROLLER_OUTPUT). `None` while
+    /// (nearly) stationary.
+    pub fn wp_eta_s(&self) -> Option<f64> {
+        let speed = self.speed_mps.filter(|speed| *speed >= ETA_MIN_SPEED_MPS)?;
+        self.wp_dist_m.map(|dist| dist / speed)
+    }
+
+    #[cfg(feature = "mission")]
+    /// Height above `home`, which need not be the vehicle's own home (e.g. a
+    /// planned home being edited). `None` without an AMSL fix or AMSL home.
+    pub fn altitude_above_m(&self, home: &crate::mission::HomePosition) -> Option<f64> {
+        self.altitude_amsl_m
+            .and_then(|amsl| home.relative_altitude_m(amsl))
+    }
+}
+
 impl Default for LinkState {
     fn default() -> Self {
         LinkState::Connecting
     }
 }
 
+/// Who the vehicle is, from its heartbeats.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VehicleIdentity {
     pub system_id: u8,
     pub component_id: u8,
     pub autopilot: AutopilotType,
     pub vehicle_type: VehicleType,
+    /// From AUTOPILOT_VERSION; `None` until it arrives.
+    pub firmware: Option<FirmwareVersion>,
+}
+
+/// Firmware version as packed in AUTOPILOT_VERSION's `flight_sw_version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl FirmwareVersion {
+    /// `None` for 0, which firmwares send when they don't report a version.
+    pub fn from_mav(flight_sw_version: u32) -> Option<Self> {
+        if flight_sw_version == 0 {
+            return None;
+        }
+        let [major, minor, patch, _release_type] = flight_sw_version.to_be_bytes();
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl std::fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -113,9 +271,10 @@ pub enum SystemStatus {
     Poweroff,
 }
 
+#[cfg(feature = "link")]
 impl SystemStatus {
-    pub(crate) fn from_mav(status: mavlink::common::MavState) -> Self {
-        use mavlink::common::MavState;
+    pub(crate) fn from_mav(status: mavlink::ardupilotmega::MavState) -> Self {
+        use mavlink::ardupilotmega::MavState;
         match status {
             MavState::MAV_STATE_BOOT => SystemStatus::Boot,
             MavState::MAV_STATE_CALIBRATING => SystemStatus::Calibrating,
@@ -145,9 +304,10 @@ pub enum VehicleType {
     Generic,
 }
 
+#[cfg(feature = "link")]
 impl VehicleType {
-    pub(crate) fn from_mav(mav_type: mavlink::common::MavType) -> Self {
-        use mavlink::common::MavType;
+    pub(crate) fn from_mav(mav_type: mavlink::ardupilotmega::MavType) -> Self {
+        use mavlink::ardupilotmega::MavType;
         match mav_type {
             MavType::MAV_TYPE_FIXED_WING => VehicleType::FixedWing,
             MavType::MAV_TYPE_QUADROTOR => VehicleType::Quadrotor,
@@ -173,9 +333,10 @@ pub enum AutopilotType {
     Px4,
 }
 
+#[cfg(feature = "link")]
 impl AutopilotType {
-    pub(crate) fn from_mav(autopilot: mavlink::common::MavAutopilot) -> Self {
-        use mavlink::common::MavAutopilot;
+    pub(crate) fn from_mav(autopilot: mavlink::ardupilotmega::MavAutopilot) -> Self {
+        use mavlink::ardupilotmega::MavAutopilot;
         match autopilot {
             MavAutopilot::MAV_AUTOPILOT_GENERIC => AutopilotType::Generic,
             MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA => AutopilotType::ArduPilotMega,
@@ -185,8 +346,8 @@ impl AutopilotType {
     }
 
     #[allow(dead_code)]
-    pub(crate) fn to_mav(self) -> mavlink::common::MavAutopilot {
-        use mavlink::common::MavAutopilot;
+    pub(crate) fn to_mav(self) -> mavlink::ardupilotmega::MavAutopilot {
+        use mavlink::ardupilotmega::MavAutopilot;
         match self {
             AutopilotType::Generic => MavAutopilot::MAV_AUTOPILOT_GENERIC,
             AutopilotType::ArduPilotMega => MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA,
@@ -208,6 +369,7 @@ pub enum GpsFixType {
     RtkFixed,
 }
 
+#[cfg(feature = "link")]
 impl GpsFixType {
     pub(crate) fn from_raw(fix_type: u8) -> Self {
         match fix_type {
@@ -221,61 +383,284 @@ impl GpsFixType {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LandedState {
+    OnGround,
+    InAir,
+    Takeoff,
+    Landing,
+}
+
+impl LandedState {
+    #[cfg(feature = "link")]
+    /// `None` for MAV_LANDED_STATE_UNDEFINED.
+    pub(crate) fn from_mav(state: mavlink::ardupilotmega::MavLandedState) -> Option<Self> {
+        use mavlink::ardupilotmega::MavLandedState;
+        match state {
+            MavLandedState::MAV_LANDED_STATE_ON_GROUND => Some(LandedState::OnGround),
+            MavLandedState::MAV_LANDED_STATE_IN_AIR => Some(LandedState::InAir),
+            MavLandedState::MAV_LANDED_STATE_TAKEOFF => Some(LandedState::Takeoff),
+            MavLandedState::MAV_LANDED_STATE_LANDING => Some(LandedState::Landing),
+            _ => None,
+        }
+    }
+
+    pub fn is_airborne(self) -> bool {
+        self != LandedState::OnGround
+    }
+}
+
+/// Execution state of the mission, from MISSION_CURRENT.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissionRunState {
+    /// The autopilot doesn't report it.
+    #[default]
+    Unknown,
+    NoMission,
+    NotStarted,
+    Active,
+    Paused,
+    Complete,
+}
+
+impl MissionRunState {
+    #[cfg(feature = "link")]
+    pub(crate) fn from_mav(state: mavlink::ardupilotmega::MissionState) -> Self {
+        use mavlink::ardupilotmega::MissionState;
+        match state {
+            MissionState::MISSION_STATE_NO_MISSION => MissionRunState::NoMission,
+            MissionState::MISSION_STATE_NOT_STARTED => MissionRunState::NotStarted,
+            MissionState::MISSION_STATE_ACTIVE => MissionRunState::Active,
+            MissionState::MISSION_STATE_PAUSED => MissionRunState::Paused,
+            MissionState::MISSION_STATE_COMPLETE => MissionRunState::Complete,
+            _ => MissionRunState::Unknown,
+        }
+    }
+}
+
+#[cfg(feature = "link")]
 /// Internal state for watch channels (writer side).
 pub(crate) struct StateWriters {
     pub vehicle_state: tokio::sync::watch::Sender<VehicleState>,
     pub telemetry: tokio::sync::watch::Sender<Telemetry>,
+    pub battery_info: tokio::sync::watch::Sender<Vec<BatteryInfo>>,
+    pub esc_telemetry: tokio::sync::watch::Sender<Vec<crate::esc::EscTelemetry>>,
+    pub remote_id: tokio::sync::watch::Sender<crate::remote_id::RemoteIdState>,
+    pub traffic: tokio::sync::watch::Sender<Vec<crate::traffic::TrafficTarget>>,
+    pub traffic_advisories: tokio::sync::watch::Sender<Vec<crate::traffic::TrafficAdvisory>>,
+    /// Hex-encoded AUTOPILOT_VERSION uid, once received.
+    pub vehicle_uid: tokio::sync::watch::Sender<Option<String>>,
+    /// The vehicle's ids and type from its last heartbeat, once one arrives.
+    pub identity: tokio::sync::watch::Sender<Option<VehicleIdentity>>,
+    /// Reboots seen since connecting.
+    pub reboots: tokio::sync::watch::Sender<u32>,
+    pub rc_channels: tokio::sync::watch::Sender<Vec<u16>>,
+    pub gcs_peers: tokio::sync::watch::Sender<Vec<crate::gcs::GcsPeer>>,
+    pub control: tokio::sync::watch::Sender<crate::control::ControlState>,
     pub home_position: tokio::sync::watch::Sender<Option<crate::mission::HomePosition>>,
     pub mission_state: tokio::sync::watch::Sender<MissionState>,
+    /// Sent on every FENCE_STATUS, even unchanged, so waiters see each one.
+    pub fence_status: tokio::sync::watch::Sender<Option<crate::geofence::FenceStatus>>,
     pub link_state: tokio::sync::watch::Sender<LinkState>,
     pub mission_progress: tokio::sync::watch::Sender<Option<crate::mission::TransferProgress>>,
-    pub param_store: tokio::sync::watch::Sender<crate::params::ParamStore>,
+    pub plan_markers: tokio::sync::watch::Sender<crate::mission::PlanMarkers>,
+    pub current_vehicle_plan: tokio::sync::watch::Sender<crate::mission::OnboardPlans>,
+    /// Opaque ids last known per plan type. Only the event loop reads it,
+    /// so it has no receiver.
+    pub plan_epochs: tokio::sync::watch::Sender<crate::mission::PlanEpochs>,
+    pub plan_changes: tokio::sync::broadcast::Sender<crate::mission::ExternalPlanChange>,
+    /// Replaced with a new `Arc` on every change, never mutated in place, so
+    /// readers can tell values apart by pointer.
+    pub param_store: tokio::sync::watch::Sender<std::sync::Arc<crate::params::ParamStore>>,
     pub param_progress: tokio::sync::watch::Sender<crate::params::ParamProgress>,
+    pub param_updates: tokio::sync::broadcast::Sender<crate::params::ParamUpdate>,
 }
 
+#[cfg(feature = "link")]
 /// Reader-side channels, cloneable via Arc.
 pub(crate) struct StateChannels {
     pub vehicle_state: tokio::sync::watch::Receiver<VehicleState>,
     pub telemetry: tokio::sync::watch::Receiver<Telemetry>,
+    pub battery_info: tokio::sync::watch::Receiver<Vec<BatteryInfo>>,
+    pub esc_telemetry: tokio::sync::watch::Receiver<Vec<crate::esc::EscTelemetry>>,
+    pub remote_id: tokio::sync::watch::Receiver<crate::remote_id::RemoteIdState>,
+    pub traffic: tokio::sync::watch::Receiver<Vec<crate::traffic::TrafficTarget>>,
+    pub traffic_advisories: tokio::sync::watch::Receiver<Vec<crate::traffic::TrafficAdvisory>>,
+    pub vehicle_uid: tokio::sync::watch::Receiver<Option<String>>,
+    pub identity: tokio::sync::watch::Receiver<Option<VehicleIdentity>>,
+    pub reboots: tokio::sync::watch::Receiver<u32>,
+    pub rc_channels: tokio::sync::watch::Receiver<Vec<u16>>,
+    pub gcs_peers: tokio::sync::watch::Receiver<Vec<crate::gcs::GcsPeer>>,
+    pub control: tokio::sync::watch::Receiver<crate::control::ControlState>,
     pub home_position: tokio::sync::watch::Receiver<Option<crate::mission::HomePosition>>,
     pub mission_state: tokio::sync::watch::Receiver<MissionState>,
+    pub fence_status: tokio::sync::watch::Receiver<Option<crate::geofence::FenceStatus>>,
     pub link_state: tokio::sync::watch::Receiver<LinkState>,
     pub mission_progress: tokio::sync::watch::Receiver<Option<crate::mission::TransferProgress>>,
-    pub param_store: tokio::sync::watch::Receiver<crate::params::ParamStore>,
+    pub plan_markers: tokio::sync::watch::Receiver<crate::mission::PlanMarkers>,
+    pub current_vehicle_plan: tokio::sync::watch::Receiver<crate::mission::OnboardPlans>,
+    /// Every change must reach each subscriber, like `param_updates`.
+    pub plan_changes: tokio::sync::broadcast::Sender<crate::mission::ExternalPlanChange>,
+    pub param_store: tokio::sync::watch::Receiver<std::sync::Arc<crate::params::ParamStore>>,
     pub param_progress: tokio::sync::watch::Receiver<crate::params::ParamProgress>,
+    /// Subscribed to per reader; every update must reach each subscriber, so
+    /// this is a broadcast rather than a watch channel.
+    pub param_updates: tokio::sync::broadcast::Sender<crate::params::ParamUpdate>,
 }
 
+#[cfg(feature = "link")]
+/// Parameter updates buffered per subscriber; a subscriber that falls
+/// further behind than this still gets the full store when a download ends.
+const PARAM_UPDATE_CAPACITY: usize = 256;
+
+#[cfg(feature = "link")]
+/// External plan changes buffered per subscriber; there are at most three
+/// per MISSION_CURRENT.
+const PLAN_CHANGE_CAPACITY: usize = 16;
+
+#[cfg(feature = "link")]
 pub(crate) fn create_channels() -> (StateWriters, StateChannels) {
     let (vs_tx, vs_rx) = tokio::sync::watch::channel(VehicleState::default());
     let (telem_tx, telem_rx) = tokio::sync::watch::channel(Telemetry::default());
+    let (bi_tx, bi_rx) = tokio::sync::watch::channel(Vec::new());
+    let (esc_tx, esc_rx) = tokio::sync::watch::channel(Vec::new());
+    let (rid_tx, rid_rx) = tokio::sync::watch::channel(crate::remote_id::RemoteIdState::default());
+    let (adsb_tx, adsb_rx) = tokio::sync::watch::channel(Vec::new());
+    let (ta_tx, ta_rx) = tokio::sync::watch::channel(Vec::new());
+    let (uid_tx, uid_rx) = tokio::sync::watch::channel(None);
+    let (id_tx, id_rx) = tokio::sync::watch::channel(None);
+    let (rb_tx, rb_rx) = tokio::sync::watch::channel(0);
+    let (rc_tx, rc_rx) = tokio::sync::watch::channel(Vec::new());
+    let (gcs_tx, gcs_rx) = tokio::sync::watch::channel(Vec::new());
+    let (ctl_tx, ctl_rx) = tokio::sync::watch::channel(crate::control::ControlState::default());
     let (home_tx, home_rx) = tokio::sync::watch::channel(None);
     let (ms_tx, ms_rx) = tokio::sync::watch::channel(MissionState::default());
+    let (fs_tx, fs_rx) = tokio::sync::watch::channel(None);
     let (ls_tx, ls_rx) = tokio::sync::watch::channel(LinkState::Connecting);
     let (mp_tx, mp_rx) = tokio::sync::watch::channel(None);
-    let (ps_tx, ps_rx) = tokio::sync::watch::channel(crate::params::ParamStore::default());
+    let (pm_tx, pm_rx) = tokio::sync::watch::channel(crate::mission::PlanMarkers::default());
+    let (op_tx, op_rx) = tokio::sync::watch::channel(crate::mission::OnboardPlans::default());
+    let pe_tx = tokio::sync::watch::Sender::new(crate::mission::PlanEpochs::default());
+    let (pc_tx, _) = tokio::sync::broadcast::channel(PLAN_CHANGE_CAPACITY);
+    let (ps_tx, ps_rx) = tokio::sync::watch::channel(std::sync::Arc::default());
     let (pp_tx, pp_rx) = tokio::sync::watch::channel(crate::params::ParamProgress::default());
+    let (pu_tx, _) = tokio::sync::broadcast::channel(PARAM_UPDATE_CAPACITY);
 
     let writers = StateWriters {
         vehicle_state: vs_tx,
         telemetry: telem_tx,
+        battery_info: bi_tx,
+        esc_telemetry: esc_tx,
+        remote_id: rid_tx,
+        traffic: adsb_tx,
+        traffic_advisories: ta_tx,
+        vehicle_uid: uid_tx,
+        identity: id_tx,
+        reboots: rb_tx,
+        rc_channels: rc_tx,
+        gcs_peers: gcs_tx,
+        control: ctl_tx,
         home_position: home_tx,
         mission_state: ms_tx,
+        fence_status: fs_tx,
         link_state: ls_tx,
         mission_progress: mp_tx,
+        plan_markers: pm_tx,
+        current_vehicle_plan: op_tx,
+        plan_epochs: pe_tx,
+        plan_changes: pc_tx.clone(),
         param_store: ps_tx,
         param_progress: pp_tx,
+        param_updates: pu_tx.clone(),
     };
 
     let channels = StateChannels {
         vehicle_state: vs_rx,
         telemetry: telem_rx,
+        battery_info: bi_rx,
+        esc_telemetry: esc_rx,
+        remote_id: rid_rx,
+        traffic: adsb_rx,
+        traffic_advisories: ta_rx,
+        vehicle_uid: uid_rx,
+        identity: id_rx,
+        reboots: rb_rx,
+        rc_channels: rc_rx,
+        gcs_peers: gcs_rx,
+        control: ctl_rx,
         home_position: home_rx,
         mission_state: ms_rx,
+        fence_status: fs_rx,
         link_state: ls_rx,
         mission_progress: mp_rx,
+        plan_markers: pm_rx,
+        current_vehicle_plan: op_rx,
+        plan_changes: pc_tx,
         param_store: ps_rx,
         param_progress: pp_rx,
+        param_updates: pu_tx,
     };
 
     (writers, channels)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clearly_airborne_needs_evidence() {
+        assert!(!Telemetry::default().is_clearly_airborne());
+        assert!(Telemetry::default().is_airborne());
+
+        let hovering = Telemetry {
+            altitude_m: Some(12.0),
+            climb_rate_mps: Some(0.0),
+            ..Telemetry::default()
+        };
+        assert!(hovering.is_clearly_airborne());
+        let climbing_out = Telemetry {
+            altitude_m: Some(0.4),
+            climb_rate_mps: Some(2.0),
+            ..Telemetry::default()
+        };
+        assert!(climbing_out.is_clearly_airborne());
+        let on_ground = Telemetry {
+            landed_state: Some(LandedState::OnGround),
+            ..hovering.clone()
+        };
+        assert!(!on_ground.is_clearly_airborne());
+    }
+
+    #[test]
+    fn wp_eta_needs_ground_speed() {
+        let cruising = Telemetry {
+            wp_dist_m: Some(300.0),
+            speed_mps: Some(12.0),
+            ..Telemetry::default()
+        };
+        assert_eq!(cruising.wp_eta_s(), Some(25.0));
+        let loitering = Telemetry {
+            speed_mps: Some(0.2),
+            ..cruising
+        };
+        assert_eq!(loitering.wp_eta_s(), None);
+    }
+
+    #[test]
+    fn firmware_version_unpacks_flight_sw_version() {
+        assert_eq!(FirmwareVersion::from_mav(0), None);
+        let version = FirmwareVersion::from_mav(0x0405_01ff).unwrap();
+        assert_eq!(
+            version,
+            FirmwareVersion {
+                major: 4,
+                minor: 5,
+                patch: 1
+            }
+        );
+        assert_eq!(version.to_string(), "4.5.1");
+    }
+}
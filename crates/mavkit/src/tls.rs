@@ -0,0 +1,278 @@
+//! TLS transport for TCP links.
+//!
+//! LTE links often end at a relay on the public internet, where plain MAVLink
+//! would be readable by anyone on the path. Here the TCP stream is wrapped in
+//! TLS, with the server certificate checked against the web PKI, a private CA,
+//! or pinned fingerprints. Reads run on an async task and writes go through
+//! the sender task; `Link` drives both like any other connection.
+//!
+//! UDP is not wrapped: run UDP links through a WireGuard (or similar) tunnel
+//! and point the endpoint at the tunnel address.
+
+use crate::error::VehicleError;
+use crate::link::Inbound;
+use mavlink::ardupilotmega::MavMessage;
+use mavlink::async_peek_reader::AsyncPeekReader;
+use mavlink::error::MessageReadError;
+use mavlink::MavHeader;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, Mutex};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use tokio_util::sync::CancellationToken;
+
+type Stream = TlsStream<TcpStream>;
+
+/// How the server certificate is checked. The default trusts the built-in
+/// web PKI roots and checks the name in the address.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsOptions {
+    /// Name the certificate must be issued to; defaults to the host part of
+    /// the address.
+    pub server_name: Option<String>,
+    /// PEM file of CA certificates to trust instead of the web PKI roots.
+    pub ca_file: Option<PathBuf>,
+    /// SHA-256 fingerprints of accepted server certificates, in hex with or
+    /// without colons. When set, only a matching certificate is accepted and
+    /// its chain is not checked, so a self-signed relay can be pinned.
+    pub pinned_sha256: Vec<String>,
+}
+
+/// A TLS session, before it is split between the link tasks.
+pub(crate) struct TlsTransport(Stream);
+
+impl TlsTransport {
+    pub(crate) fn split(self) -> (ReadHalf<Stream>, StreamWriter) {
+        let (reader, writer) = tokio::io::split(self.0);
+        (reader, StreamWriter(Mutex::new(writer)))
+    }
+}
+
+/// Connect to `addr` (`host:port`) and complete the TLS handshake.
+pub(crate) async fn connect(
+    addr: &str,
+    options: &TlsOptions,
+) -> Result<TlsTransport, VehicleError> {
+    let failed =
+        |err: &dyn std::fmt::Display| VehicleError::ConnectionFailed(format!("{addr}: {err}"));
+    let config = client_config(options).map_err(|err| failed(&err))?;
+    let name = options
+        .server_name
+        .clone()
+        .unwrap_or_else(|| host_of(addr).to_string());
+    let server_name = ServerName::try_from(name).map_err(|err| failed(&err))?;
+
+    let tcp = TcpStream::connect(addr).await.map_err(|err| failed(&err))?;
+    tcp.set_nodelay(true).map_err(|err| failed(&err))?;
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await
+        .map_err(|err| failed(&err))?;
+    Ok(TlsTransport(stream))
+}
+
+fn client_config(options: &TlsOptions) -> Result<ClientConfig, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| err.to_string())?;
+    let builder = if options.pinned_sha256.is_empty() {
+        let mut roots = RootCertStore::empty();
+        match &options.ca_file {
+            Some(path) => {
+                let certs = CertificateDer::pem_file_iter(path)
+                    .map_err(|err| format!("{}: {err}", path.display()))?;
+                for cert in certs {
+                    let cert = cert.map_err(|err| format!("{}: {err}", path.display()))?;
+                    roots.add(cert).map_err(|err| err.to_string())?;
+                }
+            }
+            None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+        builder.with_root_certificates(roots)
+    } else {
+        let pins = options
+            .pinned_sha256
+            .iter()
+            .map(|pin| parse_pin(pin))
+            .collect::<Result<_, _>>()?;
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+                pins,
+                algorithms: provider.signature_verification_algorithms,
+            }))
+    };
+    Ok(builder.with_no_client_auth())
+}
+
+/// `host` of `host:port` or `[v6]:port`.
+fn host_of(addr: &str) -> &str {
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+fn parse_pin(pin: &str) -> Result<[u8; 32], String> {
+    let invalid = || format!("invalid certificate pin {pin:?}: expected a SHA-256 fingerprint");
+    let hex: String = pin.chars().filter(|c| *c != ':').collect();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(digest)
+}
+
+/// Accepts exactly the pinned certificates. Handshake signatures are still
+/// verified, so the server must hold the pinned certificate's key.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pins: Vec<[u8; 32]>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl PinnedCertVerifier {
+    fn is_pinned(&self, cert: &CertificateDer<'_>) -> bool {
+        let digest: [u8; 32] = Sha256::digest(cert.as_ref()).into();
+        self.pins.contains(&digest)
+    }
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.is_pinned(end_entity) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// Read MAVLink v2 frames from the session until `stop` fires, every receiver
+/// is gone, or the stream fails.
+pub(crate) async fn read_loop(
+    stream: ReadHalf<Stream>,
+    tx: broadcast::Sender<Inbound>,
+    stop: CancellationToken,
+) {
+    let mut reader = AsyncPeekReader::new(stream);
+    loop {
+        let result = tokio::select! {
+            _ = stop.cancelled() => return,
+            result = mavlink::read_v2_msg_async::<MavMessage, _>(&mut reader) => result,
+        };
+        let inbound = match result {
+            Ok(received) => Ok(Arc::new(received)),
+            // A corrupt frame; the next read resynchronizes on the magic byte
+            Err(MessageReadError::Parse(_)) => continue,
+            Err(err) => Err(err.to_string()),
+        };
+        let failed = inbound.is_err();
+        if tx.send(inbound).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Write half of a TLS session, owned by the sender task.
+pub(crate) struct StreamWriter(Mutex<WriteHalf<Stream>>);
+
+impl StreamWriter {
+    pub(crate) async fn send(
+        &self,
+        header: &MavHeader,
+        message: &MavMessage,
+    ) -> Result<(), String> {
+        let mut stream = self.0.lock().await;
+        mavlink::write_v2_msg_async(&mut *stream, *header, message)
+            .await
+            .map_err(|err| err.to_string())?;
+        // Push the record out now rather than when the buffer fills
+        stream.flush().await.map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SHA-256 of "abc".
+    const ABC_SHA256: &str = "BA:78:16:BF:8F:01:CF:EA:41:41:40:DE:5D:AE:22:23:\
+                              B0:03:61:A3:96:17:7A:9C:B4:10:FF:61:F2:00:15:AD";
+
+    #[test]
+    fn options_default_to_web_pki() {
+        let options: TlsOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options, TlsOptions::default());
+        assert!(options.pinned_sha256.is_empty());
+    }
+
+    #[test]
+    fn host_is_taken_from_the_address() {
+        assert_eq!(host_of("relay.example.com:5760"), "relay.example.com");
+        assert_eq!(host_of("203.0.113.7:5760"), "203.0.113.7");
+        assert_eq!(host_of("[2001:db8::1]:5760"), "2001:db8::1");
+    }
+
+    #[test]
+    fn pins_match_certificate_fingerprints() {
+        let with_colons = parse_pin(ABC_SHA256).unwrap();
+        let plain = parse_pin(&ABC_SHA256.replace(':', "").to_lowercase()).unwrap();
+        assert_eq!(with_colons, plain);
+        assert!(parse_pin("ba7816bf").is_err());
+        assert!(parse_pin(&"zz".repeat(32)).is_err());
+
+        let verifier = PinnedCertVerifier {
+            pins: vec![plain],
+            algorithms: rustls::crypto::ring::default_provider().signature_verification_algorithms,
+        };
+        assert!(verifier.is_pinned(&CertificateDer::from(b"abc".to_vec())));
+        assert!(!verifier.is_pinned(&CertificateDer::from(b"abd".to_vec())));
+    }
+}
@@ -0,0 +1,298 @@
+//! ADS-B traffic and avoidance advisories.
+//!
+//! ADSB_VEHICLE reports from the vehicle's receiver build a traffic table.
+//! Each target is projected against our own track, assuming both keep their
+//! current velocity, to find the closest point of approach (CPA). A target
+//! that will come within the conflict volume inside the look-ahead window
+//! yields an advisory with a suggested altitude change. Nothing is commanded;
+//! the operator decides.
+
+use crate::mission::geo::LocalFrame;
+use crate::state::Telemetry;
+use mavlink::ardupilotmega::{AdsbFlags, ADSB_VEHICLE_DATA};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Targets not heard from for this long are dropped.
+pub const TRAFFIC_TIMEOUT: Duration = Duration::from_secs(20);
+/// Horizontal separation at the CPA below which traffic is a conflict.
+pub const CONFLICT_HORIZONTAL_M: f64 = 600.0;
+/// Vertical separation at the CPA below which traffic is a conflict.
+pub const CONFLICT_VERTICAL_M: f64 = 150.0;
+/// CPAs further ahead than this are ignored.
+pub const CONFLICT_LOOKAHEAD_S: f64 = 60.0;
+/// Suggested altitude changes are rounded up to this step.
+const SUGGESTION_STEP_M: f64 = 10.0;
+/// Descents are not suggested below this height above home.
+const MIN_SUGGESTED_HEIGHT_M: f64 = 30.0;
+
+/// One aircraft reported by ADSB_VEHICLE.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrafficTarget {
+    pub icao_address: u32,
+    pub callsign: Option<String>,
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    /// Barometric (QNH) or geometric altitude, whichever the target reports.
+    pub altitude_amsl_m: Option<f64>,
+    pub heading_deg: Option<f64>,
+    pub ground_speed_mps: Option<f64>,
+    /// Positive up.
+    pub vertical_speed_mps: Option<f64>,
+    pub squawk: u16,
+    #[serde(skip)]
+    pub(crate) last_seen: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AvoidanceSuggestion {
+    Climb { by_m: f64 },
+    Descend { by_m: f64 },
+}
+
+/// Predicted conflict with one target.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrafficAdvisory {
+    pub icao_address: u32,
+    pub callsign: Option<String>,
+    /// Time until the CPA; 0 when the target is already moving away.
+    pub cpa_time_s: f64,
+    /// Horizontal separation at the CPA.
+    pub cpa_distance_m: f64,
+    /// Target altitude minus ours at the CPA; positive when it passes above.
+    pub cpa_vertical_m: f64,
+    pub suggestion: AvoidanceSuggestion,
+}
+
+/// Fold an ADSB_VEHICLE report into the traffic table. Reports without
+/// valid coordinates are ignored; returns whether the table changed.
+pub(crate) fn merge_adsb(
+    traffic: &mut Vec<TrafficTarget>,
+    data: &ADSB_VEHICLE_DATA,
+    now: Instant,
+) -> bool {
+    let valid = |flag| data.flags.contains(flag);
+    if !valid(AdsbFlags::ADSB_FLAGS_VALID_COORDS) {
+        return false;
+    }
+    let velocity = valid(AdsbFlags::ADSB_FLAGS_VALID_VELOCITY);
+    let target = TrafficTarget {
+        icao_address: data.ICAO_address,
+        callsign: valid(AdsbFlags::ADSB_FLAGS_VALID_CALLSIGN)
+            .then(|| data.callsign.to_str().unwrap_or("").trim().to_string())
+            .filter(|callsign| !callsign.is_empty()),
+        latitude_deg: data.lat as f64 / 1e7,
+        longitude_deg: data.lon as f64 / 1e7,
+        altitude_amsl_m: valid(AdsbFlags::ADSB_FLAGS_VALID_ALTITUDE)
+            .then_some(data.altitude as f64 / 1000.0),
+        heading_deg: valid(AdsbFlags::ADSB_FLAGS_VALID_HEADING)
+            .then_some(data.heading as f64 / 100.0),
+        ground_speed_mps: velocity.then_some(data.hor_velocity as f64 / 100.0),
+        vertical_speed_mps: velocity.then_some(data.ver_velocity as f64 / 100.0),
+        squawk: data.squawk,
+        last_seen: Some(now),
+    };
+    match traffic
+        .iter_mut()
+        .find(|known| known.icao_address == target.icao_address)
+    {
+        Some(known) => *known = target,
+        None => traffic.push(target),
+    }
+    true
+}
+
+/// Drop targets not heard from within `TRAFFIC_TIMEOUT`. Returns whether
+/// any were dropped.
+pub(crate) fn prune_traffic(traffic: &mut Vec<TrafficTarget>, now: Instant) -> bool {
+    let before = traffic.len();
+    traffic.retain(|target| {
+        target
+            .last_seen
+            .is_some_and(|seen| now.saturating_duration_since(seen) < TRAFFIC_TIMEOUT)
+    });
+    traffic.len() != before
+}
+
+/// Conflicts between our track and `traffic`, soonest first. Empty while our
+/// own position or AMSL altitude is unknown; targets without an altitude are
+/// skipped.
+pub fn traffic_advisories(own: &Telemetry, traffic: &[TrafficTarget]) -> Vec<TrafficAdvisory> {
+    let (Some(lat), Some(lon), Some(own_alt)) =
+        (own.latitude_deg, own.longitude_deg, own.altitude_amsl_m)
+    else {
+        return Vec::new();
+    };
+    let frame = LocalFrame::new(lat, lon);
+    let own_velocity = velocity(own.heading_deg, own.speed_mps);
+    let own_climb = own.climb_rate_mps.unwrap_or(0.0);
+
+    let mut advisories: Vec<TrafficAdvisory> = traffic
+        .iter()
+        .filter_map(|target| {
+            let target_alt = target.altitude_amsl_m?;
+            let (east, north) = frame.to_local(target.latitude_deg, target.longitude_deg);
+            let target_velocity = velocity(target.heading_deg, target.ground_speed_mps);
+            let (vx, vy) = (
+                target_velocity.0 - own_velocity.0,
+                target_velocity.1 - own_velocity.1,
+            );
+            let speed_sq = vx * vx + vy * vy;
+            let time = if speed_sq > 1e-6 {
+                (-(east * vx + north * vy) / speed_sq).max(0.0)
+            } else {
+                0.0
+            };
+            if time > CONFLICT_LOOKAHEAD_S {
+                return None;
+            }
+            let distance = (east + vx * time).hypot(north + vy * time);
+            let vertical = (target_alt + target.vertical_speed_mps.unwrap_or(0.0) * time)
+                - (own_alt + own_climb * time);
+            if distance >= CONFLICT_HORIZONTAL_M || vertical.abs() >= CONFLICT_VERTICAL_M {
+                return None;
+            }
+            Some(TrafficAdvisory {
+                icao_address: target.icao_address,
+                callsign: target.callsign.clone(),
+                cpa_time_s: time,
+                cpa_distance_m: distance,
+                cpa_vertical_m: vertical,
+                suggestion: suggest(vertical, own.altitude_m),
+            })
+        })
+        .collect();
+    advisories.sort_by(|a, b| a.cpa_time_s.total_cmp(&b.cpa_time_s));
+    advisories
+}
+
+/// East/north velocity from a course and ground speed; still when unknown.
+fn velocity(heading_deg: Option<f64>, speed_mps: Option<f64>) -> (f64, f64) {
+    match (heading_deg, speed_mps) {
+        (Some(heading), Some(speed)) => {
+            let (sin, cos) = heading.to_radians().sin_cos();
+            (speed * sin, speed * cos)
+        }
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Move away from traffic `vertical` metres above us (below when negative)
+/// until the separation reaches `CONFLICT_VERTICAL_M`. Descents that would
+/// take us below `MIN_SUGGESTED_HEIGHT_M` become a climb over the traffic.
+fn suggest(vertical: f64, height_m: Option<f64>) -> AvoidanceSuggestion {
+    let round_up = |m: f64| (m / SUGGESTION_STEP_M).ceil() * SUGGESTION_STEP_M;
+    if vertical < 0.0 {
+        return AvoidanceSuggestion::Climb {
+            by_m: round_up(CONFLICT_VERTICAL_M + vertical),
+        };
+    }
+    let descent = round_up(CONFLICT_VERTICAL_M - vertical);
+    match height_m {
+        Some(height) if height - descent >= MIN_SUGGESTED_HEIGHT_M => {
+            AvoidanceSuggestion::Descend { by_m: descent }
+        }
+        _ => AvoidanceSuggestion::Climb {
+            by_m: round_up(CONFLICT_VERTICAL_M + vertical),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::geo::destination;
+
+    fn own() -> Telemetry {
+        Telemetry {
+            latitude_deg: Some(47.0),
+            longitude_deg: Some(8.0),
+            altitude_amsl_m: Some(600.0),
+            altitude_m: Some(200.0),
+            heading_deg: Some(0.0),
+            speed_mps: Some(20.0),
+            climb_rate_mps: Some(0.0),
+            ..Telemetry::default()
+        }
+    }
+
+    fn target(north_m: f64, alt: f64, heading: f64, speed: f64) -> TrafficTarget {
+        let (lat, lon) = destination(47.0, 8.0, 0.0, north_m);
+        TrafficTarget {
+            icao_address: 0xABCDEF,
+            callsign: Some("TEST1".to_string()),
+            latitude_deg: lat,
+            longitude_deg: lon,
+            altitude_amsl_m: Some(alt),
+            heading_deg: Some(heading),
+            ground_speed_mps: Some(speed),
+            vertical_speed_mps: Some(0.0),
+            squawk: 7000,
+            last_seen: Some(Instant::now()),
+        }
+    }
+
+    #[test]
+    fn head_on_traffic_above_suggests_descending() {
+        // 2 km ahead, closing at 60 m/s, 50 m above
+        let traffic = [target(2000.0, 650.0, 180.0, 40.0)];
+        let advisories = traffic_advisories(&own(), &traffic);
+        assert_eq!(advisories.len(), 1);
+        let advisory = &advisories[0];
+        assert!((advisory.cpa_time_s - 33.3).abs() < 0.1, "{advisory:?}");
+        assert!(advisory.cpa_distance_m < 1.0);
+        assert!((advisory.cpa_vertical_m - 50.0).abs() < 1e-6);
+        assert_eq!(
+            advisory.suggestion,
+            AvoidanceSuggestion::Descend { by_m: 100.0 }
+        );
+    }
+
+    #[test]
+    fn low_vehicle_climbs_over_instead() {
+        let mut own = own();
+        own.altitude_m = Some(60.0);
+        let traffic = [target(2000.0, 620.0, 180.0, 40.0)];
+        let advisories = traffic_advisories(&own, &traffic);
+        assert_eq!(
+            advisories[0].suggestion,
+            AvoidanceSuggestion::Climb { by_m: 170.0 }
+        );
+
+        let below = [target(2000.0, 560.0, 180.0, 40.0)];
+        let advisories = traffic_advisories(&own, &below);
+        assert_eq!(
+            advisories[0].suggestion,
+            AvoidanceSuggestion::Climb { by_m: 110.0 }
+        );
+    }
+
+    #[test]
+    fn separated_diverging_or_distant_traffic_is_no_conflict() {
+        let own = own();
+        // Well above
+        assert!(traffic_advisories(&own, &[target(2000.0, 900.0, 180.0, 40.0)]).is_empty());
+        // Behind and flying away
+        assert!(traffic_advisories(&own, &[target(-2000.0, 600.0, 180.0, 40.0)]).is_empty());
+        // Closing, but the CPA is beyond the look-ahead
+        assert!(traffic_advisories(&own, &[target(8000.0, 600.0, 180.0, 40.0)]).is_empty());
+        // Our own position unknown
+        assert!(
+            traffic_advisories(&Telemetry::default(), &[target(500.0, 600.0, 180.0, 40.0)])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn silent_targets_expire() {
+        let now = Instant::now();
+        let mut traffic = vec![TrafficTarget {
+            last_seen: Some(now),
+            ..target(1000.0, 600.0, 0.0, 0.0)
+        }];
+        assert!(!prune_traffic(&mut traffic, now));
+        assert!(prune_traffic(&mut traffic, now + TRAFFIC_TIMEOUT));
+        assert!(traffic.is_empty());
+    }
+}
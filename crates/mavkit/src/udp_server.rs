@@ -0,0 +1,194 @@
+//! UDP server (`udpin`) transport that follows the vehicle's address.
+//!
+//! A vehicle behind an LTE modem reaches us from whatever address its
+//! carrier's NAT hands out, and that address can change after a network
+//! blip. Replies go to the address the last valid MAVLink frame came from,
+//! so commands keep reaching the vehicle once it is heard from again. Only
+//! datagrams holding a frame that passes its checksum move the peer, so
+//! stray traffic on the port cannot redirect the link.
+//!
+//! The current peer is published on a watch channel; `Vehicle::udp_peer`
+//! hands it out.
+
+use crate::error::VehicleError;
+use crate::link::Inbound;
+use crate::vehicle::connect_error;
+use mavlink::ardupilotmega::MavMessage;
+use mavlink::error::MessageReadError;
+use mavlink::peek_reader::PeekReader;
+use mavlink::MavHeader;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::sync::{broadcast, watch};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// Largest datagram read; MAVLink frames are at most 280 bytes, but a
+/// datagram may carry several.
+const MAX_DATAGRAM: usize = 65_507;
+
+/// A bound socket, before it is split between the link tasks.
+pub(crate) struct UdpServerTransport {
+    socket: Arc<UdpSocket>,
+    peer: watch::Sender<Option<SocketAddr>>,
+}
+
+/// Bind `addr` (`host:port`) and wait for the vehicle to send first.
+pub(crate) async fn bind(addr: &str) -> Result<UdpServerTransport, VehicleError> {
+    let socket = UdpSocket::bind(addr)
+        .await
+        .map_err(|err| connect_error(addr, &err))?;
+    Ok(UdpServerTransport {
+        socket: Arc::new(socket),
+        peer: watch::channel(None).0,
+    })
+}
+
+impl UdpServerTransport {
+    /// The address replies go to; `None` until the vehicle is heard from.
+    pub(crate) fn peer(&self) -> watch::Receiver<Option<SocketAddr>> {
+        self.peer.subscribe()
+    }
+
+    pub(crate) fn split(self) -> (PeerReader, PeerWriter) {
+        let writer = PeerWriter {
+            socket: self.socket.clone(),
+            peer: self.peer.subscribe(),
+        };
+        let reader = PeerReader {
+            socket: self.socket,
+            peer: self.peer,
+        };
+        (reader, writer)
+    }
+}
+
+/// Read side of the socket, which also tracks the peer.
+pub(crate) struct PeerReader {
+    socket: Arc<UdpSocket>,
+    peer: watch::Sender<Option<SocketAddr>>,
+}
+
+/// Read datagrams until `stop` fires, every receiver is gone, or the socket
+/// fails, moving the peer to the sender of each valid frame.
+pub(crate) async fn read_loop(
+    reader: PeerReader,
+    tx: broadcast::Sender<Inbound>,
+    stop: CancellationToken,
+) {
+    let mut datagram = vec![0u8; MAX_DATAGRAM];
+    loop {
+        let result = tokio::select! {
+            _ = stop.cancelled() => return,
+            result = reader.socket.recv_from(&mut datagram) => result,
+        };
+        let (len, from) = match result {
+            Ok(received) => received,
+            Err(err) => {
+                let _ = tx.send(Err(err.to_string()));
+                return;
+            }
+        };
+        let frames = parse_frames(&datagram[..len]);
+        if frames.is_empty() {
+            continue;
+        }
+        reader.peer.send_if_modified(|peer| {
+            let moved = *peer != Some(from);
+            if moved {
+                if let Some(old) = *peer {
+                    warn!("UDP peer moved from {old} to {from}");
+                }
+            }
+            *peer = Some(from);
+            moved
+        });
+        for frame in frames {
+            if tx.send(Ok(Arc::new(frame))).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// The MAVLink v2 frames in one datagram; corrupt ones are skipped.
+fn parse_frames(datagram: &[u8]) -> Vec<(MavHeader, MavMessage)> {
+    let mut reader = PeekReader::new(datagram);
+    let mut frames = Vec::new();
+    loop {
+        match mavlink::read_v2_msg::<MavMessage, _>(&mut reader) {
+            Ok(frame) => frames.push(frame),
+            // A corrupt frame; the next read resynchronizes on the magic byte
+            Err(MessageReadError::Parse(_)) => continue,
+            // End of the datagram
+            Err(MessageReadError::Io(_)) => return frames,
+        }
+    }
+}
+
+/// Write side of the socket, sending to the current peer.
+pub(crate) struct PeerWriter {
+    socket: Arc<UdpSocket>,
+    peer: watch::Receiver<Option<SocketAddr>>,
+}
+
+impl PeerWriter {
+    /// Dropped without error until the vehicle has been heard from, as
+    /// there is nowhere to send it.
+    pub(crate) async fn send(
+        &self,
+        header: &MavHeader,
+        message: &MavMessage,
+    ) -> Result<(), String> {
+        let Some(peer) = *self.peer.borrow() else {
+            return Ok(());
+        };
+        let mut frame = Vec::new();
+        mavlink::write_v2_msg(&mut frame, *header, message).map_err(|err| err.to_string())?;
+        self.socket
+            .send_to(&frame, peer)
+            .await
+            .map(drop)
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mavlink::ardupilotmega as common;
+
+    fn frame(sequence: u8) -> Vec<u8> {
+        let header = MavHeader {
+            system_id: 1,
+            component_id: 1,
+            sequence,
+        };
+        let heartbeat = MavMessage::HEARTBEAT(common::HEARTBEAT_DATA::default());
+        let mut frame = Vec::new();
+        mavlink::write_v2_msg(&mut frame, header, &heartbeat).unwrap();
+        frame
+    }
+
+    #[test]
+    fn datagram_may_hold_several_frames() {
+        let mut datagram = frame(1);
+        datagram.extend(frame(2));
+        let frames = parse_frames(&datagram);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].0.sequence, 2);
+    }
+
+    #[test]
+    fn corrupt_frames_are_skipped() {
+        let mut corrupt = frame(1);
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0xFF;
+        assert!(parse_frames(&corrupt).is_empty());
+        assert!(parse_frames(b"not mavlink").is_empty());
+
+        corrupt.extend(frame(2));
+        assert_eq!(parse_frames(&corrupt).len(), 1);
+    }
+}
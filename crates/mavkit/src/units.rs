@@ -0,0 +1,321 @@
+//! Display units for telemetry and planning values.
+//!
+//! Everything inside mavkit is SI (metres, metres per second). Conversion to
+//! the operator's preferred units happens once, here, so the frontend only
+//! formats numbers it is handed.
+
+use serde::{Deserialize, Serialize};
+
+use crate::coords::{format_coordinate, CoordinateFormat};
+use crate::state::Telemetry;
+
+const FEET_PER_METER: f64 = 1.0 / 0.3048;
+const KNOTS_PER_MPS: f64 = 3600.0 / 1852.0;
+const MPH_PER_MPS: f64 = 3600.0 / 1609.344;
+const KPH_PER_MPS: f64 = 3.6;
+const FEET_PER_MILE: f64 = 5280.0;
+const METERS_PER_NMI: f64 = 1852.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Serialize, Deserialize)]
+pub struct Meters(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Serialize, Deserialize)]
+pub struct Feet(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Serialize, Deserialize)]
+pub struct MetersPerSecond(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Serialize, Deserialize)]
+pub struct Knots(pub f64);
+
+impl From<Meters> for Feet {
+    fn from(m: Meters) -> Self {
+        Feet(m.0 * FEET_PER_METER)
+    }
+}
+
+impl From<Feet> for Meters {
+    fn from(ft: Feet) -> Self {
+        Meters(ft.0 / FEET_PER_METER)
+    }
+}
+
+impl From<MetersPerSecond> for Knots {
+    fn from(v: MetersPerSecond) -> Self {
+        Knots(v.0 * KNOTS_PER_MPS)
+    }
+}
+
+impl From<Knots> for MetersPerSecond {
+    fn from(kn: Knots) -> Self {
+        MetersPerSecond(kn.0 / KNOTS_PER_MPS)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthUnit {
+    #[default]
+    Meters,
+    Feet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeedUnit {
+    #[default]
+    MetersPerSecond,
+    KilometersPerHour,
+    Knots,
+    MilesPerHour,
+    /// For climb rate.
+    FeetPerMinute,
+}
+
+/// Unit for long distances (waypoint distance, range); short lengths use
+/// `LengthUnit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceUnit {
+    #[default]
+    Meters,
+    Feet,
+    NauticalMiles,
+    Miles,
+}
+
+/// A converted value and the label to show next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DisplayValue {
+    pub value: f64,
+    pub unit: &'static str,
+}
+
+/// Operator unit preferences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Units {
+    /// Altitudes and heights.
+    pub altitude: LengthUnit,
+    /// Ground speed and airspeed.
+    pub speed: SpeedUnit,
+    /// Climb rate.
+    pub vertical_speed: SpeedUnit,
+    pub distance: DistanceUnit,
+    /// How positions are shown and read.
+    #[serde(default)]
+    pub coordinates: CoordinateFormat,
+}
+
+impl Units {
+    pub fn metric() -> Self {
+        Self::default()
+    }
+
+    /// Feet, miles per hour and feet per minute.
+    pub fn imperial() -> Self {
+        Self {
+            altitude: LengthUnit::Feet,
+            speed: SpeedUnit::MilesPerHour,
+            vertical_speed: SpeedUnit::FeetPerMinute,
+            distance: DistanceUnit::Miles,
+            coordinates: CoordinateFormat::DecimalDegrees,
+        }
+    }
+
+    /// Feet, knots, feet per minute and nautical miles.
+    pub fn aviation() -> Self {
+        Self {
+            altitude: LengthUnit::Feet,
+            speed: SpeedUnit::Knots,
+            vertical_speed: SpeedUnit::FeetPerMinute,
+            distance: DistanceUnit::NauticalMiles,
+            coordinates: CoordinateFormat::DegreesMinutes,
+        }
+    }
+
+    pub fn altitude(&self, m: Meters) -> DisplayValue {
+        length(self.altitude, m)
+    }
+
+    pub fn speed(&self, v: MetersPerSecond) -> DisplayValue {
+        speed(self.speed, v)
+    }
+
+    pub fn vertical_speed(&self, v: MetersPerSecond) -> DisplayValue {
+        speed(self.vertical_speed, v)
+    }
+
+    pub fn distance(&self, m: Meters) -> DisplayValue {
+        match self.distance {
+            DistanceUnit::Meters => length(LengthUnit::Meters, m),
+            DistanceUnit::Feet => length(LengthUnit::Feet, m),
+            DistanceUnit::NauticalMiles => DisplayValue {
+                value: m.0 / METERS_PER_NMI,
+                unit: "NM",
+            },
+            DistanceUnit::Miles => DisplayValue {
+                value: Feet::from(m).0 / FEET_PER_MILE,
+                unit: "mi",
+            },
+        }
+    }
+
+    /// Convert an altitude entered in display units back to metres.
+    pub fn altitude_to_meters(&self, value: f64) -> Meters {
+        match self.altitude {
+            LengthUnit::Meters => Meters(value),
+            LengthUnit::Feet => Feet(value).into(),
+        }
+    }
+
+    /// Convert a speed entered in display units back to metres per second.
+    pub fn speed_to_mps(&self, value: f64) -> MetersPerSecond {
+        match self.speed {
+            SpeedUnit::MetersPerSecond => MetersPerSecond(value),
+            SpeedUnit::KilometersPerHour => MetersPerSecond(value / KPH_PER_MPS),
+            SpeedUnit::Knots => Knots(value).into(),
+            SpeedUnit::MilesPerHour => MetersPerSecond(value / MPH_PER_MPS),
+            SpeedUnit::FeetPerMinute => MetersPerSecond(Meters::from(Feet(value)).0 / 60.0),
+        }
+    }
+}
+
+fn length(unit: LengthUnit, m: Meters) -> DisplayValue {
+    match unit {
+        LengthUnit::Meters => DisplayValue {
+            value: m.0,
+            unit: "m",
+        },
+        LengthUnit::Feet => DisplayValue {
+            value: Feet::from(m).0,
+            unit: "ft",
+        },
+    }
+}
+
+fn speed(unit: SpeedUnit, v: MetersPerSecond) -> DisplayValue {
+    match unit {
+        SpeedUnit::MetersPerSecond => DisplayValue {
+            value: v.0,
+            unit: "m/s",
+        },
+        SpeedUnit::KilometersPerHour => DisplayValue {
+            value: v.0 * KPH_PER_MPS,
+            unit: "km/h",
+        },
+        SpeedUnit::Knots => DisplayValue {
+            value: Knots::from(v).0,
+            unit: "kn",
+        },
+        SpeedUnit::MilesPerHour => DisplayValue {
+            value: v.0 * MPH_PER_MPS,
+            unit: "mph",
+        },
+        SpeedUnit::FeetPerMinute => DisplayValue {
+            value: Feet::from(Meters(v.0)).0 * 60.0,
+            unit: "ft/min",
+        },
+    }
+}
+
+/// Telemetry values that depend on the unit preference, already converted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DisplayTelemetry {
+    pub altitude: Option<DisplayValue>,
+    pub altitude_amsl: Option<DisplayValue>,
+    pub speed: Option<DisplayValue>,
+    pub airspeed: Option<DisplayValue>,
+    pub climb_rate: Option<DisplayValue>,
+    pub wp_dist: Option<DisplayValue>,
+    pub height_above_terrain: Option<DisplayValue>,
+    /// Position in the preferred coordinate format.
+    pub position: Option<String>,
+}
+
+pub fn display_telemetry(telemetry: &Telemetry, units: &Units) -> DisplayTelemetry {
+    let alt = |v: Option<f64>| v.map(|m| units.altitude(Meters(m)));
+    let spd = |v: Option<f64>| v.map(|mps| units.speed(MetersPerSecond(mps)));
+    DisplayTelemetry {
+        altitude: alt(telemetry.altitude_m),
+        altitude_amsl: alt(telemetry.altitude_amsl_m),
+        speed: spd(telemetry.speed_mps),
+        airspeed: spd(telemetry.airspeed_mps),
+        climb_rate: telemetry
+            .climb_rate_mps
+            .map(|mps| units.vertical_speed(MetersPerSecond(mps))),
+        wp_dist: telemetry.wp_dist_m.map(|m| units.distance(Meters(m))),
+        height_above_terrain: alt(telemetry.height_above_terrain_m),
+        position: telemetry
+            .latitude_deg
+            .zip(telemetry.longitude_deg)
+            .and_then(|(lat, lon)| format_coordinate(lat, lon, units.coordinates).ok()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn length_conversions_roundtrip() {
+        assert!(close(Feet::from(Meters(0.3048)).0, 1.0));
+        assert!(close(Meters::from(Feet::from(Meters(123.4))).0, 123.4));
+    }
+
+    #[test]
+    fn speed_units_convert_from_mps() {
+        let v = MetersPerSecond(10.0);
+        assert!(close(Units::aviation().speed(v).value, 19.438444924));
+        assert_eq!(Units::aviation().speed(v).unit, "kn");
+        assert!(close(Units::imperial().speed(v).value, 22.369362921));
+        let kph = Units {
+            speed: SpeedUnit::KilometersPerHour,
+            ..Units::metric()
+        };
+        assert!(close(kph.speed(v).value, 36.0));
+        assert!(close(kph.speed_to_mps(36.0).0, 10.0));
+        let climb = Units::aviation().vertical_speed(MetersPerSecond(0.508));
+        assert!(close(climb.value, 100.0));
+        assert_eq!(climb.unit, "ft/min");
+    }
+
+    #[test]
+    fn distances_use_long_units() {
+        assert!(close(Units::aviation().distance(Meters(1852.0)).value, 1.0));
+        assert!(close(
+            Units::imperial().distance(Meters(1609.344)).value,
+            1.0
+        ));
+        assert_eq!(Units::metric().distance(Meters(250.0)).unit, "m");
+    }
+
+    #[test]
+    fn display_telemetry_converts_present_fields_only() {
+        let telemetry = Telemetry {
+            altitude_m: Some(100.0),
+            speed_mps: Some(5.0),
+            latitude_deg: Some(60.1701),
+            longitude_deg: Some(24.9376),
+            ..Telemetry::default()
+        };
+        let display = display_telemetry(&telemetry, &Units::imperial());
+        let altitude = display.altitude.unwrap();
+        assert!(close(altitude.value, 328.083989501));
+        assert_eq!(altitude.unit, "ft");
+        assert!(display.airspeed.is_none());
+        assert_eq!(display.position.as_deref(), Some("60.170100, 24.937600"));
+        let aviation = display_telemetry(&telemetry, &Units::aviation());
+        assert_eq!(
+            aviation.position.as_deref(),
+            Some("60°10.2060'N 24°56.2560'E")
+        );
+        assert!(close(
+            Units::imperial().altitude_to_meters(altitude.value).0,
+            100.0
+        ));
+    }
+}
@@ -1,17 +1,56 @@
+use crate::audit::{self, AuditEntry};
+use crate::battery_health::{run_battery_health, BatteryHealth};
 use crate::command::Command;
-use crate::config::VehicleConfig;
+use crate::config::{ConfigPatch, VehicleConfig};
+use crate::control::ControlState;
 use crate::error::VehicleError;
-use crate::event_loop::run_event_loop;
-use crate::mission::{HomePosition, MissionHandle, TransferProgress};
-use crate::params::{ParamProgress, ParamStore, ParamsHandle};
+use crate::esc::EscTelemetry;
+use crate::event_loop::{run_event_loop, LoopLink};
+use crate::events::{event_channel, EventClass, EventReceiver, EventSender, VehicleEvent};
+use crate::gcs::{shutdown_warning, GcsPeer, ShutdownWarning};
+use crate::geofence::{FenceHandle, FenceStatus};
+use crate::guided::{
+    goto_path_clearance, path_warning, plan_altitude_change, plan_goto, plan_nudge, GotoProposal,
+    NudgeDirection, OrbitDirection, OrbitSession, TerrainCheck, GOTO_PROPOSAL_TTL,
+};
+use crate::landing::{DescentMonitor, LandingPhase, LandingState};
+use crate::link::{SharedLink, Transport};
+use crate::mission::{
+    deg_to_e7, ExternalPlanChange, Fence, HomePosition, MissionHandle, OnboardPlans, PlanMarkers,
+    TerrainSource, TransferProgress,
+};
+use crate::modes::{mode_hazard, ModeCheck};
+use crate::params::{ParamProgress, ParamStore, ParamUpdate, ParamsHandle};
+use crate::payload::PayloadHandle;
+use crate::rate_benchmark::{RateBenchmarkOptions, RateReport};
+use crate::remote_id::RemoteIdState;
+use crate::return_to_me::{
+    hold_position, next_step, GcsFix, ReturnToMeOptions, ReturnToMeState, StaleAction, Step,
+};
+use crate::send_queue::LinkPacing;
+#[cfg(feature = "serial")]
+use crate::serial::{self, SerialOptions};
+use crate::setup::SetupHandle;
+use crate::sitl::SitlHandle;
+use crate::smoothing::{run_smoothing, SmoothedTelemetry};
 use crate::state::{
-    create_channels, FlightMode, LinkState, MissionState, StateChannels, Telemetry,
-    VehicleIdentity, VehicleState,
+    create_channels, AutopilotType, BatteryInfo, FlightMode, LinkState, MissionState,
+    StateChannels, Telemetry, VehicleIdentity, VehicleState,
 };
-use mavlink::common::{self, MavCmd};
-use std::sync::Arc;
-use tokio::sync::{mpsc, oneshot, watch};
+#[cfg(feature = "tls")]
+use crate::tls::{self, TlsOptions};
+use crate::traffic::{TrafficAdvisory, TrafficTarget};
+use crate::vibration::{run_vibration_monitor, VibrationState};
+use mavlink::ardupilotmega::{MavCmd, MavFrame};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::task::{AbortHandle, JoinHandle};
 use tokio_util::sync::CancellationToken;
+use tracing::warn;
 
 /// Async MAVLink vehicle handle.
 ///
@@ -24,9 +63,32 @@ pub struct Vehicle {
 
 pub(crate) struct VehicleInner {
     pub(crate) command_tx: mpsc::Sender<Command>,
+    /// Priority lane for `Command::is_urgent` commands.
+    urgent_tx: mpsc::Sender<Command>,
     cancel: CancellationToken,
     channels: StateChannels,
-    _config: VehicleConfig,
+    pending_goto: Mutex<Option<(GotoProposal, Instant)>>,
+    next_goto_token: AtomicU64,
+    pub(crate) orbit: Mutex<Option<OrbitSession>>,
+    audit_log: Mutex<VecDeque<AuditEntry>>,
+    /// Queues that get every command result; closed ones are pruned.
+    event_subscribers: Mutex<Vec<EventSender<VehicleEvent>>>,
+    geofence: Mutex<Option<Fence>>,
+    terrain: Mutex<Option<Arc<dyn TerrainSource + Send + Sync>>>,
+    gcs_position: Mutex<Option<GcsFix>>,
+    return_to_me: Mutex<Option<AbortHandle>>,
+    return_to_me_state: watch::Sender<ReturnToMeState>,
+    landing: Mutex<Option<AbortHandle>>,
+    landing_state: watch::Sender<LandingState>,
+    /// Taken by `close`; the loop has released the connection once it ends.
+    event_loop: Mutex<Option<JoinHandle<()>>>,
+    /// Mirrors the event loop's copy, including `update_config` changes.
+    config: Mutex<VehicleConfig>,
+    /// Where replies go on a `udpin` link; always `None` on other links.
+    udp_peer: watch::Receiver<Option<SocketAddr>>,
+    telemetry_smoothed: watch::Receiver<SmoothedTelemetry>,
+    battery_health: watch::Receiver<BatteryHealth>,
+    vibration: watch::Receiver<VibrationState>,
 }
 
 impl Drop for VehicleInner {
@@ -52,9 +114,46 @@ impl Vehicle {
         Self::connect(&format!("tcpin:{addr}")).await
     }
 
-    /// Connect via serial port.
+    /// Connect via serial port. Outgoing traffic is paced to the baud rate.
     pub async fn connect_serial(port: &str, baud: u32) -> Result<Self, VehicleError> {
-        Self::connect(&format!("serial:{port}:{baud}")).await
+        let config = VehicleConfig {
+            link_pacing: LinkPacing::serial(baud),
+            ..VehicleConfig::default()
+        };
+        Self::connect_with_config(&format!("serial:{port}:{baud}"), config).await
+    }
+
+    /// Connect via serial port with explicit line settings (flow control,
+    /// parity, stop bits, DTR/RTS). `config.link_pacing` is used as given.
+    #[cfg(feature = "serial")]
+    pub async fn connect_serial_with_options(
+        port: &str,
+        baud: u32,
+        options: &SerialOptions,
+        config: VehicleConfig,
+        cancel: &CancellationToken,
+    ) -> Result<Self, VehicleError> {
+        if cancel.is_cancelled() {
+            return Err(VehicleError::Cancelled);
+        }
+        let transport = serial::open(port, baud, options)?;
+        Self::start(LoopLink::Own(Transport::Serial(transport)), config, cancel).await
+    }
+
+    /// Connect via TCP to `addr` (`host:port`) over TLS, checking the server
+    /// certificate as `options` says.
+    #[cfg(feature = "tls")]
+    pub async fn connect_tls(
+        addr: &str,
+        options: &TlsOptions,
+        config: VehicleConfig,
+        cancel: &CancellationToken,
+    ) -> Result<Self, VehicleError> {
+        let transport = tokio::select! {
+            _ = cancel.cancelled() => return Err(VehicleError::Cancelled),
+            result = tls::connect(addr, options) => result?,
+        };
+        Self::start(LoopLink::Own(Transport::Tls(transport)), config, cancel).await
     }
 
     /// Connect with a custom `VehicleConfig`.
@@ -62,40 +161,117 @@ impl Vehicle {
         address: &str,
         config: VehicleConfig,
     ) -> Result<Self, VehicleError> {
-        let connection = mavlink::connect_async::<common::MavMessage>(address)
-            .await
-            .map_err(|err| VehicleError::ConnectionFailed(err.to_string()))?;
+        Self::connect_with_cancel(address, config, &CancellationToken::new()).await
+    }
 
+    /// Connect, giving up with `VehicleError::Cancelled` when `cancel` fires.
+    ///
+    /// Unlike aborting the connecting task, this always closes the socket or
+    /// serial port before returning an error, so the address can be bound
+    /// again immediately.
+    pub async fn connect_with_cancel(
+        address: &str,
+        config: VehicleConfig,
+        cancel: &CancellationToken,
+    ) -> Result<Self, VehicleError> {
+        let transport = tokio::select! {
+            _ = cancel.cancelled() => return Err(VehicleError::Cancelled),
+            result = Transport::open(address) => result?,
+        };
+        Self::start(LoopLink::Own(transport), config, cancel).await
+    }
+
+    /// Attach to vehicle `system_id` on a link shared with other vehicles.
+    /// Waits for its first HEARTBEAT; commands are addressed to it and its
+    /// state only follows its own messages.
+    pub async fn attach(link: &SharedLink, system_id: u8) -> Result<Self, VehicleError> {
+        Self::attach_with_config(link, system_id, VehicleConfig::default()).await
+    }
+
+    /// `attach` with a custom `VehicleConfig`. `config.link_pacing` is not
+    /// used; the shared link is paced with `SharedLink::set_pacing`.
+    pub async fn attach_with_config(
+        link: &SharedLink,
+        system_id: u8,
+        config: VehicleConfig,
+    ) -> Result<Self, VehicleError> {
+        let link = LoopLink::Shared(link.clone(), system_id);
+        Self::start(link, config, &CancellationToken::new()).await
+    }
+
+    /// Spawn the event loop on an open link and wait for the first
+    /// HEARTBEAT, closing a link of its own again on failure.
+    async fn start(
+        link: LoopLink,
+        config: VehicleConfig,
+        cancel: &CancellationToken,
+    ) -> Result<Self, VehicleError> {
+        config.remote_id.validate()?;
+        if let Some(smoothing) = &config.telemetry_smoothing {
+            smoothing.validate()?;
+        }
         let (writers, channels) = create_channels();
-        let cancel = CancellationToken::new();
+        let shutdown = CancellationToken::new();
         let (command_tx, command_rx) = mpsc::channel(config.command_buffer_size);
+        let (urgent_tx, urgent_rx) = mpsc::channel(config.command_buffer_size);
 
-        let loop_cancel = cancel.clone();
+        let udp_peer = link.udp_peer().unwrap_or_else(|| watch::channel(None).1);
+        let (smoothed_tx, telemetry_smoothed) = watch::channel(SmoothedTelemetry::default());
+        tokio::spawn(run_smoothing(
+            channels.telemetry.clone(),
+            smoothed_tx,
+            config.telemetry_smoothing,
+        ));
+        let (health_tx, battery_health) = watch::channel(BatteryHealth::default());
+        tokio::spawn(run_battery_health(
+            channels.telemetry.clone(),
+            health_tx,
+            config.battery_cell_limits,
+        ));
+        let (vibration_tx, vibration) = watch::channel(VibrationState::default());
+        tokio::spawn(run_vibration_monitor(
+            channels.telemetry.clone(),
+            channels.vehicle_state.clone(),
+            vibration_tx,
+        ));
+        let loop_cancel = shutdown.clone();
         let loop_config_timeout = config.connect_timeout;
 
         // Spawn the event loop
         let writers_for_loop = writers;
-        tokio::spawn(run_event_loop(
-            connection,
+        let event_loop = tokio::spawn(run_event_loop(
+            link,
             command_rx,
+            urgent_rx,
             writers_for_loop,
-            VehicleConfig {
-                gcs_system_id: config.gcs_system_id,
-                gcs_component_id: config.gcs_component_id,
-                retry_policy: config.retry_policy,
-                auto_request_home: config.auto_request_home,
-                command_buffer_size: config.command_buffer_size,
-                connect_timeout: config.connect_timeout,
-            },
+            config.clone(),
             loop_cancel,
         ));
 
         let vehicle = Vehicle {
             inner: Arc::new(VehicleInner {
                 command_tx,
-                cancel,
+                urgent_tx,
+                cancel: shutdown,
                 channels,
-                _config: config,
+                pending_goto: Mutex::new(None),
+                next_goto_token: AtomicU64::new(1),
+                orbit: Mutex::new(None),
+                audit_log: Mutex::new(VecDeque::new()),
+                event_subscribers: Mutex::new(Vec::new()),
+                geofence: Mutex::new(None),
+                terrain: Mutex::new(None),
+                gcs_position: Mutex::new(None),
+                return_to_me: Mutex::new(None),
+                return_to_me_state: watch::channel(ReturnToMeState::Off).0,
+                landing: Mutex::new(None),
+                landing_state: watch::channel(LandingState::default()).0,
+                event_loop: Mutex::new(Some(event_loop)),
+                config: Mutex::new(config),
+                udp_peer,
+                telemetry_smoothed,
+                battery_health,
+                vibration,
             }),
         };
 
@@ -112,11 +288,14 @@ impl Vehicle {
             }
         };
 
-        tokio::select! {
-            result = heartbeat_wait => result?,
-            _ = tokio::time::sleep(loop_config_timeout) => {
-                return Err(VehicleError::Timeout);
-            }
+        let result = tokio::select! {
+            result = heartbeat_wait => result,
+            _ = cancel.cancelled() => Err(VehicleError::Cancelled),
+            _ = tokio::time::sleep(loop_config_timeout) => Err(VehicleError::Timeout),
+        };
+        if let Err(err) = result {
+            vehicle.close().await;
+            return Err(err);
         }
 
         Ok(vehicle)
@@ -132,6 +311,131 @@ impl Vehicle {
         self.inner.channels.telemetry.clone()
     }
 
+    /// Attitude and position for displays, updated at a steady rate and
+    /// filtered as `VehicleConfig::telemetry_smoothing` says; just the raw
+    /// values when that is off.
+    pub fn telemetry_smoothed(&self) -> watch::Receiver<SmoothedTelemetry> {
+        self.inner.telemetry_smoothed.clone()
+    }
+
+    /// Pack information (capacity, cycle count, serial) per battery.
+    pub fn battery_info(&self) -> watch::Receiver<Vec<BatteryInfo>> {
+        self.inner.channels.battery_info.clone()
+    }
+
+    /// Cell imbalance, sag and the alerts raised for them against
+    /// `VehicleConfig::battery_cell_limits`.
+    pub fn battery_health(&self) -> watch::Receiver<BatteryHealth> {
+        self.inner.battery_health.clone()
+    }
+
+    /// Vibration levels, accelerometer clip rates and alerts for clipping
+    /// that keeps getting faster, with a summary of the current or last
+    /// flight.
+    pub fn vibration(&self) -> watch::Receiver<VibrationState> {
+        self.inner.vibration.clone()
+    }
+
+    /// Per-motor RPM, voltage, current and temperature.
+    pub fn esc_telemetry(&self) -> watch::Receiver<Vec<EscTelemetry>> {
+        self.inner.channels.esc_telemetry.clone()
+    }
+
+    /// What the vehicle's Remote ID broadcasts, from OPEN_DRONE_ID_* messages.
+    pub fn remote_id(&self) -> watch::Receiver<RemoteIdState> {
+        self.inner.channels.remote_id.clone()
+    }
+
+    /// Aircraft reported by the vehicle's ADS-B receiver.
+    pub fn traffic(&self) -> watch::Receiver<Vec<TrafficTarget>> {
+        self.inner.channels.traffic.clone()
+    }
+
+    /// Predicted conflicts with `traffic`, with suggested altitude changes.
+    /// Advisory only; nothing is commanded.
+    pub fn traffic_advisories(&self) -> watch::Receiver<Vec<TrafficAdvisory>> {
+        self.inner.channels.traffic_advisories.clone()
+    }
+
+    /// The autopilot's unique board ID from AUTOPILOT_VERSION, hex encoded.
+    /// `None` until the reply arrives, or for boards without one.
+    pub fn uid(&self) -> watch::Receiver<Option<String>> {
+        self.inner.channels.vehicle_uid.clone()
+    }
+
+    /// System and component id, autopilot and vehicle type from the
+    /// vehicle's heartbeats, with the firmware version once AUTOPILOT_VERSION
+    /// arrives. `None` until the first heartbeat.
+    pub fn vehicle_identity(&self) -> watch::Receiver<Option<VehicleIdentity>> {
+        self.inner.channels.identity.clone()
+    }
+
+    /// How many times the vehicle has rebooted since connecting, spotted by
+    /// its boot clock running backwards. Each reboot clears the home
+    /// position, parameter store and onboard plans, and home is requested
+    /// again.
+    pub fn reboots(&self) -> watch::Receiver<u32> {
+        self.inner.channels.reboots.clone()
+    }
+
+    /// The address replies go to on a `udpin` link, which follows the
+    /// vehicle when its NAT mapping changes; `None` until it is heard from,
+    /// and always on other links.
+    pub fn udp_peer(&self) -> watch::Receiver<Option<SocketAddr>> {
+        self.inner.udp_peer.clone()
+    }
+
+    /// Raw RC input PWM per channel, published on every RC_CHANNELS message.
+    pub fn rc_channels(&self) -> watch::Receiver<Vec<u16>> {
+        self.inner.channels.rc_channels.clone()
+    }
+
+    /// Other ground stations currently heartbeating on this link.
+    pub fn gcs_peers(&self) -> watch::Receiver<Vec<GcsPeer>> {
+        self.inner.channels.gcs_peers.clone()
+    }
+
+    /// Commands sent to the vehicle during this session, oldest first.
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        let log = self.inner.audit_log.lock().unwrap();
+        log.iter().cloned().collect()
+    }
+
+    /// Telemetry, state, link state, mission progress, traffic advisories and
+    /// command results on
+    /// one bounded queue. A consumer that falls behind loses the oldest
+    /// telemetry-class events once `capacity` is queued; link state changes
+    /// and command results are always delivered. Must be called from within
+    /// a tokio runtime.
+    pub fn events(&self, capacity: usize) -> EventReceiver<VehicleEvent> {
+        let (tx, rx) = event_channel(capacity);
+        forward_events(self.telemetry(), tx.clone(), VehicleEvent::Telemetry);
+        forward_events(self.state(), tx.clone(), VehicleEvent::State);
+        forward_events(self.link_state(), tx.clone(), VehicleEvent::LinkState);
+        forward_events(
+            self.mission_progress(),
+            tx.clone(),
+            VehicleEvent::MissionProgress,
+        );
+        forward_events(
+            self.traffic_advisories(),
+            tx.clone(),
+            VehicleEvent::TrafficAdvisories,
+        );
+        self.subscribe_command_results(tx);
+        rx
+    }
+
+    /// Queue every later command result on `tx` until it is closed.
+    pub(crate) fn subscribe_command_results(&self, tx: EventSender<VehicleEvent>) {
+        self.inner.event_subscribers.lock().unwrap().push(tx);
+    }
+
+    /// Which ground station currently commands the vehicle.
+    pub fn control(&self) -> watch::Receiver<ControlState> {
+        self.inner.channels.control.clone()
+    }
+
     pub fn home_position(&self) -> watch::Receiver<Option<HomePosition>> {
         self.inner.channels.home_position.clone()
     }
@@ -140,18 +444,51 @@ impl Vehicle {
         self.inner.channels.mission_state.clone()
     }
 
+    /// The latest FENCE_STATUS; `None` until one arrives, which on
+    /// ArduPilot means the fence is not enabled.
+    pub fn fence_status(&self) -> watch::Receiver<Option<FenceStatus>> {
+        self.inner.channels.fence_status.clone()
+    }
+
     pub fn link_state(&self) -> watch::Receiver<LinkState> {
         self.inner.channels.link_state.clone()
     }
 
+    /// What the last upload, download or clear of each plan type left on
+    /// the vehicle (see `MissionHandle::sync_status`).
+    pub fn plan_markers(&self) -> watch::Receiver<PlanMarkers> {
+        self.inner.channels.plan_markers.clone()
+    }
+
+    /// The plans stored on the vehicle, as last uploaded, downloaded or
+    /// cleared. A plan is dropped once the vehicle reports it was changed
+    /// elsewhere; download it again to refresh.
+    pub fn current_vehicle_plan(&self) -> watch::Receiver<OnboardPlans> {
+        self.inner.channels.current_vehicle_plan.clone()
+    }
+
+    /// Plans changed on the vehicle by someone else, over RC or by another
+    /// GCS, as the vehicle reports their new opaque ids.
+    pub fn mission_changed_externally(&self) -> broadcast::Receiver<ExternalPlanChange> {
+        self.inner.channels.plan_changes.subscribe()
+    }
+
     pub fn mission_progress(&self) -> watch::Receiver<Option<TransferProgress>> {
         self.inner.channels.mission_progress.clone()
     }
 
-    pub fn param_store(&self) -> watch::Receiver<ParamStore> {
+    /// The parameter store is shared rather than copied; clone the `Arc` out
+    /// of `borrow()` to keep it. Subscribers are notified when a download
+    /// completes and when a confirmed write changes a stored value.
+    pub fn param_store(&self) -> watch::Receiver<Arc<ParamStore>> {
         self.inner.channels.param_store.clone()
     }
 
+    /// Every parameter value received, as it arrives.
+    pub fn param_updates(&self) -> broadcast::Receiver<ParamUpdate> {
+        self.inner.channels.param_updates.subscribe()
+    }
+
     pub fn param_progress(&self) -> watch::Receiver<ParamProgress> {
         self.inner.channels.param_progress.clone()
     }
@@ -162,17 +499,65 @@ impl Vehicle {
         self.send_command(|reply| Command::Arm { force, reply }).await
     }
 
+    /// Disarm, refused with `VehicleError::DisarmRefused` while the vehicle
+    /// is clearly airborne unless `force` is set.
     pub async fn disarm(&self, force: bool) -> Result<(), VehicleError> {
+        if !force && self.telemetry().borrow().is_clearly_airborne() {
+            return Err(VehicleError::DisarmRefused);
+        }
         self.send_command(|reply| Command::Disarm { force, reply }).await
     }
 
+    /// Stop the motors at once, in flight too: a forced disarm, which
+    /// crashes a flying vehicle. Like disarming and mode changes it skips
+    /// ahead of any mission or parameter transfer in progress.
+    pub async fn emergency_stop(&self) -> Result<(), VehicleError> {
+        self.send_command(|reply| Command::Disarm { force: true, reply })
+            .await
+    }
+
+    /// Switch to `custom_mode` after checking it against the vehicle's
+    /// state, see `ModeHazard` and `VehicleConfig::mode_check`.
     pub async fn set_mode(&self, custom_mode: u32) -> Result<(), VehicleError> {
+        self.check_mode(custom_mode)?;
+        self.set_mode_unchecked(custom_mode).await
+    }
+
+    /// Switch to `custom_mode` without the `ModeHazard` checks.
+    pub async fn set_mode_unchecked(&self, custom_mode: u32) -> Result<(), VehicleError> {
         self.send_command(|reply| Command::SetMode { custom_mode, reply }).await
     }
 
+    fn check_mode(&self, custom_mode: u32) -> Result<(), VehicleError> {
+        let config = self.inner.config.lock().unwrap().clone();
+        if config.mode_check == ModeCheck::Off {
+            return Ok(());
+        }
+        let state = self.state().borrow().clone();
+        let telemetry = self.telemetry().borrow().clone();
+        let mission = self.mission_state().borrow().clone();
+        let Some(hazard) = mode_hazard(&state, &telemetry, &mission, custom_mode) else {
+            return Ok(());
+        };
+        let mode = config
+            .mode_overrides
+            .mode_name(state.autopilot, state.vehicle_type, custom_mode);
+        if config.mode_check == ModeCheck::Refuse {
+            return Err(VehicleError::ModeRefused { mode, hazard });
+        }
+        warn!("mode change to {mode}: {hazard}");
+        Ok(())
+    }
+
     pub async fn set_mode_by_name(&self, name: &str) -> Result<(), VehicleError> {
         let state = self.inner.channels.vehicle_state.borrow().clone();
-        let custom_mode = crate::modes::mode_number(state.autopilot, state.vehicle_type, name)
+        let custom_mode = self
+            .inner
+            .config
+            .lock()
+            .unwrap()
+            .mode_overrides
+            .mode_number(state.autopilot, state.vehicle_type, name)
             .ok_or_else(|| VehicleError::ModeNotAvailable(name.to_string()))?;
         self.set_mode(custom_mode).await
     }
@@ -185,9 +570,13 @@ impl Vehicle {
         .await
     }
 
+    /// Fly to a target `alt_m` above home. With a terrain source set, a
+    /// straight path that grazes the terrain is handled per
+    /// `VehicleConfig::terrain_check`.
     pub async fn goto(&self, lat_deg: f64, lon_deg: f64, alt_m: f32) -> Result<(), VehicleError> {
-        let lat_e7 = (lat_deg * 1e7) as i32;
-        let lon_e7 = (lon_deg * 1e7) as i32;
+        self.check_goto_terrain(lat_deg, lon_deg, alt_m)?;
+        let lat_e7 = deg_to_e7(lat_deg);
+        let lon_e7 = deg_to_e7(lon_deg);
         self.send_command(|reply| Command::GuidedGoto {
             lat_e7,
             lon_e7,
@@ -197,6 +586,440 @@ impl Vehicle {
         .await
     }
 
+    /// First phase of a confirmed goto: compute the target without moving the
+    /// vehicle. Replaces any earlier unconfirmed proposal.
+    pub fn propose_goto(&self, lat_deg: f64, lon_deg: f64, alt_m: Option<f32>) -> GotoProposal {
+        let token = self.inner.next_goto_token.fetch_add(1, Ordering::Relaxed);
+        let telemetry = self.inner.channels.telemetry.borrow().clone();
+        let home = self.inner.channels.home_position.borrow().clone();
+        let terrain = self.inner.terrain.lock().unwrap().clone();
+        let proposal = plan_goto(
+            token,
+            &telemetry,
+            home.as_ref(),
+            terrain.as_deref().map(|terrain| terrain as &dyn TerrainSource),
+            lat_deg,
+            lon_deg,
+            alt_m,
+        );
+        *self.inner.pending_goto.lock().unwrap() = Some((proposal.clone(), Instant::now()));
+        proposal
+    }
+
+    /// Second phase of a confirmed goto: fly to the proposal identified by `token`.
+    pub async fn confirm_goto(&self, token: u64) -> Result<(), VehicleError> {
+        let proposal = {
+            let mut pending = self.inner.pending_goto.lock().unwrap();
+            match pending.take() {
+                Some((proposal, created)) if proposal.token == token => {
+                    if created.elapsed() > GOTO_PROPOSAL_TTL {
+                        return Err(VehicleError::GotoProposalExpired);
+                    }
+                    proposal
+                }
+                other => {
+                    *pending = other;
+                    return Err(VehicleError::GotoProposalExpired);
+                }
+            }
+        };
+        self.goto(proposal.latitude_deg, proposal.longitude_deg, proposal.altitude_m)
+            .await
+    }
+
+    /// Ground elevations used to check the path of guided gotos and shown
+    /// in their proposals; `None` (the default) skips the check.
+    pub fn set_terrain(&self, terrain: Option<Arc<dyn TerrainSource + Send + Sync>>) {
+        *self.inner.terrain.lock().unwrap() = terrain;
+    }
+
+    fn check_goto_terrain(
+        &self,
+        lat_deg: f64,
+        lon_deg: f64,
+        alt_m: f32,
+    ) -> Result<(), VehicleError> {
+        let check = self.inner.config.lock().unwrap().terrain_check;
+        let terrain = self.inner.terrain.lock().unwrap().clone();
+        let (Some(terrain), false) = (terrain, check == TerrainCheck::Off) else {
+            return Ok(());
+        };
+        let telemetry = self.telemetry().borrow().clone();
+        let home = self.home_position().borrow().clone();
+        let path = goto_path_clearance(
+            &telemetry,
+            home.as_ref(),
+            terrain.as_ref(),
+            lat_deg,
+            lon_deg,
+            alt_m,
+        );
+        match path.as_ref().and_then(path_warning) {
+            Some(warning) if check == TerrainCheck::Refuse => {
+                Err(VehicleError::TerrainConflict(warning))
+            }
+            Some(warning) => {
+                warn!("goto: {warning}");
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Geofence that nudges must stay within. Set automatically whenever a
+    /// fence plan is uploaded, downloaded or cleared.
+    pub fn set_geofence(&self, fence: Option<Fence>) {
+        *self.inner.geofence.lock().unwrap() = fence;
+    }
+
+    /// Move the guided target `distance_m` from the current position, relative
+    /// to the heading. The step is clamped to `VehicleConfig::nudge_limits`;
+    /// a target outside the geofence is refused.
+    pub async fn nudge(
+        &self,
+        direction: NudgeDirection,
+        distance_m: f64,
+    ) -> Result<(), VehicleError> {
+        let telemetry = self.telemetry().borrow().clone();
+        let fence = self.inner.geofence.lock().unwrap().clone();
+        let limits = self.inner.config.lock().unwrap().nudge_limits;
+        let (lat, lon, alt) = plan_nudge(
+            &telemetry,
+            fence.as_ref(),
+            &limits,
+            direction,
+            distance_m,
+        )?;
+        self.goto(lat, lon, alt).await
+    }
+
+    /// Climb (positive) or descend by `delta_m`, clamped to
+    /// `VehicleConfig::nudge_limits`. A descent towards the terrain under the
+    /// vehicle is refused.
+    pub async fn change_altitude(&self, delta_m: f32) -> Result<(), VehicleError> {
+        let telemetry = self.telemetry().borrow().clone();
+        let home = self.home_position().borrow().clone();
+        let limits = self.inner.config.lock().unwrap().nudge_limits;
+        let (lat, lon, alt) = plan_altitude_change(
+            &telemetry,
+            home.as_ref(),
+            &limits,
+            delta_m,
+        )?;
+        self.goto(lat, lon, alt).await
+    }
+
+    /// Latest operator position, used by return-to-me.
+    pub fn update_gcs_position(&self, lat_deg: f64, lon_deg: f64) {
+        *self.inner.gcs_position.lock().unwrap() = Some(GcsFix {
+            lat_deg,
+            lon_deg,
+            received: Instant::now(),
+        });
+    }
+
+    /// Switch to GUIDED and keep flying to the operator position fed through
+    /// `update_gcs_position` until `stop_return_to_me`. When the position goes
+    /// stale, following stops and `options.on_stale` is applied.
+    ///
+    /// Fails with `NoGcsPosition` unless a fresh operator position is already
+    /// known.
+    pub async fn start_return_to_me(&self, options: ReturnToMeOptions) -> Result<(), VehicleError> {
+        let fresh = self
+            .inner
+            .gcs_position
+            .lock()
+            .unwrap()
+            .is_some_and(|fix| fix.is_fresh(&options, Instant::now()));
+        if !fresh {
+            return Err(VehicleError::NoGcsPosition);
+        }
+        self.stop_return_to_me();
+        self.set_mode_by_name("GUIDED").await?;
+        // Weak, so that following does not keep the connection open once the
+        // application has dropped the vehicle
+        let inner = Arc::downgrade(&self.inner);
+        let task = tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(Duration::from_millis(options.update_interval_ms.max(100)));
+            let mut last_target = None;
+            loop {
+                ticker.tick().await;
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                let vehicle = Vehicle { inner };
+                let fix = *vehicle.inner.gcs_position.lock().unwrap();
+                match next_step(fix.as_ref(), last_target, &options, Instant::now()) {
+                    Step::Wait => {}
+                    Step::Goto { lat_deg, lon_deg } => {
+                        match vehicle.goto(lat_deg, lon_deg, options.altitude_m).await {
+                            Ok(()) => last_target = Some((lat_deg, lon_deg)),
+                            Err(err) => warn!("return-to-me: goto failed: {err}"),
+                        }
+                    }
+                    Step::Failsafe => {
+                        let action = options.on_stale;
+                        warn!("return-to-me: operator position lost, {action:?}");
+                        let result = match options.on_stale {
+                            StaleAction::Rtl => vehicle.set_mode_by_name("RTL").await,
+                            StaleAction::Hold => {
+                                let telemetry = vehicle.telemetry().borrow().clone();
+                                match hold_position(&telemetry, &options) {
+                                    Some((lat, lon, alt)) => vehicle.goto(lat, lon, alt).await,
+                                    None => vehicle.set_mode_by_name("RTL").await,
+                                }
+                            }
+                        };
+                        if let Err(err) = result {
+                            warn!("return-to-me: failsafe failed: {err}");
+                        }
+                        vehicle
+                            .inner
+                            .return_to_me_state
+                            .send_replace(ReturnToMeState::PositionLost);
+                        return;
+                    }
+                }
+            }
+        });
+        *self.inner.return_to_me.lock().unwrap() = Some(task.abort_handle());
+        self.inner
+            .return_to_me_state
+            .send_replace(ReturnToMeState::Following);
+        Ok(())
+    }
+
+    /// Stop following the operator; the vehicle keeps its last guided target.
+    pub fn stop_return_to_me(&self) {
+        if let Some(task) = self.inner.return_to_me.lock().unwrap().take() {
+            task.abort();
+            self.inner
+                .return_to_me_state
+                .send_replace(ReturnToMeState::Off);
+        }
+    }
+
+    pub fn return_to_me_state(&self) -> watch::Receiver<ReturnToMeState> {
+        self.inner.return_to_me_state.subscribe()
+    }
+
+    /// Land where the vehicle is: the autopilot's land mode, or
+    /// MAV_CMD_NAV_LAND when flying in GUIDED (or without a land mode). The
+    /// descent is then followed in `landing_state` until touchdown or
+    /// `abort_landing`.
+    pub async fn land_here(&self) -> Result<(), VehicleError> {
+        self.stop_landing_monitor();
+        let state = self.state().borrow().clone();
+        let (autopilot, vehicle_type) = (state.autopilot, state.vehicle_type);
+        let in_guided = autopilot == AutopilotType::ArduPilotMega
+            && crate::modes::mode_name(autopilot, vehicle_type, state.custom_mode) == "GUIDED";
+        match crate::modes::land_mode(autopilot, vehicle_type) {
+            Some(mode) if !in_guided => self.set_mode(mode).await?,
+            _ => {
+                self.command_long(
+                    MavCmd::MAV_CMD_NAV_LAND,
+                    // Yaw unchanged; lat/lon 0: where the vehicle is
+                    [0.0, 0.0, 0.0, f32::NAN, 0.0, 0.0, 0.0],
+                )
+                .await?
+            }
+        }
+
+        let inner = Arc::downgrade(&self.inner);
+        let mut telemetry = self.telemetry();
+        let task = tokio::spawn(async move {
+            let mut monitor = DescentMonitor::default();
+            loop {
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                let armed = inner.channels.vehicle_state.borrow().armed;
+                let landing =
+                    monitor.observe(&telemetry.borrow_and_update(), armed, Instant::now());
+                inner.landing_state.send_replace(landing);
+                if landing.phase == LandingPhase::Landed {
+                    return;
+                }
+                drop(inner);
+                if telemetry.changed().await.is_err() {
+                    return;
+                }
+            }
+        });
+        *self.inner.landing.lock().unwrap() = Some(task.abort_handle());
+        Ok(())
+    }
+
+    /// Stop a landing started by `land_here` and climb to `climb_to_m` above
+    /// home over the current position.
+    pub async fn abort_landing(&self, climb_to_m: f32) -> Result<(), VehicleError> {
+        let telemetry = self.telemetry().borrow().clone();
+        let (Some(lat), Some(lon)) = (telemetry.latitude_deg, telemetry.longitude_deg) else {
+            return Err(VehicleError::CommandNotSupported(
+                "abort landing without a position fix".to_string(),
+            ));
+        };
+        crate::guided::reposition(self, lat, lon, climb_to_m).await?;
+        self.stop_landing_monitor();
+        self.inner.landing_state.send_modify(|landing| {
+            landing.phase = LandingPhase::Aborted;
+            landing.warning = None;
+        });
+        Ok(())
+    }
+
+    fn stop_landing_monitor(&self) {
+        if let Some(task) = self.inner.landing.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    pub fn landing_state(&self) -> watch::Receiver<LandingState> {
+        self.inner.landing_state.subscribe()
+    }
+
+    /// Orbit a point of interest: MAV_CMD_DO_ORBIT on PX4, CIRCLE mode (copters)
+    /// or a guided loiter (planes) on ArduPilot. On ArduPilot the parameters
+    /// must have been downloaded so `stop_orbit` can restore the ones the
+    /// orbit overwrites.
+    pub async fn orbit(
+        &self,
+        center_lat: f64,
+        center_lon: f64,
+        radius_m: f32,
+        speed_mps: f32,
+        direction: OrbitDirection,
+    ) -> Result<(), VehicleError> {
+        crate::guided::orbit(self, center_lat, center_lon, radius_m, speed_mps, direction).await
+    }
+
+    /// Leave an orbit, hold at the current position and restore the
+    /// CIRCLE_RADIUS/CIRCLE_RATE or WP_LOITER_RAD values the orbit replaced.
+    pub async fn stop_orbit(&self) -> Result<(), VehicleError> {
+        crate::guided::stop_orbit(self).await
+    }
+
+    /// Adjust retry timeouts, home requests, link pacing and limits on the
+    /// live connection, e.g. after switching from LAN SITL to a telemetry
+    /// radio. Applied between commands; a running transfer finishes with the
+    /// settings it started with.
+    pub async fn update_config(&self, patch: ConfigPatch) -> Result<(), VehicleError> {
+        if let Some(remote_id) = &patch.remote_id {
+            remote_id.validate()?;
+        }
+        let command_patch = patch.clone();
+        self.send_command(|reply| Command::UpdateConfig {
+            patch: command_patch,
+            reply,
+        })
+        .await?;
+        patch.apply(&mut self.inner.config.lock().unwrap());
+        Ok(())
+    }
+
+    /// The configuration in effect, including `update_config` changes.
+    pub fn config(&self) -> VehicleConfig {
+        self.inner.config.lock().unwrap().clone()
+    }
+
+    /// Change the system/component id we send as, e.g. to step aside for
+    /// another GCS during a handoff. Applies to all subsequent messages.
+    pub async fn set_gcs_identity(
+        &self,
+        system_id: u8,
+        component_id: u8,
+    ) -> Result<(), VehicleError> {
+        self.send_command(|reply| Command::SetGcsIdentity {
+            system_id,
+            component_id,
+            reply,
+        })
+        .await?;
+        let mut config = self.inner.config.lock().unwrap();
+        config.gcs_system_id = system_id;
+        config.gcs_component_id = component_id;
+        Ok(())
+    }
+
+    /// Claim control of the vehicle. Fails with `NotInControl` while another
+    /// GCS is commanding it, unless `force` is set. Ownership is tracked
+    /// locally; nothing is sent to the vehicle.
+    pub async fn request_control(&self, force: bool) -> Result<(), VehicleError> {
+        self.send_command(|reply| Command::RequestControl { force, reply })
+            .await
+    }
+
+    pub async fn release_control(&self) -> Result<(), VehicleError> {
+        self.send_command(|reply| Command::ReleaseControl { reply })
+            .await
+    }
+
+    /// Keep sending commands even while another GCS owns the vehicle.
+    pub async fn set_control_override(&self, enabled: bool) -> Result<(), VehicleError> {
+        self.send_command(|reply| Command::SetControlOverride { enabled, reply })
+            .await
+    }
+
+    /// Ask the vehicle to (re)send SMART_BATTERY_INFO, e.g. after a pack swap.
+    pub async fn request_battery_info(&self) -> Result<(), VehicleError> {
+        self.command_long(
+            MavCmd::MAV_CMD_REQUEST_MESSAGE,
+            [370.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        )
+        .await
+    }
+
+    /// Listen to the autopilot for `options.window_ms` and report how often
+    /// each of its messages arrived, with jitter and gaps, and whether the
+    /// rates in `options.expected_hz` are kept to. Other commands run as
+    /// usual meanwhile.
+    pub async fn benchmark_rates(
+        &self,
+        options: RateBenchmarkOptions,
+        cancel: &CancellationToken,
+    ) -> Result<RateReport, VehicleError> {
+        self.send_command(|reply| Command::RateBenchmark {
+            options,
+            cancel: cancel.clone(),
+            reply,
+        })
+        .await
+    }
+
+    /// Send an arbitrary message built from JSON fields (see `raw_message_template`).
+    pub async fn send_raw(&self, message_name: &str, fields_json: &str) -> Result<(), VehicleError> {
+        let message = crate::raw::build_raw_message(message_name, fields_json)?;
+        self.send_command(|reply| Command::SendRaw {
+            message,
+            response: None,
+            reply,
+        })
+        .await
+        .map(|_| ())
+    }
+
+    /// Send an arbitrary message and return the next `response_name` message
+    /// received, as JSON.
+    pub async fn send_raw_and_wait(
+        &self,
+        message_name: &str,
+        fields_json: &str,
+        response_name: &str,
+    ) -> Result<String, VehicleError> {
+        let message = crate::raw::build_raw_message(message_name, fields_json)?;
+        let response = self
+            .send_command(|reply| Command::SendRaw {
+                message,
+                response: Some(response_name.to_string()),
+                reply,
+            })
+            .await?;
+        response.ok_or(VehicleError::Timeout)
+    }
+
+    /// Send a COMMAND_LONG and wait for its ACK, retrying per the retry
+    /// policy with `confirmation` counting up.
     pub async fn command_long(
         &self,
         cmd: MavCmd,
@@ -210,25 +1033,52 @@ impl Vehicle {
         .await
     }
 
+    /// Send a COMMAND_INT, for commands whose position must keep full degE7
+    /// precision (`x`/`y` are latitude/longitude in degE7 for global frames).
+    pub async fn command_int(
+        &self,
+        cmd: MavCmd,
+        frame: MavFrame,
+        params: [f32; 4],
+        x: i32,
+        y: i32,
+        z: f32,
+    ) -> Result<(), VehicleError> {
+        self.send_command(|reply| Command::CommandInt {
+            command: cmd,
+            frame,
+            params,
+            x,
+            y,
+            z,
+            reply,
+        })
+        .await
+    }
+
+    /// Ask the autopilot to restart into its bootloader, e.g. before
+    /// `flasher::flash`. The autopilot may reset before acknowledging, so a
+    /// timeout here usually still means the reboot happened.
+    pub async fn reboot_to_bootloader(&self) -> Result<(), VehicleError> {
+        self.command_long(
+            MavCmd::MAV_CMD_PREFLIGHT_REBOOT_SHUTDOWN,
+            [3.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        )
+        .await
+    }
+
     pub fn available_modes(&self) -> Vec<FlightMode> {
         let state = self.inner.channels.vehicle_state.borrow().clone();
-        crate::modes::available_modes(state.autopilot, state.vehicle_type)
+        let config = self.inner.config.lock().unwrap();
+        config
+            .mode_overrides
+            .available_modes(state.autopilot, state.vehicle_type)
     }
 
+    /// Snapshot of `vehicle_identity()`.
+    #[deprecated(note = "use `vehicle_identity()`, which has the real system and component ids")]
     pub fn identity(&self) -> Option<VehicleIdentity> {
-        let state = self.inner.channels.vehicle_state.borrow().clone();
-        if state.mode_name.is_empty()
-            && !state.armed
-            && state.custom_mode == 0
-        {
-            return None;
-        }
-        Some(VehicleIdentity {
-            system_id: 0, // Not directly exposed through watch channels
-            component_id: 0,
-            autopilot: state.autopilot,
-            vehicle_type: state.vehicle_type,
-        })
+        self.inner.channels.identity.borrow().clone()
     }
 
     /// Mission sub-API.
@@ -241,12 +1091,54 @@ impl Vehicle {
         ParamsHandle::new(self)
     }
 
-    /// Gracefully disconnect from the vehicle.
+    /// Geofence enable/disable sub-API.
+    pub fn fence(&self) -> FenceHandle<'_> {
+        FenceHandle::new(self)
+    }
+
+    /// Payload actuation sub-API.
+    pub fn payload(&self) -> PayloadHandle<'_> {
+        PayloadHandle::new(self)
+    }
+
+    /// Initial-setup sub-API (airframe selection).
+    pub fn setup(&self) -> SetupHandle<'_> {
+        SetupHandle::new(self)
+    }
+
+    /// SITL simulation control sub-API.
+    pub fn sitl(&self) -> SitlHandle<'_> {
+        SitlHandle::new(self)
+    }
+
+    /// Why disconnecting now could trigger the vehicle's GCS failsafe: it is
+    /// armed and airborne, we are sending GCS heartbeats it may be watching,
+    /// and the failsafe is not known to be off. Ask before disconnecting.
+    pub fn shutdown_warning(&self) -> Option<ShutdownWarning> {
+        if self.config().gcs_heartbeat_interval.is_none() {
+            return None;
+        }
+        let state = self.state().borrow().clone();
+        let telemetry = self.telemetry().borrow().clone();
+        shutdown_warning(&state, &telemetry, &self.setup().failsafe())
+    }
+
+    /// Stop the event loop and close the connection, returning once it is
+    /// closed. Sends `VehicleConfig::shutdown_notice` first, if set.
     pub async fn disconnect(self) -> Result<(), VehicleError> {
         let _ = self.inner.command_tx.send(Command::Shutdown).await;
+        self.close().await;
         Ok(())
     }
 
+    async fn close(&self) {
+        self.inner.cancel.cancel();
+        let event_loop = self.inner.event_loop.lock().unwrap().take();
+        if let Some(event_loop) = event_loop {
+            let _ = event_loop.await;
+        }
+    }
+
     // --- Internal helper ---
 
     pub(crate) async fn send_command<T>(
@@ -254,11 +1146,59 @@ impl Vehicle {
         make: impl FnOnce(oneshot::Sender<Result<T, VehicleError>>) -> Command,
     ) -> Result<T, VehicleError> {
         let (tx, rx) = oneshot::channel();
-        self.inner
-            .command_tx
-            .send(make(tx))
-            .await
-            .map_err(|_| VehicleError::Disconnected)?;
-        rx.await.map_err(|_| VehicleError::Disconnected)?
+        let command = make(tx);
+        let audited = command.audit();
+        let lane = if command.is_urgent() {
+            &self.inner.urgent_tx
+        } else {
+            &self.inner.command_tx
+        };
+        let (issued_at, started) = (SystemTime::now(), Instant::now());
+        let result = async {
+            lane.send(command)
+                .await
+                .map_err(|_| VehicleError::Disconnected)?;
+            rx.await.map_err(|_| VehicleError::Disconnected)?
+        }
+        .await;
+        if let Some((name, params)) = audited {
+            let error = result.as_ref().err().map(|e| e.to_string());
+            let entry = AuditEntry::new(issued_at, name, params, started.elapsed(), error);
+            self.inner.event_subscribers.lock().unwrap().retain(|tx| {
+                tx.send(EventClass::Result, VehicleEvent::CommandResult(entry.clone()))
+            });
+            audit::record(&mut self.inner.audit_log.lock().unwrap(), entry);
+        }
+        result
+    }
+}
+
+/// Queue each later value of `rx` on `tx` until either side closes.
+fn forward_events<T: Clone + Send + Sync + 'static>(
+    mut rx: watch::Receiver<T>,
+    tx: EventSender<VehicleEvent>,
+    wrap: fn(T) -> VehicleEvent,
+) {
+    tokio::spawn(async move {
+        while rx.changed().await.is_ok() {
+            let event = wrap(rx.borrow_and_update().clone());
+            if !tx.send(event.class(), event) {
+                break;
+            }
+        }
+    });
+}
+
+/// Map a failure to open `address`, picking out a busy local address.
+pub(crate) fn connect_error(address: &str, err: &(dyn std::error::Error + 'static)) -> VehicleError {
+    let mut source = Some(err);
+    while let Some(current) = source {
+        if let Some(io) = current.downcast_ref::<std::io::Error>() {
+            if io.kind() == std::io::ErrorKind::AddrInUse {
+                return VehicleError::AddressInUse(address.to_string());
+            }
+        }
+        source = current.source();
     }
+    VehicleError::ConnectionFailed(err.to_string())
 }
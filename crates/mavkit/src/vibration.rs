@@ -0,0 +1,346 @@
+//! Accelerometer clipping trend during flight.
+//!
+//! Clipping, accelerations beyond the accelerometer's range, is an early
+//! sign of a damaged prop or a worn motor bearing, and spotting it used to
+//! mean downloading the dataflash log. `Vehicle::vibration` follows the clip
+//! counters of VIBRATION and raises a `ClipAlert` once an accelerometer clips
+//! at least `MIN_ALERT_CLIPS_PER_S` and `ACCELERATION_FACTOR` times faster
+//! than over the window before. The counters are per accelerometer rather
+//! than per axis, so an alert names the axis vibrating hardest at the time
+//! as the likely source.
+//!
+//! Each flight, from arming to disarming, is summed up in a
+//! `VibrationSummary` that stays published after landing for the
+//! post-flight report.
+
+use crate::state::{Telemetry, VehicleState};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Length of the windows clip rates are measured and compared over.
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+/// Clip rate an alert needs to be raised, and clears below.
+pub const MIN_ALERT_CLIPS_PER_S: f64 = 0.5;
+/// How many times faster than the window before clipping must get.
+pub const ACCELERATION_FACTOR: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VibrationAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl VibrationAxis {
+    /// The axis with the highest level.
+    fn strongest(levels: [f64; 3]) -> Self {
+        let axes = [VibrationAxis::X, VibrationAxis::Y, VibrationAxis::Z];
+        let (axis, _) = axes
+            .into_iter()
+            .zip(levels)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((VibrationAxis::X, 0.0));
+        axis
+    }
+}
+
+/// An accelerometer clipping faster and faster.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClipAlert {
+    /// Accelerometer 0-2.
+    pub accel: u8,
+    pub clips_per_s: f64,
+    /// Rate over the window before the alert was raised.
+    pub previous_clips_per_s: f64,
+    /// Axis vibrating hardest when the alert was raised.
+    pub axis: Option<VibrationAxis>,
+}
+
+/// Vibration over one flight, from arming to disarming.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VibrationSummary {
+    pub duration_s: f64,
+    /// Highest level seen along x, y and z.
+    pub max_level_mps2: [f64; 3],
+    /// Clips per accelerometer during the flight.
+    pub clips: [u32; 3],
+    pub peak_clips_per_s: f64,
+    /// Every alert raised during the flight.
+    pub alerts: Vec<ClipAlert>,
+    /// False once the vehicle disarmed.
+    pub in_progress: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VibrationState {
+    /// Latest level along x, y and z, m/s/s.
+    pub levels_mps2: Option<[f64; 3]>,
+    /// Clips per second of each accelerometer over the latest window.
+    pub clips_per_s: [f64; 3],
+    /// Alerts whose accelerometer still clips at `MIN_ALERT_CLIPS_PER_S`.
+    pub alerts: Vec<ClipAlert>,
+    /// The flight in progress, or the last one once disarmed.
+    pub flight: Option<VibrationSummary>,
+}
+
+fn clip_rate(to: [u32; 3], from: [u32; 3]) -> [f64; 3] {
+    let window = RATE_WINDOW.as_secs_f64();
+    [0, 1, 2].map(|i| to[i].saturating_sub(from[i]) as f64 / window)
+}
+
+/// Running state behind `VibrationState`.
+#[derive(Debug, Default)]
+pub(crate) struct VibrationMonitor {
+    /// Clip counters whenever they changed, back to the start of the
+    /// window before the latest.
+    samples: VecDeque<(Instant, [u32; 3])>,
+    /// When the flight started, and the counters then.
+    flight_start: Option<(Instant, [u32; 3])>,
+    state: VibrationState,
+}
+
+impl VibrationMonitor {
+    pub(crate) fn state(&self) -> &VibrationState {
+        &self.state
+    }
+
+    fn latest(&self) -> Option<[u32; 3]> {
+        self.samples.back().map(|(_, counters)| *counters)
+    }
+
+    /// The counters as they stood at `at`, if known that far back.
+    fn counters_at(&self, at: Option<Instant>) -> Option<[u32; 3]> {
+        let at = at?;
+        self.samples
+            .iter()
+            .rev()
+            .find(|(sampled, _)| *sampled <= at)
+            .map(|(_, counters)| *counters)
+    }
+
+    pub(crate) fn set_armed(&mut self, armed: bool, now: Instant) {
+        match (self.flight_start, armed) {
+            (None, true) => {
+                self.flight_start = Some((now, self.latest().unwrap_or_default()));
+                self.state.flight = Some(VibrationSummary {
+                    in_progress: true,
+                    ..VibrationSummary::default()
+                });
+            }
+            (Some(_), false) => {
+                self.flight_start = None;
+                if let Some(flight) = &mut self.state.flight {
+                    flight.in_progress = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Take in the latest VIBRATION values; rates decay even when they
+    /// stay the same.
+    pub(crate) fn update(
+        &mut self,
+        levels: Option<[f64; 3]>,
+        clipping: Option<[u32; 3]>,
+        now: Instant,
+    ) {
+        self.state.levels_mps2 = levels;
+        if let Some(counters) = clipping {
+            // The counters restart with the autopilot
+            let restarted = self
+                .latest()
+                .is_some_and(|last| (0..3).any(|i| counters[i] < last[i]));
+            if restarted {
+                self.samples.clear();
+                if let Some((start, _)) = self.flight_start {
+                    self.flight_start = Some((start, [0; 3]));
+                }
+            }
+            if self.latest() != Some(counters) {
+                self.samples.push_back((now, counters));
+            }
+        }
+        let window_start = now.checked_sub(RATE_WINDOW);
+        let previous_start = now.checked_sub(RATE_WINDOW * 2);
+        // Keep one sample from before the earlier window as its baseline
+        while self.samples.len() > 1
+            && previous_start.is_some_and(|start| self.samples[1].0 <= start)
+        {
+            self.samples.pop_front();
+        }
+        let Some(latest) = self.latest() else {
+            return;
+        };
+        let oldest = self.samples.front().map(|(_, c)| *c).unwrap_or(latest);
+
+        let window_base = self.counters_at(window_start);
+        let current = clip_rate(latest, window_base.unwrap_or(oldest));
+        // Telling a trend takes the whole window before
+        let previous = window_base
+            .zip(self.counters_at(previous_start))
+            .map(|(base, earlier)| clip_rate(base, earlier));
+        self.state.clips_per_s = current;
+        self.update_alerts(current, previous, levels);
+
+        if let Some((started, start_counters)) = self.flight_start {
+            let flight = self
+                .state
+                .flight
+                .get_or_insert_with(VibrationSummary::default);
+            flight.duration_s = now.saturating_duration_since(started).as_secs_f64();
+            if let Some(levels) = levels {
+                for (max, level) in flight.max_level_mps2.iter_mut().zip(levels) {
+                    *max = max.max(level);
+                }
+            }
+            flight.clips = [0, 1, 2].map(|i| latest[i].saturating_sub(start_counters[i]));
+            flight.peak_clips_per_s = current.into_iter().fold(flight.peak_clips_per_s, f64::max);
+        }
+    }
+
+    fn update_alerts(
+        &mut self,
+        current: [f64; 3],
+        previous: Option<[f64; 3]>,
+        levels: Option<[f64; 3]>,
+    ) {
+        self.state
+            .alerts
+            .retain(|alert| current[alert.accel as usize] >= MIN_ALERT_CLIPS_PER_S);
+        for alert in &mut self.state.alerts {
+            alert.clips_per_s = current[alert.accel as usize];
+        }
+        let Some(previous) = previous else {
+            return;
+        };
+        for accel in 0..3u8 {
+            let rate = current[accel as usize];
+            let before = previous[accel as usize];
+            let active = self.state.alerts.iter().any(|a| a.accel == accel);
+            let accelerating =
+                rate >= MIN_ALERT_CLIPS_PER_S && rate >= before * ACCELERATION_FACTOR;
+            if active || !accelerating {
+                continue;
+            }
+            let alert = ClipAlert {
+                accel,
+                clips_per_s: rate,
+                previous_clips_per_s: before,
+                axis: levels.map(VibrationAxis::strongest),
+            };
+            self.state.alerts.push(alert);
+            if self.flight_start.is_some() {
+                if let Some(flight) = &mut self.state.flight {
+                    flight.alerts.push(alert);
+                }
+            }
+        }
+    }
+}
+
+/// Feed `out` from VIBRATION in `telemetry` and the arming state until the
+/// vehicle disconnects.
+pub(crate) async fn run_vibration_monitor(
+    mut telemetry: watch::Receiver<Telemetry>,
+    mut vehicle_state: watch::Receiver<VehicleState>,
+    out: watch::Sender<VibrationState>,
+) {
+    let mut monitor = VibrationMonitor::default();
+    loop {
+        tokio::select! {
+            changed = vehicle_state.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                let armed = vehicle_state.borrow_and_update().armed;
+                monitor.set_armed(armed, Instant::now());
+            }
+            changed = telemetry.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                let (levels, clipping) = {
+                    let telemetry = telemetry.borrow_and_update();
+                    (telemetry.vibration_mps2, telemetry.accel_clipping)
+                };
+                monitor.update(levels, clipping, Instant::now());
+            }
+        }
+        out.send_if_modified(|shown| {
+            let changed = shown != monitor.state();
+            if changed {
+                *shown = monitor.state().clone();
+            }
+            changed
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVELS: Option<[f64; 3]> = Some([12.0, 15.0, 41.0]);
+
+    /// Feed accelerometer 0 `per_s` clips a second for `seconds`, once a
+    /// second, starting at `start` with `clips`.
+    fn clip(
+        monitor: &mut VibrationMonitor,
+        start: Instant,
+        clips: &mut u32,
+        per_s: u32,
+        seconds: u64,
+    ) -> Instant {
+        let mut at = start;
+        for _ in 0..seconds {
+            at += Duration::from_secs(1);
+            *clips += per_s;
+            monitor.update(LEVELS, Some([*clips, 0, 0]), at);
+        }
+        at
+    }
+
+    #[test]
+    fn steady_clipping_is_not_an_alert() {
+        let mut monitor = VibrationMonitor::default();
+        let mut clips = 0;
+        let start = Instant::now();
+        monitor.update(LEVELS, Some([0, 0, 0]), start);
+        clip(&mut monitor, start, &mut clips, 1, 40);
+        let state = monitor.state();
+        assert!((state.clips_per_s[0] - 1.0).abs() < 1e-9);
+        assert!(state.alerts.is_empty());
+    }
+
+    #[test]
+    fn accelerating_clipping_alerts_and_lands_in_the_flight_summary() {
+        let mut monitor = VibrationMonitor::default();
+        let mut clips = 0;
+        let start = Instant::now();
+        monitor.update(LEVELS, Some([0, 0, 0]), start);
+        monitor.set_armed(true, start);
+        let at = clip(&mut monitor, start, &mut clips, 1, 20);
+        let at = clip(&mut monitor, at, &mut clips, 5, 10);
+
+        let alert = monitor.state().alerts[0];
+        assert_eq!(alert.accel, 0);
+        assert_eq!(alert.axis, Some(VibrationAxis::Z));
+        assert!(alert.clips_per_s > alert.previous_clips_per_s * ACCELERATION_FACTOR);
+
+        // Clipping stops; the alert clears once the window is quiet
+        let at = clip(&mut monitor, at, &mut clips, 0, 10);
+        assert!(monitor.state().alerts.is_empty());
+
+        monitor.set_armed(false, at);
+        let flight = monitor.state().flight.clone().unwrap();
+        assert!(!flight.in_progress);
+        assert_eq!(flight.clips, [70, 0, 0]);
+        assert_eq!(flight.alerts.len(), 1);
+        assert_eq!(flight.max_level_mps2, [12.0, 15.0, 41.0]);
+        assert!((flight.peak_clips_per_s - 5.0).abs() < 1e-9);
+    }
+}
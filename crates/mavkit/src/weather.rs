@@ -0,0 +1,387 @@
+//! Wind aloft for planning.
+//!
+//! A `WeatherProvider` supplies the wind profile at a location; planning uses
+//! it to turn still-air leg times into headwind-adjusted ones. Providers are
+//! pluggable: `FixedWind` serves an operator-entered wind with no network
+//! access, and the Open-Meteo helpers build the request and read the reply
+//! for an HTTP-backed provider.
+
+use crate::mission::geo::{bearing_deg, distance_m};
+use crate::mission::MissionPlan;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Heights above ground Open-Meteo reports wind for, metres.
+pub const OPEN_METEO_HEIGHTS_M: [u16; 4] = [10, 80, 120, 180];
+
+/// Wind at one height.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindSample {
+    /// Height above ground.
+    pub altitude_m: f64,
+    pub speed_mps: f64,
+    /// Direction the wind blows from, degrees clockwise from north.
+    pub direction_deg: f64,
+}
+
+/// Wind at several heights, sorted by altitude.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindProfile {
+    pub samples: Vec<WindSample>,
+}
+
+impl WindProfile {
+    pub fn new(mut samples: Vec<WindSample>) -> Self {
+        samples.sort_by(|a, b| a.altitude_m.total_cmp(&b.altitude_m));
+        Self { samples }
+    }
+
+    /// Wind at `altitude_m`, interpolated as a vector between the nearest
+    /// heights and held constant above and below the profile.
+    pub fn at(&self, altitude_m: f64) -> Option<WindSample> {
+        let first = self.samples.first()?;
+        let last = self.samples.last()?;
+        if altitude_m <= first.altitude_m {
+            return Some(WindSample {
+                altitude_m,
+                ..*first
+            });
+        }
+        if altitude_m >= last.altitude_m {
+            return Some(WindSample {
+                altitude_m,
+                ..*last
+            });
+        }
+        let upper = self
+            .samples
+            .iter()
+            .position(|s| s.altitude_m >= altitude_m)?;
+        let (a, b) = (self.samples[upper - 1], self.samples[upper]);
+        let t = (altitude_m - a.altitude_m) / (b.altitude_m - a.altitude_m);
+        let (ax, ay) = wind_vector(&a);
+        let (bx, by) = wind_vector(&b);
+        let (x, y) = (ax + t * (bx - ax), ay + t * (by - ay));
+        Some(WindSample {
+            altitude_m,
+            speed_mps: x.hypot(y),
+            direction_deg: x.atan2(y).to_degrees().rem_euclid(360.0),
+        })
+    }
+}
+
+/// `(east, north)` components pointing where the wind comes from.
+fn wind_vector(sample: &WindSample) -> (f64, f64) {
+    let direction = sample.direction_deg.to_radians();
+    (
+        sample.speed_mps * direction.sin(),
+        sample.speed_mps * direction.cos(),
+    )
+}
+
+/// A forecast that could not be fetched or read.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("weather: {0}")]
+pub struct WeatherError(pub String);
+
+pub type WeatherFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<WindProfile, WeatherError>> + Send + 'a>>;
+
+/// Source of wind aloft. `at_unix_s` selects the forecast hour.
+pub trait WeatherProvider: Send + Sync {
+    fn wind_profile(&self, lat: f64, lon: f64, at_unix_s: i64) -> WeatherFuture<'_>;
+}
+
+/// The same wind everywhere, for offline planning or a wind read off a
+/// windsock.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FixedWind {
+    pub speed_mps: f64,
+    pub direction_deg: f64,
+}
+
+impl WeatherProvider for FixedWind {
+    fn wind_profile(&self, _lat: f64, _lon: f64, _at_unix_s: i64) -> WeatherFuture<'_> {
+        let profile = WindProfile::new(vec![WindSample {
+            altitude_m: 0.0,
+            speed_mps: self.speed_mps,
+            direction_deg: self.direction_deg,
+        }]);
+        Box::pin(async move { Ok(profile) })
+    }
+}
+
+/// Hourly wind profiles from one forecast request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindForecast {
+    /// `(unix seconds, profile)`, in time order.
+    pub hours: Vec<(i64, WindProfile)>,
+}
+
+impl WindForecast {
+    /// The profile for the hour nearest `at_unix_s`, if the forecast covers it.
+    pub fn at(&self, at_unix_s: i64) -> Option<&WindProfile> {
+        let (first, _) = self.hours.first()?;
+        let (last, _) = self.hours.last()?;
+        if at_unix_s < first - 3600 || at_unix_s > last + 3600 {
+            return None;
+        }
+        self.hours
+            .iter()
+            .min_by_key(|(time, _)| (time - at_unix_s).abs())
+            .map(|(_, profile)| profile)
+    }
+}
+
+/// Open-Meteo forecast request for the wind at `OPEN_METEO_HEIGHTS_M`.
+pub fn open_meteo_url(lat: f64, lon: f64) -> String {
+    let hourly: Vec<String> = OPEN_METEO_HEIGHTS_M
+        .iter()
+        .flat_map(|h| [format!("wind_speed_{h}m"), format!("wind_direction_{h}m")])
+        .collect();
+    format!(
+        "https://api.open-meteo.com/v1/forecast?latitude={lat:.4}&longitude={lon:.4}\
+         &hourly={}&wind_speed_unit=ms&timeformat=unixtime&forecast_days=2",
+        hourly.join(",")
+    )
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoResponse {
+    hourly: HashMap<String, Vec<Option<f64>>>,
+}
+
+/// Read an Open-Meteo forecast response requested with `open_meteo_url`.
+/// Heights with missing values are left out of that hour's profile.
+pub fn parse_open_meteo(contents: &str) -> Result<WindForecast, WeatherError> {
+    let response: OpenMeteoResponse =
+        serde_json::from_str(contents).map_err(|err| WeatherError(err.to_string()))?;
+    let series = |name: &str| {
+        response
+            .hourly
+            .get(name)
+            .ok_or_else(|| WeatherError(format!("response has no {name} series")))
+    };
+    let times = series("time")?;
+    let mut levels = Vec::new();
+    for height in OPEN_METEO_HEIGHTS_M {
+        let speeds = series(&format!("wind_speed_{height}m"))?;
+        let directions = series(&format!("wind_direction_{height}m"))?;
+        levels.push((f64::from(height), speeds, directions));
+    }
+    let hours = times
+        .iter()
+        .enumerate()
+        .filter_map(|(index, time)| {
+            let samples = levels
+                .iter()
+                .filter_map(|(altitude_m, speeds, directions)| {
+                    Some(WindSample {
+                        altitude_m: *altitude_m,
+                        speed_mps: (*speeds.get(index)?)?,
+                        direction_deg: (*directions.get(index)?)?,
+                    })
+                })
+                .collect::<Vec<_>>();
+            Some(((*time)? as i64, WindProfile::new(samples)))
+        })
+        .filter(|(_, profile)| !profile.samples.is_empty())
+        .collect();
+    Ok(WindForecast { hours })
+}
+
+/// One leg of a route flown through a wind profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LegWind {
+    /// The item ending the leg.
+    pub seq: u16,
+    pub distance_m: f64,
+    pub track_deg: f64,
+    /// Positive against the direction of travel.
+    pub headwind_mps: f64,
+    /// Positive from the right of the track.
+    pub crosswind_mps: f64,
+    /// `None` when the crosswind is stronger than the airspeed or the
+    /// headwind stops the vehicle.
+    pub ground_speed_mps: Option<f64>,
+    pub duration_s: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindEstimate {
+    pub legs: Vec<LegWind>,
+    pub still_air_duration_s: f64,
+    /// `None` if any leg cannot be flown at the given airspeed.
+    pub duration_s: Option<f64>,
+    pub max_headwind_mps: f64,
+}
+
+/// Leg-by-leg ground speed and time for `plan` flown at `airspeed_mps`.
+///
+/// Each leg uses the wind at its destination's altitude, taken as height
+/// above ground (missions are normally relative to home). Turns, climbs and
+/// loiters are not modelled.
+pub fn wind_adjusted_estimate(
+    plan: &MissionPlan,
+    profile: &WindProfile,
+    airspeed_mps: f64,
+) -> WindEstimate {
+    let positions: Vec<_> = plan
+        .items
+        .iter()
+        .filter(|item| item.is_nav_position())
+        .collect();
+    let mut legs = Vec::new();
+    for pair in positions.windows(2) {
+        let ((lat1, lon1), (lat2, lon2)) = (pair[0].latlon_deg(), pair[1].latlon_deg());
+        let distance = distance_m(lat1, lon1, lat2, lon2);
+        if distance == 0.0 {
+            continue;
+        }
+        let track_deg = bearing_deg(lat1, lon1, lat2, lon2);
+        let (headwind_mps, crosswind_mps, ground_speed_mps) =
+            wind_triangle(profile.at(f64::from(pair[1].z)), track_deg, airspeed_mps);
+        legs.push(LegWind {
+            seq: pair[1].seq,
+            distance_m: distance,
+            track_deg,
+            headwind_mps,
+            crosswind_mps,
+            ground_speed_mps,
+            duration_s: ground_speed_mps.map(|speed| distance / speed),
+        });
+    }
+    let total_distance: f64 = legs.iter().map(|leg| leg.distance_m).sum();
+    WindEstimate {
+        still_air_duration_s: if airspeed_mps > 0.0 {
+            total_distance / airspeed_mps
+        } else {
+            0.0
+        },
+        duration_s: legs.iter().map(|leg| leg.duration_s).sum(),
+        max_headwind_mps: legs.iter().map(|leg| leg.headwind_mps).fold(0.0, f64::max),
+        legs,
+    }
+}
+
+/// Headwind, crosswind and ground speed flying `track_deg` at `airspeed_mps`
+/// through `wind` (still air when `None`). The ground speed is `None` when
+/// the track cannot be held.
+pub(crate) fn wind_triangle(
+    wind: Option<WindSample>,
+    track_deg: f64,
+    airspeed_mps: f64,
+) -> (f64, f64, Option<f64>) {
+    let Some(wind) = wind else {
+        return (0.0, 0.0, Some(airspeed_mps).filter(|speed| *speed > 0.0));
+    };
+    let relative = (wind.direction_deg - track_deg).to_radians();
+    let headwind_mps = wind.speed_mps * relative.cos();
+    let crosswind_mps = wind.speed_mps * relative.sin();
+    // Crab into the crosswind; what is left of the airspeed goes along track
+    let ground_speed_mps = (airspeed_mps > crosswind_mps.abs())
+        .then(|| (airspeed_mps.powi(2) - crosswind_mps.powi(2)).sqrt() - headwind_mps)
+        .filter(|speed| *speed > 0.0);
+    (headwind_mps, crosswind_mps, ground_speed_mps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mission::builder::{global_item, resequence, MAV_CMD_NAV_WAYPOINT};
+    use crate::mission::geo::destination;
+    use crate::mission::MissionType;
+
+    fn sample(altitude_m: f64, speed_mps: f64, direction_deg: f64) -> WindSample {
+        WindSample {
+            altitude_m,
+            speed_mps,
+            direction_deg,
+        }
+    }
+
+    fn out_and_back(leg_m: f64, alt_m: f32) -> MissionPlan {
+        let (lat, lon) = (47.0, 8.0);
+        let (north_lat, north_lon) = destination(lat, lon, 0.0, leg_m);
+        let mut items = vec![
+            global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, alt_m),
+            global_item(MAV_CMD_NAV_WAYPOINT, north_lat, north_lon, alt_m),
+            global_item(MAV_CMD_NAV_WAYPOINT, lat, lon, alt_m),
+        ];
+        resequence(&mut items);
+        MissionPlan {
+            mission_type: MissionType::Mission,
+            home: None,
+            items,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn profile_interpolates_and_clamps() {
+        let profile = WindProfile::new(vec![sample(100.0, 10.0, 90.0), sample(0.0, 4.0, 90.0)]);
+        let mid = profile.at(50.0).unwrap();
+        assert!((mid.speed_mps - 7.0).abs() < 1e-9);
+        assert!((mid.direction_deg - 90.0).abs() < 1e-9);
+        assert_eq!(profile.at(500.0).unwrap().speed_mps, 10.0);
+        assert_eq!(profile.at(-5.0).unwrap().speed_mps, 4.0);
+        assert_eq!(WindProfile::default().at(10.0), None);
+    }
+
+    #[test]
+    fn headwind_slows_one_leg_more_than_tailwind_speeds_the_other() {
+        let plan = out_and_back(1000.0, 50.0);
+        let profile = WindProfile::new(vec![sample(0.0, 5.0, 0.0)]);
+        let estimate = wind_adjusted_estimate(&plan, &profile, 15.0);
+        assert_eq!(estimate.legs.len(), 2);
+        assert!((estimate.legs[0].headwind_mps - 5.0).abs() < 1e-6);
+        assert!((estimate.legs[0].ground_speed_mps.unwrap() - 10.0).abs() < 1e-6);
+        assert!((estimate.legs[1].ground_speed_mps.unwrap() - 20.0).abs() < 1e-6);
+        assert!((estimate.still_air_duration_s - 2000.0 / 15.0).abs() < 0.1);
+        let duration = estimate.duration_s.unwrap();
+        assert!((duration - (100.0 + 50.0)).abs() < 0.1);
+        assert!((estimate.max_headwind_mps - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn wind_stronger_than_airspeed_has_no_duration() {
+        let plan = out_and_back(500.0, 30.0);
+        let profile = WindProfile::new(vec![sample(0.0, 12.0, 0.0)]);
+        let estimate = wind_adjusted_estimate(&plan, &profile, 10.0);
+        assert_eq!(estimate.legs[0].ground_speed_mps, None);
+        assert!(estimate.legs[1].ground_speed_mps.is_some());
+        assert_eq!(estimate.duration_s, None);
+    }
+
+    #[test]
+    fn forecast_picks_nearest_hour() {
+        let forecast = WindForecast {
+            hours: vec![
+                (0, WindProfile::new(vec![sample(10.0, 1.0, 0.0)])),
+                (3600, WindProfile::new(vec![sample(10.0, 2.0, 0.0)])),
+            ],
+        };
+        assert_eq!(forecast.at(2000).unwrap().samples[0].speed_mps, 2.0);
+        assert_eq!(forecast.at(-100).unwrap().samples[0].speed_mps, 1.0);
+        assert_eq!(forecast.at(4 * 3600), None);
+        assert!(open_meteo_url(47.0, 8.0).contains("wind_direction_180m"));
+    }
+
+    #[test]
+    fn parses_open_meteo_hourly_series() {
+        let json = r#"{"hourly": {
+            "time": [1700000000, 1700003600],
+            "wind_speed_10m": [3.0, null], "wind_direction_10m": [270, 280],
+            "wind_speed_80m": [5.0, 6.0], "wind_direction_80m": [275, 285],
+            "wind_speed_120m": [6.0, 7.0], "wind_direction_120m": [280, 290],
+            "wind_speed_180m": [7.0, 8.0], "wind_direction_180m": [285, 295]
+        }}"#;
+        let forecast = parse_open_meteo(json).unwrap();
+        assert_eq!(forecast.hours.len(), 2);
+        assert_eq!(forecast.hours[0].1.samples.len(), 4);
+        assert_eq!(forecast.hours[1].1.samples[0].altitude_m, 80.0);
+        assert!(parse_open_meteo(r#"{"hourly": {}}"#).is_err());
+    }
+}
@@ -6,10 +6,38 @@ use std::time::Duration;
 
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
 
+#[cfg(not(feature = "sitl-harness"))]
 fn sitl_bind_addr() -> String {
     std::env::var("MP_SITL_UDP_BIND").unwrap_or_else(|_| String::from("0.0.0.0:14550"))
 }
 
+/// Keeps the simulator a test launched running until the test ends.
+#[cfg(feature = "sitl-harness")]
+type SitlGuard = mavkit::SitlInstance;
+#[cfg(not(feature = "sitl-harness"))]
+type SitlGuard = ();
+
+/// A SITL of the test's own, with `MP_SITL_BINARY` naming a local build
+/// instead of downloading one.
+#[cfg(feature = "sitl-harness")]
+async fn connect_sitl() -> (Vehicle, SitlGuard) {
+    let mut options = mavkit::SitlOptions::copter();
+    if let Ok(binary) = std::env::var("MP_SITL_BINARY") {
+        options.binary = Some(binary.into());
+    }
+    let sitl = mavkit::SitlInstance::launch(&options)
+        .await
+        .expect("SITL should launch");
+    let vehicle = sitl.connect().await.expect("should connect to SITL");
+    (vehicle, sitl)
+}
+
+/// The SITL bridged to `MP_SITL_UDP_BIND`, e.g. by `make bridge-up`.
+#[cfg(not(feature = "sitl-harness"))]
+async fn connect_sitl() -> (Vehicle, SitlGuard) {
+    (Vehicle::connect_udp(&sitl_bind_addr()).await.unwrap(), ())
+}
+
 fn is_optional_type_unsupported(mission_type: MissionType, error: &VehicleError) -> bool {
     if mission_type == MissionType::Mission {
         return false;
@@ -47,7 +75,7 @@ where
 // ---------------------------------------------------------------------------
 
 async fn run_roundtrip_case(plan: MissionPlan) {
-    let vehicle = Vehicle::connect_udp(&sitl_bind_addr()).await.unwrap();
+    let (vehicle, _sitl) = connect_sitl().await;
 
     let result: Result<(), String> = async {
         // Wait for telemetry to arrive
@@ -185,16 +213,13 @@ async fn download_with_retries(
 fn sample_plan_mission() -> MissionPlan {
     MissionPlan {
         mission_type: MissionType::Mission,
-        home: Some(HomePosition {
-            latitude_deg: 47.397742,
-            longitude_deg: 8.545594,
-            altitude_m: 0.0,
-        }),
+        home: Some(HomePosition::amsl(47.397742, 8.545594, 0.0)),
         items: vec![
             waypoint(0, 47.397742, 8.545594, 25.0),
             waypoint(1, 47.398100, 8.546100, 30.0),
             waypoint(2, 47.398450, 8.546500, 28.0),
         ],
+        metadata: Default::default(),
     }
 }
 
@@ -212,6 +237,9 @@ fn waypoint(seq: u16, lat: f64, lon: f64, alt: f32) -> MissionItem {
         x: (lat * 1e7) as i32,
         y: (lon * 1e7) as i32,
         z: alt,
+        label: None,
+        notes: None,
+        downloaded_as_float: false,
     }
 }
 
@@ -220,29 +248,40 @@ fn waypoint(seq: u16, lat: f64, lon: f64, alt: f32) -> MissionItem {
 // ---------------------------------------------------------------------------
 
 #[tokio::test]
-#[ignore = "requires ArduPilot SITL endpoint"]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
 async fn sitl_roundtrip_mission_type_mission() {
     run_roundtrip_case(sample_plan_mission()).await;
 }
 
 #[tokio::test]
-#[ignore = "requires ArduPilot SITL endpoint"]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
 async fn sitl_roundtrip_mission_type_fence() {
     run_roundtrip_case(MissionPlan {
         mission_type: MissionType::Fence,
         home: None,
         items: Vec::new(),
+        metadata: Default::default(),
     })
     .await;
 }
 
 #[tokio::test]
-#[ignore = "requires ArduPilot SITL endpoint"]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
 async fn sitl_roundtrip_mission_type_rally() {
     run_roundtrip_case(MissionPlan {
         mission_type: MissionType::Rally,
         home: None,
         items: Vec::new(),
+        metadata: Default::default(),
     })
     .await;
 }
@@ -251,8 +290,8 @@ async fn sitl_roundtrip_mission_type_rally() {
 // Vehicle command tests
 // ---------------------------------------------------------------------------
 
-async fn setup_sitl_vehicle() -> Vehicle {
-    let vehicle = Vehicle::connect_udp(&sitl_bind_addr()).await.unwrap();
+async fn setup_sitl_vehicle() -> (Vehicle, SitlGuard) {
+    let (vehicle, sitl) = connect_sitl().await;
     // Wait for telemetry
     let mut telem_rx = vehicle.telemetry();
     tokio::time::timeout(CONNECT_TIMEOUT, async {
@@ -266,7 +305,7 @@ async fn setup_sitl_vehicle() -> Vehicle {
     })
     .await
     .expect("should receive telemetry from SITL");
-    vehicle
+    (vehicle, sitl)
 }
 
 async fn arm_with_retries(vehicle: &Vehicle, force: bool, timeout: Duration) -> Result<(), String> {
@@ -287,9 +326,12 @@ async fn arm_with_retries(vehicle: &Vehicle, force: bool, timeout: Duration) ->
 }
 
 #[tokio::test]
-#[ignore = "requires ArduPilot SITL endpoint"]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
 async fn sitl_force_arm_disarm_cycle() {
-    let vehicle = setup_sitl_vehicle().await;
+    let (vehicle, _sitl) = setup_sitl_vehicle().await;
 
     let result: Result<(), String> = async {
         vehicle.arm(true).await.map_err(|e| e.to_string())?;
@@ -309,9 +351,12 @@ async fn sitl_force_arm_disarm_cycle() {
 }
 
 #[tokio::test]
-#[ignore = "requires ArduPilot SITL endpoint"]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
 async fn sitl_set_flight_mode() {
-    let vehicle = setup_sitl_vehicle().await;
+    let (vehicle, _sitl) = setup_sitl_vehicle().await;
 
     let result: Result<(), String> = async {
         // Set GUIDED (custom_mode=4)
@@ -345,9 +390,12 @@ async fn sitl_set_flight_mode() {
 }
 
 #[tokio::test]
-#[ignore = "requires ArduPilot SITL endpoint"]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
 async fn sitl_takeoff_and_land() {
-    let vehicle = setup_sitl_vehicle().await;
+    let (vehicle, _sitl) = setup_sitl_vehicle().await;
 
     let result: Result<(), String> = async {
         vehicle.set_mode(4).await.map_err(|e| e.to_string())?; // GUIDED
@@ -375,9 +423,12 @@ async fn sitl_takeoff_and_land() {
 }
 
 #[tokio::test]
-#[ignore = "requires ArduPilot SITL endpoint"]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
 async fn sitl_guided_goto() {
-    let vehicle = setup_sitl_vehicle().await;
+    let (vehicle, _sitl) = setup_sitl_vehicle().await;
 
     let result: Result<(), String> = async {
         vehicle.set_mode(4).await.map_err(|e| e.to_string())?; // GUIDED
@@ -407,9 +458,12 @@ async fn sitl_guided_goto() {
 }
 
 #[tokio::test]
-#[ignore = "requires ArduPilot SITL endpoint"]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
 async fn sitl_get_available_modes() {
-    let vehicle = setup_sitl_vehicle().await;
+    let (vehicle, _sitl) = setup_sitl_vehicle().await;
 
     let result: Result<(), String> = async {
         let modes = vehicle.available_modes();
@@ -444,3 +498,40 @@ async fn sitl_get_available_modes() {
         panic!("{err}");
     }
 }
+
+#[tokio::test]
+#[cfg_attr(
+    not(feature = "sitl-harness"),
+    ignore = "requires ArduPilot SITL endpoint"
+)]
+async fn sitl_wind_injection() {
+    let (vehicle, _sitl) = setup_sitl_vehicle().await;
+
+    let result: Result<(), String> = async {
+        vehicle.params().download_all().await.map_err(|e| e.to_string())?;
+
+        vehicle
+            .sitl()
+            .set_wind(6.0, 270.0, 0.0)
+            .await
+            .map_err(|e| e.to_string())?;
+        let store = vehicle.param_store().borrow().clone();
+        let wind = store
+            .params
+            .get("SIM_WIND_SPD")
+            .map(|p| p.value)
+            .ok_or_else(|| String::from("SIM_WIND_SPD missing after write"))?;
+        if (wind - 6.0).abs() > 0.01 {
+            return Err(format!("expected SIM_WIND_SPD 6, got {wind}"));
+        }
+
+        vehicle.sitl().clear_failures().await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+    .await;
+
+    let _ = vehicle.disconnect().await;
+    if let Err(err) = result {
+        panic!("{err}");
+    }
+}
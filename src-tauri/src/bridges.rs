@@ -0,0 +1,98 @@
+//! Ownership of the event bridge tasks spawned for a connected vehicle.
+//!
+//! Each bridge forwards one vehicle watch channel to the frontend. They are
+//! collected in a `BridgeSet` held in `AppState` and aborted together on
+//! disconnect or reconnect, so a previous vehicle's bridges never keep
+//! running or emitting stale events.
+//!
+//! Bridges for large payloads keep the last emitted JSON in a `JsonCache`,
+//! so an unchanged value is neither serialized nor sent again.
+
+use serde::Serialize;
+use serde_json::value::RawValue;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+#[derive(Serialize, Clone)]
+pub(crate) struct BridgeHealth {
+    pub name: &'static str,
+    /// False once the task has exited, e.g. its channel closed unexpectedly.
+    pub running: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct BridgeSet {
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
+}
+
+impl BridgeSet {
+    pub(crate) fn spawn<F>(&mut self, name: &'static str, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.push((name, tokio::spawn(task)));
+    }
+
+    pub(crate) fn abort_all(&mut self) {
+        for (_, task) in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+
+    pub(crate) fn health(&self) -> Vec<BridgeHealth> {
+        self.tasks
+            .iter()
+            .map(|(name, task)| BridgeHealth {
+                name,
+                running: !task.is_finished(),
+            })
+            .collect()
+    }
+}
+
+impl Drop for BridgeSet {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+/// Serialized form of the last value a bridge emitted.
+pub(crate) struct JsonCache<T> {
+    value: Option<Arc<T>>,
+    json: Option<Box<RawValue>>,
+}
+
+impl<T> Default for JsonCache<T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            json: None,
+        }
+    }
+}
+
+impl<T: Serialize> JsonCache<T> {
+    /// JSON to emit for `value`, or `None` if it is the value already sent
+    /// (the same `Arc`, or one that serializes identically).
+    pub(crate) fn update(&mut self, value: &Arc<T>) -> Option<Box<RawValue>> {
+        if self
+            .value
+            .as_ref()
+            .is_some_and(|last| Arc::ptr_eq(last, value))
+        {
+            return None;
+        }
+        self.value = Some(value.clone());
+        let json = serde_json::value::to_raw_value(&**value).ok()?;
+        if self
+            .json
+            .as_ref()
+            .is_some_and(|last| last.get() == json.get())
+        {
+            return None;
+        }
+        self.json = Some(json.clone());
+        Some(json)
+    }
+}
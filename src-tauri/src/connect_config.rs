@@ -0,0 +1,159 @@
+//! `VehicleConfig` settings a connection can override.
+//!
+//! Each field of `ConnectOverrides` replaces the app's built-in choice when
+//! set. They come from two places: the `ConnectRequest` of a connection, and
+//! the command line (`--gcs-system-id 254 --gcs-component-id 191`), which
+//! sets them for every connection of the process so two instances of the app
+//! can talk to one vehicle under distinct GCS ids. The request wins over the
+//! command line.
+
+use mavkit::{BatteryChemistry, CellLimits, TelemetrySmoothing, VehicleConfig};
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct ConnectOverrides {
+    pub gcs_system_id: Option<u8>,
+    pub gcs_component_id: Option<u8>,
+    pub connect_timeout_ms: Option<u64>,
+    /// Period of our GCS HEARTBEAT; 0 turns it off.
+    pub gcs_heartbeat_interval_ms: Option<u64>,
+    /// Period of TIMESYNC requests; 0 turns them off.
+    pub timesync_interval_ms: Option<u64>,
+    /// Window for dropping packets that arrive twice; 0 turns it off.
+    pub link_dedup_window_ms: Option<u64>,
+    pub auto_request_home: Option<bool>,
+    pub telemetry_smoothing: Option<TelemetrySmoothing>,
+    /// Selects the cell limits behind battery alerts.
+    pub battery_chemistry: Option<BatteryChemistry>,
+}
+
+/// `Some` period, or `None` for 0.
+fn interval(ms: u64) -> Option<Duration> {
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+impl ConnectOverrides {
+    /// These overrides, with `fallback`'s filling the fields left unset.
+    pub(crate) fn or(&self, fallback: &ConnectOverrides) -> ConnectOverrides {
+        ConnectOverrides {
+            gcs_system_id: self.gcs_system_id.or(fallback.gcs_system_id),
+            gcs_component_id: self.gcs_component_id.or(fallback.gcs_component_id),
+            connect_timeout_ms: self.connect_timeout_ms.or(fallback.connect_timeout_ms),
+            gcs_heartbeat_interval_ms: self
+                .gcs_heartbeat_interval_ms
+                .or(fallback.gcs_heartbeat_interval_ms),
+            timesync_interval_ms: self.timesync_interval_ms.or(fallback.timesync_interval_ms),
+            link_dedup_window_ms: self.link_dedup_window_ms.or(fallback.link_dedup_window_ms),
+            auto_request_home: self.auto_request_home.or(fallback.auto_request_home),
+            telemetry_smoothing: self.telemetry_smoothing.or(fallback.telemetry_smoothing),
+            battery_chemistry: self.battery_chemistry.or(fallback.battery_chemistry),
+        }
+    }
+
+    pub(crate) fn apply(&self, config: &mut VehicleConfig) {
+        if let Some(id) = self.gcs_system_id {
+            config.gcs_system_id = id;
+        }
+        if let Some(id) = self.gcs_component_id {
+            config.gcs_component_id = id;
+        }
+        if let Some(ms) = self.connect_timeout_ms {
+            config.connect_timeout = Duration::from_millis(ms);
+        }
+        if let Some(ms) = self.gcs_heartbeat_interval_ms {
+            config.gcs_heartbeat_interval = interval(ms);
+        }
+        if let Some(ms) = self.timesync_interval_ms {
+            config.timesync_interval = interval(ms);
+        }
+        if let Some(ms) = self.link_dedup_window_ms {
+            config.link_dedup_window = interval(ms);
+        }
+        if let Some(auto_request_home) = self.auto_request_home {
+            config.auto_request_home = auto_request_home;
+        }
+        if let Some(smoothing) = self.telemetry_smoothing {
+            config.telemetry_smoothing = Some(smoothing);
+        }
+        if let Some(chemistry) = self.battery_chemistry {
+            config.battery_cell_limits = CellLimits::for_chemistry(chemistry);
+        }
+    }
+
+    /// Overrides given on the command line, as `--name value` or
+    /// `--name=value`. Other arguments are left for Tauri and the OS.
+    pub(crate) fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut overrides = ConnectOverrides::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let field = match name.as_str() {
+                "--gcs-system-id" => &mut overrides.gcs_system_id,
+                "--gcs-component-id" => &mut overrides.gcs_component_id,
+                _ => continue,
+            };
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{name} needs a value"))?;
+            let id = value
+                .parse::<u8>()
+                .ok()
+                .filter(|id| *id > 0)
+                .ok_or_else(|| format!("{name} must be 1-255, got {value:?}"))?;
+            *field = Some(id);
+        }
+        Ok(overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn ids_from_the_command_line() {
+        let overrides =
+            ConnectOverrides::from_args(args("app --gcs-system-id 254 --gcs-component-id=191"))
+                .unwrap();
+        assert_eq!(overrides.gcs_system_id, Some(254));
+        assert_eq!(overrides.gcs_component_id, Some(191));
+
+        assert!(ConnectOverrides::from_args(args("app --gcs-system-id 0")).is_err());
+        assert!(ConnectOverrides::from_args(args("app --gcs-system-id")).is_err());
+        assert_eq!(
+            ConnectOverrides::from_args(args("app --other 3")).unwrap(),
+            ConnectOverrides::default()
+        );
+    }
+
+    #[test]
+    fn request_wins_over_command_line() {
+        let cli = ConnectOverrides {
+            gcs_system_id: Some(254),
+            gcs_component_id: Some(191),
+            ..ConnectOverrides::default()
+        };
+        let request = ConnectOverrides {
+            gcs_system_id: Some(253),
+            gcs_heartbeat_interval_ms: Some(0),
+            ..ConnectOverrides::default()
+        };
+        let mut config = VehicleConfig {
+            gcs_heartbeat_interval: Some(Duration::from_secs(1)),
+            ..VehicleConfig::default()
+        };
+        request.or(&cli).apply(&mut config);
+        assert_eq!(config.gcs_system_id, 253);
+        assert_eq!(config.gcs_component_id, 191);
+        assert_eq!(config.gcs_heartbeat_interval, None);
+    }
+}
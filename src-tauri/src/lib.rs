@@ -1,50 +1,205 @@
+mod bridges;
+mod connect_config;
+mod operations;
+#[cfg(debug_assertions)]
+mod recorder;
+mod telemetry_stream;
+mod weather;
+
+use bridges::{BridgeHealth, BridgeSet, JsonCache};
+use connect_config::ConnectOverrides;
 use mavkit::{
-    format_param_file, parse_param_file, validate_plan, FlightMode, HomePosition, LinkState,
-    MissionIssue, MissionPlan, MissionType, Param, ParamProgress, ParamStore, Telemetry,
-    TransferProgress, Vehicle, VehicleState,
+    audit_imported_coordinates, detect_conflicts, display_telemetry, format_coordinate,
+    format_param_file, generate_search_pattern, generate_structure_scan, is_sitl,
+    mission::rtl::DEFAULT_RTL_ALT_M, open_plan, parse_coordinate, parse_param_file,
+    parse_signing_key, parse_verifying_key, preview_rtl, raw_message_template, rtl_alt_from_params,
+    seal_plan, sensor_rotations, simplify, simulate, snap_altitudes, snap_to_grid, sun_position,
+    sun_times, sun_warnings, sync_progress, validate_against_fence, validate_ardupilot_acceptance,
+    validate_plan, validate_vtol_transitions, wind_adjusted_estimate, wire_item_count,
+    wrap_vtol_block, AirframePreset, AirspaceProximity, AirspaceSet, ArduPilotProfile, AuditEntry,
+    AutopilotType, BatteryHealth, BatteryInfo, CancellationToken, ConfigPatch, Conflict,
+    ControlState, CoordinateFormat, EscTelemetry, FailsafeConfig, FailsafeOptions, FenceStatus,
+    FixedWind, FlightMode, FrameParams, GcsPeer, GotoProposal, GripperAction, HomePosition,
+    LandingState, LinkPacing, LinkState, MissionIssue, MissionPlan, MissionType, ModeOverrides,
+    NudgeDirection, OrbitDirection, Param, ParamCache, ParamChange, ParamGroup, ParamProgress,
+    ParamStore, PayloadCapabilities, PlanHistory, PlanIdentity, PlanRecipient, PlanSnapshot,
+    PlanSyncStatus, PlannedFlight, PoseOutput, PoseOutputOptions, RateBenchmarkOptions,
+    RcCalibrationSession, RcChannelCalibration, RemoteIdOperator, RemoteIdState, ReturnToMeOptions,
+    ReturnToMeState, RtlAltitude, RtlPreview, SearchPatternParams, SensorRotation, SensorSetup,
+    Separation, ShutdownWarning, SimAction, SimTimeline, Simplified, SmoothedTelemetry,
+    StaticKeyring, StructureScanParams, SunPosition, SunTimes, Telemetry, TelemetrySmoothing,
+    TlsOptions, TrafficAdvisory, TrafficTarget, TransferProgress, Units, UploadOptions, Vehicle,
+    VehicleConfig, VehicleError, VehicleIdentity, VehicleProfile, VehicleState, VibrationState,
+    VtolProfile, VtolWrapParams, WeatherProvider, WinchAction, WindEstimate, WindProfile,
 };
-use serde::Deserialize;
+#[cfg(not(target_os = "android"))]
+use mavkit::flasher::{self, BoardInfo, Firmware, FlashProgress};
+#[cfg(not(target_os = "android"))]
+use mavkit::sik::{self, SikReport, SikSettings, SikTarget};
+#[cfg(not(target_os = "android"))]
+use mavkit::SerialOptions;
+use operations::{OperationFinished, OperationKind, Operations};
+#[cfg(debug_assertions)]
+use recorder::EventRecorder;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
-use tauri::Emitter;
+use tauri::{AppHandle, Emitter, Manager};
+use telemetry_stream::{TelemetryCoalescer, TelemetryStreamConfig};
+use tokio::sync::broadcast::error::RecvError;
+use weather::OpenMeteoProvider;
 
 static TELEMETRY_INTERVAL_MS: AtomicU64 = AtomicU64::new(200);
 
+/// Airspaces closer than this to the vehicle are reported on `airspace://proximity`.
+const AIRSPACE_ALERT_RADIUS_M: f64 = 1000.0;
+
 struct AppState {
     vehicle: tokio::sync::Mutex<Option<Vehicle>>,
-    connect_abort: tokio::sync::Mutex<Option<tokio::task::AbortHandle>>,
+    /// Cancels the connect attempt in flight, if any.
+    connect_cancel: std::sync::Mutex<Option<CancellationToken>>,
+    /// Held for the whole of a connect attempt; a new attempt waits on it so
+    /// the previous one has closed its socket before the address is bound again.
+    connect_lock: tokio::sync::Mutex<()>,
+    /// Request of the connect attempt in flight, if any.
+    pending_request: std::sync::Mutex<Option<ConnectRequest>>,
+    /// Request that opened `vehicle`; stale once `vehicle` is taken.
+    link_request: std::sync::Mutex<Option<ConnectRequest>>,
+    units: std::sync::Mutex<Units>,
+    telemetry_stream: std::sync::Mutex<TelemetryStreamConfig>,
+    telemetry_resync: AtomicBool,
+    bridges: std::sync::Mutex<BridgeSet>,
+    /// Cancels the firmware flash in progress, if any.
+    flash_cancel: std::sync::Mutex<Option<CancellationToken>>,
+    /// Long-running commands, cancelled through `operation_cancel`.
+    operations: Operations,
+    /// Radio calibration being recorded, if any, with its operation id.
+    rc_calibration: std::sync::Mutex<Option<(u64, RcCalibrationSession)>>,
+    /// Loaded airspace dataset, checked against plans and the live position.
+    airspace: std::sync::Mutex<Option<Arc<AirspaceSet>>>,
+    /// Operator-entered wind; Open-Meteo is used when unset.
+    fixed_wind: std::sync::Mutex<Option<FixedWind>>,
+    open_meteo: OpenMeteoProvider,
+    /// Pose stream to an external 3D viewer, if any.
+    pose_output: std::sync::Mutex<Option<PoseOutput>>,
+    /// Set once the operator confirmed closing the app despite a shutdown warning.
+    exit_confirmed: AtomicBool,
+    /// `VehicleConfig` settings from the command line, for every connection.
+    cli_overrides: ConnectOverrides,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, PartialEq)]
 struct ConnectRequest {
     endpoint: LinkEndpoint,
+    /// GCS ids and other `VehicleConfig` settings for this connection, on
+    /// top of those given on the command line.
+    #[serde(flatten)]
+    overrides: ConnectOverrides,
+    /// STATUSTEXT sent to the vehicle when the link is closed.
+    #[serde(default)]
+    shutdown_notice: Option<String>,
+    /// Operator ID and self-ID for the Remote ID broadcast.
+    #[serde(default)]
+    remote_id: Option<RemoteIdOperator>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, PartialEq)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 enum LinkEndpoint {
     Udp { bind_addr: String },
+    /// Outgoing TCP to `addr`, encrypted when `tls` is given.
+    Tcp {
+        addr: String,
+        #[serde(default)]
+        tls: Option<TlsOptions>,
+    },
     #[cfg(not(target_os = "android"))]
-    Serial { port: String, baud: u32 },
+    Serial {
+        port: String,
+        baud: u32,
+        /// Line settings; omitted opens the port 8N1 through mavlink itself.
+        #[serde(default)]
+        options: Option<SerialOptions>,
+    },
+}
+
+/// Returned by `connect_link`.
+#[derive(Serialize)]
+struct SessionInfo {
+    /// The link was already open with the same request and was kept.
+    reused: bool,
+    vehicle: VehicleState,
 }
 
 // ---------------------------------------------------------------------------
 // Connection commands
 // ---------------------------------------------------------------------------
 
+/// Open a link, replacing the current one. Repeating the request of the
+/// open link keeps it, and repeating the one being connected waits for that
+/// attempt instead of restarting it.
 #[tauri::command]
 async fn connect_link(
     state: tauri::State<'_, AppState>,
     app: tauri::AppHandle,
     request: ConnectRequest,
-) -> Result<(), String> {
-    // Abort any in-flight connect attempt so its socket is released
-    if let Some(handle) = state.connect_abort.lock().await.take() {
-        handle.abort();
+) -> Result<SessionInfo, String> {
+    if let Some(session) = existing_session(&state, &request).await {
+        return Ok(session);
+    }
+    // Cancel any other connect attempt in flight, then wait until its socket
+    // is released
+    let same_pending = state.pending_request.lock().unwrap().as_ref() == Some(&request);
+    if !same_pending {
+        if let Some(cancel) = state.connect_cancel.lock().unwrap().take() {
+            cancel.cancel();
+        }
+    }
+    let _attempt = state.connect_lock.lock().await;
+    if let Some(session) = existing_session(&state, &request).await {
+        return Ok(session);
+    }
+    *state.pending_request.lock().unwrap() = Some(request.clone());
+    let result = open_link(&state, &app, &request).await;
+    state.pending_request.lock().unwrap().take();
+    let vehicle = result?;
+    *state.link_request.lock().unwrap() = Some(request);
+    Ok(SessionInfo {
+        reused: false,
+        vehicle,
+    })
+}
+
+/// The open link, if it was opened with `request` and is still connected.
+async fn existing_session(state: &AppState, request: &ConnectRequest) -> Option<SessionInfo> {
+    if state.link_request.lock().unwrap().as_ref() != Some(request) {
+        return None;
+    }
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref()?;
+    if *vehicle.link_state().borrow() != LinkState::Connected {
+        return None;
     }
+    let vehicle = vehicle.state().borrow().clone();
+    Some(SessionInfo {
+        reused: true,
+        vehicle,
+    })
+}
 
-    // Disconnect any existing vehicle
+/// Close the current vehicle and connect according to `request`.
+async fn open_link(
+    state: &AppState,
+    app: &tauri::AppHandle,
+    request: &ConnectRequest,
+) -> Result<VehicleState, String> {
+    // Disconnect any existing vehicle and stop its event bridges
+    state.bridges.lock().unwrap().abort_all();
+    state.operations.cancel_all();
+    state.pose_output.lock().unwrap().take();
     {
         let prev = state.vehicle.lock().await.take();
         if let Some(v) = prev {
@@ -52,43 +207,110 @@ async fn connect_link(
         }
     }
 
-    let address = match &request.endpoint {
-        LinkEndpoint::Udp { bind_addr } => format!("udpin:{bind_addr}"),
+    let link_pacing = match &request.endpoint {
+        LinkEndpoint::Udp { .. } | LinkEndpoint::Tcp { .. } => LinkPacing::UNLIMITED,
         #[cfg(not(target_os = "android"))]
-        LinkEndpoint::Serial { port, baud } => format!("serial:{port}:{baud}"),
+        LinkEndpoint::Serial { baud, .. } => LinkPacing::serial(*baud),
     };
+    let mut config = VehicleConfig {
+        link_pacing,
+        gcs_heartbeat_interval: Some(Duration::from_secs(1)),
+        timesync_interval: Some(Duration::from_secs(5)),
+        shutdown_notice: request.shutdown_notice.clone(),
+        remote_id: request.remote_id.clone().unwrap_or_default(),
+        flight_log_dir: app.path().app_data_dir().ok().map(|dir| dir.join("flight_log")),
+        mode_overrides: load_mode_overrides(app)?,
+        telemetry_smoothing: Some(TelemetrySmoothing::default()),
+        ..VehicleConfig::default()
+    };
+    request
+        .overrides
+        .or(&state.cli_overrides)
+        .apply(&mut config);
 
-    // Spawn as abortable task so cancel/reconnect can kill it
-    let task = tokio::spawn(async move { Vehicle::connect(&address).await });
-    *state.connect_abort.lock().await = Some(task.abort_handle());
-
-    let vehicle = task
-        .await
-        .map_err(|e| {
-            if e.is_cancelled() {
-                "connection cancelled".to_string()
-            } else {
-                e.to_string()
-            }
-        })?
-        .map_err(|e| e.to_string())?;
+    let cancel = CancellationToken::new();
+    *state.connect_cancel.lock().unwrap() = Some(cancel.clone());
+    let result = match &request.endpoint {
+        LinkEndpoint::Udp { bind_addr } => {
+            Vehicle::connect_with_cancel(&format!("udpin:{bind_addr}"), config, &cancel).await
+        }
+        LinkEndpoint::Tcp { addr, tls: None } => {
+            Vehicle::connect_with_cancel(&format!("tcpout:{addr}"), config, &cancel).await
+        }
+        LinkEndpoint::Tcp {
+            addr,
+            tls: Some(tls),
+        } => Vehicle::connect_tls(addr, tls, config, &cancel).await,
+        #[cfg(not(target_os = "android"))]
+        LinkEndpoint::Serial {
+            port,
+            baud,
+            options: Some(options),
+        } => Vehicle::connect_serial_with_options(port, *baud, options, config, &cancel).await,
+        #[cfg(not(target_os = "android"))]
+        LinkEndpoint::Serial {
+            port,
+            baud,
+            options: None,
+        } => Vehicle::connect_with_cancel(&format!("serial:{port}:{baud}"), config, &cancel).await,
+    };
+    state.connect_cancel.lock().unwrap().take();
 
-    // Clear abort handle now that connect completed
-    *state.connect_abort.lock().await = None;
+    let vehicle = result.map_err(|e| match e {
+        VehicleError::Cancelled => "connection cancelled".to_string(),
+        e => e.to_string(),
+    })?;
 
-    spawn_event_bridges(&app, &vehicle);
+    *state.bridges.lock().unwrap() = spawn_event_bridges(app, &vehicle);
 
+    let vehicle_state = vehicle.state().borrow().clone();
     *state.vehicle.lock().await = Some(vehicle);
-    Ok(())
+    Ok(vehicle_state)
+}
+
+/// The custom mode table in `modes.json` of the app config directory, if the
+/// user put one there for firmware with renamed or added modes.
+fn load_mode_overrides(app: &tauri::AppHandle) -> Result<ModeOverrides, String> {
+    let Ok(dir) = app.path().app_config_dir() else {
+        return Ok(ModeOverrides::default());
+    };
+    let path = dir.join("modes.json");
+    if !path.exists() {
+        return Ok(ModeOverrides::default());
+    }
+    ModeOverrides::load(&path).map_err(|e| format!("{}: {e}", path.display()))
 }
 
+/// Why disconnecting now could trigger the vehicle's GCS failsafe, if it could.
 #[tauri::command]
-async fn disconnect_link(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    // Abort any in-flight connect attempt
-    if let Some(handle) = state.connect_abort.lock().await.take() {
-        handle.abort();
+async fn vehicle_shutdown_warning(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<ShutdownWarning>, String> {
+    let guard = state.vehicle.lock().await;
+    Ok(guard.as_ref().and_then(Vehicle::shutdown_warning))
+}
+
+/// Refused while `vehicle_shutdown_warning` has a warning, unless `confirmed`.
+#[tauri::command]
+async fn disconnect_link(
+    state: tauri::State<'_, AppState>,
+    confirmed: Option<bool>,
+) -> Result<(), String> {
+    // Cancel any in-flight connect attempt
+    if let Some(cancel) = state.connect_cancel.lock().unwrap().take() {
+        cancel.cancel();
+    }
+
+    if !confirmed.unwrap_or(false) {
+        let guard = state.vehicle.lock().await;
+        if let Some(warning) = guard.as_ref().and_then(Vehicle::shutdown_warning) {
+            return Err(format!("shutdown blocked: {}", warning.message));
+        }
     }
 
+    state.bridges.lock().unwrap().abort_all();
+    state.operations.cancel_all();
+    state.pose_output.lock().unwrap().take();
     let vehicle = state.vehicle.lock().await.take();
     if let Some(v) = vehicle {
         v.disconnect().await.map_err(|e| e.to_string())?;
@@ -96,6 +318,149 @@ async fn disconnect_link(state: tauri::State<'_, AppState>) -> Result<(), String
     Ok(())
 }
 
+/// Close the app after the operator confirmed a `app://close_blocked` warning.
+#[tauri::command]
+async fn app_confirm_exit(app: AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    state.exit_confirmed.store(true, Ordering::Relaxed);
+    close_and_exit(app.clone()).await;
+    Ok(())
+}
+
+/// Window close: disconnect first so the vehicle gets the shutdown notice,
+/// and hold the close back (emitting `app://close_blocked`) while the
+/// vehicle could fail-safe on losing us.
+fn on_close_requested(window: &tauri::Window, api: &tauri::CloseRequestApi) {
+    let app = window.app_handle().clone();
+    let state = app.state::<AppState>();
+    let Ok(guard) = state.vehicle.try_lock() else {
+        return;
+    };
+    let Some(vehicle) = guard.as_ref() else {
+        return;
+    };
+    api.prevent_close();
+    if !state.exit_confirmed.load(Ordering::Relaxed) {
+        if let Some(warning) = vehicle.shutdown_warning() {
+            emit(&app, "app://close_blocked", &warning);
+            return;
+        }
+    }
+    drop(guard);
+    tauri::async_runtime::spawn(close_and_exit(app));
+}
+
+async fn close_and_exit(app: AppHandle) {
+    let state = app.state::<AppState>();
+    state.bridges.lock().unwrap().abort_all();
+    let vehicle = state.vehicle.lock().await.take();
+    if let Some(vehicle) = vehicle {
+        let _ = vehicle.disconnect().await;
+    }
+    app.exit(0);
+}
+
+/// Event bridges of the current vehicle; empty when disconnected.
+#[tauri::command]
+fn bridge_health(state: tauri::State<'_, AppState>) -> Vec<BridgeHealth> {
+    state.bridges.lock().unwrap().health()
+}
+
+// ---------------------------------------------------------------------------
+// Firmware
+// ---------------------------------------------------------------------------
+
+/// Flash the `.apj`/`.px4` file at `path` through the bootloader on `port`.
+/// With `reboot_vehicle`, the connected vehicle is first told to restart into
+/// its bootloader and disconnected, freeing the port.
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+async fn firmware_flash(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+    port: String,
+    reboot_vehicle: bool,
+) -> Result<BoardInfo, String> {
+    let contents = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let firmware = Firmware::parse(&contents).map_err(|e| e.to_string())?;
+
+    if reboot_vehicle {
+        state.bridges.lock().unwrap().abort_all();
+        let vehicle = state.vehicle.lock().await.take();
+        if let Some(v) = vehicle {
+            let _ = v.reboot_to_bootloader().await;
+            let _ = v.disconnect().await;
+        }
+    }
+
+    let cancel = CancellationToken::new();
+    if let Some(previous) = state.flash_cancel.lock().unwrap().replace(cancel.clone()) {
+        previous.cancel();
+    }
+    let (progress_tx, mut rx) = tokio::sync::watch::channel(FlashProgress::default());
+    let forwarder = tokio::spawn(async move {
+        while rx.changed().await.is_ok() {
+            let progress = rx.borrow_and_update().clone();
+            emit(&app, "firmware://progress", &progress);
+        }
+    });
+
+    let result = flasher::flash(&port, firmware, progress_tx, &cancel).await;
+    // The sender is gone, so the forwarder exits after the final update
+    let _ = forwarder.await;
+    state.flash_cancel.lock().unwrap().take();
+    result.map_err(|e| match e {
+        VehicleError::Cancelled => "flash cancelled".to_string(),
+        e => e.to_string(),
+    })
+}
+
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+fn firmware_flash_cancel(state: tauri::State<'_, AppState>) {
+    if let Some(cancel) = state.flash_cancel.lock().unwrap().take() {
+        cancel.cancel();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// SiK radio
+// ---------------------------------------------------------------------------
+
+/// Read the SiK radio on `port`, or with `remote` the one it links to. The
+/// port must not be in use by the vehicle connection.
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+async fn radio_read(port: String, baud: u32, remote: bool) -> Result<SikReport, String> {
+    sik::read_radio(&port, baud, sik_target(remote))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Write, save and apply `settings`; write the remote radio before the local one.
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+async fn radio_write(
+    port: String,
+    baud: u32,
+    remote: bool,
+    settings: SikSettings,
+) -> Result<SikReport, String> {
+    sik::write_radio(&port, baud, sik_target(remote), settings)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "android"))]
+fn sik_target(remote: bool) -> SikTarget {
+    if remote {
+        SikTarget::Remote
+    } else {
+        SikTarget::Local
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Pure commands (no connection needed)
 // ---------------------------------------------------------------------------
@@ -112,6 +477,265 @@ fn mission_validate_plan(plan: MissionPlan) -> Vec<MissionIssue> {
     validate_plan(&plan)
 }
 
+#[tauri::command]
+fn mission_audit_coordinates(coords: Vec<(f64, f64)>) -> Vec<MissionIssue> {
+    audit_imported_coordinates(&coords)
+}
+
+#[tauri::command]
+fn mission_generate_structure_scan(params: StructureScanParams) -> Result<MissionPlan, String> {
+    generate_structure_scan(&params)
+}
+
+#[tauri::command]
+fn mission_generate_search_pattern(
+    params: SearchPatternParams,
+    profile: VehicleProfile,
+) -> Result<MissionPlan, String> {
+    generate_search_pattern(&params, &profile)
+}
+
+#[tauri::command]
+fn mission_wrap_vtol_block(plan: MissionPlan, params: VtolWrapParams) -> MissionPlan {
+    wrap_vtol_block(&plan, &params)
+}
+
+#[tauri::command]
+fn mission_simplify(plan: MissionPlan, tolerance_m: f64) -> Simplified {
+    simplify(&plan, tolerance_m)
+}
+
+#[tauri::command]
+fn mission_snap_to_grid(plan: MissionPlan, spacing_m: f64) -> MissionPlan {
+    snap_to_grid(&plan, spacing_m)
+}
+
+#[tauri::command]
+fn mission_snap_altitudes(plan: MissionPlan, step_m: f32) -> MissionPlan {
+    snap_altitudes(&plan, step_m)
+}
+
+#[tauri::command]
+fn mission_seal_plan(
+    plan: MissionPlan,
+    signing_key: String,
+    recipients: Vec<String>,
+) -> Result<String, String> {
+    let key = parse_signing_key(&signing_key).map_err(|e| e.to_string())?;
+    let recipients = recipients
+        .iter()
+        .map(|r| r.trim().parse::<PlanRecipient>().map_err(|e| format!("recipient {r}: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    seal_plan(&plan, &key, &recipients).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn mission_open_sealed_plan(
+    contents: String,
+    trusted_keys: Vec<String>,
+    identity: Option<String>,
+) -> Result<MissionPlan, String> {
+    let keyring = StaticKeyring {
+        trusted: trusted_keys
+            .iter()
+            .map(|key| parse_verifying_key(key))
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?,
+        identity: identity
+            .map(|id| id.trim().parse::<PlanIdentity>())
+            .transpose()
+            .map_err(|e| format!("identity: {e}"))?,
+    };
+    open_plan(&contents, &keyring).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn mission_validate_vtol(plan: MissionPlan, profile: VtolProfile) -> Vec<MissionIssue> {
+    validate_vtol_transitions(&plan, &profile)
+}
+
+#[tauri::command]
+fn mission_sun_position(lat: f64, lon: f64, unix_s: i64) -> SunPosition {
+    sun_position(lat, lon, unix_s)
+}
+
+#[tauri::command]
+fn mission_sun_times(lat: f64, lon: f64, unix_s: i64) -> SunTimes {
+    sun_times(lat, lon, unix_s)
+}
+
+#[tauri::command]
+fn mission_sun_warnings(lat: f64, lon: f64, start_unix_s: i64, duration_s: i64) -> Vec<MissionIssue> {
+    sun_warnings(lat, lon, start_unix_s, duration_s)
+}
+
+#[tauri::command]
+fn mission_validate_fence(plan: MissionPlan, fence: MissionPlan) -> Vec<MissionIssue> {
+    validate_against_fence(&plan, &fence)
+}
+
+/// Check `plan` against ArduPilot's acceptance rules for the connected
+/// vehicle, or a generic profile when offline. Other autopilots are skipped.
+#[tauri::command]
+async fn mission_validate_ardupilot(
+    state: tauri::State<'_, AppState>,
+    plan: MissionPlan,
+) -> Result<Vec<MissionIssue>, String> {
+    let guard = state.vehicle.lock().await;
+    let profile = match guard.as_ref() {
+        Some(vehicle) => {
+            let (autopilot, vehicle_type) = {
+                let state = vehicle.state().borrow();
+                (state.autopilot, state.vehicle_type)
+            };
+            if autopilot != AutopilotType::ArduPilotMega {
+                return Ok(Vec::new());
+            }
+            ArduPilotProfile::from_params(&vehicle.param_store().borrow(), vehicle_type)
+        }
+        None => ArduPilotProfile::default(),
+    };
+    Ok(validate_ardupilot_acceptance(&plan, &profile))
+}
+
+#[tauri::command]
+fn airspace_load(state: tauri::State<'_, AppState>, path: String) -> Result<AirspaceSet, String> {
+    let contents = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let set = AirspaceSet::parse(&contents).map_err(|e| e.to_string())?;
+    *state.airspace.lock().unwrap() = Some(Arc::new(set.clone()));
+    Ok(set)
+}
+
+#[tauri::command]
+fn airspace_clear(state: tauri::State<'_, AppState>) {
+    state.airspace.lock().unwrap().take();
+}
+
+#[tauri::command]
+fn airspace_check_plan(state: tauri::State<'_, AppState>, plan: MissionPlan) -> Vec<MissionIssue> {
+    let set = state.airspace.lock().unwrap().clone();
+    set.map(|set| set.plan_warnings(&plan)).unwrap_or_default()
+}
+
+#[tauri::command]
+fn airspace_nearby(
+    state: tauri::State<'_, AppState>,
+    lat: f64,
+    lon: f64,
+    radius_m: f64,
+) -> Vec<AirspaceProximity> {
+    let set = state.airspace.lock().unwrap().clone();
+    set.map(|set| set.nearby(lat, lon, radius_m)).unwrap_or_default()
+}
+
+async fn wind_profile_at(state: &AppState, lat: f64, lon: f64) -> Result<WindProfile, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default();
+    let fixed = *state.fixed_wind.lock().unwrap();
+    let provider: &dyn WeatherProvider = match &fixed {
+        Some(wind) => wind,
+        None => &state.open_meteo,
+    };
+    provider
+        .wind_profile(lat, lon, now)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn weather_set_fixed_wind(state: tauri::State<'_, AppState>, wind: Option<FixedWind>) {
+    *state.fixed_wind.lock().unwrap() = wind;
+}
+
+#[tauri::command]
+async fn weather_wind_profile(
+    state: tauri::State<'_, AppState>,
+    lat: f64,
+    lon: f64,
+) -> Result<WindProfile, String> {
+    wind_profile_at(&state, lat, lon).await
+}
+
+#[tauri::command]
+async fn weather_route_estimate(
+    state: tauri::State<'_, AppState>,
+    plan: MissionPlan,
+    airspeed_mps: f64,
+) -> Result<WindEstimate, String> {
+    let (lat, lon) = plan_location(&plan).ok_or("plan has no position")?;
+    let profile = wind_profile_at(&state, lat, lon).await?;
+    Ok(wind_adjusted_estimate(&plan, &profile, airspeed_mps))
+}
+
+/// Simulate the flight, through the current wind when `with_wind` is set.
+#[tauri::command]
+async fn mission_simulate(
+    state: tauri::State<'_, AppState>,
+    plan: MissionPlan,
+    profile: VehicleProfile,
+    with_wind: bool,
+) -> Result<SimTimeline, String> {
+    let wind = match plan_location(&plan) {
+        Some((lat, lon)) if with_wind => Some(wind_profile_at(&state, lat, lon).await?),
+        _ => None,
+    };
+    Ok(simulate(&plan, &profile, wind.as_ref()))
+}
+
+/// Separation conflicts between several vehicles' plans, flown through the
+/// wind at the first plan when `with_wind` is set.
+#[tauri::command]
+async fn mission_check_conflicts(
+    state: tauri::State<'_, AppState>,
+    flights: Vec<PlannedFlight>,
+    separation: Separation,
+    with_wind: bool,
+) -> Result<Vec<Conflict>, String> {
+    let location = flights.first().and_then(|flight| plan_location(&flight.plan));
+    let wind = match location {
+        Some((lat, lon)) if with_wind => Some(wind_profile_at(&state, lat, lon).await?),
+        _ => None,
+    };
+    Ok(detect_conflicts(&flights, &separation, wind.as_ref()))
+}
+
+/// Where to look up the weather for a plan: home, else its first position.
+fn plan_location(plan: &MissionPlan) -> Option<(f64, f64)> {
+    plan.home
+        .as_ref()
+        .map(|home| (home.latitude_deg, home.longitude_deg))
+        .or_else(|| {
+            plan.items
+                .iter()
+                .find(|item| item.x != 0 || item.y != 0)
+                .map(|item| item.latlon_deg())
+        })
+}
+
+#[tauri::command]
+fn plan_history_snapshot(
+    project_dir: String,
+    plan: MissionPlan,
+    label: Option<String>,
+) -> Result<Option<PlanSnapshot>, String> {
+    let mut history = PlanHistory::open(&project_dir).map_err(|e| e.to_string())?;
+    history.snapshot(&plan, label).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn plan_history_list(project_dir: String) -> Result<Vec<PlanSnapshot>, String> {
+    let history = PlanHistory::open(&project_dir).map_err(|e| e.to_string())?;
+    Ok(history.list().to_vec())
+}
+
+#[tauri::command]
+fn plan_history_restore(project_dir: String, id: u64) -> Result<MissionPlan, String> {
+    let history = PlanHistory::open(&project_dir).map_err(|e| e.to_string())?;
+    history.restore(id).map_err(|e| e.to_string())
+}
+
 // ---------------------------------------------------------------------------
 // Vehicle commands
 // ---------------------------------------------------------------------------
@@ -134,10 +758,16 @@ async fn disarm_vehicle(state: tauri::State<'_, AppState>, force: bool) -> Resul
 async fn set_flight_mode(
     state: tauri::State<'_, AppState>,
     custom_mode: u32,
+    unchecked: bool,
 ) -> Result<(), String> {
     let guard = state.vehicle.lock().await;
     let vehicle = guard.as_ref().ok_or("not connected")?;
-    vehicle.set_mode(custom_mode).await.map_err(|e| e.to_string())
+    let result = if unchecked {
+        vehicle.set_mode_unchecked(custom_mode).await
+    } else {
+        vehicle.set_mode(custom_mode).await
+    };
+    result.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -150,6 +780,23 @@ async fn vehicle_takeoff(
     vehicle.takeoff(altitude_m).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn vehicle_land_here(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.land_here().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_abort_landing(
+    state: tauri::State<'_, AppState>,
+    climb_to_m: f32,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.abort_landing(climb_to_m).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn vehicle_guided_goto(
     state: tauri::State<'_, AppState>,
@@ -163,53 +810,816 @@ async fn vehicle_guided_goto(
 }
 
 #[tauri::command]
-async fn get_available_modes(
+async fn vehicle_propose_goto(
     state: tauri::State<'_, AppState>,
-) -> Result<Vec<FlightMode>, String> {
+    lat_deg: f64,
+    lon_deg: f64,
+    alt_m: Option<f32>,
+) -> Result<GotoProposal, String> {
     let guard = state.vehicle.lock().await;
     let vehicle = guard.as_ref().ok_or("not connected")?;
-    Ok(vehicle.available_modes())
+    Ok(vehicle.propose_goto(lat_deg, lon_deg, alt_m))
 }
 
-// ---------------------------------------------------------------------------
-// Settings commands
-// ---------------------------------------------------------------------------
+#[tauri::command]
+async fn vehicle_confirm_goto(state: tauri::State<'_, AppState>, token: u64) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.confirm_goto(token).await.map_err(|e| e.to_string())
+}
 
 #[tauri::command]
-fn set_telemetry_rate(rate_hz: u32) -> Result<(), String> {
-    if rate_hz == 0 || rate_hz > 20 {
-        return Err("rate_hz must be between 1 and 20".into());
-    }
-    TELEMETRY_INTERVAL_MS.store(1000 / rate_hz as u64, Ordering::Relaxed);
-    Ok(())
+async fn vehicle_nudge(
+    state: tauri::State<'_, AppState>,
+    direction: NudgeDirection,
+    distance_m: f64,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .nudge(direction, distance_m)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_change_altitude(
+    state: tauri::State<'_, AppState>,
+    delta_m: f32,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .change_altitude(delta_m)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_update_gcs_position(
+    state: tauri::State<'_, AppState>,
+    lat_deg: f64,
+    lon_deg: f64,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.update_gcs_position(lat_deg, lon_deg);
+    Ok(())
+}
+
+#[tauri::command]
+async fn vehicle_start_return_to_me(
+    state: tauri::State<'_, AppState>,
+    options: ReturnToMeOptions,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .start_return_to_me(options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Stream the vehicle pose to an external 3D viewer, replacing any stream
+/// already running.
+#[tauri::command]
+async fn pose_output_start(
+    state: tauri::State<'_, AppState>,
+    options: PoseOutputOptions,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    let output = PoseOutput::start(vehicle, &options)
+        .await
+        .map_err(|e| e.to_string())?;
+    *state.pose_output.lock().unwrap() = Some(output);
+    Ok(())
+}
+
+#[tauri::command]
+fn pose_output_stop(state: tauri::State<'_, AppState>) {
+    state.pose_output.lock().unwrap().take();
+}
+
+#[tauri::command]
+async fn vehicle_stop_return_to_me(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.stop_return_to_me();
+    Ok(())
+}
+
+#[tauri::command]
+async fn vehicle_orbit(
+    state: tauri::State<'_, AppState>,
+    center_lat: f64,
+    center_lon: f64,
+    radius_m: f32,
+    speed_mps: f32,
+    direction: OrbitDirection,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .orbit(center_lat, center_lon, radius_m, speed_mps, direction)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_stop_orbit(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.stop_orbit().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_request_battery_info(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.request_battery_info().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_update_config(
+    state: tauri::State<'_, AppState>,
+    patch: ConfigPatch,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.update_config(patch).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_set_gcs_identity(
+    state: tauri::State<'_, AppState>,
+    system_id: u8,
+    component_id: u8,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .set_gcs_identity(system_id, component_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_request_control(
+    state: tauri::State<'_, AppState>,
+    force: bool,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.request_control(force).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_release_control(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.release_control().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_set_control_override(
+    state: tauri::State<'_, AppState>,
+    enabled: bool,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .set_control_override(enabled)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vehicle_audit_log(state: tauri::State<'_, AppState>) -> Result<Vec<AuditEntry>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    Ok(vehicle.audit_log())
+}
+
+/// Write this session's command audit trail to `path` as JSON, alongside the
+/// flight log.
+#[tauri::command]
+async fn vehicle_export_audit_log(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    let json = serde_json::to_string_pretty(&vehicle.audit_log()).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn sitl_is_available(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    let store = vehicle.param_store().borrow().clone();
+    Ok(is_sitl(&store))
+}
+
+#[tauri::command]
+async fn sitl_apply(state: tauri::State<'_, AppState>, action: SimAction) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.sitl().apply(&action).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn setup_airframe_presets(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<AirframePreset>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    Ok(vehicle.setup().airframe_presets())
+}
+
+#[tauri::command]
+async fn setup_frame(state: tauri::State<'_, AppState>) -> Result<FrameParams, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    Ok(vehicle.setup().frame())
+}
+
+#[tauri::command]
+async fn setup_apply_airframe(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<Vec<Param>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .setup()
+        .apply_airframe(&id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn setup_set_frame(
+    state: tauri::State<'_, AppState>,
+    frame_class: u8,
+    frame_type: Option<u8>,
+) -> Result<Vec<Param>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .setup()
+        .set_frame(frame_class, frame_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn setup_failsafe(state: tauri::State<'_, AppState>) -> Result<FailsafeConfig, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    Ok(vehicle.setup().failsafe())
+}
+
+#[tauri::command]
+async fn setup_failsafe_options(
+    state: tauri::State<'_, AppState>,
+) -> Result<FailsafeOptions, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    Ok(vehicle.setup().failsafe_options())
+}
+
+#[tauri::command]
+async fn setup_set_failsafe(
+    state: tauri::State<'_, AppState>,
+    config: FailsafeConfig,
+) -> Result<Vec<Param>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .setup()
+        .set_failsafe(&config)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn setup_sensors(state: tauri::State<'_, AppState>) -> Result<SensorSetup, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    Ok(vehicle.setup().sensors())
+}
+
+#[tauri::command]
+fn setup_sensor_rotations() -> Vec<SensorRotation> {
+    sensor_rotations()
+}
+
+#[tauri::command]
+async fn setup_set_compass_priority(
+    state: tauri::State<'_, AppState>,
+    dev_ids: Vec<u32>,
+) -> Result<Vec<Param>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .setup()
+        .set_compass_priority(&dev_ids)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn setup_set_compass_orientation(
+    state: tauri::State<'_, AppState>,
+    slot: u8,
+    rotation: u8,
+) -> Result<Param, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .setup()
+        .set_compass_orientation(slot, rotation)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn setup_set_board_orientation(
+    state: tauri::State<'_, AppState>,
+    rotation: u8,
+) -> Result<Param, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .setup()
+        .set_board_orientation(rotation)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn setup_fixed_yaw_mag_cal(
+    state: tauri::State<'_, AppState>,
+    yaw_deg: f32,
+    compass_mask: u8,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .setup()
+        .fixed_yaw_mag_cal(yaw_deg, compass_mask)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Start recording RC channel ranges as an operation that lasts until
+/// `rc_calibration_finish` or `operation_cancel`; progress is emitted as
+/// `rc://calibration`. Replaces any calibration already running.
+#[tauri::command]
+async fn rc_calibration_start(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<u64, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    state.operations.cancel_kind(OperationKind::RcCalibration);
+    let session = vehicle.setup().start_rc_calibration();
+    let mut rx = session.progress();
+    let handle = app.clone();
+    let id = spawn_operation(
+        &app,
+        OperationKind::RcCalibration,
+        move |id, cancel| async move {
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => {
+                        drop_rc_calibration(&handle, id);
+                        return Err(VehicleError::Cancelled);
+                    }
+                    changed = rx.changed() => {
+                        // The session was finished or dropped
+                        if changed.is_err() {
+                            return Ok(());
+                        }
+                        let progress = rx.borrow_and_update().clone();
+                        emit(&handle, "rc://calibration", &progress);
+                    }
+                }
+            }
+        },
+    );
+    state.rc_calibration.lock().unwrap().replace((id, session));
+    Ok(id)
+}
+
+fn drop_rc_calibration(app: &AppHandle, id: u64) {
+    let state = app.state::<AppState>();
+    let mut current = state.rc_calibration.lock().unwrap();
+    if current.as_ref().is_some_and(|(running, _)| *running == id) {
+        current.take();
+    }
+}
+
+#[tauri::command]
+fn rc_calibration_capture_trims(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let guard = state.rc_calibration.lock().unwrap();
+    let (_, session) = guard.as_ref().ok_or("no RC calibration in progress")?;
+    session.capture_trims();
+    Ok(())
+}
+
+#[tauri::command]
+fn rc_calibration_finish(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RcChannelCalibration>, String> {
+    let (_, session) = state
+        .rc_calibration
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or("no RC calibration in progress")?;
+    session.finish().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn setup_write_rc_calibration(
+    state: tauri::State<'_, AppState>,
+    channels: Vec<RcChannelCalibration>,
+) -> Result<Vec<Param>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .setup()
+        .write_rc_calibration(&channels)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_available_modes(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<FlightMode>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    Ok(vehicle.available_modes())
+}
+
+#[tauri::command]
+async fn vehicle_rtl_preview(
+    state: tauri::State<'_, AppState>,
+    rtl_alt_m: Option<f32>,
+) -> Result<Option<RtlPreview>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    let telemetry = vehicle.telemetry().borrow().clone();
+    let Some(home) = vehicle.home_position().borrow().clone() else {
+        return Ok(None);
+    };
+    let (Some(lat), Some(lon)) = (telemetry.latitude_deg, telemetry.longitude_deg) else {
+        return Ok(None);
+    };
+    let vehicle_type = vehicle.state().borrow().vehicle_type;
+    let alt_m = telemetry.altitude_m.unwrap_or(0.0) as f32;
+    let rtl_alt_m = rtl_alt_m
+        .map(RtlAltitude::Fixed)
+        .or_else(|| rtl_alt_from_params(&vehicle.param_store().borrow(), vehicle_type))
+        .map_or(DEFAULT_RTL_ALT_M, |rtl_alt| rtl_alt.resolve(alt_m));
+    Ok(Some(preview_rtl(lat, lon, alt_m, &home, rtl_alt_m, vehicle_type)))
+}
+
+// ---------------------------------------------------------------------------
+// Raw MAVLink console
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+fn mavlink_message_template(message_name: String) -> Result<String, String> {
+    raw_message_template(&message_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn mavlink_send_raw(
+    state: tauri::State<'_, AppState>,
+    message_name: String,
+    fields_json: String,
+    response_name: Option<String>,
+) -> Result<Option<String>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    match response_name {
+        Some(response_name) => vehicle
+            .send_raw_and_wait(&message_name, &fields_json, &response_name)
+            .await
+            .map(Some)
+            .map_err(|e| e.to_string()),
+        None => vehicle
+            .send_raw(&message_name, &fields_json)
+            .await
+            .map(|_| None)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Measure message rates as an operation whose value is the `RateReport`.
+#[tauri::command]
+async fn mavlink_benchmark_rates(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    options: RateBenchmarkOptions,
+) -> Result<u64, String> {
+    let vehicle = state.vehicle.lock().await.clone().ok_or("not connected")?;
+    Ok(spawn_operation(
+        &app,
+        OperationKind::RateBenchmark,
+        move |_, cancel| async move { vehicle.benchmark_rates(options, &cancel).await },
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Payload commands
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+async fn payload_capabilities(
+    state: tauri::State<'_, AppState>,
+) -> Result<PayloadCapabilities, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    Ok(vehicle.payload().capabilities())
+}
+
+#[tauri::command]
+async fn payload_set_servo(
+    state: tauri::State<'_, AppState>,
+    channel: u8,
+    pwm: u16,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .payload()
+        .set_servo(channel, pwm)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Enable or disable the geofence; enabling returns the FENCE_STATUS that
+/// confirmed it.
+#[tauri::command]
+async fn fence_enable(
+    state: tauri::State<'_, AppState>,
+    enable: bool,
+) -> Result<Option<FenceStatus>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .fence()
+        .enable(enable)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn payload_set_relay(
+    state: tauri::State<'_, AppState>,
+    index: u8,
+    on: bool,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .payload()
+        .set_relay(index, on)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn payload_gripper(
+    state: tauri::State<'_, AppState>,
+    action: GripperAction,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle.payload().gripper(action).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn payload_winch(
+    state: tauri::State<'_, AppState>,
+    action: WinchAction,
+    length_m: f32,
+    rate_mps: f32,
+) -> Result<(), String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .payload()
+        .winch(action, length_m, rate_mps)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Settings commands
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+fn set_telemetry_rate(rate_hz: u32) -> Result<(), String> {
+    if rate_hz == 0 || rate_hz > 20 {
+        return Err("rate_hz must be between 1 and 20".into());
+    }
+    TELEMETRY_INTERVAL_MS.store(1000 / rate_hz as u64, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Switch between full and delta telemetry emission. A snapshot follows on
+/// the next tick.
+#[tauri::command]
+fn telemetry_configure_stream(state: tauri::State<'_, AppState>, config: TelemetryStreamConfig) {
+    *state.telemetry_stream.lock().unwrap() = config;
+    state.telemetry_resync.store(true, Ordering::Relaxed);
+}
+
+/// Request a full `telemetry://snapshot` (delta mode) or `telemetry://tick`
+/// on the next bridge tick, e.g. after a subscriber mounts.
+#[tauri::command]
+fn telemetry_resync(state: tauri::State<'_, AppState>) {
+    state.telemetry_resync.store(true, Ordering::Relaxed);
+}
+
+#[tauri::command]
+fn units_get(state: tauri::State<'_, AppState>) -> Units {
+    *state.units.lock().unwrap()
+}
+
+#[tauri::command]
+fn units_set(state: tauri::State<'_, AppState>, units: Units) {
+    *state.units.lock().unwrap() = units;
+}
+
+/// `lat`/`lon` as text, in `format` or else the operator's preferred one.
+#[tauri::command]
+fn coords_format(
+    state: tauri::State<'_, AppState>,
+    lat: f64,
+    lon: f64,
+    format: Option<CoordinateFormat>,
+) -> Result<String, String> {
+    let format = format.unwrap_or_else(|| state.units.lock().unwrap().coordinates);
+    format_coordinate(lat, lon, format).map_err(|e| e.to_string())
+}
+
+/// `[lat, lon]` from text in any supported format.
+#[tauri::command]
+fn coords_parse(text: String) -> Result<(f64, f64), String> {
+    parse_coordinate(&text).map_err(|e| e.to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Operations
+// ---------------------------------------------------------------------------
+
+/// Run the work `start` returns as an operation of `kind` and return its id
+/// at once; the result is emitted as `operation://finished`.
+fn spawn_operation<T, F>(
+    app: &AppHandle,
+    kind: OperationKind,
+    start: impl FnOnce(u64, CancellationToken) -> F,
+) -> u64
+where
+    T: Serialize + Send + 'static,
+    F: Future<Output = Result<T, VehicleError>> + Send + 'static,
+{
+    let (id, cancel) = app.state::<AppState>().operations.begin(kind);
+    let work = start(id, cancel);
+    let app = app.clone();
+    tokio::spawn(async move {
+        let result = work.await;
+        app.state::<AppState>().operations.end(id);
+        let finished = match result {
+            Ok(value) => OperationFinished {
+                id,
+                kind,
+                value: serde_json::to_value(value).unwrap_or_default(),
+                error: None,
+            },
+            Err(e) => OperationFinished {
+                id,
+                kind,
+                value: serde_json::Value::Null,
+                error: Some(e.to_string()),
+            },
+        };
+        emit(&app, "operation://finished", &finished);
+    });
+    id
+}
+
+/// Cancel a mission transfer, parameter download or calibration by the id
+/// its command returned. Unknown or finished ids are ignored.
+#[tauri::command]
+fn operation_cancel(state: tauri::State<'_, AppState>, id: u64) {
+    state.operations.cancel(id);
 }
 
 // ---------------------------------------------------------------------------
 // Mission commands
 // ---------------------------------------------------------------------------
 
+/// Upload `plan` as an operation; its value is the read-back diff with
+/// `verify`, otherwise null. `enable_fence` enables an uploaded fence.
 #[tauri::command]
 async fn mission_upload_plan(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     plan: MissionPlan,
-) -> Result<(), String> {
-    let guard = state.vehicle.lock().await;
-    let vehicle = guard.as_ref().ok_or("not connected")?;
-    vehicle.mission().upload(plan).await.map_err(|e| e.to_string())
+    allow_inflight_update: Option<bool>,
+    verify: Option<bool>,
+    enable_fence: Option<bool>,
+) -> Result<u64, String> {
+    let vehicle = state.vehicle.lock().await.clone().ok_or("not connected")?;
+    let options = UploadOptions {
+        verify: verify.unwrap_or(false),
+        enable_fence: enable_fence.unwrap_or(false),
+    };
+    Ok(spawn_operation(
+        &app,
+        OperationKind::MissionUpload,
+        move |_, cancel| async move {
+            vehicle
+                .mission()
+                .allow_inflight_update(allow_inflight_update.unwrap_or(false))
+                .cancel_on(&cancel)
+                .upload_with(plan, options)
+                .await
+        },
+    ))
+}
+
+/// Upload mission, fence and rally as one operation whose value is the
+/// `SyncReport`; progress is emitted as `mission.sync_progress`.
+#[tauri::command]
+async fn mission_upload_all(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    mission: MissionPlan,
+    fence: Option<MissionPlan>,
+    rally: Option<MissionPlan>,
+) -> Result<u64, String> {
+    let vehicle = state.vehicle.lock().await.clone().ok_or("not connected")?;
+
+    let parts: Vec<(MissionType, u16)> = [Some(&mission), fence.as_ref(), rally.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|plan| (plan.mission_type, wire_item_count(plan)))
+        .collect();
+    let mut rx = vehicle.mission_progress();
+    let handle = app.clone();
+    Ok(spawn_operation(
+        &app,
+        OperationKind::MissionUpload,
+        move |_, cancel| async move {
+            let forwarder = tokio::spawn(async move {
+                while rx.changed().await.is_ok() {
+                    let current: Option<TransferProgress> = rx.borrow().clone();
+                    if let Some(progress) = current.and_then(|c| sync_progress(&parts, &c)) {
+                        emit(&handle, "mission.sync_progress", &progress);
+                    }
+                }
+            });
+
+            let report = vehicle
+                .mission()
+                .cancel_on(&cancel)
+                .upload_all(mission, fence, rally)
+                .await;
+            forwarder.abort();
+            Ok(report)
+        },
+    ))
 }
 
+/// Download a plan as an operation whose value is the `MissionPlan`.
 #[tauri::command]
 async fn mission_download_plan(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     mission_type: MissionType,
-) -> Result<MissionPlan, String> {
-    let guard = state.vehicle.lock().await;
-    let vehicle = guard.as_ref().ok_or("not connected")?;
-    vehicle
-        .mission()
-        .download(mission_type)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<u64, String> {
+    let vehicle = state.vehicle.lock().await.clone().ok_or("not connected")?;
+    Ok(spawn_operation(
+        &app,
+        OperationKind::MissionDownload,
+        move |_, cancel| async move {
+            vehicle
+                .mission()
+                .cancel_on(&cancel)
+                .download(mission_type)
+                .await
+        },
+    ))
 }
 
 #[tauri::command]
@@ -226,16 +1636,59 @@ async fn mission_clear_plan(
         .map_err(|e| e.to_string())
 }
 
+/// Upload and read back `plan` as an operation whose value is whether the
+/// vehicle holds it unchanged.
 #[tauri::command]
 async fn mission_verify_roundtrip(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    plan: MissionPlan,
+) -> Result<u64, String> {
+    let vehicle = state.vehicle.lock().await.clone().ok_or("not connected")?;
+    Ok(spawn_operation(
+        &app,
+        OperationKind::MissionVerify,
+        move |_, cancel| async move {
+            vehicle
+                .mission()
+                .cancel_on(&cancel)
+                .verify_roundtrip(plan)
+                .await
+        },
+    ))
+}
+
+/// Like `mission_verify_roundtrip`, with the `PlanDiff` as the value.
+#[tauri::command]
+async fn mission_verify_roundtrip_detailed(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     plan: MissionPlan,
-) -> Result<bool, String> {
+) -> Result<u64, String> {
+    let vehicle = state.vehicle.lock().await.clone().ok_or("not connected")?;
+    Ok(spawn_operation(
+        &app,
+        OperationKind::MissionVerify,
+        move |_, cancel| async move {
+            vehicle
+                .mission()
+                .cancel_on(&cancel)
+                .verify_roundtrip_detailed(plan)
+                .await
+        },
+    ))
+}
+
+#[tauri::command]
+async fn mission_sync_status(
+    state: tauri::State<'_, AppState>,
+    plan: MissionPlan,
+) -> Result<PlanSyncStatus, String> {
     let guard = state.vehicle.lock().await;
     let vehicle = guard.as_ref().ok_or("not connected")?;
     vehicle
         .mission()
-        .verify_roundtrip(plan)
+        .sync_status(&plan)
         .await
         .map_err(|e| e.to_string())
 }
@@ -255,22 +1708,86 @@ async fn mission_set_current(
 }
 
 #[tauri::command]
-async fn mission_cancel(state: tauri::State<'_, AppState>) -> Result<(), String> {
+async fn mission_resume_from(
+    state: tauri::State<'_, AppState>,
+    seq: u16,
+) -> Result<(), String> {
     let guard = state.vehicle.lock().await;
     let vehicle = guard.as_ref().ok_or("not connected")?;
-    vehicle.mission().cancel_transfer();
-    Ok(())
+    vehicle
+        .mission()
+        .resume_from(seq)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 // ---------------------------------------------------------------------------
 // Parameter commands
 // ---------------------------------------------------------------------------
 
+/// `param://progress` payload; `operation_id` is set for downloads started
+/// by `param_download_all`.
+#[derive(Serialize, Clone)]
+struct ParamProgressEvent {
+    operation_id: Option<u64>,
+    #[serde(flatten)]
+    progress: ParamProgress,
+}
+
+/// Download all parameters as an operation whose value is the number
+/// received. Progress is emitted as `param://progress` tagged with the
+/// operation id. Replaces any download already running.
 #[tauri::command]
-async fn param_download_all(state: tauri::State<'_, AppState>) -> Result<ParamStore, String> {
-    let guard = state.vehicle.lock().await;
-    let vehicle = guard.as_ref().ok_or("not connected")?;
-    vehicle.params().download_all().await.map_err(|e| e.to_string())
+async fn param_download_all(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<u64, String> {
+    let vehicle = state.vehicle.lock().await.clone().ok_or("not connected")?;
+    state.operations.cancel_kind(OperationKind::ParamDownload);
+    let handle = app.clone();
+    Ok(spawn_operation(
+        &app,
+        OperationKind::ParamDownload,
+        move |id, cancel| async move {
+            let emit_progress = |progress: ParamProgress| {
+                let event = ParamProgressEvent {
+                    operation_id: Some(id),
+                    progress,
+                };
+                emit(&handle, "param://progress", &event);
+            };
+            let mut rx = vehicle.param_progress();
+            rx.mark_unchanged();
+            let params = vehicle.params();
+            let download = params.download_all_with_cancel(&cancel);
+            tokio::pin!(download);
+            let result = loop {
+                tokio::select! {
+                    result = &mut download => break result,
+                    Ok(()) = rx.changed() => emit_progress(rx.borrow_and_update().clone()),
+                }
+            };
+            // The final phase is published just before the download returns
+            if rx.has_changed().unwrap_or(false) {
+                emit_progress(rx.borrow_and_update().clone());
+            }
+            result.map(|store| store.params.len())
+        },
+    ))
+}
+
+/// Publish this vehicle's cached parameters right away, then download them
+/// again only if the vehicle reports they changed.
+#[tauri::command]
+async fn param_download_cached(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<ParamStore, String> {
+    // Released during the download so writes aren't held up behind it
+    let vehicle = state.vehicle.lock().await.clone().ok_or("not connected")?;
+    let dir = app.path().app_cache_dir().map_err(|e| e.to_string())?;
+    let cache = ParamCache::open(dir.join("params")).map_err(|e| e.to_string())?;
+    vehicle.params().download_cached(&cache).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -284,6 +1801,40 @@ async fn param_write(
     vehicle.params().write(name, value).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn param_write_transaction(
+    state: tauri::State<'_, AppState>,
+    changes: Vec<ParamChange>,
+) -> Result<Vec<Param>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    vehicle
+        .params()
+        .write_transaction(&changes)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn param_search(
+    state: tauri::State<'_, AppState>,
+    query: String,
+) -> Result<Vec<Param>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    let store = vehicle.param_store();
+    let results = store.borrow().search(&query).into_iter().cloned().collect();
+    Ok(results)
+}
+
+#[tauri::command]
+async fn param_groups(state: tauri::State<'_, AppState>) -> Result<Vec<ParamGroup>, String> {
+    let guard = state.vehicle.lock().await;
+    let vehicle = guard.as_ref().ok_or("not connected")?;
+    let groups = vehicle.param_store().borrow().group_by_prefix();
+    Ok(groups)
+}
+
 #[tauri::command]
 fn param_parse_file(contents: String) -> Result<HashMap<String, f32>, String> {
     parse_param_file(&contents)
@@ -298,22 +1849,45 @@ fn param_format_file(store: ParamStore) -> String {
 // Watch → Tauri event bridges
 // ---------------------------------------------------------------------------
 
-fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
-    // Telemetry — throttled by TELEMETRY_INTERVAL_MS (re-read each loop for live rate changes)
+fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) -> BridgeSet {
+    let mut bridges = BridgeSet::default();
+
+    // Telemetry — throttled by TELEMETRY_INTERVAL_MS (re-read each loop for live rate changes).
+    // In delta mode only changed fields are sent; rate-limited fields held
+    // back on one tick go out on a later one, so deltas are computed every tick.
     {
         let mut rx = vehicle.telemetry();
         let handle = app.clone();
-        tokio::spawn(async move {
+        bridges.spawn("telemetry", async move {
+            let mut coalescer = TelemetryCoalescer::default();
             loop {
                 let ms = TELEMETRY_INTERVAL_MS.load(Ordering::Relaxed);
                 tokio::time::sleep(Duration::from_millis(ms)).await;
-                match rx.has_changed() {
-                    Ok(true) => {
-                        let t: Telemetry = rx.borrow_and_update().clone();
-                        let _ = handle.emit("telemetry://tick", &t);
-                    }
-                    Ok(false) => {}
+                let changed = match rx.has_changed() {
+                    Ok(changed) => changed,
                     Err(_) => break,
+                };
+                let state = handle.state::<AppState>();
+                let config = state.telemetry_stream.lock().unwrap().clone();
+                let resync = state.telemetry_resync.swap(false, Ordering::Relaxed);
+                if !changed && !resync && !config.delta {
+                    continue;
+                }
+
+                let t: Telemetry = rx.borrow_and_update().clone();
+                let now = std::time::Instant::now();
+                if !config.delta {
+                    emit(&handle, "telemetry://tick", &t);
+                } else if resync {
+                    let snapshot = coalescer.snapshot(&t, now);
+                    emit(&handle, "telemetry://snapshot", &snapshot);
+                } else if let Some(delta) = coalescer.delta(&t, &config, now) {
+                    emit(&handle, "telemetry://delta", &delta);
+                }
+                if changed || resync {
+                    let units = *state.units.lock().unwrap();
+                    let display = display_telemetry(&t, &units);
+                    emit(&handle, "telemetry://display", &display);
                 }
             }
         });
@@ -323,10 +1897,210 @@ fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
     {
         let mut rx = vehicle.state();
         let handle = app.clone();
-        tokio::spawn(async move {
+        bridges.spawn("vehicle_state", async move {
             while rx.changed().await.is_ok() {
                 let s: VehicleState = rx.borrow().clone();
-                let _ = handle.emit("vehicle://state", &s);
+                emit(&handle, "vehicle://state", &s);
+            }
+        });
+    }
+
+    // Vehicle identity
+    {
+        let mut rx = vehicle.vehicle_identity();
+        let handle = app.clone();
+        bridges.spawn("vehicle_identity", async move {
+            while rx.changed().await.is_ok() {
+                let identity: Option<VehicleIdentity> = rx.borrow().clone();
+                emit(&handle, "vehicle://identity", &identity);
+            }
+        });
+    }
+
+    // Reboots
+    {
+        let mut rx = vehicle.reboots();
+        let handle = app.clone();
+        bridges.spawn("reboots", async move {
+            while rx.changed().await.is_ok() {
+                let count: u32 = *rx.borrow();
+                emit(&handle, "vehicle://rebooted", &count);
+            }
+        });
+    }
+
+    // BatteryInfo
+    {
+        let mut rx = vehicle.battery_info();
+        let handle = app.clone();
+        bridges.spawn("battery_info", async move {
+            while rx.changed().await.is_ok() {
+                let info: Vec<BatteryInfo> = rx.borrow().clone();
+                emit(&handle, "battery://info", &info);
+            }
+        });
+    }
+
+    // Return-to-me following state
+    {
+        let mut rx = vehicle.return_to_me_state();
+        let handle = app.clone();
+        bridges.spawn("return_to_me", async move {
+            while rx.changed().await.is_ok() {
+                let state: ReturnToMeState = *rx.borrow();
+                emit(&handle, "vehicle://return_to_me", &state);
+            }
+        });
+    }
+
+    // Supervised landing
+    {
+        let mut rx = vehicle.landing_state();
+        let handle = app.clone();
+        bridges.spawn("landing", async move {
+            while rx.changed().await.is_ok() {
+                let state: LandingState = *rx.borrow();
+                emit(&handle, "vehicle://landing", &state);
+            }
+        });
+    }
+
+    // Cell imbalance and sag
+    {
+        let mut rx = vehicle.battery_health();
+        let handle = app.clone();
+        bridges.spawn("battery_health", async move {
+            while rx.changed().await.is_ok() {
+                let health: BatteryHealth = rx.borrow().clone();
+                emit(&handle, "battery://health", &health);
+            }
+        });
+    }
+
+    // Vibration and clipping trend
+    {
+        let mut rx = vehicle.vibration();
+        let handle = app.clone();
+        bridges.spawn("vibration", async move {
+            while rx.changed().await.is_ok() {
+                let vibration: VibrationState = rx.borrow().clone();
+                emit(&handle, "vibration://state", &vibration);
+            }
+        });
+    }
+
+    // EscTelemetry
+    {
+        let mut rx = vehicle.esc_telemetry();
+        let handle = app.clone();
+        bridges.spawn("esc_telemetry", async move {
+            while rx.changed().await.is_ok() {
+                let escs: Vec<EscTelemetry> = rx.borrow().clone();
+                emit(&handle, "esc://telemetry", &escs);
+            }
+        });
+    }
+
+    // Remote ID
+    {
+        let mut rx = vehicle.remote_id();
+        let handle = app.clone();
+        bridges.spawn("remote_id", async move {
+            while rx.changed().await.is_ok() {
+                let remote_id: RemoteIdState = rx.borrow().clone();
+                emit(&handle, "remote_id://state", &remote_id);
+            }
+        });
+    }
+
+    // ADS-B traffic and avoidance advisories
+    {
+        let mut rx = vehicle.traffic();
+        let handle = app.clone();
+        bridges.spawn("traffic", async move {
+            while rx.changed().await.is_ok() {
+                let traffic: Vec<TrafficTarget> = rx.borrow().clone();
+                emit(&handle, "traffic://targets", &traffic);
+            }
+        });
+    }
+    {
+        let mut rx = vehicle.traffic_advisories();
+        let handle = app.clone();
+        bridges.spawn("traffic_advisories", async move {
+            while rx.changed().await.is_ok() {
+                let advisories: Vec<TrafficAdvisory> = rx.borrow().clone();
+                emit(&handle, "traffic://advisories", &advisories);
+            }
+        });
+    }
+
+    // Airspace proximity, re-sent when the set of nearby zones or whether
+    // the vehicle is inside one of them changes.
+    {
+        let mut rx = vehicle.telemetry();
+        let handle = app.clone();
+        bridges.spawn("airspace_proximity", async move {
+            let mut last: Vec<(usize, bool)> = Vec::new();
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                match rx.has_changed() {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(_) => break,
+                }
+                let (lat, lon) = {
+                    let t = rx.borrow_and_update();
+                    match (t.latitude_deg, t.longitude_deg) {
+                        (Some(lat), Some(lon)) => (lat, lon),
+                        _ => continue,
+                    }
+                };
+                let set = handle.state::<AppState>().airspace.lock().unwrap().clone();
+                let nearby = set
+                    .map(|set| set.nearby(lat, lon, AIRSPACE_ALERT_RADIUS_M))
+                    .unwrap_or_default();
+                let key: Vec<(usize, bool)> = nearby.iter().map(|p| (p.index, p.inside)).collect();
+                if key != last {
+                    last = key;
+                    emit(&handle, "airspace://proximity", &nearby);
+                }
+            }
+        });
+    }
+
+    // Raw RC input, unthrottled for radio calibration
+    {
+        let mut rx = vehicle.rc_channels();
+        let handle = app.clone();
+        bridges.spawn("rc_channels", async move {
+            while rx.changed().await.is_ok() {
+                let channels: Vec<u16> = rx.borrow().clone();
+                emit(&handle, "rc://channels", &channels);
+            }
+        });
+    }
+
+    // Other GCS peers
+    {
+        let mut rx = vehicle.gcs_peers();
+        let handle = app.clone();
+        bridges.spawn("gcs_peers", async move {
+            while rx.changed().await.is_ok() {
+                let peers: Vec<GcsPeer> = rx.borrow().clone();
+                emit(&handle, "gcs://peers", &peers);
+            }
+        });
+    }
+
+    // Control ownership
+    {
+        let mut rx = vehicle.control();
+        let handle = app.clone();
+        bridges.spawn("control", async move {
+            while rx.changed().await.is_ok() {
+                let control: ControlState = rx.borrow().clone();
+                emit(&handle, "control://state", &control);
             }
         });
     }
@@ -335,11 +2109,11 @@ fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
     {
         let mut rx = vehicle.home_position();
         let handle = app.clone();
-        tokio::spawn(async move {
+        bridges.spawn("home_position", async move {
             while rx.changed().await.is_ok() {
                 let hp: Option<HomePosition> = rx.borrow().clone();
                 if let Some(hp) = hp {
-                    let _ = handle.emit("home://position", &hp);
+                    emit(&handle, "home://position", &hp);
                 }
             }
         });
@@ -349,10 +2123,49 @@ fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
     {
         let mut rx = vehicle.mission_state();
         let handle = app.clone();
-        tokio::spawn(async move {
+        bridges.spawn("mission_state", async move {
             while rx.changed().await.is_ok() {
                 let ms = rx.borrow().clone();
-                let _ = handle.emit("mission.state", &ms);
+                emit(&handle, "mission.state", &ms);
+            }
+        });
+    }
+
+    // Plans stored on the vehicle
+    {
+        let mut rx = vehicle.current_vehicle_plan();
+        let handle = app.clone();
+        bridges.spawn("vehicle_plan", async move {
+            while rx.changed().await.is_ok() {
+                let plans = rx.borrow().clone();
+                emit(&handle, "mission://vehicle_plan", &plans);
+            }
+        });
+    }
+
+    // Plans changed on the vehicle by someone else
+    {
+        let mut rx = vehicle.mission_changed_externally();
+        let handle = app.clone();
+        bridges.spawn("mission_changed_externally", async move {
+            loop {
+                match rx.recv().await {
+                    Ok(change) => emit(&handle, "mission://changed_externally", &change),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    // Smoothed attitude and position for the HUD
+    {
+        let mut rx = vehicle.telemetry_smoothed();
+        let handle = app.clone();
+        bridges.spawn("telemetry_smoothed", async move {
+            while rx.changed().await.is_ok() {
+                let smoothed: SmoothedTelemetry = rx.borrow().clone();
+                emit(&handle, "telemetry://smoothed", &smoothed);
             }
         });
     }
@@ -361,10 +2174,22 @@ fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
     {
         let mut rx = vehicle.link_state();
         let handle = app.clone();
-        tokio::spawn(async move {
+        bridges.spawn("link_state", async move {
             while rx.changed().await.is_ok() {
                 let ls: LinkState = rx.borrow().clone();
-                let _ = handle.emit("link://state", &ls);
+                emit(&handle, "link://state", &ls);
+            }
+        });
+    }
+
+    // UDP peer, on udpin links
+    {
+        let mut rx = vehicle.udp_peer();
+        let handle = app.clone();
+        bridges.spawn("udp_peer", async move {
+            while rx.changed().await.is_ok() {
+                let peer = rx.borrow().map(|addr| addr.to_string());
+                emit(&handle, "link://peer", &peer);
             }
         });
     }
@@ -373,11 +2198,11 @@ fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
     {
         let mut rx = vehicle.mission_progress();
         let handle = app.clone();
-        tokio::spawn(async move {
+        bridges.spawn("mission_progress", async move {
             while rx.changed().await.is_ok() {
                 let mp: Option<TransferProgress> = rx.borrow().clone();
                 if let Some(mp) = mp {
-                    let _ = handle.emit("mission.progress", &mp);
+                    emit(&handle, "mission.progress", &mp);
                 }
             }
         });
@@ -387,10 +2212,29 @@ fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
     {
         let mut rx = vehicle.param_store();
         let handle = app.clone();
-        tokio::spawn(async move {
+        bridges.spawn("param_store", async move {
+            let mut cache = JsonCache::default();
             while rx.changed().await.is_ok() {
-                let ps: ParamStore = rx.borrow().clone();
-                let _ = handle.emit("param://store", &ps);
+                let ps: Arc<ParamStore> = rx.borrow().clone();
+                if let Some(json) = cache.update(&ps) {
+                    emit(&handle, "param://store", &json);
+                }
+            }
+        });
+    }
+
+    // Individual parameter values, as they arrive
+    {
+        let mut rx = vehicle.param_updates();
+        let handle = app.clone();
+        bridges.spawn("param_updates", async move {
+            loop {
+                match rx.recv().await {
+                    Ok(update) => emit(&handle, "param://updated", &update),
+                    // The full store follows when the download completes
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
             }
         });
     }
@@ -399,13 +2243,64 @@ fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
     {
         let mut rx = vehicle.param_progress();
         let handle = app.clone();
-        tokio::spawn(async move {
+        bridges.spawn("param_progress", async move {
             while rx.changed().await.is_ok() {
-                let pp: ParamProgress = rx.borrow().clone();
-                let _ = handle.emit("param://progress", &pp);
+                // `param_download_all` tags and emits its own progress
+                let state = handle.state::<AppState>();
+                if state.operations.is_running(OperationKind::ParamDownload) {
+                    continue;
+                }
+                let progress: ParamProgress = rx.borrow().clone();
+                let event = ParamProgressEvent {
+                    operation_id: None,
+                    progress,
+                };
+                emit(&handle, "param://progress", &event);
             }
         });
     }
+
+    bridges
+}
+
+// ---------------------------------------------------------------------------
+// Dev-mode event recording
+// ---------------------------------------------------------------------------
+
+/// Emit `event` to the frontend. Debug builds also record it while a
+/// recording is active.
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: &S) {
+    let _ = app.emit(event, payload);
+    #[cfg(debug_assertions)]
+    recorder::record(app, event, payload);
+}
+
+#[cfg(debug_assertions)]
+#[tauri::command]
+fn dev_record_start(recorder: tauri::State<'_, EventRecorder>, path: String) -> Result<(), String> {
+    recorder.start(&path)
+}
+
+#[cfg(debug_assertions)]
+#[tauri::command]
+fn dev_record_stop(recorder: tauri::State<'_, EventRecorder>) -> Result<(), String> {
+    recorder.stop()
+}
+
+#[cfg(debug_assertions)]
+#[tauri::command]
+async fn dev_replay_start(
+    app: tauri::AppHandle,
+    path: String,
+    speed: Option<f64>,
+) -> Result<(), String> {
+    recorder::start_replay(&app, &path, speed.unwrap_or(1.0))
+}
+
+#[cfg(debug_assertions)]
+#[tauri::command]
+fn dev_replay_stop(app: tauri::AppHandle) {
+    recorder::stop_replay(&app);
 }
 
 // ---------------------------------------------------------------------------
@@ -416,14 +2311,43 @@ fn spawn_event_bridges(app: &tauri::AppHandle, vehicle: &Vehicle) {
 pub fn run() {
     let state = AppState {
         vehicle: tokio::sync::Mutex::new(None),
-        connect_abort: tokio::sync::Mutex::new(None),
+        connect_cancel: std::sync::Mutex::new(None),
+        connect_lock: tokio::sync::Mutex::new(()),
+        pending_request: std::sync::Mutex::new(None),
+        link_request: std::sync::Mutex::new(None),
+        units: std::sync::Mutex::new(Units::default()),
+        telemetry_stream: std::sync::Mutex::new(TelemetryStreamConfig::default()),
+        telemetry_resync: AtomicBool::new(false),
+        bridges: std::sync::Mutex::new(BridgeSet::default()),
+        flash_cancel: std::sync::Mutex::new(None),
+        operations: Operations::default(),
+        rc_calibration: std::sync::Mutex::new(None),
+        airspace: std::sync::Mutex::new(None),
+        fixed_wind: std::sync::Mutex::new(None),
+        open_meteo: OpenMeteoProvider::default(),
+        pose_output: std::sync::Mutex::new(None),
+        exit_confirmed: AtomicBool::new(false),
+        cli_overrides: ConnectOverrides::from_args(std::env::args()).unwrap_or_else(|err| {
+            eprintln!("ignoring command-line GCS ids: {err}");
+            ConnectOverrides::default()
+        }),
     };
 
     let mut builder = tauri::Builder::default()
         .manage(state)
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_http::init());
+        .plugin(tauri_plugin_http::init())
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                on_close_requested(window, api);
+            }
+        });
+
+    #[cfg(debug_assertions)]
+    {
+        builder = builder.manage(EventRecorder::default());
+    }
 
     #[cfg(not(target_os = "android"))]
     {
@@ -437,7 +2361,7 @@ pub fn run() {
             mission_clear_plan,
             mission_verify_roundtrip,
             mission_set_current,
-            mission_cancel,
+            operation_cancel,
             arm_vehicle,
             disarm_vehicle,
             set_flight_mode,
@@ -447,8 +2371,114 @@ pub fn run() {
             set_telemetry_rate,
             param_download_all,
             param_write,
+            param_write_transaction,
             param_parse_file,
-            param_format_file
+            param_format_file,
+            payload_capabilities,
+            payload_set_servo,
+            payload_set_relay,
+            payload_gripper,
+            fence_enable,
+            payload_winch,
+            plan_history_snapshot,
+            plan_history_list,
+            plan_history_restore,
+            mission_generate_structure_scan,
+            mission_wrap_vtol_block,
+            mission_validate_vtol,
+            vehicle_rtl_preview,
+            vehicle_propose_goto,
+            vehicle_confirm_goto,
+            vehicle_orbit,
+            vehicle_stop_orbit,
+            vehicle_request_battery_info,
+            mavlink_message_template,
+            mavlink_send_raw,
+            mavlink_benchmark_rates,
+            mission_audit_coordinates,
+            vehicle_set_gcs_identity,
+            vehicle_request_control,
+            vehicle_release_control,
+            vehicle_set_control_override,
+            sitl_is_available,
+            sitl_apply,
+            #[cfg(debug_assertions)]
+            dev_record_start,
+            #[cfg(debug_assertions)]
+            dev_record_stop,
+            #[cfg(debug_assertions)]
+            dev_replay_start,
+            #[cfg(debug_assertions)]
+            dev_replay_stop,
+            param_search,
+            param_groups,
+            mission_upload_all,
+            mission_verify_roundtrip_detailed,
+            units_get,
+            units_set,
+            telemetry_configure_stream,
+            telemetry_resync,
+            bridge_health,
+            firmware_flash,
+            firmware_flash_cancel,
+            setup_airframe_presets,
+            setup_frame,
+            setup_apply_airframe,
+            setup_set_frame,
+            rc_calibration_start,
+            rc_calibration_capture_trims,
+            rc_calibration_finish,
+            setup_write_rc_calibration,
+            setup_failsafe,
+            setup_failsafe_options,
+            setup_set_failsafe,
+            setup_sensors,
+            setup_sensor_rotations,
+            setup_set_compass_priority,
+            setup_set_compass_orientation,
+            setup_set_board_orientation,
+            setup_fixed_yaw_mag_cal,
+            mission_validate_fence,
+            airspace_load,
+            airspace_clear,
+            airspace_check_plan,
+            airspace_nearby,
+            weather_set_fixed_wind,
+            weather_wind_profile,
+            weather_route_estimate,
+            mission_sun_position,
+            mission_sun_times,
+            mission_sun_warnings,
+            mission_simulate,
+            mission_check_conflicts,
+            mission_validate_ardupilot,
+            vehicle_audit_log,
+            vehicle_export_audit_log,
+            vehicle_nudge,
+            vehicle_change_altitude,
+            vehicle_update_gcs_position,
+            vehicle_start_return_to_me,
+            vehicle_stop_return_to_me,
+            pose_output_start,
+            pose_output_stop,
+            mission_simplify,
+            mission_snap_to_grid,
+            mission_snap_altitudes,
+            mission_seal_plan,
+            mission_open_sealed_plan,
+            vehicle_update_config,
+            vehicle_shutdown_warning,
+            app_confirm_exit,
+            radio_read,
+            radio_write,
+            coords_format,
+            coords_parse,
+            mission_generate_search_pattern,
+            param_download_cached,
+            mission_sync_status,
+            mission_resume_from,
+            vehicle_land_here,
+            vehicle_abort_landing
         ]);
     }
 
@@ -463,7 +2493,7 @@ pub fn run() {
             mission_clear_plan,
             mission_verify_roundtrip,
             mission_set_current,
-            mission_cancel,
+            operation_cancel,
             arm_vehicle,
             disarm_vehicle,
             set_flight_mode,
@@ -473,8 +2503,110 @@ pub fn run() {
             set_telemetry_rate,
             param_download_all,
             param_write,
+            param_write_transaction,
             param_parse_file,
-            param_format_file
+            param_format_file,
+            payload_capabilities,
+            payload_set_servo,
+            payload_set_relay,
+            payload_gripper,
+            fence_enable,
+            payload_winch,
+            plan_history_snapshot,
+            plan_history_list,
+            plan_history_restore,
+            mission_generate_structure_scan,
+            mission_wrap_vtol_block,
+            mission_validate_vtol,
+            vehicle_rtl_preview,
+            vehicle_propose_goto,
+            vehicle_confirm_goto,
+            vehicle_orbit,
+            vehicle_stop_orbit,
+            vehicle_request_battery_info,
+            mavlink_message_template,
+            mavlink_send_raw,
+            mavlink_benchmark_rates,
+            mission_audit_coordinates,
+            vehicle_set_gcs_identity,
+            vehicle_request_control,
+            vehicle_release_control,
+            vehicle_set_control_override,
+            sitl_is_available,
+            sitl_apply,
+            #[cfg(debug_assertions)]
+            dev_record_start,
+            #[cfg(debug_assertions)]
+            dev_record_stop,
+            #[cfg(debug_assertions)]
+            dev_replay_start,
+            #[cfg(debug_assertions)]
+            dev_replay_stop,
+            param_search,
+            param_groups,
+            mission_upload_all,
+            mission_verify_roundtrip_detailed,
+            units_get,
+            units_set,
+            telemetry_configure_stream,
+            telemetry_resync,
+            bridge_health,
+            setup_airframe_presets,
+            setup_frame,
+            setup_apply_airframe,
+            setup_set_frame,
+            rc_calibration_start,
+            rc_calibration_capture_trims,
+            rc_calibration_finish,
+            setup_write_rc_calibration,
+            setup_failsafe,
+            setup_failsafe_options,
+            setup_set_failsafe,
+            setup_sensors,
+            setup_sensor_rotations,
+            setup_set_compass_priority,
+            setup_set_compass_orientation,
+            setup_set_board_orientation,
+            setup_fixed_yaw_mag_cal,
+            mission_validate_fence,
+            airspace_load,
+            airspace_clear,
+            airspace_check_plan,
+            airspace_nearby,
+            weather_set_fixed_wind,
+            weather_wind_profile,
+            weather_route_estimate,
+            mission_sun_position,
+            mission_sun_times,
+            mission_sun_warnings,
+            mission_simulate,
+            mission_check_conflicts,
+            mission_validate_ardupilot,
+            vehicle_audit_log,
+            vehicle_export_audit_log,
+            vehicle_nudge,
+            vehicle_change_altitude,
+            vehicle_update_gcs_position,
+            vehicle_start_return_to_me,
+            vehicle_stop_return_to_me,
+            pose_output_start,
+            pose_output_stop,
+            mission_simplify,
+            mission_snap_to_grid,
+            mission_snap_altitudes,
+            mission_seal_plan,
+            mission_open_sealed_plan,
+            vehicle_update_config,
+            vehicle_shutdown_warning,
+            app_confirm_exit,
+            coords_format,
+            coords_parse,
+            mission_generate_search_pattern,
+            param_download_cached,
+            mission_sync_status,
+            mission_resume_from,
+            vehicle_land_here,
+            vehicle_abort_landing
         ]);
     }
 
@@ -0,0 +1,126 @@
+//! Long-running commands the frontend can cancel.
+//!
+//! A command that starts one registers it in `Operations`, resolves its
+//! `invoke` with the operation id straight away and reports the outcome as
+//! `operation://finished` once the work is done. `operation_cancel(id)` fires
+//! the operation's `CancellationToken`, whatever kind of work it is.
+
+use mavkit::CancellationToken;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OperationKind {
+    MissionUpload,
+    MissionDownload,
+    /// Upload followed by a read-back.
+    MissionVerify,
+    ParamDownload,
+    RcCalibration,
+    RateBenchmark,
+}
+
+/// `operation://finished` payload.
+#[derive(Serialize, Clone)]
+pub(crate) struct OperationFinished {
+    pub id: u64,
+    pub kind: OperationKind,
+    /// The command's result; `null` when it failed.
+    pub value: serde_json::Value,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub(crate) struct Operations {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<u64, (OperationKind, CancellationToken)>>,
+}
+
+impl Operations {
+    /// Register a new operation; ids start at 1 and are never reused.
+    pub(crate) fn begin(&self, kind: OperationKind) -> (u64, CancellationToken) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = CancellationToken::new();
+        self.running
+            .lock()
+            .unwrap()
+            .insert(id, (kind, cancel.clone()));
+        (id, cancel)
+    }
+
+    /// Cancel operation `id`; false if it is unknown or already over.
+    pub(crate) fn cancel(&self, id: u64) -> bool {
+        match self.running.lock().unwrap().remove(&id) {
+            Some((_, cancel)) => {
+                cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel every running operation of `kind`, for operations that replace
+    /// the one in progress.
+    pub(crate) fn cancel_kind(&self, kind: OperationKind) {
+        self.running.lock().unwrap().retain(|_, (running, cancel)| {
+            if *running == kind {
+                cancel.cancel();
+            }
+            *running != kind
+        });
+    }
+
+    pub(crate) fn is_running(&self, kind: OperationKind) -> bool {
+        self.running
+            .lock()
+            .unwrap()
+            .values()
+            .any(|(running, _)| *running == kind)
+    }
+
+    pub(crate) fn end(&self, id: u64) {
+        self.running.lock().unwrap().remove(&id);
+    }
+
+    /// Cancel everything, e.g. when the vehicle disconnects.
+    pub(crate) fn cancel_all(&self) {
+        for (_, (_, cancel)) in self.running.lock().unwrap().drain() {
+            cancel.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_fires_the_token_once() {
+        let operations = Operations::default();
+        let (upload, upload_cancel) = operations.begin(OperationKind::MissionUpload);
+        let (download, download_cancel) = operations.begin(OperationKind::ParamDownload);
+        assert_ne!(upload, download);
+
+        assert!(operations.cancel(upload));
+        assert!(upload_cancel.is_cancelled());
+        assert!(!operations.cancel(upload));
+
+        operations.end(download);
+        assert!(!operations.cancel(download));
+        assert!(!download_cancel.is_cancelled());
+    }
+
+    #[test]
+    fn cancel_kind_leaves_other_kinds_running() {
+        let operations = Operations::default();
+        let (_, params) = operations.begin(OperationKind::ParamDownload);
+        let (mission, mission_cancel) = operations.begin(OperationKind::MissionDownload);
+        operations.cancel_kind(OperationKind::ParamDownload);
+        assert!(params.is_cancelled());
+        assert!(!mission_cancel.is_cancelled());
+        assert!(operations.cancel(mission));
+    }
+}
@@ -0,0 +1,108 @@
+//! Dev-mode capture and replay of backend → frontend events. Compiled into
+//! debug builds only.
+//!
+//! While recording, every event sent through `emit` is appended to a
+//! JSON-lines file as `{"t_ms", "event", "payload"}`. Replay re-emits a
+//! recording with its original timing, so the UI can be worked on without a
+//! vehicle or SITL.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+#[derive(Serialize, Deserialize)]
+struct RecordedEvent {
+    t_ms: u64,
+    event: String,
+    payload: serde_json::Value,
+}
+
+struct Recording {
+    writer: BufWriter<File>,
+    started: Instant,
+}
+
+#[derive(Default)]
+pub(crate) struct EventRecorder {
+    recording: Mutex<Option<Recording>>,
+    replay: Mutex<Option<tokio::task::AbortHandle>>,
+}
+
+impl EventRecorder {
+    pub(crate) fn start(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        *self.recording.lock().unwrap() = Some(Recording {
+            writer: BufWriter::new(file),
+            started: Instant::now(),
+        });
+        Ok(())
+    }
+
+    pub(crate) fn stop(&self) -> Result<(), String> {
+        if let Some(mut recording) = self.recording.lock().unwrap().take() {
+            recording.writer.flush().map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn record<S: Serialize>(&self, event: &str, payload: &S) {
+        let mut guard = self.recording.lock().unwrap();
+        let Some(recording) = guard.as_mut() else {
+            return;
+        };
+        let Ok(payload) = serde_json::to_value(payload) else {
+            return;
+        };
+        let line = RecordedEvent {
+            t_ms: recording.started.elapsed().as_millis() as u64,
+            event: event.to_string(),
+            payload,
+        };
+        if let Ok(json) = serde_json::to_string(&line) {
+            let _ = writeln!(recording.writer, "{json}");
+        }
+    }
+
+    fn set_replay(&self, handle: Option<tokio::task::AbortHandle>) {
+        if let Some(previous) = std::mem::replace(&mut *self.replay.lock().unwrap(), handle) {
+            previous.abort();
+        }
+    }
+}
+
+/// Append an emitted event to the active recording, if any.
+pub(crate) fn record<S: Serialize>(app: &AppHandle, event: &str, payload: &S) {
+    app.state::<EventRecorder>().record(event, payload);
+}
+
+/// Re-emit a recording. `speed` scales playback (2.0 plays twice as fast).
+pub(crate) fn start_replay(app: &AppHandle, path: &str, speed: f64) -> Result<(), String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let events: Vec<RecordedEvent> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("invalid recording: {e}"))?;
+    let speed = if speed > 0.0 { speed } else { 1.0 };
+
+    let handle = app.clone();
+    let task = tokio::spawn(async move {
+        let started = tokio::time::Instant::now();
+        for recorded in events {
+            let at = Duration::from_secs_f64(recorded.t_ms as f64 / 1000.0 / speed);
+            tokio::time::sleep_until(started + at).await;
+            let _ = handle.emit(&recorded.event, &recorded.payload);
+        }
+    });
+    app.state::<EventRecorder>()
+        .set_replay(Some(task.abort_handle()));
+    Ok(())
+}
+
+pub(crate) fn stop_replay(app: &AppHandle) {
+    app.state::<EventRecorder>().set_replay(None);
+}
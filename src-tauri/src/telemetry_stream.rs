@@ -0,0 +1,202 @@
+//! Telemetry delta emission.
+//!
+//! Instead of the whole `Telemetry` object on every tick, the bridge can send
+//! only the fields that changed since the last emission
+//! (`telemetry://delta`), with optional per-field rate limits. A full
+//! `telemetry://snapshot` is sent when delta mode is enabled and whenever the
+//! frontend asks for a resync, so a newly mounted subscriber starts from a
+//! complete picture. `seq` increases with every delta and snapshot; a gap
+//! tells the frontend it missed an update and should resync.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct TelemetryStreamConfig {
+    /// Send deltas instead of full `telemetry://tick` objects.
+    #[serde(default)]
+    pub delta: bool,
+    /// Maximum emission rate per field name, in Hz. Fields not listed follow
+    /// the bridge tick rate.
+    #[serde(default)]
+    pub field_rates_hz: HashMap<String, f64>,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct TelemetryDelta {
+    pub seq: u64,
+    /// Changed fields only. A field that became unavailable is sent as null.
+    pub fields: Map<String, Value>,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct TelemetrySnapshot<T> {
+    pub seq: u64,
+    pub telemetry: T,
+}
+
+#[derive(Default)]
+pub(crate) struct TelemetryCoalescer {
+    seq: u64,
+    sent: Map<String, Value>,
+    last_emit: HashMap<String, Instant>,
+}
+
+impl TelemetryCoalescer {
+    /// Full snapshot; resets the delta baseline to `telemetry`.
+    pub(crate) fn snapshot<T: Serialize + Clone>(
+        &mut self,
+        telemetry: &T,
+        now: Instant,
+    ) -> TelemetrySnapshot<T> {
+        self.sent = to_object(telemetry);
+        self.last_emit = self.sent.keys().map(|key| (key.clone(), now)).collect();
+        self.seq += 1;
+        TelemetrySnapshot {
+            seq: self.seq,
+            telemetry: telemetry.clone(),
+        }
+    }
+
+    /// Fields of `telemetry` that differ from what was last sent and are not
+    /// held back by their rate limit. `None` when nothing is due.
+    pub(crate) fn delta<T: Serialize>(
+        &mut self,
+        telemetry: &T,
+        config: &TelemetryStreamConfig,
+        now: Instant,
+    ) -> Option<TelemetryDelta> {
+        let mut fields = Map::new();
+        for (key, value) in to_object(telemetry) {
+            if self.sent.get(&key) == Some(&value) {
+                continue;
+            }
+            let limited = config
+                .field_rates_hz
+                .get(&key)
+                .filter(|hz| **hz > 0.0)
+                .zip(self.last_emit.get(&key))
+                .is_some_and(|(hz, last)| {
+                    now.saturating_duration_since(*last) < Duration::from_secs_f64(1.0 / hz)
+                });
+            if limited {
+                continue;
+            }
+            self.sent.insert(key.clone(), value.clone());
+            self.last_emit.insert(key.clone(), now);
+            fields.insert(key, value);
+        }
+        if fields.is_empty() {
+            return None;
+        }
+        self.seq += 1;
+        Some(TelemetryDelta {
+            seq: self.seq,
+            fields,
+        })
+    }
+}
+
+fn to_object<T: Serialize>(value: &T) -> Map<String, Value> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn delta_carries_only_changed_fields() {
+        let now = Instant::now();
+        let config = TelemetryStreamConfig::default();
+        let mut coalescer = TelemetryCoalescer::default();
+        let snapshot = coalescer.snapshot(&json!({"alt": 10.0, "speed": 5.0, "fix": 3}), now);
+        assert_eq!(snapshot.seq, 1);
+
+        let unchanged = json!({"alt": 10.0, "speed": 5.0, "fix": 3});
+        assert!(coalescer.delta(&unchanged, &config, now).is_none());
+        let delta = coalescer
+            .delta(
+                &json!({"alt": 12.0, "speed": 5.0, "fix": null}),
+                &config,
+                now,
+            )
+            .unwrap();
+        assert_eq!(delta.seq, 2);
+        assert_eq!(
+            Value::Object(delta.fields),
+            json!({"alt": 12.0, "fix": null})
+        );
+    }
+
+    #[test]
+    fn rate_limited_changes_merge_into_a_later_delta() {
+        let start = Instant::now();
+        let config = TelemetryStreamConfig {
+            delta: true,
+            field_rates_hz: HashMap::from([("alt".to_string(), 2.0)]),
+        };
+        let mut coalescer = TelemetryCoalescer::default();
+        coalescer.snapshot(&json!({"alt": 10.0, "speed": 5.0}), start);
+
+        // alt went out with the snapshot, so it is held; speed is not limited
+        let delta = coalescer
+            .delta(
+                &json!({"alt": 11.0, "speed": 6.0}),
+                &config,
+                start + ms(100),
+            )
+            .unwrap();
+        assert_eq!(Value::Object(delta.fields), json!({"speed": 6.0}));
+        let held = json!({"alt": 12.0, "speed": 6.0});
+        assert!(coalescer.delta(&held, &config, start + ms(300)).is_none());
+
+        // Once due, only the latest value is sent
+        let delta = coalescer
+            .delta(
+                &json!({"alt": 13.0, "speed": 6.0}),
+                &config,
+                start + ms(500),
+            )
+            .unwrap();
+        assert_eq!(Value::Object(delta.fields), json!({"alt": 13.0}));
+
+        // A change undone within the window is never sent
+        let undone = [(14.0, 600), (13.0, 1100)];
+        for (alt, at) in undone {
+            let telemetry = json!({"alt": alt, "speed": 6.0});
+            assert!(coalescer
+                .delta(&telemetry, &config, start + ms(at))
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn seq_has_no_gaps_across_snapshots_and_deltas() {
+        let now = Instant::now();
+        let config = TelemetryStreamConfig::default();
+        let mut coalescer = TelemetryCoalescer::default();
+        let mut seqs = vec![coalescer.snapshot(&json!({"alt": 1.0}), now).seq];
+        for alt in [1.0, 2.0, 2.0, 3.0] {
+            if let Some(delta) = coalescer.delta(&json!({"alt": alt}), &config, now) {
+                seqs.push(delta.seq);
+            }
+        }
+        // A resync resets the baseline but continues the sequence
+        seqs.push(coalescer.snapshot(&json!({"alt": 3.0}), now).seq);
+        assert!(coalescer
+            .delta(&json!({"alt": 3.0}), &config, now)
+            .is_none());
+        assert_eq!(seqs, [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,71 @@
+//! Open-Meteo wind provider.
+//!
+//! Forecasts are cached per ~5 km cell for an hour, so repeated estimates
+//! while editing a plan do not refetch, and planning keeps working from the
+//! cached forecast when the network drops.
+
+use mavkit::weather::WeatherFuture;
+use mavkit::{open_meteo_url, parse_open_meteo, WeatherError, WeatherProvider, WindForecast};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri_plugin_http::reqwest;
+
+const CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Forecast cell size, degrees.
+const CELL_DEG: f64 = 0.05;
+
+#[derive(Default)]
+pub(crate) struct OpenMeteoProvider {
+    client: reqwest::Client,
+    cache: Mutex<Option<((i64, i64), Instant, WindForecast)>>,
+}
+
+impl OpenMeteoProvider {
+    async fn forecast(&self, lat: f64, lon: f64) -> Result<WindForecast, WeatherError> {
+        let cell = (
+            (lat / CELL_DEG).round() as i64,
+            (lon / CELL_DEG).round() as i64,
+        );
+        let cached = self.cache.lock().unwrap().clone();
+        if let Some((key, fetched, forecast)) = &cached {
+            if *key == cell && fetched.elapsed() < CACHE_TTL {
+                return Ok(forecast.clone());
+            }
+        }
+        let fetch = async {
+            let response = self
+                .client
+                .get(open_meteo_url(lat, lon))
+                .timeout(Duration::from_secs(10))
+                .send()
+                .await?
+                .error_for_status()?;
+            response.text().await
+        };
+        match fetch.await {
+            Ok(body) => {
+                let forecast = parse_open_meteo(&body)?;
+                *self.cache.lock().unwrap() = Some((cell, Instant::now(), forecast.clone()));
+                Ok(forecast)
+            }
+            // Offline: a stale forecast for the same cell beats none
+            Err(_) if cached.as_ref().is_some_and(|(key, _, _)| *key == cell) => {
+                Ok(cached.unwrap().2)
+            }
+            Err(err) => Err(WeatherError(err.to_string())),
+        }
+    }
+}
+
+impl WeatherProvider for OpenMeteoProvider {
+    fn wind_profile(&self, lat: f64, lon: f64, at_unix_s: i64) -> WeatherFuture<'_> {
+        Box::pin(async move {
+            let forecast = self.forecast(lat, lon).await?;
+            forecast
+                .at(at_unix_s)
+                .cloned()
+                .ok_or_else(|| WeatherError("forecast does not cover that time".into()))
+        })
+    }
+}
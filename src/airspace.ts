@@ -0,0 +1,59 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { MissionIssue, MissionPlan } from "./mission";
+
+export type AltitudeUnit = "meters" | "feet" | "flight_level";
+export type AltitudeReference = "ground" | "msl" | "standard";
+
+export type AltitudeLimit = {
+  value: number;
+  unit: AltitudeUnit;
+  reference: AltitudeReference;
+};
+
+export type Airspace = {
+  name: string;
+  class: string | null;
+  kind: string | null;
+  lower: AltitudeLimit | null;
+  upper: AltitudeLimit | null;
+  /** Outer rings as [lat, lon] degrees. */
+  polygons: [number, number][][];
+};
+
+export type AirspaceSet = {
+  airspaces: Airspace[];
+  /** Features that were not polygons and were ignored. */
+  skipped: number;
+};
+
+export type AirspaceProximity = {
+  /** Index into `AirspaceSet.airspaces`. */
+  index: number;
+  name: string;
+  inside: boolean;
+  distance_m: number;
+};
+
+/** Load an OpenAIP export or GeoJSON file, replacing any loaded dataset. */
+export async function loadAirspace(path: string): Promise<AirspaceSet> {
+  return invoke<AirspaceSet>("airspace_load", { path });
+}
+
+export async function clearAirspace(): Promise<void> {
+  return invoke("airspace_clear");
+}
+
+/** Warnings for airspaces the plan's route touches; empty when none is loaded. */
+export async function checkPlanAirspace(plan: MissionPlan): Promise<MissionIssue[]> {
+  return invoke<MissionIssue[]>("airspace_check_plan", { plan });
+}
+
+export async function nearbyAirspace(lat: number, lon: number, radiusM: number): Promise<AirspaceProximity[]> {
+  return invoke<AirspaceProximity[]>("airspace_nearby", { lat, lon, radiusM });
+}
+
+/** Zones within 1 km of the vehicle, re-sent when the list or an inside flag changes. */
+export async function subscribeAirspaceProximity(cb: (nearby: AirspaceProximity[]) => void): Promise<UnlistenFn> {
+  return listen<AirspaceProximity[]>("airspace://proximity", (event) => cb(event.payload));
+}
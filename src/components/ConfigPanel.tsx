@@ -322,6 +322,15 @@ export function ConfigPanel({ params, connected }: ConfigPanelProps) {
           <RefreshCw size={12} className={downloading ? "animate-spin" : ""} />
           {downloading ? "Downloading…" : "Refresh"}
         </button>
+        {params.downloadInProgress && (
+          <button
+            onClick={params.cancelDownload}
+            className="flex items-center gap-1.5 rounded-md border border-border bg-bg-secondary px-3 py-1.5 text-xs font-medium text-text-primary transition-opacity disabled:opacity-40"
+          >
+            <X size={12} />
+            Cancel
+          </button>
+        )}
         <button
           onClick={params.saveToFile}
           disabled={!params.store}
@@ -370,6 +379,12 @@ export function ConfigPanel({ params, connected }: ConfigPanelProps) {
           </div>
           <span className="text-[10px] text-text-muted">
             {params.progress.received} / {params.progress.expected} parameters
+            {params.progress.rate_per_sec > 0 && ` · ${params.progress.rate_per_sec.toFixed(0)}/s`}
+            {params.progress.stage === "gap_fill" &&
+              ` · re-requesting ${params.progress.missing_count} missing` +
+                (params.progress.retries > 0
+                  ? ` (retry ${params.progress.retries}/${params.progress.max_retries})`
+                  : "")}
           </span>
         </div>
       )}
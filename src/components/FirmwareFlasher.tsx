@@ -0,0 +1,117 @@
+import { useEffect, useState } from "react";
+import { open } from "@tauri-apps/plugin-dialog";
+import { toast } from "sonner";
+import { cancelFlash, flashFirmware, subscribeFlashProgress, type FlashProgress } from "../firmware";
+import { listSerialPorts } from "../telemetry";
+
+const PHASE_LABELS: Record<FlashProgress["phase"], string> = {
+  waiting_for_bootloader: "Waiting for bootloader…",
+  erasing: "Erasing…",
+  programming: "Programming",
+  verifying: "Verifying…",
+  rebooting: "Rebooting…",
+  done: "Done",
+};
+
+export function FirmwareFlasher() {
+  const [ports, setPorts] = useState<string[]>([]);
+  const [port, setPort] = useState("");
+  const [rebootVehicle, setRebootVehicle] = useState(true);
+  const [flashing, setFlashing] = useState(false);
+  const [progress, setProgress] = useState<FlashProgress | null>(null);
+
+  useEffect(() => {
+    listSerialPorts()
+      .then((found) => {
+        setPorts(found);
+        if (found.length > 0) setPort((current) => current || found[0]);
+      })
+      .catch(() => setPorts([]));
+  }, []);
+
+  useEffect(() => {
+    const unlisten = subscribeFlashProgress(setProgress);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  async function chooseAndFlash() {
+    const path = await open({
+      filters: [{ name: "Firmware", extensions: ["apj", "px4"] }],
+      multiple: false,
+    });
+    if (!path) return;
+    setFlashing(true);
+    setProgress(null);
+    try {
+      const board = await flashFirmware(path, port, rebootVehicle);
+      toast.success("Firmware flashed", { description: `Board id ${board.board_id}` });
+    } catch (err) {
+      toast.error("Firmware flash failed", { description: String(err) });
+    } finally {
+      setFlashing(false);
+    }
+  }
+
+  const percent =
+    progress && progress.bytes_total > 0 ? Math.round((progress.bytes_written / progress.bytes_total) * 100) : 0;
+
+  return (
+    <div className="rounded-lg border border-border bg-bg-secondary p-4">
+      <h3 className="mb-3 text-sm font-semibold">Firmware</h3>
+      <div className="flex items-center gap-2">
+        <select
+          value={port}
+          onChange={(e) => setPort(e.target.value)}
+          disabled={flashing}
+          className="flex-1 rounded-md border border-border bg-bg-input px-2.5 py-1.5 text-sm text-text-primary disabled:opacity-50"
+        >
+          {ports.length === 0 && <option value="">No ports</option>}
+          {ports.map((p) => <option key={p} value={p}>{p}</option>)}
+        </select>
+        {flashing ? (
+          <button
+            onClick={() => cancelFlash()}
+            className="rounded-md bg-bg-tertiary px-3 py-1.5 text-xs font-medium text-text-primary"
+          >
+            Cancel
+          </button>
+        ) : (
+          <button
+            onClick={chooseAndFlash}
+            disabled={port === ""}
+            className="rounded-md bg-accent-blue px-3 py-1.5 text-xs font-medium text-white transition-opacity disabled:opacity-40"
+          >
+            Choose file & flash
+          </button>
+        )}
+      </div>
+      <label className="mt-3 flex items-center gap-3">
+        <input
+          type="checkbox"
+          checked={rebootVehicle}
+          onChange={(e) => setRebootVehicle(e.target.checked)}
+          disabled={flashing}
+          className="h-4 w-4 accent-accent-blue"
+        />
+        <span className="text-sm">Reboot the connected vehicle into its bootloader first</span>
+      </label>
+      {progress && (
+        <div className="mt-3">
+          <div className="flex justify-between text-xs text-text-secondary">
+            <span>{PHASE_LABELS[progress.phase]}</span>
+            {progress.phase === "programming" && <span className="tabular-nums">{percent}%</span>}
+          </div>
+          <div className="mt-1 h-1.5 overflow-hidden rounded bg-bg-tertiary">
+            <div className="h-full bg-accent-blue transition-all" style={{ width: `${percent}%` }} />
+          </div>
+        </div>
+      )}
+      <p className="mt-2 text-xs text-text-muted">
+        Flashes an ArduPilot .apj or PX4 .px4 file through the board's USB bootloader. Without a connected vehicle,
+        plug the board in after starting.
+      </p>
+    </div>
+  );
+}
@@ -60,7 +60,7 @@ export function MissionPanel({ vehicle, mission }: MissionPanelProps) {
           <span className="text-text-muted">Home:</span>
           {mission.homePosition ? (
             <span className="text-text-secondary">
-              {mission.homePosition.latitude_deg.toFixed(6)}, {mission.homePosition.longitude_deg.toFixed(6)}, alt {mission.homePosition.altitude_m.toFixed(1)}m
+              {mission.homePosition.latitude_deg.toFixed(6)}, {mission.homePosition.longitude_deg.toFixed(6)}, alt {mission.homePosition.altitude_m.toFixed(1)}m {mission.homePosition.altitude_datum === "ellipsoid" ? "ellipsoid" : "AMSL"}
               {mission.homeSource ? ` (${mission.homeSource})` : ""}
             </span>
           ) : (
@@ -1,8 +1,11 @@
 import {
   Upload, Download, ShieldCheck, Trash2,
-  Plus, ArrowUp, ArrowDown, ChevronLeft, ChevronRight, X, SkipForward,
+  Plus, ArrowUp, ArrowDown, ChevronLeft, ChevronRight, X, SkipForward, History,
 } from "lucide-react";
 import { Button } from "./ui/button";
+import {
+  DropdownMenu, DropdownMenuContent, DropdownMenuItem, DropdownMenuTrigger,
+} from "./ui/dropdown-menu";
 import { Progress } from "./ui/progress";
 import { Tooltip, TooltipContent, TooltipTrigger } from "./ui/tooltip";
 import type { useMission } from "../hooks/use-mission";
@@ -34,10 +37,10 @@ function IconButton({ icon, label, onClick, disabled }: {
 export function PlannerToolbar({ mission, connected }: PlannerToolbarProps) {
   const {
     items, selectedSeq, missionType, setMissionType,
-    transferActive, progress, roundtripStatus,
+    transferActive, progress, roundtripStatus, snapshots,
     addWaypoint, insertBefore, insertAfter, deleteAt, moveUp, moveDown,
     validate, upload, download, verify, clear, cancel, setCurrent,
-    updateHomeFromVehicle,
+    updateHomeFromVehicle, restoreSnapshot,
   } = mission;
 
   const hasProgress = progress && (progress.phase === "transfer_items" || progress.phase === "request_count");
@@ -88,6 +91,21 @@ export function PlannerToolbar({ mission, connected }: PlannerToolbarProps) {
         <Button variant="ghost" size="sm" disabled={missionType !== "mission"} onClick={updateHomeFromVehicle}>
           Home from Vehicle
         </Button>
+        <DropdownMenu>
+          <DropdownMenuTrigger asChild>
+            <Button variant="ghost" size="sm" disabled={transferActive || snapshots.length === 0}>
+              <History className="h-3.5 w-3.5" /> History
+            </Button>
+          </DropdownMenuTrigger>
+          <DropdownMenuContent align="start" className="max-h-72 overflow-y-auto">
+            {[...snapshots].reverse().map((snapshot) => (
+              <DropdownMenuItem key={snapshot.id} onSelect={() => void restoreSnapshot(snapshot.id)}>
+                {new Date(snapshot.created_at_ms).toLocaleString()}
+                {snapshot.label ? ` — ${snapshot.label}` : ""}
+              </DropdownMenuItem>
+            ))}
+          </DropdownMenuContent>
+        </DropdownMenu>
 
         {transferActive && (
           <Button variant="ghost" size="sm" onClick={cancel}>Cancel</Button>
@@ -1,6 +1,7 @@
 import { useState } from "react";
 import type { Settings } from "../hooks/use-settings";
 import { setTelemetryRate } from "../telemetry";
+import { FirmwareFlasher } from "./FirmwareFlasher";
 
 type SettingsPanelProps = {
   settings: Settings;
@@ -71,6 +72,8 @@ export function SettingsPanel({ settings, updateSettings }: SettingsPanelProps)
           </span>
         </label>
       </div>
+
+      <FirmwareFlasher />
     </div>
   );
 }
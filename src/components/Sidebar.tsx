@@ -6,6 +6,7 @@ import { Button } from "./ui/button";
 import { ArmSlider } from "./ArmSlider";
 import { cn } from "../lib/utils";
 import type { useVehicle } from "../hooks/use-vehicle";
+import type { SerialOptions } from "../telemetry";
 
 type SidebarProps = {
   vehicle: ReturnType<typeof useVehicle>;
@@ -65,7 +66,9 @@ function SidebarContent({ vehicle }: { vehicle: ReturnType<typeof useVehicle> })
     telemetry, linkState, vehicleState, connected, connectionError,
     isConnecting, cancelConnect,
     connectionMode, setConnectionMode, udpBind, setUdpBind,
+    tcpAddr, setTcpAddr, tcpTls, setTcpTls, tlsPin, setTlsPin,
     serialPort, setSerialPort, baud, setBaud, serialPorts,
+    serialOptions, setSerialOptions,
     takeoffAlt, setTakeoffAlt, availableModes,
     connect, disconnect, refreshSerialPorts,
     arm, disarm, setFlightMode, takeoff, findModeNumber,
@@ -84,11 +87,12 @@ function SidebarContent({ vehicle }: { vehicle: ReturnType<typeof useVehicle> })
         <div className="space-y-2">
           <select
             value={connectionMode}
-            onChange={(e) => setConnectionMode(e.target.value as "udp" | "serial")}
+            onChange={(e) => setConnectionMode(e.target.value as "udp" | "tcp" | "serial")}
             disabled={formLocked}
             className="w-full rounded-md border border-border bg-bg-input px-2.5 py-1.5 text-sm text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
           >
             <option value="udp">UDP</option>
+            <option value="tcp">TCP</option>
             <option value="serial">Serial</option>
           </select>
 
@@ -100,6 +104,35 @@ function SidebarContent({ vehicle }: { vehicle: ReturnType<typeof useVehicle> })
               disabled={formLocked}
               className="w-full rounded-md border border-border bg-bg-input px-2.5 py-1.5 text-sm text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
             />
+          ) : connectionMode === "tcp" ? (
+            <>
+              <input
+                value={tcpAddr}
+                onChange={(e) => setTcpAddr(e.target.value)}
+                placeholder="relay.example.com:5760"
+                disabled={formLocked}
+                className="w-full rounded-md border border-border bg-bg-input px-2.5 py-1.5 text-sm text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
+              />
+              <label className="flex items-center gap-1.5 text-xs text-text-secondary">
+                <input
+                  type="checkbox"
+                  checked={tcpTls}
+                  onChange={(e) => setTcpTls(e.target.checked)}
+                  disabled={formLocked}
+                />
+                TLS
+              </label>
+              {tcpTls && (
+                <input
+                  value={tlsPin}
+                  onChange={(e) => setTlsPin(e.target.value)}
+                  placeholder="Pinned SHA-256 (optional)"
+                  title="Certificate fingerprint to accept instead of checking the CA chain"
+                  disabled={formLocked}
+                  className="w-full rounded-md border border-border bg-bg-input px-2.5 py-1.5 font-mono text-xs text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
+                />
+              )}
+            </>
           ) : (
             <>
               <div className="flex gap-1.5">
@@ -123,6 +156,62 @@ function SidebarContent({ vehicle }: { vehicle: ReturnType<typeof useVehicle> })
                 disabled={formLocked}
                 className="w-full rounded-md border border-border bg-bg-input px-2.5 py-1.5 text-sm text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
               />
+              <div className="flex gap-1.5">
+                <select
+                  value={serialOptions.flow_control}
+                  onChange={(e) => setSerialOptions({ ...serialOptions, flow_control: e.target.value as SerialOptions["flow_control"] })}
+                  disabled={formLocked}
+                  title="Flow control"
+                  className="flex-1 rounded-md border border-border bg-bg-input px-2 py-1.5 text-xs text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
+                >
+                  <option value="none">No flow ctl</option>
+                  <option value="hardware">RTS/CTS</option>
+                  <option value="software">XON/XOFF</option>
+                </select>
+                <select
+                  value={serialOptions.parity}
+                  onChange={(e) => setSerialOptions({ ...serialOptions, parity: e.target.value as SerialOptions["parity"] })}
+                  disabled={formLocked}
+                  title="Parity"
+                  className="rounded-md border border-border bg-bg-input px-2 py-1.5 text-xs text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
+                >
+                  <option value="none">N</option>
+                  <option value="even">E</option>
+                  <option value="odd">O</option>
+                </select>
+                <select
+                  value={serialOptions.stop_bits}
+                  onChange={(e) => setSerialOptions({ ...serialOptions, stop_bits: e.target.value as SerialOptions["stop_bits"] })}
+                  disabled={formLocked}
+                  title="Stop bits"
+                  className="rounded-md border border-border bg-bg-input px-2 py-1.5 text-xs text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
+                >
+                  <option value="one">1</option>
+                  <option value="two">2</option>
+                </select>
+              </div>
+              <div className="flex gap-3 text-xs text-text-secondary">
+                {(["dtr", "rts"] as const).map((line) => (
+                  <label key={line} className="flex items-center gap-1">
+                    {line.toUpperCase()}
+                    <select
+                      value={serialOptions[line] == null ? "default" : serialOptions[line] ? "high" : "low"}
+                      onChange={(e) =>
+                        setSerialOptions({
+                          ...serialOptions,
+                          [line]: e.target.value === "default" ? null : e.target.value === "high",
+                        })
+                      }
+                      disabled={formLocked}
+                      className="rounded-md border border-border bg-bg-input px-1.5 py-0.5 text-xs text-text-primary disabled:opacity-50 disabled:cursor-not-allowed"
+                    >
+                      <option value="default">default</option>
+                      <option value="high">on</option>
+                      <option value="low">off</option>
+                    </select>
+                  </label>
+                ))}
+              </div>
             </>
           )}
 
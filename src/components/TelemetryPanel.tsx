@@ -89,6 +89,7 @@ export function TelemetryPanel({ vehicle, mission }: TelemetryPanelProps) {
         <Metric label="Nav Brg" value={`${fmt(telemetry.nav_bearing_deg, 0)}°`} />
         <Metric label="Tgt Brg" value={`${fmt(telemetry.target_bearing_deg, 0)}°`} />
         <Metric label="XTrack" value={fmt(telemetry.xtrack_error_m)} unit="m" />
+        <Metric label="Alt Err" value={fmt(telemetry.alt_error_m)} unit="m" />
       </SectionRow>
 
       <SectionRow title="Attitude">
@@ -4,6 +4,7 @@ import type { useMission } from "../../hooks/use-mission";
 import { TapeGauge } from "./TapeGauge";
 import { ArtificialHorizon } from "./ArtificialHorizon";
 import { MissionMap, type SvsTelemetry } from "../MissionMap";
+import { subscribeTelemetrySmoothed, type SmoothedTelemetry } from "../../telemetry";
 import "./hud.css";
 
 type HudPanelProps = {
@@ -42,6 +43,15 @@ export function HudPanel({ vehicle, mission, svsEnabled }: HudPanelProps) {
   const { telemetry, vehicleState, vehiclePosition } = vehicle;
   const horizonRef = useRef<HTMLDivElement>(null);
   const [horizonSize, setHorizonSize] = useState({ width: 400, height: 300 });
+  const [smoothed, setSmoothed] = useState<SmoothedTelemetry | null>(null);
+
+  // The horizon follows the smoothed attitude so it moves fluidly on slow links
+  useEffect(() => {
+    const unlisten = subscribeTelemetrySmoothed(setSmoothed);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
 
   // Measure the center cell for the artificial horizon
   useEffect(() => {
@@ -169,8 +179,8 @@ export function HudPanel({ vehicle, mission, svsEnabled }: HudPanelProps) {
         {/* Center: artificial horizon */}
         <div ref={horizonRef} className="relative overflow-hidden">
           <ArtificialHorizon
-            pitch={telemetry.pitch_deg}
-            roll={telemetry.roll_deg}
+            pitch={smoothed?.pitch_deg ?? telemetry.pitch_deg}
+            roll={smoothed?.roll_deg ?? telemetry.roll_deg}
             size={horizonSize}
             climbRate={telemetry.climb_rate_mps}
             groundSpeed={telemetry.speed_mps}
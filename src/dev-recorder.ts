@@ -0,0 +1,19 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Debug builds only: capture backend events to a JSON-lines file, or replay one.
+
+export async function startEventRecording(path: string): Promise<void> {
+  await invoke("dev_record_start", { path });
+}
+
+export async function stopEventRecording(): Promise<void> {
+  await invoke("dev_record_stop");
+}
+
+export async function startEventReplay(path: string, speed = 1): Promise<void> {
+  await invoke("dev_replay_start", { path, speed });
+}
+
+export async function stopEventReplay(): Promise<void> {
+  await invoke("dev_replay_stop");
+}
@@ -0,0 +1,37 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+export type BoardInfo = {
+  bootloader_rev: number;
+  board_id: number;
+  board_rev: number;
+  flash_size: number;
+};
+
+export type FlashPhase =
+  | "waiting_for_bootloader"
+  | "erasing"
+  | "programming"
+  | "verifying"
+  | "rebooting"
+  | "done";
+
+export type FlashProgress = {
+  phase: FlashPhase;
+  bytes_written: number;
+  bytes_total: number;
+  board: BoardInfo | null;
+};
+
+/** Flash an .apj/.px4 file; resolves once the board has rebooted into it. */
+export async function flashFirmware(path: string, port: string, rebootVehicle: boolean): Promise<BoardInfo> {
+  return invoke<BoardInfo>("firmware_flash", { path, port, rebootVehicle });
+}
+
+export async function cancelFlash(): Promise<void> {
+  await invoke("firmware_flash_cancel");
+}
+
+export async function subscribeFlashProgress(cb: (progress: FlashProgress) => void): Promise<UnlistenFn> {
+  return listen<FlashProgress>("firmware://progress", (event) => cb(event.payload));
+}
@@ -1,22 +1,33 @@
 import { useEffect, useState, useCallback } from "react";
+import { appDataDir, join } from "@tauri-apps/api/path";
 import {
-  cancelMissionTransfer,
   clearMissionPlan,
+  listPlanSnapshots,
+  restorePlanSnapshot,
+  snapshotPlan,
   downloadMissionPlan,
+  subscribeMissionChangedExternally,
   subscribeMissionState,
   setCurrentMissionItem,
   subscribeMissionProgress,
   uploadMissionPlan,
+  validateAgainstFence,
+  validateArduPilotAcceptance,
   validateMissionPlan,
-  verifyMissionRoundtrip,
+  verifyMissionRoundtripDetailed,
+  planDiffIsEmpty,
+  type PlanDiff,
   type HomePosition,
   type MissionState,
   type MissionIssue,
   type MissionItem,
   type MissionPlan,
   type MissionType,
+  type PlanSnapshot,
   type TransferProgress,
 } from "../mission";
+import { checkPlanAirspace } from "../airspace";
+import { cancelOperation } from "../operations";
 import type { Telemetry } from "../telemetry";
 import { toast } from "sonner";
 
@@ -26,7 +37,29 @@ function asErrorMessage(error: unknown): string {
   return "unexpected error";
 }
 
-type HomeSource = "vehicle" | "user" | "download" | null;
+/** First mismatch in a read-back diff, phrased for a toast. */
+function describePlanDiff(diff: PlanDiff): string {
+  if (diff.expected_count !== null) {
+    return `Sent ${diff.expected_count} items, vehicle has ${diff.actual_count}`;
+  }
+  const plan = diff.plan[0];
+  if (plan) return `${plan.field}: sent ${plan.expected}, got ${plan.actual}`;
+  const item = diff.items[0];
+  if (!item) return "";
+  const fields = item.fields.map((f) => `${f.field} ${f.expected} → ${f.actual}`).join(", ");
+  const more = diff.items.length > 1 ? ` (+${diff.items.length - 1} more items)` : "";
+  return `Item ${item.seq}: ${fields}${more}`;
+}
+
+type HomeSource = "vehicle" | "user" | "download" | "history" | null;
+
+/** Quiet time after the last edit before the plan is snapshotted. */
+const SNAPSHOT_DEBOUNCE_MS = 2000;
+
+/** Plan history directory for one plan type, under the app data dir. */
+async function planHistoryDir(missionType: MissionType): Promise<string> {
+  return join(await appDataDir(), "plans", missionType);
+}
 
 function createWaypoint(seq: number, latDeg: number, lonDeg: number, altitudeM: number): MissionItem {
   return {
@@ -59,9 +92,15 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
   const [homeLonInput, setHomeLonInput] = useState("");
   const [homeAltInput, setHomeAltInput] = useState("");
   const [issues, setIssues] = useState<MissionIssue[]>([]);
+  /** Last fence downloaded or uploaded; missions are checked against it. */
+  const [fencePlan, setFencePlan] = useState<MissionPlan | null>(null);
   const [progress, setProgress] = useState<TransferProgress | null>(null);
   const [missionState, setMissionState] = useState<MissionState | null>(null);
   const [roundtripStatus, setRoundtripStatus] = useState<string>("");
+  /** Operation id of the upload, download or verify in progress. */
+  const [operationId, setOperationId] = useState<number | null>(null);
+  /** Autosaved versions of the plan being edited, oldest first. */
+  const [snapshots, setSnapshots] = useState<PlanSnapshot[]>([]);
 
   const transferActive =
     progress?.phase === "request_count" ||
@@ -103,6 +142,60 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
     };
   }
 
+  // Load the plan history when switching plan type
+  useEffect(() => {
+    let cancelled = false;
+    (async () => {
+      try {
+        const list = await listPlanSnapshots(await planHistoryDir(missionType));
+        if (!cancelled) setSnapshots(list);
+      } catch {
+        if (!cancelled) setSnapshots([]);
+      }
+    })();
+    return () => {
+      cancelled = true;
+    };
+  }, [missionType]);
+
+  // Autosave a snapshot once edits settle; unchanged plans are deduplicated
+  useEffect(() => {
+    if (items.length === 0) return;
+    const plan = buildPlan();
+    const timer = setTimeout(async () => {
+      try {
+        const dir = await planHistoryDir(plan.mission_type);
+        const snapshot = await snapshotPlan(dir, plan);
+        if (snapshot) setSnapshots(await listPlanSnapshots(dir));
+      } catch (err) {
+        console.warn("plan snapshot failed", err);
+      }
+    }, SNAPSHOT_DEBOUNCE_MS);
+    return () => clearTimeout(timer);
+  }, [items, homePosition, missionType]);
+
+  const restoreSnapshot = useCallback(
+    async (id: number) => {
+      try {
+        const plan = await restorePlanSnapshot(await planHistoryDir(missionType), id);
+        setItems(plan.items);
+        if (plan.home) {
+          setHomePosition(plan.home);
+          setHomeLatInput(plan.home.latitude_deg.toFixed(6));
+          setHomeLonInput(plan.home.longitude_deg.toFixed(6));
+          setHomeAltInput(plan.home.altitude_m.toFixed(2));
+          setHomeSource("history");
+        }
+        setSelectedSeq(null);
+        setIssues([]);
+        toast.success("Plan restored", { description: `${plan.items.length} waypoints` });
+      } catch (err) {
+        toast.error("Restore failed", { description: asErrorMessage(err) });
+      }
+    },
+    [missionType]
+  );
+
   const addWaypoint = useCallback(() => {
     setItems((prev) => {
       const seq = prev.length;
@@ -224,7 +317,9 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
     (index: number, field: "x" | "y", valueDeg: number) => {
       const encoded = Math.round(valueDeg * 1e7);
       setItems((prev) =>
-        prev.map((item, i) => (i === index ? { ...item, [field]: encoded } : item))
+        prev.map((item, i) =>
+          i === index ? { ...item, [field]: encoded, downloaded_as_float: false } : item
+        )
       );
     },
     []
@@ -235,7 +330,12 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
       setItems((prev) =>
         prev.map((item) =>
           item.seq === seq
-            ? { ...item, x: Math.round(latDeg * 1e7), y: Math.round(lonDeg * 1e7) }
+            ? {
+                ...item,
+                x: Math.round(latDeg * 1e7),
+                y: Math.round(lonDeg * 1e7),
+                downloaded_as_float: false,
+              }
             : item
         )
       );
@@ -245,22 +345,37 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
 
   const validate = useCallback(async () => {
     try {
-      const result = await validateMissionPlan(buildPlan());
+      const plan = buildPlan();
+      const result = await validateMissionPlan(plan);
+      result.push(...(await validateArduPilotAcceptance(plan)));
+      if (missionType === "mission" && fencePlan) {
+        result.push(...(await validateAgainstFence(plan, fencePlan)));
+      }
+      if (missionType === "mission") {
+        result.push(...(await checkPlanAirspace(plan)));
+      }
       setIssues(result);
       if (result.length === 0) toast.success("Plan valid");
     } catch (err) {
       toast.error("Validation failed", { description: asErrorMessage(err) });
     }
-  }, [missionType, homePosition, items]);
+  }, [missionType, homePosition, items, fencePlan]);
 
   const upload = useCallback(async () => {
     if (!connected) { toast.error("Connect to vehicle before upload"); return; }
     setProgress(null);
     try {
-      await uploadMissionPlan(buildPlan());
-      toast.success("Mission uploaded", { description: `${items.length} waypoints` });
+      const plan = buildPlan();
+      const isFence = missionType === "fence";
+      await uploadMissionPlan(plan, false, false, setOperationId, isFence);
+      if (isFence) setFencePlan(plan);
+      toast.success(isFence ? "Fence uploaded and enabled" : "Mission uploaded", {
+        description: `${items.length} waypoints`,
+      });
     } catch (err) {
       toast.error("Upload failed", { description: asErrorMessage(err) });
+    } finally {
+      setOperationId(null);
     }
   }, [connected, missionType, homePosition, items]);
 
@@ -268,8 +383,9 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
     if (!connected) { toast.error("Connect to vehicle before download"); return; }
     setProgress(null);
     try {
-      const plan = await downloadMissionPlan(missionType);
+      const plan = await downloadMissionPlan(missionType, setOperationId);
       setItems(plan.items);
+      if (missionType === "fence") setFencePlan(plan);
       if (plan.home) {
         setHomePosition(plan.home);
         setHomeLatInput(plan.home.latitude_deg.toFixed(6));
@@ -283,9 +399,28 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
       toast.success("Mission downloaded", { description: `${plan.items.length} waypoints` });
     } catch (err) {
       toast.error("Download failed", { description: asErrorMessage(err) });
+    } finally {
+      setOperationId(null);
     }
   }, [connected, missionType]);
 
+  // Offer to download again when the vehicle's plan changed elsewhere
+  useEffect(() => {
+    let stop: (() => void) | null = null;
+    (async () => {
+      stop = await subscribeMissionChangedExternally((change) => {
+        const shown = change.mission_type === missionType;
+        toast.warning(`The vehicle's ${change.mission_type} plan was changed elsewhere`, {
+          description: "Another GCS or the RC changed it; the plan shown here may be out of date.",
+          action: shown ? { label: "Download", onClick: () => void download() } : undefined,
+        });
+      });
+    })();
+    return () => {
+      stop?.();
+    };
+  }, [missionType, download]);
+
   const clear = useCallback(async () => {
     if (!connected) { toast.error("Connect to vehicle before clear"); return; }
     setProgress(null);
@@ -311,24 +446,27 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
     setProgress(null);
     setRoundtripStatus("Verifying...");
     try {
-      const ok = await verifyMissionRoundtrip(buildPlan());
+      const diff = await verifyMissionRoundtripDetailed(buildPlan(), setOperationId);
+      const ok = planDiffIsEmpty(diff);
       setRoundtripStatus(ok ? "Roundtrip: pass" : "Roundtrip: fail");
       if (ok) toast.success("Roundtrip verified");
-      else toast.warning("Roundtrip mismatch");
+      else toast.warning("Roundtrip mismatch", { description: describePlanDiff(diff) });
     } catch (err) {
       setRoundtripStatus("Verify failed");
       toast.error("Verify failed", { description: asErrorMessage(err) });
+    } finally {
+      setOperationId(null);
     }
   }, [connected, missionType, homePosition, items]);
 
   const cancel = useCallback(async () => {
-    if (!connected) return;
+    if (operationId === null) return;
     try {
-      await cancelMissionTransfer();
+      await cancelOperation(operationId);
     } catch (err) {
       toast.error("Cancel failed", { description: asErrorMessage(err) });
     }
-  }, [connected]);
+  }, [operationId]);
 
   const setCurrent = useCallback(async () => {
     if (!connected) { toast.error("Connect first"); return; }
@@ -348,7 +486,9 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
       toast.error("Vehicle position unavailable");
       return;
     }
-    const altitude = typeof telemetry.altitude_m === "number" && !Number.isNaN(telemetry.altitude_m) ? telemetry.altitude_m : 0;
+    // Home altitude is AMSL; altitude_m is relative to the vehicle's own home
+    const amsl = telemetry.altitude_amsl_m;
+    const altitude = typeof amsl === "number" && !Number.isNaN(amsl) ? amsl : 0;
     setHomePosition({ latitude_deg: lat, longitude_deg: lon, altitude_m: altitude });
     setHomeSource("vehicle");
     setHomeLatInput(lat.toFixed(6));
@@ -402,6 +542,7 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
     transferActive,
     missionState,
     roundtripStatus,
+    snapshots,
     // Actions
     addWaypoint,
     addWaypointAt,
@@ -423,5 +564,6 @@ export function useMission(connected: boolean, telemetry: Telemetry, vehicleHome
     updateHomeFromVehicle,
     setArbitraryHome,
     setHomeFromMap,
+    restoreSnapshot,
   };
 }
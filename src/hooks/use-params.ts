@@ -5,11 +5,15 @@ import {
   parseParamFile,
   formatParamFile,
   subscribeParamStore,
+  subscribeParamUpdates,
   subscribeParamProgress,
+  applyParamUpdates,
   type Param,
   type ParamStore,
   type ParamProgress,
+  type ParamUpdate,
 } from "../params";
+import { cancelOperation } from "../operations";
 import { fetchParamMetadata, type ParamMetadataMap } from "../param-metadata";
 import { save, open } from "@tauri-apps/plugin-dialog";
 import { readTextFile, writeTextFile } from "@tauri-apps/plugin-fs";
@@ -24,25 +28,40 @@ function asErrorMessage(error: unknown): string {
 export function useParams(connected: boolean, vehicleType?: string) {
   const [store, setStore] = useState<ParamStore | null>(null);
   const [progress, setProgress] = useState<ParamProgress | null>(null);
+  const [downloadId, setDownloadId] = useState<number | null>(null);
   const [search, setSearch] = useState("");
   const [editingParam, setEditingParam] = useState<string | null>(null);
   const [editValue, setEditValue] = useState("");
   const [metadata, setMetadata] = useState<ParamMetadataMap | null>(null);
   const [metadataLoading, setMetadataLoading] = useState(false);
   const lastFetchedType = useRef<string | undefined>();
+  const pendingUpdates = useRef(new Map<string, ParamUpdate>());
 
   // Subscribe to param events
   useEffect(() => {
     let stopStore: (() => void) | null = null;
+    let stopUpdates: (() => void) | null = null;
     let stopProgress: (() => void) | null = null;
 
+    // Updates arrive per parameter; apply them at most once per frame
+    const flushUpdates = () => {
+      const updates = [...pendingUpdates.current.values()];
+      pendingUpdates.current.clear();
+      setStore((prev) => applyParamUpdates(prev, updates));
+    };
+
     (async () => {
       stopStore = await subscribeParamStore(setStore);
+      stopUpdates = await subscribeParamUpdates((update) => {
+        if (pendingUpdates.current.size === 0) requestAnimationFrame(flushUpdates);
+        pendingUpdates.current.set(update.name, update);
+      });
       stopProgress = await subscribeParamProgress(setProgress);
     })();
 
     return () => {
       stopStore?.();
+      stopUpdates?.();
       stopProgress?.();
     };
   }, []);
@@ -52,6 +71,7 @@ export function useParams(connected: boolean, vehicleType?: string) {
     if (!connected) {
       setStore(null);
       setProgress(null);
+      setDownloadId(null);
       setEditingParam(null);
       setMetadata(null);
       lastFetchedType.current = undefined;
@@ -123,15 +143,26 @@ export function useParams(connected: boolean, vehicleType?: string) {
       return;
     }
     try {
-      const result = await downloadAllParams();
+      const count = await downloadAllParams(setDownloadId);
       toast.success("Parameters downloaded", {
-        description: `${Object.keys(result.params).length} parameters`,
+        description: `${count} parameters`,
       });
     } catch (err) {
       toast.error("Parameter download failed", { description: asErrorMessage(err) });
+    } finally {
+      setDownloadId(null);
     }
   }, [connected]);
 
+  const cancelDownload = useCallback(async () => {
+    if (downloadId === null) return;
+    try {
+      await cancelOperation(downloadId);
+    } catch (err) {
+      toast.error("Failed to cancel download", { description: asErrorMessage(err) });
+    }
+  }, [downloadId]);
+
   const write = useCallback(
     async (name: string, value: number) => {
       if (!connected) {
@@ -206,6 +237,8 @@ export function useParams(connected: boolean, vehicleType?: string) {
     filteredParams,
     groupedParams,
     download,
+    downloadInProgress: downloadId !== null,
+    cancelDownload,
     write,
     saveToFile,
     loadFromFile,
@@ -2,20 +2,34 @@ import { useEffect, useRef, useState, useCallback, useMemo } from "react";
 import {
   armVehicle,
   connectLink,
+  DEFAULT_SERIAL_OPTIONS,
   disarmVehicle,
+  confirmExit,
   disconnectLink,
   getAvailableModes,
+  getShutdownWarning,
+  isAddressInUseError,
+  isDefaultSerialOptions,
+  isDisarmRefusedError,
+  isModeRefusedError,
   listSerialPorts,
   setFlightMode,
+  subscribeBatteryHealth,
+  subscribeCloseBlocked,
   subscribeLinkState,
   subscribeHomePosition,
+  subscribeLinkPeer,
+  subscribeRebooted,
   subscribeTelemetry,
   subscribeVehicleState,
+  subscribeVibration,
   vehicleGuidedGoto,
   vehicleTakeoff,
+  type BatteryAlert,
   type ConnectRequest,
   type FlightModeEntry,
   type LinkState,
+  type SerialOptions,
   type Telemetry,
   type VehicleState,
 } from "../telemetry";
@@ -38,10 +52,14 @@ export function useVehicle() {
   const [isConnecting, setIsConnecting] = useState(false);
 
   // Connection form state
-  const [mode, setMode] = useState<"udp" | "serial">("udp");
+  const [mode, setMode] = useState<"udp" | "tcp" | "serial">("udp");
   const [udpBind, setUdpBind] = useState("0.0.0.0:14550");
+  const [tcpAddr, setTcpAddr] = useState("");
+  const [tcpTls, setTcpTls] = useState(true);
+  const [tlsPin, setTlsPin] = useState("");
   const [serialPort, setSerialPort] = useState("");
   const [baud, setBaud] = useState(57600);
+  const [serialOptions, setSerialOptions] = useState<SerialOptions>(DEFAULT_SERIAL_OPTIONS);
   const [serialPorts, setSerialPorts] = useState<string[]>([]);
   const [takeoffAlt, setTakeoffAlt] = useState("10");
   const [followVehicle, setFollowVehicle] = useState(true);
@@ -87,12 +105,71 @@ export function useVehicle() {
     let stopLinkState: (() => void) | null = null;
     let stopHome: (() => void) | null = null;
     let stopVehicleState: (() => void) | null = null;
+    let stopCloseBlocked: (() => void) | null = null;
+    let stopRebooted: (() => void) | null = null;
+    let stopLinkPeer: (() => void) | null = null;
+    let stopBatteryHealth: (() => void) | null = null;
+    let stopVibration: (() => void) | null = null;
 
     (async () => {
       stopTelemetry = await subscribeTelemetry(onTelemetryEvent);
       stopLinkState = await subscribeLinkState(setLinkState);
       stopHome = await subscribeHomePosition(setHomePosition);
       stopVehicleState = await subscribeVehicleState(setVehicleState);
+      stopRebooted = await subscribeRebooted(() => {
+        setHomePosition(null);
+        toast.warning("Vehicle rebooted", {
+          description: "Parameters and onboard plans need downloading again",
+        });
+      });
+      let lastPeer: string | null = null;
+      stopLinkPeer = await subscribeLinkPeer((peer) => {
+        if (lastPeer !== null && peer !== null) {
+          toast.info("Vehicle address changed", { description: `Now replying to ${peer}` });
+        }
+        lastPeer = peer;
+      });
+      let raisedAlerts: BatteryAlert[] = [];
+      stopBatteryHealth = await subscribeBatteryHealth((health) => {
+        for (const alert of health.alerts) {
+          if (raisedAlerts.includes(alert)) continue;
+          const cell = health.weakest_cell !== null ? `Cell ${health.weakest_cell + 1}` : "A cell";
+          const description = {
+            imbalance: `Cells ${health.imbalance_v?.toFixed(2)} V apart`,
+            sag: `${cell} sags ${health.sag_v?.toFixed(2)} V under load`,
+            low_cell: `${cell} at ${health.weakest_cell_v?.toFixed(2)} V`,
+          }[alert];
+          toast.warning("Battery pack warning", { description });
+        }
+        raisedAlerts = health.alerts;
+      });
+      let clippingAccels: number[] = [];
+      let flightInProgress = false;
+      stopVibration = await subscribeVibration((vibration) => {
+        for (const alert of vibration.alerts) {
+          if (clippingAccels.includes(alert.accel)) continue;
+          const axis = alert.axis ? `, strongest on ${alert.axis.toUpperCase()}` : "";
+          toast.warning("Accelerometer clipping increasing", {
+            description: `IMU ${alert.accel + 1} at ${alert.clips_per_s.toFixed(1)} clips/s${axis}; check props and motors`,
+          });
+        }
+        clippingAccels = vibration.alerts.map((alert) => alert.accel);
+
+        const flight = vibration.flight;
+        if (flightInProgress && flight && !flight.in_progress) {
+          const clips = flight.clips.reduce((sum, n) => sum + n, 0);
+          const levels = flight.max_level_mps2.map((level) => level.toFixed(0)).join("/");
+          const description = `Max vibration ${levels} m/s² (X/Y/Z), ${clips} clips`;
+          if (clips > 0) toast.warning("Flight vibration summary", { description });
+          else toast.info("Flight vibration summary", { description });
+        }
+        flightInProgress = flight?.in_progress ?? false;
+      });
+      stopCloseBlocked = await subscribeCloseBlocked((warning) => {
+        if (window.confirm(`${warning.message}. Close anyway?`)) {
+          confirmExit().catch(() => {});
+        }
+      });
     })();
 
     return () => {
@@ -100,6 +177,11 @@ export function useVehicle() {
       stopLinkState?.();
       stopHome?.();
       stopVehicleState?.();
+      stopCloseBlocked?.();
+      stopRebooted?.();
+      stopLinkPeer?.();
+      stopBatteryHealth?.();
+      stopVibration?.();
       if (rafId.current) cancelAnimationFrame(rafId.current);
     };
   }, [onTelemetryEvent]);
@@ -119,22 +201,52 @@ export function useVehicle() {
     cancelledRef.current = false;
     setConnectionError(null);
     setIsConnecting(true);
+    const pin = tlsPin.trim();
+    const shutdown_notice = "GCS disconnecting";
     const request: ConnectRequest =
       mode === "udp"
-        ? { endpoint: { kind: "udp", bind_addr: udpBind } }
-        : { endpoint: { kind: "serial", port: serialPort, baud } };
+        ? { endpoint: { kind: "udp", bind_addr: udpBind }, shutdown_notice }
+        : mode === "tcp"
+        ? {
+            endpoint: {
+              kind: "tcp",
+              addr: tcpAddr,
+              ...(tcpTls ? { tls: pin ? { pinned_sha256: [pin] } : {} } : {}),
+            },
+            shutdown_notice,
+          }
+        : {
+            endpoint: {
+              kind: "serial",
+              port: serialPort,
+              baud,
+              // Leave the default 8N1 open path alone unless something was changed
+              ...(isDefaultSerialOptions(serialOptions) ? {} : { options: serialOptions }),
+            },
+            shutdown_notice,
+          };
     try {
-      await connectLink(request);
+      const session = await connectLink(request);
+      if (session.reused) {
+        setVehicleState(session.vehicle);
+        toast.info("Already connected");
+      }
     } catch (err) {
       if (!cancelledRef.current) {
         const msg = asErrorMessage(err);
         setConnectionError(msg);
-        toast.error("Connection failed", { description: msg });
+        if (isAddressInUseError(msg)) {
+          toast.error("Port already in use", {
+            description: `${msg}. Close other ground stations using it and try again.`,
+          });
+        } else {
+          toast.error("Connection failed", { description: msg });
+        }
       }
     } finally {
       setIsConnecting(false);
     }
-  }, [mode, udpBind, serialPort, baud]);
+  }, [mode, udpBind, tcpAddr, tcpTls, tlsPin, serialPort, baud, serialOptions]);
 
   const cancelConnect = useCallback(async () => {
     cancelledRef.current = true;
@@ -149,7 +261,9 @@ export function useVehicle() {
 
   const disconnect = useCallback(async () => {
     try {
-      await disconnectLink();
+      const warning = await getShutdownWarning();
+      if (warning && !window.confirm(`${warning.message}. Disconnect anyway?`)) return;
+      await disconnectLink(warning !== null);
     } catch (err) {
       toast.error("Disconnect failed", { description: asErrorMessage(err) });
     }
@@ -185,7 +299,22 @@ export function useVehicle() {
         await disarmVehicle(force);
         toast.success("Vehicle disarmed");
       } catch (err) {
-        toast.error("Failed to disarm", { description: asErrorMessage(err) });
+        const msg = asErrorMessage(err);
+        if (isDisarmRefusedError(msg)) {
+          toast.error("Disarm refused", {
+            description: "The vehicle is airborne; disarming stops the motors in flight.",
+            action: {
+              label: "Disarm anyway",
+              onClick: () => {
+                disarmVehicle(true).catch((err) =>
+                  toast.error("Failed to disarm", { description: asErrorMessage(err) })
+                );
+              },
+            },
+          });
+        } else {
+          toast.error("Failed to disarm", { description: msg });
+        }
       }
     },
     [connected]
@@ -197,7 +326,22 @@ export function useVehicle() {
       try {
         await setFlightMode(customMode);
       } catch (err) {
-        toast.error("Failed to set mode", { description: asErrorMessage(err) });
+        const msg = asErrorMessage(err);
+        if (isModeRefusedError(msg)) {
+          toast.error("Mode change refused", {
+            description: msg,
+            action: {
+              label: "Switch anyway",
+              onClick: () => {
+                setFlightMode(customMode, true).catch((err) =>
+                  toast.error("Failed to set mode", { description: asErrorMessage(err) })
+                );
+              },
+            },
+          });
+        } else {
+          toast.error("Failed to set mode", { description: msg });
+        }
       }
     },
     [connected]
@@ -251,8 +395,10 @@ export function useVehicle() {
     // Connection form
     connectionMode: mode, setConnectionMode: setMode,
     udpBind, setUdpBind,
+    tcpAddr, setTcpAddr, tcpTls, setTcpTls, tlsPin, setTlsPin,
     serialPort, setSerialPort,
     baud, setBaud,
+    serialOptions, setSerialOptions,
     serialPorts,
     takeoffAlt, setTakeoffAlt,
     followVehicle, setFollowVehicle,
@@ -0,0 +1,52 @@
+import { invoke } from "@tauri-apps/api/core";
+import { runOperation } from "./operations";
+
+export async function getMessageTemplate(messageName: string): Promise<string> {
+  return invoke<string>("mavlink_message_template", { messageName });
+}
+
+export async function sendRawMessage(
+  messageName: string,
+  fieldsJson: string,
+  responseName?: string,
+): Promise<string | null> {
+  return invoke<string | null>("mavlink_send_raw", {
+    messageName,
+    fieldsJson,
+    responseName: responseName ?? null,
+  });
+}
+
+export type RateBenchmarkOptions = {
+  window_ms: number;
+  /** Rates asked for with SET_MESSAGE_INTERVAL, by message name. */
+  expected_hz?: Record<string, number>;
+};
+
+export type MessageRate = {
+  message: string;
+  count: number;
+  rate_hz: number;
+  /** Standard deviation of the intervals between arrivals. */
+  jitter_ms: number | null;
+  max_gap_ms: number;
+  /** Intervals over twice the expected (or mean) one. */
+  gaps: number;
+  expected_hz: number | null;
+  /** Whether the rate is within 20% of `expected_hz`. */
+  honored: boolean | null;
+};
+
+export type RateReport = {
+  window_ms: number;
+  total: number;
+  messages: MessageRate[];
+};
+
+/** Listen for the window and report how often each autopilot message arrived. */
+export async function benchmarkMessageRates(
+  options: RateBenchmarkOptions,
+  onStart?: (id: number) => void,
+): Promise<RateReport> {
+  return runOperation<RateReport>("mavlink_benchmark_rates", { options }, onStart);
+}
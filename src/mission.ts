@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { runOperation } from "./operations";
 
 export type MissionType = "mission" | "fence" | "rally";
 
@@ -24,18 +25,27 @@ export type MissionItem = {
   x: number;
   y: number;
   z: number;
+  label?: string;
+  notes?: string;
+  /** Position came from a float MISSION_ITEM download; cleared when moved. */
+  downloaded_as_float?: boolean;
 };
 
+export type AltitudeDatum = "amsl" | "ellipsoid";
+
 export type HomePosition = {
   latitude_deg: number;
   longitude_deg: number;
+  /** Absolute altitude in `altitude_datum` (AMSL when omitted). */
   altitude_m: number;
+  altitude_datum?: AltitudeDatum;
 };
 
 export type MissionPlan = {
   mission_type: MissionType;
   home: HomePosition | null;
   items: MissionItem[];
+  metadata?: Record<string, string>;
 };
 
 export type MissionIssue = {
@@ -67,40 +77,424 @@ export type TransferProgress = {
 export type MissionState = {
   current_seq: number;
   total_items: number;
+  /** Opaque ids of the stored plans; 0 when the autopilot doesn't report them. */
+  mission_id: number;
+  fence_id: number;
+  rally_points_id: number;
+  mission_state: MissionRunState;
+};
+
+export type MissionRunState =
+  | "unknown"
+  | "no_mission"
+  | "not_started"
+  | "active"
+  | "paused"
+  | "complete";
+
+export type PlanSnapshot = {
+  id: number;
+  hash: string;
+  created_at_ms: number;
+  label: string | null;
+};
+
+export async function snapshotPlan(
+  projectDir: string,
+  plan: MissionPlan,
+  label?: string,
+): Promise<PlanSnapshot | null> {
+  return invoke<PlanSnapshot | null>("plan_history_snapshot", { projectDir, plan, label: label ?? null });
+}
+
+export async function listPlanSnapshots(projectDir: string): Promise<PlanSnapshot[]> {
+  return invoke<PlanSnapshot[]>("plan_history_list", { projectDir });
+}
+
+export async function restorePlanSnapshot(projectDir: string, id: number): Promise<MissionPlan> {
+  return invoke<MissionPlan>("plan_history_restore", { projectDir, id });
+}
+
+export type StructureScanParams = {
+  footprint: [number, number][];
+  bottom_alt_m: number;
+  top_alt_m: number;
+  standoff_m: number;
+  layer_spacing_m: number;
+  max_point_spacing_m: number;
+  gimbal_pitch_deg: number[];
+  /** Planned start (unix seconds); orbits then start on the sunlit side. */
+  start_unix_s?: number | null;
+};
+
+export async function generateStructureScan(params: StructureScanParams): Promise<MissionPlan> {
+  return invoke<MissionPlan>("mission_generate_structure_scan", { params });
+}
+
+export type SearchPattern =
+  | { kind: "expanding_square" }
+  | { kind: "sector_search" }
+  | { kind: "creeping_line"; leg_length_m: number };
+
+export type SearchPatternParams = {
+  pattern: SearchPattern;
+  /** Datum, or commence search point for the creeping line, as [lat, lon]. */
+  datum: [number, number];
+  track_deg: number;
+  /** Track spacing; the leg length of the sector search. */
+  spacing_m: number;
+  legs: number;
+  alt_m: number;
+  left_turns?: boolean;
+  /** Search ground speed; the profile's cruise speed when unset. */
+  speed_mps?: number | null;
+};
+
+export async function generateSearchPattern(
+  params: SearchPatternParams,
+  profile: VehicleProfile,
+): Promise<MissionPlan> {
+  return invoke<MissionPlan>("mission_generate_search_pattern", { params, profile });
+}
+
+export type SunPosition = { azimuth_deg: number; elevation_deg: number };
+
+/** Sun events of one local solar day, as unix seconds; null when the sun never gets there. */
+export type SunTimes = {
+  sunrise: number | null;
+  morning_golden_hour_end: number | null;
+  solar_noon: number;
+  evening_golden_hour_start: number | null;
+  sunset: number | null;
+};
+
+export async function sunPosition(lat: number, lon: number, unixS: number): Promise<SunPosition> {
+  return invoke<SunPosition>("mission_sun_position", { lat, lon, unixS });
+}
+
+export async function sunTimes(lat: number, lon: number, unixS: number): Promise<SunTimes> {
+  return invoke<SunTimes>("mission_sun_times", { lat, lon, unixS });
+}
+
+/** Low-sun and below-horizon warnings for a survey flown from `startUnixS`. */
+export async function sunWarnings(
+  lat: number,
+  lon: number,
+  startUnixS: number,
+  durationS: number,
+): Promise<MissionIssue[]> {
+  return invoke<MissionIssue[]>("mission_sun_warnings", { lat, lon, startUnixS, durationS });
+}
+
+export type VehicleProfile = {
+  cruise_speed_mps: number;
+  climb_rate_mps: number;
+  descent_rate_mps: number;
 };
 
+export type SimSample = {
+  t_s: number;
+  latitude_deg: number;
+  longitude_deg: number;
+  altitude_m: number;
+  heading_deg: number;
+  pitch_deg: number;
+  ground_speed_mps: number;
+  climb_rate_mps: number;
+  seq: number | null;
+};
+
+export type SimTimeline = {
+  /** One sample per second. */
+  samples: SimSample[];
+  duration_s: number;
+  distance_m: number;
+  /** Item the wind stops the vehicle from reaching; the timeline ends before it. */
+  blocked_at: number | null;
+};
+
+/** Per-second preview flight, through the current wind when `withWind` is set. */
+export async function simulateMission(
+  plan: MissionPlan,
+  profile: VehicleProfile,
+  withWind: boolean,
+): Promise<SimTimeline> {
+  return invoke<SimTimeline>("mission_simulate", { plan, profile, withWind });
+}
+
+export type Separation = { horizontal_m: number; vertical_m: number };
+
+export type PlannedFlight = {
+  name: string;
+  plan: MissionPlan;
+  profile: VehicleProfile;
+  /** Launch time relative to the start of the operation, seconds. */
+  start_offset_s?: number;
+};
+
+export type Conflict = {
+  /** Indices into the checked flights. */
+  flights: [number, number];
+  start_s: number;
+  end_s: number;
+  closest_s: number;
+  horizontal_m: number;
+  vertical_m: number;
+  seqs: [number | null, number | null];
+  latitude_deg: number;
+  longitude_deg: number;
+};
+
+/** Times and places where two of the flights lose separation. */
+export async function checkMissionConflicts(
+  flights: PlannedFlight[],
+  separation: Separation,
+  withWind: boolean,
+): Promise<Conflict[]> {
+  return invoke<Conflict[]>("mission_check_conflicts", { flights, separation, withWind });
+}
+
+export type VtolProfile = {
+  transition_airspeed_mps: number;
+  transition_accel_mps2: number;
+  back_transition_decel_mps2: number;
+  max_climb_near_transition_m: number;
+};
+
+export type VtolWrapParams = {
+  launch: [number, number];
+  takeoff_alt_m: number;
+  landing: [number, number] | null;
+};
+
+export async function wrapVtolBlock(plan: MissionPlan, params: VtolWrapParams): Promise<MissionPlan> {
+  return invoke<MissionPlan>("mission_wrap_vtol_block", { plan, params });
+}
+
+export type Simplified = {
+  plan: MissionPlan;
+  removed: number[];
+  reanchored: number[];
+};
+
+/**
+ * Drop waypoints within `toleranceM` of the straight track between their
+ * neighbours. DO items of removed waypoints move to the nearest kept one.
+ */
+export async function simplifyPlan(plan: MissionPlan, toleranceM: number): Promise<Simplified> {
+  return invoke<Simplified>("mission_simplify", { plan, toleranceM });
+}
+
+export async function snapToGrid(plan: MissionPlan, spacingM: number): Promise<MissionPlan> {
+  return invoke<MissionPlan>("mission_snap_to_grid", { plan, spacingM });
+}
+
+export async function snapAltitudes(plan: MissionPlan, stepM: number): Promise<MissionPlan> {
+  return invoke<MissionPlan>("mission_snap_altitudes", { plan, stepM });
+}
+
+/**
+ * Sign `plan` with a base64 Ed25519 secret key and, when `recipients` (age
+ * public keys) are given, encrypt it to them. Returns the file contents.
+ */
+export async function sealPlan(plan: MissionPlan, signingKey: string, recipients: string[]): Promise<string> {
+  return invoke<string>("mission_seal_plan", { plan, signingKey, recipients });
+}
+
+/** Verify a sealed plan file against trusted signer keys and decrypt it if needed. */
+export async function openSealedPlan(
+  contents: string,
+  trustedKeys: string[],
+  identity: string | null,
+): Promise<MissionPlan> {
+  return invoke<MissionPlan>("mission_open_sealed_plan", { contents, trustedKeys, identity });
+}
+
+export async function validateVtolTransitions(plan: MissionPlan, profile: VtolProfile): Promise<MissionIssue[]> {
+  return invoke<MissionIssue[]>("mission_validate_vtol", { plan, profile });
+}
+
+/**
+ * Check items and the legs between them against a fence plan: inside every
+ * inclusion zone, outside every exclusion zone. Messages carry distances.
+ */
+export async function validateAgainstFence(plan: MissionPlan, fence: MissionPlan): Promise<MissionIssue[]> {
+  return invoke<MissionIssue[]>("mission_validate_fence", { plan, fence });
+}
+
 export async function validateMissionPlan(plan: MissionPlan): Promise<MissionIssue[]> {
   return invoke<MissionIssue[]>("mission_validate_plan", { plan });
 }
 
-export async function uploadMissionPlan(plan: MissionPlan): Promise<void> {
-  await invoke("mission_upload_plan", { plan });
+export async function validateArduPilotAcceptance(plan: MissionPlan): Promise<MissionIssue[]> {
+  return invoke<MissionIssue[]>("mission_validate_ardupilot", { plan });
+}
+
+export async function auditImportedCoordinates(coords: [number, number][]): Promise<MissionIssue[]> {
+  return invoke<MissionIssue[]>("mission_audit_coordinates", { coords });
+}
+
+export type FieldMismatch = {
+  field: string;
+  expected: string;
+  actual: string;
+};
+
+export type ItemDiff = {
+  seq: number;
+  fields: FieldMismatch[];
+};
+
+export type PlanDiff = {
+  plan: FieldMismatch[];
+  items: ItemDiff[];
+  expected_count: number | null;
+  actual_count: number | null;
+};
+
+export function planDiffIsEmpty(diff: PlanDiff): boolean {
+  return diff.plan.length === 0 && diff.items.length === 0 && diff.expected_count === null;
+}
+
+/**
+ * Returns the read-back diff when `verify` is set, otherwise null.
+ * `enableFence` enables an uploaded fence and fails if the vehicle doesn't confirm it.
+ * `onStart` gets the operation id for `cancelOperation`.
+ */
+export async function uploadMissionPlan(
+  plan: MissionPlan,
+  allowInflightUpdate = false,
+  verify = false,
+  onStart?: (id: number) => void,
+  enableFence = false,
+): Promise<PlanDiff | null> {
+  return runOperation<PlanDiff | null>(
+    "mission_upload_plan",
+    { plan, allowInflightUpdate, verify, enableFence },
+    onStart,
+  );
+}
+
+export type FenceBreachType = "none" | "min_altitude" | "max_altitude" | "boundary";
+
+export type FenceStatus = {
+  breached: boolean;
+  breach_count: number;
+  breach_type: FenceBreachType;
+  breach_time_ms: number;
+};
+
+/** Enabling resolves with the FENCE_STATUS that confirmed it; disabling with null. */
+export async function enableFence(enable: boolean): Promise<FenceStatus | null> {
+  return invoke<FenceStatus | null>("fence_enable", { enable });
+}
+
+export type SyncOutcome =
+  | { status: "uploaded" }
+  | { status: "skipped" }
+  | { status: "failed"; error: string }
+  | { status: "not_attempted" };
+
+export type SyncPart = {
+  mission_type: MissionType;
+  outcome: SyncOutcome;
+};
+
+export type SyncReport = {
+  parts: SyncPart[];
+};
+
+export type SyncProgress = {
+  mission_type: MissionType;
+  part_index: number;
+  part_count: number;
+  completed_items: number;
+  total_items: number;
+};
+
+export async function uploadAll(
+  mission: MissionPlan,
+  fence: MissionPlan | null = null,
+  rally: MissionPlan | null = null,
+  onStart?: (id: number) => void,
+): Promise<SyncReport> {
+  return runOperation<SyncReport>("mission_upload_all", { mission, fence, rally }, onStart);
+}
+
+export async function subscribeSyncProgress(cb: (event: SyncProgress) => void): Promise<UnlistenFn> {
+  return listen<SyncProgress>("mission.sync_progress", (event) => cb(event.payload));
 }
 
-export async function downloadMissionPlan(missionType: MissionType): Promise<MissionPlan> {
-  return invoke<MissionPlan>("mission_download_plan", { missionType });
+export async function downloadMissionPlan(
+  missionType: MissionType,
+  onStart?: (id: number) => void,
+): Promise<MissionPlan> {
+  return runOperation<MissionPlan>("mission_download_plan", { missionType }, onStart);
 }
 
 export async function clearMissionPlan(missionType: MissionType): Promise<void> {
   await invoke("mission_clear_plan", { missionType });
 }
 
-export async function verifyMissionRoundtrip(plan: MissionPlan): Promise<boolean> {
-  return invoke<boolean>("mission_verify_roundtrip", { plan });
+export async function verifyMissionRoundtrip(
+  plan: MissionPlan,
+  onStart?: (id: number) => void,
+): Promise<boolean> {
+  return runOperation<boolean>("mission_verify_roundtrip", { plan }, onStart);
+}
+
+export async function verifyMissionRoundtripDetailed(
+  plan: MissionPlan,
+  onStart?: (id: number) => void,
+): Promise<PlanDiff> {
+  return runOperation<PlanDiff>("mission_verify_roundtrip_detailed", { plan }, onStart);
+}
+
+export type PlanSyncStatus = "in_sync" | "local_changes" | "vehicle_changed" | "unknown";
+
+/** Whether the vehicle still holds `plan`, checked without downloading it. */
+export async function missionSyncStatus(plan: MissionPlan): Promise<PlanSyncStatus> {
+  return invoke<PlanSyncStatus>("mission_sync_status", { plan });
 }
 
 export async function setCurrentMissionItem(seq: number): Promise<void> {
   await invoke("mission_set_current", { seq });
 }
 
-export async function cancelMissionTransfer(): Promise<void> {
-  await invoke("mission_cancel");
+/** Make `seq` the current item and switch to the mission mode (AUTO). */
+export async function resumeMissionFrom(seq: number): Promise<void> {
+  await invoke("mission_resume_from", { seq });
 }
 
 export async function subscribeMissionProgress(cb: (event: TransferProgress) => void): Promise<UnlistenFn> {
   return listen<TransferProgress>("mission.progress", (event) => cb(event.payload));
 }
 
+/** The plans stored on the vehicle; null while unknown or changed by someone else. */
+export type OnboardPlans = {
+  mission: MissionPlan | null;
+  fence: MissionPlan | null;
+  rally: MissionPlan | null;
+};
+
+export async function subscribeVehiclePlan(cb: (plans: OnboardPlans) => void): Promise<UnlistenFn> {
+  return listen<OnboardPlans>("mission://vehicle_plan", (event) => cb(event.payload));
+}
+
+/** A plan on the vehicle changed over RC or by another GCS. */
+export type ExternalPlanChange = {
+  mission_type: MissionType;
+  /** The opaque id the plan was last known by. */
+  previous_id: number;
+  opaque_id: number;
+};
+
+export async function subscribeMissionChangedExternally(
+  cb: (change: ExternalPlanChange) => void,
+): Promise<UnlistenFn> {
+  return listen<ExternalPlanChange>("mission://changed_externally", (event) => cb(event.payload));
+}
+
 export async function subscribeMissionState(cb: (event: MissionState) => void): Promise<UnlistenFn> {
   return listen<MissionState>("mission.state", (event) => cb(event.payload));
 }
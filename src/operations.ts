@@ -0,0 +1,54 @@
+import { invoke, type InvokeArgs } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+export type OperationKind =
+  | "mission_upload"
+  | "mission_download"
+  | "mission_verify"
+  | "param_download"
+  | "rc_calibration"
+  | "rate_benchmark";
+
+/** Outcome of a long-running command (`operation://finished`). */
+export type OperationFinished = {
+  id: number;
+  kind: OperationKind;
+  /** The command's result; null when it failed. */
+  value: unknown;
+  error: string | null;
+};
+
+/** Cancel a running operation by the id its command returned. */
+export async function cancelOperation(id: number): Promise<void> {
+  await invoke("operation_cancel", { id });
+}
+
+/**
+ * Invoke a command that starts an operation and resolve with its result once
+ * it finishes. `onStart` gets the operation id, e.g. to offer cancelling it.
+ */
+export async function runOperation<T>(
+  command: string,
+  args: InvokeArgs = {},
+  onStart?: (id: number) => void,
+): Promise<T> {
+  // Listen first so an operation that finishes at once isn't missed
+  const finished = new Map<number, OperationFinished>();
+  let wake: (() => void) | null = null;
+  const unlisten = await listen<OperationFinished>("operation://finished", (event) => {
+    finished.set(event.payload.id, event.payload);
+    wake?.();
+  });
+  try {
+    const id = await invoke<number>(command, args);
+    onStart?.(id);
+    while (!finished.has(id)) {
+      await new Promise<void>((resolve) => { wake = resolve; });
+    }
+    const result = finished.get(id)!;
+    if (result.error !== null) throw result.error;
+    return result.value as T;
+  } finally {
+    unlisten();
+  }
+}
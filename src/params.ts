@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { runOperation } from "./operations";
 
 export type ParamType = "uint8" | "int8" | "uint16" | "int16" | "uint32" | "int32" | "real32";
 
@@ -15,22 +16,79 @@ export type ParamStore = {
   expected_count: number;
 };
 
+/** A single value as received from the vehicle (`param://updated`). */
+export type ParamUpdate = {
+  name: string;
+  value: number;
+  index: number;
+};
+
 export type ParamTransferPhase = "idle" | "downloading" | "completed" | "failed";
 
+export type ParamDownloadStage = "bulk" | "gap_fill";
+
 export type ParamProgress = {
   phase: ParamTransferPhase;
   received: number;
   expected: number;
+  stage: ParamDownloadStage;
+  missing_count: number;
+  requested: number[];
+  retries: number;
+  max_retries: number;
+  rate_per_sec: number;
+  /** Set for downloads started by `downloadAllParams`. */
+  operation_id: number | null;
 };
 
-export async function downloadAllParams(): Promise<ParamStore> {
-  return invoke<ParamStore>("param_download_all");
+/**
+ * Download all parameters and resolve with the number received; the values
+ * arrive via `param://store`. `onStart` gets the operation id, which also
+ * tags the download's `param://progress` events.
+ */
+export async function downloadAllParams(onStart?: (id: number) => void): Promise<number> {
+  return runOperation<number>("param_download_all", {}, onStart);
+}
+
+/**
+ * Show this vehicle's cached parameters at once (via `param://store`), then
+ * download again only if the vehicle reports they changed.
+ */
+export async function downloadParamsCached(): Promise<ParamStore> {
+  return invoke<ParamStore>("param_download_cached");
 }
 
 export async function writeParam(name: string, value: number): Promise<Param> {
   return invoke<Param>("param_write", { name, value });
 }
 
+export type ParamChange = {
+  name: string;
+  value: number;
+};
+
+/**
+ * Write interdependent parameters together: enable parameters first, each
+ * checked against its echo. If one is rejected, those already written are
+ * set back and the promise rejects.
+ */
+export async function writeParamTransaction(changes: ParamChange[]): Promise<Param[]> {
+  return invoke<Param[]>("param_write_transaction", { changes });
+}
+
+export type ParamGroup = {
+  prefix: string;
+  names: string[];
+};
+
+export async function searchParams(query: string): Promise<Param[]> {
+  return invoke<Param[]>("param_search", { query });
+}
+
+export async function getParamGroups(): Promise<ParamGroup[]> {
+  return invoke<ParamGroup[]>("param_groups");
+}
+
 export async function parseParamFile(contents: string): Promise<Record<string, number>> {
   return invoke<Record<string, number>>("param_parse_file", { contents });
 }
@@ -43,6 +101,23 @@ export async function subscribeParamStore(cb: (store: ParamStore) => void): Prom
   return listen<ParamStore>("param://store", (event) => cb(event.payload));
 }
 
+export async function subscribeParamUpdates(cb: (update: ParamUpdate) => void): Promise<UnlistenFn> {
+  return listen<ParamUpdate>("param://updated", (event) => cb(event.payload));
+}
+
+/** Apply updates to params already in `store`; unknown names are left for the next full store. */
+export function applyParamUpdates(store: ParamStore | null, updates: Iterable<ParamUpdate>): ParamStore | null {
+  if (!store) return store;
+  let params: Record<string, Param> | null = null;
+  for (const update of updates) {
+    const existing = store.params[update.name];
+    if (!existing) continue;
+    if (!params) params = { ...store.params };
+    params[update.name] = { ...existing, value: update.value, index: update.index };
+  }
+  return params ? { ...store, params } : store;
+}
+
 export async function subscribeParamProgress(cb: (progress: ParamProgress) => void): Promise<UnlistenFn> {
   return listen<ParamProgress>("param://progress", (event) => cb(event.payload));
 }
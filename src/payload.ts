@@ -0,0 +1,32 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export type GripperAction = "release" | "grab";
+
+export type WinchAction = "relax" | "deliver" | "hold" | "retract";
+
+export type PayloadCapabilities = {
+  servo: boolean;
+  relay: boolean;
+  gripper: boolean;
+  winch: boolean;
+};
+
+export async function getPayloadCapabilities(): Promise<PayloadCapabilities> {
+  return invoke<PayloadCapabilities>("payload_capabilities");
+}
+
+export async function setServo(channel: number, pwm: number): Promise<void> {
+  await invoke("payload_set_servo", { channel, pwm });
+}
+
+export async function setRelay(index: number, on: boolean): Promise<void> {
+  await invoke("payload_set_relay", { index, on });
+}
+
+export async function gripper(action: GripperAction): Promise<void> {
+  await invoke("payload_gripper", { action });
+}
+
+export async function winch(action: WinchAction, lengthM = 0, rateMps = 0): Promise<void> {
+  await invoke("payload_winch", { action, lengthM, rateMps });
+}
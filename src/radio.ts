@@ -0,0 +1,49 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export type SikParam = {
+  register: number;
+  name: string;
+  value: number;
+};
+
+export type SikSettings = {
+  net_id?: number | null;
+  air_speed_kbps?: number | null;
+  tx_power_dbm?: number | null;
+  ecc?: boolean | null;
+};
+
+export type SikRssi = {
+  local_rssi: number;
+  remote_rssi: number;
+  local_noise: number;
+  remote_noise: number;
+  packets: number;
+  tx_errors: number;
+  rx_errors: number;
+  ecc_corrected: number;
+  temperature_c: number | null;
+};
+
+export type SikReport = {
+  target: "local" | "remote";
+  version: string;
+  params: SikParam[];
+  settings: SikSettings;
+  rssi: SikRssi | null;
+};
+
+/** Read a SiK radio over its AT command mode; the port must not be connected. */
+export async function readRadio(port: string, baud: number, remote: boolean): Promise<SikReport> {
+  return invoke<SikReport>("radio_read", { port, baud, remote });
+}
+
+/** Write, save and apply settings. Change the remote radio before the local one. */
+export async function writeRadio(
+  port: string,
+  baud: number,
+  remote: boolean,
+  settings: SikSettings,
+): Promise<SikReport> {
+  return invoke<SikReport>("radio_write", { port, baud, remote, settings });
+}
@@ -0,0 +1,205 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { Param } from "./params";
+
+export type AirframeCategory = "multirotor" | "helicopter" | "plane" | "vtol" | "rover" | "boat";
+
+export type AirframePreset = {
+  id: string;
+  name: string;
+  category: AirframeCategory;
+  params: { name: string; value: number }[];
+};
+
+export type FrameParams = {
+  frame_class: number | null;
+  frame_type: number | null;
+  /** PX4 airframe id. */
+  sys_autostart: number | null;
+  /** Id of the preset the current parameters match. */
+  preset: string | null;
+};
+
+/** Presets for the connected firmware; needs downloaded parameters. */
+export async function getAirframePresets(): Promise<AirframePreset[]> {
+  return invoke<AirframePreset[]>("setup_airframe_presets");
+}
+
+export async function getFrame(): Promise<FrameParams> {
+  return invoke<FrameParams>("setup_frame");
+}
+
+/** Write a preset's parameters, verified. Reboot the vehicle to apply. */
+export async function applyAirframe(id: string): Promise<Param[]> {
+  return invoke<Param[]>("setup_apply_airframe", { id });
+}
+
+/** Set ArduPilot FRAME_CLASS and optionally FRAME_TYPE. */
+export async function setFrame(frameClass: number, frameType?: number): Promise<Param[]> {
+  return invoke<Param[]>("setup_set_frame", { frameClass, frameType: frameType ?? null });
+}
+
+export type RcChannelCalibration = {
+  /** 1-based, as in RC1_MIN. */
+  channel: number;
+  min: number;
+  max: number;
+  trim: number;
+};
+
+export type RcCalibration = {
+  /** Range seen so far; index i is channel i + 1. */
+  channels: RcChannelCalibration[];
+  trims_captured: boolean;
+};
+
+/** Raw RC input PWM per channel, on every RC_CHANNELS message. */
+export async function subscribeRcChannels(cb: (channels: number[]) => void): Promise<UnlistenFn> {
+  return listen<number[]>("rc://channels", (event) => cb(event.payload));
+}
+
+export async function subscribeRcCalibration(cb: (calibration: RcCalibration) => void): Promise<UnlistenFn> {
+  return listen<RcCalibration>("rc://calibration", (event) => cb(event.payload));
+}
+
+/**
+ * Start recording channel ranges; move every stick and switch end to end.
+ * Resolves with the operation id, which `cancelOperation` stops.
+ */
+export async function startRcCalibration(): Promise<number> {
+  return invoke<number>("rc_calibration_start");
+}
+
+/** Take the current values as trims (sticks centred, throttle low). */
+export async function captureRcTrims(): Promise<void> {
+  return invoke("rc_calibration_capture_trims");
+}
+
+/** Stop recording and return the channels that moved enough to calibrate. */
+export async function finishRcCalibration(): Promise<RcChannelCalibration[]> {
+  return invoke<RcChannelCalibration[]>("rc_calibration_finish");
+}
+
+/** Write RCx_MIN/MAX/TRIM for each channel, verified. */
+export async function writeRcCalibration(channels: RcChannelCalibration[]): Promise<Param[]> {
+  return invoke<Param[]>("setup_write_rc_calibration", { channels });
+}
+
+/** "other" carries a firmware value without a name. */
+export type FailsafeAction =
+  | "disabled"
+  | "hold"
+  | "rtl"
+  | "smart_rtl"
+  | "land"
+  | "continue_mission"
+  | "terminate"
+  | { other: number };
+
+/** Null fields are unavailable on this firmware, or left unchanged when writing. */
+export type FailsafeConfig = {
+  battery_low_action: FailsafeAction | null;
+  battery_low_voltage: number | null;
+  /** ArduPilot: remaining capacity. */
+  battery_low_mah: number | null;
+  /** PX4: remaining charge. */
+  battery_low_percent: number | null;
+  battery_critical_action: FailsafeAction | null;
+  battery_critical_voltage: number | null;
+  battery_critical_mah: number | null;
+  battery_critical_percent: number | null;
+  rc_loss_action: FailsafeAction | null;
+  rc_loss_timeout_s: number | null;
+  gcs_loss_action: FailsafeAction | null;
+  gcs_loss_timeout_s: number | null;
+};
+
+/** Actions offered per failsafe; empty when it cannot be configured. */
+export type FailsafeOptions = {
+  battery_low: FailsafeAction[];
+  battery_critical: FailsafeAction[];
+  rc_loss: FailsafeAction[];
+  gcs_loss: FailsafeAction[];
+};
+
+/** Needs downloaded parameters. */
+export async function getFailsafe(): Promise<FailsafeConfig> {
+  return invoke<FailsafeConfig>("setup_failsafe");
+}
+
+export async function getFailsafeOptions(): Promise<FailsafeOptions> {
+  return invoke<FailsafeOptions>("setup_failsafe_options");
+}
+
+/** Validate and write the parameters that changed, verified. */
+export async function setFailsafe(config: FailsafeConfig): Promise<Param[]> {
+  return invoke<Param[]>("setup_set_failsafe", { config });
+}
+
+export type SensorRotation = { value: number; name: string };
+
+export type BusType = "unknown" | "i2c" | "spi" | "uavcan" | "sitl" | "msp" | "serial";
+
+export type DeviceId = {
+  raw: number;
+  bus_type: BusType;
+  bus: number;
+  address: number;
+  devtype: number;
+  driver: string | null;
+};
+
+export type Compass = {
+  /** Calibration slot, 0-based. */
+  slot: number;
+  device: DeviceId;
+  /** 1 for the primary compass; null when not in use. */
+  priority: number | null;
+  orientation: number | null;
+  external: boolean | null;
+  offsets: [number, number, number] | null;
+};
+
+export type Imu = {
+  slot: number;
+  accel: DeviceId | null;
+  gyro: DeviceId | null;
+};
+
+export type SensorSetup = {
+  compasses: Compass[];
+  /** Detected but without a calibration slot. */
+  unassigned_compasses: DeviceId[];
+  imus: Imu[];
+  board_orientation: number | null;
+};
+
+/** Needs downloaded parameters. */
+export async function getSensors(): Promise<SensorSetup> {
+  return invoke<SensorSetup>("setup_sensors");
+}
+
+export async function getSensorRotations(): Promise<SensorRotation[]> {
+  return invoke<SensorRotation[]>("setup_sensor_rotations");
+}
+
+/** Compass order by device id, primary first. Reboot to apply. */
+export async function setCompassPriority(devIds: number[]): Promise<Param[]> {
+  return invoke<Param[]>("setup_set_compass_priority", { devIds });
+}
+
+export async function setCompassOrientation(slot: number, rotation: number): Promise<Param> {
+  return invoke<Param>("setup_set_compass_orientation", { slot, rotation });
+}
+
+export async function setBoardOrientation(rotation: number): Promise<Param> {
+  return invoke<Param>("setup_set_board_orientation", { rotation });
+}
+
+/**
+ * Calibrate compasses from a known heading, for vehicles that cannot be
+ * rotated (ArduPilot, needs a GPS fix). A mask of 0 selects all compasses.
+ */
+export async function fixedYawMagCal(yawDeg: number, compassMask = 0): Promise<void> {
+  return invoke("setup_fixed_yaw_mag_cal", { yawDeg, compassMask });
+}
@@ -0,0 +1,19 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export type RcFailure = "none" | "no_pulses" | "neutral";
+
+export type SimAction =
+  | { kind: "speedup"; factor: number }
+  | { kind: "wind"; speed_mps: number; direction_deg: number; turbulence: number }
+  | { kind: "gps_glitch"; lat_deg: number; lon_deg: number; alt_m: number }
+  | { kind: "gps_enabled"; enabled: boolean }
+  | { kind: "rc_failure"; mode: RcFailure }
+  | { kind: "clear_failures" };
+
+export async function isSitlAvailable(): Promise<boolean> {
+  return invoke<boolean>("sitl_is_available");
+}
+
+export async function applySimAction(action: SimAction): Promise<void> {
+  await invoke("sitl_apply", { action });
+}
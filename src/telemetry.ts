@@ -1,18 +1,65 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { FailsafeAction } from "./setup";
+
+export type SerialOptions = {
+  flow_control: "none" | "software" | "hardware";
+  parity: "none" | "odd" | "even";
+  stop_bits: "one" | "two";
+  /** Level to drive DTR/RTS to after opening; null leaves the driver default. */
+  dtr: boolean | null;
+  rts: boolean | null;
+};
+
+export const DEFAULT_SERIAL_OPTIONS: SerialOptions = {
+  flow_control: "none",
+  parity: "none",
+  stop_bits: "one",
+  dtr: null,
+  rts: null,
+};
+
+/** Server certificate checks for a TLS link; empty trusts the web PKI roots. */
+export type TlsOptions = {
+  server_name?: string;
+  /** PEM file of CA certificates to trust instead. */
+  ca_file?: string;
+  /** SHA-256 certificate fingerprints; when set, only these are accepted. */
+  pinned_sha256?: string[];
+};
 
 export type LinkEndpoint =
   | { kind: "udp"; bind_addr: string }
-  | { kind: "serial"; port: string; baud: number };
+  | { kind: "tcp"; addr: string; tls?: TlsOptions }
+  | { kind: "serial"; port: string; baud: number; options?: SerialOptions };
 
 export type ConnectRequest = {
   endpoint: LinkEndpoint;
+  /** Overrides `--gcs-system-id` / `--gcs-component-id` on the command line. */
+  gcs_system_id?: number;
+  gcs_component_id?: number;
+  connect_timeout_ms?: number;
+  /** 0 stops our GCS HEARTBEAT. */
+  gcs_heartbeat_interval_ms?: number;
+  /** 0 stops TIMESYNC requests. */
+  timesync_interval_ms?: number;
+  /** Drop packets arriving twice within this window; 0 turns it off. */
+  link_dedup_window_ms?: number;
+  auto_request_home?: boolean;
+  telemetry_smoothing?: TelemetrySmoothing;
+  /** Selects the cell limits behind battery alerts; LiPo when unset. */
+  battery_chemistry?: BatteryChemistry;
+  /** STATUSTEXT sent to the vehicle when the link is closed. */
+  shutdown_notice?: string;
+  /** Operator ID and self-ID for the Remote ID broadcast. */
+  remote_id?: RemoteIdOperator;
 };
 
 export type LinkState = "connecting" | "connected" | "disconnected" | { error: string };
 
 export type Telemetry = {
   altitude_m?: number;
+  altitude_amsl_m?: number;
   speed_mps?: number;
   heading_deg?: number;
   latitude_deg?: number;
@@ -43,6 +90,9 @@ export type Telemetry = {
   nav_bearing_deg?: number;
   target_bearing_deg?: number;
   xtrack_error_m?: number;
+  /** Target altitude minus the current one, positive when below it. */
+  alt_error_m?: number;
+  airspeed_error_mps?: number;
 
   // TERRAIN_REPORT
   terrain_height_m?: number;
@@ -59,6 +109,27 @@ export type Telemetry = {
 
   // SERVO_OUTPUT_RAW
   servo_outputs?: number[];
+  /** Vibration level along x, y and z, m/s/s. */
+  vibration_mps2?: [number, number, number];
+  /** Clip counts of accelerometers 0-2 since boot. */
+  accel_clipping?: [number, number, number];
+
+  // Vehicle clock (SYSTEM_TIME / TIMESYNC)
+  time_boot_ms?: number;
+  /** UTC by the vehicle's clock, microseconds since the Unix epoch. */
+  time_utc_us?: number;
+
+  // EXTENDED_SYS_STATE
+  landed_state?: "on_ground" | "in_air" | "takeoff" | "landing";
+
+  // Flight timer and odometer, since connecting
+  armed_time_s?: number;
+  distance_flown_m?: number;
+  flights?: number;
+  /** Totals over every session of this airframe, once its flight log is loaded. */
+  total_armed_time_s?: number;
+  total_distance_flown_m?: number;
+  total_flights?: number;
 };
 
 export type VehicleState = {
@@ -70,6 +141,16 @@ export type VehicleState = {
   autopilot: string;
 };
 
+/** Who the vehicle is, from its heartbeats (`vehicle://identity`). */
+export type VehicleIdentity = {
+  system_id: number;
+  component_id: number;
+  autopilot: string;
+  vehicle_type: string;
+  /** From AUTOPILOT_VERSION; null until it arrives. */
+  firmware: { major: number; minor: number; patch: number } | null;
+};
+
 export type HomePosition = {
   latitude_deg: number;
   longitude_deg: number;
@@ -81,58 +162,619 @@ export type FlightModeEntry = {
   name: string;
 };
 
-export async function connectLink(request: ConnectRequest): Promise<void> {
-  await invoke("connect_link", { request });
+export type SessionInfo = {
+  /** The link was already open with the same request and was kept. */
+  reused: boolean;
+  vehicle: VehicleState;
+};
+
+/** Repeating the request of the open link keeps it instead of reconnecting. */
+export async function connectLink(request: ConnectRequest): Promise<SessionInfo> {
+  return invoke<SessionInfo>("connect_link", { request });
+}
+
+/** The local port is held by another program, or a previous link still closing. */
+export function isAddressInUseError(message: string): boolean {
+  return message.startsWith("address already in use");
+}
+
+export function isDefaultSerialOptions(options: SerialOptions): boolean {
+  return (Object.keys(DEFAULT_SERIAL_OPTIONS) as (keyof SerialOptions)[]).every(
+    (key) => options[key] === DEFAULT_SERIAL_OPTIONS[key],
+  );
+}
+
+/** Refused while `getShutdownWarning` reports a warning, unless `confirmed`. */
+export async function disconnectLink(confirmed = false): Promise<void> {
+  await invoke("disconnect_link", { confirmed });
+}
+
+/** Why closing the link now could trigger the vehicle's GCS failsafe. */
+export type ShutdownWarning = {
+  gcs_loss_action: FailsafeAction | null;
+  gcs_loss_timeout_s: number | null;
+  message: string;
+};
+
+export async function getShutdownWarning(): Promise<ShutdownWarning | null> {
+  return invoke<ShutdownWarning | null>("vehicle_shutdown_warning");
 }
 
-export async function disconnectLink(): Promise<void> {
-  await invoke("disconnect_link");
+/** Closing the window was held back; call `confirmExit` to close anyway. */
+export async function subscribeCloseBlocked(
+  cb: (warning: ShutdownWarning) => void,
+): Promise<UnlistenFn> {
+  return listen<ShutdownWarning>("app://close_blocked", (event) => cb(event.payload));
+}
+
+export async function confirmExit(): Promise<void> {
+  await invoke("app_confirm_exit");
+}
+
+export type BridgeHealth = {
+  name: string;
+  running: boolean;
+};
+
+/** Backend event bridges for the connected vehicle; any `running: false` means a dead pipeline. */
+export async function getBridgeHealth(): Promise<BridgeHealth[]> {
+  return invoke<BridgeHealth[]>("bridge_health");
 }
 
 export async function listSerialPorts(): Promise<string[]> {
   return invoke<string[]>("list_serial_ports_cmd");
 }
 
+export type TelemetrySmoothing = {
+  /** Low-pass time constant; 0 follows the samples exactly. */
+  time_constant_s: number;
+  /** Extrapolate between samples from the latest rate of change. */
+  predict: boolean;
+  rate_hz: number;
+};
+
+/**
+ * Attitude and position at a steady rate, predicted between samples and
+ * low-pass filtered (`telemetry://smoothed`), for displays that should move
+ * fluidly on slow links.
+ */
+export type SmoothedTelemetry = {
+  roll_deg: number | null;
+  pitch_deg: number | null;
+  yaw_deg: number | null;
+  latitude_deg: number | null;
+  longitude_deg: number | null;
+  altitude_m: number | null;
+  altitude_amsl_m: number | null;
+  time_boot_ms: number | null;
+};
+
+export async function subscribeTelemetrySmoothed(
+  cb: (smoothed: SmoothedTelemetry) => void,
+): Promise<UnlistenFn> {
+  return listen<SmoothedTelemetry>("telemetry://smoothed", (event) => cb(event.payload));
+}
+
 export async function subscribeTelemetry(cb: (telemetry: Telemetry) => void): Promise<UnlistenFn> {
   return listen<Telemetry>("telemetry://tick", (event) => cb(event.payload));
 }
 
+export type TelemetryStreamConfig = {
+  delta: boolean;
+  /** Per-field maximum rate in Hz, keyed by Telemetry field name. */
+  field_rates_hz?: Partial<Record<keyof Telemetry, number>>;
+};
+
+type TelemetryDelta = { seq: number; fields: Partial<Telemetry> };
+type TelemetrySnapshot = { seq: number; telemetry: Telemetry };
+
+export async function configureTelemetryStream(config: TelemetryStreamConfig): Promise<void> {
+  await invoke("telemetry_configure_stream", { config });
+}
+
+export async function resyncTelemetry(): Promise<void> {
+  await invoke("telemetry_resync");
+}
+
+/**
+ * Delta-mode counterpart of `subscribeTelemetry`: merges snapshots and deltas
+ * into a full Telemetry object, asking for a resync on subscribe and whenever
+ * a sequence gap shows an update was missed.
+ */
+export async function subscribeTelemetryDeltas(cb: (telemetry: Telemetry) => void): Promise<UnlistenFn> {
+  let current: Telemetry | null = null;
+  let seq = 0;
+  const unlistenSnapshot = await listen<TelemetrySnapshot>("telemetry://snapshot", (event) => {
+    current = event.payload.telemetry;
+    seq = event.payload.seq;
+    cb(current);
+  });
+  const unlistenDelta = await listen<TelemetryDelta>("telemetry://delta", (event) => {
+    if (current === null) return;
+    if (event.payload.seq !== seq + 1) {
+      current = null;
+      void resyncTelemetry();
+      return;
+    }
+    seq = event.payload.seq;
+    current = { ...current, ...event.payload.fields };
+    cb(current);
+  });
+  await resyncTelemetry();
+  return () => {
+    unlistenSnapshot();
+    unlistenDelta();
+  };
+}
+
 export async function subscribeLinkState(cb: (state: LinkState) => void): Promise<UnlistenFn> {
   return listen<LinkState>("link://state", (event) => cb(event.payload));
 }
 
+/**
+ * Fires when a `udpin` link starts replying to a new vehicle address, e.g.
+ * after its NAT mapping changed; `null` until it is first heard from.
+ */
+export async function subscribeLinkPeer(cb: (peer: string | null) => void): Promise<UnlistenFn> {
+  return listen<string | null>("link://peer", (event) => cb(event.payload));
+}
+
 export async function subscribeHomePosition(cb: (hp: HomePosition) => void): Promise<UnlistenFn> {
   return listen<HomePosition>("home://position", (event) => cb(event.payload));
 }
 
+export type BatteryInfo = {
+  id: number;
+  design_capacity_mah: number | null;
+  full_capacity_mah: number | null;
+  cycle_count: number | null;
+  serial_number: string | null;
+  device_name: string | null;
+  manufacture_date: string | null;
+  cells_in_series: number | null;
+  health_pct: number | null;
+};
+
+export async function subscribeBatteryInfo(cb: (info: BatteryInfo[]) => void): Promise<UnlistenFn> {
+  return listen<BatteryInfo[]>("battery://info", (event) => cb(event.payload));
+}
+
+export async function requestBatteryInfo(): Promise<void> {
+  await invoke("vehicle_request_battery_info");
+}
+
+export type BatteryChemistry = "li_po" | "li_hv" | "li_ion" | "li_fe";
+
+export type BatteryAlert = "imbalance" | "sag" | "low_cell";
+
+/** Cell spread and sag of the pack, from per-cell voltages (`battery://health`). */
+export type BatteryHealth = {
+  imbalance_v: number | null;
+  weakest_cell: number | null;
+  weakest_cell_v: number | null;
+  cell_resistance_mohm: number | null;
+  sag_v: number | null;
+  alerts: BatteryAlert[];
+};
+
+export async function subscribeBatteryHealth(cb: (health: BatteryHealth) => void): Promise<UnlistenFn> {
+  return listen<BatteryHealth>("battery://health", (event) => cb(event.payload));
+}
+
+export type VibrationAxis = "x" | "y" | "z";
+
+/** An accelerometer clipping faster and faster. */
+export type ClipAlert = {
+  accel: number;
+  clips_per_s: number;
+  previous_clips_per_s: number;
+  /** Axis vibrating hardest when raised, the likely source. */
+  axis: VibrationAxis | null;
+};
+
+export type VibrationSummary = {
+  duration_s: number;
+  max_level_mps2: [number, number, number];
+  clips: [number, number, number];
+  peak_clips_per_s: number;
+  alerts: ClipAlert[];
+  in_progress: boolean;
+};
+
+/** Clip rates and alerts, with the current or last flight (`vibration://state`). */
+export type VibrationState = {
+  levels_mps2: [number, number, number] | null;
+  clips_per_s: [number, number, number];
+  alerts: ClipAlert[];
+  flight: VibrationSummary | null;
+};
+
+export async function subscribeVibration(cb: (vibration: VibrationState) => void): Promise<UnlistenFn> {
+  return listen<VibrationState>("vibration://state", (event) => cb(event.payload));
+}
+
+export type EscTelemetry = {
+  index: number;
+  rpm: number | null;
+  voltage_v: number | null;
+  current_a: number | null;
+  temperature_c: number | null;
+  over_temperature: boolean;
+};
+
+export async function subscribeEscTelemetry(cb: (escs: EscTelemetry[]) => void): Promise<UnlistenFn> {
+  return listen<EscTelemetry[]>("esc://telemetry", (event) => cb(event.payload));
+}
+
+export type RemoteIdOperator = {
+  operator_id?: string;
+  self_id?: string;
+};
+
+export type RemoteIdStatus = "undeclared" | "ground" | "airborne" | "emergency" | "system_failure";
+
+export type UasId = {
+  id_type: "none" | "serial_number" | "caa_registration" | "utm_assigned" | "specific_session";
+  id: string;
+};
+
+export type RemoteIdState = {
+  uas_ids: UasId[];
+  location: {
+    status: RemoteIdStatus;
+    latitude_deg: number;
+    longitude_deg: number;
+    altitude_geodetic_m: number | null;
+    height_m: number | null;
+    direction_deg: number | null;
+    speed_horizontal_mps: number | null;
+    speed_vertical_mps: number | null;
+  } | null;
+  operator_latitude_deg: number | null;
+  operator_longitude_deg: number | null;
+  operator_id: string | null;
+  self_id: string | null;
+  ready_to_arm: boolean | null;
+  arm_error: string | null;
+};
+
+export async function subscribeRemoteId(cb: (state: RemoteIdState) => void): Promise<UnlistenFn> {
+  return listen<RemoteIdState>("remote_id://state", (event) => cb(event.payload));
+}
+
+export type TrafficTarget = {
+  icao_address: number;
+  callsign: string | null;
+  latitude_deg: number;
+  longitude_deg: number;
+  altitude_amsl_m: number | null;
+  heading_deg: number | null;
+  ground_speed_mps: number | null;
+  vertical_speed_mps: number | null;
+  squawk: number;
+};
+
+export type AvoidanceSuggestion =
+  | { action: "climb"; by_m: number }
+  | { action: "descend"; by_m: number };
+
+export type TrafficAdvisory = {
+  icao_address: number;
+  callsign: string | null;
+  cpa_time_s: number;
+  cpa_distance_m: number;
+  cpa_vertical_m: number;
+  suggestion: AvoidanceSuggestion;
+};
+
+export async function subscribeTraffic(cb: (traffic: TrafficTarget[]) => void): Promise<UnlistenFn> {
+  return listen<TrafficTarget[]>("traffic://targets", (event) => cb(event.payload));
+}
+
+export async function subscribeTrafficAdvisories(
+  cb: (advisories: TrafficAdvisory[]) => void,
+): Promise<UnlistenFn> {
+  return listen<TrafficAdvisory[]>("traffic://advisories", (event) => cb(event.payload));
+}
+
+export type GcsPeer = {
+  system_id: number;
+  component_id: number;
+  conflicts: boolean;
+};
+
+export async function subscribeGcsPeers(cb: (peers: GcsPeer[]) => void): Promise<UnlistenFn> {
+  return listen<GcsPeer[]>("gcs://peers", (event) => cb(event.payload));
+}
+
+export type RetryPolicy = {
+  request_timeout_ms: number;
+  item_timeout_ms: number;
+  max_retries: number;
+};
+
+export type LinkPacing = {
+  bytes_per_sec: number | null;
+  burst_bytes: number;
+};
+
+export type NudgeLimits = {
+  max_step_m: number;
+  max_alt_step_m: number;
+};
+
+/** What a guided goto does when its path passes too close to the terrain. */
+export type TerrainCheck = "off" | "warn" | "refuse";
+
+/** Live connection settings; omitted fields keep their current value. */
+export type ConfigPatch = {
+  retry_policy?: RetryPolicy;
+  auto_request_home?: boolean;
+  link_pacing?: LinkPacing;
+  esc_max_temperature_c?: number;
+  nudge_limits?: NudgeLimits;
+  /** Applies once a terrain source is set on the vehicle. */
+  terrain_check?: TerrainCheck;
+  /** Replaces the Remote ID operator data; empty fields stop being sent. */
+  remote_id?: RemoteIdOperator;
+  /** Replaces the custom mode table; shown from the next heartbeat. */
+  mode_overrides?: ModeOverrides;
+  mode_check?: ModeCheck;
+};
+
+/**
+ * Modes of custom ArduPilot builds, merged over the built-in tables: an entry
+ * renames the mode with its number, or adds it. Loaded from `modes.json` in
+ * the app config directory on connect.
+ */
+export type ModeOverrides = {
+  copter?: FlightModeEntry[];
+  plane?: FlightModeEntry[];
+  rover?: FlightModeEntry[];
+};
+
+export async function updateVehicleConfig(patch: ConfigPatch): Promise<void> {
+  await invoke("vehicle_update_config", { patch });
+}
+
+export async function setGcsIdentity(systemId: number, componentId: number): Promise<void> {
+  await invoke("vehicle_set_gcs_identity", { systemId, componentId });
+}
+
+export type ControlOwner =
+  | { kind: "unclaimed" }
+  | { kind: "us" }
+  | { kind: "peer"; system_id: number; component_id: number };
+
+export type ControlState = {
+  owner: ControlOwner;
+  override_enabled: boolean;
+};
+
+export async function subscribeControlState(cb: (state: ControlState) => void): Promise<UnlistenFn> {
+  return listen<ControlState>("control://state", (event) => cb(event.payload));
+}
+
+export async function requestControl(force = false): Promise<void> {
+  await invoke("vehicle_request_control", { force });
+}
+
+export async function releaseControl(): Promise<void> {
+  await invoke("vehicle_release_control");
+}
+
+export async function setControlOverride(enabled: boolean): Promise<void> {
+  await invoke("vehicle_set_control_override", { enabled });
+}
+
+export type AuditEntry = {
+  time_unix_ms: number;
+  command: string;
+  params: string;
+  duration_ms: number;
+  error: string | null;
+};
+
+export async function getAuditLog(): Promise<AuditEntry[]> {
+  return invoke<AuditEntry[]>("vehicle_audit_log");
+}
+
+export async function exportAuditLog(path: string): Promise<void> {
+  await invoke("vehicle_export_audit_log", { path });
+}
+
 export async function subscribeVehicleState(cb: (state: VehicleState) => void): Promise<UnlistenFn> {
   return listen<VehicleState>("vehicle://state", (event) => cb(event.payload));
 }
 
+/** Null until the first heartbeat. */
+export async function subscribeVehicleIdentity(
+  cb: (identity: VehicleIdentity | null) => void,
+): Promise<UnlistenFn> {
+  return listen<VehicleIdentity | null>("vehicle://identity", (event) => cb(event.payload));
+}
+
+/**
+ * Fires on each reboot seen mid-session, with the count so far. The
+ * parameters and onboard plans are cleared and home is requested again.
+ */
+export async function subscribeRebooted(cb: (count: number) => void): Promise<UnlistenFn> {
+  return listen<number>("vehicle://rebooted", (event) => cb(event.payload));
+}
+
 export async function armVehicle(force: boolean): Promise<void> {
   await invoke("arm_vehicle", { force });
 }
 
+/** Refused while the vehicle is clearly airborne, unless `force`. */
 export async function disarmVehicle(force: boolean): Promise<void> {
   await invoke("disarm_vehicle", { force });
 }
 
-export async function setFlightMode(customMode: number): Promise<void> {
-  await invoke("set_flight_mode", { customMode });
+/** The disarm was refused in flight; `disarmVehicle(true)` disarms anyway. */
+export function isDisarmRefusedError(message: string): boolean {
+  return message.startsWith("disarm refused");
+}
+
+/** Refused when the mode looks like a mistake right now, unless `unchecked`. */
+export async function setFlightMode(customMode: number, unchecked = false): Promise<void> {
+  await invoke("set_flight_mode", { customMode, unchecked });
+}
+
+/** Why a mode change was refused: no mission, no GPS fix, or disarmed. */
+export type ModeHazard = "no_mission" | "no_gps_fix" | "disarmed";
+
+/** What mode changes with a `ModeHazard` do. */
+export type ModeCheck = "off" | "warn" | "refuse";
+
+/** The mode change was refused; `setFlightMode(mode, true)` switches anyway. */
+export function isModeRefusedError(message: string): boolean {
+  return message.startsWith("mode change to ");
 }
 
 export async function vehicleTakeoff(altitudeM: number): Promise<void> {
   await invoke("vehicle_takeoff", { altitudeM });
 }
 
+/** Land in place, then follow the descent via `subscribeLanding`. */
+export async function vehicleLandHere(): Promise<void> {
+  await invoke("vehicle_land_here");
+}
+
+/** Stop the landing and climb to `climbToM` above home over the current position. */
+export async function vehicleAbortLanding(climbToM: number): Promise<void> {
+  await invoke("vehicle_abort_landing", { climbToM });
+}
+
+export type LandingState = {
+  phase: "idle" | "descending" | "landed" | "aborted";
+  altitude_m: number | null;
+  /** Positive while sinking. */
+  descent_rate_mps: number | null;
+  warning: "fast_descent" | "stalled" | null;
+};
+
+export async function subscribeLanding(cb: (state: LandingState) => void): Promise<UnlistenFn> {
+  return listen<LandingState>("vehicle://landing", (event) => cb(event.payload));
+}
+
 export async function vehicleGuidedGoto(latDeg: number, lonDeg: number, altM: number): Promise<void> {
   await invoke("vehicle_guided_goto", { latDeg, lonDeg, altM });
 }
 
+export type GotoProposal = {
+  token: number;
+  latitude_deg: number;
+  longitude_deg: number;
+  altitude_m: number;
+  distance_m: number | null;
+  eta_s: number | null;
+  terrain_clearance_m: number | null;
+  /** Lowest clearance along the path to the target, when terrain is known. */
+  path_clearance_m: number | null;
+  warnings: string[];
+};
+
+export async function proposeGoto(latDeg: number, lonDeg: number, altM?: number): Promise<GotoProposal> {
+  return invoke<GotoProposal>("vehicle_propose_goto", { latDeg, lonDeg, altM: altM ?? null });
+}
+
+export async function confirmGoto(token: number): Promise<void> {
+  await invoke("vehicle_confirm_goto", { token });
+}
+
+export type NudgeDirection = "forward" | "back" | "left" | "right";
+
+export async function vehicleNudge(direction: NudgeDirection, distanceM: number): Promise<void> {
+  await invoke("vehicle_nudge", { direction, distanceM });
+}
+
+export async function vehicleChangeAltitude(deltaM: number): Promise<void> {
+  await invoke("vehicle_change_altitude", { deltaM });
+}
+
+export type ReturnToMeOptions = {
+  altitude_m: number;
+  update_interval_ms: number;
+  stale_after_ms: number;
+  on_stale: "hold" | "rtl";
+  min_move_m: number;
+};
+
+export type ReturnToMeState = "off" | "following" | "position_lost";
+
+export async function updateGcsPosition(latDeg: number, lonDeg: number): Promise<void> {
+  await invoke("vehicle_update_gcs_position", { latDeg, lonDeg });
+}
+
+/** Rejects unless `updateGcsPosition` reported a fix within `stale_after_ms`. */
+export async function startReturnToMe(options: ReturnToMeOptions): Promise<void> {
+  await invoke("vehicle_start_return_to_me", { options });
+}
+
+export async function stopReturnToMe(): Promise<void> {
+  await invoke("vehicle_stop_return_to_me");
+}
+
+export async function subscribeReturnToMe(cb: (state: ReturnToMeState) => void): Promise<UnlistenFn> {
+  return listen<ReturnToMeState>("vehicle://return_to_me", (event) => cb(event.payload));
+}
+
+/** Pose stream for external 3D viewers; see `PoseFormat` in mavkit. */
+export type PoseOutputOptions = {
+  /** Viewer address, `host:port`. */
+  target: string;
+  format: "json" | "flight_gear";
+  /** Datagrams per second, at most 60. */
+  rate_hz: number;
+};
+
+/** Replaces any stream already running; stops on disconnect. */
+export async function startPoseOutput(options: PoseOutputOptions): Promise<void> {
+  await invoke("pose_output_start", { options });
+}
+
+export async function stopPoseOutput(): Promise<void> {
+  await invoke("pose_output_stop");
+}
+
+export type OrbitDirection = "clockwise" | "counter_clockwise";
+
+export async function vehicleOrbit(
+  centerLat: number,
+  centerLon: number,
+  radiusM: number,
+  speedMps: number,
+  direction: OrbitDirection,
+): Promise<void> {
+  await invoke("vehicle_orbit", { centerLat, centerLon, radiusM, speedMps, direction });
+}
+
+export async function vehicleStopOrbit(): Promise<void> {
+  await invoke("vehicle_stop_orbit");
+}
+
 export async function getAvailableModes(): Promise<FlightModeEntry[]> {
   return invoke<FlightModeEntry[]>("get_available_modes");
 }
 
+export type RtlSegmentKind = "climb" | "cruise" | "descend" | "loiter";
+
+export type RtlPoint = {
+  latitude_deg: number;
+  longitude_deg: number;
+  altitude_m: number;
+};
+
+export type RtlPreview = {
+  segments: { kind: RtlSegmentKind; points: RtlPoint[] }[];
+  distance_m: number;
+};
+
+export async function getRtlPreview(rtlAltM?: number): Promise<RtlPreview | null> {
+  return invoke<RtlPreview | null>("vehicle_rtl_preview", { rtlAltM: rtlAltM ?? null });
+}
+
 export async function setTelemetryRate(rateHz: number): Promise<void> {
   await invoke("set_telemetry_rate", { rateHz });
 }
@@ -0,0 +1,84 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+export type LengthUnit = "meters" | "feet";
+export type SpeedUnit = "meters_per_second" | "kilometers_per_hour" | "knots" | "miles_per_hour" | "feet_per_minute";
+export type DistanceUnit = "meters" | "feet" | "nautical_miles" | "miles";
+export type CoordinateFormat =
+  | "decimal_degrees"
+  | "degrees_minutes"
+  | "degrees_minutes_seconds"
+  | "utm"
+  | "mgrs";
+
+export type Units = {
+  altitude: LengthUnit;
+  speed: SpeedUnit;
+  vertical_speed: SpeedUnit;
+  distance: DistanceUnit;
+  coordinates: CoordinateFormat;
+};
+
+export const METRIC_UNITS: Units = {
+  altitude: "meters",
+  speed: "meters_per_second",
+  vertical_speed: "meters_per_second",
+  distance: "meters",
+  coordinates: "decimal_degrees",
+};
+
+export const IMPERIAL_UNITS: Units = {
+  altitude: "feet",
+  speed: "miles_per_hour",
+  vertical_speed: "feet_per_minute",
+  distance: "miles",
+  coordinates: "decimal_degrees",
+};
+
+export const AVIATION_UNITS: Units = {
+  altitude: "feet",
+  speed: "knots",
+  vertical_speed: "feet_per_minute",
+  distance: "nautical_miles",
+  coordinates: "degrees_minutes",
+};
+
+export type DisplayValue = {
+  value: number;
+  unit: string;
+};
+
+/** Telemetry converted to the selected units by the backend. */
+export type DisplayTelemetry = {
+  altitude: DisplayValue | null;
+  altitude_amsl: DisplayValue | null;
+  speed: DisplayValue | null;
+  airspeed: DisplayValue | null;
+  climb_rate: DisplayValue | null;
+  wp_dist: DisplayValue | null;
+  height_above_terrain: DisplayValue | null;
+  /** Position in the preferred coordinate format. */
+  position: string | null;
+};
+
+export async function getUnits(): Promise<Units> {
+  return invoke<Units>("units_get");
+}
+
+export async function setUnits(units: Units): Promise<void> {
+  await invoke("units_set", { units });
+}
+
+export async function subscribeDisplayTelemetry(cb: (display: DisplayTelemetry) => void): Promise<UnlistenFn> {
+  return listen<DisplayTelemetry>("telemetry://display", (event) => cb(event.payload));
+}
+
+/** Format a position; uses the preferred coordinate format when none is given. */
+export async function formatCoordinate(lat: number, lon: number, format?: CoordinateFormat): Promise<string> {
+  return invoke<string>("coords_format", { lat, lon, format: format ?? null });
+}
+
+/** Read a position typed as decimal degrees, DDM, DMS, UTM or MGRS. */
+export async function parseCoordinate(text: string): Promise<[number, number]> {
+  return invoke<[number, number]>("coords_parse", { text });
+}
@@ -0,0 +1,45 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { MissionPlan } from "./mission";
+
+export type WindSample = {
+  /** Height above ground. */
+  altitude_m: number;
+  speed_mps: number;
+  /** Direction the wind blows from, degrees clockwise from north. */
+  direction_deg: number;
+};
+
+export type WindProfile = { samples: WindSample[] };
+
+export type FixedWind = { speed_mps: number; direction_deg: number };
+
+export type LegWind = {
+  seq: number;
+  distance_m: number;
+  track_deg: number;
+  headwind_mps: number;
+  crosswind_mps: number;
+  /** Null when the wind is too strong to hold the track at this airspeed. */
+  ground_speed_mps: number | null;
+  duration_s: number | null;
+};
+
+export type WindEstimate = {
+  legs: LegWind[];
+  still_air_duration_s: number;
+  duration_s: number | null;
+  max_headwind_mps: number;
+};
+
+/** Use a fixed wind for all estimates (offline), or null to use Open-Meteo. */
+export async function setFixedWind(wind: FixedWind | null): Promise<void> {
+  return invoke("weather_set_fixed_wind", { wind });
+}
+
+export async function windProfile(lat: number, lon: number): Promise<WindProfile> {
+  return invoke<WindProfile>("weather_wind_profile", { lat, lon });
+}
+
+export async function windRouteEstimate(plan: MissionPlan, airspeedMps: number): Promise<WindEstimate> {
+  return invoke<WindEstimate>("weather_route_estimate", { plan, airspeedMps });
+}